@@ -1,24 +1,43 @@
-use serde_json::{json};
+mod references;
+
+use futures::StreamExt;
+use futures::stream::FuturesUnordered;
+use serde::{Deserialize, Serialize};
+use serde_json::{Value, json};
 use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::Duration;
 use tokio::sync::Mutex;
 
-use crate::errors::AgenticFlowError;
-use crate::llm_client::LLMClient;
+use crate::errors::{AgenticFlowError, ExecutionFailure};
+use crate::llm_client::{LLMRouter, Purpose};
 use crate::mcp_manager::MCPManager;
-use crate::model::{ChatMessage, ChatResponse};
-use crate::planner::{Executor, PlanStep};
-use crate::tool_registry::{ExecutionContext, ToolRegistry};
+use crate::model::{ChatMessage, ChatResponse, FinishReason};
+use crate::observer::{self, ErrorContext, ErrorObserver};
+use crate::planner::{Executor, IterativePlanner, NextStep, PlanStep, StepGraph, StepOutcome};
+use crate::tokens::{TrimStrategy, trim_messages};
+use crate::tool_registry::{self, ExecutionContext, ToolRegistry};
 
 pub struct Agent {
     manager: Arc<Mutex<MCPManager>>,
     tool_registry: Arc<Mutex<ToolRegistry>>,
-    llm_client: LLMClient,
+    llm: LLMRouter,
+    config: AgentConfig,
+    error_observer: Option<Arc<dyn ErrorObserver>>,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
 pub struct AgentConfig {
     pub max_steps: usize,
     pub timeout_seconds: u64,
+    pub retry: StepRetryPolicy,
+    pub on_step_failure: FailurePolicy,
+    pub synthesis: SynthesisConfig,
+    /// Serialized tool results longer than this are truncated before entering the context.
+    pub max_result_chars: usize,
+    pub run_limits: RunLimits,
+    pub execution_mode: ExecutionMode,
 }
 
 impl Default for AgentConfig {
@@ -26,6 +45,119 @@ impl Default for AgentConfig {
         Self {
             max_steps: 10,
             timeout_seconds: 30,
+            retry: StepRetryPolicy::default(),
+            on_step_failure: FailurePolicy::default(),
+            synthesis: SynthesisConfig::default(),
+            max_result_chars: 20_000,
+            run_limits: RunLimits::default(),
+            execution_mode: ExecutionMode::default(),
+        }
+    }
+}
+
+/// How often a failing step is attempted before its error is handed to the [`FailurePolicy`].
+/// Only errors for which [`AgenticFlowError::is_retryable`] holds are retried.
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct StepRetryPolicy {
+    pub max_attempts: usize,
+    /// Delay before the second attempt, multiplied by the attempt number afterwards.
+    pub backoff_ms: u64,
+}
+
+impl Default for StepRetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_attempts: 1,
+            backoff_ms: 500,
+        }
+    }
+}
+
+/// What the executor does once a step has failed all its attempts.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FailurePolicy {
+    /// Stop the run and return the step's error.
+    #[default]
+    Abort,
+    /// Record the error in the context and carry on with the next step.
+    Continue,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct SynthesisConfig {
+    /// When disabled the executor returns the raw context as JSON instead of asking the LLM.
+    pub enabled: bool,
+    pub system_prompt: String,
+}
+
+impl Default for SynthesisConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            system_prompt: "Synthesize the following context into result".to_string(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct RunLimits {
+    /// Maximum tool invocations per run, retries included.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_tool_calls: Option<usize>,
+    /// Maximum size of the serialized context passed to synthesis.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_context_chars: Option<usize>,
+    /// Maximum estimated tokens of the synthesis request; longer messages are truncated.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_context_tokens: Option<usize>,
+}
+
+/// How the steps of a plan run. In both modes a step runs once the steps in its
+/// [`depends_on`](PlanStep::depends_on) are recorded, and a plan whose dependencies
+/// cannot be met fails with [`AgenticFlowError::PlanningError`] before any step runs.
+#[derive(Debug, Clone, PartialEq, Default, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ExecutionMode {
+    #[default]
+    Sequential,
+    /// Runs up to `workers` steps at a time, each as soon as its dependencies are
+    /// recorded; results are recorded in plan order, dependencies first.
+    Parallel { workers: usize },
+}
+
+pub const DEFAULT_PARALLEL_WORKERS: usize = 4;
+
+/// Execution strategy names as written in the `execution` section of a config file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ExecutionStrategy {
+    #[default]
+    Sequential,
+    Parallel,
+}
+
+/// The `execution` section of a config file, e.g. `{ mode = "parallel", workers = 8 }`.
+#[derive(Debug, Clone, PartialEq, Default, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ExecutionConfig {
+    pub mode: ExecutionStrategy,
+    /// The most steps running at once, only used in parallel mode; defaults to
+    /// [`DEFAULT_PARALLEL_WORKERS`]. Also read as `max_parallel`.
+    #[serde(alias = "max_parallel", skip_serializing_if = "Option::is_none")]
+    pub workers: Option<usize>,
+}
+
+impl ExecutionConfig {
+    pub fn execution_mode(&self) -> ExecutionMode {
+        match self.mode {
+            ExecutionStrategy::Sequential => ExecutionMode::Sequential,
+            ExecutionStrategy::Parallel => ExecutionMode::Parallel {
+                workers: self.workers.unwrap_or(DEFAULT_PARALLEL_WORKERS),
+            },
         }
     }
 }
@@ -38,29 +170,53 @@ pub struct AgentResponse {
 }
 
 impl Agent {
+    /// Builds an agent that synthesizes with `llm`: an [`LLMClient`](crate::llm_client::LLMClient),
+    /// or an [`LLMRouter`] whose [`Purpose::Synthesis`] client is used.
     pub fn new(
         manager: Arc<Mutex<MCPManager>>,
         tool_registry: Arc<Mutex<ToolRegistry>>,
-        llm_client: LLMClient,
+        llm: impl Into<LLMRouter>,
     ) -> Self {
         Self {
             manager,
             tool_registry,
-            llm_client,
+            llm: llm.into(),
+            config: AgentConfig::default(),
+            error_observer: None,
         }
     }
 
+    pub fn with_config(mut self, config: AgentConfig) -> Self {
+        self.config = config;
+        self
+    }
+
+    /// Reports steps that failed all their attempts to `observer` as `step` errors.
+    pub fn with_error_observer(mut self, observer: Arc<dyn ErrorObserver>) -> Self {
+        self.error_observer = Some(observer);
+        self
+    }
+
+    pub fn config(&self) -> &AgentConfig {
+        &self.config
+    }
+
     pub async fn execute_tool(
         &self,
         tool_name: &str,
         params: serde_json::Value,
         context: &mut ExecutionContext,
     ) -> Result<serde_json::Value, AgenticFlowError> {
+        // Local tools run without holding the registry, so parallel steps overlap.
+        let local_tool = self.tool_registry.lock().await.local_tool(tool_name);
+        if let Some(local_tool) = local_tool {
+            return tool_registry::execute_local(&*local_tool, params, context).await;
+        }
         let manager = self.manager.lock().await;
         let tool_registry = self.tool_registry.lock().await;
 
         tool_registry
-            .execute_tool(tool_name, params, &*manager, context)
+            .execute_tool(tool_name, params, &manager, context)
             .await
     }
 
@@ -68,28 +224,248 @@ impl Agent {
         &self,
         messages: Vec<ChatMessage>,
     ) -> Result<Box<dyn ChatResponse>, AgenticFlowError> {
-        self.llm_client.chat_completions(messages, vec![]).await
+        let messages = match self.config.run_limits.max_context_tokens {
+            Some(budget) => trim_messages(messages, budget, TrimStrategy::TruncateLongest),
+            None => messages,
+        };
+        self.llm
+            .client(Purpose::Synthesis)
+            .chat_completions(messages, vec![])
+            .await
+    }
+
+    /// Runs `step` with the references in its params resolved from `completed`, see
+    /// [`references`].
+    async fn execute_step(
+        &self,
+        step: &PlanStep,
+        completed: &[StepOutcome],
+        context: &mut ExecutionContext,
+        tool_calls: &AtomicUsize,
+    ) -> Result<Value, AgenticFlowError> {
+        let params = references::resolve(&step.params, completed)?;
+        let policy = &self.config.retry;
+        let mut attempt = 1;
+
+        loop {
+            let calls = tool_calls.fetch_add(1, Ordering::SeqCst) + 1;
+            let limit = self.config.run_limits.max_tool_calls;
+            if let Some(max_tool_calls) = limit.filter(|max| calls > *max) {
+                return Err(AgenticFlowError::ExecutionError(format!(
+                    "Run exceeded the limit of {} tool calls",
+                    max_tool_calls
+                )));
+            }
+
+            match self
+                .execute_tool(&step.tool_name, params.clone(), context)
+                .await
+            {
+                Ok(result) => return Ok(result),
+                Err(e) if e.is_retryable() && attempt < policy.max_attempts => {
+                    tokio::time::sleep(Duration::from_millis(policy.backoff_ms * attempt as u64))
+                        .await;
+                    attempt += 1;
+                }
+                Err(e) => return Err(e),
+            }
+        }
+    }
+
+    fn record_result(
+        &self,
+        run: &mut RunState,
+        index: usize,
+        step: &PlanStep,
+        result: Result<Value, AgenticFlowError>,
+    ) -> Result<(), AgenticFlowError> {
+        if let Err(e) = &result {
+            let mut context = ErrorContext::new("step", e);
+            context.tool = Some(step.tool_name.clone());
+            observer::report(&self.error_observer, e, context);
+        }
+
+        let value = match result {
+            Ok(value) => truncate_value(value, self.config.max_result_chars),
+            Err(e) => match self.config.on_step_failure {
+                FailurePolicy::Abort => {
+                    return Err(AgenticFlowError::ExecutionFailed(Box::new(
+                        ExecutionFailure {
+                            step_index: index,
+                            step_id: step.id.clone(),
+                            tool: step.tool_name.clone(),
+                            params: step.params.clone(),
+                            error: e,
+                            completed: std::mem::take(&mut run.completed),
+                            context: run.context.data().clone(),
+                        },
+                    )));
+                }
+                FailurePolicy::Continue => json!({ "error": e.to_string() }),
+            },
+        };
+
+        run.context
+            .set(format!("{}: {}", index + 1, step.tool_name), value.clone());
+        run.completed.push(StepOutcome {
+            step_index: index,
+            step_id: step.id.clone(),
+            tool: step.tool_name.clone(),
+            params: step.params.clone(),
+            result: value,
+        });
+        Ok(())
+    }
+
+    async fn run_steps(&self, steps: Vec<PlanStep>) -> Result<RunState, AgenticFlowError> {
+        let mut run = RunState::default();
+        let tool_calls = AtomicUsize::new(0);
+        let graph = StepGraph::new(&steps)?;
+
+        match self.config.execution_mode {
+            ExecutionMode::Sequential => {
+                for &index in &graph.order {
+                    let step = &steps[index];
+                    let result = self
+                        .execute_step(step, &run.completed, &mut run.context, &tool_calls)
+                        .await;
+                    self.record_result(&mut run, index, step, result)?;
+                }
+            }
+            ExecutionMode::Parallel { workers } => {
+                let mut started = vec![false; steps.len()];
+                let mut recorded = vec![false; steps.len()];
+                let mut finished: Vec<Option<_>> = steps.iter().map(|_| None).collect();
+                let mut next_to_record = 0;
+                let mut running = FuturesUnordered::new();
+
+                loop {
+                    for &index in &graph.order {
+                        if running.len() >= workers.max(1) {
+                            break;
+                        }
+                        let dependencies = &graph.dependencies[index];
+                        if started[index] || !dependencies.iter().all(|&step| recorded[step]) {
+                            continue;
+                        }
+                        started[index] = true;
+                        let step = &steps[index];
+                        let completed = run.completed.clone();
+                        let mut step_context = run.context.clone();
+                        let tool_calls = &tool_calls;
+                        running.push(async move {
+                            let result = self
+                                .execute_step(step, &completed, &mut step_context, tool_calls)
+                                .await;
+                            (index, result, step_context)
+                        });
+                    }
+
+                    let Some((index, result, step_context)) = running.next().await else {
+                        break;
+                    };
+                    finished[index] = Some((result, step_context));
+                    while let Some(&index) = graph.order.get(next_to_record) {
+                        let Some((result, step_context)) = finished[index].take() else {
+                            break;
+                        };
+                        run.context.extend(step_context);
+                        self.record_result(&mut run, index, &steps[index], result)?;
+                        recorded[index] = true;
+                        next_to_record += 1;
+                    }
+                }
+            }
+        }
+
+        Ok(run)
+    }
+
+    async fn synthesize(&self, context: &ExecutionContext) -> Result<String, AgenticFlowError> {
+        let mut context_json = json!(context.data()).to_string();
+        if let Some(max_context_chars) = self.config.run_limits.max_context_chars {
+            context_json = truncate_chars(&context_json, max_context_chars);
+        }
+
+        if !self.config.synthesis.enabled {
+            return Ok(context_json);
+        }
+
+        let response = self
+            .call_llm(vec![
+                ChatMessage::system(self.config.synthesis.system_prompt.clone()),
+                ChatMessage::user(format!("Context: {}", context_json)),
+            ])
+            .await?;
+        if response.finish_reason() == Some(FinishReason::Length) {
+            println!("WARNING: The synthesized answer was cut off by the model's token limit");
+        }
+        Ok(response.message().content.to_string())
+    }
+}
+
+/// What a run has recorded so far.
+#[derive(Default)]
+struct RunState {
+    context: ExecutionContext,
+    completed: Vec<StepOutcome>,
+}
+
+fn truncate_chars(text: &str, max_chars: usize) -> String {
+    match text.char_indices().nth(max_chars) {
+        Some((end, _)) => format!("{}... [truncated]", &text[..end]),
+        None => text.to_string(),
+    }
+}
+
+fn truncate_value(value: Value, max_chars: usize) -> Value {
+    let serialized = value.to_string();
+    if serialized.chars().count() <= max_chars {
+        value
+    } else {
+        Value::String(truncate_chars(&serialized, max_chars))
     }
 }
 
 #[async_trait::async_trait]
 impl Executor for Agent {
     async fn execute(&self, steps: Vec<PlanStep>) -> Result<String, AgenticFlowError> {
-        let mut context = ExecutionContext::new();
-        let mut step = 1;
+        let run = self.run_steps(steps).await?;
+        // A budget spent by the synthesis keeps the results of the steps.
+        self.synthesize(&run.context)
+            .await
+            .map_err(|error| error.with_run_progress(&run.completed, run.context.data()))
+    }
+
+    /// Runs up to [`AgentConfig::max_steps`] steps, each with the retries, limits and
+    /// [`FailurePolicy`] of a planned step, and returns the planner's answer. A run out of
+    /// steps is synthesized from what they returned.
+    async fn execute_iteratively(
+        &self,
+        task: &str,
+        planner: &dyn IterativePlanner,
+    ) -> Result<String, AgenticFlowError> {
+        let mut run = RunState::default();
+        let tool_calls = AtomicUsize::new(0);
 
-        for PlanStep { tool_name, params } in steps {
+        for index in 0..self.config.max_steps {
+            let step = match planner.next_step(task, &run.completed).await {
+                Ok(NextStep::Call(step)) => step,
+                Ok(NextStep::Finish(answer)) => return Ok(answer),
+                Err(error) => {
+                    return Err(error
+                        .surfacing_budget()
+                        .with_run_progress(&run.completed, run.context.data()));
+                }
+            };
             let result = self
-                .execute_tool(&tool_name, params, &mut context)
-                .await
-                .unwrap();
-            context.set(format!("{}: {}", step, tool_name), result);
-            step += 1;
+                .execute_step(&step, &run.completed, &mut run.context, &tool_calls)
+                .await;
+            self.record_result(&mut run, index, &step, result)?;
         }
 
-        self.call_llm(vec![
-            ChatMessage::system("Synthesize the following context into result".to_string()),
-            ChatMessage::user(format!("Context: {}", json!(context.data()))),
-        ]).await.map(|res| res.message().content.to_string())
+        self.synthesize(&run.context)
+            .await
+            .map_err(|error| error.with_run_progress(&run.completed, run.context.data()))
     }
 }
@@ -0,0 +1,119 @@
+//! References to the results of earlier steps in the params of a later one, such as
+//! `"{{steps.1.output.url}}"`.
+//!
+//! A reference is `steps.`, the step's 1-based position in the plan or its
+//! [`id`](crate::planner::PlanStep::id), `.output`, then field names and array indices
+//! into that step's recorded result, e.g. `{{steps.fetch.output.items.0.url}}`. A string
+//! that is a single reference becomes the referenced value, of any type; references
+//! inside a longer string are replaced by the value's text. `\{{` stands for a literal
+//! `{{`, and `{{...}}` that does not start with `steps.` is left as it is.
+
+use serde_json::{Map, Value};
+
+use crate::{errors::AgenticFlowError, planner::StepOutcome};
+
+const OPEN: &str = "{{";
+const CLOSE: &str = "}}";
+const PREFIX: &str = "steps.";
+
+/// `params` with every reference replaced by the result it names in `completed`. Fails
+/// with [`AgenticFlowError::ExecutionError`] naming the reference when the step has not
+/// run or its result has no such field.
+pub(super) fn resolve(
+    params: &Value,
+    completed: &[StepOutcome],
+) -> Result<Value, AgenticFlowError> {
+    match params {
+        Value::String(text) => resolve_text(text, completed),
+        Value::Array(items) => items
+            .iter()
+            .map(|item| resolve(item, completed))
+            .collect::<Result<_, _>>()
+            .map(Value::Array),
+        Value::Object(fields) => fields
+            .iter()
+            .map(|(key, value)| Ok((key.clone(), resolve(value, completed)?)))
+            .collect::<Result<Map<_, _>, _>>()
+            .map(Value::Object),
+        value => Ok(value.clone()),
+    }
+}
+
+fn resolve_text(text: &str, completed: &[StepOutcome]) -> Result<Value, AgenticFlowError> {
+    if let Some(path) = whole_reference(text) {
+        return lookup(path, completed).cloned();
+    }
+
+    let mut resolved = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(start) = rest.find(OPEN) {
+        if rest[..start].ends_with('\\') {
+            resolved.push_str(&rest[..start - 1]);
+            resolved.push_str(OPEN);
+            rest = &rest[start + OPEN.len()..];
+            continue;
+        }
+        let Some(length) = rest[start..].find(CLOSE) else {
+            break;
+        };
+        let path = rest[start + OPEN.len()..start + length].trim();
+        resolved.push_str(&rest[..start]);
+        if path.starts_with(PREFIX) {
+            match lookup(path, completed)? {
+                Value::String(value) => resolved.push_str(value),
+                value => resolved.push_str(&value.to_string()),
+            }
+        } else {
+            resolved.push_str(&rest[start..start + length + CLOSE.len()]);
+        }
+        rest = &rest[start + length + CLOSE.len()..];
+    }
+    resolved.push_str(rest);
+    Ok(Value::String(resolved))
+}
+
+/// The path of `text` if it is nothing but one reference.
+fn whole_reference(text: &str) -> Option<&str> {
+    let path = text.trim().strip_prefix(OPEN)?.strip_suffix(CLOSE)?.trim();
+    let single = path.starts_with(PREFIX) && !path.contains(OPEN) && !path.contains(CLOSE);
+    single.then_some(path)
+}
+
+/// The value `path`, such as `steps.1.output.url`, names in `completed`.
+fn lookup<'a>(path: &str, completed: &'a [StepOutcome]) -> Result<&'a Value, AgenticFlowError> {
+    let unresolved = |reason: String| {
+        AgenticFlowError::ExecutionError(format!("Cannot resolve '{}': {}", path, reason))
+    };
+    let mut segments = path.split('.').skip(1);
+    let step = segments.next().unwrap_or_default();
+    if segments.next() != Some("output") {
+        return Err(unresolved(
+            "expected {{steps.<step>.output}}, optionally followed by fields".to_string(),
+        ));
+    }
+    let outcome = completed
+        .iter()
+        .find(|outcome| step.parse() == Ok(outcome.step_index + 1))
+        .or_else(|| {
+            completed
+                .iter()
+                .find(|outcome| outcome.step_id.as_deref() == Some(step))
+        })
+        .ok_or_else(|| unresolved(format!("step {} has not run", step)))?;
+
+    let mut value = &outcome.result;
+    for segment in segments {
+        let field = match value {
+            Value::Object(fields) => fields.get(segment),
+            Value::Array(items) => segment
+                .parse()
+                .ok()
+                .and_then(|index: usize| items.get(index)),
+            _ => None,
+        };
+        value = field.ok_or_else(|| {
+            unresolved(format!("the output of step {} has no '{}'", step, segment))
+        })?;
+    }
+    Ok(value)
+}
@@ -1,71 +1,402 @@
-use std::collections::HashMap;
+mod diff;
+mod env;
+mod mcp_servers;
+mod profiles;
+mod redact;
+mod validation;
 
-use serde::{Deserialize, Serialize};
+pub use diff::ConfigDiff;
+pub use profiles::{PROFILE_ENV_VAR, ProfileOverrides};
+pub(crate) use redact::redact_value;
+pub use redact::{REDACTED, is_secret_key};
+pub use validation::{ConfigValidationReport, ConfigViolation};
 
-use crate::{agent::AgentConfig, llm_client::OllamaModel};
+use std::{collections::HashMap, fmt, fs, path::Path};
 
-#[derive(Debug, Clone)]
+use serde::{Deserialize, Serialize, de::DeserializeOwned};
+use serde_json::Value;
+
+use crate::{
+    agent::{AgentConfig, ExecutionConfig},
+    errors::AgenticFlowError,
+    http::HttpConfig,
+    llm_client::{OllamaModel, OllamaOptions},
+    planner::PlannerConfig,
+    secrets::SecretRef,
+};
+
+#[derive(Debug, Clone, Default, PartialEq, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
 pub struct SystemConfig {
     pub mcp_config: MCPConfig,
     pub enabled_servers: Vec<String>,
     pub llm_config: LLMConfig,
     pub agent_config: AgentConfig,
+    pub planner: PlannerConfig,
+    /// Overrides `agent_config.execution_mode` when present.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub execution: Option<ExecutionConfig>,
 }
 
-impl Default for SystemConfig {
-    fn default() -> Self {
-        Self {
-            mcp_config: MCPConfig::default(),
-            enabled_servers: vec![],
-            llm_config: LLMConfig::default(),
-            agent_config: AgentConfig::default(),
-        }
-    }
-}
-
-#[derive(Debug, Clone, Deserialize, Serialize)]
+#[derive(Debug, Clone, Default, PartialEq, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
 pub struct MCPConfig {
     pub servers: HashMap<String, ServerConfig>,
 }
 
-impl Default for MCPConfig {
-    fn default() -> Self {
-        Self {
-            servers: HashMap::new(),
-        }
-    }
-    
-}
-
-#[derive(Debug, Clone, Deserialize, Serialize)]
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
 pub enum ServerType {
     Python,
     Node,
+    /// An arbitrary executable given by `ServerConfig::command`.
+    Command,
     // TODO: Docker or Docker Toolkit
 }
 
-#[derive(Debug, Clone, Deserialize, Serialize)]
+/// `Debug` masks `env` values and `config` entries whose key looks like a secret.
+#[derive(Clone, PartialEq, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
 pub struct ServerConfig {
     pub server_type: ServerType,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
     pub module_name: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
     pub package_name: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub command: Option<String>,
+    /// Extra arguments appended after the type-specific base command.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub args: Vec<String>,
+    /// Environment variables set for the server process.
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub env: HashMap<String, String>,
+    /// Environment variables whose values are secrets, resolved when the system starts.
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub secrets: HashMap<String, SecretRef>,
+    #[serde(default)]
     pub auto_install: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
     pub config: Option<serde_json::Value>,
 }
 
-#[derive(Debug, Clone)]
+impl fmt::Debug for ServerConfig {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ServerConfig")
+            .field("server_type", &self.server_type)
+            .field("module_name", &self.module_name)
+            .field("package_name", &self.package_name)
+            .field("command", &self.command)
+            .field("args", &self.args)
+            .field("env", &redact::RedactedEnv(&self.env))
+            .field("secrets", &self.secrets)
+            .field("auto_install", &self.auto_install)
+            .field("config", &self.config.as_ref().map(redact::RedactedValue))
+            .finish()
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Default, Deserialize, Serialize)]
+pub enum ProviderKind {
+    #[default]
+    #[serde(rename = "ollama")]
+    Ollama,
+    #[serde(rename = "open_router", alias = "openrouter")]
+    OpenRouter,
+    #[serde(rename = "openai")]
+    OpenAI,
+    #[serde(rename = "anthropic")]
+    Anthropic,
+    #[serde(rename = "gemini")]
+    Gemini,
+    #[serde(rename = "groq")]
+    Groq,
+    /// Any server speaking the OpenAI chat completions API.
+    #[serde(rename = "openai_compatible")]
+    OpenAICompatible { base_url: String },
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
 pub struct LLMConfig {
+    pub provider: ProviderKind,
     pub model: String,
+    pub temperature: f32,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_tokens: Option<usize>,
+    /// Environment variable holding the API key, defaults to the provider's usual one.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub api_key_env: Option<String>,
+    /// The API key from any secret source, resolved when the client is built.
+    /// Takes the place of `api_key_env`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub api_key: Option<SecretRef>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub timeout_seconds: Option<u64>,
+    /// The Ollama server, as a URL or `host[:port]`. Defaults to `OLLAMA_HOST`, then
+    /// `http://localhost:11434`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub base_url: Option<String>,
+    /// The site of the app, sent to OpenRouter as `HTTP-Referer`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub app_url: Option<String>,
+    /// The name of the app, sent to OpenRouter as `X-Title`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub app_name: Option<String>,
+    /// The model `LLMClient::embed` uses with Ollama and OpenRouter, defaults to `model`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub embedding_model: Option<String>,
+    /// How Ollama runs the model, e.g. its context size as `num_ctx`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub ollama: Option<OllamaOptions>,
+    /// Checks that the LLM server has the model when the system starts, see
+    /// `LLMClient::verify`.
+    pub verify_on_startup: bool,
+    /// The proxy, trusted certificates and connection settings of the HTTP client.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub http: Option<HttpConfig>,
 }
 
 impl Default for LLMConfig {
     fn default() -> Self {
         Self {
+            provider: ProviderKind::Ollama,
             model: OllamaModel::GPToss.to_string(),
+            temperature: 0.7,
+            max_tokens: None,
+            api_key_env: None,
+            api_key: None,
+            timeout_seconds: None,
+            base_url: None,
+            app_url: None,
+            app_name: None,
+            embedding_model: None,
+            ollama: None,
+            verify_on_startup: false,
+            http: None,
         }
     }
 }
 
+/// File formats understood by [`SystemConfig::from_file`] and [`SystemConfig::to_file`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConfigFormat {
+    Toml,
+    Json,
+    #[cfg(feature = "yaml")]
+    Yaml,
+}
+
+impl ConfigFormat {
+    /// Detects the format from the file extension.
+    pub fn from_path(path: &Path) -> Result<Self, AgenticFlowError> {
+        let extension = path
+            .extension()
+            .and_then(|ext| ext.to_str())
+            .map(|ext| ext.to_ascii_lowercase());
+
+        match extension.as_deref() {
+            Some("toml") => Ok(ConfigFormat::Toml),
+            Some("json") => Ok(ConfigFormat::Json),
+            #[cfg(feature = "yaml")]
+            Some("yaml" | "yml") => Ok(ConfigFormat::Yaml),
+            #[cfg(not(feature = "yaml"))]
+            Some("yaml" | "yml") => Err(AgenticFlowError::ConfigError(format!(
+                "YAML config '{}' requires the `yaml` feature",
+                path.display()
+            ))),
+            _ => Err(AgenticFlowError::ConfigError(format!(
+                "Unsupported config file extension: '{}'",
+                path.display()
+            ))),
+        }
+    }
+}
+
+impl SystemConfig {
+    pub fn builder() -> SystemConfigBuilder {
+        SystemConfigBuilder::default()
+    }
+
+    /// The agent config with the `execution` section, if any, applied.
+    pub fn resolved_agent_config(&self) -> AgentConfig {
+        let mut agent_config = self.agent_config.clone();
+        if let Some(execution) = &self.execution {
+            agent_config.execution_mode = execution.execution_mode();
+        }
+        agent_config
+    }
+
+    /// Loads the configuration from a TOML, JSON or (with the `yaml` feature) YAML file.
+    ///
+    /// String values may reference environment variables as `${VAR}` or
+    /// `${VAR:-default}`. After parsing, `AGENTIC_FLOW__SECTION__FIELD`
+    /// environment variables are applied on top, so the precedence is
+    /// env override > profile > file > defaults.
+    ///
+    /// The profile named by `AGENTIC_FLOW_PROFILE`, if set, is applied, see
+    /// [`SystemConfig::from_file_with_profile`].
+    pub fn from_file(path: impl AsRef<Path>) -> Result<Self, AgenticFlowError> {
+        let profile = std::env::var(PROFILE_ENV_VAR)
+            .ok()
+            .filter(|profile| !profile.is_empty());
+        Self::load(path.as_ref(), profile.as_deref())
+    }
+
+    /// Loads the configuration from a file and overlays the named entry of its
+    /// `profiles` section: tables are deep-merged, scalars and lists replaced,
+    /// and a `"+key"` list is appended to the base list instead.
+    pub fn from_file_with_profile(
+        path: impl AsRef<Path>,
+        profile: &str,
+    ) -> Result<Self, AgenticFlowError> {
+        Self::load(path.as_ref(), Some(profile))
+    }
+
+    /// Lists the keys each profile in the file overrides, sorted by profile name.
+    pub fn profile_overrides(
+        path: impl AsRef<Path>,
+    ) -> Result<Vec<ProfileOverrides>, AgenticFlowError> {
+        let (contents, format) = read_config_file(path.as_ref())?;
+        let mut base = parse_value(&contents, format)?;
+        let profiles = profiles::take_profiles(&mut base)?;
+
+        profiles
+            .keys()
+            .map(|name| profiles::apply_profile(&mut base.clone(), &profiles, name))
+            .collect()
+    }
+
+    fn load(path: &Path, profile: Option<&str>) -> Result<Self, AgenticFlowError> {
+        let (contents, format) = read_config_file(path)?;
+        Self::parse_with_profile(&contents, format, profile)?.apply_env_overrides()
+    }
+
+    /// Parses the configuration from a string in the given format, expanding `${VAR}` references.
+    pub fn parse(contents: &str, format: ConfigFormat) -> Result<Self, AgenticFlowError> {
+        Self::parse_with_profile(contents, format, None)
+    }
+
+    /// Like [`SystemConfig::parse`], overlaying the named profile first.
+    pub fn parse_with_profile(
+        contents: &str,
+        format: ConfigFormat,
+        profile: Option<&str>,
+    ) -> Result<Self, AgenticFlowError> {
+        let mut value = parse_value(contents, format)?;
+        let profiles = profiles::take_profiles(&mut value)?;
+        if let Some(profile) = profile {
+            profiles::apply_profile(&mut value, &profiles, profile)?;
+        }
+
+        env::expand_value(&mut value, "", &|name: &str| std::env::var(name).ok())?;
+        from_value_with_path(value)
+    }
+
+    /// Applies `AGENTIC_FLOW__...` overrides from the process environment.
+    pub fn apply_env_overrides(self) -> Result<Self, AgenticFlowError> {
+        let vars = std::env::vars_os()
+            .filter_map(|(key, value)| Some((key.into_string().ok()?, value.into_string().ok()?)));
+        self.apply_overrides(vars)
+    }
+
+    /// Applies `AGENTIC_FLOW__...` overrides from the given key/value pairs.
+    ///
+    /// `AGENTIC_FLOW__LLM__MODEL=qwen3:8b` sets `llm_config.model` and
+    /// `AGENTIC_FLOW__MCP__SERVERS__WEB_SEARCH__MODULE_NAME=...` reaches into a
+    /// server entry. Keys without the prefix are ignored.
+    pub fn apply_overrides<I>(self, vars: I) -> Result<Self, AgenticFlowError>
+    where
+        I: IntoIterator<Item = (String, String)>,
+    {
+        let mut value = self.to_value()?;
+        if env::apply_overrides(&mut value, vars)? {
+            from_value_with_path(value)
+        } else {
+            Ok(self)
+        }
+    }
+
+    /// The configuration as a JSON value, e.g. for diffing two configs.
+    pub fn to_value(&self) -> Result<Value, AgenticFlowError> {
+        serde_json::to_value(self).map_err(serialize_error)
+    }
+
+    /// Like [`SystemConfig::to_value`] with secret-looking values replaced by [`REDACTED`],
+    /// safe to log. The result is not meant to be loaded back.
+    pub fn to_redacted_value(&self) -> Result<Value, AgenticFlowError> {
+        let mut value = self.to_value()?;
+        redact::redact_value(&mut value);
+        Ok(value)
+    }
+
+    /// Writes the configuration to a file, picking the format from the extension.
+    pub fn to_file(&self, path: impl AsRef<Path>) -> Result<(), AgenticFlowError> {
+        let path = path.as_ref();
+        let contents = self.to_string_as(ConfigFormat::from_path(path)?)?;
+
+        fs::write(path, contents).map_err(|e| {
+            AgenticFlowError::ConfigError(format!(
+                "Failed to write config file '{}': {}",
+                path.display(),
+                e
+            ))
+        })
+    }
+
+    /// Serializes the configuration into the given format.
+    pub fn to_string_as(&self, format: ConfigFormat) -> Result<String, AgenticFlowError> {
+        match format {
+            ConfigFormat::Toml => toml::to_string_pretty(self).map_err(serialize_error),
+            ConfigFormat::Json => serde_json::to_string_pretty(self).map_err(serialize_error),
+            #[cfg(feature = "yaml")]
+            ConfigFormat::Yaml => serde_yaml::to_string(self).map_err(serialize_error),
+        }
+    }
+}
+
+fn read_config_file(path: &Path) -> Result<(String, ConfigFormat), AgenticFlowError> {
+    let format = ConfigFormat::from_path(path)?;
+    let contents = fs::read_to_string(path).map_err(|e| {
+        AgenticFlowError::ConfigError(format!(
+            "Failed to read config file '{}': {}",
+            path.display(),
+            e
+        ))
+    })?;
+    Ok((contents, format))
+}
+
+fn serialize_error<E: std::fmt::Display>(e: E) -> AgenticFlowError {
+    AgenticFlowError::ConfigError(format!("Failed to serialize config: {}", e))
+}
+
+fn parse_value(contents: &str, format: ConfigFormat) -> Result<Value, AgenticFlowError> {
+    let parsed = match format {
+        ConfigFormat::Toml => toml::from_str::<Value>(contents).map_err(|e| e.to_string()),
+        ConfigFormat::Json => serde_json::from_str::<Value>(contents).map_err(|e| e.to_string()),
+        #[cfg(feature = "yaml")]
+        ConfigFormat::Yaml => serde_yaml::from_str::<Value>(contents).map_err(|e| e.to_string()),
+    };
+
+    match parsed {
+        // An empty YAML document parses to null, treat it like an empty table.
+        Ok(Value::Null) => Ok(Value::Object(Default::default())),
+        Ok(value) => Ok(value),
+        Err(e) => Err(AgenticFlowError::ConfigError(format!(
+            "Failed to parse config: {}",
+            e
+        ))),
+    }
+}
+
+/// Deserializes `value`, reporting the key path of the offending field on failure.
+fn from_value_with_path<T: DeserializeOwned>(value: Value) -> Result<T, AgenticFlowError> {
+    serde_path_to_error::deserialize(value).map_err(|e| {
+        let path = e.path().to_string();
+        AgenticFlowError::ConfigError(format!("Invalid config at '{}': {}", path, e.into_inner()))
+    })
+}
+
 // Example configuration helper
 impl SystemConfig {
     pub fn example() -> Self {
@@ -84,8 +415,61 @@ impl SystemConfig {
             enabled_servers: vec![],
             llm_config: LLMConfig {
                 model: OllamaModel::GPToss.to_string(),
+                ..LLMConfig::default()
             },
             agent_config: AgentConfig::default(),
+            planner: PlannerConfig::default(),
+            execution: None,
         }
     }
 }
+
+/// Fluent construction of a [`SystemConfig`], validated by [`SystemConfigBuilder::build`].
+#[derive(Debug, Clone, Default)]
+pub struct SystemConfigBuilder {
+    config: SystemConfig,
+}
+
+impl SystemConfigBuilder {
+    pub fn llm(mut self, llm_config: LLMConfig) -> Self {
+        self.config.llm_config = llm_config;
+        self
+    }
+
+    pub fn agent(mut self, agent_config: AgentConfig) -> Self {
+        self.config.agent_config = agent_config;
+        self
+    }
+
+    pub fn mcp_server(mut self, name: impl Into<String>, server_config: ServerConfig) -> Self {
+        self.config
+            .mcp_config
+            .servers
+            .insert(name.into(), server_config);
+        self
+    }
+
+    pub fn enable_server(mut self, name: impl Into<String>) -> Self {
+        let name = name.into();
+        if !self.config.enabled_servers.contains(&name) {
+            self.config.enabled_servers.push(name);
+        }
+        self
+    }
+
+    /// Accepts a [`PlannerKind`](crate::planner::PlannerKind) or a full [`PlannerConfig`].
+    pub fn planner(mut self, planner: impl Into<PlannerConfig>) -> Self {
+        self.config.planner = planner.into();
+        self
+    }
+
+    pub fn execution(mut self, execution: ExecutionConfig) -> Self {
+        self.config.execution = Some(execution);
+        self
+    }
+
+    pub fn build(self) -> Result<SystemConfig, AgenticFlowError> {
+        self.config.validate()?;
+        Ok(self.config)
+    }
+}
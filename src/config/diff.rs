@@ -0,0 +1,55 @@
+use super::SystemConfig;
+
+/// What changed between two [`SystemConfig`]s, as computed by [`SystemConfig::diff`].
+///
+/// Server names are sorted so the diff is stable regardless of map order.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ConfigDiff {
+    pub servers_added: Vec<String>,
+    pub servers_removed: Vec<String>,
+    /// Servers present in both configs whose `ServerConfig` differs.
+    pub servers_changed: Vec<String>,
+    pub llm_config_changed: bool,
+    pub agent_config_changed: bool,
+    pub planner_changed: bool,
+}
+
+impl ConfigDiff {
+    pub fn is_empty(&self) -> bool {
+        *self == ConfigDiff::default()
+    }
+}
+
+impl SystemConfig {
+    /// Compares `self` (the current config) against `new`.
+    pub fn diff(&self, new: &SystemConfig) -> ConfigDiff {
+        let current = &self.mcp_config.servers;
+        let next = &new.mcp_config.servers;
+
+        let mut diff = ConfigDiff {
+            servers_added: next
+                .keys()
+                .filter(|name| !current.contains_key(*name))
+                .cloned()
+                .collect(),
+            servers_removed: current
+                .keys()
+                .filter(|name| !next.contains_key(*name))
+                .cloned()
+                .collect(),
+            servers_changed: current
+                .iter()
+                .filter(|(name, server)| next.get(*name).is_some_and(|other| other != *server))
+                .map(|(name, _)| name.clone())
+                .collect(),
+            llm_config_changed: self.llm_config != new.llm_config,
+            agent_config_changed: self.resolved_agent_config() != new.resolved_agent_config(),
+            planner_changed: self.planner != new.planner,
+        };
+
+        diff.servers_added.sort();
+        diff.servers_removed.sort();
+        diff.servers_changed.sort();
+        diff
+    }
+}
@@ -0,0 +1,177 @@
+//! `${VAR}` expansion and `AGENTIC_FLOW__SECTION__FIELD` overrides applied to raw config values.
+
+use serde_json::{Map, Value};
+
+use crate::errors::AgenticFlowError;
+
+pub const OVERRIDE_PREFIX: &str = "AGENTIC_FLOW__";
+
+/// Expands `${VAR}` and `${VAR:-default}` in every string of `value`.
+/// `$$` produces a literal `$`.
+pub fn expand_value<F>(value: &mut Value, path: &str, lookup: &F) -> Result<(), AgenticFlowError>
+where
+    F: Fn(&str) -> Option<String>,
+{
+    match value {
+        Value::String(text) => *text = expand_str(text, path, lookup)?,
+        Value::Array(items) => {
+            for (index, item) in items.iter_mut().enumerate() {
+                expand_value(item, &join_path(path, &index.to_string()), lookup)?;
+            }
+        }
+        Value::Object(map) => {
+            for (key, item) in map.iter_mut() {
+                expand_value(item, &join_path(path, key), lookup)?;
+            }
+        }
+        _ => {}
+    }
+    Ok(())
+}
+
+pub fn expand_str<F>(input: &str, path: &str, lookup: &F) -> Result<String, AgenticFlowError>
+where
+    F: Fn(&str) -> Option<String>,
+{
+    let mut output = String::with_capacity(input.len());
+    let mut rest = input;
+
+    while let Some(start) = rest.find('$') {
+        output.push_str(&rest[..start]);
+        let after = &rest[start + 1..];
+
+        if let Some(stripped) = after.strip_prefix('$') {
+            output.push('$');
+            rest = stripped;
+        } else if let Some(body) = after.strip_prefix('{') {
+            let end = body.find('}').ok_or_else(|| {
+                AgenticFlowError::ConfigError(format!(
+                    "Unterminated '${{' in config value at '{}'",
+                    path
+                ))
+            })?;
+            let expression = &body[..end];
+            let (name, default) = match expression.split_once(":-") {
+                Some((name, default)) => (name, Some(default)),
+                None => (expression, None),
+            };
+
+            let resolved = match default {
+                Some(default) => Some(
+                    lookup(name)
+                        .filter(|value| !value.is_empty())
+                        .unwrap_or_else(|| default.to_string()),
+                ),
+                None => lookup(name),
+            };
+
+            match resolved {
+                Some(value) => output.push_str(&value),
+                None => {
+                    return Err(AgenticFlowError::ConfigError(format!(
+                        "Environment variable '{}' referenced at '{}' is not set",
+                        name, path
+                    )));
+                }
+            }
+            rest = &body[end + 1..];
+        } else {
+            output.push('$');
+            rest = after;
+        }
+    }
+
+    output.push_str(rest);
+    Ok(output)
+}
+
+/// Applies `AGENTIC_FLOW__A__B=value` pairs onto `value`, returning whether anything changed.
+///
+/// Segments are matched case-insensitively against field names; a segment that
+/// does not match directly also matches `<segment>_config`, so
+/// `AGENTIC_FLOW__LLM__MODEL` targets `llm_config.model`. Values are parsed as
+/// JSON when the target is not a string, falling back to the raw text.
+pub fn apply_overrides<I>(value: &mut Value, vars: I) -> Result<bool, AgenticFlowError>
+where
+    I: IntoIterator<Item = (String, String)>,
+{
+    let mut overrides: Vec<(String, String)> = vars
+        .into_iter()
+        .filter_map(|(key, raw)| {
+            key.strip_prefix(OVERRIDE_PREFIX)
+                .map(|path| (path.to_string(), raw))
+        })
+        .collect();
+    // Environment iteration order is unspecified, keep the result deterministic.
+    overrides.sort();
+
+    for (key, raw) in &overrides {
+        let segments: Vec<String> = key.split("__").map(|s| s.to_ascii_lowercase()).collect();
+        if segments.iter().any(|segment| segment.is_empty()) {
+            return Err(AgenticFlowError::ConfigError(format!(
+                "Invalid config override '{}{}'",
+                OVERRIDE_PREFIX, key
+            )));
+        }
+
+        set_path(value, &segments, raw).map_err(|reason| {
+            AgenticFlowError::ConfigError(format!(
+                "Invalid config override '{}{}': {}",
+                OVERRIDE_PREFIX, key, reason
+            ))
+        })?;
+    }
+
+    Ok(!overrides.is_empty())
+}
+
+fn set_path(root: &mut Value, segments: &[String], raw: &str) -> Result<(), String> {
+    let (last, parents) = segments
+        .split_last()
+        .ok_or_else(|| "empty override path".to_string())?;
+
+    let mut current = root;
+    for segment in parents {
+        let table = as_table(current, segment)?;
+        let key = resolve_key(table, segment);
+        current = table.entry(key).or_insert(Value::Null);
+    }
+
+    let table = as_table(current, last)?;
+    let key = resolve_key(table, last);
+    let value = match table.get(&key) {
+        Some(Value::String(_)) => Value::String(raw.to_string()),
+        _ => serde_json::from_str(raw).unwrap_or_else(|_| Value::String(raw.to_string())),
+    };
+    table.insert(key, value);
+    Ok(())
+}
+
+fn as_table<'a>(value: &'a mut Value, segment: &str) -> Result<&'a mut Map<String, Value>, String> {
+    if value.is_null() {
+        *value = Value::Object(Map::new());
+    }
+    value
+        .as_object_mut()
+        .ok_or_else(|| format!("cannot set '{}' on a non-table value", segment))
+}
+
+fn resolve_key(table: &Map<String, Value>, segment: &str) -> String {
+    if table.contains_key(segment) {
+        return segment.to_string();
+    }
+    let aliased = format!("{}_config", segment);
+    if table.contains_key(&aliased) {
+        aliased
+    } else {
+        segment.to_string()
+    }
+}
+
+pub fn join_path(path: &str, segment: &str) -> String {
+    if path.is_empty() {
+        segment.to_string()
+    } else {
+        format!("{}.{}", path, segment)
+    }
+}
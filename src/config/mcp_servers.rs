@@ -0,0 +1,161 @@
+use std::{collections::HashMap, fs, path::Path};
+
+use serde_json::{Map, Value, json};
+
+use crate::errors::AgenticFlowError;
+
+use super::{MCPConfig, ServerConfig, ServerType};
+
+const MCP_SERVERS_KEY: &str = "mcpServers";
+const KNOWN_SERVER_KEYS: [&str; 3] = ["command", "args", "env"];
+
+impl ServerConfig {
+    /// The program and full argument list used to launch this server.
+    pub fn command_line(&self) -> Result<(String, Vec<String>), AgenticFlowError> {
+        let (program, mut args) = match self.server_type {
+            ServerType::Python => {
+                let module_name = self.module_name.as_ref().ok_or_else(|| {
+                    AgenticFlowError::ToolError("Python module name required".to_string())
+                })?;
+                (
+                    "python".to_string(),
+                    vec!["-m".to_string(), module_name.clone()],
+                )
+            }
+            ServerType::Node => {
+                let package_name = self.package_name.as_ref().ok_or_else(|| {
+                    AgenticFlowError::ToolError("Node package name required".to_string())
+                })?;
+                (
+                    "npx".to_string(),
+                    vec!["-y".to_string(), package_name.clone()],
+                )
+            }
+            ServerType::Command => {
+                let program = self.command.as_ref().ok_or_else(|| {
+                    AgenticFlowError::ToolError("Server command required".to_string())
+                })?;
+                (program.clone(), vec![])
+            }
+        };
+
+        args.extend(self.args.iter().cloned());
+        Ok((program, args))
+    }
+}
+
+impl MCPConfig {
+    /// Imports servers from the `mcpServers` block used by Claude Desktop, Cursor and friends:
+    ///
+    /// ```json
+    /// {"mcpServers": {"fetch": {"command": "uvx", "args": ["mcp-server-fetch"], "env": {}}}}
+    /// ```
+    ///
+    /// Every entry becomes a [`ServerType::Command`] server. Other top-level keys are ignored
+    /// and unknown per-server keys only produce a warning.
+    pub fn from_mcp_servers_json(value: &Value) -> Result<Self, AgenticFlowError> {
+        let entries = value
+            .get(MCP_SERVERS_KEY)
+            .and_then(Value::as_object)
+            .ok_or_else(|| {
+                AgenticFlowError::ConfigError(format!("Expected an '{}' object", MCP_SERVERS_KEY))
+            })?;
+
+        let mut servers = HashMap::new();
+        for (name, entry) in entries {
+            servers.insert(name.clone(), import_server(name, entry)?);
+        }
+
+        Ok(Self { servers })
+    }
+
+    /// Reads a JSON file containing an `mcpServers` block, see [`MCPConfig::from_mcp_servers_json`].
+    pub fn from_mcp_servers_file(path: impl AsRef<Path>) -> Result<Self, AgenticFlowError> {
+        let path = path.as_ref();
+        let contents = fs::read_to_string(path).map_err(|e| {
+            AgenticFlowError::ConfigError(format!(
+                "Failed to read MCP servers file '{}': {}",
+                path.display(),
+                e
+            ))
+        })?;
+        let value: Value = serde_json::from_str(&contents).map_err(|e| {
+            AgenticFlowError::ConfigError(format!(
+                "Failed to parse MCP servers file '{}': {}",
+                path.display(),
+                e
+            ))
+        })?;
+
+        Self::from_mcp_servers_json(&value)
+    }
+
+    /// Exports the servers as an `mcpServers` block. Python and Node servers are written
+    /// with their resolved `python -m` / `npx -y` command lines.
+    pub fn to_mcp_servers_json(&self) -> Result<Value, AgenticFlowError> {
+        let mut entries = Map::new();
+        for (name, server) in &self.servers {
+            let (command, args) = server.command_line().map_err(|e| {
+                AgenticFlowError::ConfigError(format!("Cannot export server '{}': {}", name, e))
+            })?;
+
+            let mut entry = json!({ "command": command, "args": args });
+            if !server.env.is_empty() {
+                entry["env"] = json!(server.env);
+            }
+            entries.insert(name.clone(), entry);
+        }
+
+        let mut exported = Map::new();
+        exported.insert(MCP_SERVERS_KEY.to_string(), Value::Object(entries));
+        Ok(Value::Object(exported))
+    }
+}
+
+fn import_server(name: &str, entry: &Value) -> Result<ServerConfig, AgenticFlowError> {
+    let invalid = |message: &str| {
+        AgenticFlowError::ConfigError(format!("Invalid MCP server '{}': {}", name, message))
+    };
+
+    let entry = entry
+        .as_object()
+        .ok_or_else(|| invalid("expected an object"))?;
+
+    for key in entry.keys() {
+        if !KNOWN_SERVER_KEYS.contains(&key.as_str()) {
+            println!(
+                "WARNING: Ignoring unknown key '{}' for MCP server '{}'",
+                key, name
+            );
+        }
+    }
+
+    let command = entry
+        .get("command")
+        .and_then(Value::as_str)
+        .ok_or_else(|| invalid("'command' must be a string"))?;
+
+    let args = match entry.get("args") {
+        None | Some(Value::Null) => vec![],
+        Some(args) => serde_json::from_value(args.clone())
+            .map_err(|_| invalid("'args' must be an array of strings"))?,
+    };
+
+    let env = match entry.get("env") {
+        None | Some(Value::Null) => HashMap::new(),
+        Some(env) => serde_json::from_value(env.clone())
+            .map_err(|_| invalid("'env' must be an object of strings"))?,
+    };
+
+    Ok(ServerConfig {
+        server_type: ServerType::Command,
+        module_name: None,
+        package_name: None,
+        command: Some(command.to_string()),
+        args,
+        env,
+        secrets: HashMap::new(),
+        auto_install: false,
+        config: None,
+    })
+}
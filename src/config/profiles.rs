@@ -0,0 +1,130 @@
+//! Named `profiles` that overlay the base config, e.g. `[profiles.prod]`.
+
+use serde_json::{Map, Value};
+
+use crate::errors::AgenticFlowError;
+
+use super::env::join_path;
+
+pub const PROFILES_KEY: &str = "profiles";
+pub const PROFILE_ENV_VAR: &str = "AGENTIC_FLOW_PROFILE";
+
+/// The keys a profile sets on top of the base config, as dotted paths.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ProfileOverrides {
+    pub profile: String,
+    /// Sorted. Appended lists are listed as `+path`.
+    pub keys: Vec<String>,
+}
+
+/// Removes the `profiles` table from `value` and returns it.
+pub fn take_profiles(value: &mut Value) -> Result<Map<String, Value>, AgenticFlowError> {
+    let profiles = match value
+        .as_object_mut()
+        .and_then(|root| root.remove(PROFILES_KEY))
+    {
+        None => return Ok(Map::new()),
+        Some(profiles) => profiles,
+    };
+
+    match profiles {
+        Value::Object(profiles) => Ok(profiles),
+        _ => Err(AgenticFlowError::ConfigError(format!(
+            "'{}' must be a table of named profiles",
+            PROFILES_KEY
+        ))),
+    }
+}
+
+/// Overlays the named profile onto `base` and returns the keys it overrode.
+pub fn apply_profile(
+    base: &mut Value,
+    profiles: &Map<String, Value>,
+    name: &str,
+) -> Result<ProfileOverrides, AgenticFlowError> {
+    let overlay = profiles.get(name).ok_or_else(|| {
+        let available: Vec<&str> = profiles.keys().map(String::as_str).collect();
+        AgenticFlowError::ConfigError(format!(
+            "Unknown config profile '{}', available profiles: [{}]",
+            name,
+            available.join(", ")
+        ))
+    })?;
+    if !overlay.is_object() {
+        return Err(AgenticFlowError::ConfigError(format!(
+            "Config profile '{}' must be a table",
+            name
+        )));
+    }
+
+    let mut keys = Vec::new();
+    merge(base, overlay, "", &mut keys)?;
+    keys.sort();
+
+    Ok(ProfileOverrides {
+        profile: name.to_string(),
+        keys,
+    })
+}
+
+/// Deep-merges tables; scalars and lists are replaced, unless the key is written
+/// as `"+key"`, in which case the list is appended to the base list.
+fn merge(
+    base: &mut Value,
+    overlay: &Value,
+    path: &str,
+    keys: &mut Vec<String>,
+) -> Result<(), AgenticFlowError> {
+    let (Value::Object(base_map), Value::Object(overlay_map)) = (&mut *base, overlay) else {
+        *base = overlay.clone();
+        keys.push(path.to_string());
+        return Ok(());
+    };
+
+    for (key, value) in overlay_map {
+        if let Some(list_key) = key.strip_prefix('+') {
+            let item_path = join_path(path, list_key);
+            append(base_map, list_key, value, &item_path)?;
+            keys.push(format!("+{}", item_path));
+            continue;
+        }
+
+        let item_path = join_path(path, key);
+        match base_map.get_mut(key) {
+            Some(existing) if existing.is_object() && value.is_object() => {
+                merge(existing, value, &item_path, keys)?
+            }
+            _ => {
+                base_map.insert(key.clone(), value.clone());
+                keys.push(item_path);
+            }
+        }
+    }
+
+    Ok(())
+}
+
+fn append(
+    base_map: &mut Map<String, Value>,
+    key: &str,
+    value: &Value,
+    path: &str,
+) -> Result<(), AgenticFlowError> {
+    let items = value.as_array().ok_or_else(|| {
+        AgenticFlowError::ConfigError(format!("'+{}' in a profile must be a list", path))
+    })?;
+
+    match base_map
+        .entry(key.to_string())
+        .or_insert_with(|| Value::Array(vec![]))
+    {
+        Value::Array(existing) => {
+            existing.extend(items.iter().cloned());
+            Ok(())
+        }
+        _ => Err(AgenticFlowError::ConfigError(format!(
+            "'+{}' in a profile appends to a list, but '{}' is not a list",
+            path, path
+        ))),
+    }
+}
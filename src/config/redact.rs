@@ -0,0 +1,85 @@
+//! Masking of credentials in `Debug` output and redacted config dumps.
+
+use std::{collections::HashMap, fmt};
+
+use serde_json::Value;
+
+pub const REDACTED: &str = "***";
+
+const SECRET_MARKERS: [&str; 7] = [
+    "SECRET",
+    "TOKEN",
+    "KEY",
+    "PASSWORD",
+    "PASSWD",
+    "CREDENTIALS",
+    "AUTHORIZATION",
+];
+
+/// Whether a key such as `GITHUB_TOKEN`, `api_key` or `clientSecret` names a secret.
+///
+/// The key is split on `_`, `-` and `.`, and a part matches when it ends with a marker,
+/// so `max_tokens` is not a secret. Keys ending in `_env` name an environment variable
+/// rather than hold its value and are never secret.
+pub fn is_secret_key(key: &str) -> bool {
+    let key = key.to_ascii_uppercase();
+    let parts: Vec<&str> = key
+        .split(['_', '-', '.'])
+        .filter(|part| !part.is_empty())
+        .collect();
+    if parts.last() == Some(&"ENV") {
+        return false;
+    }
+
+    parts
+        .iter()
+        .any(|part| SECRET_MARKERS.iter().any(|marker| part.ends_with(marker)))
+}
+
+/// Replaces the value of every secret-named key in `value`, at any depth.
+pub fn redact_value(value: &mut Value) {
+    match value {
+        Value::Object(map) => {
+            for (key, item) in map.iter_mut() {
+                if is_secret_key(key) {
+                    *item = Value::String(REDACTED.to_string());
+                } else {
+                    redact_value(item);
+                }
+            }
+        }
+        Value::Array(items) => items.iter_mut().for_each(redact_value),
+        _ => {}
+    }
+}
+
+/// `Debug` for an env map that masks secret-named values.
+pub struct RedactedEnv<'a>(pub &'a HashMap<String, String>);
+
+impl fmt::Debug for RedactedEnv<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut keys: Vec<&String> = self.0.keys().collect();
+        keys.sort();
+
+        let mut map = f.debug_map();
+        for key in keys {
+            if is_secret_key(key) {
+                map.entry(key, &REDACTED);
+            } else {
+                map.entry(key, &self.0[key]);
+            }
+        }
+        map.finish()
+    }
+}
+
+/// `Debug` for a free-form JSON value that masks secret-named keys.
+pub struct RedactedValue<'a>(pub &'a Value);
+
+impl fmt::Debug for RedactedValue<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut value = self.0.clone();
+        redact_value(&mut value);
+        write!(f, "{}", value)
+    }
+}
@@ -0,0 +1,305 @@
+use std::fmt;
+
+use crate::{
+    agent::{ExecutionMode, ExecutionStrategy},
+    errors::AgenticFlowError,
+    llm_client::ollama_base_url,
+    planner::PlannerStrategy,
+};
+
+use super::{ProviderKind, ServerType, SystemConfig};
+
+/// A single problem found by [`SystemConfig::validate`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct ConfigViolation {
+    /// Dotted path of the offending field, e.g. `mcp_config.servers.web.module_name`.
+    pub path: String,
+    pub message: String,
+}
+
+/// Every violation found in a config, rather than just the first one.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ConfigValidationReport {
+    pub violations: Vec<ConfigViolation>,
+}
+
+impl ConfigValidationReport {
+    fn push(&mut self, path: impl Into<String>, message: impl Into<String>) {
+        self.violations.push(ConfigViolation {
+            path: path.into(),
+            message: message.into(),
+        });
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.violations.is_empty()
+    }
+
+    pub fn paths(&self) -> Vec<&str> {
+        self.violations.iter().map(|v| v.path.as_str()).collect()
+    }
+}
+
+impl fmt::Display for ConfigValidationReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} config violation(s)", self.violations.len())?;
+        for violation in &self.violations {
+            write!(f, "\n  - {}: {}", violation.path, violation.message)?;
+        }
+        Ok(())
+    }
+}
+
+impl From<ConfigValidationReport> for AgenticFlowError {
+    fn from(report: ConfigValidationReport) -> Self {
+        AgenticFlowError::ConfigError(report.to_string())
+    }
+}
+
+impl SystemConfig {
+    /// Checks the config for problems without touching the network or spawning processes.
+    pub fn validate(&self) -> Result<(), ConfigValidationReport> {
+        let mut report = ConfigValidationReport::default();
+
+        let mut server_names: Vec<&String> = self.mcp_config.servers.keys().collect();
+        server_names.sort();
+        for name in server_names {
+            let server = &self.mcp_config.servers[name];
+            let path = format!("mcp_config.servers.{}", name);
+            let (field, value) = match server.server_type {
+                ServerType::Python => ("module_name", &server.module_name),
+                ServerType::Node => ("package_name", &server.package_name),
+                ServerType::Command => ("command", &server.command),
+            };
+            if value.as_deref().is_none_or(|value| value.trim().is_empty()) {
+                report.push(
+                    format!("{}.{}", path, field),
+                    format!("required for {:?} servers", server.server_type),
+                );
+            }
+
+            let mut shadowed: Vec<&String> = server
+                .secrets
+                .keys()
+                .filter(|var| server.env.contains_key(*var))
+                .collect();
+            shadowed.sort();
+            for var in shadowed {
+                report.push(
+                    format!("{}.secrets.{}", path, var),
+                    "also set in env, use one or the other",
+                );
+            }
+        }
+
+        for (index, name) in self.enabled_servers.iter().enumerate() {
+            if !self.mcp_config.servers.contains_key(name) {
+                report.push(
+                    format!("enabled_servers.{}", index),
+                    format!("server '{}' is not defined in mcp_config.servers", name),
+                );
+            }
+        }
+
+        let llm = &self.llm_config;
+        if llm.model.trim().is_empty() {
+            report.push("llm_config.model", "must not be empty");
+        }
+        if !(0.0..=2.0).contains(&llm.temperature) {
+            report.push(
+                "llm_config.temperature",
+                format!("must be between 0.0 and 2.0, got {}", llm.temperature),
+            );
+        }
+        if llm.api_key.is_some() && llm.api_key_env.is_some() {
+            report.push(
+                "llm_config.api_key",
+                "set either api_key or api_key_env, not both",
+            );
+        }
+        if llm.max_tokens == Some(0) {
+            report.push("llm_config.max_tokens", "must be greater than zero");
+        }
+        if llm.timeout_seconds == Some(0) {
+            report.push("llm_config.timeout_seconds", "must be greater than zero");
+        }
+        if llm
+            .http
+            .as_ref()
+            .is_some_and(|http| http.max_response_bytes == Some(0))
+        {
+            report.push(
+                "llm_config.http.max_response_bytes",
+                "must be greater than zero",
+            );
+        }
+        let malformed_base_url = llm
+            .base_url
+            .as_deref()
+            .is_some_and(|base_url| ollama_base_url(base_url).is_err());
+        if llm.provider == ProviderKind::Ollama && malformed_base_url {
+            report.push("llm_config.base_url", "must be a URL or host[:port]");
+        }
+
+        if self.agent_config.max_steps == 0 {
+            report.push("agent_config.max_steps", "must be greater than zero");
+        }
+        if self.agent_config.timeout_seconds == 0 {
+            report.push("agent_config.timeout_seconds", "must be greater than zero");
+        }
+
+        if self.planner.mcts_simulations == Some(0) {
+            report.push("planner.mcts_simulations", "must be greater than zero");
+        }
+        if self.planner.beam_width == Some(0) {
+            report.push("planner.beam_width", "must be greater than zero");
+        }
+        if self.planner.beam_depth == Some(0) {
+            report.push("planner.beam_depth", "must be greater than zero");
+        }
+        for (index, strategy) in self.planner.fallback.iter().enumerate() {
+            if *strategy == PlannerStrategy::React {
+                report.push(
+                    format!("planner.fallback.{}", index),
+                    "react chooses its steps while they run and cannot be a fallback",
+                );
+            }
+        }
+
+        if self
+            .execution
+            .as_ref()
+            .is_some_and(|e| e.workers == Some(0))
+        {
+            report.push("execution.workers", "must be greater than zero");
+        }
+
+        if report.is_empty() {
+            Ok(())
+        } else {
+            Err(report)
+        }
+    }
+
+    /// Settings that are valid but have no effect, such as `mcts_simulations` on a
+    /// non-MCTS planner. These never fail [`SystemConfig::validate`].
+    pub fn warnings(&self) -> ConfigValidationReport {
+        let mut report = ConfigValidationReport::default();
+
+        let planner = &self.planner;
+        let uses_mcts = planner.kind == PlannerStrategy::Mcts
+            || planner.fallback.contains(&PlannerStrategy::Mcts);
+        if planner.mcts_simulations.is_some() && !uses_mcts {
+            report.push(
+                "planner.mcts_simulations",
+                format!("ignored by the {:?} planner", planner.kind),
+            );
+        }
+        if planner.mcts_strategy.is_some() && !uses_mcts {
+            report.push(
+                "planner.mcts_strategy",
+                format!("ignored by the {:?} planner", planner.kind),
+            );
+        }
+        let uses_beam = planner.kind == PlannerStrategy::Beam
+            || planner.fallback.contains(&PlannerStrategy::Beam);
+        let beam_settings = [
+            ("planner.beam_width", planner.beam_width.is_some()),
+            ("planner.beam_depth", planner.beam_depth.is_some()),
+            ("planner.beam_scorer", planner.beam_scorer.is_some()),
+        ];
+        for (path, set) in beam_settings {
+            if set && !uses_beam {
+                report.push(path, format!("ignored by the {:?} planner", planner.kind));
+            }
+        }
+        if planner.critique_rounds == Some(0) {
+            report.push("planner.critique_rounds", "0 rounds disables critique");
+        }
+        if planner.kind == PlannerStrategy::React {
+            if planner.critique_rounds.is_some_and(|rounds| rounds > 0) {
+                report.push("planner.critique_rounds", "ignored by the React planner");
+            }
+            if planner.schema_repair_rounds.is_some() {
+                report.push(
+                    "planner.schema_repair_rounds",
+                    "ignored by the React planner",
+                );
+            }
+            if !planner.fallback.is_empty() {
+                report.push("planner.fallback", "ignored by the React planner");
+            }
+        }
+        for (index, strategy) in planner.fallback.iter().enumerate() {
+            if planner.fallback[..index].contains(strategy) || *strategy == planner.kind {
+                report.push(
+                    format!("planner.fallback.{}", index),
+                    format!("{:?} is already tried earlier", strategy),
+                );
+            }
+        }
+
+        let llm = &self.llm_config;
+        if llm.base_url.is_some() && llm.provider != ProviderKind::Ollama {
+            report.push(
+                "llm_config.base_url",
+                format!("ignored by the {:?} provider", llm.provider),
+            );
+        }
+        let embeds = matches!(
+            llm.provider,
+            ProviderKind::Ollama | ProviderKind::OpenRouter
+        );
+        if llm.embedding_model.is_some() && !embeds {
+            report.push(
+                "llm_config.embedding_model",
+                format!("ignored by the {:?} provider", llm.provider),
+            );
+        }
+        if llm.ollama.is_some() && llm.provider != ProviderKind::Ollama {
+            report.push(
+                "llm_config.ollama",
+                format!("ignored by the {:?} provider", llm.provider),
+            );
+        }
+        if llm.verify_on_startup && llm.provider != ProviderKind::Ollama {
+            report.push(
+                "llm_config.verify_on_startup",
+                format!("ignored by the {:?} provider", llm.provider),
+            );
+        }
+        let attribution = [("app_url", &llm.app_url), ("app_name", &llm.app_name)];
+        for (field, value) in attribution {
+            if value.is_some() && llm.provider != ProviderKind::OpenRouter {
+                report.push(
+                    format!("llm_config.{}", field),
+                    format!("ignored by the {:?} provider", llm.provider),
+                );
+            }
+        }
+        if llm
+            .http
+            .as_ref()
+            .is_some_and(|http| http.danger_accept_invalid_certs)
+        {
+            report.push(
+                "llm_config.http.danger_accept_invalid_certs",
+                "TLS certificates are not verified",
+            );
+        }
+
+        if let Some(execution) = &self.execution {
+            if execution.mode == ExecutionStrategy::Sequential && execution.workers.is_some() {
+                report.push("execution.workers", "ignored in sequential mode");
+            }
+            if self.agent_config.execution_mode != ExecutionMode::default() {
+                report.push(
+                    "agent_config.execution_mode",
+                    "overridden by the execution section",
+                );
+            }
+        }
+
+        report
+    }
+}
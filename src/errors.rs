@@ -1,3 +1,38 @@
+mod budget;
+mod conversions;
+mod execution;
+mod kind;
+mod planning;
+mod provider;
+mod wire;
+
+use std::{fmt, time::Duration};
+
+pub use budget::BudgetOverrun;
+pub(crate) use conversions::HTTP_REQUEST;
+pub use conversions::with_timeout;
+pub use execution::ExecutionFailure;
+pub use kind::ErrorKind;
+pub use planning::PlanningDiagnostics;
+pub use provider::ProviderError;
+pub use wire::WireError;
+
+/// Where a tool lives: registered locally or provided by an MCP server.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ToolOrigin {
+    Local,
+    Mcp { server: String },
+}
+
+impl fmt::Display for ToolOrigin {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ToolOrigin::Local => write!(f, "local tool"),
+            ToolOrigin::Mcp { server } => write!(f, "MCP server '{}'", server),
+        }
+    }
+}
+
 #[derive(Debug, Clone)]
 pub enum AgenticFlowError {
     PlanningError(String),
@@ -6,19 +41,305 @@ pub enum AgenticFlowError {
     ParseError(String),
     NetworkError(String),
     ExecutionError(String),
-    ServerNotFound
+    ConfigError(String),
+    /// The provider answered with an unsuccessful status not covered by a more specific variant.
+    ApiResponseError {
+        status: u16,
+        /// The provider's error message, or the start of the body if it sent none.
+        message: String,
+        provider_error: Option<ProviderError>,
+    },
+    /// The provider answered 429. `retry_after` comes from the `Retry-After` header.
+    RateLimited {
+        retry_after: Option<Duration>,
+        provider_error: Option<ProviderError>,
+    },
+    /// The provider answered 401 or 403.
+    Unauthorized {
+        status: u16,
+        provider_error: Option<ProviderError>,
+    },
+    /// The prompt did not fit in the model's context window.
+    ContextLengthExceeded {
+        provider_error: Option<ProviderError>,
+    },
+    /// The answer was cut off at the token limit, so it cannot be used. Asking again with
+    /// a higher `max_tokens` may succeed.
+    OutputTruncated {
+        max_tokens: Option<usize>,
+    },
+    /// An operation ran out of time, such as an LLM request exceeding `timeout_seconds`.
+    Timeout {
+        /// What timed out, e.g. `llm chat`.
+        operation: &'static str,
+        /// Zero when it is not known, as for a timeout of a `reqwest::Client` built
+        /// elsewhere.
+        limit: Duration,
+    },
+    /// A request waited longer than `waited` for its turn under the client's concurrency
+    /// or rate limit, and was not sent.
+    QueueTimeout {
+        waited: Duration,
+    },
+    /// An operation was stopped before it finished, such as a task aborted at shutdown.
+    Cancelled {
+        operation: &'static str,
+    },
+    ToolNotFound {
+        tool: String,
+    },
+    ToolExecutionFailed {
+        tool: String,
+        origin: ToolOrigin,
+        source: Box<AgenticFlowError>,
+    },
+    /// An MCP server could not be started, stopped or queried, or a call to it failed.
+    McpServerError {
+        server: String,
+        tool: Option<String>,
+        source: Box<AgenticFlowError>,
+    },
+    /// A plan step failed, for executors that do not keep the run's partial state.
+    StepFailed {
+        /// Zero-based position of the step in the plan.
+        step_index: usize,
+        step_id: Option<String>,
+        tool: String,
+        source: Box<AgenticFlowError>,
+    },
+    /// A run spent the [`Budget`](crate::llm_client::Budget) it was given, and an LLM
+    /// call was refused.
+    BudgetExceeded(Box<BudgetOverrun>),
+    /// A step failed and the [`Agent`](crate::agent::Agent) aborted the run.
+    /// Displays like [`AgenticFlowError::StepFailed`].
+    ExecutionFailed(Box<ExecutionFailure>),
+    PlanningFailed {
+        planner: String,
+        /// The planner stage that failed, e.g. `decompose`, `refine` or `simulate`.
+        phase: &'static str,
+        source: Box<AgenticFlowError>,
+        diagnostics: Box<PlanningDiagnostics>,
+    },
+    /// A request that still failed after the retries of a
+    /// [`RetryPolicy`](crate::llm_client::RetryPolicy). `source` is the last attempt's error.
+    RetriesExhausted {
+        attempts: usize,
+        source: Box<AgenticFlowError>,
+    },
+    /// An error whose [`ErrorKind`] was set by [`AgenticFlowError::with_kind`].
+    /// Displays as its source.
+    Classified {
+        kind: ErrorKind,
+        source: Box<AgenticFlowError>,
+    },
+}
+
+impl AgenticFlowError {
+    pub(crate) fn mcp_server(server: &str, tool: Option<&str>, source: AgenticFlowError) -> Self {
+        AgenticFlowError::McpServerError {
+            server: server.to_string(),
+            tool: tool.map(str::to_string),
+            source: Box::new(source),
+        }
+    }
+
+    /// The tool involved, for tool, MCP and step errors.
+    pub fn tool_name(&self) -> Option<&str> {
+        match self {
+            AgenticFlowError::ToolNotFound { tool }
+            | AgenticFlowError::ToolExecutionFailed { tool, .. }
+            | AgenticFlowError::StepFailed { tool, .. } => Some(tool),
+            AgenticFlowError::ExecutionFailed(failure) => Some(&failure.tool),
+            AgenticFlowError::McpServerError { tool, .. } => tool.as_deref(),
+            AgenticFlowError::PlanningFailed { source, .. }
+            | AgenticFlowError::RetriesExhausted { source, .. }
+            | AgenticFlowError::Classified { source, .. } => source.tool_name(),
+            _ => None,
+        }
+    }
+
+    /// The MCP server involved, looking through wrapping step and tool errors.
+    pub fn server_name(&self) -> Option<&str> {
+        match self {
+            AgenticFlowError::McpServerError { server, .. } => Some(server),
+            AgenticFlowError::ToolExecutionFailed {
+                origin: ToolOrigin::Mcp { server },
+                ..
+            } => Some(server),
+            AgenticFlowError::ToolExecutionFailed { source, .. }
+            | AgenticFlowError::StepFailed { source, .. }
+            | AgenticFlowError::PlanningFailed { source, .. }
+            | AgenticFlowError::RetriesExhausted { source, .. }
+            | AgenticFlowError::Classified { source, .. } => source.server_name(),
+            AgenticFlowError::ExecutionFailed(failure) => failure.error.server_name(),
+            _ => None,
+        }
+    }
+
+    /// The failed step's position, for step errors.
+    pub fn step_index(&self) -> Option<usize> {
+        match self {
+            AgenticFlowError::StepFailed { step_index, .. } => Some(*step_index),
+            AgenticFlowError::ExecutionFailed(failure) => Some(failure.step_index),
+            AgenticFlowError::Classified { source, .. } => source.step_index(),
+            _ => None,
+        }
+    }
+
+    /// The partial run state, for runs aborted by a failing step.
+    pub fn execution_failure(&self) -> Option<&ExecutionFailure> {
+        match self {
+            AgenticFlowError::ExecutionFailed(failure) => Some(failure),
+            AgenticFlowError::Classified { source, .. } => source.execution_failure(),
+            _ => None,
+        }
+    }
+
+    /// The innermost error, after unwrapping step, tool, server and planning context.
+    pub fn root_cause(&self) -> &AgenticFlowError {
+        match self {
+            AgenticFlowError::ToolExecutionFailed { source, .. }
+            | AgenticFlowError::McpServerError { source, .. }
+            | AgenticFlowError::StepFailed { source, .. }
+            | AgenticFlowError::PlanningFailed { source, .. }
+            | AgenticFlowError::RetriesExhausted { source, .. }
+            | AgenticFlowError::Classified { source, .. } => source.root_cause(),
+            AgenticFlowError::ExecutionFailed(failure) => failure.error.root_cause(),
+            _ => self,
+        }
+    }
 }
 
-impl std::fmt::Display for AgenticFlowError {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+impl fmt::Display for AgenticFlowError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
             AgenticFlowError::PlanningError(msg) => write!(f, "Planning error: {}", msg),
             AgenticFlowError::ToolError(msg) => write!(f, "Tool error: {}", msg),
             AgenticFlowError::ApiClientError(msg) => write!(f, "API client error: {}", msg),
             AgenticFlowError::ParseError(msg) => write!(f, "Parse error: {}", msg),
             AgenticFlowError::NetworkError(msg) => write!(f, "Network error: {}", msg),
-            AgenticFlowError::ServerNotFound => write!(f, "Server not found"),
             AgenticFlowError::ExecutionError(msg) => write!(f, "Execution error: {}", msg),
+            AgenticFlowError::ConfigError(msg) => write!(f, "Config error: {}", msg),
+            AgenticFlowError::Timeout { operation, limit } if limit.is_zero() => {
+                write!(f, "operation '{}' timed out", operation)
+            }
+            AgenticFlowError::Timeout { operation, limit } => write!(
+                f,
+                "operation '{}' timed out after {}s",
+                operation,
+                limit.as_secs_f64()
+            ),
+            AgenticFlowError::QueueTimeout { waited } => write!(
+                f,
+                "request waited {}s for the client's concurrency or rate limit",
+                waited.as_secs_f64()
+            ),
+            AgenticFlowError::Cancelled { operation } => {
+                write!(f, "operation '{}' was cancelled", operation)
+            }
+            AgenticFlowError::ApiResponseError {
+                status, message, ..
+            } => write!(f, "API request failed with status {}: {}", status, message),
+            AgenticFlowError::RateLimited { retry_after, .. } => match retry_after {
+                Some(retry_after) => write!(
+                    f,
+                    "Rate limited by the provider, retry after {}s",
+                    retry_after.as_secs_f64()
+                ),
+                None => write!(f, "Rate limited by the provider"),
+            },
+            AgenticFlowError::Unauthorized {
+                status,
+                provider_error,
+            } => write!(
+                f,
+                "The provider rejected the credentials ({}){}",
+                status,
+                provider_message(provider_error)
+            ),
+            AgenticFlowError::ContextLengthExceeded { provider_error } => write!(
+                f,
+                "The prompt exceeds the model's context length{}",
+                provider_message(provider_error)
+            ),
+            AgenticFlowError::OutputTruncated { max_tokens } => match max_tokens {
+                Some(max_tokens) => write!(
+                    f,
+                    "The response was cut off at the limit of {} tokens",
+                    max_tokens
+                ),
+                None => write!(f, "The response was cut off at the model's token limit"),
+            },
+            AgenticFlowError::ToolNotFound { tool } => write!(f, "Tool '{}' not found", tool),
+            AgenticFlowError::ToolExecutionFailed {
+                tool,
+                origin,
+                source,
+            } => write!(f, "Tool '{}' ({}) failed: {}", tool, origin, source),
+            AgenticFlowError::McpServerError {
+                server,
+                tool: Some(tool),
+                source,
+            } => write!(
+                f,
+                "MCP server '{}' failed calling '{}': {}",
+                server, tool, source
+            ),
+            AgenticFlowError::McpServerError {
+                server,
+                tool: None,
+                source,
+            } => write!(f, "MCP server '{}' failed: {}", server, source),
+            AgenticFlowError::StepFailed {
+                step_index,
+                tool,
+                source,
+                ..
+            } => write!(f, "Step {} ('{}') failed: {}", step_index + 1, tool, source),
+            AgenticFlowError::PlanningFailed {
+                planner,
+                phase,
+                source,
+                ..
+            } => write!(
+                f,
+                "Planner '{}' failed during {}: {}",
+                planner, phase, source
+            ),
+            AgenticFlowError::BudgetExceeded(overrun) => write!(f, "{}", overrun),
+            AgenticFlowError::ExecutionFailed(failure) => write!(
+                f,
+                "Step {} ('{}') failed: {}",
+                failure.step_index + 1,
+                failure.tool,
+                failure.error
+            ),
+            AgenticFlowError::RetriesExhausted { attempts, source } => {
+                write!(f, "{} (gave up after {} attempts)", source, attempts)
+            }
+            AgenticFlowError::Classified { source, .. } => write!(f, "{}", source),
+        }
+    }
+}
+
+fn provider_message(provider_error: &Option<ProviderError>) -> String {
+    match provider_error {
+        Some(error) if !error.message.is_empty() => format!(": {}", error.message),
+        _ => String::new(),
+    }
+}
+
+impl std::error::Error for AgenticFlowError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            AgenticFlowError::ToolExecutionFailed { source, .. }
+            | AgenticFlowError::McpServerError { source, .. }
+            | AgenticFlowError::StepFailed { source, .. }
+            | AgenticFlowError::PlanningFailed { source, .. }
+            | AgenticFlowError::RetriesExhausted { source, .. } => Some(source.as_ref()),
+            AgenticFlowError::ExecutionFailed(failure) => Some(&failure.error),
+            AgenticFlowError::Classified { source, .. } => source.source(),
+            _ => None,
         }
     }
 }
@@ -0,0 +1,83 @@
+//! What a run had spent, and done, when it ran out of budget.
+
+use std::{collections::HashMap, fmt};
+
+use serde_json::Value;
+
+use super::AgenticFlowError;
+use crate::{
+    llm_client::{Budget, RunTotals},
+    planner::StepOutcome,
+};
+
+/// The payload of [`AgenticFlowError::BudgetExceeded`]: the budget, what the run had
+/// spent when a call was refused, and the work done before it.
+#[derive(Debug, Clone)]
+pub struct BudgetOverrun {
+    pub budget: Budget,
+    /// The totals of the run whose budget was spent, at the refused call.
+    pub totals: RunTotals,
+    /// The steps the [`Agent`](crate::agent::Agent) had recorded, in plan order; empty
+    /// when the budget ran out while planning.
+    pub completed: Vec<StepOutcome>,
+    /// The execution context when the budget ran out.
+    pub context: HashMap<String, Value>,
+}
+
+impl fmt::Display for BudgetOverrun {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut spent = Vec::new();
+        if let Some(max) = self.budget.max_total_tokens {
+            let tokens = self.totals.usage.map_or(0, |usage| usage.total_tokens);
+            spent.push(format!("{} of {} tokens", tokens, max));
+        }
+        if let Some(max) = self.budget.max_cost_usd {
+            let cost = self.totals.cost_usd.unwrap_or(0.0);
+            spent.push(format!("${:.4} of ${:.4}", cost, max));
+        }
+        write!(f, "The run exhausted its budget: {}", spent.join(", "))
+    }
+}
+
+impl AgenticFlowError {
+    /// The spent budget behind this error, looking through wrapping errors such as a
+    /// failed planner phase.
+    pub fn budget_overrun(&self) -> Option<&BudgetOverrun> {
+        match self.root_cause() {
+            AgenticFlowError::BudgetExceeded(overrun) => Some(overrun),
+            _ => None,
+        }
+    }
+
+    /// This error with the work of a run, if it is a spent budget.
+    pub(crate) fn with_run_progress(
+        self,
+        completed: &[StepOutcome],
+        context: &HashMap<String, Value>,
+    ) -> Self {
+        match self {
+            AgenticFlowError::BudgetExceeded(mut overrun) => {
+                overrun.completed = completed.to_vec();
+                overrun.context = context.clone();
+                AgenticFlowError::BudgetExceeded(overrun)
+            }
+            error => error,
+        }
+    }
+
+    /// A [`AgenticFlowError::BudgetExceeded`] in place of an error wrapping one, keeping
+    /// the run state of a failed step; other errors are returned as they are.
+    pub(crate) fn surfacing_budget(self) -> Self {
+        if matches!(self, AgenticFlowError::BudgetExceeded(_)) {
+            return self;
+        }
+        let Some(overrun) = self.budget_overrun() else {
+            return self;
+        };
+        let overrun = AgenticFlowError::BudgetExceeded(Box::new(overrun.clone()));
+        match self.execution_failure() {
+            Some(failure) => overrun.with_run_progress(&failure.completed, &failure.context),
+            None => overrun,
+        }
+    }
+}
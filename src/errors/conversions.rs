@@ -0,0 +1,54 @@
+//! Conversions from the errors of the crates we build on, so call sites can use `?`.
+
+use std::time::Duration;
+
+use super::AgenticFlowError;
+
+/// The operation of a [`AgenticFlowError::Timeout`] converted from a `reqwest` timeout.
+pub(crate) const HTTP_REQUEST: &str = "http request";
+
+impl From<reqwest::Error> for AgenticFlowError {
+    /// Request timeouts, including the connect timeout of an
+    /// [`HttpConfig`](crate::http::HttpConfig), become [`AgenticFlowError::Timeout`] for
+    /// an `http request`. reqwest does not say what the limit was, so it is zero here;
+    /// `LLMClient` fills in the one from its `HttpConfig`.
+    fn from(error: reqwest::Error) -> Self {
+        if error.is_timeout() {
+            AgenticFlowError::Timeout {
+                operation: HTTP_REQUEST,
+                limit: Duration::ZERO,
+            }
+        } else if error.is_connect() || error.is_request() || error.is_body() || error.is_decode() {
+            AgenticFlowError::NetworkError(error.to_string())
+        } else {
+            AgenticFlowError::ApiClientError(error.to_string())
+        }
+    }
+}
+
+impl From<serde_json::Error> for AgenticFlowError {
+    fn from(error: serde_json::Error) -> Self {
+        AgenticFlowError::ParseError(error.to_string())
+    }
+}
+
+impl From<tokio::task::JoinError> for AgenticFlowError {
+    fn from(error: tokio::task::JoinError) -> Self {
+        if error.is_cancelled() {
+            AgenticFlowError::Cancelled { operation: "task" }
+        } else {
+            AgenticFlowError::ExecutionError(format!("Task failed: {}", error))
+        }
+    }
+}
+
+/// Runs `future`, failing with [`AgenticFlowError::Timeout`] if it takes longer than `limit`.
+pub async fn with_timeout<T>(
+    operation: &'static str,
+    limit: Duration,
+    future: impl Future<Output = Result<T, AgenticFlowError>>,
+) -> Result<T, AgenticFlowError> {
+    tokio::time::timeout(limit, future)
+        .await
+        .map_err(|_| AgenticFlowError::Timeout { operation, limit })?
+}
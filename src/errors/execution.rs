@@ -0,0 +1,35 @@
+//! What a run had gathered when one of its steps failed.
+
+use std::collections::HashMap;
+
+use serde::{Serialize, Serializer};
+use serde_json::Value;
+
+use super::AgenticFlowError;
+use crate::planner::StepOutcome;
+
+/// The payload of [`AgenticFlowError::ExecutionFailed`]: the step that failed, why,
+/// and the state of the run up to that point, so a caller can report or resume it.
+#[derive(Debug, Clone, Serialize)]
+pub struct ExecutionFailure {
+    /// Zero-based position of the failed step in the plan.
+    pub step_index: usize,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub step_id: Option<String>,
+    pub tool: String,
+    pub params: Value,
+    /// The step's own error, after retries. Serialized as its message.
+    #[serde(serialize_with = "serialize_display")]
+    pub error: AgenticFlowError,
+    /// The steps recorded before the failure, in plan order.
+    pub completed: Vec<StepOutcome>,
+    /// The execution context at the time of the failure.
+    pub context: HashMap<String, Value>,
+}
+
+fn serialize_display<S: Serializer>(
+    error: &AgenticFlowError,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    serializer.collect_str(error)
+}
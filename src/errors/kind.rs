@@ -0,0 +1,95 @@
+//! Classifying errors so every retry loop makes the same call.
+
+use std::time::Duration;
+
+use super::AgenticFlowError;
+
+/// How an error should be handled, independent of where it came from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ErrorKind {
+    /// Likely to succeed if tried again: network failures and 5xx responses.
+    Transient,
+    /// Fails the same way every time.
+    Permanent,
+    RateLimited {
+        retry_after: Option<Duration>,
+    },
+    AuthFailure,
+    /// The request itself was rejected, e.g. a 400 or 422 response.
+    InvalidInput,
+    Cancelled,
+    Timeout,
+}
+
+impl ErrorKind {
+    /// Classifies an HTTP error status.
+    pub fn from_status(status: u16) -> Self {
+        match status {
+            401 | 403 => ErrorKind::AuthFailure,
+            408 => ErrorKind::Timeout,
+            429 => ErrorKind::RateLimited { retry_after: None },
+            400 | 422 => ErrorKind::InvalidInput,
+            500..=599 => ErrorKind::Transient,
+            _ => ErrorKind::Permanent,
+        }
+    }
+
+    pub fn is_retryable(&self) -> bool {
+        matches!(
+            self,
+            ErrorKind::Transient | ErrorKind::RateLimited { .. } | ErrorKind::Timeout
+        )
+    }
+}
+
+impl AgenticFlowError {
+    /// How this error should be handled. Wrapping variants take the kind of their
+    /// source, and [`AgenticFlowError::with_kind`] overrides it.
+    pub fn kind(&self) -> ErrorKind {
+        match self {
+            AgenticFlowError::NetworkError(_) => ErrorKind::Transient,
+            // Tool failures are retried by the step retry policy unless the tool says otherwise.
+            AgenticFlowError::ToolError(_) => ErrorKind::Transient,
+            AgenticFlowError::ApiResponseError { status, .. } => ErrorKind::from_status(*status),
+            AgenticFlowError::RateLimited { retry_after, .. } => ErrorKind::RateLimited {
+                retry_after: *retry_after,
+            },
+            AgenticFlowError::Unauthorized { .. } => ErrorKind::AuthFailure,
+            AgenticFlowError::ContextLengthExceeded { .. } => ErrorKind::InvalidInput,
+            // The same request is cut off the same way; only a higher limit helps.
+            AgenticFlowError::OutputTruncated { .. } => ErrorKind::InvalidInput,
+            AgenticFlowError::ConfigError(_) => ErrorKind::InvalidInput,
+            // Retried like a transient error: the next attempt may be faster.
+            AgenticFlowError::Timeout { .. } => ErrorKind::Timeout,
+            // The queue may be shorter next time.
+            AgenticFlowError::QueueTimeout { .. } => ErrorKind::Timeout,
+            AgenticFlowError::Cancelled { .. } => ErrorKind::Cancelled,
+            AgenticFlowError::PlanningError(_)
+            | AgenticFlowError::ApiClientError(_)
+            | AgenticFlowError::ParseError(_)
+            | AgenticFlowError::ExecutionError(_)
+            | AgenticFlowError::ToolNotFound { .. }
+            | AgenticFlowError::BudgetExceeded(_) => ErrorKind::Permanent,
+            AgenticFlowError::Classified { kind, .. } => *kind,
+            AgenticFlowError::ExecutionFailed(failure) => failure.error.kind(),
+            AgenticFlowError::ToolExecutionFailed { source, .. }
+            | AgenticFlowError::McpServerError { source, .. }
+            | AgenticFlowError::StepFailed { source, .. }
+            | AgenticFlowError::PlanningFailed { source, .. }
+            | AgenticFlowError::RetriesExhausted { source, .. } => source.kind(),
+        }
+    }
+
+    pub fn is_retryable(&self) -> bool {
+        self.kind().is_retryable()
+    }
+
+    /// Overrides the classification of this error, for callers that know better,
+    /// such as a tool whose input can never succeed.
+    pub fn with_kind(self, kind: ErrorKind) -> Self {
+        AgenticFlowError::Classified {
+            kind,
+            source: Box::new(self),
+        }
+    }
+}
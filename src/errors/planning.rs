@@ -0,0 +1,69 @@
+//! What a planner saw when it failed.
+
+use serde::Serialize;
+
+use super::AgenticFlowError;
+use crate::model::ChatMessage;
+
+/// Attached to [`AgenticFlowError::PlanningFailed`]: the model output the planner could
+/// not use, if it got that far, and how many attempts came before.
+#[derive(Debug, Clone, PartialEq, Eq, Default, Serialize)]
+pub struct PlanningDiagnostics {
+    /// The assistant content of the offending response.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub raw_content: Option<String>,
+    /// The offending response's tool calls, as JSON.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tool_calls: Option<String>,
+    /// Attempts made before this one, e.g. earlier planners of a fallback chain or
+    /// earlier MCTS simulations.
+    pub retries: usize,
+    /// Set when the raw output must not leave the process; the wire form then omits it.
+    #[serde(skip)]
+    pub redacted: bool,
+}
+
+impl PlanningDiagnostics {
+    pub fn from_response(message: &ChatMessage) -> Self {
+        Self {
+            raw_content: Some(message.content.clone()),
+            tool_calls: message
+                .tool_calls
+                .as_ref()
+                .map(|tool_calls| serde_json::to_string(tool_calls).unwrap_or_default()),
+            ..Self::default()
+        }
+    }
+
+    /// Cuts the raw output down to `max_chars` characters each.
+    pub fn truncate(&mut self, max_chars: usize) {
+        for text in [&mut self.raw_content, &mut self.tool_calls]
+            .into_iter()
+            .flatten()
+        {
+            if let Some((end, _)) = text.char_indices().nth(max_chars) {
+                text.truncate(end);
+                text.push_str("... [truncated]");
+            }
+        }
+    }
+}
+
+impl AgenticFlowError {
+    /// The diagnostics of a planning failure, possibly behind other context.
+    pub fn planning_diagnostics(&self) -> Option<&PlanningDiagnostics> {
+        match self {
+            AgenticFlowError::PlanningFailed { diagnostics, .. } => Some(diagnostics),
+            AgenticFlowError::Classified { source, .. } => source.planning_diagnostics(),
+            _ => None,
+        }
+    }
+
+    pub(crate) fn planning_diagnostics_mut(&mut self) -> Option<&mut PlanningDiagnostics> {
+        match self {
+            AgenticFlowError::PlanningFailed { diagnostics, .. } => Some(diagnostics),
+            AgenticFlowError::Classified { source, .. } => source.planning_diagnostics_mut(),
+            _ => None,
+        }
+    }
+}
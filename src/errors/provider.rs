@@ -0,0 +1,176 @@
+//! Turning unsuccessful provider responses into typed errors.
+
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use serde::Serialize;
+use serde_json::Value;
+
+use super::AgenticFlowError;
+
+/// Longest response body kept as the message when the provider sent no error JSON, or in
+/// the message of a response that could not be parsed.
+const MAX_BODY_CHARS: usize = 500;
+
+/// The error object from a provider's response body.
+///
+/// OpenRouter and OpenAI-compatible servers send
+/// `{"error": {"message": ..., "code": ..., "type": ...}}`, Ollama sends `{"error": "..."}`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ProviderError {
+    /// The provider's error code, e.g. `"context_length_exceeded"`; numeric codes are
+    /// kept as their decimal string.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub code: Option<String>,
+    pub message: String,
+    #[serde(rename = "type", skip_serializing_if = "Option::is_none")]
+    pub error_type: Option<String>,
+}
+
+impl ProviderError {
+    /// Parses the error object of a response body, if it has one.
+    pub fn from_body(body: &str) -> Option<Self> {
+        let value: Value = serde_json::from_str(body).ok()?;
+        match value.get("error")? {
+            Value::String(message) => Some(Self {
+                code: None,
+                message: message.clone(),
+                error_type: None,
+            }),
+            Value::Object(error) => Some(Self {
+                code: error.get("code").and_then(scalar_string),
+                message: error
+                    .get("message")
+                    .and_then(Value::as_str)
+                    .unwrap_or_default()
+                    .to_string(),
+                error_type: error.get("type").and_then(scalar_string),
+            }),
+            _ => None,
+        }
+    }
+
+    fn is_context_length_exceeded(&self) -> bool {
+        let message = self.message.to_lowercase();
+        self.code.as_deref() == Some("context_length_exceeded")
+            || message.contains("context length")
+            || message.contains("context window")
+    }
+}
+
+fn scalar_string(value: &Value) -> Option<String> {
+    match value {
+        Value::String(text) => Some(text.clone()),
+        Value::Number(number) => Some(number.to_string()),
+        _ => None,
+    }
+}
+
+const MONTHS: [&str; 12] = [
+    "Jan", "Feb", "Mar", "Apr", "May", "Jun", "Jul", "Aug", "Sep", "Oct", "Nov", "Dec",
+];
+
+/// Parses a `Retry-After` header, given in seconds or as an HTTP date; a date in the past
+/// means retrying at once.
+fn parse_retry_after(value: &str) -> Option<Duration> {
+    let value = value.trim();
+    if let Ok(seconds) = value.parse::<f64>() {
+        return Duration::try_from_secs_f64(seconds).ok();
+    }
+    let at = parse_http_date(value)?;
+    Some(at.duration_since(SystemTime::now()).unwrap_or_default())
+}
+
+/// Parses a date in the format HTTP servers send, e.g. `Wed, 21 Oct 2015 07:28:00 GMT`.
+fn parse_http_date(value: &str) -> Option<SystemTime> {
+    let parts: Vec<&str> = value.split_ascii_whitespace().collect();
+    let [_weekday, day, month, year, time, "GMT"] = parts[..] else {
+        return None;
+    };
+    let day: i64 = day.parse().ok().filter(|day| (1..=31).contains(day))?;
+    let month = MONTHS.iter().position(|name| *name == month)? as i64 + 1;
+    let year: i64 = year.parse().ok()?;
+    let clock: Vec<u64> = time
+        .split(':')
+        .map(|part| part.parse().ok())
+        .collect::<Option<_>>()?;
+    let [hours @ 0..24, minutes @ 0..60, seconds @ 0..61] = clock[..] else {
+        return None;
+    };
+
+    let days = u64::try_from(days_since_epoch(year, month, day)).ok()?;
+    let seconds = days * 86_400 + hours * 3_600 + minutes * 60 + seconds;
+    Some(UNIX_EPOCH + Duration::from_secs(seconds))
+}
+
+/// Days from 1970-01-01 to a date of the proleptic Gregorian calendar.
+fn days_since_epoch(year: i64, month: i64, day: i64) -> i64 {
+    // Counts years from March, so the leap day is the last day of a year.
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let year_of_era = year - era * 400;
+    let day_of_year = (153 * ((month + 9) % 12) + 2) / 5 + day - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    era * 146_097 + day_of_era - 719_468
+}
+
+impl AgenticFlowError {
+    /// Classifies an unsuccessful provider response from its status, `Retry-After`
+    /// header and body.
+    pub fn from_http_response(status: u16, retry_after: Option<&str>, body: &str) -> Self {
+        let provider_error = ProviderError::from_body(body);
+
+        match status {
+            429 => AgenticFlowError::RateLimited {
+                retry_after: retry_after.and_then(parse_retry_after),
+                provider_error,
+            },
+            401 | 403 => AgenticFlowError::Unauthorized {
+                status,
+                provider_error,
+            },
+            400 | 413
+                if provider_error
+                    .as_ref()
+                    .is_some_and(ProviderError::is_context_length_exceeded) =>
+            {
+                AgenticFlowError::ContextLengthExceeded { provider_error }
+            }
+            _ => AgenticFlowError::ApiResponseError {
+                status,
+                message: match &provider_error {
+                    Some(error) => error.message.clone(),
+                    None => body.trim().chars().take(MAX_BODY_CHARS).collect(),
+                },
+                provider_error,
+            },
+        }
+    }
+
+    /// A [`AgenticFlowError::ParseError`] for a successful response whose body did not
+    /// parse, quoting the start of the body.
+    pub(crate) fn unparseable_body(error: serde_json::Error, body: &str) -> Self {
+        let start: String = body.trim().chars().take(MAX_BODY_CHARS).collect();
+        AgenticFlowError::ParseError(format!("{} in response body: {}", error, start))
+    }
+
+    /// The error of a successful response whose body is an error object instead of an
+    /// answer, as OpenRouter sends for rate limits and moderation refusals mid-request.
+    /// A `429` code is a [`AgenticFlowError::RateLimited`], so it is retried.
+    pub(crate) fn from_error_body(body: &str) -> Option<Self> {
+        let provider_error = ProviderError::from_body(body)?;
+        if provider_error.code.as_deref() == Some("429") {
+            return Some(AgenticFlowError::RateLimited {
+                retry_after: None,
+                provider_error: Some(provider_error),
+            });
+        }
+        let code = provider_error
+            .code
+            .map(|code| format!(" {}", code))
+            .unwrap_or_default();
+        Some(AgenticFlowError::ApiClientError(format!(
+            "the provider answered with error{}: {}",
+            code, provider_error.message
+        )))
+    }
+}
@@ -0,0 +1,197 @@
+//! A stable JSON form of errors for API boundaries.
+
+use serde::Serialize;
+use serde_json::{Value, json};
+
+use super::{AgenticFlowError, PlanningDiagnostics, ToolOrigin};
+use crate::config::REDACTED;
+
+/// An error as returned over an API: a stable `code` per variant, the display
+/// message, the variant's structured fields and whether a retry may succeed.
+///
+/// Tool params, prompts and the execution context are never included. The raw model
+/// output of a planning failure is, unless its diagnostics are marked redacted.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct WireError {
+    pub code: &'static str,
+    pub message: String,
+    pub details: Value,
+    pub retryable: bool,
+}
+
+impl AgenticFlowError {
+    pub fn to_wire(&self) -> WireError {
+        WireError::from(self)
+    }
+}
+
+impl From<&AgenticFlowError> for WireError {
+    fn from(error: &AgenticFlowError) -> Self {
+        // No wildcard arm: a new variant must be given a code here.
+        let (code, details) = match error {
+            AgenticFlowError::PlanningError(_) => ("planning_error", json!({})),
+            AgenticFlowError::ToolError(_) => ("tool_error", json!({})),
+            AgenticFlowError::ApiClientError(_) => ("api_client_error", json!({})),
+            AgenticFlowError::ParseError(_) => ("parse_error", json!({})),
+            AgenticFlowError::NetworkError(_) => ("network_error", json!({})),
+            AgenticFlowError::ExecutionError(_) => ("execution_error", json!({})),
+            AgenticFlowError::ConfigError(_) => ("config_error", json!({})),
+            AgenticFlowError::ApiResponseError {
+                status,
+                provider_error,
+                ..
+            } => (
+                "api_response_error",
+                json!({ "status": status, "provider_error": provider_error }),
+            ),
+            AgenticFlowError::RateLimited {
+                retry_after,
+                provider_error,
+            } => (
+                "rate_limited",
+                json!({
+                    "retry_after_seconds": retry_after.map(|delay| delay.as_secs_f64()),
+                    "provider_error": provider_error,
+                }),
+            ),
+            AgenticFlowError::Unauthorized {
+                status,
+                provider_error,
+            } => (
+                "unauthorized",
+                json!({ "status": status, "provider_error": provider_error }),
+            ),
+            AgenticFlowError::ContextLengthExceeded { provider_error } => (
+                "context_length_exceeded",
+                json!({ "provider_error": provider_error }),
+            ),
+            AgenticFlowError::OutputTruncated { max_tokens } => {
+                ("output_truncated", json!({ "max_tokens": max_tokens }))
+            }
+            AgenticFlowError::Timeout { operation, limit } => (
+                "timeout",
+                json!({ "operation": operation, "limit_seconds": limit.as_secs_f64() }),
+            ),
+            AgenticFlowError::QueueTimeout { waited } => (
+                "queue_timeout",
+                json!({ "waited_seconds": waited.as_secs_f64() }),
+            ),
+            AgenticFlowError::Cancelled { operation } => {
+                ("cancelled", json!({ "operation": operation }))
+            }
+            AgenticFlowError::ToolNotFound { tool } => ("tool_not_found", json!({ "tool": tool })),
+            AgenticFlowError::ToolExecutionFailed {
+                tool,
+                origin,
+                source,
+            } => (
+                "tool_execution_failed",
+                json!({
+                    "tool": tool,
+                    "server": match origin {
+                        ToolOrigin::Local => None,
+                        ToolOrigin::Mcp { server } => Some(server),
+                    },
+                    "cause": source.to_wire(),
+                }),
+            ),
+            AgenticFlowError::McpServerError {
+                server,
+                tool,
+                source,
+            } => (
+                "mcp_server_error",
+                json!({ "server": server, "tool": tool, "cause": source.to_wire() }),
+            ),
+            AgenticFlowError::StepFailed {
+                step_index,
+                step_id,
+                tool,
+                source,
+            } => (
+                "step_failed",
+                json!({
+                    "step_index": step_index,
+                    "step_id": step_id,
+                    "tool": tool,
+                    "cause": source.to_wire(),
+                }),
+            ),
+            AgenticFlowError::BudgetExceeded(overrun) => (
+                "budget_exceeded",
+                json!({
+                    "max_total_tokens": overrun.budget.max_total_tokens,
+                    "max_cost_usd": overrun.budget.max_cost_usd,
+                    "total_tokens": overrun.totals.usage.map(|usage| usage.total_tokens),
+                    "cost_usd": overrun.totals.cost_usd,
+                    "completed_steps": overrun
+                        .completed
+                        .iter()
+                        .map(|outcome| {
+                            json!({ "step_index": outcome.step_index, "tool": outcome.tool })
+                        })
+                        .collect::<Vec<_>>(),
+                }),
+            ),
+            AgenticFlowError::ExecutionFailed(failure) => (
+                "execution_failed",
+                json!({
+                    "step_index": failure.step_index,
+                    "step_id": failure.step_id,
+                    "tool": failure.tool,
+                    "completed_steps": failure
+                        .completed
+                        .iter()
+                        .map(|outcome| {
+                            json!({ "step_index": outcome.step_index, "tool": outcome.tool })
+                        })
+                        .collect::<Vec<_>>(),
+                    "cause": failure.error.to_wire(),
+                }),
+            ),
+            AgenticFlowError::PlanningFailed {
+                planner,
+                phase,
+                source,
+                diagnostics,
+            } => (
+                "planning_failed",
+                json!({
+                    "planner": planner,
+                    "phase": phase,
+                    "diagnostics": wire_diagnostics(diagnostics),
+                    "cause": source.to_wire(),
+                }),
+            ),
+            AgenticFlowError::RetriesExhausted { attempts, source } => (
+                "retries_exhausted",
+                json!({ "attempts": attempts, "cause": source.to_wire() }),
+            ),
+            AgenticFlowError::Classified { source, .. } => {
+                return WireError {
+                    retryable: error.is_retryable(),
+                    ..source.to_wire()
+                };
+            }
+        };
+
+        WireError {
+            code,
+            message: error.to_string(),
+            details,
+            retryable: error.is_retryable(),
+        }
+    }
+}
+
+fn wire_diagnostics(diagnostics: &PlanningDiagnostics) -> Value {
+    let mut value = json!(diagnostics);
+    if diagnostics.redacted {
+        for key in ["raw_content", "tool_calls"] {
+            if let Some(field) = value.get_mut(key) {
+                *field = json!(REDACTED);
+            }
+        }
+    }
+    value
+}
@@ -0,0 +1,162 @@
+//! The HTTP client shared by the LLM providers: proxy, trusted certificates, timeouts and
+//! connection pooling, and the [`Transport`] requests are sent over.
+
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use reqwest::{Certificate, Client, ClientBuilder, Proxy};
+use serde::{Deserialize, Serialize};
+
+use crate::errors::AgenticFlowError;
+
+/// How HTTP clients are built. The default is a plain [`reqwest::Client`], which already
+/// reads the `HTTPS_PROXY` and `HTTP_PROXY` environment variables.
+///
+/// In the config it is the `[llm_config.http]` table.
+#[derive(Debug, Clone, Default, PartialEq, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct HttpConfig {
+    /// Sends every request through this proxy, e.g. `http://proxy.internal:3128`, in
+    /// place of the one from the environment.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub proxy: Option<String>,
+    /// A PEM file of certificates to trust besides the system ones, such as the CA of a
+    /// corporate proxy.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub ca_bundle_path: Option<PathBuf>,
+    /// Gives up connecting after this many seconds.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub connect_timeout_seconds: Option<u64>,
+    /// The most idle connections kept open per host.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub pool_max_idle: Option<usize>,
+    /// Fails requests whose response body is larger than this many bytes, see
+    /// `LLMClient::with_max_response_bytes`. Defaults to 32 MiB.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_response_bytes: Option<usize>,
+    /// Accepts any TLS certificate, including expired and self-signed ones. Only for
+    /// testing: anyone on the network can then read and change the requests.
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub danger_accept_invalid_certs: bool,
+}
+
+impl HttpConfig {
+    /// Builds the client. Fails with [`AgenticFlowError::ApiClientError`] when the proxy
+    /// URL is malformed or the CA bundle cannot be read.
+    pub fn build_client(&self) -> Result<Client, AgenticFlowError> {
+        build(self.builder()?)
+    }
+
+    /// Builds the client for `transport`; over a unix socket the proxy and connection
+    /// settings do not apply and are ignored.
+    pub fn build_client_for(&self, transport: &Transport) -> Result<Client, AgenticFlowError> {
+        match transport {
+            Transport::Tcp(_) => self.build_client(),
+            #[cfg(unix)]
+            Transport::UnixSocket(path) => build(self.builder()?.unix_socket(path.as_path())),
+        }
+    }
+
+    fn builder(&self) -> Result<ClientBuilder, AgenticFlowError> {
+        let mut builder = Client::builder();
+        if let Some(proxy) = &self.proxy {
+            let proxy = Proxy::all(proxy).map_err(|error| {
+                AgenticFlowError::ApiClientError(format!(
+                    "invalid proxy URL '{}': {}",
+                    proxy, error
+                ))
+            })?;
+            builder = builder.proxy(proxy);
+        }
+        if let Some(path) = &self.ca_bundle_path {
+            for certificate in read_ca_bundle(path)? {
+                builder = builder.add_root_certificate(certificate);
+            }
+        }
+        if let Some(seconds) = self.connect_timeout_seconds {
+            builder = builder.connect_timeout(Duration::from_secs(seconds));
+        }
+        if let Some(max_idle) = self.pool_max_idle {
+            builder = builder.pool_max_idle_per_host(max_idle);
+        }
+        if self.danger_accept_invalid_certs {
+            builder = builder.danger_accept_invalid_certs(true);
+        }
+        Ok(builder)
+    }
+}
+
+fn build(builder: ClientBuilder) -> Result<Client, AgenticFlowError> {
+    builder.build().map_err(|e| {
+        AgenticFlowError::ApiClientError(format!("Failed to build HTTP client: {}", e))
+    })
+}
+
+/// How a provider reaches its server.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Transport {
+    /// A server at a base URL such as `http://localhost:11434`, without a trailing slash.
+    Tcp(String),
+    /// A server bound to a unix domain socket such as `/run/ollama.sock`. Requests are
+    /// sent to `http://localhost`, and the client must be built for the socket with
+    /// [`HttpConfig::build_client_for`].
+    #[cfg(unix)]
+    UnixSocket(PathBuf),
+}
+
+impl Transport {
+    /// The base URL the paths of requests are appended to.
+    pub fn base_url(&self) -> &str {
+        match self {
+            Transport::Tcp(base_url) => base_url,
+            #[cfg(unix)]
+            Transport::UnixSocket(_) => "http://localhost",
+        }
+    }
+
+    /// The URL of `path` on the server, e.g. `api/chat`.
+    pub fn url(&self, path: &str) -> String {
+        format!("{}/{}", self.base_url(), path)
+    }
+
+    /// The error of a request that could not be sent. Failing to connect to a unix
+    /// socket is a [`AgenticFlowError::NetworkError`] naming the socket and the cause,
+    /// such as the socket not existing.
+    pub(crate) fn send_error(&self, error: reqwest::Error) -> AgenticFlowError {
+        match self {
+            #[cfg(unix)]
+            Transport::UnixSocket(path) if error.is_connect() => {
+                let mut cause: &dyn std::error::Error = &error;
+                while let Some(source) = cause.source() {
+                    cause = source;
+                }
+                AgenticFlowError::NetworkError(format!(
+                    "cannot connect to the unix socket '{}': {}",
+                    path.display(),
+                    cause
+                ))
+            }
+            _ => error.into(),
+        }
+    }
+}
+
+fn read_ca_bundle(path: &Path) -> Result<Vec<Certificate>, AgenticFlowError> {
+    let invalid = |reason: String| {
+        AgenticFlowError::ApiClientError(format!(
+            "cannot read CA bundle '{}': {}",
+            path.display(),
+            reason
+        ))
+    };
+    let pem = fs::read(path).map_err(|error| invalid(error.to_string()))?;
+    let certificates =
+        Certificate::from_pem_bundle(&pem).map_err(|error| invalid(error.to_string()))?;
+    if certificates.is_empty() {
+        return Err(invalid("no PEM certificates in the file".to_string()));
+    }
+    Ok(certificates)
+}
@@ -0,0 +1,131 @@
+//! Repairing the almost-valid JSON small models write, such as tool call arguments with a
+//! trailing comma or single quotes.
+
+use std::{iter::Peekable, str::Chars};
+
+use serde_json::Value;
+
+use crate::errors::AgenticFlowError;
+
+/// JSON read by [`parse_lenient`], and whether it had to be repaired first.
+#[derive(Debug, Clone, PartialEq)]
+pub struct LenientJson {
+    pub value: Value,
+    pub repaired: bool,
+}
+
+/// Parses `text` as JSON, repairing it with [`repair_json`] when it is not valid. Fails
+/// with [`AgenticFlowError::ParseError`] quoting `text` and the attempted repair when
+/// the repaired text is not valid either.
+pub fn parse_lenient(text: &str) -> Result<LenientJson, AgenticFlowError> {
+    if let Ok(value) = serde_json::from_str(text) {
+        return Ok(LenientJson {
+            value,
+            repaired: false,
+        });
+    }
+    let repaired = repair_json(text);
+    serde_json::from_str(&repaired)
+        .map(|value| LenientJson {
+            value,
+            repaired: true,
+        })
+        .map_err(|error| {
+            AgenticFlowError::ParseError(format!(
+                "{} in JSON {:?}, attempted repair {:?}",
+                error, text, repaired
+            ))
+        })
+}
+
+/// `text` with the defects models commonly leave in JSON fixed: text before the first
+/// object or array and after its end is dropped, single-quoted strings and unquoted keys
+/// get double quotes, trailing commas are removed, Python's `True`, `False` and `None`
+/// become JSON literals, and unclosed strings, objects and arrays are closed. Other
+/// defects are left as they are, so the result may still not be valid JSON.
+pub fn repair_json(text: &str) -> String {
+    let Some(start) = text.find(['{', '[']) else {
+        return text.trim().to_string();
+    };
+    let mut repaired = String::with_capacity(text.len());
+    let mut closers: Vec<char> = Vec::new();
+    let mut chars = text[start..].chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '"' | '\'' => push_string(&mut repaired, &mut chars, c),
+            '{' => {
+                closers.push('}');
+                repaired.push(c);
+            }
+            '[' => {
+                closers.push(']');
+                repaired.push(c);
+            }
+            '}' | ']' => {
+                trim_trailing_comma(&mut repaired);
+                if closers.last() == Some(&c) {
+                    closers.pop();
+                }
+                repaired.push(c);
+                if closers.is_empty() {
+                    break;
+                }
+            }
+            c if c.is_alphabetic() || c == '_' || c == '$' => {
+                let mut word = c.to_string();
+                while let Some(next) =
+                    chars.next_if(|next| next.is_alphanumeric() || *next == '_' || *next == '$')
+                {
+                    word.push(next);
+                }
+                let is_key = chars.clone().find(|next| !next.is_whitespace()) == Some(':');
+                match word.as_str() {
+                    _ if is_key => {
+                        repaired.push('"');
+                        repaired.push_str(&word);
+                        repaired.push('"');
+                    }
+                    "True" => repaired.push_str("true"),
+                    "False" => repaired.push_str("false"),
+                    "None" => repaired.push_str("null"),
+                    _ => repaired.push_str(&word),
+                }
+            }
+            c => repaired.push(c),
+        }
+    }
+    while let Some(closer) = closers.pop() {
+        trim_trailing_comma(&mut repaired);
+        repaired.push(closer);
+    }
+    repaired
+}
+
+/// Copies the string that `quote` opened as a double-quoted one, escaping the double
+/// quotes inside a single-quoted string and closing it if the text ends first.
+fn push_string(repaired: &mut String, chars: &mut Peekable<Chars<'_>>, quote: char) {
+    repaired.push('"');
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => match chars.next() {
+                Some('\'') => repaired.push('\''),
+                Some(escaped) => {
+                    repaired.push('\\');
+                    repaired.push(escaped);
+                }
+                None => {}
+            },
+            c if c == quote => break,
+            '"' => repaired.push_str("\\\""),
+            c => repaired.push(c),
+        }
+    }
+    repaired.push('"');
+}
+
+fn trim_trailing_comma(repaired: &mut String) {
+    let end = repaired.trim_end().len();
+    if repaired[..end].ends_with(',') {
+        repaired.truncate(end - 1);
+    }
+}
@@ -1,70 +1,194 @@
 pub mod agent;
 pub mod config;
 pub mod errors;
+pub mod http;
+pub mod json_repair;
 pub mod llm_client;
 pub mod mcp_manager;
 pub mod model;
+pub mod observer;
 pub mod planner;
+pub mod prompt;
+pub mod reload;
+pub mod secrets;
+pub mod tokens;
 pub mod tool_registry;
 pub mod worker;
 
-use std::sync::Arc;
+use std::sync::{
+    Arc, Mutex as StdMutex, RwLock,
+    atomic::{AtomicU64, Ordering},
+};
 use tokio::sync::Mutex;
 
 use agent::Agent;
 use errors::AgenticFlowError;
-use llm_client::LLMClient;
+use llm_client::{Budget, LLMClient, LLMRouter, RunTotals, UsageReport};
 use mcp_manager::MCPManager;
+use model::{ProviderStats, Usage};
 use tool_registry::ToolRegistry;
 
 use crate::{
     config::SystemConfig,
-    planner::{Executor, MultiStepPlanner, Planner},
+    observer::{ErrorContext, ErrorObserver},
+    planner::{Executor, Planner, PlannerConfig},
+    secrets::{CachedSecretResolver, SecretResolver},
     tool_registry::LocalTool,
 };
 
 pub struct AgenticSystem {
     manager: Arc<Mutex<MCPManager>>,
-    agent: Box<dyn Executor>,
     tool_registry: Arc<Mutex<ToolRegistry>>,
+    runtime: RwLock<Arc<Runtime>>,
+    /// Set when the client was built from `llm_config`, so a reload may rebuild it.
+    llm_client_from_config: bool,
+    secret_resolver: Arc<dyn SecretResolver>,
+    error_observer: Option<Arc<dyn ErrorObserver>>,
+    /// Numbers the `plan_and_execute` calls for [`ErrorContext::run_id`].
+    runs: AtomicU64,
+    last_run: StdMutex<RunTotals>,
+}
+
+/// The settings of a single [`AgenticSystem::plan_and_execute_with`] run.
+#[derive(Debug, Clone, Default)]
+pub struct RunOptions {
+    /// Aborts the run with [`AgenticFlowError::BudgetExceeded`] once its planner and
+    /// agent calls have spent it; the steps executed by then are in the error's
+    /// [`BudgetOverrun`](errors::BudgetOverrun).
+    pub budget: Option<Budget>,
+}
+
+/// The config-dependent half of the system. Runs take a snapshot when they start,
+/// so a reload only affects runs started after it.
+struct Runtime {
+    config: SystemConfig,
+    llm: LLMRouter,
+    agent: Box<dyn Executor>,
     planner: Box<dyn Planner>,
 }
 
+impl Runtime {
+    fn new(
+        config: SystemConfig,
+        llm: LLMRouter,
+        manager: &Arc<Mutex<MCPManager>>,
+        tool_registry: &Arc<Mutex<ToolRegistry>>,
+        error_observer: &Option<Arc<dyn ErrorObserver>>,
+    ) -> Self {
+        let llm = match error_observer {
+            Some(observer) => {
+                llm.map_clients(|client| client.with_error_observer(observer.clone()))
+            }
+            None => llm,
+        };
+        let mut agent = Agent::new(manager.clone(), tool_registry.clone(), llm.clone())
+            .with_config(config.resolved_agent_config());
+        if let Some(observer) = error_observer {
+            agent = agent.with_error_observer(observer.clone());
+        }
+        let agent = Box::new(agent);
+
+        let planner = config.planner.build(llm.clone(), tool_registry.clone());
+
+        Self {
+            config,
+            llm,
+            agent,
+            planner,
+        }
+    }
+}
+
 impl AgenticSystem {
+    pub fn builder() -> AgenticSystemBuilder {
+        AgenticSystemBuilder::default()
+    }
+
+    /// Builds the system with `llm`: an [`LLMClient`] for every call, or an [`LLMRouter`]
+    /// with a client for each [`Purpose`](llm_client::Purpose).
     pub async fn new(
         config: SystemConfig,
         tools: Vec<Box<dyn LocalTool>>,
-        llm_client: LLMClient,
+        llm: impl Into<LLMRouter>,
     ) -> Result<Self, AgenticFlowError> {
-        let manager = Self::initialize_mcp_manager(&config).await?;
-        let tool_registry = Self::initialize_tool_registry(tools, &manager).await?;
+        Self::assemble(
+            config,
+            tools,
+            Some(llm.into()),
+            default_secret_resolver(),
+            None,
+        )
+        .await
+    }
 
-        let agent = Box::new(Agent::new(
-            manager.clone(),
-            tool_registry.clone(),
-            llm_client.clone(),
-        ));
+    /// Builds the system with an [`LLMClient`] constructed from `config.llm_config`.
+    pub async fn from_config(
+        config: SystemConfig,
+        tools: Vec<Box<dyn LocalTool>>,
+    ) -> Result<Self, AgenticFlowError> {
+        Self::from_config_with_resolver(config, tools, default_secret_resolver()).await
+    }
 
-        let planner = Box::new(MultiStepPlanner::new(
-            llm_client.clone(),
-            tool_registry.clone(),
-        ));
+    /// Like [`AgenticSystem::from_config`], resolving the `api_key` and server `secrets`
+    /// refs with a custom resolver instead of the default env/file one.
+    pub async fn from_config_with_resolver(
+        config: SystemConfig,
+        tools: Vec<Box<dyn LocalTool>>,
+        secret_resolver: Arc<dyn SecretResolver>,
+    ) -> Result<Self, AgenticFlowError> {
+        Self::assemble(config, tools, None, secret_resolver, None).await
+    }
+
+    async fn assemble(
+        config: SystemConfig,
+        tools: Vec<Box<dyn LocalTool>>,
+        llm: Option<LLMRouter>,
+        secret_resolver: Arc<dyn SecretResolver>,
+        error_observer: Option<Arc<dyn ErrorObserver>>,
+    ) -> Result<Self, AgenticFlowError> {
+        config.validate()?;
+        warn_ignored_settings(&config);
+
+        let llm_client_from_config = llm.is_none();
+        let llm = match llm {
+            Some(llm) => llm,
+            None => {
+                LLMClient::from_config_with_resolver(&config.llm_config, &*secret_resolver)?.into()
+            }
+        };
+        if config.llm_config.verify_on_startup {
+            llm.verify().await?;
+        }
+
+        let manager =
+            Self::initialize_mcp_manager(&config, &*secret_resolver, &error_observer).await?;
+        let tool_registry = Self::initialize_tool_registry(tools, &manager).await?;
+        let runtime = Runtime::new(config, llm, &manager, &tool_registry, &error_observer);
 
         Ok(Self {
             manager,
-            agent,
             tool_registry,
-            planner,
+            runtime: RwLock::new(Arc::new(runtime)),
+            llm_client_from_config,
+            secret_resolver,
+            error_observer,
+            runs: AtomicU64::new(0),
+            last_run: StdMutex::default(),
         })
     }
 
     async fn initialize_mcp_manager(
         config: &SystemConfig,
+        secret_resolver: &dyn SecretResolver,
+        error_observer: &Option<Arc<dyn ErrorObserver>>,
     ) -> Result<Arc<Mutex<MCPManager>>, AgenticFlowError> {
-        let mut manager = MCPManager::new(config.mcp_config.clone());
+        let mcp_config = secrets::resolve_mcp_config(&config.mcp_config, secret_resolver)?;
+        let mut manager = MCPManager::new(mcp_config);
 
         for server_name in config.mcp_config.servers.keys() {
-            manager.start_server(server_name).await?;
+            manager.start_server(server_name).await.inspect_err(|e| {
+                observer::report(error_observer, e, ErrorContext::new("mcp", e))
+            })?;
         }
 
         Ok(Arc::new(Mutex::new(manager)))
@@ -91,8 +215,84 @@ impl AgenticSystem {
 
     /// Plans and executes a complex task
     pub async fn plan_and_execute(&self, task: &str) -> Result<String, AgenticFlowError> {
-        let steps = self.planner.plan(task).await?;
-        self.agent.execute(steps).await
+        self.plan_and_execute_with(task, RunOptions::default())
+            .await
+    }
+
+    /// Plans and executes a complex task with `options`, such as a budget:
+    ///
+    /// ```rust,no_run
+    /// # use agentic_flow_lib::{AgenticSystem, RunOptions, llm_client::Budget};
+    /// # async fn example(system: &AgenticSystem) {
+    /// let options = RunOptions {
+    ///     budget: Some(Budget::tokens(20_000)),
+    /// };
+    /// match system.plan_and_execute_with("Summarize the open issues", options).await {
+    ///     Ok(answer) => println!("{}", answer),
+    ///     Err(error) => match error.budget_overrun() {
+    ///         Some(overrun) => println!("{} after {} steps", error, overrun.completed.len()),
+    ///         None => println!("{}", error),
+    ///     },
+    /// }
+    /// # }
+    /// ```
+    ///
+    /// A spent budget fails the run with [`AgenticFlowError::BudgetExceeded`], even when
+    /// a planner or a step reported it wrapped in their own error.
+    pub async fn plan_and_execute_with(
+        &self,
+        task: &str,
+        options: RunOptions,
+    ) -> Result<String, AgenticFlowError> {
+        let runtime = self.runtime();
+        let run_id = self.runs.fetch_add(1, Ordering::Relaxed) + 1;
+        let run = observer::in_run(run_id.to_string(), async {
+            if let Some(planner) = runtime.planner.as_iterative() {
+                return runtime.agent.execute_iteratively(task, planner).await;
+            }
+            let steps = runtime.planner.plan(task).await?;
+            runtime.agent.execute(steps).await
+        });
+        let (result, totals) = match options.budget {
+            Some(budget) => llm_client::summing_run_within(budget, run).await,
+            None => llm_client::summing_run(run).await,
+        };
+        *self
+            .last_run
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner()) = totals;
+        result.map_err(AgenticFlowError::surfacing_budget)
+    }
+
+    /// The calls, tokens and cost of the system's LLM clients so far, by model. A reload
+    /// that rebuilds the client keeps adding to the same report.
+    pub fn usage_report(&self) -> UsageReport {
+        self.runtime().llm.usage_report()
+    }
+
+    /// The tokens spent by the planner and agent calls of the last `plan_and_execute`
+    /// to finish, successful or not. `None` when no response reported its usage.
+    pub fn last_run_usage(&self) -> Option<Usage> {
+        self.last_run_totals().usage
+    }
+
+    /// The provider timings of the planner and agent calls of the last `plan_and_execute`
+    /// to finish, summed. `None` when no response reported any; Ollama and llama.cpp do.
+    pub fn last_run_stats(&self) -> Option<ProviderStats> {
+        self.last_run_totals().stats
+    }
+
+    /// The responses, tokens and timings of the last `plan_and_execute` to finish.
+    pub fn last_run_totals(&self) -> RunTotals {
+        *self
+            .last_run
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    /// Returns the config currently in effect.
+    pub fn config(&self) -> SystemConfig {
+        self.runtime().config.clone()
     }
 
     /// Returns available tools
@@ -100,6 +300,20 @@ impl AgenticSystem {
         self.tool_registry.lock().await.get_tools_names()
     }
 
+    /// Returns the names of the running MCP servers, sorted
+    pub async fn get_active_servers(&self) -> Vec<String> {
+        let mut servers = self.manager.lock().await.get_active_server_names();
+        servers.sort();
+        servers
+    }
+
+    fn runtime(&self) -> Arc<Runtime> {
+        self.runtime
+            .read()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .clone()
+    }
+
     /// Gracefully shuts down the system
     pub async fn shutdown(self) -> Result<(), AgenticFlowError> {
         let mut manager = self.manager.lock().await;
@@ -109,3 +323,82 @@ impl AgenticSystem {
         Ok(())
     }
 }
+
+fn default_secret_resolver() -> Arc<dyn SecretResolver> {
+    Arc::new(CachedSecretResolver::default())
+}
+
+fn warn_ignored_settings(config: &SystemConfig) {
+    for warning in config.warnings().violations {
+        println!("WARNING: Config '{}': {}", warning.path, warning.message);
+    }
+}
+
+/// Assembles an [`AgenticSystem`] from a config, local tools and optional overrides.
+#[derive(Default)]
+pub struct AgenticSystemBuilder {
+    config: SystemConfig,
+    tools: Vec<Box<dyn LocalTool>>,
+    llm: Option<LLMRouter>,
+    planner: Option<PlannerConfig>,
+    secret_resolver: Option<Arc<dyn SecretResolver>>,
+    error_observer: Option<Arc<dyn ErrorObserver>>,
+}
+
+impl AgenticSystemBuilder {
+    pub fn config(mut self, config: SystemConfig) -> Self {
+        self.config = config;
+        self
+    }
+
+    pub fn tool<T: LocalTool + 'static>(mut self, tool: T) -> Self {
+        self.tools.push(Box::new(tool));
+        self
+    }
+
+    pub fn tools(mut self, tools: Vec<Box<dyn LocalTool>>) -> Self {
+        self.tools.extend(tools);
+        self
+    }
+
+    /// Uses a custom client, or an [`LLMRouter`], instead of one built from `llm_config`.
+    pub fn llm_client(mut self, llm: impl Into<LLMRouter>) -> Self {
+        self.llm = Some(llm.into());
+        self
+    }
+
+    /// Overrides the planner selected in the config.
+    pub fn planner(mut self, planner: impl Into<PlannerConfig>) -> Self {
+        self.planner = Some(planner.into());
+        self
+    }
+
+    /// Resolves secret refs in the config with a custom resolver.
+    pub fn secret_resolver(mut self, secret_resolver: Arc<dyn SecretResolver>) -> Self {
+        self.secret_resolver = Some(secret_resolver);
+        self
+    }
+
+    /// Reports the errors listed in [`observer`] to `observer`.
+    pub fn error_observer(mut self, observer: Arc<dyn ErrorObserver>) -> Self {
+        self.error_observer = Some(observer);
+        self
+    }
+
+    pub async fn build(self) -> Result<AgenticSystem, AgenticFlowError> {
+        let mut config = self.config;
+        if let Some(planner) = self.planner {
+            config.planner = planner;
+        }
+
+        let secret_resolver = self.secret_resolver.unwrap_or_else(default_secret_resolver);
+        AgenticSystem::assemble(
+            config,
+            self.tools,
+            self.llm,
+            secret_resolver,
+            self.error_observer,
+        )
+        .await
+    }
+}
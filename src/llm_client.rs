@@ -1,10 +1,91 @@
-use std::sync::Arc;
+mod anthropic;
+mod body;
+mod builder;
+mod cache;
+mod capabilities;
+mod cost;
+mod dialect;
+mod embeddings;
+mod fallback;
+mod gemini;
+mod groq;
+mod headers;
+mod layer;
+mod llama_cpp;
+#[cfg(feature = "test-utils")]
+mod mock;
+mod models;
+mod openai;
+mod options;
+#[cfg(feature = "test-utils")]
+mod recording;
+mod request_log;
+mod retry;
+mod router;
+mod throttle;
+mod tool_emulation;
+mod typed;
+mod usage;
+
+use std::{
+    fmt,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
 
 use async_trait::async_trait;
+use futures::stream::{self, StreamExt};
 use reqwest::{Client as HttpClient, Response};
+use serde::de::DeserializeOwned;
 use serde_json::{Value, json};
 
-use crate::{errors::AgenticFlowError, model::*};
+pub use anthropic::AnthropicModel;
+pub use builder::LLMClientBuilder;
+pub use cache::{CacheConfig, CacheLayer, CacheStats};
+pub use capabilities::ModelCapabilities;
+pub use cost::{CostTracker, ModelPrice, ModelUsage, PriceTable, UsageReport};
+pub use gemini::GeminiModel;
+pub use groq::GroqModel;
+pub use llama_cpp::LlamaCppProvider;
+#[cfg(feature = "test-utils")]
+pub use mock::{ChatCallLog, MockLLMProvider, RecordedCall};
+pub use models::PullProgress;
+pub use openai::OpenAIModel;
+pub use tokio_util::sync::CancellationToken;
+
+pub use body::DEFAULT_MAX_RESPONSE_BYTES;
+pub use layer::{LLMLayer, LLMRequest, LLMResponse, Next};
+pub use options::{
+    GenerationSettings, OllamaOptions, OutputFormat, RequestOptions, SamplingOptions, ToolChoice,
+};
+#[cfg(feature = "test-utils")]
+pub use recording::{RECORD_ENV_VAR, RecordingProvider};
+pub use request_log::{LLMRequestLog, LoggingLayer, ResponseSummary};
+pub use retry::{RetryLayer, RetryPolicy};
+pub use router::{LLMRouter, Purpose};
+pub use tool_emulation::{ToolEmulation, ToolEmulationLayer};
+
+pub use usage::{Budget, RunTotals, summing_run, summing_run_within};
+
+use anthropic::AnthropicProvider;
+use capabilities::OllamaShow;
+use embeddings::{OllamaEmbeddings, OpenAIEmbeddings};
+use fallback::{FallbackResponse, falls_through};
+use gemini::GeminiProvider;
+use groq::GroqProvider;
+use headers::ExtraHeaders;
+use models::OllamaTags;
+use openai::OpenAIProvider;
+use throttle::Throttle;
+
+use crate::{
+    config::{LLMConfig, ProviderKind},
+    errors::{AgenticFlowError, HTTP_REQUEST, with_timeout},
+    http::{HttpConfig, Transport},
+    model::*,
+    observer::{self, ErrorContext, ErrorObserver},
+    secrets::{DefaultSecretResolver, SecretResolver, SecretString, resolve_for},
+};
 
 #[derive(Debug, Clone)]
 pub enum OllamaModel {
@@ -15,15 +96,15 @@ pub enum OllamaModel {
     Custom(String),
 }
 
-impl OllamaModel {
-    pub fn to_string(&self) -> String {
-        match self {
-            OllamaModel::GPToss => "gpt-oss:20b".to_string(),
-            OllamaModel::Gemma2_2b => "gemma2:2b".to_string(),
-            OllamaModel::Gemma3_4b => "gemma3:4b".to_string(),
-            OllamaModel::Qwen3_8B => "qwen3:8b".to_string(),
-            OllamaModel::Custom(name) => name.clone(),
-        }
+impl fmt::Display for OllamaModel {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            OllamaModel::GPToss => "gpt-oss:20b",
+            OllamaModel::Gemma2_2b => "gemma2:2b",
+            OllamaModel::Gemma3_4b => "gemma3:4b",
+            OllamaModel::Qwen3_8B => "qwen3:8b",
+            OllamaModel::Custom(name) => name,
+        })
     }
 }
 
@@ -34,85 +115,332 @@ pub enum OpenRouterModel {
     Custom(String),
 }
 
-impl OpenRouterModel {
-    pub fn to_string(&self) -> String{
-        match self {
-            OpenRouterModel::Flash2 => "google/gemini-2.0-flash-001".to_string(),
-            OpenRouterModel::GPTMini => "openai/gpt-4o-mini".to_string(),
-            OpenRouterModel::Custom(name) => name.clone(),
-        }
+impl fmt::Display for OpenRouterModel {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            OpenRouterModel::Flash2 => "google/gemini-2.0-flash-001",
+            OpenRouterModel::GPTMini => "openai/gpt-4o-mini",
+            OpenRouterModel::Custom(name) => name,
+        })
+    }
+}
+
+/// Parses a successful response body, keeping the JSON for [`ChatResponse::raw`].
+fn parse_body<T: RawResponse>(body: &str) -> Result<T, AgenticFlowError> {
+    T::from_body(body).map_err(|error| AgenticFlowError::unparseable_body(error, body))
+}
+
+/// Parses the body of an OpenAI-style chat completion, which may be an error object or
+/// hold no choices even though the request succeeded.
+fn parse_chat_body(body: &str) -> Result<OpenRouterResponse, AgenticFlowError> {
+    if let Some(error) = AgenticFlowError::from_error_body(body) {
+        return Err(error);
     }
+    let response = parse_body::<OpenRouterResponse>(body)?;
+    if !response.has_choices() {
+        return Err(AgenticFlowError::ApiClientError(
+            "the provider answered without any choices".to_string(),
+        ));
+    }
+    Ok(response)
 }
 
 #[async_trait]
 pub trait LLMProvider: Send + Sync {
     fn http_client(&self) -> &reqwest::Client;
 
+    /// The URL the paths of requests are appended to, without a trailing slash. For a
+    /// provider reached through a unix socket, only the host of the requests.
     fn base_url(&self) -> &str;
 
-    fn api_key(&self) -> Option<String> {
+    /// How requests reach the server: by default [`Transport::Tcp`] to the
+    /// [`base_url`](Self::base_url). Providers that return a
+    /// [`Transport::UnixSocket`] must have an [`http_client`](Self::http_client) built for
+    /// it.
+    fn transport(&self) -> Transport {
+        Transport::Tcp(self.base_url().to_string())
+    }
+
+    /// The API key requests are sent with: `None` for providers that need none, and an
+    /// error for those that need one and have none.
+    fn api_key(&self) -> Result<Option<String>, AgenticFlowError> {
+        Ok(None)
+    }
+
+    /// The model requests are sent to, when the provider has a fixed one.
+    fn model_name(&self) -> Option<&str> {
         None
     }
 
+    /// Whether chat requests can ask for an [`OutputFormat`] other than
+    /// [`OutputFormat::None`].
+    fn supports_output_format(&self) -> bool {
+        true
+    }
+
+    /// Headers sent with every request: by default the [`api_key`](Self::api_key), if
+    /// any, as a bearer token.
+    fn request_headers(&self) -> Result<Vec<(&'static str, String)>, AgenticFlowError> {
+        Ok(self
+            .api_key()?
+            .map(|key| ("Authorization", format!("Bearer {}", key)))
+            .into_iter()
+            .collect())
+    }
+
     async fn completion(
         &self,
         prompt: String,
-        temperature: f32,
+        settings: &GenerationSettings,
     ) -> Result<Box<dyn CompletionResponse>, AgenticFlowError>;
 
     async fn chat_completions(
         &self,
         messages: Vec<ChatMessage>,
-        temperature: f32,
+        settings: &GenerationSettings,
         tools: Vec<Value>,
     ) -> Result<Box<dyn ChatResponse>, AgenticFlowError>;
 
+    /// Embeds each of `inputs` as a vector, in order. By default the provider has no
+    /// embeddings and fails with [`AgenticFlowError::ApiClientError`].
+    async fn embed(&self, _inputs: Vec<String>) -> Result<Vec<Vec<f32>>, AgenticFlowError> {
+        Err(AgenticFlowError::ApiClientError(
+            "embeddings not supported".to_string(),
+        ))
+    }
+
+    /// The most inputs [`LLMClient::embed`] sends to [`embed`](Self::embed) at once.
+    fn embedding_batch_size(&self) -> usize {
+        embeddings::OPENAI_BATCH_SIZE
+    }
+
+    /// Whether [`available_models`](Self::available_models) can list the models of the
+    /// server.
+    fn lists_models(&self) -> bool {
+        false
+    }
+
+    /// The models the server has. By default the provider cannot list them and fails
+    /// with [`AgenticFlowError::ApiClientError`].
+    async fn available_models(&self) -> Result<Vec<String>, AgenticFlowError> {
+        Err(AgenticFlowError::ApiClientError(
+            "listing models not supported".to_string(),
+        ))
+    }
+
+    /// `known` updated with what the server reports of the model requests are sent to.
+    /// By default the provider cannot describe models and fails with
+    /// [`AgenticFlowError::ApiClientError`].
+    async fn model_capabilities(
+        &self,
+        _known: ModelCapabilities,
+    ) -> Result<ModelCapabilities, AgenticFlowError> {
+        Err(AgenticFlowError::ApiClientError(
+            "describing models not supported".to_string(),
+        ))
+    }
+
+    /// Downloads the model requests are sent to onto the server, passing each line of
+    /// progress to `progress`. By default the provider cannot and fails with
+    /// [`AgenticFlowError::ApiClientError`].
+    async fn pull_model(
+        &self,
+        _progress: &(dyn for<'a> Fn(&'a PullProgress) + Send + Sync),
+    ) -> Result<(), AgenticFlowError> {
+        Err(AgenticFlowError::ApiClientError(
+            "pulling models not supported".to_string(),
+        ))
+    }
+
     async fn send_request(
         &self,
         request: Value,
         endpoint: &str,
     ) -> Result<Response, AgenticFlowError> {
-        let url = format!("{}/{}", self.base_url(), endpoint);
-        let response = self
-            .http_client()
-            .post(&url)
-            .header(
-                "Authorization",
-                format!("Bearer {}", self.api_key().unwrap_or_default()),
-            )
+        let transport = self.transport();
+        let url = transport.url(endpoint);
+        let mut builder = self.http_client().post(&url);
+        let mut headers: Vec<(String, String)> = self
+            .request_headers()?
+            .into_iter()
+            .map(|(name, value)| (name.to_string(), value))
+            .collect();
+        headers.extend(headers::extra().iter().cloned());
+        request_log::record_request(&url, &headers, &request);
+        for (name, value) in headers {
+            builder = builder.header(name, value);
+        }
+        let response = builder
             .json(&request)
             .send()
             .await
-            .map_err(|e| {
-                AgenticFlowError::NetworkError(format!("Failed to send request: {}", e))
-            })?;
+            .map_err(|error| transport.send_error(error))?;
+        request_log::record_status(response.status().as_u16());
 
         if response.status().is_success() {
-            Ok(response)
-        } else {
-            Err(AgenticFlowError::ApiClientError(format!(
-                "API request failed with status: {} {}",
-                response.status(),
-                response.text().await.unwrap_or_default()
-            )))
+            return Ok(response);
         }
+
+        let status = response.status().as_u16();
+        let retry_after = response
+            .headers()
+            .get(reqwest::header::RETRY_AFTER)
+            .and_then(|value| value.to_str().ok())
+            .map(str::to_string);
+        let body = body::read(response).await.unwrap_or_default();
+        Err(AgenticFlowError::from_http_response(
+            status,
+            retry_after.as_deref(),
+            &body,
+        ))
+    }
+}
+
+const DEFAULT_OLLAMA_URL: &str = "http://localhost:11434";
+
+/// Normalizes an Ollama address the way the ollama CLI reads `OLLAMA_HOST`: without a
+/// scheme the address is `http` on port 11434, so `myhost` and `myhost:8080` work too.
+pub(crate) fn ollama_base_url(address: &str) -> Result<String, AgenticFlowError> {
+    let address = address.trim();
+    let invalid = |reason: String| {
+        AgenticFlowError::ApiClientError(format!(
+            "Invalid Ollama base URL '{}': {}",
+            address, reason
+        ))
+    };
+
+    let has_scheme = address.contains("://");
+    let url = if has_scheme {
+        address.to_string()
+    } else {
+        format!("http://{}", address.trim_end_matches('/'))
+    };
+    let mut url = reqwest::Url::parse(&url).map_err(|e| invalid(e.to_string()))?;
+    if !matches!(url.scheme(), "http" | "https") {
+        return Err(invalid(format!("unsupported scheme '{}'", url.scheme())));
+    }
+    if !has_scheme && url.port().is_none() {
+        // Cannot fail: the URL has a host.
+        let _ = url.set_port(Some(11434));
+    }
+    Ok(url.as_str().trim_end_matches('/').to_string())
+}
+
+/// `OLLAMA_HOST` when it is set and valid, otherwise `http://localhost:11434`.
+fn default_ollama_url() -> String {
+    let Some(host) = std::env::var("OLLAMA_HOST")
+        .ok()
+        .filter(|host| !host.trim().is_empty())
+    else {
+        return DEFAULT_OLLAMA_URL.to_string();
+    };
+    ollama_base_url(&host).unwrap_or_else(|error| {
+        println!("WARNING: ignoring OLLAMA_HOST: {}", error);
+        DEFAULT_OLLAMA_URL.to_string()
+    })
+}
+
+/// Moves the settings Ollama reads from `options` there: the temperature, the token limit
+/// as `num_predict`, the stop sequences and the sampling options. The output format and
+/// `think` go next to `options`.
+fn with_ollama_options(request: &mut Value, settings: &GenerationSettings) {
+    let Some(object) = request.as_object_mut() else {
+        return;
+    };
+    object.remove("max_tokens");
+    object.remove("temperature");
+    object.remove("response_format");
+    let stop = object.remove("stop");
+    if let Some(format) = settings.format.ollama_format() {
+        object.insert("format".to_string(), format);
+    }
+    if let Some(think) = settings.thinking {
+        object.insert("think".to_string(), json!(think));
+    }
+
+    let mut options = settings.sampling.wire_fields(dialect::OLLAMA_SAMPLING);
+    options.insert("temperature".to_string(), json!(settings.temperature));
+    if let Some(max_tokens) = settings.max_tokens {
+        options.insert("num_predict".to_string(), json!(max_tokens));
+    }
+    if let Some(stop) = stop {
+        options.insert("stop".to_string(), stop);
     }
+    object.insert("options".to_string(), Value::Object(options));
 }
 
-struct OllamaProvider {
+/// A provider for an Ollama server, for settings beyond those of
+/// [`LLMClient::from_ollama`], such as a separate embedding model or a larger context:
+///
+/// ```rust,no_run
+/// # use agentic_flow_lib::llm_client::{LLMClient, OllamaModel, OllamaOptions, OllamaProvider};
+/// let client = LLMClient::from(
+///     OllamaProvider::new(OllamaModel::Qwen3_8B)
+///         .with_embedding_model(OllamaModel::Custom("nomic-embed-text".to_string()))
+///         .with_options(OllamaOptions {
+///             num_ctx: Some(16384),
+///             ..OllamaOptions::default()
+///         }),
+/// );
+/// ```
+pub struct OllamaProvider {
     client: HttpClient,
-    base_url: String,
+    transport: Transport,
     model: String,
+    embedding_model: Option<String>,
+    options: OllamaOptions,
 }
 
 impl OllamaProvider {
     pub fn new(model: OllamaModel) -> Self {
+        Self::with_client(HttpClient::new(), model, default_ollama_url())
+    }
+
+    pub fn with_client(client: HttpClient, model: OllamaModel, base_url: String) -> Self {
         Self {
-            base_url: "http://localhost:11434".to_string(),
-            client: HttpClient::new(),
+            transport: Transport::Tcp(base_url),
+            client,
             model: model.to_string(),
+            embedding_model: None,
+            options: OllamaOptions::default(),
         }
     }
+
+    /// A provider for an Ollama server reached through the unix socket at `path`, such
+    /// as one mounted into a container. Fails with [`AgenticFlowError::ApiClientError`]
+    /// when the client cannot be built; a missing socket fails each request with a
+    /// [`AgenticFlowError::NetworkError`] naming it.
+    #[cfg(unix)]
+    pub fn with_socket(
+        path: impl Into<std::path::PathBuf>,
+        model: OllamaModel,
+    ) -> Result<Self, AgenticFlowError> {
+        let transport = Transport::UnixSocket(path.into());
+        let client = HttpConfig::default().build_client_for(&transport)?;
+        Ok(Self {
+            transport,
+            ..Self::with_client(client, model, String::new())
+        })
+    }
+
+    /// Embeds with `model` in place of the chat model.
+    pub fn with_embedding_model(mut self, model: OllamaModel) -> Self {
+        self.embedding_model = Some(model.to_string());
+        self
+    }
+
+    /// Sends `options` with every chat and completion request.
+    pub fn with_options(mut self, options: OllamaOptions) -> Self {
+        self.options = options;
+        self
+    }
+
+    /// Sends requests with a client built from `http`. Fails with
+    /// [`AgenticFlowError::ApiClientError`] when `http` is invalid.
+    pub fn with_http(mut self, http: &HttpConfig) -> Result<Self, AgenticFlowError> {
+        self.client = http.build_client_for(&self.transport)?;
+        Ok(self)
+    }
 }
 
 #[async_trait]
@@ -122,65 +450,285 @@ impl LLMProvider for OllamaProvider {
     }
 
     fn base_url(&self) -> &str {
-        &self.base_url
+        self.transport.base_url()
+    }
+
+    fn transport(&self) -> Transport {
+        self.transport.clone()
+    }
+
+    fn model_name(&self) -> Option<&str> {
+        Some(&self.model)
     }
 
     async fn chat_completions(
         &self,
         messages: Vec<ChatMessage>,
-        temperature: f32,
+        settings: &GenerationSettings,
         tools: Vec<Value>,
     ) -> Result<Box<dyn ChatResponse>, AgenticFlowError> {
         let req = ChatCompletionRequest {
             model: self.model.to_string(),
             messages,
-            temperature,
+            temperature: settings.temperature,
+            max_tokens: settings.max_tokens,
+            stop: settings.stop.clone(),
+            response_format: settings.format.response_format(),
+            // Ollama has no `tool_choice`.
+            tool_choice: None,
+            parallel_tool_calls: None,
             stream: false,
-            tools,
+            tools: settings.tool_choice.narrowed_tools("Ollama", tools),
         };
-        let response = self.send_request(json!(req), "api/chat").await?;
+        let mut request = json!(req);
+        request["messages"] = json!(dialect::ollama_messages(&req.messages));
+        with_ollama_options(&mut request, settings);
+        self.options.apply(&mut request);
+        let response = self.send_request(request, "api/chat").await?;
 
-        let response_text = response.text().await.unwrap();
-        serde_json::from_str::<OllamaResponse>(&response_text)
-            .map_err(|e| AgenticFlowError::ParseError(format!("Failed to parse response: {}", e)))
-            .map(|res| Box::new(res) as Box<dyn ChatResponse>)
+        let response_text = body::read(response).await?;
+        let mut response = parse_body::<OllamaResponse>(&response_text)?;
+        response.message.extract_think_blocks();
+        Ok(Box::new(response))
     }
 
     async fn completion(
         &self,
         prompt: String,
-        temperature: f32,
+        settings: &GenerationSettings,
     ) -> Result<Box<dyn CompletionResponse>, AgenticFlowError> {
         let request = CompletionRequest {
             model: self.model.to_string(),
-            prompt: prompt,
+            prompt,
             max_tokens: None,
-            temperature: Some(temperature),
+            temperature: Some(settings.temperature),
             stream: Some(false),
+            stop: settings.stop.clone(),
         };
-        let response = self.send_request(json!(request), "api/generate").await?;
+        let mut request = json!(request);
+        with_ollama_options(&mut request, settings);
+        self.options.apply(&mut request);
+        let response = self.send_request(request, "api/generate").await?;
+
+        let response_text = body::read(response).await?;
+        let response = parse_body::<OllamaCompletionResponse>(&response_text)?;
+        Ok(Box::new(response))
+    }
+
+    fn embedding_batch_size(&self) -> usize {
+        embeddings::OLLAMA_BATCH_SIZE
+    }
+
+    async fn embed(&self, inputs: Vec<String>) -> Result<Vec<Vec<f32>>, AgenticFlowError> {
+        let request = json!({
+            "model": self.embedding_model.as_ref().unwrap_or(&self.model),
+            "input": inputs,
+        });
+        let response = self.send_request(request, "api/embed").await?;
+
+        let response_text = body::read(response).await?;
+        let response = embeddings::parse::<OllamaEmbeddings>(&response_text)?;
+        Ok(response.embeddings)
+    }
+
+    fn lists_models(&self) -> bool {
+        true
+    }
+
+    async fn available_models(&self) -> Result<Vec<String>, AgenticFlowError> {
+        let response = self
+            .client
+            .get(self.transport.url("api/tags"))
+            .send()
+            .await
+            .map_err(|error| self.transport.send_error(error))?;
+        let status = response.status().as_u16();
+        let response_text = body::read(response).await?;
+        if !(200..300).contains(&status) {
+            return Err(AgenticFlowError::from_http_response(
+                status,
+                None,
+                &response_text,
+            ));
+        }
+        let tags: OllamaTags = serde_json::from_str(&response_text)
+            .map_err(|error| AgenticFlowError::unparseable_body(error, &response_text))?;
+        Ok(tags.into_names())
+    }
+
+    async fn model_capabilities(
+        &self,
+        known: ModelCapabilities,
+    ) -> Result<ModelCapabilities, AgenticFlowError> {
+        let response = self
+            .send_request(json!({ "model": self.model }), "api/show")
+            .await?;
+        let response_text = body::read(response).await?;
+        let show: OllamaShow = serde_json::from_str(&response_text)
+            .map_err(|error| AgenticFlowError::unparseable_body(error, &response_text))?;
+        Ok(show.apply(known))
+    }
+
+    async fn pull_model(
+        &self,
+        progress: &(dyn for<'a> Fn(&'a PullProgress) + Send + Sync),
+    ) -> Result<(), AgenticFlowError> {
+        let request = json!({ "model": self.model, "stream": true });
+        let mut response = self.send_request(request, "api/pull").await?;
+
+        // The progress arrives as NDJSON, a line of which may span chunks.
+        let mut pending: Vec<u8> = Vec::new();
+        while let Some(chunk) = response.chunk().await? {
+            pending.extend_from_slice(&chunk);
+            while let Some(end) = pending.iter().position(|byte| *byte == b'\n') {
+                let line: Vec<u8> = pending.drain(..=end).collect();
+                if let Some(line) = models::pull_progress(&self.model, &line)? {
+                    progress(&line);
+                }
+            }
+        }
+        if let Some(line) = models::pull_progress(&self.model, &pending)? {
+            progress(&line);
+        }
+        Ok(())
+    }
+}
+
+/// Where a provider reads its API key from.
+enum ApiKeySource {
+    /// Read from the environment on every request.
+    Env(String),
+    /// Passed to the client, or resolved once from a
+    /// [`SecretRef`](crate::secrets::SecretRef) when the client was built.
+    Resolved(SecretString),
+}
 
-        let response_text = response.text().await.unwrap();
-        serde_json::from_str::<OllamaCompletionResponse>(&response_text)
-            .map_err(|e| AgenticFlowError::ParseError(format!("Failed to parse response: {}", e)))
-            .map(|res| Box::new(res) as Box<dyn CompletionResponse>)
+impl ApiKeySource {
+    /// The key, or an error naming `provider` when it is read from an environment
+    /// variable that is not set. Failing here keeps the request from going out with an
+    /// empty token.
+    fn require(&self, provider: &str) -> Result<String, AgenticFlowError> {
+        match self {
+            ApiKeySource::Env(name) => std::env::var(name)
+                .ok()
+                .filter(|key| !key.is_empty())
+                .ok_or_else(|| {
+                    AgenticFlowError::ApiClientError(format!(
+                        "missing API key for {}: set {} or pass a key to the client",
+                        provider, name
+                    ))
+                }),
+            ApiKeySource::Resolved(key) => Ok(key.expose().to_string()),
+        }
     }
 }
 
-struct OpenRouterProvider {
+/// OpenRouter answers 404 when no provider of the model supports every parameter of a
+/// request that requires them, which only requests with a format do.
+fn unsupported_format(
+    error: AgenticFlowError,
+    model: &str,
+    format: &OutputFormat,
+) -> AgenticFlowError {
+    let unsupported = *format != OutputFormat::None
+        && matches!(
+            error,
+            AgenticFlowError::ApiResponseError { status: 404, .. }
+        );
+    if !unsupported {
+        return error;
+    }
+    let format = match format {
+        OutputFormat::Schema(_) => "a JSON schema",
+        _ => "JSON",
+    };
+    AgenticFlowError::ApiClientError(format!(
+        "Model '{}' does not support {} output on OpenRouter: {}",
+        model, format, error
+    ))
+}
+
+/// A provider for the OpenRouter API, for settings beyond those of
+/// [`LLMClient::from_open_router`]:
+///
+/// ```rust,no_run
+/// # use agentic_flow_lib::llm_client::{LLMClient, OpenRouterModel, OpenRouterProvider};
+/// let client = LLMClient::from(
+///     OpenRouterProvider::new(OpenRouterModel::Flash2)
+///         .with_app_url("https://example.com")
+///         .with_app_name("Example"),
+/// );
+/// ```
+pub struct OpenRouterProvider {
     client: HttpClient,
-    base_url: &'static str,
+    base_url: String,
     model: String,
+    api_key: ApiKeySource,
+    app_url: Option<String>,
+    app_name: Option<String>,
+    embedding_model: Option<String>,
 }
 
 impl OpenRouterProvider {
     pub fn new(model: OpenRouterModel) -> Self {
+        Self::with_client(
+            HttpClient::new(),
+            model,
+            ApiKeySource::Env("OPENROUTER_API_KEY".to_string()),
+        )
+    }
+
+    fn with_client(client: HttpClient, model: OpenRouterModel, api_key: ApiKeySource) -> Self {
         Self {
-            client: HttpClient::new(),
-            base_url: "https://openrouter.ai/api/v1",
+            client,
+            base_url: "https://openrouter.ai/api/v1".to_string(),
             model: model.to_string(),
+            api_key,
+            app_url: None,
+            app_name: None,
+            embedding_model: None,
         }
     }
+
+    /// Sends requests to `base_url` in place of `https://openrouter.ai/api/v1`, such as a
+    /// proxy.
+    pub fn with_base_url(mut self, base_url: &str) -> Self {
+        self.base_url = base_url.trim_end_matches('/').to_string();
+        self
+    }
+
+    /// Sends `api_key` in place of reading `OPENROUTER_API_KEY`.
+    pub fn with_api_key(mut self, api_key: impl Into<SecretString>) -> Self {
+        self.api_key = ApiKeySource::Resolved(api_key.into());
+        self
+    }
+
+    /// The site of the app, sent as `HTTP-Referer`. OpenRouter attributes requests and
+    /// ranks apps by it, and some free models reject requests without it.
+    pub fn with_app_url(mut self, url: &str) -> Self {
+        self.app_url = Some(url.to_string());
+        self
+    }
+
+    /// The name of the app, sent as `X-Title`.
+    pub fn with_app_name(mut self, name: &str) -> Self {
+        self.app_name = Some(name.to_string());
+        self
+    }
+
+    /// Embeds with `model`, such as `openai/text-embedding-3-small`, in place of the chat
+    /// model.
+    pub fn with_embedding_model(mut self, model: OpenRouterModel) -> Self {
+        self.embedding_model = Some(model.to_string());
+        self
+    }
+
+    /// Sends requests with a client built from `http`, e.g. through a corporate proxy.
+    /// Fails with [`AgenticFlowError::ApiClientError`] when `http` is invalid.
+    pub fn with_http(mut self, http: &HttpConfig) -> Result<Self, AgenticFlowError> {
+        self.client = http.build_client()?;
+        Ok(self)
+    }
 }
 
 #[async_trait]
@@ -193,62 +741,234 @@ impl LLMProvider for OpenRouterProvider {
         &self.base_url
     }
 
-    fn api_key(&self) -> Option<String> {
-        match std::env::var("OPENROUTER_API_KEY") {
-            Ok(key) => Some(key),
-            Err(_) => {
-                println!("WARNING: OPENROUTER_API_KEY is not set in environment variables.");
-                None
-            }
+    fn api_key(&self) -> Result<Option<String>, AgenticFlowError> {
+        self.api_key.require("OpenRouter").map(Some)
+    }
+
+    fn request_headers(&self) -> Result<Vec<(&'static str, String)>, AgenticFlowError> {
+        let mut headers = vec![(
+            "Authorization",
+            format!("Bearer {}", self.api_key.require("OpenRouter")?),
+        )];
+        if let Some(url) = &self.app_url {
+            headers.push(("HTTP-Referer", url.clone()));
         }
+        if let Some(name) = &self.app_name {
+            headers.push(("X-Title", name.clone()));
+        }
+        Ok(headers)
+    }
+
+    fn model_name(&self) -> Option<&str> {
+        Some(&self.model)
     }
 
     async fn chat_completions(
         &self,
         messages: Vec<ChatMessage>,
-        temperature: f32,
+        settings: &GenerationSettings,
         tools: Vec<Value>,
     ) -> Result<Box<dyn ChatResponse>, AgenticFlowError> {
         let req = ChatCompletionRequest {
             model: self.model.to_string(),
             messages,
-            temperature,
+            temperature: settings.temperature,
+            max_tokens: settings.max_tokens,
+            stop: settings.stop.clone(),
+            response_format: settings.format.response_format(),
+            tool_choice: settings.tool_choice.openai_tool_choice(&tools),
+            // Only allowed with tools.
+            parallel_tool_calls: settings.parallel_tool_calls.filter(|_| !tools.is_empty()),
             stream: false,
             tools,
         };
-        let response = self.send_request(json!(req), "chat/completions").await?;
+        let mut request = json!(req);
+        request["messages"] = json!(dialect::openai_messages(&req.messages));
+        dialect::with_sampling(
+            &mut request,
+            &settings.sampling,
+            dialect::OPENROUTER_SAMPLING,
+        );
+        if req.response_format.is_some() {
+            // Without it, OpenRouter may route to a provider that ignores the format.
+            request["provider"] = json!({ "require_parameters": true });
+        }
+        let response = self
+            .send_request(request, "chat/completions")
+            .await
+            .map_err(|error| unsupported_format(error, &self.model, &settings.format))?;
 
-        let response_text = response.text().await.unwrap();
-        serde_json::from_str::<OpenRouterResponse>(&response_text)
-            .map_err(|e| AgenticFlowError::ParseError(format!("Failed to parse response: {}", e)))
-            .map(|res| Box::new(res) as Box<dyn ChatResponse>)
+        let response_text = body::read(response).await?;
+        let response = parse_chat_body(&response_text)?;
+        Ok(Box::new(response))
     }
 
     async fn completion(
         &self,
         prompt: String,
-        temperature: f32,
+        settings: &GenerationSettings,
     ) -> Result<Box<dyn CompletionResponse>, AgenticFlowError> {
         let request = CompletionRequest {
             model: self.model.to_string(),
             prompt,
-            max_tokens: None,
-            temperature: Some(temperature),
+            max_tokens: settings.max_tokens,
+            temperature: Some(settings.temperature),
             stream: Some(false),
+            stop: settings.stop.clone(),
         };
-        let response = self.send_request(json!(request), "completions").await?;
+        let mut request = json!(request);
+        dialect::with_sampling(
+            &mut request,
+            &settings.sampling,
+            dialect::OPENROUTER_SAMPLING,
+        );
+        let response = self.send_request(request, "completions").await?;
 
-        let response_text = response.text().await.unwrap();
-        serde_json::from_str::<OpenRouterCompletionResponse>(&response_text)
-            .map_err(|e| AgenticFlowError::ParseError(format!("Failed to parse response: {}", e)))
-            .map(|res| Box::new(res) as Box<dyn CompletionResponse>)
+        let response_text = body::read(response).await?;
+        if let Some(error) = AgenticFlowError::from_error_body(&response_text) {
+            return Err(error);
+        }
+        let response = parse_body::<OpenRouterCompletionResponse>(&response_text)?;
+        Ok(Box::new(response))
+    }
+
+    async fn embed(&self, inputs: Vec<String>) -> Result<Vec<Vec<f32>>, AgenticFlowError> {
+        let request = json!({
+            "model": self.embedding_model.as_ref().unwrap_or(&self.model),
+            "input": inputs,
+        });
+        let response = self.send_request(request, "embeddings").await?;
+
+        let response_text = body::read(response).await?;
+        let response = embeddings::parse::<OpenAIEmbeddings>(&response_text)?;
+        Ok(response.into_vectors())
+    }
+}
+
+/// Provider for any server implementing the OpenAI chat completions API.
+struct OpenAICompatibleProvider {
+    client: HttpClient,
+    base_url: String,
+    model: String,
+    api_key: Option<ApiKeySource>,
+}
+
+impl OpenAICompatibleProvider {
+    fn new(
+        client: HttpClient,
+        base_url: &str,
+        model: String,
+        api_key: Option<ApiKeySource>,
+    ) -> Self {
+        Self {
+            client,
+            base_url: base_url.trim_end_matches('/').to_string(),
+            model,
+            api_key,
+        }
     }
 }
 
+#[async_trait]
+impl LLMProvider for OpenAICompatibleProvider {
+    fn http_client(&self) -> &HttpClient {
+        &self.client
+    }
+
+    fn base_url(&self) -> &str {
+        &self.base_url
+    }
+
+    fn api_key(&self) -> Result<Option<String>, AgenticFlowError> {
+        self.api_key
+            .as_ref()
+            .map(|key| key.require(&format!("the server at {}", self.base_url)))
+            .transpose()
+    }
+
+    fn model_name(&self) -> Option<&str> {
+        Some(&self.model)
+    }
+
+    async fn chat_completions(
+        &self,
+        messages: Vec<ChatMessage>,
+        settings: &GenerationSettings,
+        tools: Vec<Value>,
+    ) -> Result<Box<dyn ChatResponse>, AgenticFlowError> {
+        let req = ChatCompletionRequest {
+            model: self.model.to_string(),
+            messages,
+            temperature: settings.temperature,
+            max_tokens: settings.max_tokens,
+            stop: settings.stop.clone(),
+            response_format: settings.format.response_format(),
+            tool_choice: settings.tool_choice.openai_tool_choice(&tools),
+            // Only allowed with tools.
+            parallel_tool_calls: settings.parallel_tool_calls.filter(|_| !tools.is_empty()),
+            stream: false,
+            tools,
+        };
+        let mut request = json!(req);
+        request["messages"] = json!(dialect::openai_messages(&req.messages));
+        dialect::with_sampling(&mut request, &settings.sampling, dialect::OPENAI_SAMPLING);
+        let response = self.send_request(request, "chat/completions").await?;
+
+        let response_text = body::read(response).await?;
+        let response = parse_chat_body(&response_text)?;
+        Ok(Box::new(response))
+    }
+
+    async fn completion(
+        &self,
+        prompt: String,
+        settings: &GenerationSettings,
+    ) -> Result<Box<dyn CompletionResponse>, AgenticFlowError> {
+        let request = CompletionRequest {
+            model: self.model.to_string(),
+            prompt,
+            max_tokens: settings.max_tokens,
+            temperature: Some(settings.temperature),
+            stream: Some(false),
+            stop: settings.stop.clone(),
+        };
+        let mut request = json!(request);
+        dialect::with_sampling(&mut request, &settings.sampling, dialect::OPENAI_SAMPLING);
+        let response = self.send_request(request, "completions").await?;
+
+        let response_text = body::read(response).await?;
+        if let Some(error) = AgenticFlowError::from_error_body(&response_text) {
+            return Err(error);
+        }
+        let response = parse_body::<OpenRouterCompletionResponse>(&response_text)?;
+        Ok(Box::new(response))
+    }
+}
+
+/// How many requests [`LLMClient::chat_completions_batch`] and
+/// [`LLMClient::completion_batch`] have in flight at once.
+pub const BATCH_CONCURRENCY: usize = 8;
+
 #[derive(Clone)]
 pub struct LLMClient {
     inner: Arc<dyn LLMProvider>,
-    temperature: f32,
+    generation: GenerationSettings,
+    timeout: Option<Duration>,
+    error_observer: Option<Arc<dyn ErrorObserver>>,
+    logging: Option<Arc<LoggingLayer>>,
+    keep_raw_responses: bool,
+    retry: Option<Arc<RetryLayer>>,
+    cost_tracker: CostTracker,
+    cache: Option<Arc<CacheLayer>>,
+    layers: Vec<Arc<dyn LLMLayer>>,
+    capabilities: Arc<Mutex<Option<ModelCapabilities>>>,
+    tool_emulation: ToolEmulation,
+    throttle: Throttle,
+    fallbacks: Vec<LLMClient>,
+    headers: ExtraHeaders,
+    max_response_bytes: usize,
+    /// The `connect_timeout_seconds` of the [`HttpConfig`] the provider was built with.
+    connect_timeout: Option<Duration>,
 }
 
 impl Default for LLMClient {
@@ -258,49 +978,1004 @@ impl Default for LLMClient {
 }
 
 impl LLMClient {
+    /// An Ollama client for the server in `OLLAMA_HOST`, or `http://localhost:11434`.
     pub fn from_ollama(model: OllamaModel) -> Self {
-        Self {
-            inner: Arc::new(OllamaProvider::new(model)),
-            temperature: 0.7,
-        }
+        Self::from(OllamaProvider::new(model))
+    }
+
+    /// An Ollama client for the server at `base_url`, written as a URL or, as in
+    /// `OLLAMA_HOST`, as `host[:port]`. Fails with [`AgenticFlowError::ApiClientError`]
+    /// when the address is malformed.
+    pub fn from_ollama_at(
+        base_url: impl Into<String>,
+        model: OllamaModel,
+    ) -> Result<Self, AgenticFlowError> {
+        let base_url = ollama_base_url(&base_url.into())?;
+        Ok(Self::from(OllamaProvider::with_client(
+            HttpClient::new(),
+            model,
+            base_url,
+        )))
+    }
+
+    /// An Ollama client for the server bound to the unix socket at `path`, see
+    /// [`OllamaProvider::with_socket`].
+    #[cfg(unix)]
+    pub fn from_ollama_socket(
+        path: impl Into<std::path::PathBuf>,
+        model: OllamaModel,
+    ) -> Result<Self, AgenticFlowError> {
+        Ok(Self::from(OllamaProvider::with_socket(path, model)?))
     }
 
     pub fn from_open_router(model: OpenRouterModel) -> Self {
-        Self {
-            inner: Arc::new(OpenRouterProvider::new(model)),
-            temperature: 0.7,
-        }
+        Self::from(OpenRouterProvider::new(model))
+    }
+
+    /// An OpenRouter client sending `api_key` in place of reading `OPENROUTER_API_KEY`.
+    pub fn from_open_router_with_key(
+        model: OpenRouterModel,
+        api_key: impl Into<SecretString>,
+    ) -> Self {
+        Self::from(OpenRouterProvider::new(model).with_api_key(api_key))
+    }
+
+    /// An OpenRouter client for the API at `base_url` in place of
+    /// `https://openrouter.ai/api/v1`, such as a proxy, sending `api_key`.
+    pub fn from_open_router_at(
+        base_url: &str,
+        model: OpenRouterModel,
+        api_key: impl Into<SecretString>,
+    ) -> Self {
+        Self::from(
+            OpenRouterProvider::new(model)
+                .with_base_url(base_url)
+                .with_api_key(api_key),
+        )
+    }
+
+    pub fn from_openai(model: OpenAIModel) -> Self {
+        Self::from(OpenAIProvider::new(model))
+    }
+
+    /// An OpenAI client sending `api_key` in place of reading `OPENAI_API_KEY`.
+    pub fn from_openai_with_key(model: OpenAIModel, api_key: impl Into<SecretString>) -> Self {
+        Self::from(OpenAIProvider::with_client(
+            HttpClient::new(),
+            model,
+            ApiKeySource::Resolved(api_key.into()),
+        ))
+    }
+
+    pub fn from_anthropic(model: AnthropicModel) -> Self {
+        Self::from(AnthropicProvider::new(model))
+    }
+
+    /// An Anthropic client sending `api_key` in place of reading `ANTHROPIC_API_KEY`.
+    pub fn from_anthropic_with_key(
+        model: AnthropicModel,
+        api_key: impl Into<SecretString>,
+    ) -> Self {
+        Self::from(AnthropicProvider::with_client(
+            HttpClient::new(),
+            model,
+            ApiKeySource::Resolved(api_key.into()),
+        ))
+    }
+
+    /// A Gemini client reading its key from `GEMINI_API_KEY`.
+    pub fn from_gemini(model: GeminiModel) -> Self {
+        Self::gemini(model, ApiKeySource::Env("GEMINI_API_KEY".to_string()))
+    }
+
+    pub fn from_gemini_with_key(model: GeminiModel, api_key: impl Into<SecretString>) -> Self {
+        Self::gemini(model, ApiKeySource::Resolved(api_key.into()))
+    }
+
+    fn gemini(model: GeminiModel, api_key: ApiKeySource) -> Self {
+        Self::from(GeminiProvider::new(model, api_key))
+    }
+
+    pub fn from_groq(model: GroqModel) -> Self {
+        Self::from(GroqProvider::new(model))
+    }
+
+    /// A Groq client sending `api_key` in place of reading `GROQ_API_KEY`.
+    pub fn from_groq_with_key(model: GroqModel, api_key: impl Into<SecretString>) -> Self {
+        Self::from(GroqProvider::with_client(
+            HttpClient::new(),
+            model,
+            ApiKeySource::Resolved(api_key.into()),
+        ))
+    }
+
+    /// An Ollama client for the server in `OLLAMA_HOST`, or `http://localhost:11434`,
+    /// whose HTTP client is built from `http`, with its `max_response_bytes`. Fails with
+    /// [`AgenticFlowError::ApiClientError`] when `http` is invalid; the same holds for the
+    /// other `_with_http` constructors.
+    pub fn from_ollama_with_http(
+        model: OllamaModel,
+        http: &HttpConfig,
+    ) -> Result<Self, AgenticFlowError> {
+        Ok(Self::from(OllamaProvider::with_client(
+            http.build_client()?,
+            model,
+            default_ollama_url(),
+        ))
+        .with_http_limits(http))
+    }
+
+    pub fn from_open_router_with_http(
+        model: OpenRouterModel,
+        http: &HttpConfig,
+    ) -> Result<Self, AgenticFlowError> {
+        Ok(Self::from(OpenRouterProvider::with_client(
+            http.build_client()?,
+            model,
+            ApiKeySource::Env("OPENROUTER_API_KEY".to_string()),
+        ))
+        .with_http_limits(http))
+    }
+
+    pub fn from_openai_with_http(
+        model: OpenAIModel,
+        http: &HttpConfig,
+    ) -> Result<Self, AgenticFlowError> {
+        Ok(Self::from(OpenAIProvider::with_client(
+            http.build_client()?,
+            model,
+            ApiKeySource::Env("OPENAI_API_KEY".to_string()),
+        ))
+        .with_http_limits(http))
+    }
+
+    pub fn from_anthropic_with_http(
+        model: AnthropicModel,
+        http: &HttpConfig,
+    ) -> Result<Self, AgenticFlowError> {
+        Ok(Self::from(AnthropicProvider::with_client(
+            http.build_client()?,
+            model,
+            ApiKeySource::Env("ANTHROPIC_API_KEY".to_string()),
+        ))
+        .with_http_limits(http))
+    }
+
+    pub fn from_gemini_with_http(
+        model: GeminiModel,
+        http: &HttpConfig,
+    ) -> Result<Self, AgenticFlowError> {
+        Ok(Self::from(GeminiProvider::with_client(
+            http.build_client()?,
+            model,
+            ApiKeySource::Env("GEMINI_API_KEY".to_string()),
+        ))
+        .with_http_limits(http))
+    }
+
+    pub fn from_groq_with_http(
+        model: GroqModel,
+        http: &HttpConfig,
+    ) -> Result<Self, AgenticFlowError> {
+        Ok(Self::from(GroqProvider::with_client(
+            http.build_client()?,
+            model,
+            ApiKeySource::Env("GROQ_API_KEY".to_string()),
+        ))
+        .with_http_limits(http))
     }
 
     pub fn from<T>(provider: T) -> Self
     where
         T: LLMProvider + 'static,
     {
+        Self::from_shared(Arc::new(provider))
+    }
+
+    /// Builds a client with [`LLMClientBuilder`], which checks the settings.
+    pub fn builder() -> LLMClientBuilder {
+        LLMClientBuilder::new()
+    }
+
+    fn from_shared(inner: Arc<dyn LLMProvider>) -> Self {
         Self {
-            inner: Arc::new(provider),
-            temperature: 0.7,
+            inner,
+            generation: GenerationSettings::default(),
+            timeout: None,
+            error_observer: None,
+            logging: None,
+            keep_raw_responses: true,
+            retry: None,
+            cost_tracker: CostTracker::default(),
+            cache: None,
+            layers: Vec::new(),
+            capabilities: Arc::default(),
+            tool_emulation: ToolEmulation::default(),
+            throttle: Throttle::default(),
+            fallbacks: Vec::new(),
+            headers: Arc::new([]),
+            max_response_bytes: DEFAULT_MAX_RESPONSE_BYTES,
+            connect_timeout: None,
+        }
+    }
+
+    /// Takes the response limit and the connect timeout of `http`, which the provider's
+    /// HTTP client was built from.
+    fn with_http_limits(mut self, http: &HttpConfig) -> Self {
+        self.connect_timeout = http.connect_timeout_seconds.map(Duration::from_secs);
+        match http.max_response_bytes {
+            Some(max_bytes) => self.with_max_response_bytes(max_bytes),
+            None => self,
+        }
+    }
+
+    /// Builds the client described by an [`LLMConfig`], resolving `api_key` with the
+    /// [`DefaultSecretResolver`].
+    pub fn from_config(config: &LLMConfig) -> Result<Self, AgenticFlowError> {
+        Self::from_config_with_resolver(config, &DefaultSecretResolver)
+    }
+
+    /// Builds the client described by an [`LLMConfig`], resolving `api_key` with `resolver`.
+    pub fn from_config_with_resolver(
+        config: &LLMConfig,
+        resolver: &dyn SecretResolver,
+    ) -> Result<Self, AgenticFlowError> {
+        let http_client = config.http.clone().unwrap_or_default().build_client()?;
+
+        let api_key = match &config.api_key {
+            Some(secret) => {
+                let needed_by = format!("LLM provider {:?}", config.provider);
+                let key = resolve_for(resolver, secret, &needed_by)?;
+                Some(ApiKeySource::Resolved(SecretString::from(key)))
+            }
+            None => config.api_key_env.clone().map(ApiKeySource::Env),
+        };
+
+        let model = config.model.clone();
+        let inner: Arc<dyn LLMProvider> =
+            match &config.provider {
+                ProviderKind::Ollama => {
+                    let mut provider = OllamaProvider::with_client(
+                        http_client,
+                        OllamaModel::Custom(model),
+                        match &config.base_url {
+                            Some(base_url) => ollama_base_url(base_url)?,
+                            None => default_ollama_url(),
+                        },
+                    );
+                    provider.embedding_model = config.embedding_model.clone();
+                    provider.options = config.ollama.clone().unwrap_or_default();
+                    Arc::new(provider)
+                }
+                ProviderKind::OpenRouter => {
+                    let mut provider = OpenRouterProvider::with_client(
+                        http_client,
+                        OpenRouterModel::Custom(model),
+                        api_key
+                            .unwrap_or_else(|| ApiKeySource::Env("OPENROUTER_API_KEY".to_string())),
+                    );
+                    provider.app_url = config.app_url.clone();
+                    provider.app_name = config.app_name.clone();
+                    provider.embedding_model = config.embedding_model.clone();
+                    Arc::new(provider)
+                }
+                ProviderKind::OpenAI => Arc::new(OpenAIProvider::with_client(
+                    http_client,
+                    OpenAIModel::Custom(model),
+                    api_key.unwrap_or_else(|| ApiKeySource::Env("OPENAI_API_KEY".to_string())),
+                )),
+                ProviderKind::Anthropic => Arc::new(AnthropicProvider::with_client(
+                    http_client,
+                    AnthropicModel::Custom(model),
+                    api_key.unwrap_or_else(|| ApiKeySource::Env("ANTHROPIC_API_KEY".to_string())),
+                )),
+                ProviderKind::Gemini => Arc::new(GeminiProvider::with_client(
+                    http_client,
+                    GeminiModel::Custom(model),
+                    api_key.unwrap_or_else(|| ApiKeySource::Env("GEMINI_API_KEY".to_string())),
+                )),
+                ProviderKind::Groq => Arc::new(GroqProvider::with_client(
+                    http_client,
+                    GroqModel::Custom(model),
+                    api_key.unwrap_or_else(|| ApiKeySource::Env("GROQ_API_KEY".to_string())),
+                )),
+                ProviderKind::OpenAICompatible { base_url } => Arc::new(
+                    OpenAICompatibleProvider::new(http_client, base_url, model, api_key),
+                ),
+            };
+
+        let mut builder = LLMClientBuilder::new()
+            .shared_provider(inner)
+            .temperature(config.temperature);
+        if let Some(max_tokens) = config.max_tokens {
+            builder = builder.max_tokens(max_tokens);
         }
+        if let Some(seconds) = config.timeout_seconds {
+            builder = builder.timeout(Duration::from_secs(seconds));
+        }
+        let max_response_bytes = config
+            .http
+            .as_ref()
+            .and_then(|http| http.max_response_bytes);
+        if let Some(max_bytes) = max_response_bytes {
+            builder = builder.max_response_bytes(max_bytes);
+        }
+        let mut client = builder.build()?;
+        client.connect_timeout = config
+            .http
+            .as_ref()
+            .and_then(|http| http.connect_timeout_seconds)
+            .map(Duration::from_secs);
+        Ok(client)
     }
 
     pub fn with_temperature(mut self, temperature: f32) -> Self {
-        self.temperature = temperature;
+        self.generation.temperature = temperature;
+        self
+    }
+
+    /// Sampling parameters beyond the temperature. Each provider gets the ones it
+    /// supports; with a temperature of 0, a fixed `seed` makes answers reproducible.
+    pub fn with_sampling(mut self, sampling: SamplingOptions) -> Self {
+        self.generation.sampling = sampling;
+        self
+    }
+
+    /// Ends every answer at the first of the `stop` sequences, which is left out of it.
+    /// [`RequestOptions::stop`] overrides them for a single call.
+    pub fn with_stop(mut self, stop: Vec<String>) -> Self {
+        self.generation.stop = Some(stop).filter(|stop| !stop.is_empty());
+        self
+    }
+
+    /// Asks for answers in `format`, e.g. [`OutputFormat::Json`] for any JSON value.
+    /// [`RequestOptions::format`] overrides it for a single call. Ollama receives it as
+    /// `format`, OpenAI-style providers as `response_format`, and Gemini as
+    /// `responseMimeType` and `responseSchema`; Anthropic requests with a format fail.
+    pub fn with_output_format(mut self, format: OutputFormat) -> Self {
+        self.generation.format = format;
+        self
+    }
+
+    /// Allows or forbids several tool calls in one answer. OpenAI-style providers receive
+    /// it as `parallel_tool_calls`, and Anthropic as `disable_parallel_tool_use`; Ollama,
+    /// Gemini and llama.cpp ignore it.
+    pub fn with_parallel_tool_calls(mut self, parallel: bool) -> Self {
+        self.generation.parallel_tool_calls = Some(parallel);
+        self
+    }
+
+    /// Turns the thinking of reasoning models such as Qwen3 and DeepSeek-R1 on or off.
+    /// Ollama receives it as `think`; other providers ignore it. The reasoning ends up in
+    /// [`ChatMessage::thinking`] rather than the content.
+    pub fn with_thinking(mut self, thinking: bool) -> Self {
+        self.generation.thinking = Some(thinking);
         self
     }
 
+    /// Caps the tokens generated per response at `max_tokens`. A response cut off at the
+    /// limit reports [`FinishReason::Length`]. [`RequestOptions::max_tokens`] overrides it
+    /// for a single call.
+    pub fn with_max_tokens(mut self, max_tokens: usize) -> Self {
+        self.generation.max_tokens = Some(max_tokens);
+        self
+    }
+
+    /// Fails requests that take longer than `limit` with [`AgenticFlowError::Timeout`].
+    /// [`RequestOptions::timeout`] overrides it for a single call.
+    pub fn with_timeout(mut self, limit: Duration) -> Self {
+        self.timeout = Some(limit);
+        self
+    }
+
+    /// Fails requests whose response body is larger than `max_bytes` with
+    /// [`AgenticFlowError::ApiClientError`], without reading the rest of it; by default
+    /// [`DEFAULT_MAX_RESPONSE_BYTES`]. Streamed responses have no limit.
+    pub fn with_max_response_bytes(mut self, max_bytes: usize) -> Self {
+        self.max_response_bytes = max_bytes;
+        self
+    }
+
+    /// Reports failed requests to `observer` as `llm` errors.
+    pub fn with_error_observer(mut self, observer: Arc<dyn ErrorObserver>) -> Self {
+        self.error_observer = Some(observer);
+        self
+    }
+
+    /// Calls `observer` after every chat and completion request with what was sent, with
+    /// credentials redacted, and how it ended. Clients without one copy nothing. Sets
+    /// the [`LoggingLayer`] that comes after the cache.
+    pub fn with_request_observer(
+        mut self,
+        observer: impl Fn(&LLMRequestLog) + Send + Sync + 'static,
+    ) -> Self {
+        self.logging = Some(Arc::new(LoggingLayer::new(observer)));
+        self
+    }
+
+    /// Whether responses keep their raw body for [`ChatResponse::raw`]. On by default;
+    /// turn it off to free the body once the response is parsed.
+    pub fn with_raw_responses(mut self, keep: bool) -> Self {
+        self.keep_raw_responses = keep;
+        self
+    }
+
+    /// Retries requests that fail with a retryable error as `policy` allows. A request
+    /// that fails after retries does so with [`AgenticFlowError::RetriesExhausted`]. Sets
+    /// the [`RetryLayer`] that comes after the layers of [`layer`](Self::layer); it also
+    /// retries embedding and model list requests.
+    pub fn with_retry(mut self, policy: RetryPolicy) -> Self {
+        self.retry = Some(Arc::new(RetryLayer::new(policy)));
+        self
+    }
+
+    /// Records this client's usage in `tracker`, e.g. one with a [`PriceTable`] or one
+    /// shared with other clients. Every client starts with a tracker of its own, which
+    /// its clones share.
+    pub fn with_cost_tracker(mut self, tracker: CostTracker) -> Self {
+        self.cost_tracker = tracker;
+        self
+    }
+
+    pub fn cost_tracker(&self) -> &CostTracker {
+        &self.cost_tracker
+    }
+
+    /// The calls, tokens and cost recorded so far, by model.
+    pub fn usage_report(&self) -> UsageReport {
+        self.cost_tracker.report()
+    }
+
+    /// Answers a chat request identical to an earlier one from memory, without calling
+    /// the provider. Requests count as identical when their model, messages, tools and
+    /// generation settings are. Clones share the cache. Sets the [`CacheLayer`] that
+    /// comes first.
+    pub fn with_cache(mut self, config: CacheConfig) -> Self {
+        self.cache = Some(Arc::new(CacheLayer::new(config)));
+        self
+    }
+
+    /// Passes chat and completion requests through `layer` on their way to the provider.
+    /// Requests go through the layer of [`with_cache`](Self::with_cache), then the
+    /// [`ToolEmulationLayer`] when the client [emulates tools](Self::emulates_tools), then
+    /// that of [`with_request_observer`](Self::with_request_observer), then the layers
+    /// added here in the order they were added, then that of
+    /// [`with_retry`](Self::with_retry). After the last layer, each attempt waits for the
+    /// concurrency and rate limits and is sent under the timeout. Clones share the layers.
+    pub fn layer(mut self, layer: impl LLMLayer + 'static) -> Self {
+        self.layers.push(Arc::new(layer));
+        self
+    }
+
+    /// Sends a request that fails here to `secondary` instead, when the provider could
+    /// not be reached, timed out or answered 5xx. Requests it rejected, with 4xx or a body
+    /// that does not parse, fail at once. Each call adds a fallback after the earlier ones,
+    /// followed by the fallbacks of `secondary`. Each client sends the request with its own
+    /// settings, retries and limits.
+    pub fn with_fallback(mut self, mut secondary: LLMClient) -> Self {
+        let further = std::mem::take(&mut secondary.fallbacks);
+        self.fallbacks.push(secondary);
+        self.fallbacks.extend(further);
+        self
+    }
+
+    /// Sends at most `max_in_flight` requests at once, across this client and its clones.
+    /// Requests beyond it wait for one to finish.
+    pub fn with_max_concurrency(mut self, max_in_flight: usize) -> Self {
+        self.throttle = self.throttle.with_max_concurrency(max_in_flight);
+        self
+    }
+
+    /// Sends at most `requests_per_minute` requests a minute on average, across this
+    /// client and its clones, in bursts of up to a second's worth. Requests beyond it wait
+    /// their turn.
+    pub fn with_rate_limit(mut self, requests_per_minute: u32) -> Self {
+        self.throttle = self.throttle.with_rate_limit(requests_per_minute);
+        self
+    }
+
+    /// Fails a request with [`AgenticFlowError::QueueTimeout`] when it waits longer than
+    /// `limit` for [`with_max_concurrency`](Self::with_max_concurrency) or
+    /// [`with_rate_limit`](Self::with_rate_limit). Requests wait indefinitely otherwise.
+    pub fn with_queue_timeout(mut self, limit: Duration) -> Self {
+        self.throttle.queue_timeout = Some(limit);
+        self
+    }
+
+    /// Replaces the [`capabilities`](Self::capabilities) of the model, such as a
+    /// [`OllamaModel::Custom`] one the crate does not know.
+    pub fn with_capabilities(mut self, capabilities: ModelCapabilities) -> Self {
+        self.capabilities = Arc::new(Mutex::new(Some(capabilities)));
+        self
+    }
+
+    /// What the model requests are sent to supports: those given to
+    /// [`with_capabilities`](Self::with_capabilities) or found by
+    /// [`refresh_capabilities`](Self::refresh_capabilities), and otherwise the
+    /// [`ModelCapabilities::built_in`] ones or the default. JSON mode also needs a
+    /// provider that takes an [`OutputFormat`].
+    pub fn capabilities(&self) -> ModelCapabilities {
+        let known = *self
+            .capabilities
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        let mut capabilities = known
+            .or_else(|| self.model_name().and_then(ModelCapabilities::built_in))
+            .unwrap_or_default();
+        capabilities.supports_json_mode &= self.inner.supports_output_format();
+        capabilities
+    }
+
+    /// Asks the server what the model supports, for providers that can tell such as
+    /// Ollama, whose `api/show` reports tool and image support and the context length,
+    /// and keeps the answer for [`capabilities`](Self::capabilities) of this client and
+    /// its clones. Fails with [`AgenticFlowError::ApiClientError`] for other providers.
+    pub async fn refresh_capabilities(&self) -> Result<ModelCapabilities, AgenticFlowError> {
+        let known = self.capabilities();
+        let refreshed = self
+            .limited("llm capabilities", &RequestOptions::default(), || {
+                self.inner.model_capabilities(known)
+            })
+            .await?;
+        *self
+            .capabilities
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner()) = Some(refreshed);
+        Ok(self.capabilities())
+    }
+
+    /// Sets when tool calls are emulated through the prompt with the
+    /// [`ToolEmulationLayer`], for models without native function calling such as
+    /// `gemma2:2b`. The default, [`ToolEmulation::Auto`], emulates them for models whose
+    /// [`capabilities`](Self::capabilities) say they do not support tools.
+    pub fn with_tool_emulation(mut self, tool_emulation: ToolEmulation) -> Self {
+        self.tool_emulation = tool_emulation;
+        self
+    }
+
+    /// Whether chat requests with tools go through the [`ToolEmulationLayer`].
+    pub fn emulates_tools(&self) -> bool {
+        match self.tool_emulation {
+            ToolEmulation::Auto => !self.capabilities().supports_tools,
+            ToolEmulation::Always => true,
+            ToolEmulation::Never => false,
+        }
+    }
+
+    /// How often the cache answered a request; `None` without
+    /// [`with_cache`](Self::with_cache).
+    pub fn cache_stats(&self) -> Option<CacheStats> {
+        self.cache.as_deref().map(CacheLayer::stats)
+    }
+
+    pub(crate) fn error_observer(&self) -> &Option<Arc<dyn ErrorObserver>> {
+        &self.error_observer
+    }
+
+    pub fn temperature(&self) -> f32 {
+        self.generation.temperature
+    }
+
+    pub fn max_tokens(&self) -> Option<usize> {
+        self.generation.max_tokens
+    }
+
+    pub fn sampling(&self) -> &SamplingOptions {
+        &self.generation.sampling
+    }
+
+    pub fn model_name(&self) -> Option<&str> {
+        self.inner.model_name()
+    }
+
     pub async fn chat_completions(
         &self,
         messages: Vec<ChatMessage>,
         tools: Vec<Value>,
     ) -> Result<Box<dyn ChatResponse>, AgenticFlowError> {
-        self.inner
-            .chat_completions(messages, self.temperature, tools)
+        self.chat_completions_with(messages, tools, &RequestOptions::default())
             .await
     }
 
+    /// [`chat_completions`](Self::chat_completions) with the client's settings
+    /// overridden by `options`.
+    pub async fn chat_completions_with(
+        &self,
+        messages: Vec<ChatMessage>,
+        tools: Vec<Value>,
+        options: &RequestOptions,
+    ) -> Result<Box<dyn ChatResponse>, AgenticFlowError> {
+        let mut result = self
+            .chat_completions_here(messages.clone(), tools.clone(), options)
+            .await;
+        for (position, client) in self.fallbacks.iter().enumerate() {
+            if !result.as_ref().is_err_and(falls_through) {
+                break;
+            }
+            result = client
+                .chat_completions_here(messages.clone(), tools.clone(), options)
+                .await
+                .map(|response| -> Box<dyn ChatResponse> {
+                    Box::new(FallbackResponse {
+                        response,
+                        fallback: position + 1,
+                    })
+                });
+        }
+        result
+    }
+
+    /// Sends a chat request with this client's provider, without its fallbacks.
+    async fn chat_completions_here(
+        &self,
+        messages: Vec<ChatMessage>,
+        tools: Vec<Value>,
+        options: &RequestOptions,
+    ) -> Result<Box<dyn ChatResponse>, AgenticFlowError> {
+        let request = LLMRequest::Chat {
+            messages,
+            tools,
+            settings: self.generation.overridden_by(options),
+        };
+        let mut response = self.layered(request, options).await?.into_chat()?;
+        if !self.keep_raw_responses {
+            response.take_raw();
+        }
+        Ok(response)
+    }
+
     pub async fn completion(
         &self,
         prompt: String,
     ) -> Result<Box<dyn CompletionResponse>, AgenticFlowError> {
-        self.inner.completion(prompt, self.temperature).await
+        self.completion_with(prompt, &RequestOptions::default())
+            .await
+    }
+
+    /// [`completion`](Self::completion) with the client's settings overridden by
+    /// `options`.
+    pub async fn completion_with(
+        &self,
+        prompt: String,
+        options: &RequestOptions,
+    ) -> Result<Box<dyn CompletionResponse>, AgenticFlowError> {
+        let mut result = self.completion_here(prompt.clone(), options).await;
+        for (position, client) in self.fallbacks.iter().enumerate() {
+            if !result.as_ref().is_err_and(falls_through) {
+                break;
+            }
+            result = client.completion_here(prompt.clone(), options).await.map(
+                |response| -> Box<dyn CompletionResponse> {
+                    Box::new(FallbackResponse {
+                        response,
+                        fallback: position + 1,
+                    })
+                },
+            );
+        }
+        result
+    }
+
+    /// Sends a completion request with this client's provider, without its fallbacks.
+    async fn completion_here(
+        &self,
+        prompt: String,
+        options: &RequestOptions,
+    ) -> Result<Box<dyn CompletionResponse>, AgenticFlowError> {
+        let request = LLMRequest::Completion {
+            prompt,
+            settings: self.generation.overridden_by(options),
+        };
+        let mut response = self.layered(request, options).await?.into_completion()?;
+        if !self.keep_raw_responses {
+            response.take_raw();
+        }
+        Ok(response)
+    }
+
+    /// Sends one chat request per conversation in `conversations`, all with `tools`, and
+    /// returns their results in the same order. Up to [`BATCH_CONCURRENCY`] requests are
+    /// in flight at once, fewer with [`with_max_concurrency`](Self::with_max_concurrency),
+    /// so servers that answer one request at a time, such as Ollama, have the next one
+    /// queued. A failed request leaves the others unaffected.
+    pub async fn chat_completions_batch(
+        &self,
+        conversations: Vec<Vec<ChatMessage>>,
+        tools: Vec<Value>,
+    ) -> Vec<Result<Box<dyn ChatResponse>, AgenticFlowError>> {
+        self.chat_completions_batch_with(conversations, tools, &RequestOptions::default())
+            .await
+    }
+
+    /// [`chat_completions_batch`](Self::chat_completions_batch) with the client's settings
+    /// overridden by `options` for every request.
+    pub async fn chat_completions_batch_with(
+        &self,
+        conversations: Vec<Vec<ChatMessage>>,
+        tools: Vec<Value>,
+        options: &RequestOptions,
+    ) -> Vec<Result<Box<dyn ChatResponse>, AgenticFlowError>> {
+        stream::iter(conversations)
+            .map(|messages| self.chat_completions_with(messages, tools.clone(), options))
+            .buffered(BATCH_CONCURRENCY)
+            .collect()
+            .await
+    }
+
+    /// Sends one completion request per prompt, like
+    /// [`chat_completions_batch`](Self::chat_completions_batch), and returns the text of
+    /// each response or its error, in the order of `prompts`.
+    pub async fn completion_batch(
+        &self,
+        prompts: Vec<String>,
+    ) -> Vec<Result<String, AgenticFlowError>> {
+        stream::iter(prompts)
+            .map(|prompt| async move {
+                let response = self.completion(prompt).await?;
+                Ok(response.response().to_string())
+            })
+            .buffered(BATCH_CONCURRENCY)
+            .collect()
+            .await
+    }
+
+    /// Embeds each of `inputs` as a vector, in order. The inputs are sent in batches of
+    /// the size the provider handles well, each throttled, timed out and retried like a
+    /// chat request. Fallbacks are not tried, since another model's vectors would not be
+    /// comparable. Fails with [`AgenticFlowError::ApiClientError`] for providers without
+    /// embeddings.
+    pub async fn embed(&self, inputs: Vec<String>) -> Result<Vec<Vec<f32>>, AgenticFlowError> {
+        let batch_size = self.inner.embedding_batch_size().max(1);
+        let mut vectors = Vec::with_capacity(inputs.len());
+        for batch in inputs.chunks(batch_size) {
+            let embedded = self
+                .limited("llm embed", &RequestOptions::default(), || {
+                    self.inner.embed(batch.to_vec())
+                })
+                .await?;
+            embeddings::check_count(batch.len(), &embedded)?;
+            vectors.extend(embedded);
+        }
+        Ok(vectors)
+    }
+
+    /// The models the server has, for providers that can list them such as Ollama. Fails
+    /// with [`AgenticFlowError::ApiClientError`] for the others.
+    pub async fn available_models(&self) -> Result<Vec<String>, AgenticFlowError> {
+        self.limited("llm models", &RequestOptions::default(), || {
+            self.inner.available_models()
+        })
+        .await
+    }
+
+    /// Checks that the server has the model requests are sent to. A missing model is
+    /// pulled when `pull` is given, which gets each line of the pull's progress, and
+    /// otherwise fails with an [`AgenticFlowError::ApiClientError`] naming the model and
+    /// suggesting `ollama pull`.
+    pub async fn ensure_model(
+        &self,
+        pull: Option<&(dyn Fn(&PullProgress) + Send + Sync)>,
+    ) -> Result<(), AgenticFlowError> {
+        let available = self.available_models().await?;
+        let model = self.model_name().unwrap_or_default();
+        if models::is_available(model, &available) {
+            return Ok(());
+        }
+        match pull {
+            Some(progress) => self.inner.pull_model(progress).await,
+            None => Err(models::missing_model(model)),
+        }
+    }
+
+    /// Checks that the client can serve requests, as [`LLMConfig::verify_on_startup`]
+    /// asks: for providers that list their models, such as Ollama, that the server has
+    /// the model. Other providers are not checked.
+    pub async fn verify(&self) -> Result<(), AgenticFlowError> {
+        if !self.inner.lists_models() {
+            return Ok(());
+        }
+        self.ensure_model(None).await
+    }
+
+    /// Asks for an answer matching the JSON schema of `T` and deserializes it. See
+    /// [`generate_typed_with_schema`](Self::generate_typed_with_schema).
+    #[cfg(feature = "schemars")]
+    pub async fn generate_typed<T: DeserializeOwned + schemars::JsonSchema>(
+        &self,
+        messages: Vec<ChatMessage>,
+    ) -> Result<T, AgenticFlowError> {
+        let schema = schemars::schema_for!(T).to_value();
+        self.generate_typed_with_schema(messages, schema).await
+    }
+
+    /// Asks for an answer matching the JSON `schema` and deserializes it into `T`. The
+    /// schema is sent as an [`OutputFormat::Schema`] when the provider supports one, and
+    /// in a system message in any case. `<think>` blocks and code fences around the JSON
+    /// are dropped. An answer that does not deserialize gets one corrective request with
+    /// the error before failing with [`AgenticFlowError::ParseError`].
+    pub async fn generate_typed_with_schema<T: DeserializeOwned>(
+        &self,
+        messages: Vec<ChatMessage>,
+        schema: Value,
+    ) -> Result<T, AgenticFlowError> {
+        let mut options = RequestOptions::default();
+        if self.inner.supports_output_format() {
+            options = options.with_format(OutputFormat::Schema(schema.clone()));
+        }
+        let mut messages: Vec<ChatMessage> = std::iter::once(typed::schema_prompt(&schema))
+            .chain(messages)
+            .collect();
+        let mut corrected = false;
+        loop {
+            let response = self
+                .chat_completions_with(messages.clone(), vec![], &options)
+                .await?;
+            if response.finish_reason() == Some(FinishReason::Length) {
+                return Err(AgenticFlowError::OutputTruncated {
+                    max_tokens: self.max_tokens(),
+                });
+            }
+            let answer = typed::json_text(response.message());
+            let error = match serde_json::from_str(&answer) {
+                Ok(value) => return Ok(value),
+                Err(error) => error,
+            };
+            if corrected {
+                return Err(typed::parse_error(error, &answer));
+            }
+            messages.push(response.message().clone());
+            messages.push(typed::correction_prompt(&error));
+            corrected = true;
+        }
+    }
+
+    /// Sends `request` through the layers and then to the provider, as
+    /// [`limited`](Self::limited) sends other requests.
+    async fn layered(
+        &self,
+        request: LLMRequest,
+        options: &RequestOptions,
+    ) -> Result<LLMResponse, AgenticFlowError> {
+        let layers: Vec<Arc<dyn LLMLayer>> = self
+            .cache
+            .iter()
+            .map(|layer| layer.clone() as Arc<dyn LLMLayer>)
+            .chain(
+                self.emulates_tools()
+                    .then(|| Arc::new(ToolEmulationLayer) as Arc<dyn LLMLayer>),
+            )
+            .chain(
+                self.logging
+                    .iter()
+                    .map(|layer| layer.clone() as Arc<dyn LLMLayer>),
+            )
+            .chain(self.layers.iter().cloned())
+            .chain(
+                self.retry
+                    .iter()
+                    .map(|layer| layer.clone() as Arc<dyn LLMLayer>),
+            )
+            .collect();
+        let timeout = options.timeout.or(self.timeout);
+        let operation = request.operation();
+        let next = Next::new(&layers, self, timeout);
+        self.guarded(operation, options, next.run(request)).await
+    }
+
+    /// Sends a request that went through the layers, unless its run has spent its
+    /// [`Budget`], and records its usage.
+    async fn send(
+        &self,
+        request: LLMRequest,
+        timeout: Option<Duration>,
+    ) -> Result<LLMResponse, AgenticFlowError> {
+        let operation = request.operation();
+        match request {
+            LLMRequest::Chat {
+                messages,
+                tools,
+                settings,
+            } => {
+                usage::check_budget()?;
+                let request = self.inner.chat_completions(messages, &settings, tools);
+                let response = self.attempt(operation, timeout, request).await?;
+                let model = self
+                    .inner
+                    .model_name()
+                    .or(response.model())
+                    .unwrap_or("unknown");
+                self.record_usage(model, response.usage(), response.stats());
+                Ok(LLMResponse::Chat(response))
+            }
+            LLMRequest::Completion { prompt, settings } => {
+                usage::check_budget()?;
+                let request = self.inner.completion(prompt, &settings);
+                let response = self.attempt(operation, timeout, request).await?;
+                let model = self.inner.model_name().unwrap_or("unknown");
+                self.record_usage(model, response.usage(), response.stats());
+                Ok(LLMResponse::Completion(response))
+            }
+        }
+    }
+
+    /// Adds a response to the client's usage report and to the totals of its run.
+    fn record_usage(&self, model: &str, usage: Option<Usage>, stats: Option<ProviderStats>) {
+        self.cost_tracker.record(model, usage);
+        let cost_usd = usage.and_then(|usage| self.cost_tracker.cost(model, &usage));
+        usage::record(usage, stats, cost_usd);
+    }
+
+    /// Sends the request made by `request` under the timeout, retrying as the
+    /// [`RetryPolicy`] allows, and reports the final failure, for requests that do not go
+    /// through the layers.
+    async fn limited<T, F>(
+        &self,
+        operation: &'static str,
+        options: &RequestOptions,
+        request: impl Fn() -> F,
+    ) -> Result<T, AgenticFlowError>
+    where
+        F: Future<Output = Result<T, AgenticFlowError>>,
+    {
+        let timeout = options.timeout.or(self.timeout);
+        let policy = self.retry.as_deref().map(RetryLayer::policy);
+        let attempts = retry::retrying(policy, || self.attempt(operation, timeout, request()));
+        self.guarded(operation, options, attempts).await
+    }
+
+    /// Sends one attempt of a request once it is its turn under the concurrency and rate
+    /// limits, with the extra headers. The timeout covers the attempt as a whole, from
+    /// sending the request to reading the last byte of the body; the time spent waiting
+    /// for the limits does not count towards it.
+    async fn attempt<T>(
+        &self,
+        operation: &'static str,
+        timeout: Option<Duration>,
+        request: impl Future<Output = Result<T, AgenticFlowError>>,
+    ) -> Result<T, AgenticFlowError> {
+        let _slot = self.throttle.acquire().await?;
+        let sent = body::limited(
+            self.max_response_bytes,
+            headers::sending(&self.headers, request),
+        );
+        // reqwest only says that the request timed out; the limit is the one it was given.
+        let sent = async {
+            sent.await.map_err(|error| match error {
+                AgenticFlowError::Timeout {
+                    operation: HTTP_REQUEST,
+                    limit,
+                } if limit.is_zero() => AgenticFlowError::Timeout {
+                    operation: HTTP_REQUEST,
+                    limit: self.connect_timeout.unwrap_or(limit),
+                },
+                error => error,
+            })
+        };
+        match timeout {
+            Some(limit) => with_timeout(operation, limit, sent).await,
+            None => sent.await,
+        }
+    }
+
+    /// Awaits `attempts`, stopping when the [`RequestOptions::cancellation`] token is
+    /// cancelled, and reports a failure to the error observer.
+    async fn guarded<T>(
+        &self,
+        operation: &'static str,
+        options: &RequestOptions,
+        attempts: impl Future<Output = Result<T, AgenticFlowError>>,
+    ) -> Result<T, AgenticFlowError> {
+        // Dropping the attempts drops the request in flight, or the wait for the next one.
+        let result = match &options.cancellation {
+            Some(token) => token
+                .run_until_cancelled(attempts)
+                .await
+                .unwrap_or(Err(AgenticFlowError::Cancelled { operation })),
+            None => attempts.await,
+        };
+        if let Err(error) = &result {
+            observer::report(&self.error_observer, error, ErrorContext::new("llm", error));
+        }
+        result
     }
 }
@@ -0,0 +1,290 @@
+//! Anthropic's Messages API.
+//!
+//! System prompts go in a top-level `system` field, tools are described by an
+//! `input_schema`, and tool calls and their results are content blocks of the assistant
+//! and user turns.
+
+use std::fmt;
+
+use async_trait::async_trait;
+use reqwest::Client as HttpClient;
+use serde_json::{Map, Value, json};
+
+use super::{
+    ApiKeySource, GenerationSettings, LLMProvider, OutputFormat, ToolChoice, body, parse_body,
+};
+use crate::{errors::AgenticFlowError, model::*};
+
+/// The sampling options the Messages API accepts.
+const SAMPLING: &[(&str, &str)] = &[("top_p", "top_p"), ("top_k", "top_k")];
+
+/// The Messages API requires a limit on the answer length; this one is used when the
+/// client sets none.
+const DEFAULT_MAX_TOKENS: usize = 4096;
+
+const API_VERSION: &str = "2023-06-01";
+
+#[derive(Debug, Clone)]
+pub enum AnthropicModel {
+    ClaudeOpus4,
+    ClaudeSonnet4,
+    Claude35Haiku,
+    Custom(String),
+}
+
+impl fmt::Display for AnthropicModel {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            AnthropicModel::ClaudeOpus4 => "claude-opus-4-0",
+            AnthropicModel::ClaudeSonnet4 => "claude-sonnet-4-0",
+            AnthropicModel::Claude35Haiku => "claude-3-5-haiku-latest",
+            AnthropicModel::Custom(name) => name,
+        })
+    }
+}
+
+pub(super) struct AnthropicProvider {
+    client: HttpClient,
+    base_url: String,
+    model: String,
+    api_key: ApiKeySource,
+}
+
+impl AnthropicProvider {
+    pub fn new(model: AnthropicModel) -> Self {
+        Self::with_client(
+            HttpClient::new(),
+            model,
+            ApiKeySource::Env("ANTHROPIC_API_KEY".to_string()),
+        )
+    }
+
+    pub(super) fn with_client(
+        client: HttpClient,
+        model: AnthropicModel,
+        api_key: ApiKeySource,
+    ) -> Self {
+        Self {
+            client,
+            base_url: "https://api.anthropic.com/v1".to_string(),
+            model: model.to_string(),
+            api_key,
+        }
+    }
+
+    fn request(
+        &self,
+        messages: &[ChatMessage],
+        settings: &GenerationSettings,
+        tools: &[Value],
+    ) -> Result<Value, AgenticFlowError> {
+        if settings.format != OutputFormat::None {
+            return Err(AgenticFlowError::ApiClientError(format!(
+                "Model '{}' does not support JSON output: the Messages API has no JSON mode",
+                self.model
+            )));
+        }
+        let (system, messages) = anthropic_messages(messages);
+        let mut request = json!({
+            "model": self.model,
+            "max_tokens": settings.max_tokens.unwrap_or(DEFAULT_MAX_TOKENS),
+            "temperature": settings.temperature,
+            "messages": messages,
+        });
+        if let Some(object) = request.as_object_mut() {
+            object.extend(settings.sampling.wire_fields(SAMPLING));
+        }
+        if let Some(stop) = &settings.stop {
+            request["stop_sequences"] = json!(stop);
+        }
+        if let Some(system) = system {
+            request["system"] = json!(system);
+        }
+        if !tools.is_empty() {
+            request["tools"] = json!(tools.iter().map(anthropic_tool).collect::<Vec<_>>());
+        }
+        if let Some(tool_choice) = anthropic_tool_choice(settings, tools) {
+            request["tool_choice"] = tool_choice;
+        }
+        Ok(request)
+    }
+
+    async fn send_messages(&self, request: Value) -> Result<AnthropicResponse, AgenticFlowError> {
+        let response = self.send_request(request, "messages").await?;
+        let response_text = body::read(response).await?;
+        parse_body::<AnthropicResponse>(&response_text)
+    }
+}
+
+/// Splits off the system prompts, joined by blank lines, and turns the rest into turns of
+/// content blocks. Consecutive messages of the same role are merged into one turn, as
+/// the results of several tool calls must be.
+fn anthropic_messages(messages: &[ChatMessage]) -> (Option<String>, Vec<Value>) {
+    let mut system = Vec::new();
+    let mut turns: Vec<(&'static str, Vec<Value>)> = Vec::new();
+
+    for message in messages {
+        let (role, blocks) = match &message.role {
+            Role::System => {
+                system.push(message.content.as_str());
+                continue;
+            }
+            Role::Assistant => ("assistant", assistant_blocks(message)),
+            Role::Tool => (
+                "user",
+                vec![json!({
+                    "type": "tool_result",
+                    "tool_use_id": message.tool_call_id.clone().unwrap_or_default(),
+                    "content": message.content,
+                })],
+            ),
+            Role::User | Role::Other(_) => ("user", user_blocks(message)),
+        };
+        match turns.last_mut() {
+            Some((last_role, last_blocks)) if *last_role == role => last_blocks.extend(blocks),
+            _ => turns.push((role, blocks)),
+        }
+    }
+
+    let turns = turns
+        .into_iter()
+        .map(|(role, content)| json!({ "role": role, "content": content }))
+        .collect();
+    let system = Some(system.join("\n\n")).filter(|system| !system.is_empty());
+    (system, turns)
+}
+
+fn text_block(text: &str) -> Value {
+    json!({ "type": "text", "text": text })
+}
+
+/// The images of a user message as base64 `image` blocks, followed by its text.
+fn user_blocks(message: &ChatMessage) -> Vec<Value> {
+    let mut blocks: Vec<Value> = message
+        .images
+        .iter()
+        .flatten()
+        .map(|image| {
+            json!({
+                "type": "image",
+                "source": { "type": "base64", "media_type": image.media_type, "data": image.data },
+            })
+        })
+        .collect();
+    if blocks.is_empty() || !message.content.is_empty() {
+        blocks.push(text_block(&message.content));
+    }
+    blocks
+}
+
+/// The text and tool calls of an assistant message. Calls without an id, as Ollama sends
+/// them, are numbered so their results can refer to them.
+fn assistant_blocks(message: &ChatMessage) -> Vec<Value> {
+    let mut blocks = Vec::new();
+    if !message.content.is_empty() {
+        blocks.push(text_block(&message.content));
+    }
+    for (index, tool_call) in message.tool_calls.iter().flatten().enumerate() {
+        let input = match &tool_call.function.arguments {
+            Value::Object(_) => tool_call.function.arguments.clone(),
+            _ => Value::Object(Map::new()),
+        };
+        blocks.push(json!({
+            "type": "tool_use",
+            "id": tool_call.id.clone().unwrap_or_else(|| format!("call_{}", index)),
+            "name": tool_call.function.name,
+            "input": input,
+        }));
+    }
+    blocks
+}
+
+/// The Messages API's `tool_choice`, sent only with tools and when it is not `auto` or
+/// parallel tool calls are forbidden.
+fn anthropic_tool_choice(settings: &GenerationSettings, tools: &[Value]) -> Option<Value> {
+    if tools.is_empty() {
+        return None;
+    }
+    let mut tool_choice = match &settings.tool_choice {
+        ToolChoice::Auto => json!({ "type": "auto" }),
+        ToolChoice::None => json!({ "type": "none" }),
+        ToolChoice::Required => json!({ "type": "any" }),
+        ToolChoice::Specific(name) => json!({ "type": "tool", "name": name }),
+    };
+    // `none` takes no `disable_parallel_tool_use`.
+    let parallel = settings
+        .parallel_tool_calls
+        .filter(|_| settings.tool_choice != ToolChoice::None);
+    match parallel {
+        Some(parallel) => tool_choice["disable_parallel_tool_use"] = json!(!parallel),
+        None if settings.tool_choice == ToolChoice::Auto => return None,
+        None => {}
+    }
+    Some(tool_choice)
+}
+
+/// Turns an OpenAI-style `{"type": "function", "function": {..}}` tool into Anthropic's
+/// `{"name", "description", "input_schema"}`. Other tools are passed through.
+fn anthropic_tool(tool: &Value) -> Value {
+    let Some(function) = tool.get("function") else {
+        return tool.clone();
+    };
+    let input_schema = match &function["parameters"] {
+        Value::Null => json!({ "type": "object" }),
+        parameters => parameters.clone(),
+    };
+    json!({
+        "name": function["name"],
+        "description": function["description"],
+        "input_schema": input_schema,
+    })
+}
+
+#[async_trait]
+impl LLMProvider for AnthropicProvider {
+    fn http_client(&self) -> &HttpClient {
+        &self.client
+    }
+
+    fn base_url(&self) -> &str {
+        &self.base_url
+    }
+
+    fn api_key(&self) -> Result<Option<String>, AgenticFlowError> {
+        self.api_key.require("Anthropic").map(Some)
+    }
+
+    fn request_headers(&self) -> Result<Vec<(&'static str, String)>, AgenticFlowError> {
+        Ok(vec![
+            ("x-api-key", self.api_key.require("Anthropic")?),
+            ("anthropic-version", API_VERSION.to_string()),
+        ])
+    }
+
+    fn model_name(&self) -> Option<&str> {
+        Some(&self.model)
+    }
+
+    fn supports_output_format(&self) -> bool {
+        false
+    }
+
+    async fn chat_completions(
+        &self,
+        messages: Vec<ChatMessage>,
+        settings: &GenerationSettings,
+        tools: Vec<Value>,
+    ) -> Result<Box<dyn ChatResponse>, AgenticFlowError> {
+        let request = self.request(&messages, settings, &tools)?;
+        Ok(Box::new(self.send_messages(request).await?))
+    }
+
+    async fn completion(
+        &self,
+        prompt: String,
+        settings: &GenerationSettings,
+    ) -> Result<Box<dyn CompletionResponse>, AgenticFlowError> {
+        let request = self.request(&[ChatMessage::user(prompt)], settings, &[])?;
+        Ok(Box::new(self.send_messages(request).await?))
+    }
+}
@@ -0,0 +1,51 @@
+//! Reading response bodies within the size limit of an [`LLMClient`](super::LLMClient),
+//! set with [`with_max_response_bytes`](super::LLMClient::with_max_response_bytes).
+
+use reqwest::Response;
+
+use crate::errors::AgenticFlowError;
+
+/// The size limit of response bodies when none is set: 32 MiB, far more than any answer
+/// or list of embeddings, but a bound on a server that keeps sending.
+pub const DEFAULT_MAX_RESPONSE_BYTES: usize = 32 * 1024 * 1024;
+
+tokio::task_local! {
+    static MAX_RESPONSE_BYTES: usize;
+}
+
+/// Runs `future` with the bodies read inside it limited to `max_bytes`.
+pub(super) async fn limited<F: Future>(max_bytes: usize, future: F) -> F::Output {
+    MAX_RESPONSE_BYTES.scope(max_bytes, future).await
+}
+
+/// The body of `response` as text, read up to the limit of the enclosing [`limited`], or
+/// [`DEFAULT_MAX_RESPONSE_BYTES`] outside one.
+///
+/// Fails with [`AgenticFlowError::NetworkError`] when the connection breaks before the
+/// end of the body, and with [`AgenticFlowError::ApiClientError`] as soon as the body,
+/// or the `Content-Length` announcing it, exceeds the limit.
+pub(super) async fn read(mut response: Response) -> Result<String, AgenticFlowError> {
+    let max_bytes = MAX_RESPONSE_BYTES
+        .try_with(|max_bytes| *max_bytes)
+        .unwrap_or(DEFAULT_MAX_RESPONSE_BYTES);
+    let too_large = || {
+        AgenticFlowError::ApiClientError(format!(
+            "the response body exceeds the limit of {} bytes",
+            max_bytes
+        ))
+    };
+    if response
+        .content_length()
+        .is_some_and(|length| length > max_bytes as u64)
+    {
+        return Err(too_large());
+    }
+    let mut body = Vec::new();
+    while let Some(chunk) = response.chunk().await? {
+        if body.len() + chunk.len() > max_bytes {
+            return Err(too_large());
+        }
+        body.extend_from_slice(&chunk);
+    }
+    Ok(String::from_utf8_lossy(&body).into_owned())
+}
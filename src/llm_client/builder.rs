@@ -0,0 +1,198 @@
+//! [`LLMClientBuilder`], which checks the settings of a client before building it.
+
+use std::{sync::Arc, time::Duration};
+
+use reqwest::header::{HeaderName, HeaderValue};
+
+use super::{LLMClient, LLMProvider, RetryPolicy, SamplingOptions};
+use crate::errors::AgenticFlowError;
+
+/// Builds an [`LLMClient`] from a provider and its settings, failing with
+/// [`AgenticFlowError::ApiClientError`] when they do not make sense together:
+///
+/// ```rust,no_run
+/// # use std::time::Duration;
+/// # use agentic_flow_lib::llm_client::{LLMClient, OllamaModel, OllamaProvider};
+/// # fn example() -> Result<(), agentic_flow_lib::errors::AgenticFlowError> {
+/// let client = LLMClient::builder()
+///     .provider(OllamaProvider::new(OllamaModel::Qwen3_8B))
+///     .temperature(0.2)
+///     .timeout(Duration::from_secs(60))
+///     .headers([("X-Team", "search")])
+///     .build()?;
+/// # Ok(())
+/// # }
+/// ```
+///
+/// Settings without a method here are set on the built client, with the `with_` methods
+/// of [`LLMClient`].
+#[derive(Default)]
+pub struct LLMClientBuilder {
+    provider: Option<Arc<dyn LLMProvider>>,
+    temperature: Option<f32>,
+    timeout: Option<Duration>,
+    max_tokens: Option<usize>,
+    sampling: Option<SamplingOptions>,
+    headers: Vec<(String, String)>,
+    retry: Option<RetryPolicy>,
+    max_concurrency: Option<usize>,
+    rate_limit: Option<u32>,
+    max_response_bytes: Option<usize>,
+}
+
+impl LLMClientBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The provider the client sends its requests to. Required.
+    pub fn provider<T>(mut self, provider: T) -> Self
+    where
+        T: LLMProvider + 'static,
+    {
+        self.provider = Some(Arc::new(provider));
+        self
+    }
+
+    pub(super) fn shared_provider(mut self, provider: Arc<dyn LLMProvider>) -> Self {
+        self.provider = Some(provider);
+        self
+    }
+
+    /// Between 0.0 and 2.0; 0.7 when unset.
+    pub fn temperature(mut self, temperature: f32) -> Self {
+        self.temperature = Some(temperature);
+        self
+    }
+
+    /// See [`LLMClient::with_timeout`]; must not be zero.
+    pub fn timeout(mut self, limit: Duration) -> Self {
+        self.timeout = Some(limit);
+        self
+    }
+
+    /// See [`LLMClient::with_max_tokens`]; must not be zero.
+    pub fn max_tokens(mut self, max_tokens: usize) -> Self {
+        self.max_tokens = Some(max_tokens);
+        self
+    }
+
+    /// See [`LLMClient::with_sampling`]; `top_p` must be between 0.0 and 1.0.
+    pub fn sampling(mut self, sampling: SamplingOptions) -> Self {
+        self.sampling = Some(sampling);
+        self
+    }
+
+    /// Adds headers to every request the provider sends, after its own, such as the
+    /// `Authorization` header. Names and values must be valid in HTTP.
+    pub fn headers<K, V>(mut self, headers: impl IntoIterator<Item = (K, V)>) -> Self
+    where
+        K: Into<String>,
+        V: Into<String>,
+    {
+        self.headers.extend(
+            headers
+                .into_iter()
+                .map(|(name, value)| (name.into(), value.into())),
+        );
+        self
+    }
+
+    /// See [`LLMClient::with_retry`].
+    pub fn retry(mut self, policy: RetryPolicy) -> Self {
+        self.retry = Some(policy);
+        self
+    }
+
+    /// See [`LLMClient::with_max_concurrency`]; must not be zero.
+    pub fn max_concurrency(mut self, max_in_flight: usize) -> Self {
+        self.max_concurrency = Some(max_in_flight);
+        self
+    }
+
+    /// See [`LLMClient::with_rate_limit`]; must not be zero.
+    pub fn rate_limit(mut self, requests_per_minute: u32) -> Self {
+        self.rate_limit = Some(requests_per_minute);
+        self
+    }
+
+    /// See [`LLMClient::with_max_response_bytes`]; must not be zero.
+    pub fn max_response_bytes(mut self, max_bytes: usize) -> Self {
+        self.max_response_bytes = Some(max_bytes);
+        self
+    }
+
+    pub fn build(self) -> Result<LLMClient, AgenticFlowError> {
+        self.check()?;
+        let Some(provider) = self.provider else {
+            return Err(invalid("a provider is required"));
+        };
+        let mut client = LLMClient::from_shared(provider);
+        if let Some(temperature) = self.temperature {
+            client = client.with_temperature(temperature);
+        }
+        if let Some(limit) = self.timeout {
+            client = client.with_timeout(limit);
+        }
+        client.generation.max_tokens = self.max_tokens;
+        if let Some(sampling) = self.sampling {
+            client = client.with_sampling(sampling);
+        }
+        client.headers = self.headers.into();
+        if let Some(policy) = self.retry {
+            client = client.with_retry(policy);
+        }
+        if let Some(max_in_flight) = self.max_concurrency {
+            client = client.with_max_concurrency(max_in_flight);
+        }
+        if let Some(requests_per_minute) = self.rate_limit {
+            client = client.with_rate_limit(requests_per_minute);
+        }
+        if let Some(max_bytes) = self.max_response_bytes {
+            client = client.with_max_response_bytes(max_bytes);
+        }
+        Ok(client)
+    }
+
+    fn check(&self) -> Result<(), AgenticFlowError> {
+        if let Some(temperature) = self.temperature.filter(|t| !(0.0..=2.0).contains(t)) {
+            return Err(invalid(format!(
+                "temperature must be between 0.0 and 2.0, got {}",
+                temperature
+            )));
+        }
+        let top_p = self.sampling.as_ref().and_then(|sampling| sampling.top_p);
+        if let Some(top_p) = top_p.filter(|p| !(0.0..=1.0).contains(p)) {
+            return Err(invalid(format!(
+                "top_p must be between 0.0 and 1.0, got {}",
+                top_p
+            )));
+        }
+        let zero = [
+            ("timeout", self.timeout == Some(Duration::ZERO)),
+            ("max_tokens", self.max_tokens == Some(0)),
+            ("max_concurrency", self.max_concurrency == Some(0)),
+            ("rate_limit", self.rate_limit == Some(0)),
+            ("max_response_bytes", self.max_response_bytes == Some(0)),
+        ];
+        if let Some((setting, _)) = zero.iter().find(|(_, is_zero)| *is_zero) {
+            return Err(invalid(format!("{} must be greater than zero", setting)));
+        }
+        for (name, value) in &self.headers {
+            if HeaderName::from_bytes(name.as_bytes()).is_err() {
+                return Err(invalid(format!("'{}' is not a valid header name", name)));
+            }
+            if HeaderValue::from_str(value).is_err() {
+                return Err(invalid(format!(
+                    "the value of header '{}' is not valid",
+                    name
+                )));
+            }
+        }
+        Ok(())
+    }
+}
+
+fn invalid(reason: impl Into<String>) -> AgenticFlowError {
+    AgenticFlowError::ApiClientError(format!("invalid LLM client: {}", reason.into()))
+}
@@ -0,0 +1,222 @@
+//! Answering repeated chat requests from memory.
+
+use std::{
+    collections::HashMap,
+    collections::hash_map::DefaultHasher,
+    hash::{Hash, Hasher},
+    sync::{
+        Arc, Mutex,
+        atomic::{AtomicU64, Ordering},
+    },
+    time::{Duration, Instant},
+};
+
+use async_trait::async_trait;
+use serde_json::{Value, json};
+
+use super::{GenerationSettings, LLMLayer, LLMRequest, LLMResponse, Next};
+use crate::{
+    errors::AgenticFlowError,
+    model::{ChatMessage, ChatResponse, FinishReason},
+};
+
+/// How [`LLMClient::with_cache`](super::LLMClient::with_cache) caches chat responses.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CacheConfig {
+    /// Most responses kept; the least recently used one makes room for a new one.
+    pub capacity: usize,
+    /// How long a response stays valid; forever when unset.
+    pub ttl: Option<Duration>,
+    /// Also caches requests with a temperature above 0. Off by default, since asking
+    /// again would give a different answer.
+    pub cache_sampled: bool,
+}
+
+impl Default for CacheConfig {
+    fn default() -> Self {
+        Self {
+            capacity: 256,
+            ttl: None,
+            cache_sampled: false,
+        }
+    }
+}
+
+/// How often the cache answered a request.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct CacheStats {
+    pub hits: u64,
+    pub misses: u64,
+}
+
+/// A response answered from the cache. It spent no tokens, so it has no usage.
+#[derive(Debug, Clone)]
+struct CachedResponse {
+    message: ChatMessage,
+    finish_reason: Option<FinishReason>,
+    model: Option<String>,
+}
+
+impl ChatResponse for CachedResponse {
+    fn message(&self) -> &ChatMessage {
+        &self.message
+    }
+
+    fn finish_reason(&self) -> Option<FinishReason> {
+        self.finish_reason.clone()
+    }
+
+    fn model(&self) -> Option<&str> {
+        self.model.as_deref()
+    }
+}
+
+struct Entry {
+    response: CachedResponse,
+    stored: Instant,
+    used: Instant,
+}
+
+/// The layer of [`LLMClient::with_cache`](super::LLMClient::with_cache): answers a chat
+/// request identical to an earlier one from memory, without passing it on. Completion
+/// requests pass through. Clones share the cache.
+#[derive(Clone)]
+pub struct CacheLayer {
+    config: CacheConfig,
+    entries: Arc<Mutex<HashMap<u64, Entry>>>,
+    hits: Arc<AtomicU64>,
+    misses: Arc<AtomicU64>,
+}
+
+impl CacheLayer {
+    pub fn new(config: CacheConfig) -> Self {
+        Self {
+            config,
+            entries: Arc::default(),
+            hits: Arc::default(),
+            misses: Arc::default(),
+        }
+    }
+
+    /// How often the cache answered a request.
+    pub fn stats(&self) -> CacheStats {
+        CacheStats {
+            hits: self.hits.load(Ordering::Relaxed),
+            misses: self.misses.load(Ordering::Relaxed),
+        }
+    }
+
+    /// The key of a request, or `None` if it is not cached.
+    fn key(
+        &self,
+        model: Option<&str>,
+        messages: &[ChatMessage],
+        tools: &[Value],
+        settings: &GenerationSettings,
+    ) -> Option<u64> {
+        if settings.temperature > 0.0 && !self.config.cache_sampled {
+            return None;
+        }
+        let request = json!({
+            "model": model,
+            "messages": messages,
+            "tools": tools,
+            "settings": settings.to_value(),
+        });
+        let mut hasher = DefaultHasher::new();
+        request.to_string().hash(&mut hasher);
+        Some(hasher.finish())
+    }
+
+    fn get(&self, key: u64) -> Option<Box<dyn ChatResponse>> {
+        let mut entries = self.lock();
+        let expired = entries
+            .get(&key)
+            .is_some_and(|entry| self.is_expired(entry));
+        if expired {
+            entries.remove(&key);
+        }
+        match entries.get_mut(&key) {
+            Some(entry) => {
+                entry.used = Instant::now();
+                self.hits.fetch_add(1, Ordering::Relaxed);
+                Some(Box::new(entry.response.clone()))
+            }
+            None => {
+                self.misses.fetch_add(1, Ordering::Relaxed);
+                None
+            }
+        }
+    }
+
+    fn insert(&self, key: u64, response: &dyn ChatResponse) {
+        if self.config.capacity == 0 {
+            return;
+        }
+        let mut entries = self.lock();
+        entries.retain(|_, entry| !self.is_expired(entry));
+        if entries.len() >= self.config.capacity && !entries.contains_key(&key) {
+            let least_recent = entries
+                .iter()
+                .min_by_key(|(_, entry)| entry.used)
+                .map(|(key, _)| *key);
+            if let Some(least_recent) = least_recent {
+                entries.remove(&least_recent);
+            }
+        }
+        let now = Instant::now();
+        entries.insert(
+            key,
+            Entry {
+                response: CachedResponse {
+                    message: response.message().clone(),
+                    finish_reason: response.finish_reason(),
+                    model: response.model().map(str::to_string),
+                },
+                stored: now,
+                used: now,
+            },
+        );
+    }
+
+    fn is_expired(&self, entry: &Entry) -> bool {
+        self.config
+            .ttl
+            .is_some_and(|ttl| entry.stored.elapsed() >= ttl)
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, HashMap<u64, Entry>> {
+        self.entries
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+#[async_trait]
+impl LLMLayer for CacheLayer {
+    async fn call(
+        &self,
+        request: LLMRequest,
+        next: Next<'_>,
+    ) -> Result<LLMResponse, AgenticFlowError> {
+        let key = match &request {
+            LLMRequest::Chat {
+                messages,
+                tools,
+                settings,
+            } => self.key(next.model_name(), messages, tools, settings),
+            LLMRequest::Completion { .. } => None,
+        };
+        let Some(key) = key else {
+            return next.run(request).await;
+        };
+        if let Some(response) = self.get(key) {
+            return Ok(LLMResponse::Chat(response));
+        }
+        let response = next.run(request).await?;
+        if let LLMResponse::Chat(chat) = &response {
+            self.insert(key, chat.as_ref());
+        }
+        Ok(response)
+    }
+}
@@ -0,0 +1,132 @@
+//! What models can do: call tools, answer in JSON, see images, and how long a context
+//! they take.
+
+use serde::Deserialize;
+use serde_json::Value;
+
+/// What a model supports, as [`LLMClient::capabilities`](super::LLMClient::capabilities)
+/// reports it. The default is what the crate assumes of models it does not know: tool
+/// calls and JSON answers, but no images, and an unknown context length.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ModelCapabilities {
+    /// Whether the model calls tools, which planners need.
+    pub supports_tools: bool,
+    /// Whether requests can ask for an [`OutputFormat`](super::OutputFormat).
+    pub supports_json_mode: bool,
+    /// Whether messages can show the model images.
+    pub supports_vision: bool,
+    /// The tokens of the model's context window, if known.
+    pub context_length: Option<usize>,
+}
+
+impl Default for ModelCapabilities {
+    fn default() -> Self {
+        Self {
+            supports_tools: true,
+            supports_json_mode: true,
+            supports_vision: false,
+            context_length: None,
+        }
+    }
+}
+
+const fn capabilities(tools: bool, json: bool, vision: bool, context: usize) -> ModelCapabilities {
+    ModelCapabilities {
+        supports_tools: tools,
+        supports_json_mode: json,
+        supports_vision: vision,
+        context_length: Some(context),
+    }
+}
+
+/// The models the crate knows, by name or, for Ollama, by family without the tag.
+const BUILT_IN: &[(&str, ModelCapabilities)] = &[
+    // Ollama
+    ("gpt-oss", capabilities(true, true, false, 131_072)),
+    ("gemma2", capabilities(false, true, false, 8_192)),
+    ("gemma3", capabilities(false, true, true, 131_072)),
+    ("qwen3", capabilities(true, true, false, 40_960)),
+    ("qwen2.5", capabilities(true, true, false, 32_768)),
+    ("llama3.1", capabilities(true, true, false, 131_072)),
+    ("llama3.2", capabilities(true, true, false, 131_072)),
+    ("mistral", capabilities(true, true, false, 32_768)),
+    ("llava", capabilities(false, true, true, 4_096)),
+    // OpenAI, also through OpenRouter
+    ("gpt-4o", capabilities(true, true, true, 128_000)),
+    ("gpt-4o-mini", capabilities(true, true, true, 128_000)),
+    ("o3-mini", capabilities(true, true, false, 200_000)),
+    // Anthropic, whose API takes no output format
+    ("claude-opus-4-0", capabilities(true, false, true, 200_000)),
+    (
+        "claude-sonnet-4-0",
+        capabilities(true, false, true, 200_000),
+    ),
+    (
+        "claude-3-5-haiku-latest",
+        capabilities(true, false, false, 200_000),
+    ),
+    // Gemini, also through OpenRouter
+    (
+        "gemini-2.0-flash",
+        capabilities(true, true, true, 1_048_576),
+    ),
+    (
+        "gemini-2.0-flash-001",
+        capabilities(true, true, true, 1_048_576),
+    ),
+    ("gemini-1.5-pro", capabilities(true, true, true, 2_097_152)),
+    // Groq
+    (
+        "llama-3.3-70b-versatile",
+        capabilities(true, true, false, 131_072),
+    ),
+];
+
+impl ModelCapabilities {
+    /// What the crate knows of the model named `model`, such as `qwen3:8b` or
+    /// `openai/gpt-4o-mini`: looked up by the full name, then without an Ollama tag and
+    /// without an OpenRouter vendor. `None` for models it does not know.
+    pub fn built_in(model: &str) -> Option<Self> {
+        let model = model.to_ascii_lowercase();
+        let unvendored = model.rsplit('/').next().unwrap_or(&model);
+        [model.as_str(), unvendored]
+            .into_iter()
+            .flat_map(|name| [name, name.split(':').next().unwrap_or(name)])
+            .find_map(|name| {
+                BUILT_IN
+                    .iter()
+                    .find(|(known, _)| *known == name)
+                    .map(|(_, capabilities)| *capabilities)
+            })
+    }
+}
+
+/// The answer of Ollama's `api/show`.
+#[derive(Deserialize)]
+pub(super) struct OllamaShow {
+    /// What the model can do, e.g. `completion`, `tools` and `vision`; missing from
+    /// older servers.
+    #[serde(default)]
+    capabilities: Option<Vec<String>>,
+    #[serde(default)]
+    model_info: serde_json::Map<String, Value>,
+}
+
+impl OllamaShow {
+    /// `known` updated with what the server reported.
+    pub(super) fn apply(&self, mut known: ModelCapabilities) -> ModelCapabilities {
+        if let Some(reported) = &self.capabilities {
+            known.supports_tools = reported.iter().any(|capability| capability == "tools");
+            known.supports_vision = reported.iter().any(|capability| capability == "vision");
+        }
+        let context_length = self
+            .model_info
+            .iter()
+            .find(|(key, _)| key.ends_with(".context_length"))
+            .and_then(|(_, value)| value.as_u64());
+        if let Some(context_length) = context_length {
+            known.context_length = Some(context_length as usize);
+        }
+        known
+    }
+}
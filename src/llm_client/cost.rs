@@ -0,0 +1,148 @@
+//! Tokens and cost accumulated by an [`LLMClient`](super::LLMClient) and its clones.
+
+use std::{
+    collections::{BTreeMap, HashMap},
+    sync::{Arc, Mutex},
+};
+
+use crate::model::Usage;
+
+/// What a model costs, in USD per million tokens.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ModelPrice {
+    pub prompt_per_million: f64,
+    pub completion_per_million: f64,
+}
+
+impl ModelPrice {
+    pub fn new(prompt_per_million: f64, completion_per_million: f64) -> Self {
+        Self {
+            prompt_per_million,
+            completion_per_million,
+        }
+    }
+
+    pub fn cost(&self, usage: &Usage) -> f64 {
+        (usage.prompt_tokens as f64 * self.prompt_per_million
+            + usage.completion_tokens as f64 * self.completion_per_million)
+            / 1_000_000.0
+    }
+}
+
+/// Prices keyed by model name, as the client sends it, e.g. `openai/gpt-4o-mini`.
+pub type PriceTable = HashMap<String, ModelPrice>;
+
+/// The calls made to one model.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ModelUsage {
+    pub calls: usize,
+    /// The sum of the usage the responses reported; `None` if none did.
+    pub usage: Option<Usage>,
+    /// In USD, when the price table has the model.
+    pub cost_usd: Option<f64>,
+}
+
+/// What [`LLMClient::usage_report`](super::LLMClient::usage_report) returns.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct UsageReport {
+    pub models: BTreeMap<String, ModelUsage>,
+}
+
+impl UsageReport {
+    pub fn calls(&self) -> usize {
+        self.models.values().map(|model| model.calls).sum()
+    }
+
+    /// The usage of all models, or `None` if no response reported any.
+    pub fn total_usage(&self) -> Option<Usage> {
+        self.models
+            .values()
+            .filter_map(|model| model.usage)
+            .reduce(|sum, usage| sum + usage)
+    }
+
+    /// The cost of the models with a price, or `None` if none has one.
+    pub fn total_cost_usd(&self) -> Option<f64> {
+        self.models
+            .values()
+            .filter_map(|model| model.cost_usd)
+            .reduce(|sum, cost| sum + cost)
+    }
+
+    /// Adds the calls of `other` to those of the same models here.
+    pub(crate) fn merge(&mut self, other: UsageReport) {
+        for (model, usage) in other.models {
+            let entry = self.models.entry(model).or_default();
+            entry.calls += usage.calls;
+            entry.usage = add(entry.usage, usage.usage, |sum, usage| sum + usage);
+            entry.cost_usd = add(entry.cost_usd, usage.cost_usd, |sum, cost| sum + cost);
+        }
+    }
+}
+
+fn add<T>(sum: Option<T>, value: Option<T>, plus: impl FnOnce(T, T) -> T) -> Option<T> {
+    match (sum, value) {
+        (Some(sum), Some(value)) => Some(plus(sum, value)),
+        (sum, value) => sum.or(value),
+    }
+}
+
+/// Records the usage of every successful request. Clones share the records, so the
+/// clones of a client, such as those a planner makes, add to the same report.
+#[derive(Debug, Clone, Default)]
+pub struct CostTracker {
+    prices: Arc<PriceTable>,
+    models: Arc<Mutex<BTreeMap<String, ModelUsage>>>,
+}
+
+impl CostTracker {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// A tracker that prices the models in `prices`.
+    pub fn with_prices(prices: PriceTable) -> Self {
+        Self {
+            prices: Arc::new(prices),
+            ..Self::default()
+        }
+    }
+
+    pub fn record(&self, model: &str, usage: Option<Usage>) {
+        let mut models = self
+            .models
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        let entry = models.entry(model.to_string()).or_default();
+        entry.calls += 1;
+        if let Some(usage) = usage {
+            entry.usage = Some(match entry.usage {
+                Some(sum) => sum + usage,
+                None => usage,
+            });
+            if let Some(price) = self.prices.get(model) {
+                entry.cost_usd = Some(entry.cost_usd.unwrap_or(0.0) + price.cost(&usage));
+            }
+        }
+    }
+
+    /// What `usage` of `model` cost, when the price table has the model.
+    pub(crate) fn cost(&self, model: &str, usage: &Usage) -> Option<f64> {
+        self.prices.get(model).map(|price| price.cost(usage))
+    }
+
+    /// Whether both record into the same report, such as the trackers of two clones.
+    pub(crate) fn shares_records_with(&self, other: &CostTracker) -> bool {
+        Arc::ptr_eq(&self.models, &other.models)
+    }
+
+    pub fn report(&self) -> UsageReport {
+        let models = self
+            .models
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        UsageReport {
+            models: models.clone(),
+        }
+    }
+}
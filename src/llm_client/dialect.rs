@@ -0,0 +1,132 @@
+//! What each provider family expects chat messages and sampling options to look like on
+//! the wire.
+
+use serde_json::{Map, Value, json};
+
+use super::SamplingOptions;
+use crate::model::ChatMessage;
+
+/// The sampling options OpenAI and most OpenAI-compatible servers accept; there is no
+/// `top_k` or repetition penalty.
+pub(super) const OPENAI_SAMPLING: &[(&str, &str)] = &[
+    ("top_p", "top_p"),
+    ("seed", "seed"),
+    ("frequency_penalty", "frequency_penalty"),
+    ("presence_penalty", "presence_penalty"),
+];
+
+/// OpenRouter passes every option on to the model, with `repeat_penalty` named
+/// `repetition_penalty`.
+pub(super) const OPENROUTER_SAMPLING: &[(&str, &str)] = &[
+    ("top_p", "top_p"),
+    ("top_k", "top_k"),
+    ("seed", "seed"),
+    ("repeat_penalty", "repetition_penalty"),
+    ("frequency_penalty", "frequency_penalty"),
+    ("presence_penalty", "presence_penalty"),
+];
+
+/// Ollama's `options` and llama.cpp's `/completion` use the names of
+/// [`SamplingOptions`].
+pub(super) const OLLAMA_SAMPLING: &[(&str, &str)] = &[
+    ("top_p", "top_p"),
+    ("top_k", "top_k"),
+    ("seed", "seed"),
+    ("repeat_penalty", "repeat_penalty"),
+    ("frequency_penalty", "frequency_penalty"),
+    ("presence_penalty", "presence_penalty"),
+];
+
+/// Adds the options of `sampling` that `names` supports to the top level of `request`.
+pub(super) fn with_sampling(
+    request: &mut Value,
+    sampling: &SamplingOptions,
+    names: &[(&str, &str)],
+) {
+    if let Some(object) = request.as_object_mut() {
+        object.extend(sampling.wire_fields(names));
+    }
+}
+
+/// Serializes `messages` for OpenRouter and OpenAI-compatible servers, which take tool
+/// call arguments as a JSON-encoded string, and images as `image_url` parts of an array
+/// content. Messages without images keep their string content.
+pub(super) fn openai_messages(messages: &[ChatMessage]) -> Vec<Value> {
+    messages
+        .iter()
+        .map(|message| {
+            let mut value = json!(message);
+            if let Some(object) = value.as_object_mut() {
+                object.remove("images");
+            }
+            if let Some(images) = message.images.as_ref().filter(|images| !images.is_empty()) {
+                let text = Some(json!({ "type": "text", "text": message.content }))
+                    .filter(|_| !message.content.is_empty());
+                let parts: Vec<Value> = text
+                    .into_iter()
+                    .chain(images.iter().map(|image| {
+                        json!({ "type": "image_url", "image_url": { "url": image.data_url() } })
+                    }))
+                    .collect();
+                value["content"] = json!(parts);
+            }
+            for tool_call in tool_calls_mut(&mut value) {
+                let arguments = tool_call
+                    .get_mut("function")
+                    .and_then(|function| function.get_mut("arguments"))
+                    .filter(|arguments| !arguments.is_string());
+                if let Some(arguments) = arguments {
+                    *arguments = Value::String(arguments.to_string());
+                }
+            }
+            value
+        })
+        .collect()
+}
+
+/// Serializes `messages` for Ollama's `api/chat`. Ollama matches tool results to calls by
+/// order, so the call ids and `tool_call_id` are left out, and the tool of a result is
+/// named `tool_name`. Images are sent as their base64 data.
+pub(super) fn ollama_messages(messages: &[ChatMessage]) -> Vec<Value> {
+    messages
+        .iter()
+        .map(|message| {
+            let mut value = json!(message);
+            if let Some(object) = value.as_object_mut() {
+                object.remove("tool_call_id");
+                if let Some(name) = object.remove("name") {
+                    object.insert("tool_name".to_string(), name);
+                }
+                if let Some(images) = &message.images {
+                    let data: Vec<&str> = images.iter().map(|image| image.data.as_str()).collect();
+                    object.insert("images".to_string(), json!(data));
+                }
+            }
+            for tool_call in tool_calls_mut(&mut value) {
+                tool_call.remove("id");
+                tool_call.remove("type");
+            }
+            value
+        })
+        .collect()
+}
+
+/// Leaves out the `thinking` field, for providers that reject message fields they do not
+/// know.
+pub(super) fn without_thinking(mut messages: Vec<Value>) -> Vec<Value> {
+    for message in &mut messages {
+        if let Some(object) = message.as_object_mut() {
+            object.remove("thinking");
+        }
+    }
+    messages
+}
+
+fn tool_calls_mut(message: &mut Value) -> impl Iterator<Item = &mut Map<String, Value>> {
+    message
+        .get_mut("tool_calls")
+        .and_then(Value::as_array_mut)
+        .into_iter()
+        .flatten()
+        .filter_map(Value::as_object_mut)
+}
@@ -0,0 +1,62 @@
+//! Embedding requests: Ollama's `api/embed` and the OpenAI-style `embeddings` endpoint
+//! OpenRouter serves.
+
+use serde::Deserialize;
+
+use crate::errors::AgenticFlowError;
+
+/// Inputs per `api/embed` request. Ollama embeds a batch on one model instance, so large
+/// batches mostly make single requests long enough to hit timeouts.
+pub(super) const OLLAMA_BATCH_SIZE: usize = 64;
+
+/// Inputs per OpenAI-style `embeddings` request, well below OpenAI's limit of 2048.
+pub(super) const OPENAI_BATCH_SIZE: usize = 256;
+
+/// The answer of Ollama's `api/embed`: a vector per input, in order.
+#[derive(Deserialize)]
+pub(super) struct OllamaEmbeddings {
+    pub(super) embeddings: Vec<Vec<f32>>,
+}
+
+/// The answer of an OpenAI-style `embeddings` endpoint, where each vector names the input
+/// it belongs to.
+#[derive(Deserialize)]
+pub(super) struct OpenAIEmbeddings {
+    data: Vec<OpenAIEmbedding>,
+}
+
+#[derive(Deserialize)]
+struct OpenAIEmbedding {
+    #[serde(default)]
+    index: usize,
+    embedding: Vec<f32>,
+}
+
+impl OpenAIEmbeddings {
+    /// The vectors in the order of the inputs.
+    pub(super) fn into_vectors(mut self) -> Vec<Vec<f32>> {
+        self.data.sort_by_key(|embedding| embedding.index);
+        self.data
+            .into_iter()
+            .map(|embedding| embedding.embedding)
+            .collect()
+    }
+}
+
+/// Parses an embeddings response body.
+pub(super) fn parse<T: for<'de> Deserialize<'de>>(body: &str) -> Result<T, AgenticFlowError> {
+    serde_json::from_str(body).map_err(|error| AgenticFlowError::unparseable_body(error, body))
+}
+
+/// Fails when a provider answered a batch of `inputs` with another number of vectors,
+/// which would pair the later inputs with the wrong vectors.
+pub(super) fn check_count(inputs: usize, vectors: &[Vec<f32>]) -> Result<(), AgenticFlowError> {
+    if vectors.len() == inputs {
+        return Ok(());
+    }
+    Err(AgenticFlowError::ParseError(format!(
+        "expected {} embeddings, got {}",
+        inputs,
+        vectors.len()
+    )))
+}
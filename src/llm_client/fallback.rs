@@ -0,0 +1,82 @@
+//! Serving a request from the next client of the chain when one fails.
+
+use serde_json::Value;
+
+use crate::{
+    errors::{AgenticFlowError, ErrorKind},
+    model::{ChatMessage, ChatResponse, CompletionResponse, FinishReason, ProviderStats, Usage},
+};
+
+/// Whether the next client of the chain should get the request: the provider could not
+/// be reached, took too long or failed on its side. A request it rejected would be
+/// rejected the same way by the next one.
+pub(super) fn falls_through(error: &AgenticFlowError) -> bool {
+    matches!(error.kind(), ErrorKind::Transient | ErrorKind::Timeout)
+}
+
+/// A response served by a fallback, remembering which one.
+#[derive(Debug)]
+pub(super) struct FallbackResponse<R: ?Sized> {
+    pub(super) response: Box<R>,
+    pub(super) fallback: usize,
+}
+
+impl ChatResponse for FallbackResponse<dyn ChatResponse> {
+    fn message(&self) -> &ChatMessage {
+        self.response.message()
+    }
+
+    fn finish_reason(&self) -> Option<FinishReason> {
+        self.response.finish_reason()
+    }
+
+    fn model(&self) -> Option<&str> {
+        self.response.model()
+    }
+
+    fn usage(&self) -> Option<Usage> {
+        self.response.usage()
+    }
+
+    fn stats(&self) -> Option<ProviderStats> {
+        self.response.stats()
+    }
+
+    fn raw(&self) -> Option<&Value> {
+        self.response.raw()
+    }
+
+    fn take_raw(&mut self) -> Option<Value> {
+        self.response.take_raw()
+    }
+
+    fn fallback(&self) -> Option<usize> {
+        Some(self.fallback)
+    }
+}
+
+impl CompletionResponse for FallbackResponse<dyn CompletionResponse> {
+    fn response(&self) -> &str {
+        self.response.response()
+    }
+
+    fn usage(&self) -> Option<Usage> {
+        self.response.usage()
+    }
+
+    fn stats(&self) -> Option<ProviderStats> {
+        self.response.stats()
+    }
+
+    fn raw(&self) -> Option<&Value> {
+        self.response.raw()
+    }
+
+    fn take_raw(&mut self) -> Option<Value> {
+        self.response.take_raw()
+    }
+
+    fn fallback(&self) -> Option<usize> {
+        Some(self.fallback)
+    }
+}
@@ -0,0 +1,303 @@
+//! The Gemini API at `generativelanguage.googleapis.com`.
+//!
+//! Conversations are `contents` of `user` and `model` turns made of `parts`; tools are
+//! `functionDeclarations`, and tool calls and their results are `functionCall` and
+//! `functionResponse` parts.
+
+use std::fmt;
+
+use async_trait::async_trait;
+use reqwest::Client as HttpClient;
+use serde_json::{Map, Value, json};
+
+use super::{
+    ApiKeySource, GenerationSettings, LLMProvider, OutputFormat, ToolChoice, body, parse_body,
+};
+use crate::{errors::AgenticFlowError, model::*};
+
+/// JSON Schema keys Gemini rejects in function parameters.
+const UNSUPPORTED_SCHEMA_KEYS: [&str; 2] = ["$schema", "additionalProperties"];
+
+/// The sampling options `generationConfig` accepts, under Gemini's names.
+const SAMPLING: &[(&str, &str)] = &[
+    ("top_p", "topP"),
+    ("top_k", "topK"),
+    ("seed", "seed"),
+    ("frequency_penalty", "frequencyPenalty"),
+    ("presence_penalty", "presencePenalty"),
+];
+
+#[derive(Debug, Clone)]
+pub enum GeminiModel {
+    Flash2,
+    Pro15,
+    Custom(String),
+}
+
+impl fmt::Display for GeminiModel {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            GeminiModel::Flash2 => "gemini-2.0-flash",
+            GeminiModel::Pro15 => "gemini-1.5-pro",
+            GeminiModel::Custom(name) => name,
+        })
+    }
+}
+
+pub(super) struct GeminiProvider {
+    client: HttpClient,
+    base_url: String,
+    model: String,
+    api_key: ApiKeySource,
+}
+
+impl GeminiProvider {
+    pub fn new(model: GeminiModel, api_key: ApiKeySource) -> Self {
+        Self::with_client(HttpClient::new(), model, api_key)
+    }
+
+    pub(super) fn with_client(
+        client: HttpClient,
+        model: GeminiModel,
+        api_key: ApiKeySource,
+    ) -> Self {
+        Self {
+            client,
+            base_url: "https://generativelanguage.googleapis.com/v1beta".to_string(),
+            model: model.to_string(),
+            api_key,
+        }
+    }
+
+    fn request(
+        &self,
+        messages: &[ChatMessage],
+        settings: &GenerationSettings,
+        tools: &[Value],
+    ) -> Value {
+        let (system, contents) = gemini_contents(messages);
+        let mut request = json!({
+            "contents": contents,
+            "generationConfig": { "temperature": settings.temperature },
+        });
+        if let Some(max_tokens) = settings.max_tokens {
+            request["generationConfig"]["maxOutputTokens"] = json!(max_tokens);
+        }
+        if let Some(config) = request["generationConfig"].as_object_mut() {
+            config.extend(settings.sampling.wire_fields(SAMPLING));
+        }
+        if let Some(stop) = &settings.stop {
+            request["generationConfig"]["stopSequences"] = json!(stop);
+        }
+        match &settings.format {
+            OutputFormat::None => {}
+            OutputFormat::Json => {
+                request["generationConfig"]["responseMimeType"] = json!("application/json");
+            }
+            OutputFormat::Schema(schema) => {
+                request["generationConfig"]["responseMimeType"] = json!("application/json");
+                request["generationConfig"]["responseSchema"] = supported_schema(schema);
+            }
+        }
+        if let Some(system) = system {
+            request["systemInstruction"] = json!({ "parts": [{ "text": system }] });
+        }
+        if !tools.is_empty() {
+            let declarations: Vec<Value> = tools.iter().map(function_declaration).collect();
+            request["tools"] = json!([{ "functionDeclarations": declarations }]);
+        }
+        if let Some(config) = function_calling_config(&settings.tool_choice, tools) {
+            request["toolConfig"] = json!({ "functionCallingConfig": config });
+        }
+        request
+    }
+
+    async fn generate(&self, request: Value) -> Result<GeminiResponse, AgenticFlowError> {
+        let endpoint = format!("models/{}:generateContent", self.model);
+        let response = self.send_request(request, &endpoint).await?;
+        let response_text = body::read(response).await?;
+        parse_body::<GeminiResponse>(&response_text)
+    }
+}
+
+/// Splits off the system prompts, joined by blank lines, and turns the rest into turns
+/// of parts. Consecutive messages of the same role are merged into one turn.
+fn gemini_contents(messages: &[ChatMessage]) -> (Option<String>, Vec<Value>) {
+    let mut system = Vec::new();
+    let mut turns: Vec<(&'static str, Vec<Value>)> = Vec::new();
+
+    for (position, message) in messages.iter().enumerate() {
+        let (role, parts) = match &message.role {
+            Role::System => {
+                system.push(message.content.as_str());
+                continue;
+            }
+            Role::Assistant => ("model", model_parts(message)),
+            Role::Tool => (
+                "user",
+                vec![json!({
+                    "functionResponse": {
+                        "name": result_tool_name(message, &messages[..position]),
+                        "response": result_object(&message.content),
+                    }
+                })],
+            ),
+            Role::User | Role::Other(_) => ("user", user_parts(message)),
+        };
+        match turns.last_mut() {
+            Some((last_role, last_parts)) if *last_role == role => last_parts.extend(parts),
+            _ => turns.push((role, parts)),
+        }
+    }
+
+    let turns = turns
+        .into_iter()
+        .map(|(role, parts)| json!({ "role": role, "parts": parts }))
+        .collect();
+    let system = Some(system.join("\n\n")).filter(|system| !system.is_empty());
+    (system, turns)
+}
+
+/// The text of a user message, followed by its images as `inlineData` parts.
+fn user_parts(message: &ChatMessage) -> Vec<Value> {
+    let images: Vec<Value> = message
+        .images
+        .iter()
+        .flatten()
+        .map(|image| json!({ "inlineData": { "mimeType": image.media_type, "data": image.data } }))
+        .collect();
+    let text = Some(json!({ "text": message.content }))
+        .filter(|_| !message.content.is_empty() || images.is_empty());
+    text.into_iter().chain(images).collect()
+}
+
+fn model_parts(message: &ChatMessage) -> Vec<Value> {
+    let mut parts = Vec::new();
+    if !message.content.is_empty() {
+        parts.push(json!({ "text": message.content }));
+    }
+    for tool_call in message.tool_calls.iter().flatten() {
+        let args = match &tool_call.function.arguments {
+            Value::Object(_) => tool_call.function.arguments.clone(),
+            _ => Value::Object(Map::new()),
+        };
+        parts.push(json!({
+            "functionCall": { "name": tool_call.function.name, "args": args }
+        }));
+    }
+    parts
+}
+
+/// Gemini matches results to calls by function name: the result's `name`, or else the
+/// name of the earlier call with its `tool_call_id`.
+fn result_tool_name(result: &ChatMessage, earlier: &[ChatMessage]) -> String {
+    if let Some(name) = &result.name {
+        return name.clone();
+    }
+    earlier
+        .iter()
+        .flat_map(|message| message.tool_calls.iter().flatten())
+        .find(|tool_call| tool_call.id.is_some() && tool_call.id == result.tool_call_id)
+        .map(|tool_call| tool_call.function.name.clone())
+        .unwrap_or_default()
+}
+
+/// A `functionResponse` must be an object: a result that is not a JSON object is
+/// wrapped as `{"content": ...}`.
+fn result_object(content: &str) -> Value {
+    match serde_json::from_str::<Value>(content) {
+        Ok(object @ Value::Object(_)) => object,
+        _ => json!({ "content": content }),
+    }
+}
+
+/// The `functionCallingConfig` for `tool_choice`, sent only with tools and when it is not
+/// `Auto`: `ANY` requires a call, of `allowedFunctionNames` for a specific tool.
+fn function_calling_config(tool_choice: &ToolChoice, tools: &[Value]) -> Option<Value> {
+    if tools.is_empty() {
+        return None;
+    }
+    match tool_choice {
+        ToolChoice::Auto => None,
+        ToolChoice::None => Some(json!({ "mode": "NONE" })),
+        ToolChoice::Required => Some(json!({ "mode": "ANY" })),
+        ToolChoice::Specific(name) => Some(json!({
+            "mode": "ANY",
+            "allowedFunctionNames": [name],
+        })),
+    }
+}
+
+/// Turns an OpenAI-style `{"type": "function", "function": {..}}` tool into a function
+/// declaration. Gemini rejects an object schema without properties, so such a schema is
+/// left out.
+fn function_declaration(tool: &Value) -> Value {
+    let function = tool.get("function").unwrap_or(tool);
+    let mut declaration = json!({
+        "name": function["name"],
+        "description": function["description"],
+    });
+    let has_properties = function["parameters"]["properties"]
+        .as_object()
+        .is_some_and(|properties| !properties.is_empty());
+    if has_properties {
+        declaration["parameters"] = supported_schema(&function["parameters"]);
+    }
+    declaration
+}
+
+fn supported_schema(schema: &Value) -> Value {
+    match schema {
+        Value::Object(object) => Value::Object(
+            object
+                .iter()
+                .filter(|(key, _)| !UNSUPPORTED_SCHEMA_KEYS.contains(&key.as_str()))
+                .map(|(key, value)| (key.clone(), supported_schema(value)))
+                .collect(),
+        ),
+        Value::Array(items) => Value::Array(items.iter().map(supported_schema).collect()),
+        other => other.clone(),
+    }
+}
+
+#[async_trait]
+impl LLMProvider for GeminiProvider {
+    fn http_client(&self) -> &HttpClient {
+        &self.client
+    }
+
+    fn base_url(&self) -> &str {
+        &self.base_url
+    }
+
+    fn api_key(&self) -> Result<Option<String>, AgenticFlowError> {
+        self.api_key.require("Gemini").map(Some)
+    }
+
+    fn request_headers(&self) -> Result<Vec<(&'static str, String)>, AgenticFlowError> {
+        Ok(vec![("x-goog-api-key", self.api_key.require("Gemini")?)])
+    }
+
+    fn model_name(&self) -> Option<&str> {
+        Some(&self.model)
+    }
+
+    async fn chat_completions(
+        &self,
+        messages: Vec<ChatMessage>,
+        settings: &GenerationSettings,
+        tools: Vec<Value>,
+    ) -> Result<Box<dyn ChatResponse>, AgenticFlowError> {
+        let request = self.request(&messages, settings, &tools);
+        Ok(Box::new(self.generate(request).await?))
+    }
+
+    async fn completion(
+        &self,
+        prompt: String,
+        settings: &GenerationSettings,
+    ) -> Result<Box<dyn CompletionResponse>, AgenticFlowError> {
+        let request = self.request(&[ChatMessage::user(prompt)], settings, &[]);
+        Ok(Box::new(self.generate(request).await?))
+    }
+}
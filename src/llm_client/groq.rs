@@ -0,0 +1,150 @@
+//! GroqCloud's OpenAI-compatible API at `api.groq.com/openai/v1`.
+//!
+//! Groq rejects message fields it does not know, so the `thinking` of earlier answers is
+//! left out of requests.
+
+use std::fmt;
+
+use async_trait::async_trait;
+use reqwest::Client as HttpClient;
+use serde_json::{Value, json};
+
+use super::{ApiKeySource, GenerationSettings, LLMProvider, body, dialect, parse_chat_body};
+use crate::{errors::AgenticFlowError, model::*};
+
+#[derive(Debug, Clone)]
+pub enum GroqModel {
+    Llama33_70B,
+    Custom(String),
+}
+
+impl fmt::Display for GroqModel {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            GroqModel::Llama33_70B => "llama-3.3-70b-versatile",
+            GroqModel::Custom(name) => name,
+        })
+    }
+}
+
+pub(super) struct GroqProvider {
+    client: HttpClient,
+    base_url: String,
+    model: String,
+    api_key: ApiKeySource,
+}
+
+impl GroqProvider {
+    pub fn new(model: GroqModel) -> Self {
+        Self::with_client(
+            HttpClient::new(),
+            model,
+            ApiKeySource::Env("GROQ_API_KEY".to_string()),
+        )
+    }
+
+    pub(super) fn with_client(client: HttpClient, model: GroqModel, api_key: ApiKeySource) -> Self {
+        Self {
+            client,
+            base_url: "https://api.groq.com/openai/v1".to_string(),
+            model: model.to_string(),
+            api_key,
+        }
+    }
+
+    async fn chat(
+        &self,
+        messages: Vec<ChatMessage>,
+        settings: &GenerationSettings,
+        tools: Vec<Value>,
+    ) -> Result<OpenRouterResponse, AgenticFlowError> {
+        let req = ChatCompletionRequest {
+            model: self.model.to_string(),
+            messages,
+            temperature: settings.temperature,
+            max_tokens: settings.max_tokens,
+            stop: settings.stop.clone(),
+            response_format: settings.format.response_format(),
+            tool_choice: settings.tool_choice.openai_tool_choice(&tools),
+            // Only allowed with tools.
+            parallel_tool_calls: settings.parallel_tool_calls.filter(|_| !tools.is_empty()),
+            stream: false,
+            tools,
+        };
+        let mut request = json!(req);
+        request["messages"] = json!(dialect::without_thinking(dialect::openai_messages(
+            &req.messages
+        )));
+        dialect::with_sampling(&mut request, &settings.sampling, dialect::OPENAI_SAMPLING);
+        // Groq rejects an empty `tools` list.
+        if let Some(object) = request.as_object_mut().filter(|_| req.tools.is_empty()) {
+            object.remove("tools");
+        }
+        let response = self.send_request(request, "chat/completions").await?;
+
+        let response_text = body::read(response).await?;
+        parse_chat_body(&response_text)
+    }
+}
+
+/// Groq has no completions endpoint; a prompt is sent as a single user message and the
+/// text of the answer is the completion.
+#[derive(Debug)]
+struct GroqCompletion(OpenRouterResponse);
+
+impl CompletionResponse for GroqCompletion {
+    fn response(&self) -> &str {
+        &self.0.message().content
+    }
+
+    fn usage(&self) -> Option<Usage> {
+        ChatResponse::usage(&self.0)
+    }
+
+    fn raw(&self) -> Option<&Value> {
+        ChatResponse::raw(&self.0)
+    }
+
+    fn take_raw(&mut self) -> Option<Value> {
+        ChatResponse::take_raw(&mut self.0)
+    }
+}
+
+#[async_trait]
+impl LLMProvider for GroqProvider {
+    fn http_client(&self) -> &HttpClient {
+        &self.client
+    }
+
+    fn base_url(&self) -> &str {
+        &self.base_url
+    }
+
+    fn api_key(&self) -> Result<Option<String>, AgenticFlowError> {
+        self.api_key.require("Groq").map(Some)
+    }
+
+    fn model_name(&self) -> Option<&str> {
+        Some(&self.model)
+    }
+
+    async fn chat_completions(
+        &self,
+        messages: Vec<ChatMessage>,
+        settings: &GenerationSettings,
+        tools: Vec<Value>,
+    ) -> Result<Box<dyn ChatResponse>, AgenticFlowError> {
+        Ok(Box::new(self.chat(messages, settings, tools).await?))
+    }
+
+    async fn completion(
+        &self,
+        prompt: String,
+        settings: &GenerationSettings,
+    ) -> Result<Box<dyn CompletionResponse>, AgenticFlowError> {
+        let response = self
+            .chat(vec![ChatMessage::user(prompt)], settings, Vec::new())
+            .await?;
+        Ok(Box::new(GroqCompletion(response)))
+    }
+}
@@ -0,0 +1,26 @@
+//! The headers an [`LLMClient`](super::LLMClient) adds to every request of its provider,
+//! set with [`LLMClientBuilder::headers`](super::LLMClientBuilder::headers).
+
+use std::sync::Arc;
+
+/// Header names and values, in the order they are sent.
+pub(super) type ExtraHeaders = Arc<[(String, String)]>;
+
+tokio::task_local! {
+    static EXTRA_HEADERS: ExtraHeaders;
+}
+
+/// Runs `future` with `headers` added to the requests sent inside it.
+pub(super) async fn sending<F: Future>(headers: &ExtraHeaders, future: F) -> F::Output {
+    if headers.is_empty() {
+        return future.await;
+    }
+    EXTRA_HEADERS.scope(headers.clone(), future).await
+}
+
+/// The headers of the enclosing [`sending`], if there is one.
+pub(super) fn extra() -> ExtraHeaders {
+    EXTRA_HEADERS
+        .try_with(Arc::clone)
+        .unwrap_or_else(|_| Arc::new([]))
+}
@@ -0,0 +1,163 @@
+//! Middleware around the requests of an [`LLMClient`]: the [`LLMLayer`] trait, and the
+//! [`LLMRequest`] and [`LLMResponse`] that pass through it.
+
+use std::{sync::Arc, time::Duration};
+
+use async_trait::async_trait;
+use serde_json::Value;
+
+use super::{GenerationSettings, LLMClient};
+use crate::{
+    errors::AgenticFlowError,
+    model::{ChatMessage, ChatResponse, CompletionResponse},
+};
+
+/// A chat or completion request on its way to the provider.
+#[derive(Debug, Clone)]
+pub enum LLMRequest {
+    Chat {
+        messages: Vec<ChatMessage>,
+        tools: Vec<Value>,
+        settings: GenerationSettings,
+    },
+    Completion {
+        prompt: String,
+        settings: GenerationSettings,
+    },
+}
+
+impl LLMRequest {
+    /// `llm chat` or `llm completion`, as errors and request logs name the request.
+    pub fn operation(&self) -> &'static str {
+        match self {
+            LLMRequest::Chat { .. } => "llm chat",
+            LLMRequest::Completion { .. } => "llm completion",
+        }
+    }
+
+    pub fn settings(&self) -> &GenerationSettings {
+        match self {
+            LLMRequest::Chat { settings, .. } | LLMRequest::Completion { settings, .. } => settings,
+        }
+    }
+}
+
+/// The answer to an [`LLMRequest`], of the same kind.
+#[derive(Debug)]
+pub enum LLMResponse {
+    Chat(Box<dyn ChatResponse>),
+    Completion(Box<dyn CompletionResponse>),
+}
+
+impl LLMResponse {
+    /// The chat response, failing with [`AgenticFlowError::ApiClientError`] when a layer
+    /// answered with a completion.
+    pub fn into_chat(self) -> Result<Box<dyn ChatResponse>, AgenticFlowError> {
+        match self {
+            LLMResponse::Chat(response) => Ok(response),
+            LLMResponse::Completion(_) => Err(mismatched("chat", "completion")),
+        }
+    }
+
+    /// The completion response, failing with [`AgenticFlowError::ApiClientError`] when a
+    /// layer answered with a chat response.
+    pub fn into_completion(self) -> Result<Box<dyn CompletionResponse>, AgenticFlowError> {
+        match self {
+            LLMResponse::Completion(response) => Ok(response),
+            LLMResponse::Chat(_) => Err(mismatched("completion", "chat")),
+        }
+    }
+}
+
+fn mismatched(request: &str, response: &str) -> AgenticFlowError {
+    AgenticFlowError::ApiClientError(format!(
+        "a layer answered a {} request with a {} response",
+        request, response
+    ))
+}
+
+/// Middleware added to a client with [`LLMClient::layer`]. A layer may change the
+/// request, answer it itself, or pass it on to the rest of the layers and the provider
+/// with [`Next::run`], as often as it likes, and change what comes back. A layer that
+/// scrubs email addresses from chat requests:
+///
+/// ```rust,no_run
+/// use agentic_flow_lib::errors::AgenticFlowError;
+/// use agentic_flow_lib::llm_client::{LLMLayer, LLMRequest, LLMResponse, Next};
+///
+/// struct ScrubEmails;
+///
+/// #[async_trait::async_trait]
+/// impl LLMLayer for ScrubEmails {
+///     async fn call(
+///         &self,
+///         mut request: LLMRequest,
+///         next: Next<'_>,
+///     ) -> Result<LLMResponse, AgenticFlowError> {
+///         if let LLMRequest::Chat { messages, .. } = &mut request {
+///             for message in messages {
+///                 message.content = message
+///                     .content
+///                     .split(' ')
+///                     .map(|word| if word.contains('@') { "[email]" } else { word })
+///                     .collect::<Vec<_>>()
+///                     .join(" ");
+///             }
+///         }
+///         next.run(request).await
+///     }
+/// }
+/// ```
+///
+/// Layers are shared by the clones of a client, so they must be `Send` and `Sync`.
+#[async_trait]
+pub trait LLMLayer: Send + Sync {
+    async fn call(
+        &self,
+        request: LLMRequest,
+        next: Next<'_>,
+    ) -> Result<LLMResponse, AgenticFlowError>;
+}
+
+/// The layers after the current one, followed by the provider.
+#[derive(Clone, Copy)]
+pub struct Next<'a> {
+    layers: &'a [Arc<dyn LLMLayer>],
+    client: &'a LLMClient,
+    timeout: Option<Duration>,
+}
+
+impl<'a> Next<'a> {
+    pub(super) fn new(
+        layers: &'a [Arc<dyn LLMLayer>],
+        client: &'a LLMClient,
+        timeout: Option<Duration>,
+    ) -> Self {
+        Self {
+            layers,
+            client,
+            timeout,
+        }
+    }
+
+    /// Passes `request` to the next layer, or sends it once the layers are done: under
+    /// the client's concurrency and rate limits and its timeout, recording the usage of
+    /// the response.
+    pub async fn run(self, request: LLMRequest) -> Result<LLMResponse, AgenticFlowError> {
+        match self.layers.split_first() {
+            Some((layer, rest)) => {
+                let next = Self {
+                    layers: rest,
+                    ..self
+                };
+                layer.call(request, next).await
+            }
+            None => self.client.send(request, self.timeout).await,
+        }
+    }
+
+    /// The model the provider sends requests to, if it names one.
+    pub fn model_name(&self) -> Option<&'a str> {
+        self.client.model_name()
+    }
+}
@@ -0,0 +1,270 @@
+//! llama.cpp's `llama-server`, called through its native `/completion` endpoint.
+//!
+//! The endpoint takes a raw prompt, so chat messages are rendered with a ChatML template
+//! here. Tools are described in the system prompt and the model is asked to answer with
+//! a `{"tool_calls": [..]}` object, which [`LlamaCppProvider::TOOL_CALL_GRAMMAR`] can
+//! enforce for models without native function calling.
+
+use async_trait::async_trait;
+use reqwest::Client as HttpClient;
+use serde_json::{Value, json};
+
+use super::{GenerationSettings, LLMProvider, OutputFormat, body, dialect, parse_body};
+use crate::{errors::AgenticFlowError, http::HttpConfig, model::*};
+
+const END_OF_TURN: &str = "<|im_end|>";
+
+/// A provider for a `llama-server`. Models are chosen when the server starts, so there is
+/// no model to pick here.
+pub struct LlamaCppProvider {
+    client: HttpClient,
+    base_url: String,
+    grammar: Option<String>,
+}
+
+impl LlamaCppProvider {
+    /// A GBNF grammar that only admits a `{"tool_calls": [{"name": .., "arguments": {..}}]}`
+    /// object, the answer the chat template asks for when tools are offered.
+    pub const TOOL_CALL_GRAMMAR: &'static str = r#"root ::= "{" ws "\"tool_calls\"" ws ":" ws "[" ws ( call ( ws "," ws call )* )? ws "]" ws "}"
+call ::= "{" ws "\"name\"" ws ":" ws string ws "," ws "\"arguments\"" ws ":" ws object ws "}"
+object ::= "{" ws ( string ws ":" ws value ( ws "," ws string ws ":" ws value )* )? ws "}"
+array ::= "[" ws ( value ( ws "," ws value )* )? ws "]"
+value ::= object | array | string | number | "true" | "false" | "null"
+string ::= "\"" ( [^"\\\x7F\x00-\x1F] | "\\" ( ["\\/bfnrt] | "u" [0-9a-fA-F] [0-9a-fA-F] [0-9a-fA-F] [0-9a-fA-F] ) )* "\""
+number ::= "-"? ( [0-9] | [1-9] [0-9]* ) ( "." [0-9]+ )? ( [eE] [-+]? [0-9]+ )?
+ws ::= [ \t\n]*
+"#;
+
+    /// A provider for a `llama-server` on `http://localhost:8080`.
+    pub fn new() -> Self {
+        Self::with_base_url("http://localhost:8080")
+    }
+
+    pub fn with_base_url(base_url: &str) -> Self {
+        Self {
+            client: HttpClient::new(),
+            base_url: base_url.trim_end_matches('/').to_string(),
+            grammar: None,
+        }
+    }
+
+    /// Constrains every answer to the GBNF grammar `gbnf`.
+    pub fn with_grammar(mut self, gbnf: &str) -> Self {
+        self.grammar = Some(gbnf.to_string());
+        self
+    }
+
+    /// Sends requests with a client built from `http`. Fails with
+    /// [`AgenticFlowError::ApiClientError`] when `http` is invalid.
+    pub fn with_http(mut self, http: &HttpConfig) -> Result<Self, AgenticFlowError> {
+        self.client = http.build_client()?;
+        Ok(self)
+    }
+
+    async fn complete(
+        &self,
+        prompt: String,
+        settings: &GenerationSettings,
+        stop: &[&str],
+    ) -> Result<LlamaCppResponse, AgenticFlowError> {
+        let mut request = json!({
+            "prompt": prompt,
+            "temperature": settings.temperature,
+            "stream": false,
+        });
+        if let Some(max_tokens) = settings.max_tokens {
+            request["n_predict"] = json!(max_tokens);
+        }
+        dialect::with_sampling(&mut request, &settings.sampling, dialect::OLLAMA_SAMPLING);
+        // llama.cpp takes a grammar or a schema, not both; the provider's grammar wins.
+        match (&self.grammar, &settings.format) {
+            (Some(grammar), _) => request["grammar"] = json!(grammar),
+            (None, OutputFormat::Json) => request["json_schema"] = json!({}),
+            (None, OutputFormat::Schema(schema)) => request["json_schema"] = schema.clone(),
+            (None, OutputFormat::None) => {}
+        }
+        let stop: Vec<&str> = stop
+            .iter()
+            .copied()
+            .chain(settings.stop.iter().flatten().map(String::as_str))
+            .collect();
+        if !stop.is_empty() {
+            request["stop"] = json!(stop);
+        }
+        let response = self.send_request(request, "completion").await?;
+
+        let response_text = body::read(response).await?;
+        parse_body::<LlamaCppResponse>(&response_text)
+    }
+}
+
+impl Default for LlamaCppProvider {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Renders `messages` as a ChatML prompt that ends with an open assistant turn. Tools are
+/// listed in a system turn, and earlier tool calls are written as the JSON the model is
+/// asked to answer with.
+fn chat_prompt(messages: &[ChatMessage], tools: &[Value]) -> String {
+    let mut prompt = String::new();
+    if !tools.is_empty() {
+        let tools = tools
+            .iter()
+            .map(|tool| tool.get("function").unwrap_or(tool).to_string())
+            .collect::<Vec<_>>()
+            .join("\n");
+        let instructions = format!(
+            "You can call these tools:\n{}\n\nTo call tools, answer with only a JSON \
+             object of the form {{\"tool_calls\": [{{\"name\": \"<tool name>\", \
+             \"arguments\": {{..}}}}]}}.",
+            tools
+        );
+        push_turn(&mut prompt, "system", &instructions);
+    }
+    for message in messages {
+        let content = match message.tool_calls.as_deref() {
+            Some(tool_calls) if !tool_calls.is_empty() => tool_calls_json(tool_calls).to_string(),
+            _ => message.content.clone(),
+        };
+        push_turn(&mut prompt, message.role.as_str(), &content);
+    }
+    prompt.push_str("<|im_start|>assistant\n");
+    prompt
+}
+
+fn push_turn(prompt: &mut String, role: &str, content: &str) {
+    prompt.push_str(&format!(
+        "<|im_start|>{}\n{}{}\n",
+        role, content, END_OF_TURN
+    ));
+}
+
+fn tool_calls_json(tool_calls: &[ToolCall]) -> Value {
+    let calls: Vec<Value> = tool_calls
+        .iter()
+        .map(|tool_call| {
+            json!({
+                "name": tool_call.function.name,
+                "arguments": tool_call.function.arguments,
+            })
+        })
+        .collect();
+    json!({ "tool_calls": calls })
+}
+
+/// The tool calls of an answer that is a `{"tool_calls": [..]}` object.
+fn parse_tool_calls(content: &str) -> Option<Vec<ToolCall>> {
+    let answer: Value = serde_json::from_str(content.trim()).ok()?;
+    let calls = answer.get("tool_calls")?.as_array()?;
+    calls
+        .iter()
+        .map(|call| {
+            let name = call.get("name")?.as_str()?.to_string();
+            let arguments = call.get("arguments").cloned().unwrap_or_default();
+            let (arguments, repaired) = normalize_arguments(arguments);
+            let mut call = ToolCall::new(name, arguments);
+            call.function.repaired = repaired;
+            Some(call)
+        })
+        .collect()
+}
+
+/// The answer to a templated chat, with a `{"tool_calls": [..]}` answer read as tool calls.
+#[derive(Debug)]
+struct TemplatedChatResponse {
+    message: ChatMessage,
+    finish_reason: FinishReason,
+    completion: LlamaCppResponse,
+}
+
+impl TemplatedChatResponse {
+    fn new(completion: LlamaCppResponse, offered_tools: bool) -> Self {
+        let tool_calls = Some(completion.content.as_str())
+            .filter(|_| offered_tools)
+            .and_then(parse_tool_calls)
+            .filter(|tool_calls| !tool_calls.is_empty());
+        let (message, finish_reason) = match tool_calls {
+            Some(tool_calls) => (
+                ChatMessage::builder(Role::Assistant)
+                    .tool_calls(tool_calls)
+                    .build(),
+                FinishReason::ToolCalls,
+            ),
+            None => (
+                ChatMessage::assistant(completion.content.trim().to_string()),
+                completion.finish_reason(),
+            ),
+        };
+        Self {
+            message,
+            finish_reason,
+            completion,
+        }
+    }
+}
+
+impl ChatResponse for TemplatedChatResponse {
+    fn message(&self) -> &ChatMessage {
+        &self.message
+    }
+
+    fn finish_reason(&self) -> Option<FinishReason> {
+        Some(self.finish_reason.clone())
+    }
+
+    fn model(&self) -> Option<&str> {
+        self.completion.model.as_deref()
+    }
+
+    fn usage(&self) -> Option<Usage> {
+        self.completion.usage()
+    }
+
+    fn stats(&self) -> Option<ProviderStats> {
+        self.completion.stats()
+    }
+
+    fn raw(&self) -> Option<&Value> {
+        self.completion.raw()
+    }
+
+    fn take_raw(&mut self) -> Option<Value> {
+        self.completion.take_raw()
+    }
+}
+
+#[async_trait]
+impl LLMProvider for LlamaCppProvider {
+    fn http_client(&self) -> &HttpClient {
+        &self.client
+    }
+
+    fn base_url(&self) -> &str {
+        &self.base_url
+    }
+
+    async fn chat_completions(
+        &self,
+        messages: Vec<ChatMessage>,
+        settings: &GenerationSettings,
+        tools: Vec<Value>,
+    ) -> Result<Box<dyn ChatResponse>, AgenticFlowError> {
+        let tools = settings.tool_choice.narrowed_tools("llama.cpp", tools);
+        let prompt = chat_prompt(&messages, &tools);
+        let completion = self.complete(prompt, settings, &[END_OF_TURN]).await?;
+        Ok(Box::new(TemplatedChatResponse::new(
+            completion,
+            !tools.is_empty(),
+        )))
+    }
+
+    async fn completion(
+        &self,
+        prompt: String,
+        settings: &GenerationSettings,
+    ) -> Result<Box<dyn CompletionResponse>, AgenticFlowError> {
+        Ok(Box::new(self.complete(prompt, settings, &[]).await?))
+    }
+}
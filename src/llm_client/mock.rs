@@ -0,0 +1,243 @@
+//! A scripted [`LLMProvider`] for tests, so they need no model server.
+
+use std::{
+    collections::{HashMap, VecDeque},
+    sync::{
+        Arc, Mutex, MutexGuard,
+        atomic::{AtomicUsize, Ordering},
+    },
+    time::Duration,
+};
+
+use async_trait::async_trait;
+use reqwest::Client as HttpClient;
+use serde_json::Value;
+
+use super::{GenerationSettings, LLMProvider};
+use crate::{
+    errors::AgenticFlowError,
+    model::{
+        ChatMessage, ChatResponse, CompletionResponse, FinishReason, OllamaCompletionResponse,
+        OllamaResponse,
+    },
+};
+
+/// The messages of every chat call made to a [`MockLLMProvider`].
+pub type ChatCallLog = Arc<Mutex<Vec<Vec<ChatMessage>>>>;
+
+/// A chat call a [`MockLLMProvider`] received.
+#[derive(Debug, Clone)]
+pub struct RecordedCall {
+    pub messages: Vec<ChatMessage>,
+    /// The tool definitions sent with the messages.
+    pub tools: Vec<Value>,
+}
+
+/// A provider that answers from a script instead of a model.
+///
+/// Chat calls get the queued [`with_chat_responses`](Self::with_chat_responses) in order,
+/// then the [`with_chat_response`](Self::with_chat_response) answer, an empty assistant
+/// message unless set. Calls can be made to wait or fail, and every chat call is
+/// recorded. Clones share the script and the recorded calls, so a clone kept by a test
+/// sees the calls made through the one moved into an [`LLMClient`](super::LLMClient):
+///
+/// ```rust,no_run
+/// # use agentic_flow_lib::{llm_client::{LLMClient, MockLLMProvider}, model::ChatMessage};
+/// # async fn example() {
+/// let mock = MockLLMProvider::new()
+///     .with_chat_responses(vec![ChatMessage::assistant("Paris".to_string())]);
+/// let client = LLMClient::from(mock.clone());
+///
+/// client
+///     .chat_completions(vec![ChatMessage::user("Capital of France?".to_string())], vec![])
+///     .await
+///     .unwrap();
+///
+/// assert_eq!(mock.calls()[0].messages[0].content, "Capital of France?");
+/// # }
+/// ```
+#[derive(Clone)]
+pub struct MockLLMProvider {
+    client: HttpClient,
+    chat_response: OllamaResponse,
+    chat_responses: Arc<Mutex<VecDeque<ChatMessage>>>,
+    completion_response: OllamaCompletionResponse,
+    chat_calls: ChatCallLog,
+    calls: Arc<Mutex<Vec<RecordedCall>>>,
+    chat_error: Option<AgenticFlowError>,
+    chat_failures: Arc<Mutex<VecDeque<AgenticFlowError>>>,
+    /// Failures of single calls, by their 1-based number.
+    failures_on_call: HashMap<usize, AgenticFlowError>,
+    chat_delay: Option<Duration>,
+    /// Delays of single calls, by their 1-based number.
+    delays_on_call: HashMap<usize, Duration>,
+    in_flight: Arc<AtomicUsize>,
+    max_in_flight: Arc<AtomicUsize>,
+}
+
+impl Default for MockLLMProvider {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+fn locked<T>(mutex: &Mutex<T>) -> MutexGuard<'_, T> {
+    mutex
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+}
+
+impl MockLLMProvider {
+    pub fn new() -> Self {
+        Self {
+            client: HttpClient::new(),
+            chat_response: OllamaResponse::default(),
+            chat_responses: Arc::default(),
+            completion_response: OllamaCompletionResponse::default(),
+            chat_calls: ChatCallLog::default(),
+            calls: Arc::default(),
+            chat_error: None,
+            chat_failures: Arc::default(),
+            failures_on_call: HashMap::new(),
+            chat_delay: None,
+            delays_on_call: HashMap::new(),
+            in_flight: Arc::default(),
+            max_in_flight: Arc::default(),
+        }
+    }
+
+    /// The chat calls received so far, in order.
+    pub fn calls(&self) -> Vec<RecordedCall> {
+        locked(&self.calls).clone()
+    }
+
+    /// A handle to the chat call log that stays valid after the provider is moved into a client.
+    pub fn chat_calls(&self) -> ChatCallLog {
+        self.chat_calls.clone()
+    }
+
+    /// The most chat calls that were in flight at once, valid after the provider is moved
+    /// into a client.
+    pub fn max_in_flight(&self) -> Arc<AtomicUsize> {
+        self.max_in_flight.clone()
+    }
+
+    pub async fn with_completion_response(mut self, resp: Option<String>) -> Self {
+        self.completion_response = OllamaCompletionResponse::default();
+        self.completion_response.response = resp.unwrap_or_default();
+        self
+    }
+
+    pub async fn with_chat_response(mut self, resp: Option<ChatMessage>) -> Self {
+        self.chat_response = OllamaResponse::default();
+        self.chat_response.message = resp.unwrap_or_else(|| ChatMessage::assistant("".to_string()));
+        self
+    }
+
+    /// Makes the first chat calls answer with `messages`, in order, before later calls get
+    /// the chat response.
+    pub fn with_chat_responses(self, messages: Vec<ChatMessage>) -> Self {
+        *locked(&self.chat_responses) = messages.into();
+        self
+    }
+
+    /// Makes chat responses report `reason`, e.g. [`FinishReason::Length`] for a cut-off answer.
+    pub fn with_finish_reason(mut self, reason: FinishReason) -> Self {
+        self.chat_response.done_reason = Some(reason);
+        self
+    }
+
+    /// Makes chat responses come from `model` and report the given token counts.
+    pub fn with_usage(mut self, model: &str, prompt_tokens: u32, completion_tokens: u32) -> Self {
+        self.chat_response.model = Some(model.to_string());
+        self.chat_response.prompt_eval_count = Some(prompt_tokens);
+        self.chat_response.eval_count = Some(completion_tokens);
+        self
+    }
+
+    /// Makes every chat call take `delay` before answering.
+    pub fn with_chat_delay(mut self, delay: Duration) -> Self {
+        self.chat_delay = Some(delay);
+        self
+    }
+
+    /// Makes chat call number `call`, counted from 1, take `delay` before answering, in
+    /// place of the delay of every call.
+    pub fn with_delay_on_call(mut self, call: usize, delay: Duration) -> Self {
+        self.delays_on_call.insert(call, delay);
+        self
+    }
+
+    /// Makes every chat call fail with `error`.
+    pub fn with_chat_error(mut self, error: AgenticFlowError) -> Self {
+        self.chat_error = Some(error);
+        self
+    }
+
+    /// Makes the first chat calls fail with `errors`, in order, before later calls succeed.
+    pub fn with_chat_failures(self, errors: Vec<AgenticFlowError>) -> Self {
+        *locked(&self.chat_failures) = errors.into();
+        self
+    }
+
+    /// Makes chat call number `call`, counted from 1, fail with `error`. The call uses up
+    /// no queued response.
+    pub fn with_failure_on_call(mut self, call: usize, error: AgenticFlowError) -> Self {
+        self.failures_on_call.insert(call, error);
+        self
+    }
+}
+
+#[async_trait]
+impl LLMProvider for MockLLMProvider {
+    fn http_client(&self) -> &HttpClient {
+        &self.client
+    }
+
+    fn base_url(&self) -> &str {
+        ""
+    }
+
+    async fn chat_completions(
+        &self,
+        messages: Vec<ChatMessage>,
+        _settings: &GenerationSettings,
+        tools: Vec<Value>,
+    ) -> Result<Box<dyn ChatResponse>, AgenticFlowError> {
+        locked(&self.chat_calls).push(messages.clone());
+        let call = {
+            let mut calls = locked(&self.calls);
+            calls.push(RecordedCall { messages, tools });
+            calls.len()
+        };
+        let in_flight = self.in_flight.fetch_add(1, Ordering::SeqCst) + 1;
+        self.max_in_flight.fetch_max(in_flight, Ordering::SeqCst);
+        if let Some(delay) = self.delays_on_call.get(&call).or(self.chat_delay.as_ref()) {
+            tokio::time::sleep(*delay).await;
+        }
+        self.in_flight.fetch_sub(1, Ordering::SeqCst);
+        if let Some(error) = &self.chat_error {
+            return Err(error.clone());
+        }
+        if let Some(error) = self.failures_on_call.get(&call) {
+            return Err(error.clone());
+        }
+        if let Some(error) = locked(&self.chat_failures).pop_front() {
+            return Err(error);
+        }
+        if let Some(message) = locked(&self.chat_responses).pop_front() {
+            let mut response = self.chat_response.clone();
+            response.message = message;
+            return Ok(Box::new(response));
+        }
+        Ok(Box::new(self.chat_response.clone()))
+    }
+
+    async fn completion(
+        &self,
+        _prompt: String,
+        _settings: &GenerationSettings,
+    ) -> Result<Box<dyn CompletionResponse>, AgenticFlowError> {
+        Ok(Box::new(self.completion_response.clone()))
+    }
+}
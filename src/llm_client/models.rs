@@ -0,0 +1,92 @@
+//! The models of an Ollama server: listing them with `api/tags` and pulling one with
+//! `api/pull`.
+
+use serde::Deserialize;
+
+use crate::errors::AgenticFlowError;
+
+/// The answer of Ollama's `api/tags`.
+#[derive(Deserialize)]
+pub(super) struct OllamaTags {
+    #[serde(default)]
+    models: Vec<OllamaTag>,
+}
+
+#[derive(Deserialize)]
+struct OllamaTag {
+    name: String,
+}
+
+impl OllamaTags {
+    pub(super) fn into_names(self) -> Vec<String> {
+        self.models.into_iter().map(|model| model.name).collect()
+    }
+}
+
+/// A line of the progress Ollama streams while pulling a model.
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+pub struct PullProgress {
+    /// What the pull is doing, e.g. `pulling manifest` or `success`.
+    #[serde(default)]
+    pub status: String,
+    /// The layer being downloaded.
+    #[serde(default)]
+    pub digest: Option<String>,
+    /// The size of the layer in bytes.
+    #[serde(default)]
+    pub total: Option<u64>,
+    /// The bytes of the layer downloaded so far.
+    #[serde(default)]
+    pub completed: Option<u64>,
+}
+
+#[derive(Deserialize)]
+struct PullLine {
+    #[serde(default)]
+    error: Option<String>,
+    #[serde(flatten)]
+    progress: PullProgress,
+}
+
+/// Reads a line of the `api/pull` stream: `None` for a blank line, and an error for the
+/// line Ollama sends when the pull fails.
+pub(super) fn pull_progress(
+    model: &str,
+    line: &[u8],
+) -> Result<Option<PullProgress>, AgenticFlowError> {
+    let line = String::from_utf8_lossy(line);
+    let line = line.trim();
+    if line.is_empty() {
+        return Ok(None);
+    }
+    let parsed: PullLine = serde_json::from_str(line)
+        .map_err(|error| AgenticFlowError::unparseable_body(error, line))?;
+    match parsed.error {
+        Some(error) => Err(AgenticFlowError::ApiClientError(format!(
+            "pulling model '{}' failed: {}",
+            model, error
+        ))),
+        None => Ok(Some(parsed.progress)),
+    }
+}
+
+/// Whether `model` is one of the `available` names. Ollama lists models with their tag,
+/// and a model named without one is its `latest` tag.
+pub(super) fn is_available(model: &str, available: &[String]) -> bool {
+    let tagged = if model.contains(':') {
+        model.to_string()
+    } else {
+        format!("{}:latest", model)
+    };
+    available
+        .iter()
+        .any(|name| *name == model || *name == tagged)
+}
+
+/// The error for a model the server does not have.
+pub(super) fn missing_model(model: &str) -> AgenticFlowError {
+    AgenticFlowError::ApiClientError(format!(
+        "model '{}' is not available on the Ollama server: run `ollama pull {}` first",
+        model, model
+    ))
+}
@@ -0,0 +1,166 @@
+//! The OpenAI API, called directly rather than through OpenRouter.
+
+use std::fmt;
+
+use async_trait::async_trait;
+use reqwest::Client as HttpClient;
+use serde_json::{Value, json};
+
+use super::{
+    ApiKeySource, GenerationSettings, LLMProvider, body, dialect, parse_body, parse_chat_body,
+};
+use crate::{errors::AgenticFlowError, model::*};
+
+#[derive(Debug, Clone)]
+pub enum OpenAIModel {
+    GPT4o,
+    GPT4oMini,
+    O3Mini,
+    Custom(String),
+}
+
+impl fmt::Display for OpenAIModel {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            OpenAIModel::GPT4o => "gpt-4o",
+            OpenAIModel::GPT4oMini => "gpt-4o-mini",
+            OpenAIModel::O3Mini => "o3-mini",
+            OpenAIModel::Custom(name) => name,
+        })
+    }
+}
+
+/// Reasoning models reject a `temperature`, and take the token limit as
+/// `max_completion_tokens`.
+fn is_reasoning_model(model: &str) -> bool {
+    ["o1", "o3", "o4"]
+        .iter()
+        .any(|family| model == *family || model.starts_with(&format!("{}-", family)))
+}
+
+pub(super) struct OpenAIProvider {
+    client: HttpClient,
+    base_url: String,
+    model: String,
+    api_key: ApiKeySource,
+}
+
+impl OpenAIProvider {
+    pub fn new(model: OpenAIModel) -> Self {
+        Self::with_client(
+            HttpClient::new(),
+            model,
+            ApiKeySource::Env("OPENAI_API_KEY".to_string()),
+        )
+    }
+
+    pub(super) fn with_client(
+        client: HttpClient,
+        model: OpenAIModel,
+        api_key: ApiKeySource,
+    ) -> Self {
+        Self {
+            client,
+            base_url: "https://api.openai.com/v1".to_string(),
+            model: model.to_string(),
+            api_key,
+        }
+    }
+
+    /// Drops the settings OpenAI rejects: an empty `tools` list, and the temperature of
+    /// reasoning models, whose `max_tokens` is renamed.
+    fn adapt(&self, mut request: Value) -> Value {
+        if let Some(object) = request.as_object_mut() {
+            let no_tools = object
+                .get("tools")
+                .and_then(Value::as_array)
+                .is_some_and(Vec::is_empty);
+            if no_tools {
+                object.remove("tools");
+            }
+            if is_reasoning_model(&self.model) {
+                object.remove("temperature");
+                if let Some(max_tokens) = object.remove("max_tokens") {
+                    object.insert("max_completion_tokens".to_string(), max_tokens);
+                }
+            }
+        }
+        request
+    }
+}
+
+#[async_trait]
+impl LLMProvider for OpenAIProvider {
+    fn http_client(&self) -> &HttpClient {
+        &self.client
+    }
+
+    fn base_url(&self) -> &str {
+        &self.base_url
+    }
+
+    fn api_key(&self) -> Result<Option<String>, AgenticFlowError> {
+        self.api_key.require("OpenAI").map(Some)
+    }
+
+    fn model_name(&self) -> Option<&str> {
+        Some(&self.model)
+    }
+
+    async fn chat_completions(
+        &self,
+        messages: Vec<ChatMessage>,
+        settings: &GenerationSettings,
+        tools: Vec<Value>,
+    ) -> Result<Box<dyn ChatResponse>, AgenticFlowError> {
+        let req = ChatCompletionRequest {
+            model: self.model.to_string(),
+            messages,
+            temperature: settings.temperature,
+            max_tokens: settings.max_tokens,
+            stop: settings.stop.clone(),
+            response_format: settings.format.response_format(),
+            tool_choice: settings.tool_choice.openai_tool_choice(&tools),
+            // Only allowed with tools.
+            parallel_tool_calls: settings.parallel_tool_calls.filter(|_| !tools.is_empty()),
+            stream: false,
+            tools,
+        };
+        let mut request = json!(req);
+        request["messages"] = json!(dialect::without_thinking(dialect::openai_messages(
+            &req.messages
+        )));
+        dialect::with_sampling(&mut request, &settings.sampling, dialect::OPENAI_SAMPLING);
+        let response = self
+            .send_request(self.adapt(request), "chat/completions")
+            .await?;
+
+        let response_text = body::read(response).await?;
+        let response = parse_chat_body(&response_text)?;
+        Ok(Box::new(response))
+    }
+
+    async fn completion(
+        &self,
+        prompt: String,
+        settings: &GenerationSettings,
+    ) -> Result<Box<dyn CompletionResponse>, AgenticFlowError> {
+        let request = CompletionRequest {
+            model: self.model.to_string(),
+            prompt,
+            max_tokens: settings.max_tokens,
+            temperature: Some(settings.temperature),
+            stream: Some(false),
+            stop: settings.stop.clone(),
+        };
+        let mut request = json!(request);
+        dialect::with_sampling(&mut request, &settings.sampling, dialect::OPENAI_SAMPLING);
+        let response = self
+            .send_request(self.adapt(request), "completions")
+            .await?;
+
+        let response_text = body::read(response).await?;
+        let response = parse_body::<OpenRouterCompletionResponse>(&response_text)?;
+        Ok(Box::new(response))
+    }
+}
@@ -0,0 +1,329 @@
+//! Settings for a single request, overriding those of the [`LLMClient`](super::LLMClient).
+
+use std::{sync::Once, time::Duration};
+
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value, json};
+use tokio_util::sync::CancellationToken;
+
+/// Per-call overrides for `LLMClient::chat_completions_with` and
+/// `LLMClient::completion_with`. Unset fields keep the client's setting.
+#[derive(Debug, Clone, Default)]
+pub struct RequestOptions {
+    /// Limit for sending the request and reading the whole response, in place of the
+    /// client's [`with_timeout`](super::LLMClient::with_timeout).
+    pub timeout: Option<Duration>,
+    /// Most tokens to generate, in place of the client's
+    /// [`with_max_tokens`](super::LLMClient::with_max_tokens).
+    pub max_tokens: Option<usize>,
+    /// Stop sequences in place of the client's [`with_stop`](super::LLMClient::with_stop);
+    /// an empty list sends none.
+    pub stop: Option<Vec<String>>,
+    /// The shape of the answer, in place of the client's
+    /// [`with_output_format`](super::LLMClient::with_output_format).
+    pub format: Option<OutputFormat>,
+    /// Whether the model must, may or must not call the tools it is offered.
+    pub tool_choice: Option<ToolChoice>,
+    /// Stops the call when cancelled, wherever it is: waiting for the rate limits, sending
+    /// the request, reading the answer or waiting to retry. The request in flight is
+    /// dropped, closing its connection, and the call fails with
+    /// [`AgenticFlowError::Cancelled`](crate::errors::AgenticFlowError::Cancelled).
+    pub cancellation: Option<CancellationToken>,
+}
+
+impl RequestOptions {
+    pub fn with_timeout(mut self, limit: Duration) -> Self {
+        self.timeout = Some(limit);
+        self
+    }
+
+    pub fn with_max_tokens(mut self, max_tokens: usize) -> Self {
+        self.max_tokens = Some(max_tokens);
+        self
+    }
+
+    pub fn with_stop(mut self, stop: Vec<String>) -> Self {
+        self.stop = Some(stop);
+        self
+    }
+
+    pub fn with_format(mut self, format: OutputFormat) -> Self {
+        self.format = Some(format);
+        self
+    }
+
+    pub fn with_tool_choice(mut self, tool_choice: ToolChoice) -> Self {
+        self.tool_choice = Some(tool_choice);
+        self
+    }
+
+    pub fn with_cancellation(mut self, token: CancellationToken) -> Self {
+        self.cancellation = Some(token);
+        self
+    }
+}
+
+/// What the model is asked to answer with.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum OutputFormat {
+    /// Free text.
+    #[default]
+    None,
+    /// Any JSON value.
+    Json,
+    /// JSON matching the schema.
+    Schema(Value),
+}
+
+impl OutputFormat {
+    /// OpenAI's `response_format`.
+    pub(super) fn response_format(&self) -> Option<Value> {
+        match self {
+            OutputFormat::None => None,
+            OutputFormat::Json => Some(json!({ "type": "json_object" })),
+            OutputFormat::Schema(schema) => Some(json!({
+                "type": "json_schema",
+                "json_schema": { "name": "response", "strict": true, "schema": schema },
+            })),
+        }
+    }
+
+    /// Ollama's `format`: `"json"` or the schema itself.
+    pub(super) fn ollama_format(&self) -> Option<Value> {
+        match self {
+            OutputFormat::None => None,
+            OutputFormat::Json => Some(json!("json")),
+            OutputFormat::Schema(schema) => Some(schema.clone()),
+        }
+    }
+}
+
+/// Which of the offered tools the model has to call.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ToolChoice {
+    /// The model decides whether to call tools.
+    #[default]
+    Auto,
+    /// The model answers without calling any tool; the tools still describe what the
+    /// plan can use.
+    None,
+    /// The model calls at least one tool.
+    Required,
+    /// The model calls the tool with this name.
+    Specific(String),
+}
+
+impl ToolChoice {
+    /// OpenAI's `tool_choice`. Nothing is sent for `Auto`, the default, or without tools,
+    /// which OpenAI rejects a `tool_choice` for.
+    pub(super) fn openai_tool_choice(&self, tools: &[Value]) -> Option<Value> {
+        if tools.is_empty() {
+            return None;
+        }
+        match self {
+            ToolChoice::Auto => None,
+            ToolChoice::None => Some(json!("none")),
+            ToolChoice::Required => Some(json!("required")),
+            ToolChoice::Specific(name) => Some(json!({
+                "type": "function",
+                "function": { "name": name },
+            })),
+        }
+    }
+
+    /// The tools to offer a provider that has no `tool_choice`: none for `None`, and only
+    /// the named tool for `Specific`. Such a provider cannot be made to call a tool, which
+    /// `Required` and `Specific` warn about once per process.
+    pub(super) fn narrowed_tools(&self, provider: &str, tools: Vec<Value>) -> Vec<Value> {
+        static WARNING: Once = Once::new();
+        let warn = || {
+            WARNING.call_once(|| {
+                println!(
+                    "WARNING: {} cannot require a tool call; the model may answer without one.",
+                    provider
+                )
+            })
+        };
+        match self {
+            ToolChoice::Auto => tools,
+            ToolChoice::None => Vec::new(),
+            ToolChoice::Required => {
+                warn();
+                tools
+            }
+            ToolChoice::Specific(name) => {
+                warn();
+                tools
+                    .into_iter()
+                    .filter(|tool| tool_name(tool) == Some(name.as_str()))
+                    .collect()
+            }
+        }
+    }
+}
+
+/// The name of a tool in OpenAI's `{"type": "function", "function": {"name", ..}}` form.
+fn tool_name(tool: &Value) -> Option<&str> {
+    tool["function"]["name"].as_str()
+}
+
+/// What the model is asked to generate with, as handed to an
+/// [`LLMProvider`](super::LLMProvider). Each provider puts these where its API expects
+/// them.
+#[derive(Debug, Clone, PartialEq)]
+pub struct GenerationSettings {
+    pub temperature: f32,
+    /// Most tokens to generate; the provider's default when unset.
+    pub max_tokens: Option<usize>,
+    pub sampling: SamplingOptions,
+    /// Sequences that end the answer when generated; they are not part of it.
+    pub stop: Option<Vec<String>>,
+    pub format: OutputFormat,
+    pub tool_choice: ToolChoice,
+    /// Whether the model may call several tools in one answer; the provider's default
+    /// when unset.
+    pub parallel_tool_calls: Option<bool>,
+    /// Whether a reasoning model thinks before answering; the provider's default when
+    /// unset.
+    pub thinking: Option<bool>,
+}
+
+impl Default for GenerationSettings {
+    fn default() -> Self {
+        Self {
+            temperature: 0.7,
+            max_tokens: None,
+            sampling: SamplingOptions::default(),
+            stop: None,
+            format: OutputFormat::None,
+            tool_choice: ToolChoice::Auto,
+            parallel_tool_calls: None,
+            thinking: None,
+        }
+    }
+}
+
+impl GenerationSettings {
+    /// These settings with the fields `options` sets replaced.
+    pub(super) fn overridden_by(&self, options: &RequestOptions) -> Self {
+        let stop = match &options.stop {
+            Some(stop) if stop.is_empty() => None,
+            Some(stop) => Some(stop.clone()),
+            None => self.stop.clone(),
+        };
+        Self {
+            max_tokens: options.max_tokens.or(self.max_tokens),
+            stop,
+            format: options.format.as_ref().unwrap_or(&self.format).clone(),
+            tool_choice: options
+                .tool_choice
+                .as_ref()
+                .unwrap_or(&self.tool_choice)
+                .clone(),
+            ..self.clone()
+        }
+    }
+
+    /// The settings as JSON, for telling requests with different settings apart.
+    pub(super) fn to_value(&self) -> Value {
+        json!({
+            "temperature": self.temperature,
+            "max_tokens": self.max_tokens,
+            "sampling": self.sampling,
+            "stop": self.stop,
+            "format": self.format,
+            "tool_choice": self.tool_choice,
+            "parallel_tool_calls": self.parallel_tool_calls,
+            "thinking": self.thinking,
+        })
+    }
+}
+
+/// Sampling parameters beyond the temperature, set with
+/// [`LLMClient::with_sampling`](super::LLMClient::with_sampling). Unset fields keep the
+/// provider's default, and a provider that does not support a field leaves it out of the
+/// request.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct SamplingOptions {
+    /// Samples from the smallest set of tokens whose probabilities add up to `top_p`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub top_p: Option<f32>,
+    /// Samples from the `top_k` most likely tokens.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub top_k: Option<u32>,
+    /// Makes sampling reproducible; with a temperature of 0 the same request gets the same
+    /// answer on providers that honor it.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub seed: Option<u64>,
+    /// Penalizes tokens that already occur, as Ollama, llama.cpp and OpenRouter
+    /// understand it; above 1 discourages repetition.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub repeat_penalty: Option<f32>,
+    /// OpenAI's penalty growing with how often a token occurs.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub frequency_penalty: Option<f32>,
+    /// OpenAI's penalty for any token that occurs.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub presence_penalty: Option<f32>,
+}
+
+impl SamplingOptions {
+    /// The fields that are set, renamed as `names` says: pairs of a field and the
+    /// provider's name for it. Fields missing from `names` are not sent.
+    pub(super) fn wire_fields(&self, names: &[(&str, &str)]) -> Map<String, Value> {
+        let Ok(Value::Object(fields)) = serde_json::to_value(self) else {
+            return Map::new();
+        };
+        names
+            .iter()
+            .filter_map(|(field, wire_name)| {
+                let value = fields.get(*field)?;
+                Some((wire_name.to_string(), value.clone()))
+            })
+            .collect()
+    }
+}
+
+/// How Ollama runs the model, as opposed to how it samples, set with
+/// [`OllamaProvider::with_options`](super::OllamaProvider::with_options). Unset fields
+/// keep the server's default.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct OllamaOptions {
+    /// The context window in tokens. Ollama's default of 2048 silently drops the start of
+    /// longer prompts, such as the tool definitions offered to a planner.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub num_ctx: Option<u32>,
+    /// How long the model stays loaded after a request, e.g. `10m`, or `-1m` to keep it
+    /// loaded.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub keep_alive: Option<String>,
+    /// The layers offloaded to the GPU; 0 runs the model on the CPU.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub num_gpu: Option<u32>,
+    /// The CPU threads the model runs on.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub num_thread: Option<u32>,
+}
+
+impl OllamaOptions {
+    /// Adds the options that are set to an `api/chat` or `api/generate` request:
+    /// `keep_alive` next to `options`, where Ollama reads it, and the others in `options`.
+    pub(super) fn apply(&self, request: &mut Value) {
+        if let Some(keep_alive) = &self.keep_alive {
+            request["keep_alive"] = json!(keep_alive);
+        }
+        let runtime = [
+            ("num_ctx", self.num_ctx),
+            ("num_gpu", self.num_gpu),
+            ("num_thread", self.num_thread),
+        ];
+        for (name, value) in runtime {
+            if let Some(value) = value {
+                request["options"][name] = json!(value);
+            }
+        }
+    }
+}
@@ -0,0 +1,438 @@
+//! Recording the answers of a provider to a cassette file and replaying them, so tests of
+//! code that calls an LLM run without one.
+
+use std::{
+    collections::HashMap,
+    fs,
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
+};
+
+use async_trait::async_trait;
+use reqwest::Client as HttpClient;
+use serde::{Deserialize, Serialize};
+use serde_json::{Value, json};
+
+use super::{GenerationSettings, LLMProvider};
+use crate::{
+    errors::AgenticFlowError,
+    model::{ChatMessage, ChatResponse, CompletionResponse, FinishReason, Usage},
+};
+
+/// The environment variable that makes [`RecordingProvider::from_env`] record.
+pub const RECORD_ENV_VAR: &str = "AGENTIC_FLOW_RECORD";
+
+/// The longest part of a value quoted when a request is not in the cassette.
+const QUOTED_VALUE_CHARS: usize = 200;
+
+/// A provider that records the answers of another provider to a cassette, a JSON file, or
+/// answers from one without calling any provider.
+///
+/// Requests are matched by a hash of their model, messages, tools and generation
+/// settings. A request sent several times gets its recorded answers in order, then the
+/// last one again. A request that is not in the cassette fails with an
+/// [`AgenticFlowError::ApiClientError`] showing how it differs from the nearest
+/// recorded one.
+pub struct RecordingProvider {
+    /// The provider recorded from; `None` when replaying.
+    inner: Option<Arc<dyn LLMProvider>>,
+    path: PathBuf,
+    model: Option<String>,
+    client: HttpClient,
+    cassette: Mutex<Cassette>,
+    /// How many answers to each request were replayed so far.
+    replayed: Mutex<HashMap<String, usize>>,
+}
+
+#[derive(Default, Serialize, Deserialize)]
+struct Cassette {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    model: Option<String>,
+    #[serde(default)]
+    interactions: Vec<Interaction>,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+struct Interaction {
+    hash: String,
+    request: Value,
+    response: RecordedResponse,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+enum RecordedResponse {
+    Chat {
+        message: ChatMessage,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        finish_reason: Option<FinishReason>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        model: Option<String>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        usage: Option<Usage>,
+    },
+    Completion {
+        text: String,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        usage: Option<Usage>,
+    },
+}
+
+/// A chat answer served from a cassette.
+#[derive(Debug)]
+struct ReplayedChat {
+    message: ChatMessage,
+    finish_reason: Option<FinishReason>,
+    model: Option<String>,
+    usage: Option<Usage>,
+}
+
+impl ChatResponse for ReplayedChat {
+    fn message(&self) -> &ChatMessage {
+        &self.message
+    }
+
+    fn finish_reason(&self) -> Option<FinishReason> {
+        self.finish_reason.clone()
+    }
+
+    fn model(&self) -> Option<&str> {
+        self.model.as_deref()
+    }
+
+    fn usage(&self) -> Option<Usage> {
+        self.usage
+    }
+}
+
+/// A completion served from a cassette.
+#[derive(Debug)]
+struct ReplayedCompletion {
+    text: String,
+    usage: Option<Usage>,
+}
+
+impl CompletionResponse for ReplayedCompletion {
+    fn response(&self) -> &str {
+        &self.text
+    }
+
+    fn usage(&self) -> Option<Usage> {
+        self.usage
+    }
+}
+
+impl RecordingProvider {
+    /// Sends requests to `inner` and writes each answer to the cassette at `path`,
+    /// replacing what it held before. The file is written after every answer.
+    pub fn record(inner: impl LLMProvider + 'static, path: impl Into<PathBuf>) -> Self {
+        let model = inner.model_name().map(str::to_string);
+        Self {
+            client: HttpClient::new(),
+            inner: Some(Arc::new(inner)),
+            path: path.into(),
+            cassette: Mutex::new(Cassette {
+                model: model.clone(),
+                interactions: Vec::new(),
+            }),
+            model,
+            replayed: Mutex::default(),
+        }
+    }
+
+    /// Answers from the cassette at `path`. Fails with
+    /// [`AgenticFlowError::ApiClientError`] when it cannot be read.
+    pub fn replay(path: impl Into<PathBuf>) -> Result<Self, AgenticFlowError> {
+        let path = path.into();
+        let cassette = read_cassette(&path)?;
+        Ok(Self {
+            inner: None,
+            model: cassette.model.clone(),
+            path,
+            client: HttpClient::new(),
+            cassette: Mutex::new(cassette),
+            replayed: Mutex::default(),
+        })
+    }
+
+    /// Records from `inner` when [`RECORD_ENV_VAR`] is set, and replays otherwise, so
+    /// a test re-records its cassette with `AGENTIC_FLOW_RECORD=1 cargo test`.
+    pub fn from_env(
+        inner: impl LLMProvider + 'static,
+        path: impl Into<PathBuf>,
+    ) -> Result<Self, AgenticFlowError> {
+        match std::env::var_os(RECORD_ENV_VAR) {
+            Some(_) => Ok(Self::record(inner, path)),
+            None => Self::replay(path),
+        }
+    }
+
+    /// Whether requests go to the recorded provider.
+    pub fn is_recording(&self) -> bool {
+        self.inner.is_some()
+    }
+
+    fn lock_cassette(&self) -> std::sync::MutexGuard<'_, Cassette> {
+        self.cassette
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    /// Adds an answer to the cassette and writes it.
+    fn save(&self, request: Value, response: RecordedResponse) -> Result<(), AgenticFlowError> {
+        let mut cassette = self.lock_cassette();
+        cassette.interactions.push(Interaction {
+            hash: request_hash(&request),
+            request,
+            response,
+        });
+        let json = serde_json::to_string_pretty(&*cassette)?;
+        fs::write(&self.path, json).map_err(|error| {
+            AgenticFlowError::ApiClientError(format!(
+                "cannot write cassette '{}': {}",
+                self.path.display(),
+                error
+            ))
+        })
+    }
+
+    /// The recorded answer to `request`.
+    fn answer(&self, request: &Value) -> Result<RecordedResponse, AgenticFlowError> {
+        let hash = request_hash(request);
+        let cassette = self.lock_cassette();
+        let recorded: Vec<&Interaction> = cassette
+            .interactions
+            .iter()
+            .filter(|interaction| interaction.hash == hash)
+            .collect();
+        if recorded.is_empty() {
+            return Err(self.not_recorded(request, &cassette));
+        }
+        let mut replayed = self
+            .replayed
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        let count = replayed.entry(hash).or_default();
+        let interaction = recorded[(*count).min(recorded.len() - 1)];
+        *count += 1;
+        Ok(interaction.response.clone())
+    }
+
+    /// The error for a request the cassette has no answer to, naming where it differs
+    /// from the recorded request closest to it.
+    fn not_recorded(&self, request: &Value, cassette: &Cassette) -> AgenticFlowError {
+        let nearest = cassette
+            .interactions
+            .iter()
+            .map(|interaction| {
+                let mut differences = Vec::new();
+                differ("", &interaction.request, request, &mut differences);
+                differences
+            })
+            .min_by_key(Vec::len);
+        let detail = match nearest.as_deref() {
+            None => "the cassette is empty".to_string(),
+            Some([]) => "a recorded request is the same, but its hash does not match".to_string(),
+            Some(differences) => {
+                let (path, recorded, sent) = &differences[0];
+                format!(
+                    "the nearest recorded request differs in {} place(s), first at `{}`: recorded {}, sent {}",
+                    differences.len(),
+                    path,
+                    quoted(recorded),
+                    quoted(sent)
+                )
+            }
+        };
+        AgenticFlowError::ApiClientError(format!(
+            "no answer in cassette '{}' for this request: {}; record it again with {}=1",
+            self.path.display(),
+            detail,
+            RECORD_ENV_VAR
+        ))
+    }
+}
+
+fn read_cassette(path: &Path) -> Result<Cassette, AgenticFlowError> {
+    let invalid = |reason: String| {
+        AgenticFlowError::ApiClientError(format!(
+            "cannot read cassette '{}': {}",
+            path.display(),
+            reason
+        ))
+    };
+    let json = fs::read_to_string(path).map_err(|error| invalid(error.to_string()))?;
+    serde_json::from_str(&json).map_err(|error| invalid(error.to_string()))
+}
+
+/// The request of a chat call as it is matched.
+fn chat_request(
+    model: Option<&str>,
+    messages: &[ChatMessage],
+    tools: &[Value],
+    settings: &GenerationSettings,
+) -> Value {
+    json!({
+        "model": model,
+        "messages": messages,
+        "tools": tools,
+        "settings": settings.to_value(),
+    })
+}
+
+/// The request of a completion call as it is matched.
+fn completion_request(model: Option<&str>, prompt: &str, settings: &GenerationSettings) -> Value {
+    json!({
+        "model": model,
+        "prompt": prompt,
+        "settings": settings.to_value(),
+    })
+}
+
+/// A hash of `request` that stays the same across Rust versions and platforms, unlike
+/// the standard hasher: 64-bit FNV-1a over its JSON, whose objects have sorted keys.
+fn request_hash(request: &Value) -> String {
+    let hash = request
+        .to_string()
+        .bytes()
+        .fold(0xcbf2_9ce4_8422_2325_u64, |hash, byte| {
+            (hash ^ u64::from(byte)).wrapping_mul(0x0100_0000_01b3)
+        });
+    format!("{:016x}", hash)
+}
+
+/// Collects the JSON paths where `recorded` and `sent` differ, with the value each has
+/// there.
+fn differ(
+    path: &str,
+    recorded: &Value,
+    sent: &Value,
+    differences: &mut Vec<(String, Option<Value>, Option<Value>)>,
+) {
+    match (recorded, sent) {
+        (Value::Object(recorded), Value::Object(sent)) => {
+            for (key, value) in recorded {
+                let path = format!("{}.{}", path, key);
+                match sent.get(key) {
+                    Some(other) => differ(&path, value, other, differences),
+                    None => differences.push((path, Some(value.clone()), None)),
+                }
+            }
+            for (key, value) in sent.iter().filter(|(key, _)| !recorded.contains_key(*key)) {
+                differences.push((format!("{}.{}", path, key), None, Some(value.clone())));
+            }
+        }
+        (Value::Array(recorded), Value::Array(sent)) => {
+            for index in 0..recorded.len().max(sent.len()) {
+                let path = format!("{}[{}]", path, index);
+                match (recorded.get(index), sent.get(index)) {
+                    (Some(value), Some(other)) => differ(&path, value, other, differences),
+                    (value, other) => differences.push((path, value.cloned(), other.cloned())),
+                }
+            }
+        }
+        _ if recorded != sent => {
+            differences.push((path.to_string(), Some(recorded.clone()), Some(sent.clone())))
+        }
+        _ => {}
+    }
+}
+
+fn quoted(value: &Option<Value>) -> String {
+    match value {
+        Some(value) => value.to_string().chars().take(QUOTED_VALUE_CHARS).collect(),
+        None => "nothing".to_string(),
+    }
+}
+
+#[async_trait]
+impl LLMProvider for RecordingProvider {
+    fn http_client(&self) -> &HttpClient {
+        &self.client
+    }
+
+    fn base_url(&self) -> &str {
+        match &self.inner {
+            Some(inner) => inner.base_url(),
+            None => "",
+        }
+    }
+
+    fn model_name(&self) -> Option<&str> {
+        self.model.as_deref()
+    }
+
+    fn supports_output_format(&self) -> bool {
+        self.inner
+            .as_ref()
+            .is_none_or(|inner| inner.supports_output_format())
+    }
+
+    async fn chat_completions(
+        &self,
+        messages: Vec<ChatMessage>,
+        settings: &GenerationSettings,
+        tools: Vec<Value>,
+    ) -> Result<Box<dyn ChatResponse>, AgenticFlowError> {
+        let request = chat_request(self.model_name(), &messages, &tools, settings);
+        let Some(inner) = &self.inner else {
+            let RecordedResponse::Chat {
+                message,
+                finish_reason,
+                model,
+                usage,
+            } = self.answer(&request)?
+            else {
+                return Err(AgenticFlowError::ApiClientError(format!(
+                    "cassette '{}' has a completion recorded for a chat request",
+                    self.path.display()
+                )));
+            };
+            return Ok(Box::new(ReplayedChat {
+                message,
+                finish_reason,
+                model,
+                usage,
+            }));
+        };
+
+        let response = inner.chat_completions(messages, settings, tools).await?;
+        self.save(
+            request,
+            RecordedResponse::Chat {
+                message: response.message().clone(),
+                finish_reason: response.finish_reason(),
+                model: response.model().map(str::to_string),
+                usage: response.usage(),
+            },
+        )?;
+        Ok(response)
+    }
+
+    async fn completion(
+        &self,
+        prompt: String,
+        settings: &GenerationSettings,
+    ) -> Result<Box<dyn CompletionResponse>, AgenticFlowError> {
+        let request = completion_request(self.model_name(), &prompt, settings);
+        let Some(inner) = &self.inner else {
+            let RecordedResponse::Completion { text, usage } = self.answer(&request)? else {
+                return Err(AgenticFlowError::ApiClientError(format!(
+                    "cassette '{}' has a chat answer recorded for a completion request",
+                    self.path.display()
+                )));
+            };
+            return Ok(Box::new(ReplayedCompletion { text, usage }));
+        };
+
+        let response = inner.completion(prompt, settings).await?;
+        self.save(
+            request,
+            RecordedResponse::Completion {
+                text: response.response().to_string(),
+                usage: response.usage(),
+            },
+        )?;
+        Ok(response)
+    }
+}
@@ -0,0 +1,239 @@
+//! Reporting the requests an [`LLMClient`](super::LLMClient) sends, with their secrets
+//! redacted, to an observer installed with
+//! [`with_request_observer`](super::LLMClient::with_request_observer).
+
+use std::{
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use async_trait::async_trait;
+use serde_json::Value;
+
+use super::{LLMLayer, LLMRequest, LLMResponse, Next};
+use crate::{
+    config::{REDACTED, is_secret_key, redact_value},
+    errors::AgenticFlowError,
+    model::{ChatResponse, CompletionResponse, FinishReason, Usage},
+};
+
+/// Gets each chat and completion request a client sends.
+type RequestObserver = Arc<dyn Fn(&LLMRequestLog) + Send + Sync>;
+
+/// The layer of [`LLMClient::with_request_observer`](super::LLMClient::with_request_observer):
+/// calls its observer with each request the layers after it sent, with credentials
+/// redacted, and how it ended.
+#[derive(Clone)]
+pub struct LoggingLayer {
+    observer: RequestObserver,
+}
+
+impl LoggingLayer {
+    pub fn new(observer: impl Fn(&LLMRequestLog) + Send + Sync + 'static) -> Self {
+        Self {
+            observer: Arc::new(observer),
+        }
+    }
+}
+
+#[async_trait]
+impl LLMLayer for LoggingLayer {
+    async fn call(
+        &self,
+        request: LLMRequest,
+        next: Next<'_>,
+    ) -> Result<LLMResponse, AgenticFlowError> {
+        let operation = request.operation();
+        let started = Instant::now();
+        let (result, sent) = recording(next.run(request)).await;
+        let outcome = result
+            .as_ref()
+            .map(|response| match response {
+                LLMResponse::Chat(response) => ResponseSummary::of_chat(response.as_ref()),
+                LLMResponse::Completion(response) => {
+                    ResponseSummary::of_completion(response.as_ref())
+                }
+            })
+            .map_err(ToString::to_string);
+        (self.observer)(&sent.into_log(operation, started.elapsed(), outcome));
+        result
+    }
+}
+
+/// A chat or completion request as the provider got it, and how it ended. Retries of the
+/// request are reported once, with the last attempt.
+#[derive(Debug, Clone)]
+pub struct LLMRequestLog {
+    /// `llm chat` or `llm completion`.
+    pub operation: &'static str,
+    /// The URL the request went to; `None` when the provider sent no HTTP request.
+    pub endpoint: Option<String>,
+    /// The request headers, with credentials replaced by [`REDACTED`].
+    pub headers: Vec<(String, String)>,
+    /// The JSON body of the request, with credentials replaced by [`REDACTED`].
+    pub request: Option<Value>,
+    /// The HTTP status of the answer, if there was one.
+    pub status: Option<u16>,
+    /// How long the request took, including retries and waiting for the rate limits.
+    pub duration: Duration,
+    /// A summary of the response, or the message of the error the request failed with.
+    pub outcome: Result<ResponseSummary, String>,
+}
+
+/// The parts of a response worth logging.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ResponseSummary {
+    /// The model the provider says served the request.
+    pub model: Option<String>,
+    pub finish_reason: Option<FinishReason>,
+    pub usage: Option<Usage>,
+    /// The characters of the answer's text.
+    pub content_chars: usize,
+    /// The names of the tools the answer calls.
+    pub tool_calls: Vec<String>,
+}
+
+impl ResponseSummary {
+    fn of_chat(response: &dyn ChatResponse) -> Self {
+        let message = response.message();
+        Self {
+            model: response.model().map(str::to_string),
+            finish_reason: response.finish_reason(),
+            usage: response.usage(),
+            content_chars: message.content.chars().count(),
+            tool_calls: message
+                .tool_calls
+                .iter()
+                .flatten()
+                .map(|call| call.function.name.clone())
+                .collect(),
+        }
+    }
+
+    fn of_completion(response: &dyn CompletionResponse) -> Self {
+        Self {
+            usage: response.usage(),
+            content_chars: response.response().chars().count(),
+            ..Self::default()
+        }
+    }
+}
+
+/// The request a provider sent inside [`recording`].
+#[derive(Default)]
+struct SentRequest {
+    endpoint: Option<String>,
+    headers: Vec<(String, String)>,
+    request: Option<Value>,
+    status: Option<u16>,
+}
+
+impl SentRequest {
+    fn into_log(
+        self,
+        operation: &'static str,
+        duration: Duration,
+        outcome: Result<ResponseSummary, String>,
+    ) -> LLMRequestLog {
+        LLMRequestLog {
+            operation,
+            endpoint: self.endpoint,
+            headers: self.headers,
+            request: self.request,
+            status: self.status,
+            duration,
+            outcome,
+        }
+    }
+}
+
+tokio::task_local! {
+    static SENT_REQUEST: Arc<Mutex<SentRequest>>;
+}
+
+/// Runs `future` and returns its output with the last request sent inside it.
+async fn recording<F: Future>(future: F) -> (F::Output, SentRequest) {
+    let sent = Arc::new(Mutex::new(SentRequest::default()));
+    let output = SENT_REQUEST.scope(sent.clone(), future).await;
+    let sent = std::mem::take(&mut *sent.lock().unwrap_or_else(|poisoned| poisoned.into_inner()));
+    (output, sent)
+}
+
+fn with_sent(update: impl FnOnce(&mut SentRequest)) {
+    let _ = SENT_REQUEST.try_with(|sent| {
+        update(&mut sent.lock().unwrap_or_else(|poisoned| poisoned.into_inner()));
+    });
+}
+
+/// Keeps a redacted copy of a request about to be sent, inside [`recording`]. Outside of
+/// it, nothing is copied.
+pub(super) fn record_request(url: &str, headers: &[(String, String)], body: &Value) {
+    with_sent(|sent| {
+        *sent = SentRequest {
+            endpoint: Some(redacted_url(url)),
+            headers: headers
+                .iter()
+                .map(|(name, value)| (name.clone(), redacted_header(name, value)))
+                .collect(),
+            request: Some(redacted(body)),
+            status: None,
+        }
+    });
+}
+
+/// Keeps the status of the answer to the request last passed to [`record_request`].
+pub(super) fn record_status(status: u16) {
+    with_sent(|sent| sent.status = Some(status));
+}
+
+/// `value` with its token replaced, when it is a bearer token.
+fn redacted_bearer(value: &str) -> Option<String> {
+    let (scheme, token) = value.split_once(' ')?;
+    let is_token = !token.is_empty() && !token.contains(char::is_whitespace);
+    (is_token && scheme.eq_ignore_ascii_case("bearer")).then(|| format!("{} {}", scheme, REDACTED))
+}
+
+fn redacted_header(name: &str, value: &str) -> String {
+    match redacted_bearer(value) {
+        Some(redacted) => redacted,
+        None if is_secret_key(name) => REDACTED.to_string(),
+        None => value.to_string(),
+    }
+}
+
+/// `url` with the values of secret-named query parameters, such as Gemini's `key`,
+/// replaced.
+fn redacted_url(url: &str) -> String {
+    let Some((path, query)) = url.split_once('?') else {
+        return url.to_string();
+    };
+    let query: Vec<String> = query
+        .split('&')
+        .map(|pair| match pair.split_once('=') {
+            Some((name, _)) if is_secret_key(name) => format!("{}={}", name, REDACTED),
+            _ => pair.to_string(),
+        })
+        .collect();
+    format!("{}?{}", path, query.join("&"))
+}
+
+/// A copy of `value` with secret-named fields and bearer tokens replaced.
+fn redacted(value: &Value) -> Value {
+    let mut value = value.clone();
+    redact_value(&mut value);
+    replace_bearer_tokens(&mut value);
+    value
+}
+
+fn replace_bearer_tokens(value: &mut Value) {
+    match value {
+        Value::Object(map) => map.values_mut().for_each(replace_bearer_tokens),
+        Value::Array(items) => items.iter_mut().for_each(replace_bearer_tokens),
+        Value::String(text) => {
+            if let Some(redacted) = redacted_bearer(text) {
+                *text = redacted;
+            }
+        }
+        _ => {}
+    }
+}
@@ -0,0 +1,134 @@
+//! Retrying failed LLM requests with exponential backoff.
+
+use std::{
+    collections::hash_map::RandomState,
+    hash::{BuildHasher, Hasher},
+    time::Duration,
+};
+
+use async_trait::async_trait;
+
+use super::{LLMLayer, LLMRequest, LLMResponse, Next};
+use crate::errors::{AgenticFlowError, ErrorKind};
+
+/// How often [`LLMClient`](super::LLMClient) sends a request that failed with a
+/// retryable error: 429, 5xx, timeouts and connection errors, as decided by
+/// [`AgenticFlowError::is_retryable`]. Other errors, such as 400, 401 or 404, fail at
+/// once.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RetryPolicy {
+    /// Attempts including the first one; 1 disables retries.
+    pub max_attempts: usize,
+    /// Delay before the second attempt, doubled for every attempt after that.
+    pub base_delay: Duration,
+    /// Upper bound of the backoff delay, and of a `Retry-After` delay.
+    pub max_delay: Duration,
+    /// Waits a random 50–100% of the backoff delay, so clients failing together do not
+    /// retry together.
+    pub jitter: bool,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_attempts: 3,
+            base_delay: Duration::from_millis(500),
+            max_delay: Duration::from_secs(30),
+            jitter: true,
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// How long to wait after failed attempt number `attempt` (starting at 1). The
+    /// provider's `Retry-After` takes the place of the backoff delay, up to `max_delay`
+    /// too, so a server asking for a day does not stall the run for one.
+    pub fn delay(&self, attempt: usize, error: &AgenticFlowError) -> Duration {
+        if let ErrorKind::RateLimited {
+            retry_after: Some(retry_after),
+        } = error.kind()
+        {
+            return retry_after.min(self.max_delay);
+        }
+
+        let exponent = attempt.saturating_sub(1).min(31) as u32;
+        let backoff = self
+            .base_delay
+            .saturating_mul(2u32.saturating_pow(exponent))
+            .min(self.max_delay);
+        if self.jitter {
+            backoff.mul_f64(0.5 + random_fraction() / 2.0)
+        } else {
+            backoff
+        }
+    }
+}
+
+/// The layer of [`LLMClient::with_retry`](super::LLMClient::with_retry): sends the
+/// request again through the layers after it while `policy` allows. A request that fails
+/// after retries does so with [`AgenticFlowError::RetriesExhausted`].
+#[derive(Debug, Clone)]
+pub struct RetryLayer {
+    policy: RetryPolicy,
+}
+
+impl RetryLayer {
+    pub fn new(policy: RetryPolicy) -> Self {
+        Self { policy }
+    }
+
+    pub fn policy(&self) -> &RetryPolicy {
+        &self.policy
+    }
+}
+
+#[async_trait]
+impl LLMLayer for RetryLayer {
+    async fn call(
+        &self,
+        request: LLMRequest,
+        next: Next<'_>,
+    ) -> Result<LLMResponse, AgenticFlowError> {
+        retrying(Some(&self.policy), || next.run(request.clone())).await
+    }
+}
+
+/// Runs `attempt` until it succeeds or `policy` gives up, waiting the policy's delay in
+/// between. Without a policy, `attempt` runs once.
+pub(super) async fn retrying<T, F>(
+    policy: Option<&RetryPolicy>,
+    mut attempt: impl FnMut() -> F,
+) -> Result<T, AgenticFlowError>
+where
+    F: Future<Output = Result<T, AgenticFlowError>>,
+{
+    let mut attempts = 1;
+    loop {
+        let error = match attempt().await {
+            Ok(response) => return Ok(response),
+            // Not retried: waiting again is what the queue timeout gave up on.
+            Err(error @ AgenticFlowError::QueueTimeout { .. }) => return Err(error),
+            Err(error) => error,
+        };
+        let retry = policy.filter(|policy| error.is_retryable() && attempts < policy.max_attempts);
+        match retry {
+            Some(policy) => {
+                tokio::time::sleep(policy.delay(attempts, &error)).await;
+                attempts += 1;
+            }
+            None if attempts > 1 => {
+                return Err(AgenticFlowError::RetriesExhausted {
+                    attempts,
+                    source: Box::new(error),
+                });
+            }
+            None => return Err(error),
+        }
+    }
+}
+
+/// A number in `[0, 1)` from the randomly seeded standard hasher.
+fn random_fraction() -> f64 {
+    let random = RandomState::new().build_hasher().finish();
+    (random >> 11) as f64 / (1u64 << 53) as f64
+}
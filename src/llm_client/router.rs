@@ -0,0 +1,103 @@
+//! Picking the client for each kind of call, e.g. a small model for planning and a
+//! stronger one for the final answer.
+
+use std::collections::HashMap;
+
+use super::{CostTracker, LLMClient, UsageReport};
+use crate::errors::AgenticFlowError;
+
+/// What a call to the LLM is for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Purpose {
+    /// The calls of the planners that produce a plan.
+    Planning,
+    /// The call of the agent that turns the results of a run into its answer.
+    Synthesis,
+    /// The calls that critique and revise a plan.
+    Reflection,
+    /// Any other call, and those of purposes without a client of their own.
+    Default,
+}
+
+/// Clients keyed by [`Purpose`]. A purpose without a client uses the default one, so a
+/// router made from a single [`LLMClient`] sends every call to it.
+#[derive(Clone)]
+pub struct LLMRouter {
+    default: LLMClient,
+    routes: HashMap<Purpose, LLMClient>,
+}
+
+impl LLMRouter {
+    pub fn new(default: LLMClient) -> Self {
+        Self {
+            default,
+            routes: HashMap::new(),
+        }
+    }
+
+    /// Sends the calls for `purpose` to `client`.
+    pub fn with_client(mut self, purpose: Purpose, client: LLMClient) -> Self {
+        match purpose {
+            Purpose::Default => self.default = client,
+            purpose => {
+                self.routes.insert(purpose, client);
+            }
+        }
+        self
+    }
+
+    /// The client for `purpose`.
+    pub fn client(&self, purpose: Purpose) -> &LLMClient {
+        self.routes.get(&purpose).unwrap_or(&self.default)
+    }
+
+    /// The calls, tokens and cost of all clients so far, by model. Clients that share a
+    /// [`CostTracker`] are counted once.
+    pub fn usage_report(&self) -> UsageReport {
+        let mut trackers: Vec<&CostTracker> = Vec::new();
+        for client in self.clients() {
+            let tracker = client.cost_tracker();
+            if !trackers
+                .iter()
+                .any(|seen| seen.shares_records_with(tracker))
+            {
+                trackers.push(tracker);
+            }
+        }
+        let mut report = UsageReport::default();
+        for tracker in trackers {
+            report.merge(tracker.report());
+        }
+        report
+    }
+
+    /// [`LLMClient::verify`]s every client.
+    pub async fn verify(&self) -> Result<(), AgenticFlowError> {
+        for client in self.clients() {
+            client.verify().await?;
+        }
+        Ok(())
+    }
+
+    /// Applies `f` to every client.
+    pub(crate) fn map_clients(self, f: impl Fn(LLMClient) -> LLMClient) -> Self {
+        Self {
+            default: f(self.default),
+            routes: self
+                .routes
+                .into_iter()
+                .map(|(purpose, client)| (purpose, f(client)))
+                .collect(),
+        }
+    }
+
+    fn clients(&self) -> impl Iterator<Item = &LLMClient> {
+        std::iter::once(&self.default).chain(self.routes.values())
+    }
+}
+
+impl From<LLMClient> for LLMRouter {
+    fn from(client: LLMClient) -> Self {
+        Self::new(client)
+    }
+}
@@ -0,0 +1,103 @@
+//! Limits on how many requests a client and its clones send at once and per minute.
+
+use std::{
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use tokio::sync::{OwnedSemaphorePermit, Semaphore};
+
+use crate::errors::AgenticFlowError;
+
+/// Allows `per_second` requests a second on average, in bursts of up to a second's worth.
+#[derive(Debug)]
+struct TokenBucket {
+    per_second: f64,
+    capacity: f64,
+    tokens: f64,
+    refilled: Instant,
+}
+
+impl TokenBucket {
+    fn new(requests_per_minute: u32) -> Self {
+        let per_second = f64::from(requests_per_minute.max(1)) / 60.0;
+        let capacity = per_second.max(1.0);
+        Self {
+            per_second,
+            capacity,
+            tokens: capacity,
+            refilled: Instant::now(),
+        }
+    }
+
+    /// Takes a token, or says how long until there is one.
+    fn take(&mut self) -> Result<(), Duration> {
+        let now = Instant::now();
+        let elapsed = now.duration_since(self.refilled).as_secs_f64();
+        self.tokens = (self.tokens + elapsed * self.per_second).min(self.capacity);
+        self.refilled = now;
+        if self.tokens >= 1.0 {
+            self.tokens -= 1.0;
+            return Ok(());
+        }
+        Err(Duration::from_secs_f64(
+            (1.0 - self.tokens) / self.per_second,
+        ))
+    }
+}
+
+/// The limits of a client, shared by its clones. Requests beyond them wait their turn.
+#[derive(Debug, Clone, Default)]
+pub(super) struct Throttle {
+    concurrency: Option<Arc<Semaphore>>,
+    rate: Option<Arc<Mutex<TokenBucket>>>,
+    pub(super) queue_timeout: Option<Duration>,
+}
+
+/// Held while a request is in flight.
+pub(super) struct Slot {
+    _permit: Option<OwnedSemaphorePermit>,
+}
+
+impl Throttle {
+    pub(super) fn with_max_concurrency(mut self, max_in_flight: usize) -> Self {
+        self.concurrency = Some(Arc::new(Semaphore::new(max_in_flight.max(1))));
+        self
+    }
+
+    pub(super) fn with_rate_limit(mut self, requests_per_minute: u32) -> Self {
+        self.rate = Some(Arc::new(Mutex::new(TokenBucket::new(requests_per_minute))));
+        self
+    }
+
+    /// Waits for a free slot and, with a rate limit, for the request's turn. Fails with
+    /// [`AgenticFlowError::QueueTimeout`] if that takes longer than the queue timeout.
+    pub(super) async fn acquire(&self) -> Result<Slot, AgenticFlowError> {
+        match self.queue_timeout {
+            Some(limit) => tokio::time::timeout(limit, self.wait())
+                .await
+                .map_err(|_| AgenticFlowError::QueueTimeout { waited: limit }),
+            None => Ok(self.wait().await),
+        }
+    }
+
+    async fn wait(&self) -> Slot {
+        let permit = match &self.concurrency {
+            Some(semaphore) => semaphore.clone().acquire_owned().await.ok(),
+            None => None,
+        };
+        if let Some(bucket) = &self.rate {
+            loop {
+                let taken = bucket
+                    .lock()
+                    .unwrap_or_else(|poisoned| poisoned.into_inner())
+                    .take();
+                match taken {
+                    Ok(()) => break,
+                    Err(wait) => tokio::time::sleep(wait).await,
+                }
+            }
+        }
+        Slot { _permit: permit }
+    }
+}
@@ -0,0 +1,296 @@
+//! Tool calls for models without native function calling: the tools are described in
+//! the system prompt, and the JSON the model answers with is read back as tool calls.
+
+use std::collections::HashMap;
+
+use async_trait::async_trait;
+use serde::Deserialize;
+use serde_json::{Value, json};
+
+use super::{LLMLayer, LLMRequest, LLMResponse, Next, ToolChoice, typed};
+use crate::{
+    errors::AgenticFlowError,
+    json_repair::parse_lenient,
+    model::{
+        ChatMessage, ChatResponse, FinishReason, ProviderStats, Role, ToolCall, Usage,
+        decode_arguments,
+    },
+};
+
+/// When [`LLMClient`](super::LLMClient) emulates tool calls with
+/// [`ToolEmulationLayer`], as set by
+/// [`with_tool_emulation`](super::LLMClient::with_tool_emulation).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ToolEmulation {
+    /// Emulates them for models whose [`capabilities`](super::LLMClient::capabilities)
+    /// say they do not support tools.
+    #[default]
+    Auto,
+    /// Emulates them for every model.
+    Always,
+    /// Sends the tools as they are, even to models that ignore them.
+    Never,
+}
+
+/// The layer of [`with_tool_emulation`](super::LLMClient::with_tool_emulation): sends
+/// chat requests with tools without them, describing the tools and how to call them in
+/// the system prompt, and turns a JSON answer, raw or in a code fence, into the
+/// [`ToolCall`]s of the returned message, repairing almost-valid JSON. A plain text
+/// answer is returned as it is.
+///
+/// Earlier tool calls and their results in the conversation are rewritten as the text
+/// the model would have written and user messages. An answer that looks like JSON but
+/// cannot be repaired or names no known tool gets one corrective request with the error
+/// before failing with [`AgenticFlowError::ParseError`] holding the answer.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ToolEmulationLayer;
+
+#[async_trait]
+impl LLMLayer for ToolEmulationLayer {
+    async fn call(
+        &self,
+        request: LLMRequest,
+        next: Next<'_>,
+    ) -> Result<LLMResponse, AgenticFlowError> {
+        let (messages, tools, mut settings) = match request {
+            LLMRequest::Chat {
+                messages,
+                tools,
+                settings,
+            } if !tools.is_empty() => (messages, tools, settings),
+            request => return next.run(request).await,
+        };
+        let tools: Vec<Value> = match &settings.tool_choice {
+            ToolChoice::None => Vec::new(),
+            ToolChoice::Specific(name) => tools
+                .into_iter()
+                .filter(|tool| tool["function"]["name"].as_str() == Some(name.as_str()))
+                .collect(),
+            ToolChoice::Auto | ToolChoice::Required => tools,
+        };
+        let mut messages = plain_history(messages);
+        if !tools.is_empty() {
+            add_instructions(&mut messages, &tools_prompt(&tools, &settings.tool_choice));
+        }
+        settings.tool_choice = ToolChoice::Auto;
+        settings.parallel_tool_calls = None;
+
+        let mut corrected = false;
+        loop {
+            let request = LLMRequest::Chat {
+                messages: messages.clone(),
+                tools: Vec::new(),
+                settings: settings.clone(),
+            };
+            let response = next.run(request).await?.into_chat()?;
+            let answer = typed::json_text(response.message());
+            let calls_tools = answer.starts_with(['{', '[']) || answer.contains("tool_calls");
+            if tools.is_empty() || !calls_tools {
+                return Ok(LLMResponse::Chat(response));
+            }
+            let error = match tool_calls(&answer, &tools) {
+                Ok(calls) => {
+                    let mut message = response.message().clone();
+                    message.content = String::new();
+                    message.tool_calls = Some(calls);
+                    return Ok(LLMResponse::Chat(Box::new(EmulatedResponse {
+                        response,
+                        message,
+                    })));
+                }
+                Err(error) => error,
+            };
+            if corrected {
+                return Err(error);
+            }
+            messages.push(ChatMessage::assistant(response.message().content.clone()));
+            messages.push(ChatMessage::user(format!(
+                "Your tool call could not be read: {}. Respond again with only the JSON \
+                 object, or with plain text to answer without calling a tool.",
+                error
+            )));
+            corrected = true;
+        }
+    }
+}
+
+/// Describes `tools` and the JSON that calls them.
+fn tools_prompt(tools: &[Value], tool_choice: &ToolChoice) -> String {
+    let schemas: Vec<String> = tools
+        .iter()
+        .map(|tool| tool["function"].to_string())
+        .collect();
+    let mut prompt = format!(
+        "You can call these tools, given as JSON schemas:\n{}\n\n\
+         To call tools, respond with only a JSON object of this shape, without any other \
+         text:\n{{\"tool_calls\": [{{\"name\": \"tool_name\", \"arguments\": {{}}}}]}}\n\
+         To answer without calling a tool, respond with plain text.",
+        schemas.join("\n")
+    );
+    match tool_choice {
+        ToolChoice::Required => prompt.push_str(" You must call at least one tool."),
+        ToolChoice::Specific(name) => {
+            prompt.push_str(&format!(" You must call the tool {}.", name))
+        }
+        ToolChoice::Auto | ToolChoice::None => {}
+    }
+    prompt
+}
+
+/// Appends `instructions` to the leading system message, or adds one with them.
+fn add_instructions(messages: &mut Vec<ChatMessage>, instructions: &str) {
+    match messages
+        .first_mut()
+        .filter(|first| first.role == Role::System)
+    {
+        Some(system) => {
+            system.content.push_str("\n\n");
+            system.content.push_str(instructions);
+        }
+        None => messages.insert(0, ChatMessage::system(instructions.to_string())),
+    }
+}
+
+/// `messages` without tool calls and tool results, which a model without tool support
+/// may reject: calls become the JSON that makes them, results user messages.
+fn plain_history(messages: Vec<ChatMessage>) -> Vec<ChatMessage> {
+    let mut names: HashMap<String, String> = HashMap::new();
+    messages
+        .into_iter()
+        .map(|mut message| {
+            if let Some(calls) = message.tool_calls.take().filter(|calls| !calls.is_empty()) {
+                for call in &calls {
+                    if let Some(id) = &call.id {
+                        names.insert(id.clone(), call.function.name.clone());
+                    }
+                }
+                let calls: Vec<Value> = calls
+                    .iter()
+                    .map(|call| {
+                        json!({
+                            "name": call.function.name,
+                            "arguments": call.function.arguments,
+                        })
+                    })
+                    .collect();
+                let json = json!({ "tool_calls": calls }).to_string();
+                message.content = match message.content.trim() {
+                    "" => json,
+                    content => format!("{}\n{}", content, json),
+                };
+                return message;
+            }
+            if message.role != Role::Tool {
+                return message;
+            }
+            let name = message.name.clone().or_else(|| {
+                message
+                    .tool_call_id
+                    .as_ref()
+                    .and_then(|id| names.get(id).cloned())
+            });
+            let content = match name {
+                Some(name) => format!("Result of the tool {}: {}", name, message.content),
+                None => format!("Result of the tool call: {}", message.content),
+            };
+            ChatMessage::user(content)
+        })
+        .collect()
+}
+
+/// The calls the model wrote, as JSON of the shape [`tools_prompt`] asks for, a list of
+/// calls, or a single call.
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum EmulatedCalls {
+    Wrapped { tool_calls: Vec<EmulatedCall> },
+    Many(Vec<EmulatedCall>),
+    One(EmulatedCall),
+}
+
+#[derive(Deserialize)]
+struct EmulatedCall {
+    name: String,
+    #[serde(default)]
+    arguments: Value,
+}
+
+/// The tool calls of `answer`, failing with what is wrong with it.
+/// Almost-valid JSON is repaired, and the calls are marked as
+/// [`repaired`](crate::model::Function::repaired).
+fn tool_calls(answer: &str, tools: &[Value]) -> Result<Vec<ToolCall>, AgenticFlowError> {
+    let invalid = |reason: String| {
+        AgenticFlowError::ParseError(format!("{} in tool call answer: {}", reason, answer))
+    };
+    let json = parse_lenient(answer)?;
+    let calls = match serde_json::from_value(json.value).map_err(|_| {
+        invalid("expected an object with tool_calls, each with a name and arguments".to_string())
+    })? {
+        EmulatedCalls::Wrapped { tool_calls } | EmulatedCalls::Many(tool_calls) => tool_calls,
+        EmulatedCalls::One(call) => vec![call],
+    };
+    if calls.is_empty() {
+        return Err(invalid("no tool calls".to_string()));
+    }
+    calls
+        .into_iter()
+        .enumerate()
+        .map(|(index, call)| {
+            let known = tools
+                .iter()
+                .any(|tool| tool["function"]["name"].as_str() == Some(call.name.as_str()));
+            if !known {
+                return Err(invalid(format!("unknown tool '{}'", call.name)));
+            }
+            let (arguments, repaired) = match call.arguments {
+                Value::Null => (json!({}), false),
+                arguments => decode_arguments(arguments)?,
+            };
+            let mut tool_call =
+                ToolCall::new(call.name, arguments).with_id(format!("call_{}", index + 1));
+            tool_call.function.repaired = json.repaired || repaired;
+            Ok(tool_call)
+        })
+        .collect()
+}
+
+/// A response whose message holds the tool calls read from its text.
+#[derive(Debug)]
+struct EmulatedResponse {
+    response: Box<dyn ChatResponse>,
+    message: ChatMessage,
+}
+
+impl ChatResponse for EmulatedResponse {
+    fn message(&self) -> &ChatMessage {
+        &self.message
+    }
+
+    fn finish_reason(&self) -> Option<FinishReason> {
+        Some(FinishReason::ToolCalls)
+    }
+
+    fn model(&self) -> Option<&str> {
+        self.response.model()
+    }
+
+    fn usage(&self) -> Option<Usage> {
+        self.response.usage()
+    }
+
+    fn stats(&self) -> Option<ProviderStats> {
+        self.response.stats()
+    }
+
+    fn raw(&self) -> Option<&Value> {
+        self.response.raw()
+    }
+
+    fn take_raw(&mut self) -> Option<Value> {
+        self.response.take_raw()
+    }
+
+    fn fallback(&self) -> Option<usize> {
+        self.response.fallback()
+    }
+}
@@ -0,0 +1,46 @@
+//! Answers read as JSON into a Rust type.
+
+use serde_json::Value;
+
+use crate::{errors::AgenticFlowError, model::ChatMessage};
+
+/// The longest part of an answer quoted in a parse error.
+const QUOTED_ANSWER_CHARS: usize = 200;
+
+/// Tells the model to answer with JSON matching `schema`, for providers that cannot be
+/// made to and as a reminder for those that can.
+pub(super) fn schema_prompt(schema: &Value) -> ChatMessage {
+    ChatMessage::system(format!(
+        "Respond only with a JSON value matching this JSON schema, without any other text:\n{}",
+        schema
+    ))
+}
+
+/// Asks the model to answer again after its answer failed to deserialize with `error`.
+pub(super) fn correction_prompt(error: &serde_json::Error) -> ChatMessage {
+    ChatMessage::user(format!(
+        "Your answer could not be read: {}. Respond again with only a JSON value matching the schema.",
+        error
+    ))
+}
+
+/// The JSON of an answer: its text without `<think>` blocks, and the inside of the first
+/// code fence when there is one.
+pub(super) fn json_text(message: &ChatMessage) -> String {
+    let text = message.text();
+    let Some(start) = text.find("```") else {
+        return text.trim().to_string();
+    };
+    let fenced = &text[start + "```".len()..];
+    // Skips the language tag of the fence, e.g. `json`.
+    let body = fenced.split_once('\n').map_or(fenced, |(_, body)| body);
+    let body = body.find("```").map_or(body, |end| &body[..end]);
+    body.trim().to_string()
+}
+
+/// The [`AgenticFlowError::ParseError`] for an answer that failed to deserialize with
+/// `error`, quoting the start of the answer.
+pub(super) fn parse_error(error: serde_json::Error, answer: &str) -> AgenticFlowError {
+    let quoted: String = answer.chars().take(QUOTED_ANSWER_CHARS).collect();
+    AgenticFlowError::ParseError(format!("{} in answer: {}", error, quoted))
+}
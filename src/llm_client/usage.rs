@@ -0,0 +1,180 @@
+//! Summing the tokens spent and the time taken by the LLM requests of a run, and holding
+//! a run to its [`Budget`].
+
+use std::sync::{Arc, Mutex};
+
+use crate::{
+    errors::{AgenticFlowError, BudgetOverrun},
+    model::{ProviderStats, Usage},
+};
+
+tokio::task_local! {
+    static RUN: Arc<Run>;
+}
+
+/// What the LLM responses of a run add up to, as [`summing_run`] sums them.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct RunTotals {
+    /// The responses received, including those that reported neither usage nor stats.
+    pub responses: usize,
+    /// The tokens spent, or `None` if no response reported any.
+    pub usage: Option<Usage>,
+    /// The provider timings, or `None` if no response reported any, e.g. for providers
+    /// other than Ollama and llama.cpp.
+    pub stats: Option<ProviderStats>,
+    /// In USD, for the responses of models in the
+    /// [`CostTracker`](super::CostTracker)'s price table; `None` if there were none.
+    pub cost_usd: Option<f64>,
+}
+
+impl RunTotals {
+    fn add(&mut self, other: RunTotals) {
+        self.responses += other.responses;
+        self.usage = sum(self.usage, other.usage);
+        self.stats = sum(self.stats, other.stats);
+        self.cost_usd = sum(self.cost_usd, other.cost_usd);
+    }
+}
+
+fn sum<T: std::ops::Add<Output = T>>(left: Option<T>, right: Option<T>) -> Option<T> {
+    match (left, right) {
+        (Some(left), Some(right)) => Some(left + right),
+        (left, right) => left.or(right),
+    }
+}
+
+/// The most a run may spend, as given to [`summing_run_within`] or with
+/// [`RunOptions::budget`](crate::RunOptions::budget). Each LLM call of the run checks
+/// what the run has spent first, and fails with
+/// [`AgenticFlowError::BudgetExceeded`] once a limit is reached; the call that reaches it
+/// is not stopped, so a run may end somewhat above its budget.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Budget {
+    /// The most prompt and completion tokens, as the responses report them.
+    pub max_total_tokens: Option<u32>,
+    /// The most USD, as priced by the [`CostTracker`](super::CostTracker) of each client.
+    /// Calls to models without a price cost nothing.
+    pub max_cost_usd: Option<f64>,
+}
+
+impl Budget {
+    /// A budget of `max_total_tokens` tokens.
+    pub fn tokens(max_total_tokens: u32) -> Self {
+        Self {
+            max_total_tokens: Some(max_total_tokens),
+            ..Self::default()
+        }
+    }
+
+    /// A budget of `max_cost_usd` USD.
+    pub fn cost_usd(max_cost_usd: f64) -> Self {
+        Self {
+            max_cost_usd: Some(max_cost_usd),
+            ..Self::default()
+        }
+    }
+
+    /// Whether a run that spent `totals` may not make another call.
+    pub fn is_spent_by(&self, totals: &RunTotals) -> bool {
+        let tokens = totals.usage.map_or(0, |usage| usage.total_tokens);
+        let cost = totals.cost_usd.unwrap_or(0.0);
+        self.max_total_tokens.is_some_and(|max| tokens >= max)
+            || self.max_cost_usd.is_some_and(|max| cost >= max)
+    }
+}
+
+/// A run in progress, inside the run enclosing it, if any.
+struct Run {
+    totals: Mutex<RunTotals>,
+    budget: Option<Budget>,
+    outer: Option<Arc<Run>>,
+}
+
+impl Run {
+    fn totals(&self) -> RunTotals {
+        *self
+            .totals
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    /// This run and the runs enclosing it, innermost first.
+    fn chain(&self) -> impl Iterator<Item = &Run> {
+        std::iter::successors(Some(self), |run| run.outer.as_deref())
+    }
+}
+
+/// Runs `future` and returns its output with the totals of every LLM response received
+/// inside it, such as the planner and agent calls of a run:
+///
+/// ```rust,no_run
+/// # use agentic_flow_lib::planner::Planner;
+/// # async fn example(planner: &dyn Planner) {
+/// use agentic_flow_lib::llm_client::summing_run;
+///
+/// let (plan, totals) = summing_run(planner.plan("Find the latest release")).await;
+/// if let Some(stats) = totals.stats {
+///     println!("{:?} for {} responses", stats.total_duration, totals.responses);
+/// }
+/// # }
+/// ```
+///
+/// Runs may be nested; the responses of an inner run also count for the outer one.
+pub async fn summing_run<F: Future>(future: F) -> (F::Output, RunTotals) {
+    run(None, future).await
+}
+
+/// Like [`summing_run`], failing the LLM calls of `future` with
+/// [`AgenticFlowError::BudgetExceeded`] once they have spent `budget`. The budgets of
+/// enclosing runs still apply.
+pub async fn summing_run_within<F: Future>(budget: Budget, future: F) -> (F::Output, RunTotals) {
+    run(Some(budget), future).await
+}
+
+async fn run<F: Future>(budget: Option<Budget>, future: F) -> (F::Output, RunTotals) {
+    let run = Arc::new(Run {
+        totals: Mutex::default(),
+        budget,
+        outer: RUN.try_with(Arc::clone).ok(),
+    });
+    let output = RUN.scope(run.clone(), future).await;
+    (output, run.totals())
+}
+
+/// Adds a response to the totals of the enclosing runs, if there are any.
+pub(super) fn record(usage: Option<Usage>, stats: Option<ProviderStats>, cost_usd: Option<f64>) {
+    let response = RunTotals {
+        responses: 1,
+        usage,
+        stats,
+        cost_usd,
+    };
+    let _ = RUN.try_with(|run| {
+        for run in run.chain() {
+            run.totals
+                .lock()
+                .unwrap_or_else(|poisoned| poisoned.into_inner())
+                .add(response);
+        }
+    });
+}
+
+/// Fails with [`AgenticFlowError::BudgetExceeded`] when an enclosing run has spent its
+/// budget.
+pub(super) fn check_budget() -> Result<(), AgenticFlowError> {
+    RUN.try_with(|run| {
+        for run in run.chain() {
+            let totals = run.totals();
+            if let Some(budget) = run.budget.filter(|budget| budget.is_spent_by(&totals)) {
+                return Err(AgenticFlowError::BudgetExceeded(Box::new(BudgetOverrun {
+                    budget,
+                    totals,
+                    completed: Vec::new(),
+                    context: Default::default(),
+                })));
+            }
+        }
+        Ok(())
+    })
+    .unwrap_or(Ok(()))
+}
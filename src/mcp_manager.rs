@@ -1,14 +1,10 @@
-use rmcp::{
-    RoleClient, ServiceExt,
-    service::RunningService,
-    transport::{ConfigureCommandExt, TokioChildProcess},
-};
+use rmcp::{RoleClient, ServiceExt, service::RunningService, transport::TokioChildProcess};
 
 use std::collections::HashMap;
 use tokio::process::Command;
 
 use crate::{
-    config::{MCPConfig, ServerType},
+    config::{MCPConfig, ServerConfig},
     errors::AgenticFlowError,
 };
 
@@ -20,6 +16,26 @@ pub struct MCPTool {
     pub server_name: String,
 }
 
+/// Builds the process command for a server: the type-specific base command
+/// followed by `args`, with `env` applied.
+pub fn build_server_command(server_config: &ServerConfig) -> Result<Command, AgenticFlowError> {
+    let (program, args) = server_config.command_line()?;
+
+    let mut command = Command::new(program);
+    command.args(args).envs(&server_config.env);
+
+    Ok(command)
+}
+
+/// The error for a server that is configured but not (or no longer) running.
+pub(crate) fn not_running(server_name: &str) -> AgenticFlowError {
+    AgenticFlowError::mcp_server(
+        server_name,
+        None,
+        AgenticFlowError::ExecutionError("Server is not running".to_string()),
+    )
+}
+
 pub struct MCPManager {
     active_servers: HashMap<String, RunningService<RoleClient, ()>>,
     config: MCPConfig,
@@ -33,42 +49,41 @@ impl MCPManager {
         }
     }
 
+    pub fn config(&self) -> &MCPConfig {
+        &self.config
+    }
+
+    /// Replaces the server definitions used by subsequent [`MCPManager::start_server`] calls.
+    /// Servers that are already running are left untouched.
+    pub fn set_config(&mut self, config: MCPConfig) {
+        self.config = config;
+    }
+
     pub async fn start_server(&mut self, server_name: &str) -> Result<(), AgenticFlowError> {
         let server_config = self.config.servers.get(server_name).ok_or_else(|| {
-            AgenticFlowError::ToolError(format!("Server config not found: {}", server_name))
+            AgenticFlowError::mcp_server(
+                server_name,
+                None,
+                AgenticFlowError::ConfigError("Server config not found".to_string()),
+            )
         })?;
 
-        let service = match server_config.server_type {
-            ServerType::Python => {
-                let module_name = server_config.module_name.as_ref().ok_or_else(|| {
-                    AgenticFlowError::ToolError("Python module name required".to_string())
-                })?;
-                ().serve(
-                    TokioChildProcess::new(Command::new("python").configure(|cmd| {
-                        cmd.arg("-m").arg(module_name);
-                    }))
-                    .map_err(|e| {
-                        AgenticFlowError::ToolError(format!("Failed to start Python server: {}", e))
-                    })?,
-                )
-                .await
-            }
-            ServerType::Node => {
-                let package_name = server_config.package_name.as_ref().ok_or_else(|| {
-                    AgenticFlowError::ToolError("Node package name required".to_string())
-                })?;
-                ().serve(
-                    TokioChildProcess::new(Command::new("npx").configure(|cmd| {
-                        cmd.arg("-y").arg(package_name);
-                    }))
-                    .map_err(|e| {
-                        AgenticFlowError::ToolError(format!("Failed to start Node server: {}", e))
-                    })?,
-                )
-                .await
-            }
-        }
-        .unwrap();
+        let command = build_server_command(server_config)
+            .map_err(|e| AgenticFlowError::mcp_server(server_name, None, e))?;
+        let transport = TokioChildProcess::new(command).map_err(|e| {
+            AgenticFlowError::mcp_server(
+                server_name,
+                None,
+                AgenticFlowError::NetworkError(format!("Failed to start process: {}", e)),
+            )
+        })?;
+        let service = ().serve(transport).await.map_err(|e| {
+            AgenticFlowError::mcp_server(
+                server_name,
+                None,
+                AgenticFlowError::NetworkError(format!("Failed to initialize: {}", e)),
+            )
+        })?;
 
         self.active_servers.insert(server_name.to_string(), service);
 
@@ -78,10 +93,11 @@ impl MCPManager {
     pub async fn stop_server(&mut self, server_name: &str) -> Result<(), AgenticFlowError> {
         if let Some(service) = self.active_servers.remove(server_name) {
             service.cancel().await.map_err(|e| {
-                AgenticFlowError::ToolError(format!(
-                    "Failed to stop server '{}': {}",
-                    server_name, e
-                ))
+                AgenticFlowError::mcp_server(
+                    server_name,
+                    None,
+                    AgenticFlowError::ExecutionError(format!("Failed to stop: {}", e)),
+                )
             })?;
         }
         Ok(())
@@ -94,24 +110,26 @@ impl MCPManager {
         let service = self
             .active_servers
             .get(server_name)
-            .ok_or(AgenticFlowError::ServerNotFound)?;
-
-        if let Ok(tools) = service.list_tools(Default::default()).await {
-            Ok(tools
-                .tools
-                .into_iter()
-                .map(|tool| MCPTool {
-                    name: tool.name.clone().to_string(),
-                    description: tool.description.clone().unwrap_or_default().to_string(),
-                    input_schema: tool.schema_as_json_value(),
-                    server_name: server_name.to_string(),
-                })
-                .collect())
-        } else {
-            Err(AgenticFlowError::ToolError(
-                "Failed to list tools".to_string(),
-            ))
-        }
+            .ok_or_else(|| not_running(server_name))?;
+
+        let tools = service.list_tools(Default::default()).await.map_err(|e| {
+            AgenticFlowError::mcp_server(
+                server_name,
+                None,
+                AgenticFlowError::ToolError(format!("Failed to list tools: {}", e)),
+            )
+        })?;
+
+        Ok(tools
+            .tools
+            .into_iter()
+            .map(|tool| MCPTool {
+                name: tool.name.clone().to_string(),
+                description: tool.description.clone().unwrap_or_default().to_string(),
+                input_schema: tool.schema_as_json_value(),
+                server_name: server_name.to_string(),
+            })
+            .collect())
     }
 
     pub fn get_active_server_names(&self) -> Vec<String> {
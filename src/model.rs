@@ -1,102 +1,707 @@
-use std::fmt::Debug;
+use std::{
+    fmt::{self, Debug},
+    sync::LazyLock,
+};
 
-use serde::{ Deserialize, Serialize};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
 use serde_json::Value;
 
+use crate::{errors::AgenticFlowError, json_repair::parse_lenient};
+
+use stats::OllamaTimings;
+
+mod anthropic;
+mod gemini;
+mod history;
+mod llama_cpp;
+mod stats;
+mod stream;
+
+pub use anthropic::AnthropicResponse;
+pub use gemini::GeminiResponse;
+pub use history::{ConversationHistory, RetentionStrategy};
+pub use llama_cpp::LlamaCppResponse;
+pub use stats::ProviderStats;
+pub use stream::{
+    ChatStreamChunk, MessageAccumulator, MessageDelta, OllamaMessageFragment, OllamaStreamChunk,
+    OpenRouterDelta, OpenRouterFunctionDelta, OpenRouterStreamChoice, OpenRouterStreamChunk,
+    OpenRouterToolCallDelta, ToolCallDelta,
+};
+
 #[derive(Serialize, Deserialize)]
 pub struct ChatCompletionRequest {
     pub model: String,
     pub messages: Vec<ChatMessage>,
     pub temperature: f32,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_tokens: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub stop: Option<Vec<String>>,
+    /// In OpenAI's form; Ollama receives it as `format`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub response_format: Option<Value>,
+    /// In OpenAI's form; left out for providers without one.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tool_choice: Option<Value>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub parallel_tool_calls: Option<bool>,
     pub stream: bool,
     pub tools: Vec<Value>,
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(try_from = "WireFunction")]
 pub struct Function {
     pub name: String,
+    /// Always the decoded arguments: OpenAI-style providers send a JSON-encoded string,
+    /// which is parsed when the call is deserialized. Almost-valid JSON is repaired with
+    /// [`json_repair`](crate::json_repair), and JSON that cannot be fails the
+    /// deserialization.
+    #[serde(default)]
     pub arguments: Value,
+    /// Whether the arguments had to be repaired.
+    #[serde(skip)]
+    pub repaired: bool,
+}
+
+/// A [`Function`] as providers send it.
+#[derive(Deserialize)]
+struct WireFunction {
+    name: String,
+    #[serde(default)]
+    arguments: Value,
+}
+
+impl TryFrom<WireFunction> for Function {
+    type Error = AgenticFlowError;
+
+    fn try_from(function: WireFunction) -> Result<Self, Self::Error> {
+        let (arguments, repaired) = decode_arguments(function.arguments)?;
+        Ok(Self {
+            name: function.name,
+            arguments,
+            repaired,
+        })
+    }
+}
+
+/// Decodes arguments sent as a JSON-encoded string, and whether they had to be repaired.
+/// An empty string becomes `{}`, a string that is not JSON and has no object or array to
+/// repair is kept as is. Fails with [`AgenticFlowError::ParseError`] for an object or
+/// array that cannot be repaired.
+pub(crate) fn decode_arguments(arguments: Value) -> Result<(Value, bool), AgenticFlowError> {
+    match arguments {
+        Value::String(text) if text.trim().is_empty() => {
+            Ok((Value::Object(Default::default()), false))
+        }
+        Value::String(text) if text.contains(['{', '[']) => {
+            let json = parse_lenient(&text)?;
+            Ok((json.value, json.repaired))
+        }
+        Value::String(text) => Ok((
+            serde_json::from_str(&text).unwrap_or(Value::String(text)),
+            false,
+        )),
+        other => Ok((other, false)),
+    }
+}
+
+/// The [`decode_arguments`], keeping arguments that cannot be repaired as they are.
+pub(crate) fn normalize_arguments(arguments: Value) -> (Value, bool) {
+    decode_arguments(arguments.clone()).unwrap_or((arguments, false))
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug)]
 pub struct ToolCall {
+    /// Set by OpenAI-compatible providers, and referenced by the [`ChatMessage::tool`]
+    /// message carrying the result. Ollama sends none.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub id: Option<String>,
+    #[serde(rename = "type", default, skip_serializing_if = "Option::is_none")]
+    pub call_type: Option<String>,
     pub function: Function,
 }
 
+impl ToolCall {
+    pub fn new(name: String, arguments: Value) -> Self {
+        Self {
+            id: None,
+            call_type: None,
+            function: Function {
+                name,
+                arguments,
+                repaired: false,
+            },
+        }
+    }
+
+    pub fn with_id(mut self, id: String) -> Self {
+        self.id = Some(id);
+        self.call_type = Some("function".to_string());
+        self
+    }
+}
+
+/// Who a [`ChatMessage`] is from, written as the lowercase role name on the wire.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub enum Role {
+    System,
+    User,
+    Assistant,
+    Tool,
+    /// A role this crate does not know, kept so unusual provider responses still parse.
+    Other(String),
+}
+
+impl Role {
+    pub fn as_str(&self) -> &str {
+        match self {
+            Role::System => "system",
+            Role::User => "user",
+            Role::Assistant => "assistant",
+            Role::Tool => "tool",
+            Role::Other(role) => role,
+        }
+    }
+}
+
+impl From<&str> for Role {
+    fn from(role: &str) -> Self {
+        match role {
+            "system" => Role::System,
+            "user" => Role::User,
+            "assistant" => Role::Assistant,
+            "tool" => Role::Tool,
+            other => Role::Other(other.to_string()),
+        }
+    }
+}
+
+impl fmt::Display for Role {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+impl Serialize for Role {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(self.as_str())
+    }
+}
+
+impl<'de> Deserialize<'de> for Role {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let role = String::deserialize(deserializer)?;
+        Ok(Role::from(role.as_str()))
+    }
+}
+
 #[derive(Serialize, Deserialize, Clone, Debug)]
 pub struct ChatMessage {
-    pub role: String,
+    pub role: Role,
+    /// Empty for messages that only call tools, which OpenAI sends with `null` content.
+    #[serde(default, deserialize_with = "deserialize_content")]
     pub content: String,
     pub thinking: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub tool_calls: Option<Vec<ToolCall>>,
+    /// For [`Role::Tool`] messages: the [`ToolCall::id`] this is the result of.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tool_call_id: Option<String>,
+    /// For [`Role::Tool`] messages: the tool that produced the result.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    /// Images for vision models, sent in the shape each provider expects. Providers
+    /// without image input leave them out.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub images: Option<Vec<ImageData>>,
 }
 
-impl ChatMessage{
-    pub fn user(content: String) -> Self {
+/// An image attached to a [`ChatMessage`].
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct ImageData {
+    /// The MIME type, e.g. `image/png`.
+    pub media_type: String,
+    /// The image, base64-encoded.
+    pub data: String,
+}
+
+impl ImageData {
+    pub fn new(media_type: impl Into<String>, data: impl Into<String>) -> Self {
         Self {
-            role: "user".to_string(),
-            content,
-            thinking: None,
-            tool_calls: None,
+            media_type: media_type.into(),
+            data: data.into(),
         }
     }
 
-    pub fn assistant(content: String) -> Self {
-        Self {
-            role: "assistant".to_string(),
-            content,
-            thinking: None,
-            tool_calls: None,
+    /// The image as a `data:` URL, the way OpenAI-compatible providers take it.
+    pub fn data_url(&self) -> String {
+        format!("data:{};base64,{}", self.media_type, self.data)
+    }
+}
+
+fn deserialize_content<'de, D: Deserializer<'de>>(deserializer: D) -> Result<String, D::Error> {
+    Ok(Option::<String>::deserialize(deserializer)?.unwrap_or_default())
+}
+
+impl ChatMessage{
+    /// Starts a message with `role` and empty content.
+    pub fn builder(role: Role) -> ChatMessageBuilder {
+        ChatMessageBuilder {
+            message: ChatMessage {
+                role,
+                content: String::new(),
+                thinking: None,
+                tool_calls: None,
+                tool_call_id: None,
+                name: None,
+                images: None,
+            },
         }
     }
 
+    pub fn user(content: String) -> Self {
+        Self::builder(Role::User).content(content).build()
+    }
+
+    /// A user message showing `images` to the model along with `content`.
+    pub fn user_with_images(content: String, images: Vec<ImageData>) -> Self {
+        Self::builder(Role::User)
+            .content(content)
+            .images(images)
+            .build()
+    }
+
+    pub fn assistant(content: String) -> Self {
+        Self::builder(Role::Assistant).content(content).build()
+    }
+
     pub fn system(content: String) -> Self {
-        Self {
-            role: "system".to_string(),
-            content,
-            thinking: None,
-            tool_calls: None,
-        }
+        Self::builder(Role::System).content(content).build()
+    }
+
+    /// The result of the tool call with id `tool_call_id`.
+    pub fn tool(content: String, tool_call_id: String) -> Self {
+        Self::builder(Role::Tool)
+            .content(content)
+            .tool_call_id(tool_call_id)
+            .build()
+    }
+
+    /// Names the tool whose result this message carries.
+    pub fn with_name(mut self, name: String) -> Self {
+        self.name = Some(name);
+        self
     }
 
     pub fn with_tool_calls(mut self, tool_calls: Vec<ToolCall>) -> Self {
         self.tool_calls = Some(tool_calls);
         self
     }
+
+    /// Whether the message asks for at least one tool call.
+    pub fn is_tool_call(&self) -> bool {
+        self.tool_calls
+            .as_ref()
+            .is_some_and(|tool_calls| !tool_calls.is_empty())
+    }
+
+    /// The content without the `<think>...</think>` blocks some models write their
+    /// reasoning in, trimmed. An unclosed block runs to the end of the content.
+    pub fn text(&self) -> String {
+        split_think_blocks(&self.content).0.trim().to_string()
+    }
+
+    /// Moves the `<think>` blocks of the content to [`thinking`](Self::thinking), after
+    /// any reasoning the provider sent on its own, leaving the trimmed rest as the
+    /// content.
+    pub(crate) fn extract_think_blocks(&mut self) {
+        let (text, blocks) = split_think_blocks(&self.content);
+        if blocks.is_empty() {
+            return;
+        }
+        let thinking: Vec<&str> = self
+            .thinking
+            .iter()
+            .map(String::as_str)
+            .chain(blocks)
+            .map(str::trim)
+            .filter(|block| !block.is_empty())
+            .collect();
+        self.thinking = Some(thinking.join("\n\n")).filter(|thinking| !thinking.is_empty());
+        self.content = text.trim().to_string();
+    }
+
+    /// A copy with the content and thinking cut down to `max_chars` characters each.
+    pub fn truncated(&self, max_chars: usize) -> Self {
+        let mut message = self.clone();
+        for text in [Some(&mut message.content), message.thinking.as_mut()]
+            .into_iter()
+            .flatten()
+        {
+            if let Some((end, _)) = text.char_indices().nth(max_chars) {
+                text.truncate(end);
+                text.push_str("... [truncated]");
+            }
+        }
+        message
+    }
+}
+
+/// Splits `content` into the text around its `<think>...</think>` blocks and the insides
+/// of the blocks. An unclosed block runs to the end of the content.
+fn split_think_blocks(content: &str) -> (String, Vec<&str>) {
+    let mut text = String::new();
+    let mut blocks = Vec::new();
+    let mut rest = content;
+    while let Some(start) = rest.find("<think>") {
+        text.push_str(&rest[..start]);
+        let inside = &rest[start + "<think>".len()..];
+        rest = match inside.find("</think>") {
+            Some(end) => {
+                blocks.push(&inside[..end]);
+                &inside[end + "</think>".len()..]
+            }
+            None => {
+                blocks.push(inside);
+                ""
+            }
+        };
+    }
+    text.push_str(rest);
+    (text, blocks)
+}
+
+/// Fluent construction of a [`ChatMessage`], started by [`ChatMessage::builder`].
+#[derive(Debug, Clone)]
+pub struct ChatMessageBuilder {
+    message: ChatMessage,
+}
+
+impl ChatMessageBuilder {
+    pub fn content(mut self, content: impl Into<String>) -> Self {
+        self.message.content = content.into();
+        self
+    }
+
+    pub fn thinking(mut self, thinking: impl Into<String>) -> Self {
+        self.message.thinking = Some(thinking.into());
+        self
+    }
+
+    pub fn name(mut self, name: impl Into<String>) -> Self {
+        self.message.name = Some(name.into());
+        self
+    }
+
+    pub fn tool_calls(mut self, tool_calls: Vec<ToolCall>) -> Self {
+        self.message.tool_calls = Some(tool_calls);
+        self
+    }
+
+    pub fn tool_call_id(mut self, tool_call_id: impl Into<String>) -> Self {
+        self.message.tool_call_id = Some(tool_call_id.into());
+        self
+    }
+
+    pub fn images(mut self, images: Vec<ImageData>) -> Self {
+        self.message.images = Some(images);
+        self
+    }
+
+    pub fn build(self) -> ChatMessage {
+        self.message
+    }
+}
+
+/// Why the model stopped generating, from OpenRouter's `finish_reason` or Ollama's
+/// `done_reason`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum FinishReason {
+    Stop,
+    /// The output hit the token limit and is cut off.
+    Length,
+    ToolCalls,
+    ContentFilter,
+    Other(String),
+}
+
+impl From<&str> for FinishReason {
+    fn from(reason: &str) -> Self {
+        match reason {
+            "stop" => FinishReason::Stop,
+            "length" => FinishReason::Length,
+            "tool_calls" => FinishReason::ToolCalls,
+            "content_filter" => FinishReason::ContentFilter,
+            other => FinishReason::Other(other.to_string()),
+        }
+    }
+}
+
+impl FinishReason {
+    pub fn as_str(&self) -> &str {
+        match self {
+            FinishReason::Stop => "stop",
+            FinishReason::Length => "length",
+            FinishReason::ToolCalls => "tool_calls",
+            FinishReason::ContentFilter => "content_filter",
+            FinishReason::Other(reason) => reason,
+        }
+    }
+}
+
+impl Serialize for FinishReason {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(self.as_str())
+    }
+}
+
+impl<'de> Deserialize<'de> for FinishReason {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let reason = String::deserialize(deserializer)?;
+        Ok(FinishReason::from(reason.as_str()))
+    }
+}
+
+/// Tokens spent on a request.
+///
+/// Deserializes from OpenRouter's `usage` object. Ollama reports its counts as separate
+/// response fields, see [`Usage::from_ollama`].
+#[derive(Serialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Usage {
+    pub prompt_tokens: u32,
+    pub completion_tokens: u32,
+    pub total_tokens: u32,
+    /// Prompt tokens served from the provider's prompt cache, when it reports them.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cached_tokens: Option<u32>,
+}
+
+impl Usage {
+    pub fn new(prompt_tokens: u32, completion_tokens: u32) -> Self {
+        Self {
+            prompt_tokens,
+            completion_tokens,
+            total_tokens: prompt_tokens + completion_tokens,
+            cached_tokens: None,
+        }
+    }
+
+    /// Usage from Ollama's `prompt_eval_count` and `eval_count`, or `None` if it sent neither.
+    /// Ollama leaves out `prompt_eval_count` when the whole prompt was cached.
+    pub fn from_ollama(prompt_eval_count: Option<u32>, eval_count: Option<u32>) -> Option<Self> {
+        if prompt_eval_count.is_none() && eval_count.is_none() {
+            return None;
+        }
+        Some(Self::new(
+            prompt_eval_count.unwrap_or(0),
+            eval_count.unwrap_or(0),
+        ))
+    }
+}
+
+/// Sums the counts of two requests. The cached tokens are `None` only if neither
+/// reported them.
+impl std::ops::Add for Usage {
+    type Output = Usage;
+
+    fn add(self, other: Usage) -> Usage {
+        let cached_tokens = match (self.cached_tokens, other.cached_tokens) {
+            (None, None) => None,
+            (left, right) => Some(left.unwrap_or(0) + right.unwrap_or(0)),
+        };
+        Usage {
+            prompt_tokens: self.prompt_tokens + other.prompt_tokens,
+            completion_tokens: self.completion_tokens + other.completion_tokens,
+            total_tokens: self.total_tokens + other.total_tokens,
+            cached_tokens,
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct UsageFields {
+    #[serde(default)]
+    prompt_tokens: u32,
+    #[serde(default)]
+    completion_tokens: u32,
+    #[serde(default)]
+    total_tokens: Option<u32>,
+    #[serde(default)]
+    cached_tokens: Option<u32>,
+    #[serde(default)]
+    prompt_tokens_details: Option<PromptTokensDetails>,
+}
+
+#[derive(Deserialize)]
+struct PromptTokensDetails {
+    #[serde(default)]
+    cached_tokens: Option<u32>,
+}
+
+impl<'de> Deserialize<'de> for Usage {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let fields = UsageFields::deserialize(deserializer)?;
+        Ok(Usage {
+            prompt_tokens: fields.prompt_tokens,
+            completion_tokens: fields.completion_tokens,
+            total_tokens: fields
+                .total_tokens
+                .unwrap_or(fields.prompt_tokens + fields.completion_tokens),
+            cached_tokens: fields
+                .prompt_tokens_details
+                .and_then(|details| details.cached_tokens)
+                .or(fields.cached_tokens),
+        })
+    }
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug)]
 pub struct OllamaResponse {
     pub message: ChatMessage,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub model: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub done_reason: Option<FinishReason>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub prompt_eval_count: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub eval_count: Option<u32>,
+    #[serde(flatten)]
+    timings: OllamaTimings,
+    #[serde(skip)]
+    raw: Option<Value>,
 }
 
 impl Default for OllamaResponse {
     fn default() -> Self {
         Self {
             message: ChatMessage::assistant("".to_string()),
+            model: None,
+            done_reason: None,
+            prompt_eval_count: None,
+            eval_count: None,
+            timings: OllamaTimings::default(),
+            raw: None,
         }
     }
 }
 
 #[derive(Serialize, Deserialize, Debug)]
 pub struct OpenRouterResponse {
+    #[serde(default, deserialize_with = "deserialize_choices")]
     choices: Vec<OpenRouterChoice>,
+    /// The model that served the request, which OpenRouter may pick itself.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    model: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    usage: Option<Usage>,
+    #[serde(skip)]
+    raw: Option<Value>,
 }
 
 #[derive(Serialize, Deserialize, Debug)]
 struct OpenRouterChoice {
     message: ChatMessage,
-    finish_reason: String,
+    #[serde(default)]
+    finish_reason: Option<FinishReason>,
+}
+
+/// Moves the tool calls of later choices into the first one, for providers that send one
+/// call per choice: the message of a response then holds every call, in order.
+fn deserialize_choices<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<Vec<OpenRouterChoice>, D::Error> {
+    let mut choices = Vec::<OpenRouterChoice>::deserialize(deserializer)?;
+    let later_calls: Vec<ToolCall> = choices
+        .iter_mut()
+        .skip(1)
+        .flat_map(|choice| choice.message.tool_calls.take().unwrap_or_default())
+        .collect();
+    if let Some(first) = choices.first_mut().filter(|_| !later_calls.is_empty()) {
+        first
+            .message
+            .tool_calls
+            .get_or_insert_with(Vec::new)
+            .extend(later_calls);
+    }
+    Ok(choices)
 }
 
 pub trait ChatResponse: Send + Sync + Debug {
     fn message(&self) -> &ChatMessage;
+
+    fn finish_reason(&self) -> Option<FinishReason> {
+        None
+    }
+
+    /// The model the provider says served the request.
+    fn model(&self) -> Option<&str> {
+        None
+    }
+
+    /// The tokens the request used, if the provider reported them.
+    fn usage(&self) -> Option<Usage> {
+        None
+    }
+
+    /// How long the provider took and how many tokens it evaluated, if it reported it.
+    fn stats(&self) -> Option<ProviderStats> {
+        None
+    }
+
+    /// The response body as the provider sent it, including fields the typed response
+    /// drops.
+    fn raw(&self) -> Option<&Value> {
+        None
+    }
+
+    /// Removes the raw body from the response.
+    fn take_raw(&mut self) -> Option<Value> {
+        None
+    }
+
+    /// Which fallback of [`LLMClient::with_fallback`](crate::llm_client::LLMClient::with_fallback)
+    /// served the request, counting from 1; `None` when the primary client did.
+    fn fallback(&self) -> Option<usize> {
+        None
+    }
 }
 
+impl OpenRouterResponse {
+    pub(crate) fn has_choices(&self) -> bool {
+        !self.choices.is_empty()
+    }
+}
+
+/// The message of a response without choices.
+static NO_MESSAGE: LazyLock<ChatMessage> = LazyLock::new(|| ChatMessage::assistant(String::new()));
+
 impl ChatResponse for OpenRouterResponse {
     fn message(&self) -> &ChatMessage {
-        &self.choices[0].message
+        self.choices
+            .first()
+            .map_or(&*NO_MESSAGE, |choice| &choice.message)
+    }
+
+    fn finish_reason(&self) -> Option<FinishReason> {
+        self.choices.first()?.finish_reason.clone()
+    }
+
+    fn model(&self) -> Option<&str> {
+        self.model.as_deref()
+    }
+
+    fn usage(&self) -> Option<Usage> {
+        self.usage
+    }
+
+    fn raw(&self) -> Option<&Value> {
+        self.raw.as_ref()
+    }
+
+    fn take_raw(&mut self) -> Option<Value> {
+        self.raw.take()
     }
 }
 
@@ -104,6 +709,30 @@ impl ChatResponse for OllamaResponse {
     fn message(&self) -> &ChatMessage {
         &self.message
     }
+
+    fn finish_reason(&self) -> Option<FinishReason> {
+        self.done_reason.clone()
+    }
+
+    fn model(&self) -> Option<&str> {
+        self.model.as_deref()
+    }
+
+    fn usage(&self) -> Option<Usage> {
+        Usage::from_ollama(self.prompt_eval_count, self.eval_count)
+    }
+
+    fn stats(&self) -> Option<ProviderStats> {
+        ProviderStats::from_ollama(self.timings, self.prompt_eval_count, self.eval_count)
+    }
+
+    fn raw(&self) -> Option<&Value> {
+        self.raw.as_ref()
+    }
+
+    fn take_raw(&mut self) -> Option<Value> {
+        self.raw.take()
+    }
 }
 
 // Completions takes a prompt input instead of a series of messages
@@ -114,12 +743,18 @@ pub struct CompletionRequest {
     pub max_tokens: Option<usize>,
     pub temperature: Option<f32>,
     pub stream: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub stop: Option<Vec<String>>,
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug)]
 pub struct OpenRouterCompletionResponse {
     pub id: String,
     pub choices: Vec<CompletionChoice>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub usage: Option<Usage>,
+    #[serde(skip)]
+    raw: Option<Value>,
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug)]
@@ -129,18 +764,65 @@ pub struct CompletionChoice {
     pub finish_reason: String,
 }
 
-#[derive(Serialize, Deserialize, Clone, Debug)]
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
 pub struct OllamaCompletionResponse {
     pub response: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub prompt_eval_count: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub eval_count: Option<u32>,
+    #[serde(flatten)]
+    timings: OllamaTimings,
+    #[serde(skip)]
+    raw: Option<Value>,
 }
 
 pub trait CompletionResponse: Send + Sync + Debug {
     fn response(&self) -> &str;
+
+    /// The tokens the request used, if the provider reported them.
+    fn usage(&self) -> Option<Usage> {
+        None
+    }
+
+    /// How long the provider took and how many tokens it evaluated, if it reported it.
+    fn stats(&self) -> Option<ProviderStats> {
+        None
+    }
+
+    /// The response body as the provider sent it, including fields the typed response
+    /// drops.
+    fn raw(&self) -> Option<&Value> {
+        None
+    }
+
+    /// Removes the raw body from the response.
+    fn take_raw(&mut self) -> Option<Value> {
+        None
+    }
+
+    /// Which fallback of [`LLMClient::with_fallback`](crate::llm_client::LLMClient::with_fallback)
+    /// served the request, counting from 1; `None` when the primary client did.
+    fn fallback(&self) -> Option<usize> {
+        None
+    }
 }
 
 impl CompletionResponse for OpenRouterCompletionResponse {
     fn response(&self) -> &str {
-        &self.choices[0].text
+        self.choices.first().map_or("", |choice| &choice.text)
+    }
+
+    fn usage(&self) -> Option<Usage> {
+        self.usage
+    }
+
+    fn raw(&self) -> Option<&Value> {
+        self.raw.as_ref()
+    }
+
+    fn take_raw(&mut self) -> Option<Value> {
+        self.raw.take()
     }
 }
 
@@ -148,4 +830,57 @@ impl CompletionResponse for OllamaCompletionResponse {
     fn response(&self) -> &str {
         &self.response
     }
+
+    fn usage(&self) -> Option<Usage> {
+        Usage::from_ollama(self.prompt_eval_count, self.eval_count)
+    }
+
+    fn stats(&self) -> Option<ProviderStats> {
+        ProviderStats::from_ollama(self.timings, self.prompt_eval_count, self.eval_count)
+    }
+
+    fn raw(&self) -> Option<&Value> {
+        self.raw.as_ref()
+    }
+
+    fn take_raw(&mut self) -> Option<Value> {
+        self.raw.take()
+    }
+}
+
+/// Response types that keep the JSON body they were parsed from.
+pub(crate) trait RawResponse: for<'de> Deserialize<'de> {
+    fn set_raw(&mut self, raw: Value);
+
+    /// Parses `body` once into a [`Value`], then the typed response from that value.
+    fn from_body(body: &str) -> Result<Self, serde_json::Error> {
+        let raw: Value = serde_json::from_str(body)?;
+        let mut response = Self::deserialize(&raw)?;
+        response.set_raw(raw);
+        Ok(response)
+    }
+}
+
+impl RawResponse for OllamaResponse {
+    fn set_raw(&mut self, raw: Value) {
+        self.raw = Some(raw);
+    }
+}
+
+impl RawResponse for OpenRouterResponse {
+    fn set_raw(&mut self, raw: Value) {
+        self.raw = Some(raw);
+    }
+}
+
+impl RawResponse for OllamaCompletionResponse {
+    fn set_raw(&mut self, raw: Value) {
+        self.raw = Some(raw);
+    }
+}
+
+impl RawResponse for OpenRouterCompletionResponse {
+    fn set_raw(&mut self, raw: Value) {
+        self.raw = Some(raw);
+    }
 }
@@ -0,0 +1,162 @@
+//! Responses of Anthropic's Messages API, which sends the assistant turn as a list of
+//! content blocks rather than a message.
+
+use serde::Deserialize;
+use serde_json::Value;
+
+use super::{
+    ChatMessage, ChatResponse, CompletionResponse, FinishReason, RawResponse, Role, ToolCall, Usage,
+};
+
+/// A response of the Messages API, with its content blocks joined into a [`ChatMessage`].
+#[derive(Debug, Clone, Deserialize)]
+#[serde(from = "MessagesResponse")]
+pub struct AnthropicResponse {
+    message: ChatMessage,
+    model: Option<String>,
+    stop_reason: Option<FinishReason>,
+    usage: Option<Usage>,
+    raw: Option<Value>,
+}
+
+#[derive(Deserialize)]
+struct MessagesResponse {
+    #[serde(default)]
+    content: Vec<ContentBlock>,
+    #[serde(default)]
+    model: Option<String>,
+    #[serde(default)]
+    stop_reason: Option<String>,
+    #[serde(default)]
+    usage: Option<AnthropicUsage>,
+}
+
+#[derive(Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum ContentBlock {
+    Text {
+        text: String,
+    },
+    Thinking {
+        thinking: String,
+    },
+    ToolUse {
+        id: String,
+        name: String,
+        #[serde(default)]
+        input: Value,
+    },
+    #[serde(other)]
+    Other,
+}
+
+#[derive(Deserialize)]
+struct AnthropicUsage {
+    #[serde(default)]
+    input_tokens: u32,
+    #[serde(default)]
+    output_tokens: u32,
+    #[serde(default)]
+    cache_read_input_tokens: Option<u32>,
+}
+
+/// Anthropic's `stop_reason` as the [`FinishReason`] OpenAI-style providers would send.
+fn finish_reason(stop_reason: &str) -> FinishReason {
+    match stop_reason {
+        "end_turn" | "stop_sequence" => FinishReason::Stop,
+        "max_tokens" => FinishReason::Length,
+        "tool_use" => FinishReason::ToolCalls,
+        "refusal" => FinishReason::ContentFilter,
+        other => FinishReason::from(other),
+    }
+}
+
+impl From<MessagesResponse> for AnthropicResponse {
+    fn from(response: MessagesResponse) -> Self {
+        let mut text = String::new();
+        let mut thinking = String::new();
+        let mut tool_calls = Vec::new();
+        for block in response.content {
+            match block {
+                ContentBlock::Text { text: block } => text.push_str(&block),
+                ContentBlock::Thinking { thinking: block } => thinking.push_str(&block),
+                ContentBlock::ToolUse { id, name, input } => {
+                    tool_calls.push(ToolCall::new(name, input).with_id(id));
+                }
+                ContentBlock::Other => {}
+            }
+        }
+
+        let mut builder = ChatMessage::builder(Role::Assistant).content(text);
+        if !thinking.is_empty() {
+            builder = builder.thinking(thinking);
+        }
+        if !tool_calls.is_empty() {
+            builder = builder.tool_calls(tool_calls);
+        }
+
+        Self {
+            message: builder.build(),
+            model: response.model,
+            stop_reason: response.stop_reason.as_deref().map(finish_reason),
+            usage: response.usage.map(|usage| Usage {
+                cached_tokens: usage.cache_read_input_tokens,
+                ..Usage::new(usage.input_tokens, usage.output_tokens)
+            }),
+            raw: None,
+        }
+    }
+}
+
+impl ChatResponse for AnthropicResponse {
+    fn message(&self) -> &ChatMessage {
+        &self.message
+    }
+
+    /// [`FinishReason::Length`] when Anthropic stopped at `max_tokens`.
+    fn finish_reason(&self) -> Option<FinishReason> {
+        self.stop_reason.clone()
+    }
+
+    fn model(&self) -> Option<&str> {
+        self.model.as_deref()
+    }
+
+    fn usage(&self) -> Option<Usage> {
+        self.usage
+    }
+
+    fn raw(&self) -> Option<&Value> {
+        self.raw.as_ref()
+    }
+
+    fn take_raw(&mut self) -> Option<Value> {
+        self.raw.take()
+    }
+}
+
+/// Anthropic has no completions endpoint; a prompt is sent as a single user message and
+/// the text of the answer is the completion.
+impl CompletionResponse for AnthropicResponse {
+    fn response(&self) -> &str {
+        &self.message.content
+    }
+
+    fn usage(&self) -> Option<Usage> {
+        self.usage
+    }
+
+    fn raw(&self) -> Option<&Value> {
+        self.raw.as_ref()
+    }
+
+    fn take_raw(&mut self) -> Option<Value> {
+        self.raw.take()
+    }
+}
+
+impl RawResponse for AnthropicResponse {
+    fn set_raw(&mut self, raw: Value) {
+        self.raw = Some(raw);
+    }
+}
@@ -0,0 +1,207 @@
+//! Responses of the Gemini API's `generateContent`, which sends the answer as the `parts`
+//! of a candidate.
+
+use serde::Deserialize;
+use serde_json::Value;
+
+use super::{
+    ChatMessage, ChatResponse, CompletionResponse, FinishReason, RawResponse, Role, ToolCall, Usage,
+};
+
+/// A `generateContent` response, with the parts of its first candidate joined into a
+/// [`ChatMessage`].
+#[derive(Debug, Clone, Deserialize)]
+#[serde(from = "GenerateContentResponse")]
+pub struct GeminiResponse {
+    message: ChatMessage,
+    model: Option<String>,
+    finish_reason: Option<FinishReason>,
+    usage: Option<Usage>,
+    raw: Option<Value>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct GenerateContentResponse {
+    #[serde(default)]
+    candidates: Vec<Candidate>,
+    #[serde(default)]
+    usage_metadata: Option<UsageMetadata>,
+    #[serde(default)]
+    model_version: Option<String>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Candidate {
+    #[serde(default)]
+    content: Option<Content>,
+    #[serde(default)]
+    finish_reason: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct Content {
+    #[serde(default)]
+    parts: Vec<Part>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Part {
+    #[serde(default)]
+    text: Option<String>,
+    /// Set on parts that carry the model's reasoning rather than the answer.
+    #[serde(default)]
+    thought: bool,
+    #[serde(default)]
+    function_call: Option<FunctionCall>,
+}
+
+#[derive(Deserialize)]
+struct FunctionCall {
+    #[serde(default)]
+    id: Option<String>,
+    name: String,
+    #[serde(default)]
+    args: Value,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct UsageMetadata {
+    #[serde(default)]
+    prompt_token_count: u32,
+    #[serde(default)]
+    candidates_token_count: u32,
+    #[serde(default)]
+    thoughts_token_count: u32,
+    #[serde(default)]
+    total_token_count: Option<u32>,
+    #[serde(default)]
+    cached_content_token_count: Option<u32>,
+}
+
+/// Gemini's `finishReason` as the [`FinishReason`] OpenAI-style providers would send.
+/// Gemini finishes with `STOP` when it calls functions too.
+fn finish_reason(reason: &str, calls_tools: bool) -> FinishReason {
+    match reason {
+        "STOP" if calls_tools => FinishReason::ToolCalls,
+        "STOP" => FinishReason::Stop,
+        "MAX_TOKENS" => FinishReason::Length,
+        "SAFETY" | "RECITATION" | "BLOCKLIST" | "PROHIBITED_CONTENT" | "SPII" => {
+            FinishReason::ContentFilter
+        }
+        other => FinishReason::Other(other.to_string()),
+    }
+}
+
+impl From<GenerateContentResponse> for GeminiResponse {
+    fn from(response: GenerateContentResponse) -> Self {
+        let candidate = response.candidates.into_iter().next();
+        let reason = candidate
+            .as_ref()
+            .and_then(|candidate| candidate.finish_reason.clone());
+        let parts = candidate
+            .and_then(|candidate| candidate.content)
+            .map(|content| content.parts)
+            .unwrap_or_default();
+
+        let mut text = String::new();
+        let mut thinking = String::new();
+        let mut tool_calls = Vec::new();
+        for part in parts {
+            if let Some(call) = part.function_call {
+                let tool_call = ToolCall::new(call.name, call.args);
+                tool_calls.push(match call.id {
+                    Some(id) => tool_call.with_id(id),
+                    None => tool_call,
+                });
+            }
+            match (part.text, part.thought) {
+                (Some(part), true) => thinking.push_str(&part),
+                (Some(part), false) => text.push_str(&part),
+                (None, _) => {}
+            }
+        }
+
+        let finish_reason = reason.map(|reason| finish_reason(&reason, !tool_calls.is_empty()));
+        let mut builder = ChatMessage::builder(Role::Assistant).content(text);
+        if !thinking.is_empty() {
+            builder = builder.thinking(thinking);
+        }
+        if !tool_calls.is_empty() {
+            builder = builder.tool_calls(tool_calls);
+        }
+
+        Self {
+            message: builder.build(),
+            model: response.model_version,
+            finish_reason,
+            usage: response.usage_metadata.map(|usage| {
+                let completion_tokens = usage.candidates_token_count + usage.thoughts_token_count;
+                Usage {
+                    prompt_tokens: usage.prompt_token_count,
+                    completion_tokens,
+                    total_tokens: usage
+                        .total_token_count
+                        .unwrap_or(usage.prompt_token_count + completion_tokens),
+                    cached_tokens: usage.cached_content_token_count,
+                }
+            }),
+            raw: None,
+        }
+    }
+}
+
+impl ChatResponse for GeminiResponse {
+    fn message(&self) -> &ChatMessage {
+        &self.message
+    }
+
+    fn finish_reason(&self) -> Option<FinishReason> {
+        self.finish_reason.clone()
+    }
+
+    fn model(&self) -> Option<&str> {
+        self.model.as_deref()
+    }
+
+    fn usage(&self) -> Option<Usage> {
+        self.usage
+    }
+
+    fn raw(&self) -> Option<&Value> {
+        self.raw.as_ref()
+    }
+
+    fn take_raw(&mut self) -> Option<Value> {
+        self.raw.take()
+    }
+}
+
+/// Gemini has no completions endpoint; a prompt is sent as a single user turn and the
+/// text of the answer is the completion.
+impl CompletionResponse for GeminiResponse {
+    fn response(&self) -> &str {
+        &self.message.content
+    }
+
+    fn usage(&self) -> Option<Usage> {
+        self.usage
+    }
+
+    fn raw(&self) -> Option<&Value> {
+        self.raw.as_ref()
+    }
+
+    fn take_raw(&mut self) -> Option<Value> {
+        self.raw.take()
+    }
+}
+
+impl RawResponse for GeminiResponse {
+    fn set_raw(&mut self, raw: Value) {
+        self.raw = Some(raw);
+    }
+}
@@ -0,0 +1,213 @@
+//! [`ConversationHistory`], the messages of a multi-turn conversation with a limit on how
+//! many of them are kept.
+
+use crate::{
+    errors::AgenticFlowError,
+    llm_client::LLMClient,
+    tokens::{estimate_message_tokens, estimate_messages_tokens},
+};
+
+use super::{ChatMessage, Role};
+
+/// Written before the summary that replaces older turns.
+const SUMMARY_PREFIX: &str = "Summary of the conversation so far: ";
+
+const SUMMARIZE_PROMPT: &str = "Summarize the following conversation in a few sentences. \
+Keep every fact, decision and open question a later reply could need.";
+
+/// How much of its conversation a [`ConversationHistory`] keeps. The system prompt is
+/// always kept, and so is the last turn.
+#[derive(Clone, Default)]
+pub enum RetentionStrategy {
+    /// Keeps every turn.
+    #[default]
+    Unbounded,
+    /// Keeps the latest turns, at most this many.
+    MaxMessages(usize),
+    /// Keeps the latest turns that, with the system prompt, take at most this many
+    /// tokens as [`estimate_messages_tokens`] counts them.
+    MaxTokens(usize),
+    /// Once there are more than `max_messages` turns, [`ConversationHistory::compact`]
+    /// asks `client` to summarize all but the latest `keep_recent` of them, and replaces
+    /// them with one assistant message holding the summary.
+    Summarize {
+        client: Box<LLMClient>,
+        max_messages: usize,
+        keep_recent: usize,
+    },
+}
+
+/// A system prompt and the turns of a conversation after it, cut down as they grow with
+/// a [`RetentionStrategy`]:
+///
+/// ```rust,no_run
+/// # use agentic_flow_lib::llm_client::LLMClient;
+/// # use agentic_flow_lib::model::{ConversationHistory, RetentionStrategy};
+/// # async fn example(client: LLMClient) -> Result<(), agentic_flow_lib::errors::AgenticFlowError> {
+/// let mut history = ConversationHistory::new("You are a helpful assistant.")
+///     .with_retention(RetentionStrategy::MaxMessages(20));
+/// history.push_user("What is the capital of France?");
+/// let response = client.chat_completions(history.as_messages(), vec![]).await?;
+/// history.push(response.message().clone());
+/// # Ok(())
+/// # }
+/// ```
+///
+/// When a tool result would be left at the start of the kept turns without the assistant
+/// message that called the tool, it is dropped too.
+#[derive(Clone)]
+pub struct ConversationHistory {
+    system_prompt: ChatMessage,
+    turns: Vec<ChatMessage>,
+    retention: RetentionStrategy,
+}
+
+impl ConversationHistory {
+    pub fn new(system_prompt: impl Into<String>) -> Self {
+        Self {
+            system_prompt: ChatMessage::system(system_prompt.into()),
+            turns: Vec::new(),
+            retention: RetentionStrategy::default(),
+        }
+    }
+
+    /// Replaces the [`RetentionStrategy::Unbounded`] default, cutting down the turns
+    /// already pushed for the window strategies.
+    pub fn with_retention(mut self, retention: RetentionStrategy) -> Self {
+        self.retention = retention;
+        self.apply_window();
+        self
+    }
+
+    pub fn system_prompt(&self) -> &str {
+        &self.system_prompt.content
+    }
+
+    pub fn set_system_prompt(&mut self, system_prompt: impl Into<String>) {
+        self.system_prompt.content = system_prompt.into();
+        self.apply_window();
+    }
+
+    /// The kept turns, without the system prompt.
+    pub fn turns(&self) -> &[ChatMessage] {
+        &self.turns
+    }
+
+    /// The number of kept turns.
+    pub fn len(&self) -> usize {
+        self.turns.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.turns.is_empty()
+    }
+
+    /// Drops every turn, keeping the system prompt.
+    pub fn clear(&mut self) {
+        self.turns.clear();
+    }
+
+    /// Adds `message` as the latest turn, such as the message of a chat response with its
+    /// tool calls.
+    pub fn push(&mut self, message: ChatMessage) {
+        self.turns.push(message);
+        self.apply_window();
+    }
+
+    pub fn push_user(&mut self, content: impl Into<String>) {
+        self.push(ChatMessage::user(content.into()));
+    }
+
+    pub fn push_assistant(&mut self, content: impl Into<String>) {
+        self.push(ChatMessage::assistant(content.into()));
+    }
+
+    /// Adds the result of the tool call with id `tool_call_id`.
+    pub fn push_tool(&mut self, content: impl Into<String>, tool_call_id: impl Into<String>) {
+        self.push(ChatMessage::tool(content.into(), tool_call_id.into()));
+    }
+
+    /// The system prompt followed by the kept turns, as
+    /// [`LLMClient::chat_completions`] takes them.
+    pub fn as_messages(&self) -> Vec<ChatMessage> {
+        std::iter::once(&self.system_prompt)
+            .chain(&self.turns)
+            .cloned()
+            .collect()
+    }
+
+    /// For [`RetentionStrategy::Summarize`], summarizes the older turns once there are too
+    /// many; the other strategies cut down the turns as they are pushed, so this does
+    /// nothing for them. Call it before [`as_messages`](Self::as_messages). When the
+    /// summary request fails, the turns are kept as they are and the error is returned.
+    pub async fn compact(&mut self) -> Result<(), AgenticFlowError> {
+        let RetentionStrategy::Summarize {
+            client,
+            max_messages,
+            keep_recent,
+        } = &self.retention
+        else {
+            return Ok(());
+        };
+        if self.turns.len() <= *max_messages {
+            return Ok(());
+        }
+        let evicted = self.evicted_count(self.turns.len().saturating_sub(*keep_recent));
+        if evicted == 0 {
+            return Ok(());
+        }
+        let transcript: Vec<String> = self.turns[..evicted]
+            .iter()
+            .map(|message| format!("{}: {}", message.role, message.text()))
+            .collect();
+        let response = client
+            .chat_completions(
+                vec![
+                    ChatMessage::system(SUMMARIZE_PROMPT.to_string()),
+                    ChatMessage::user(transcript.join("\n")),
+                ],
+                vec![],
+            )
+            .await?;
+        let summary =
+            ChatMessage::assistant(format!("{}{}", SUMMARY_PREFIX, response.message().text()));
+        self.turns.splice(..evicted, [summary]);
+        Ok(())
+    }
+
+    fn apply_window(&mut self) {
+        let evicted = match self.retention {
+            RetentionStrategy::MaxMessages(max) => self.turns.len().saturating_sub(max),
+            RetentionStrategy::MaxTokens(budget) => {
+                let total = estimate_message_tokens(&self.system_prompt, "")
+                    + estimate_messages_tokens(&self.turns, "");
+                let mut excess = total.saturating_sub(budget);
+                self.turns
+                    .iter()
+                    .take_while(|message| {
+                        let evict = excess > 0;
+                        excess = excess.saturating_sub(estimate_message_tokens(message, ""));
+                        evict
+                    })
+                    .count()
+            }
+            RetentionStrategy::Unbounded | RetentionStrategy::Summarize { .. } => 0,
+        };
+        let evicted = self.evicted_count(evicted);
+        self.turns.drain(..evicted);
+    }
+
+    /// How many of the oldest turns to drop when `wanted` of them should go: never the
+    /// last turn, and also the tool results that would start the rest.
+    fn evicted_count(&self, wanted: usize) -> usize {
+        if wanted == 0 {
+            return 0;
+        }
+        let last = self.turns.len().saturating_sub(1);
+        let mut evicted = wanted.min(last);
+        while evicted < last && self.turns[evicted].role == Role::Tool {
+            evicted += 1;
+        }
+        evicted
+    }
+}
@@ -0,0 +1,75 @@
+//! Responses of llama.cpp's `llama-server` `/completion` endpoint.
+
+use serde::Deserialize;
+use serde_json::Value;
+
+use super::{
+    CompletionResponse, FinishReason, ProviderStats, RawResponse, Usage, stats::LlamaCppTimings,
+};
+
+/// A `/completion` response of `llama-server`.
+#[derive(Debug, Clone, Deserialize)]
+pub struct LlamaCppResponse {
+    pub content: String,
+    #[serde(default)]
+    pub model: Option<String>,
+    /// Why generation stopped: `eos`, `word` (a stop string) or `limit` (`n_predict`).
+    #[serde(default)]
+    pub stop_type: Option<String>,
+    /// Sent instead of `stop_type` by older servers.
+    #[serde(default)]
+    pub stopped_limit: bool,
+    #[serde(default)]
+    pub tokens_evaluated: Option<u32>,
+    #[serde(default)]
+    pub tokens_predicted: Option<u32>,
+    #[serde(default)]
+    pub tokens_cached: Option<u32>,
+    #[serde(default)]
+    timings: Option<LlamaCppTimings>,
+    #[serde(skip)]
+    raw: Option<Value>,
+}
+
+impl LlamaCppResponse {
+    /// [`FinishReason::Length`] when generation hit `n_predict`, otherwise
+    /// [`FinishReason::Stop`].
+    pub fn finish_reason(&self) -> FinishReason {
+        match self.stop_type.as_deref() {
+            Some("limit") => FinishReason::Length,
+            None if self.stopped_limit => FinishReason::Length,
+            _ => FinishReason::Stop,
+        }
+    }
+}
+
+impl CompletionResponse for LlamaCppResponse {
+    fn response(&self) -> &str {
+        &self.content
+    }
+
+    fn usage(&self) -> Option<Usage> {
+        Some(Usage {
+            cached_tokens: self.tokens_cached,
+            ..Usage::new(self.tokens_evaluated?, self.tokens_predicted?)
+        })
+    }
+
+    fn stats(&self) -> Option<ProviderStats> {
+        self.timings.map(ProviderStats::from)
+    }
+
+    fn raw(&self) -> Option<&Value> {
+        self.raw.as_ref()
+    }
+
+    fn take_raw(&mut self) -> Option<Value> {
+        self.raw.take()
+    }
+}
+
+impl RawResponse for LlamaCppResponse {
+    fn set_raw(&mut self, raw: Value) {
+        self.raw = Some(raw);
+    }
+}
@@ -0,0 +1,125 @@
+//! [`ProviderStats`], the timings a provider reports for a response.
+
+use std::{ops::Add, time::Duration};
+
+use serde::{Deserialize, Serialize};
+
+/// How long a provider took to answer and how many tokens it evaluated, as it reported
+/// them. Ollama reports every field; llama.cpp the prompt and generation ones. Fields
+/// the provider did not report are `None`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct ProviderStats {
+    /// The whole request on the server, loading the model included.
+    pub total_duration: Option<Duration>,
+    /// Loading the model into memory.
+    pub load_duration: Option<Duration>,
+    pub prompt_eval_count: Option<u32>,
+    /// Evaluating the prompt.
+    pub prompt_eval_duration: Option<Duration>,
+    /// The tokens generated.
+    pub eval_count: Option<u32>,
+    /// Generating the answer.
+    pub eval_duration: Option<Duration>,
+}
+
+impl ProviderStats {
+    /// Generated tokens per second, or `None` without both the count and the duration.
+    pub fn eval_tokens_per_second(&self) -> Option<f64> {
+        tokens_per_second(self.eval_count?, self.eval_duration?)
+    }
+
+    /// Prompt tokens evaluated per second, or `None` without both the count and the
+    /// duration.
+    pub fn prompt_tokens_per_second(&self) -> Option<f64> {
+        tokens_per_second(self.prompt_eval_count?, self.prompt_eval_duration?)
+    }
+
+    /// The stats from Ollama's counts and nanosecond durations, or `None` if it sent none.
+    pub(crate) fn from_ollama(
+        timings: OllamaTimings,
+        prompt_eval_count: Option<u32>,
+        eval_count: Option<u32>,
+    ) -> Option<Self> {
+        let nanos = |duration: Option<u64>| duration.map(Duration::from_nanos);
+        Some(Self {
+            total_duration: nanos(timings.total_duration),
+            load_duration: nanos(timings.load_duration),
+            prompt_eval_count,
+            prompt_eval_duration: nanos(timings.prompt_eval_duration),
+            eval_count,
+            eval_duration: nanos(timings.eval_duration),
+        })
+        .filter(|stats| *stats != Self::default())
+    }
+}
+
+fn tokens_per_second(tokens: u32, duration: Duration) -> Option<f64> {
+    Some(tokens as f64 / duration.as_secs_f64()).filter(|rate| rate.is_finite())
+}
+
+/// Sums the durations and counts, for the stats of several responses, e.g. those of a
+/// run. A field stays `None` when neither side reported it.
+impl Add for ProviderStats {
+    type Output = ProviderStats;
+
+    fn add(self, other: ProviderStats) -> ProviderStats {
+        fn sum<T: Add<Output = T>>(left: Option<T>, right: Option<T>) -> Option<T> {
+            match (left, right) {
+                (Some(left), Some(right)) => Some(left + right),
+                (left, right) => left.or(right),
+            }
+        }
+        ProviderStats {
+            total_duration: sum(self.total_duration, other.total_duration),
+            load_duration: sum(self.load_duration, other.load_duration),
+            prompt_eval_count: sum(self.prompt_eval_count, other.prompt_eval_count),
+            prompt_eval_duration: sum(self.prompt_eval_duration, other.prompt_eval_duration),
+            eval_count: sum(self.eval_count, other.eval_count),
+            eval_duration: sum(self.eval_duration, other.eval_duration),
+        }
+    }
+}
+
+/// The durations of an Ollama response, in nanoseconds; absent from streamed chunks
+/// before the last one.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default)]
+pub(crate) struct OllamaTimings {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) total_duration: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) load_duration: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) prompt_eval_duration: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) eval_duration: Option<u64>,
+}
+
+/// The `timings` of a llama.cpp response, in milliseconds.
+#[derive(Deserialize, Clone, Copy, Debug, Default)]
+pub(crate) struct LlamaCppTimings {
+    #[serde(default)]
+    pub(crate) prompt_n: Option<u32>,
+    #[serde(default)]
+    pub(crate) prompt_ms: Option<f64>,
+    #[serde(default)]
+    pub(crate) predicted_n: Option<u32>,
+    #[serde(default)]
+    pub(crate) predicted_ms: Option<f64>,
+}
+
+impl From<LlamaCppTimings> for ProviderStats {
+    fn from(timings: LlamaCppTimings) -> Self {
+        let millis = |duration: Option<f64>| {
+            duration
+                .filter(|millis| millis.is_finite() && *millis >= 0.0)
+                .map(|millis| Duration::from_secs_f64(millis / 1000.0))
+        };
+        Self {
+            prompt_eval_count: timings.prompt_n,
+            prompt_eval_duration: millis(timings.prompt_ms),
+            eval_count: timings.predicted_n,
+            eval_duration: millis(timings.predicted_ms),
+            ..Self::default()
+        }
+    }
+}
@@ -0,0 +1,294 @@
+//! Streamed chat responses: the chunk shapes each provider sends, the common
+//! [`ChatStreamChunk`] they convert into, and the [`MessageAccumulator`] that joins the
+//! chunks back into a [`ChatMessage`].
+
+use serde::Deserialize;
+use serde_json::Value;
+
+use super::{ChatMessage, FinishReason, Function, Role, ToolCall, Usage, normalize_arguments};
+
+/// One piece of a streamed response, whichever provider sent it.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ChatStreamChunk {
+    pub delta: MessageDelta,
+    /// Set on the last chunk of the message.
+    pub finish_reason: Option<FinishReason>,
+    pub usage: Option<Usage>,
+}
+
+/// What a chunk adds to the message.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct MessageDelta {
+    pub role: Option<Role>,
+    pub content: Option<String>,
+    pub thinking: Option<String>,
+    pub tool_calls: Vec<ToolCallDelta>,
+}
+
+/// A fragment of a tool call.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ToolCallDelta {
+    /// Which call of the message the fragment belongs to. `None` for a call that arrives
+    /// whole, as Ollama sends them.
+    pub index: Option<usize>,
+    pub id: Option<String>,
+    pub call_type: Option<String>,
+    pub name: Option<String>,
+    /// A piece of the JSON-encoded arguments.
+    pub arguments: Option<String>,
+}
+
+/// A line of Ollama's NDJSON stream.
+#[derive(Debug, Clone, Deserialize)]
+pub struct OllamaStreamChunk {
+    #[serde(default)]
+    pub model: Option<String>,
+    #[serde(default)]
+    pub message: Option<OllamaMessageFragment>,
+    #[serde(default)]
+    pub done: bool,
+    #[serde(default)]
+    pub done_reason: Option<FinishReason>,
+    #[serde(default)]
+    pub prompt_eval_count: Option<u32>,
+    #[serde(default)]
+    pub eval_count: Option<u32>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct OllamaMessageFragment {
+    #[serde(default)]
+    pub role: Option<Role>,
+    #[serde(default)]
+    pub content: String,
+    #[serde(default)]
+    pub thinking: Option<String>,
+    #[serde(default)]
+    pub tool_calls: Vec<ToolCall>,
+}
+
+impl From<OllamaStreamChunk> for ChatStreamChunk {
+    fn from(chunk: OllamaStreamChunk) -> Self {
+        let delta = match chunk.message {
+            Some(message) => MessageDelta {
+                role: message.role,
+                content: Some(message.content).filter(|content| !content.is_empty()),
+                thinking: message.thinking.filter(|thinking| !thinking.is_empty()),
+                tool_calls: message
+                    .tool_calls
+                    .into_iter()
+                    .map(|tool_call| ToolCallDelta {
+                        index: None,
+                        id: tool_call.id,
+                        call_type: tool_call.call_type,
+                        name: Some(tool_call.function.name),
+                        arguments: Some(tool_call.function.arguments.to_string()),
+                    })
+                    .collect(),
+            },
+            None => MessageDelta::default(),
+        };
+        let usage = match chunk.done {
+            true => Usage::from_ollama(chunk.prompt_eval_count, chunk.eval_count),
+            false => None,
+        };
+
+        ChatStreamChunk {
+            delta,
+            finish_reason: chunk.done_reason,
+            usage,
+        }
+    }
+}
+
+/// The JSON of a `data:` line in OpenRouter's server-sent event stream.
+#[derive(Debug, Clone, Deserialize)]
+pub struct OpenRouterStreamChunk {
+    #[serde(default)]
+    pub model: Option<String>,
+    #[serde(default)]
+    pub choices: Vec<OpenRouterStreamChoice>,
+    /// Sent on the last chunk.
+    #[serde(default)]
+    pub usage: Option<Usage>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct OpenRouterStreamChoice {
+    #[serde(default)]
+    pub delta: OpenRouterDelta,
+    #[serde(default)]
+    pub finish_reason: Option<FinishReason>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct OpenRouterDelta {
+    #[serde(default)]
+    pub role: Option<Role>,
+    #[serde(default)]
+    pub content: Option<String>,
+    #[serde(default)]
+    pub reasoning: Option<String>,
+    #[serde(default)]
+    pub tool_calls: Vec<OpenRouterToolCallDelta>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct OpenRouterToolCallDelta {
+    pub index: usize,
+    #[serde(default)]
+    pub id: Option<String>,
+    #[serde(rename = "type", default)]
+    pub call_type: Option<String>,
+    #[serde(default)]
+    pub function: Option<OpenRouterFunctionDelta>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct OpenRouterFunctionDelta {
+    #[serde(default)]
+    pub name: Option<String>,
+    #[serde(default)]
+    pub arguments: Option<String>,
+}
+
+impl From<OpenRouterStreamChunk> for ChatStreamChunk {
+    fn from(chunk: OpenRouterStreamChunk) -> Self {
+        let (delta, finish_reason) = match chunk.choices.into_iter().next() {
+            Some(choice) => (choice.delta, choice.finish_reason),
+            None => (OpenRouterDelta::default(), None),
+        };
+
+        ChatStreamChunk {
+            delta: MessageDelta {
+                role: delta.role,
+                content: delta.content.filter(|content| !content.is_empty()),
+                thinking: delta.reasoning.filter(|reasoning| !reasoning.is_empty()),
+                tool_calls: delta
+                    .tool_calls
+                    .into_iter()
+                    .map(|tool_call| {
+                        let function = tool_call.function;
+                        ToolCallDelta {
+                            index: Some(tool_call.index),
+                            id: tool_call.id,
+                            call_type: tool_call.call_type,
+                            name: function.as_ref().and_then(|f| f.name.clone()),
+                            arguments: function.and_then(|f| f.arguments),
+                        }
+                    })
+                    .collect(),
+            },
+            finish_reason,
+            usage: chunk.usage,
+        }
+    }
+}
+
+/// Joins the chunks of a streamed response into the message a non-streaming request
+/// would have returned.
+#[derive(Debug, Default)]
+pub struct MessageAccumulator {
+    role: Option<Role>,
+    content: String,
+    thinking: String,
+    tool_calls: Vec<PartialToolCall>,
+    finish_reason: Option<FinishReason>,
+    usage: Option<Usage>,
+}
+
+#[derive(Debug, Default)]
+struct PartialToolCall {
+    index: Option<usize>,
+    id: Option<String>,
+    call_type: Option<String>,
+    name: String,
+    arguments: String,
+}
+
+impl MessageAccumulator {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn push(&mut self, chunk: ChatStreamChunk) {
+        let delta = chunk.delta;
+        if delta.role.is_some() {
+            self.role = delta.role;
+        }
+        self.content.extend(delta.content);
+        self.thinking.extend(delta.thinking);
+
+        for fragment in delta.tool_calls {
+            let position = fragment.index.and_then(|index| {
+                self.tool_calls
+                    .iter()
+                    .position(|tool_call| tool_call.index == Some(index))
+            });
+            let tool_call = match position {
+                Some(position) => &mut self.tool_calls[position],
+                None => {
+                    self.tool_calls.push(PartialToolCall {
+                        index: fragment.index,
+                        ..PartialToolCall::default()
+                    });
+                    self.tool_calls.last_mut().unwrap()
+                }
+            };
+            if fragment.id.is_some() {
+                tool_call.id = fragment.id;
+            }
+            if fragment.call_type.is_some() {
+                tool_call.call_type = fragment.call_type;
+            }
+            tool_call.name.extend(fragment.name);
+            tool_call.arguments.extend(fragment.arguments);
+        }
+
+        if chunk.finish_reason.is_some() {
+            self.finish_reason = chunk.finish_reason;
+        }
+        if chunk.usage.is_some() {
+            self.usage = chunk.usage;
+        }
+    }
+
+    /// The finish reason of the last chunk that had one.
+    pub fn finish_reason(&self) -> Option<&FinishReason> {
+        self.finish_reason.as_ref()
+    }
+
+    pub fn usage(&self) -> Option<&Usage> {
+        self.usage.as_ref()
+    }
+
+    /// The message the chunks add up to.
+    pub fn finish(self) -> ChatMessage {
+        let tool_calls: Vec<ToolCall> = self
+            .tool_calls
+            .into_iter()
+            .map(|tool_call| {
+                let (arguments, repaired) = normalize_arguments(Value::String(tool_call.arguments));
+                ToolCall {
+                    id: tool_call.id,
+                    call_type: tool_call.call_type,
+                    function: Function {
+                        name: tool_call.name,
+                        arguments,
+                        repaired,
+                    },
+                }
+            })
+            .collect();
+
+        ChatMessage {
+            role: self.role.unwrap_or(Role::Assistant),
+            content: self.content,
+            thinking: Some(self.thinking).filter(|thinking| !thinking.is_empty()),
+            tool_calls: Some(tool_calls).filter(|tool_calls| !tool_calls.is_empty()),
+            tool_call_id: None,
+            name: None,
+            images: None,
+        }
+    }
+}
@@ -0,0 +1,95 @@
+//! Reporting errors to a monitoring service as they cross component boundaries.
+//!
+//! An [`ErrorObserver`] sees these errors before they propagate:
+//!
+//! | Component | Reported when |
+//! | --- | --- |
+//! | `llm` | an [`LLMClient`](crate::llm_client::LLMClient) request fails |
+//! | `planner` | the model answered but the planner could not use the answer |
+//! | `step` | a plan step fails all its attempts in the [`Agent`](crate::agent::Agent) |
+//! | `pool` | a step sent to an [`AgenticTaskPool`](crate::worker::AgenticTaskPool) fails |
+//! | `mcp` | an MCP server fails to start, stop or restart in an [`AgenticSystem`](crate::AgenticSystem) |
+
+use std::{
+    panic::{AssertUnwindSafe, catch_unwind},
+    sync::Arc,
+};
+
+use crate::errors::AgenticFlowError;
+
+tokio::task_local! {
+    static RUN_ID: String;
+}
+
+/// Receives errors at the reporting points listed in the [module docs](self).
+///
+/// Observers only look: the error propagates unchanged whatever the observer does,
+/// and a panicking observer is caught and ignored.
+pub trait ErrorObserver: Send + Sync {
+    fn on_error(&self, error: &AgenticFlowError, context: &ErrorContext);
+}
+
+/// Where a reported error happened.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ErrorContext {
+    /// The reporting point, e.g. `llm` or `step`.
+    pub component: &'static str,
+    /// The [`AgenticSystem::plan_and_execute`](crate::AgenticSystem::plan_and_execute)
+    /// call the error happened in, if any.
+    pub run_id: Option<String>,
+    pub tool: Option<String>,
+    pub server: Option<String>,
+}
+
+impl ErrorContext {
+    /// Context for `error` reported by `component`, with the tool and server the error names.
+    pub fn new(component: &'static str, error: &AgenticFlowError) -> Self {
+        Self {
+            component,
+            run_id: RUN_ID.try_with(String::clone).ok(),
+            tool: error.tool_name().map(str::to_string),
+            server: error.server_name().map(str::to_string),
+        }
+    }
+}
+
+/// Runs `future` as the run `run_id`, so errors reported from it carry that id.
+pub(crate) async fn in_run<F: Future>(run_id: String, future: F) -> F::Output {
+    RUN_ID.scope(run_id, future).await
+}
+
+pub(crate) fn report(
+    observer: &Option<Arc<dyn ErrorObserver>>,
+    error: &AgenticFlowError,
+    context: ErrorContext,
+) {
+    if let Some(observer) = observer {
+        let reported = catch_unwind(AssertUnwindSafe(|| observer.on_error(error, &context)));
+        if reported.is_err() {
+            println!(
+                "WARNING: Error observer panicked while reporting a {} error",
+                context.component
+            );
+        }
+    }
+}
+
+/// Logs every reported error as a `tracing` event at error level.
+#[cfg(feature = "tracing")]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct TracingErrorObserver;
+
+#[cfg(feature = "tracing")]
+impl ErrorObserver for TracingErrorObserver {
+    fn on_error(&self, error: &AgenticFlowError, context: &ErrorContext) {
+        tracing::error!(
+            component = context.component,
+            run_id = context.run_id.as_deref(),
+            tool = context.tool.as_deref(),
+            server = context.server.as_deref(),
+            code = error.to_wire().code,
+            "{}",
+            error
+        );
+    }
+}
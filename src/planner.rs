@@ -1,41 +1,604 @@
-use core::fmt;
+mod beam;
+mod graph;
+mod mcts;
+mod prompts;
+mod react;
+mod reflexion;
+mod schema;
+
 use std::{sync::Arc, vec};
 
 use tokio::sync::Mutex;
 
-use serde_json::Value;
+use serde::{Deserialize, Serialize};
+use serde_json::{Value, json};
 
 use crate::{
-    errors::AgenticFlowError,
-    llm_client::LLMClient,
-    model::{ChatMessage, ToolCall},
+    errors::{AgenticFlowError, PlanningDiagnostics},
+    llm_client::{LLMClient, LLMRouter, Purpose, RequestOptions, ToolChoice},
+    model::{ChatMessage, ChatResponse, FinishReason, ToolCall, decode_arguments},
+    observer::{self, ErrorContext, ErrorObserver},
+    prompt::{ChatPrompt, describe_tools},
     tool_registry::ToolRegistry,
 };
 
+pub use beam::{
+    BeamScorer, BeamSearchPlanner, DEFAULT_BEAM_DEPTH, DEFAULT_BEAM_WIDTH, HeuristicScorer,
+    LLMScorer, PlanScorer,
+};
+pub(crate) use graph::StepGraph;
+pub use mcts::{
+    DEFAULT_MCTS_BRANCHING, DEFAULT_MCTS_DEPTH, DEFAULT_MCTS_EXPLORATION, LLMJudgeEvaluator,
+    LLMStepGenerator, MctsStrategy, MonteCarloTreeSearchPlanner, PlanEvaluator,
+    PlanLengthEvaluator, StepGenerator,
+};
+pub use prompts::PlannerPrompts;
+pub use react::ReActPlanner;
+pub use reflexion::{ReflectedPlan, ReflexionPlanner};
+pub use schema::{DEFAULT_SCHEMA_REPAIR_ROUNDS, SchemaRepairPlanner};
+
+/// One tool call of a plan.
+///
+/// The JSON form is part of persisted plans and checkpoints: `tool_name` and `params`,
+/// plus `id`, `description` and `depends_on` when set. Do not rename these fields.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct PlanStep {
+    /// Identifies the step in errors and outcomes, e.g. `fetch-docs`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub id: Option<String>,
     pub tool_name: String,
+    #[serde(default)]
     pub params: Value,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    /// Whether `params` were repaired from almost-valid JSON the model wrote, such as
+    /// arguments with a trailing comma; see [`json_repair`](crate::json_repair).
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub params_repaired: bool,
+    /// The ids of the steps that must be recorded before this one runs. The
+    /// [`Agent`](crate::agent::Agent) runs a step after those it depends on, and in
+    /// parallel mode alongside the steps it does not depend on.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub depends_on: Vec<String>,
 }
 
-impl fmt::Debug for PlanStep {
-    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        write!(f, "PlanStep {{ tool_name: {}, params: {} }}", self.tool_name, self.params)
+impl PlanStep {
+    pub fn new(tool_name: impl Into<String>, params: Value) -> Self {
+        Self {
+            id: None,
+            tool_name: tool_name.into(),
+            params,
+            description: None,
+            params_repaired: false,
+            depends_on: Vec::new(),
+        }
+    }
+
+    pub fn with_id(mut self, id: impl Into<String>) -> Self {
+        self.id = Some(id.into());
+        self
+    }
+
+    pub fn with_description(mut self, description: impl Into<String>) -> Self {
+        self.description = Some(description.into());
+        self
     }
+
+    pub fn with_depends_on<I: Into<String>>(mut self, ids: impl IntoIterator<Item = I>) -> Self {
+        self.depends_on = ids.into_iter().map(Into::into).collect();
+        self
+    }
+}
+
+/// A step that ran to completion, as recorded in the execution context.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct StepOutcome {
+    /// Zero-based position of the step in the plan.
+    pub step_index: usize,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub step_id: Option<String>,
+    pub tool: String,
+    pub params: Value,
+    /// The recorded value: the tool's result, or `{"error": ...}` for a step that failed
+    /// under [`FailurePolicy::Continue`](crate::agent::FailurePolicy::Continue).
+    pub result: Value,
 }
 
 #[async_trait::async_trait]
 pub trait Executor: Send + Sync {
     async fn execute(&self, steps: Vec<PlanStep>) -> Result<String, AgenticFlowError>;
+
+    /// Runs the steps `planner` chooses for `task`, one at a time, and returns its final
+    /// answer. Executors that only run whole plans fail with
+    /// [`AgenticFlowError::ExecutionError`].
+    async fn execute_iteratively(
+        &self,
+        _task: &str,
+        _planner: &dyn IterativePlanner,
+    ) -> Result<String, AgenticFlowError> {
+        Err(AgenticFlowError::ExecutionError(
+            "this executor cannot run planners that choose one step at a time".to_string(),
+        ))
+    }
 }
 
 #[async_trait::async_trait]
 pub trait Planner: Send + Sync {
     async fn plan(&self, task: &str) -> Result<Vec<PlanStep>, AgenticFlowError>;
+
+    /// This planner as an [`IterativePlanner`], if it chooses its steps while they run.
+    /// [`AgenticSystem`](crate::AgenticSystem) then hands it to
+    /// [`Executor::execute_iteratively`] instead of calling `plan`.
+    fn as_iterative(&self) -> Option<&dyn IterativePlanner> {
+        None
+    }
+}
+
+/// What an [`IterativePlanner`] does next.
+#[derive(Debug, Clone, PartialEq)]
+pub enum NextStep {
+    /// Runs this step, then asks for the next one with its outcome.
+    Call(PlanStep),
+    /// Ends the run with this answer.
+    Finish(String),
+}
+
+/// A planner that chooses one step at a time from the outcomes of the steps before it,
+/// for tasks whose later steps depend on what the earlier ones return.
+#[async_trait::async_trait]
+pub trait IterativePlanner: Send + Sync {
+    /// The step after the `observed` ones, which ran in order, or the final answer.
+    async fn next_step(
+        &self,
+        task: &str,
+        observed: &[StepOutcome],
+    ) -> Result<NextStep, AgenticFlowError>;
+}
+
+/// Selects which [`Planner`] implementation the system uses.
+#[derive(Debug, Clone, PartialEq, Default, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PlannerKind {
+    #[default]
+    MultiStep,
+    ChainOfThought,
+    Htn,
+    /// [`MonteCarloTreeSearchPlanner`] searching a tree of partial plans.
+    Mcts {
+        simulations: usize,
+    },
+    /// [`MonteCarloTreeSearchPlanner`] with [`MctsStrategy::Sampling`].
+    MctsSampling {
+        simulations: usize,
+    },
+    /// [`ReActPlanner`], which chooses each step after seeing the result of the last.
+    React,
+    /// [`BeamSearchPlanner`], which keeps the best `width` partial plans at each step.
+    BeamSearch {
+        width: usize,
+        depth: usize,
+        scorer: BeamScorer,
+    },
+}
+
+impl PlannerKind {
+    pub fn build(
+        &self,
+        llm_client: LLMClient,
+        tool_registry: Arc<Mutex<ToolRegistry>>,
+    ) -> Box<dyn Planner> {
+        match self {
+            PlannerKind::MultiStep => Box::new(MultiStepPlanner::new(llm_client, tool_registry)),
+            PlannerKind::ChainOfThought => {
+                Box::new(ChainOfThoughtPlanner::new(llm_client, tool_registry))
+            }
+            PlannerKind::Htn => Box::new(HTNPlanner::new(llm_client, tool_registry)),
+            PlannerKind::Mcts { simulations } => Box::new(MonteCarloTreeSearchPlanner::new(
+                llm_client,
+                tool_registry,
+                *simulations,
+            )),
+            PlannerKind::MctsSampling { simulations } => Box::new(
+                MonteCarloTreeSearchPlanner::new(llm_client, tool_registry, *simulations)
+                    .with_strategy(MctsStrategy::Sampling),
+            ),
+            PlannerKind::React => Box::new(ReActPlanner::new(llm_client, tool_registry)),
+            PlannerKind::BeamSearch {
+                width,
+                depth,
+                scorer,
+            } => {
+                let planner = BeamSearchPlanner::new(llm_client.clone(), tool_registry)
+                    .with_beam_width(*width)
+                    .with_max_depth(*depth);
+                match scorer {
+                    BeamScorer::Heuristic => Box::new(planner),
+                    BeamScorer::Llm => Box::new(planner.with_scorer(LLMScorer::new(llm_client))),
+                }
+            }
+        }
+    }
+}
+
+pub const DEFAULT_MCTS_SIMULATIONS: usize = 5;
+
+/// Planner names as written in the `planner` section of a config file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize, Serialize)]
+pub enum PlannerStrategy {
+    #[default]
+    #[serde(rename = "multistep", alias = "multi_step")]
+    MultiStep,
+    #[serde(rename = "cot", alias = "chain_of_thought")]
+    ChainOfThought,
+    #[serde(rename = "htn")]
+    Htn,
+    #[serde(rename = "mcts")]
+    Mcts,
+    #[serde(rename = "react")]
+    React,
+    #[serde(rename = "beam", alias = "beam_search")]
+    Beam,
+}
+
+/// The `planner` section of a config file.
+///
+/// ```toml
+/// [planner]
+/// kind = "mcts"
+/// mcts_simulations = 8
+/// critique_rounds = 1
+/// schema_repair_rounds = 1
+/// fallback = ["multistep"]
+/// ```
+#[derive(Debug, Clone, PartialEq, Default, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct PlannerConfig {
+    pub kind: PlannerStrategy,
+    /// Only used by `mcts`, defaults to [`DEFAULT_MCTS_SIMULATIONS`].
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub mcts_simulations: Option<usize>,
+    /// Only used by `mcts`, defaults to `tree`; `sampling` keeps the earlier behavior.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub mcts_strategy: Option<MctsStrategy>,
+    /// Only used by `beam`, defaults to [`DEFAULT_BEAM_WIDTH`].
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub beam_width: Option<usize>,
+    /// Only used by `beam`, defaults to [`DEFAULT_BEAM_DEPTH`].
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub beam_depth: Option<usize>,
+    /// Only used by `beam`, defaults to `heuristic`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub beam_scorer: Option<BeamScorer>,
+    /// How many times the LLM is asked to critique and revise the plan.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub critique_rounds: Option<usize>,
+    /// Checks the plan against the parameter schemas of the tools and asks the LLM up to
+    /// this many times to fix the steps that break them; see [`SchemaRepairPlanner`].
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub schema_repair_rounds: Option<usize>,
+    /// Planners tried in order when the previous one fails or returns an empty plan.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub fallback: Vec<PlannerStrategy>,
+    #[serde(skip_serializing_if = "DiagnosticsConfig::is_default")]
+    pub diagnostics: DiagnosticsConfig,
+}
+
+pub const DEFAULT_DIAGNOSTIC_CHARS: usize = 2000;
+
+/// How much of the model output planning errors keep, from `[planner.diagnostics]`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct DiagnosticsConfig {
+    /// Longest raw content or tool call JSON kept, in characters.
+    pub max_chars: usize,
+    /// Leaves the raw output out of the wire form of planning errors.
+    pub redact: bool,
+}
+
+impl Default for DiagnosticsConfig {
+    fn default() -> Self {
+        Self {
+            max_chars: DEFAULT_DIAGNOSTIC_CHARS,
+            redact: false,
+        }
+    }
+}
+
+impl DiagnosticsConfig {
+    fn is_default(&self) -> bool {
+        *self == Self::default()
+    }
+}
+
+impl PlannerConfig {
+    /// The primary planner selected by this section.
+    pub fn planner_kind(&self) -> PlannerKind {
+        self.kind_for(self.kind)
+    }
+
+    fn kind_for(&self, strategy: PlannerStrategy) -> PlannerKind {
+        match strategy {
+            PlannerStrategy::MultiStep => PlannerKind::MultiStep,
+            PlannerStrategy::ChainOfThought => PlannerKind::ChainOfThought,
+            PlannerStrategy::Htn => PlannerKind::Htn,
+            PlannerStrategy::Mcts => {
+                let simulations = self.mcts_simulations.unwrap_or(DEFAULT_MCTS_SIMULATIONS);
+                match self.mcts_strategy.unwrap_or_default() {
+                    MctsStrategy::Tree => PlannerKind::Mcts { simulations },
+                    MctsStrategy::Sampling => PlannerKind::MctsSampling { simulations },
+                }
+            }
+            PlannerStrategy::React => PlannerKind::React,
+            PlannerStrategy::Beam => PlannerKind::BeamSearch {
+                width: self.beam_width.unwrap_or(DEFAULT_BEAM_WIDTH),
+                depth: self.beam_depth.unwrap_or(DEFAULT_BEAM_DEPTH),
+                scorer: self.beam_scorer.unwrap_or_default(),
+            },
+        }
+    }
+
+    /// Builds the primary planner, chained with its fallbacks and wrapped in critique rounds.
+    /// With an [`LLMRouter`], the planners use its [`Purpose::Planning`] client and the
+    /// critique rounds its [`Purpose::Reflection`] client. The critiqued plan is then
+    /// checked against the tool schemas. The `react` planner has no plan to fall back
+    /// from, critique or check, and ignores all three.
+    pub fn build(
+        &self,
+        llm: impl Into<LLMRouter>,
+        tool_registry: Arc<Mutex<ToolRegistry>>,
+    ) -> Box<dyn Planner> {
+        let llm = llm.into();
+        let llm_client = llm.client(Purpose::Planning);
+        let mut planner = self
+            .planner_kind()
+            .build(llm_client.clone(), tool_registry.clone());
+
+        let iterative = self.kind == PlannerStrategy::React;
+        if !self.fallback.is_empty() && !iterative {
+            let mut planners = vec![planner];
+            planners.extend(self.fallback.iter().map(|strategy| {
+                self.kind_for(*strategy)
+                    .build(llm_client.clone(), tool_registry.clone())
+            }));
+            planner = Box::new(FallbackPlanner::new(planners));
+        }
+
+        let error_observer = llm_client.error_observer().clone();
+        let critique_rounds = self
+            .critique_rounds
+            .filter(|rounds| *rounds > 0 && !iterative);
+        if let Some(rounds) = critique_rounds {
+            planner = Box::new(CritiquePlanner::new(
+                planner,
+                llm.client(Purpose::Reflection).clone(),
+                tool_registry.clone(),
+                rounds,
+            ));
+        }
+        if let Some(rounds) = self.schema_repair_rounds.filter(|_| !iterative) {
+            planner = Box::new(SchemaRepairPlanner::new(
+                planner,
+                llm_client.clone(),
+                tool_registry,
+                rounds,
+            ));
+        }
+
+        Box::new(DiagnosticsPlanner {
+            inner: planner,
+            config: self.diagnostics,
+            error_observer,
+        })
+    }
+}
+
+impl From<PlannerKind> for PlannerConfig {
+    fn from(kind: PlannerKind) -> Self {
+        let (kind, mcts_simulations) = match kind {
+            PlannerKind::MultiStep => (PlannerStrategy::MultiStep, None),
+            PlannerKind::ChainOfThought => (PlannerStrategy::ChainOfThought, None),
+            PlannerKind::Htn => (PlannerStrategy::Htn, None),
+            PlannerKind::Mcts { simulations } => (PlannerStrategy::Mcts, Some(simulations)),
+            PlannerKind::React => (PlannerStrategy::React, None),
+            PlannerKind::MctsSampling { simulations } => {
+                return Self {
+                    kind: PlannerStrategy::Mcts,
+                    mcts_simulations: Some(simulations),
+                    mcts_strategy: Some(MctsStrategy::Sampling),
+                    ..Self::default()
+                };
+            }
+            PlannerKind::BeamSearch {
+                width,
+                depth,
+                scorer,
+            } => {
+                return Self {
+                    kind: PlannerStrategy::Beam,
+                    beam_width: Some(width),
+                    beam_depth: Some(depth),
+                    beam_scorer: Some(scorer),
+                    ..Self::default()
+                };
+            }
+        };
+
+        Self {
+            kind,
+            mcts_simulations,
+            ..Self::default()
+        }
+    }
+}
+
+/// Tries each planner in turn until one returns a non-empty plan.
+pub struct FallbackPlanner {
+    planners: Vec<Box<dyn Planner>>,
+}
+
+impl FallbackPlanner {
+    pub fn new(planners: Vec<Box<dyn Planner>>) -> Self {
+        Self { planners }
+    }
+}
+
+#[async_trait::async_trait]
+impl Planner for FallbackPlanner {
+    async fn plan(&self, task: &str) -> Result<Vec<PlanStep>, AgenticFlowError> {
+        let mut last = Ok(vec![]);
+
+        for (index, planner) in self.planners.iter().enumerate() {
+            match planner.plan(task).await {
+                Ok(steps) if !steps.is_empty() => return Ok(steps),
+                Err(mut error) => {
+                    if let Some(diagnostics) = error.planning_diagnostics_mut() {
+                        diagnostics.retries += index;
+                    }
+                    last = Err(error);
+                }
+                result => last = result,
+            }
+            if index + 1 < self.planners.len() {
+                println!(
+                    "WARNING: Planner {} of {} produced no plan, trying the next one",
+                    index + 1,
+                    self.planners.len()
+                );
+            }
+        }
+
+        last
+    }
+}
+
+/// Applies the [`DiagnosticsConfig`] limits to the errors of an inner planner, and reports
+/// those caused by unusable model output. Failed LLM calls are reported by the client.
+struct DiagnosticsPlanner {
+    inner: Box<dyn Planner>,
+    config: DiagnosticsConfig,
+    error_observer: Option<Arc<dyn ErrorObserver>>,
+}
+
+impl DiagnosticsPlanner {
+    fn diagnose(&self, mut error: AgenticFlowError) -> AgenticFlowError {
+        if let Some(diagnostics) = error.planning_diagnostics_mut() {
+            diagnostics.truncate(self.config.max_chars);
+            diagnostics.redacted = self.config.redact;
+        }
+        let answered = error
+            .planning_diagnostics()
+            .is_some_and(|diagnostics| diagnostics.raw_content.is_some());
+        if answered {
+            let context = ErrorContext::new("planner", &error);
+            observer::report(&self.error_observer, &error, context);
+        }
+        error
+    }
+}
+
+#[async_trait::async_trait]
+impl Planner for DiagnosticsPlanner {
+    async fn plan(&self, task: &str) -> Result<Vec<PlanStep>, AgenticFlowError> {
+        self.inner
+            .plan(task)
+            .await
+            .map_err(|error| self.diagnose(error))
+    }
+
+    fn as_iterative(&self) -> Option<&dyn IterativePlanner> {
+        self.inner
+            .as_iterative()
+            .map(|_| self as &dyn IterativePlanner)
+    }
+}
+
+#[async_trait::async_trait]
+impl IterativePlanner for DiagnosticsPlanner {
+    async fn next_step(
+        &self,
+        task: &str,
+        observed: &[StepOutcome],
+    ) -> Result<NextStep, AgenticFlowError> {
+        let Some(inner) = self.inner.as_iterative() else {
+            return Err(AgenticFlowError::PlanningError(
+                "the planner does not choose one step at a time".to_string(),
+            ));
+        };
+        inner
+            .next_step(task, observed)
+            .await
+            .map_err(|error| self.diagnose(error))
+    }
+}
+
+/// Asks the LLM to critique and revise the plan of an inner planner.
+pub struct CritiquePlanner {
+    inner: Box<dyn Planner>,
+    llm_client: LLMClient,
+    tool_registry: Arc<Mutex<ToolRegistry>>,
+    rounds: usize,
+}
+
+impl CritiquePlanner {
+    pub fn new(
+        inner: Box<dyn Planner>,
+        llm_client: LLMClient,
+        tool_registry: Arc<Mutex<ToolRegistry>>,
+        rounds: usize,
+    ) -> Self {
+        Self {
+            inner,
+            llm_client,
+            tool_registry,
+            rounds,
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl Planner for CritiquePlanner {
+    async fn plan(&self, task: &str) -> Result<Vec<PlanStep>, AgenticFlowError> {
+        let mut steps = self.inner.plan(task).await?;
+        let tools = plan_tools(self.tool_registry.lock().await.get_tools_for_planner());
+
+        for _ in 0..self.rounds {
+            let plan = steps
+                .iter()
+                .enumerate()
+                .map(|(index, step)| describe_step(index, step))
+                .collect::<Vec<_>>()
+                .join("\n");
+            let messages = vec![
+                ChatMessage::system("Critique the plan below. If it can be improved, respond with the complete revised plan as tool calls; otherwise respond without tool calls.".to_string()),
+                ChatMessage::user(format!("Task: {}\n\nPlan:\n{}", task, plan)),
+            ];
+
+            let response = self
+                .llm_client
+                .chat_completions(messages, tools.clone())
+                .await
+                .map_err(planning_failed("critique", "critique"))?;
+            let revised = plan_from_response(
+                "critique",
+                "critique",
+                response.as_ref(),
+                self.llm_client.max_tokens(),
+            )?;
+            if revised.is_empty() {
+                break;
+            }
+            steps = revised;
+        }
+
+        Ok(steps)
+    }
 }
 
 pub struct MultiStepPlanner {
     llm_client: LLMClient,
     tool_registry: Arc<Mutex<ToolRegistry>>,
+    prompt: ChatPrompt,
+    schema_repair_rounds: Option<usize>,
 }
 
 impl MultiStepPlanner {
@@ -43,197 +606,400 @@ impl MultiStepPlanner {
         Self {
             llm_client,
             tool_registry,
+            prompt: PlannerPrompts::default().multistep,
+            schema_repair_rounds: None,
         }
     }
+
+    /// Replaces the system and user prompts, which may use `{task}` and `{tools}`.
+    pub fn with_prompt(mut self, prompt: ChatPrompt) -> Self {
+        self.prompt = prompt;
+        self
+    }
+
+    /// Uses the `multistep` prompt of `prompts`, failing with
+    /// [`AgenticFlowError::ConfigError`] if it has a placeholder other than `{task}` and
+    /// `{tools}`, or lacks `{task}`.
+    pub fn with_prompts(self, prompts: &PlannerPrompts) -> Result<Self, AgenticFlowError> {
+        prompts::check("multistep", &prompts.multistep, None, "task")?;
+        Ok(self.with_prompt(prompts.multistep.clone()))
+    }
+
+    /// Checks the plan against the parameter schemas of the tools, asking the LLM
+    /// [`DEFAULT_SCHEMA_REPAIR_ROUNDS`] times to fix the steps that break them, as
+    /// [`SchemaRepairPlanner`] does.
+    pub fn with_schema_repair(self) -> Self {
+        self.with_schema_repair_rounds(DEFAULT_SCHEMA_REPAIR_ROUNDS)
+    }
+
+    /// Like [`with_schema_repair`](Self::with_schema_repair), with up to `rounds` repair
+    /// rounds; 0 only checks the plan.
+    pub fn with_schema_repair_rounds(mut self, rounds: usize) -> Self {
+        self.schema_repair_rounds = Some(rounds);
+        self
+    }
 }
 
 #[async_trait::async_trait]
 impl Planner for MultiStepPlanner {
     async fn plan(&self, task: &str) -> Result<Vec<PlanStep>, AgenticFlowError> {
-        let messages = vec![
-            ChatMessage::system("Analyze the task and create a multi-step plan.".to_string()),
-            ChatMessage::user(task.to_string()),
-        ];
-
-        let tools = self.tool_registry.lock().await.get_tools_for_planner();
+        // Such a model would answer without tool calls, which reads as an empty plan.
+        if !self.llm_client.capabilities().supports_tools && !self.llm_client.emulates_tools() {
+            return Err(AgenticFlowError::PlanningError(format!(
+                "model {} does not support tool calls, which MultiStepPlanner plans with; \
+                 use a model with tool support, LLMClient::with_capabilities if it has it, \
+                 or ToolEmulation::Auto",
+                self.llm_client.model_name().unwrap_or("unknown")
+            )));
+        }
+        let tools = plan_tools(self.tool_registry.lock().await.get_tools_for_planner());
+        let messages = self
+            .prompt
+            .messages(&[("task", task), ("tools", &describe_tools(&tools))])?;
+        // A plan without tool calls is of no use; models that support it must call one.
+        let options = RequestOptions::default().with_tool_choice(ToolChoice::Required);
 
-        self.llm_client
-            .chat_completions(messages, tools)
+        let response = self
+            .llm_client
+            .chat_completions_with(messages, tools.clone(), &options)
             .await
-            .map(|response| {
-                let message = response.message();
-                collect_as_plan_steps(&message.tool_calls)
-            })
+            .map_err(planning_failed("multistep", "plan"))?;
+        let steps = plan_from_response(
+            "multistep",
+            "plan",
+            response.as_ref(),
+            self.llm_client.max_tokens(),
+        )?;
+        match self.schema_repair_rounds {
+            Some(rounds) => {
+                schema::repair("multistep", &self.llm_client, task, &tools, steps, rounds).await
+            }
+            None => Ok(steps),
+        }
     }
 }
 
 impl From<&ToolCall> for PlanStep {
     fn from(tool_call: &ToolCall) -> Self {
-        PlanStep {
-            tool_name: tool_call.function.name.clone(),
-            params: tool_call.function.arguments.clone(),
+        let mut step = PlanStep::new(
+            tool_call.function.name.clone(),
+            tool_call.function.arguments.clone(),
+        );
+        step.params_repaired = tool_call.function.repaired;
+        step
+    }
+}
+
+/// Wraps an LLM failure with the planner and phase it happened in.
+fn planning_failed(
+    planner: &'static str,
+    phase: &'static str,
+) -> impl FnOnce(AgenticFlowError) -> AgenticFlowError {
+    move |source| AgenticFlowError::PlanningFailed {
+        planner: planner.to_string(),
+        phase,
+        source: Box::new(source),
+        diagnostics: Box::default(),
+    }
+}
+
+/// Turns the tool calls of a planning response into plan steps. A response that
+/// cannot be used fails with the response attached as diagnostics; one cut off at the
+/// token limit, `max_tokens` if the client sets one, fails with
+/// [`AgenticFlowError::OutputTruncated`] rather than passing for a shorter plan.
+fn plan_from_response(
+    planner: &'static str,
+    phase: &'static str,
+    response: &dyn ChatResponse,
+    max_tokens: Option<usize>,
+) -> Result<Vec<PlanStep>, AgenticFlowError> {
+    let message = response.message();
+    let steps = if response.finish_reason() == Some(FinishReason::Length) {
+        Err(AgenticFlowError::OutputTruncated { max_tokens })
+    } else {
+        message
+            .tool_calls
+            .iter()
+            .flatten()
+            .map(plan_step)
+            .collect::<Result<_, _>>()
+    };
+    steps.map_err(|source| AgenticFlowError::PlanningFailed {
+        planner: planner.to_string(),
+        phase,
+        source: Box::new(source),
+        diagnostics: Box::new(PlanningDiagnostics::from_response(message)),
+    })
+}
+
+/// The argument a planning tool call names its step with, offered by [`plan_tools`].
+const STEP_ID_ARGUMENT: &str = "_step_id";
+/// The argument a planning tool call lists the ids of the steps it needs in.
+const DEPENDS_ON_ARGUMENT: &str = "_depends_on";
+
+/// `tools` with the optional [`STEP_ID_ARGUMENT`] and [`DEPENDS_ON_ARGUMENT`] added to
+/// their parameters, for the planners that make whole plans to say which steps need the
+/// results of others.
+fn plan_tools(mut tools: Vec<Value>) -> Vec<Value> {
+    for tool in &mut tools {
+        let Some(parameters) = tool["function"]["parameters"].as_object_mut() else {
+            continue;
+        };
+        let properties = parameters.entry("properties").or_insert_with(|| json!({}));
+        if let Some(properties) = properties.as_object_mut() {
+            properties.insert(
+                STEP_ID_ARGUMENT.to_string(),
+                json!({
+                    "type": "string",
+                    "description": "Names this step, for the _depends_on of other steps. An \
+                                    argument of a later step written as \
+                                    \"{{steps.<id>.output.<field>}}\" is replaced by that \
+                                    field of this step's result."
+                }),
+            );
+            properties.insert(
+                DEPENDS_ON_ARGUMENT.to_string(),
+                json!({
+                    "type": "array",
+                    "items": {"type": "string"},
+                    "description": "The _step_id of each step whose result this step needs. \
+                                    Steps that need nothing from each other may run at the \
+                                    same time."
+                }),
+            );
         }
     }
+    tools
+}
+
+/// Accepts arguments given as an object or as a JSON-encoded object, repairing
+/// almost-valid JSON; missing or empty arguments become `{}`. The step's id and
+/// dependencies are taken out of the arguments, see [`plan_tools`].
+fn plan_step(tool_call: &ToolCall) -> Result<PlanStep, AgenticFlowError> {
+    let mut step = PlanStep::from(tool_call);
+    let (params, repaired) = decode_arguments(step.params)?;
+    step.params_repaired |= repaired;
+    let mut params = match params {
+        Value::Object(params) => params,
+        Value::Null => Default::default(),
+        _ => return Err(invalid_arguments(&step.tool_name)),
+    };
+    if let Some(id) = params.remove(STEP_ID_ARGUMENT) {
+        step.id = Some(match id {
+            Value::String(id) => id,
+            id => id.to_string(),
+        });
+    }
+    if let Some(depends_on) = params.remove(DEPENDS_ON_ARGUMENT) {
+        step.depends_on = match depends_on {
+            Value::Array(ids) => ids
+                .into_iter()
+                .map(|id| match id {
+                    Value::String(id) => id,
+                    id => id.to_string(),
+                })
+                .collect(),
+            Value::String(id) => vec![id],
+            _ => Vec::new(),
+        };
+    }
+    step.params = Value::Object(params);
+    Ok(step)
 }
 
-fn collect_as_plan_steps(tool_calls: &Option<Vec<ToolCall>>) -> Vec<PlanStep> {
-    tool_calls
-        .iter()
-        .flat_map(|f| f.into_iter().map(|tool_call| tool_call.into()))
-        .collect()
+/// `step` as a line of a numbered plan, with its id and dependencies if it has them.
+fn describe_step(index: usize, step: &PlanStep) -> String {
+    let mut line = format!("{}. {} {}", index + 1, step.tool_name, step.params);
+    if let Some(id) = &step.id {
+        line.push_str(&format!(" (id: {})", id));
+    }
+    if !step.depends_on.is_empty() {
+        line.push_str(&format!(" (depends on: {})", step.depends_on.join(", ")));
+    }
+    line
 }
+
+fn invalid_arguments(tool_name: &str) -> AgenticFlowError {
+    AgenticFlowError::PlanningError(format!(
+        "Tool call '{}' has arguments that are not a JSON object",
+        tool_name
+    ))
+}
+
+/// Where the chain of thought ends and the plan begins.
+const PLAN_MARKER: &str = "\nPlan:";
+
 pub struct ChainOfThoughtPlanner {
     llm_client: LLMClient,
     tool_registry: Arc<Mutex<ToolRegistry>>,
+    chain_prompt: ChatPrompt,
+    plan_prompt: ChatPrompt,
 }
 
 impl ChainOfThoughtPlanner {
     pub fn new(llm_client: LLMClient, tool_registry: Arc<Mutex<ToolRegistry>>) -> Self {
+        let prompts = PlannerPrompts::default();
         Self {
             llm_client,
             tool_registry,
+            chain_prompt: prompts.chain_of_thought,
+            plan_prompt: prompts.chain_of_thought_plan,
         }
     }
+
+    /// Replaces the prompts asking for the chain of thought, which may use `{task}` and
+    /// `{tools}`. The answer is cut at a line starting with `Plan:`.
+    pub fn with_chain_prompt(mut self, prompt: ChatPrompt) -> Self {
+        self.chain_prompt = prompt;
+        self
+    }
+
+    /// Replaces the prompts asking for the plan, which may use `{task}`, `{tools}` and
+    /// `{chain_of_thought}`.
+    pub fn with_plan_prompt(mut self, prompt: ChatPrompt) -> Self {
+        self.plan_prompt = prompt;
+        self
+    }
+
+    /// Uses the `chain_of_thought` and `chain_of_thought_plan` prompts of `prompts`,
+    /// failing with [`AgenticFlowError::ConfigError`] if one has a placeholder its phase
+    /// has no value for, or the first lacks `{task}` or the second `{chain_of_thought}`.
+    pub fn with_prompts(self, prompts: &PlannerPrompts) -> Result<Self, AgenticFlowError> {
+        prompts::check("chain_of_thought", &prompts.chain_of_thought, None, "task")?;
+        prompts::check(
+            "chain_of_thought_plan",
+            &prompts.chain_of_thought_plan,
+            Some("chain_of_thought"),
+            "chain_of_thought",
+        )?;
+        Ok(self
+            .with_chain_prompt(prompts.chain_of_thought.clone())
+            .with_plan_prompt(prompts.chain_of_thought_plan.clone()))
+    }
 }
 
 #[async_trait::async_trait]
 impl Planner for ChainOfThoughtPlanner {
     async fn plan(&self, task: &str) -> Result<Vec<PlanStep>, AgenticFlowError> {
         // Step 1: Ask the LLM for a detailed chain of thought.
-        let chain_messages = vec![
-            ChatMessage::system("Provide a detailed chain-of-thought analysis before forming a plan.".to_string()),
-            ChatMessage::user(format!("Task: {}\nChain-of-Thought:", task)),
-        ];
+        let tools = plan_tools(self.tool_registry.lock().await.get_tools_for_planner());
+        let tool_list = describe_tools(&tools);
+        let chain_messages = self
+            .chain_prompt
+            .messages(&[("task", task), ("tools", &tool_list)])?;
+        // Stop before the model writes the plan itself, which is the next phase. The
+        // tools inform the reasoning but may not be called yet.
+        let chain_options = RequestOptions::default()
+            .with_stop(vec![PLAN_MARKER.to_string()])
+            .with_tool_choice(ToolChoice::None);
         let chain_response = self.llm_client
-            .chat_completions(chain_messages, vec![])
-            .await?;
+            .chat_completions_with(chain_messages, tools.clone(), &chain_options)
+            .await
+            .map_err(planning_failed("cot", "chain_of_thought"))?;
         let chain_thought = &chain_response.message().content;
         
         // Step 2: Use the chain-of-thought to generate a multi-step plan.
-        let plan_prompt = format!(
-            "Based on the following chain-of-thought, generate a multi-step plan with tool calls in JSON format.\n\nChain-of-Thought:\n{}\n\nPlan:",
-            chain_thought
-        );
-        let plan_messages = vec![
-            ChatMessage::system("Generate a multi-step plan using the provided chain-of-thought.".to_string()),
-            ChatMessage::user(plan_prompt),
-        ];
-        let tools = self.tool_registry.lock().await.get_tools_for_planner();
+        let plan_messages = self.plan_prompt.messages(&[
+            ("task", task),
+            ("tools", &tool_list),
+            ("chain_of_thought", chain_thought),
+        ])?;
         let plan_response = self.llm_client
             .chat_completions(plan_messages, tools)
-            .await?;
-        
-        let tool_calls = &plan_response.message().tool_calls;
-        Ok(collect_as_plan_steps(tool_calls))
+            .await
+            .map_err(planning_failed("cot", "plan"))?;
+
+        plan_from_response(
+            "cot",
+            "plan",
+            plan_response.as_ref(),
+            self.llm_client.max_tokens(),
+        )
     }
 }
 
 pub struct HTNPlanner {
     llm_client: LLMClient,
     tool_registry: Arc<Mutex<ToolRegistry>>,
+    decompose_prompt: ChatPrompt,
+    refine_prompt: ChatPrompt,
 }
 
 impl HTNPlanner {
     pub fn new(llm_client: LLMClient, tool_registry: Arc<Mutex<ToolRegistry>>) -> Self {
+        let prompts = PlannerPrompts::default();
         Self {
             llm_client,
             tool_registry,
+            decompose_prompt: prompts.htn_decompose,
+            refine_prompt: prompts.htn_refine,
         }
     }
+
+    /// Replaces the prompts asking for the subtasks, which may use `{task}` and `{tools}`.
+    pub fn with_decompose_prompt(mut self, prompt: ChatPrompt) -> Self {
+        self.decompose_prompt = prompt;
+        self
+    }
+
+    /// Replaces the prompts asking for the plan, which may use `{task}`, `{tools}` and
+    /// `{hierarchy}`.
+    pub fn with_refine_prompt(mut self, prompt: ChatPrompt) -> Self {
+        self.refine_prompt = prompt;
+        self
+    }
+
+    /// Uses the `htn_decompose` and `htn_refine` prompts of `prompts`, failing with
+    /// [`AgenticFlowError::ConfigError`] if one has a placeholder its phase has no value
+    /// for, or the first lacks `{task}` or the second `{hierarchy}`.
+    pub fn with_prompts(self, prompts: &PlannerPrompts) -> Result<Self, AgenticFlowError> {
+        prompts::check("htn_decompose", &prompts.htn_decompose, None, "task")?;
+        prompts::check(
+            "htn_refine",
+            &prompts.htn_refine,
+            Some("hierarchy"),
+            "hierarchy",
+        )?;
+        Ok(self
+            .with_decompose_prompt(prompts.htn_decompose.clone())
+            .with_refine_prompt(prompts.htn_refine.clone()))
+    }
 }
 
 #[async_trait::async_trait]
 impl Planner for HTNPlanner {
     async fn plan(&self, task: &str) -> Result<Vec<PlanStep>, AgenticFlowError> {
         // Step 1: Decompose the task into high-level subtasks
-        let decompose_messages = vec![
-            ChatMessage::system("You are an HTN planner. Decompose the high-level task into logical subtasks.".to_string()),
-            ChatMessage::user(format!("Task: {}\nDecompose this into a hierarchy of subtasks:", task)),
-        ];
+        let tools = plan_tools(self.tool_registry.lock().await.get_tools_for_planner());
+        let tool_list = describe_tools(&tools);
+        let decompose_messages = self
+            .decompose_prompt
+            .messages(&[("task", task), ("tools", &tool_list)])?;
+        // The subtasks are free text: the tools inform them but may not be called yet.
+        let decompose_options = RequestOptions::default().with_tool_choice(ToolChoice::None);
         let decompose_response = self.llm_client
-            .chat_completions(decompose_messages, vec![])
-            .await?;
+            .chat_completions_with(decompose_messages, tools.clone(), &decompose_options)
+            .await
+            .map_err(planning_failed("htn", "decompose"))?;
         let hierarchy = &decompose_response.message().content;
         
         // Step 2: Refine each subtask into primitive actions (tool calls)
-        let refine_messages = vec![
-            ChatMessage::system("Based on the task hierarchy, generate a concrete execution plan using available tools.".to_string()),
-            ChatMessage::user(format!(
-                "Task: {}\n\nTask Hierarchy:\n{}\n\nGenerate a detailed plan using tool calls that implements this hierarchy:",
-                task, hierarchy
-            )),
-        ];
-        
-        let tools = self.tool_registry.lock().await.get_tools_for_planner();
+        let refine_messages = self.refine_prompt.messages(&[
+            ("task", task),
+            ("tools", &tool_list),
+            ("hierarchy", hierarchy),
+        ])?;
+
         let plan_response = self.llm_client
             .chat_completions(refine_messages, tools)
-            .await?;
+            .await
+            .map_err(planning_failed("htn", "refine"))?;
 
-        let tool_calls = &plan_response.message().tool_calls;
-        Ok(collect_as_plan_steps(tool_calls))
+        plan_from_response(
+            "htn",
+            "refine",
+            plan_response.as_ref(),
+            self.llm_client.max_tokens(),
+        )
     }
 }
-
-#[derive(Clone)]
-pub struct MonteCarloTreeSearchPlanner {
-    llm_client: LLMClient,
-    tool_registry: Arc<Mutex<ToolRegistry>>,
-    simulations: usize,
-}
-
-impl MonteCarloTreeSearchPlanner {
-    pub fn new(
-        llm_client: LLMClient,
-        tool_registry: Arc<Mutex<ToolRegistry>>,
-        simulations: usize,
-    ) -> Self {
-        Self {
-            llm_client,
-            tool_registry,
-            simulations,
-        }
-    }
-}
-
-#[async_trait::async_trait]
-impl Planner for MonteCarloTreeSearchPlanner {
-    async fn plan(&self, task: &str) -> Result<Vec<PlanStep>, AgenticFlowError> {
-        // Initialize MCTS parameters.
-        let mut best_plan = Vec::new();
-        let mut best_score = f64::MIN;
-
-        let tools = self.tool_registry.lock().await.get_tools_for_planner();
-        let llm_client = self.llm_client.clone().with_temperature(0.9);
-        // Perform multiple simulations.
-        for _ in 0..self.simulations {
-            // Use the LLM to simulate a plan for a given task.
-            let simulation_messages = vec![
-                ChatMessage::system("Simulate a potential plan for task execution using Monte Carlo Tree Search.".to_string()),
-                ChatMessage::user(format!("Task: {}", task)),
-            ];
-
-            let simulation_response = llm_client
-                .chat_completions(simulation_messages, tools.clone())
-                .await?;
-
-            let tool_calls = &simulation_response.message().tool_calls;
-            let plan_steps = collect_as_plan_steps(tool_calls);
-
-            // Evaluate the simulated plan using a simple heuristic:
-            // Here, a shorter plan is considered more efficient.
-            let score = if plan_steps.is_empty() {
-                0.0
-            } else {
-                1.0 / plan_steps.len() as f64
-            };
-
-            // Keep the best plan according to the score.
-            if score > best_score {
-                best_score = score;
-                best_plan = plan_steps;
-            }
-        }
-
-        Ok(best_plan)
-    }
-}
\ No newline at end of file
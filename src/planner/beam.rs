@@ -0,0 +1,344 @@
+//! Beam search over partial plans: the plan grows one step at a time, and only the best
+//! partial plans at each depth are extended further.
+
+use std::sync::Arc;
+
+use futures::stream::{self, StreamExt};
+use serde::{Deserialize, Serialize};
+use serde_json::{Value, json};
+use tokio::sync::Mutex;
+
+use super::{PlanStep, Planner, describe_step, plan_from_response, plan_tools, planning_failed};
+use crate::{
+    errors::AgenticFlowError,
+    llm_client::{BATCH_CONCURRENCY, LLMClient, RequestOptions, ToolChoice},
+    model::ChatMessage,
+    prompt::{ChatPrompt, describe_tools},
+    tool_registry::ToolRegistry,
+};
+
+pub const DEFAULT_BEAM_WIDTH: usize = 3;
+pub const DEFAULT_BEAM_DEPTH: usize = 8;
+
+/// The tool the model calls instead of adding a step once the plan is complete.
+pub(super) const DONE_TOOL: &str = "done";
+
+/// Scores plans for [`BeamSearchPlanner`]; higher is better. `finished` plans are ones
+/// the model ended, the others may still grow.
+#[async_trait::async_trait]
+pub trait PlanScorer: Send + Sync {
+    async fn score(
+        &self,
+        task: &str,
+        steps: &[PlanStep],
+        finished: bool,
+    ) -> Result<f64, AgenticFlowError>;
+}
+
+/// Scores without asking the model: each step costs a point and each step that repeats an
+/// earlier one with the same params two more, so shorter plans win, as with
+/// [`MonteCarloTreeSearchPlanner`](super::MonteCarloTreeSearchPlanner). An empty plan
+/// scores lowest of all, as it does nothing for the task.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct HeuristicScorer;
+
+#[async_trait::async_trait]
+impl PlanScorer for HeuristicScorer {
+    async fn score(
+        &self,
+        _task: &str,
+        steps: &[PlanStep],
+        _finished: bool,
+    ) -> Result<f64, AgenticFlowError> {
+        if steps.is_empty() {
+            return Ok(f64::NEG_INFINITY);
+        }
+        let repeats = steps
+            .iter()
+            .enumerate()
+            .filter(|(index, step)| {
+                steps[..*index].iter().any(|earlier| {
+                    earlier.tool_name == step.tool_name && earlier.params == step.params
+                })
+            })
+            .count();
+        Ok(-(steps.len() as f64) - 2.0 * repeats as f64)
+    }
+}
+
+/// Asks the model to rate each plan from 0 to 10 for how well it achieves, or is on its
+/// way to achieving, the task. An answer without a number scores 0.
+pub struct LLMScorer {
+    llm_client: LLMClient,
+}
+
+impl LLMScorer {
+    pub fn new(llm_client: LLMClient) -> Self {
+        Self { llm_client }
+    }
+}
+
+#[async_trait::async_trait]
+impl PlanScorer for LLMScorer {
+    async fn score(
+        &self,
+        task: &str,
+        steps: &[PlanStep],
+        finished: bool,
+    ) -> Result<f64, AgenticFlowError> {
+        let state = if finished {
+            "complete plan"
+        } else {
+            "first steps of a plan"
+        };
+        let messages = vec![
+            ChatMessage::system(
+                "Rate plans from 0 to 10 for how well they achieve the task. Answer with the \
+                 number only."
+                    .to_string(),
+            ),
+            ChatMessage::user(format!(
+                "Task: {}\n\nRate these {}:\n{}",
+                task,
+                state,
+                describe_plan(steps)
+            )),
+        ];
+        let response = self
+            .llm_client
+            .chat_completions(messages, vec![])
+            .await
+            .map_err(planning_failed("beam", "score"))?;
+        let content = &response.message().content;
+        let number = content
+            .split(|c: char| !c.is_ascii_digit() && c != '.')
+            .find_map(|word| word.parse::<f64>().ok());
+        Ok(number.unwrap_or(0.0))
+    }
+}
+
+/// Which [`PlanScorer`] a [`BeamSearchPlanner`] built from a config uses, from
+/// `beam_scorer` in the `planner` section.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BeamScorer {
+    /// [`HeuristicScorer`].
+    #[default]
+    Heuristic,
+    /// [`LLMScorer`], with the planning client.
+    Llm,
+}
+
+/// Builds the plan one step at a time. At each depth the model proposes `width` next steps
+/// for each partial plan in the beam, as a single required tool call each, or calls
+/// `done` to end the plan. The extended plans are scored and the best `width` kept for the
+/// next depth. The search ends when no partial plan is left or after `depth` steps, and
+/// the best-scoring finished plan is returned, or the best partial one if none finished.
+///
+/// The proposals of all plans of a depth are requested together, as are their scores. A
+/// proposal the planner cannot use is skipped; the plan fails only if none can be used.
+pub struct BeamSearchPlanner {
+    llm_client: LLMClient,
+    tool_registry: Arc<Mutex<ToolRegistry>>,
+    width: usize,
+    depth: usize,
+    scorer: Arc<dyn PlanScorer>,
+    prompt: ChatPrompt,
+}
+
+/// A plan in the beam and its score.
+struct Candidate {
+    steps: Vec<PlanStep>,
+    score: f64,
+}
+
+impl BeamSearchPlanner {
+    pub fn new(llm_client: LLMClient, tool_registry: Arc<Mutex<ToolRegistry>>) -> Self {
+        Self {
+            llm_client,
+            tool_registry,
+            width: DEFAULT_BEAM_WIDTH,
+            depth: DEFAULT_BEAM_DEPTH,
+            scorer: Arc::new(HeuristicScorer),
+            prompt: next_step_prompt(),
+        }
+    }
+
+    /// How many partial plans are kept at each depth, and how many next steps are asked
+    /// for each of them. At least 1.
+    pub fn with_beam_width(mut self, width: usize) -> Self {
+        self.width = width.max(1);
+        self
+    }
+
+    /// The most steps a plan may have.
+    pub fn with_max_depth(mut self, depth: usize) -> Self {
+        self.depth = depth;
+        self
+    }
+
+    pub fn with_scorer(mut self, scorer: impl PlanScorer + 'static) -> Self {
+        self.scorer = Arc::new(scorer);
+        self
+    }
+
+    /// Replaces the prompts asking for the next step, which may use `{task}`, `{tools}`
+    /// and `{plan}`, the numbered steps so far.
+    pub fn with_prompt(mut self, prompt: ChatPrompt) -> Self {
+        self.prompt = prompt;
+        self
+    }
+
+    /// The plans one step longer than those in `beam`, or ended, and the last error of a
+    /// proposal that could not be used.
+    async fn expand(
+        &self,
+        task: &str,
+        tools: &[Value],
+        beam: &[Candidate],
+    ) -> Result<(Vec<(Vec<PlanStep>, bool)>, Option<AgenticFlowError>), AgenticFlowError> {
+        let tool_list = describe_tools(tools);
+        let mut conversations = Vec::with_capacity(beam.len() * self.width);
+        for candidate in beam {
+            let plan = describe_plan(&candidate.steps);
+            let messages =
+                self.prompt
+                    .messages(&[("task", task), ("tools", &tool_list), ("plan", &plan)])?;
+            conversations.extend(std::iter::repeat_n(messages, self.width));
+        }
+        // Several samples of the same plan should differ, as for MCTS simulations.
+        let llm_client = self.llm_client.clone().with_temperature(0.9);
+        let options = RequestOptions::default().with_tool_choice(ToolChoice::Required);
+        let responses = llm_client
+            .chat_completions_batch_with(conversations, tools.to_vec(), &options)
+            .await;
+
+        let mut extended: Vec<(Vec<PlanStep>, bool)> = Vec::new();
+        let mut last_error = None;
+        for (index, response) in responses.into_iter().enumerate() {
+            let response = response.map_err(planning_failed("beam", "expand"))?;
+            let proposed = match plan_from_response(
+                "beam",
+                "expand",
+                response.as_ref(),
+                llm_client.max_tokens(),
+            ) {
+                Ok(proposed) => proposed,
+                Err(error) => {
+                    last_error = Some(error);
+                    continue;
+                }
+            };
+            let mut steps = beam[index / self.width].steps.clone();
+            let finished = match proposed.into_iter().next() {
+                Some(step) if step.tool_name != DONE_TOOL => {
+                    steps.push(step);
+                    false
+                }
+                _ => true,
+            };
+            if !extended.contains(&(steps.clone(), finished)) {
+                extended.push((steps, finished));
+            }
+        }
+        Ok((extended, last_error))
+    }
+}
+
+#[async_trait::async_trait]
+impl Planner for BeamSearchPlanner {
+    async fn plan(&self, task: &str) -> Result<Vec<PlanStep>, AgenticFlowError> {
+        let mut tools = plan_tools(self.tool_registry.lock().await.get_tools_for_planner());
+        tools.push(done_tool());
+
+        let mut beam = vec![Candidate {
+            steps: Vec::new(),
+            score: 0.0,
+        }];
+        let mut finished: Vec<Candidate> = Vec::new();
+        let mut parsed_any = false;
+        let mut last_error = None;
+        for _ in 0..self.depth {
+            if beam.is_empty() {
+                break;
+            }
+            let (extended, error) = self.expand(task, &tools, &beam).await?;
+            parsed_any |= !extended.is_empty();
+            last_error = error.or(last_error);
+
+            let scoring: Vec<_> = extended
+                .iter()
+                .map(|(steps, finished)| self.scorer.score(task, steps, *finished))
+                .collect();
+            let scores: Vec<Result<f64, AgenticFlowError>> = stream::iter(scoring)
+                .buffered(BATCH_CONCURRENCY)
+                .collect()
+                .await;
+            let mut next = Vec::with_capacity(extended.len());
+            for ((steps, ended), score) in extended.into_iter().zip(scores) {
+                let candidate = Candidate {
+                    steps,
+                    score: score?,
+                };
+                if ended {
+                    finished.push(candidate);
+                } else {
+                    next.push(candidate);
+                }
+            }
+            next.sort_by(|a, b| b.score.total_cmp(&a.score));
+            next.truncate(self.width);
+            beam = next;
+        }
+
+        match last_error.filter(|_| !parsed_any) {
+            Some(error) => Err(error),
+            None => {
+                // The first of equally good plans wins, as it was proposed first.
+                let pool = if finished.is_empty() { beam } else { finished };
+                let best = pool.into_iter().reduce(|best, candidate| {
+                    if candidate.score > best.score {
+                        candidate
+                    } else {
+                        best
+                    }
+                });
+                Ok(best.map(|candidate| candidate.steps).unwrap_or_default())
+            }
+        }
+    }
+}
+
+/// The prompts asking for the step after `{plan}`, or for [`DONE_TOOL`].
+pub(super) fn next_step_prompt() -> ChatPrompt {
+    ChatPrompt::builtin(
+        "Build a plan for the task one step at a time. Call the one tool that should run \
+         next, or call done once the steps so far complete the task.",
+        "Task: {task}\n\nPlan so far:\n{plan}",
+    )
+}
+
+/// The definition of [`DONE_TOOL`], offered with the tools of the plan.
+pub(super) fn done_tool() -> Value {
+    json!({
+        "type": "function",
+        "function": {
+            "name": DONE_TOOL,
+            "description": "Ends the plan; call it once the steps so far complete the task.",
+            "parameters": {"type": "object", "properties": {}}
+        }
+    })
+}
+
+/// `steps` as the numbered plan of a prompt.
+pub(super) fn describe_plan(steps: &[PlanStep]) -> String {
+    if steps.is_empty() {
+        return "(no steps yet)".to_string();
+    }
+    steps
+        .iter()
+        .enumerate()
+        .map(|(index, step)| describe_step(index, step))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
@@ -0,0 +1,85 @@
+//! The order in which the steps of a plan may run, from their
+//! [`depends_on`](super::PlanStep::depends_on).
+
+use std::collections::HashMap;
+
+use super::PlanStep;
+use crate::errors::AgenticFlowError;
+
+/// The dependencies of the steps of a plan, by position, and an order that runs each
+/// step after the steps it depends on.
+pub(crate) struct StepGraph {
+    /// Plan order where the dependencies allow it.
+    pub(crate) order: Vec<usize>,
+    pub(crate) dependencies: Vec<Vec<usize>>,
+}
+
+impl StepGraph {
+    /// Fails with [`AgenticFlowError::PlanningError`] when ids are used twice, a step
+    /// depends on an id no step has, or steps depend on each other in a cycle.
+    pub(crate) fn new(steps: &[PlanStep]) -> Result<Self, AgenticFlowError> {
+        let mut positions = HashMap::new();
+        for (index, step) in steps.iter().enumerate() {
+            let Some(id) = &step.id else {
+                continue;
+            };
+            if positions.insert(id.as_str(), index).is_some() {
+                return Err(AgenticFlowError::PlanningError(format!(
+                    "More than one step has the id '{}'",
+                    id
+                )));
+            }
+        }
+
+        let dependencies = steps
+            .iter()
+            .enumerate()
+            .map(|(index, step)| {
+                step.depends_on
+                    .iter()
+                    .map(|id| {
+                        positions.get(id.as_str()).copied().ok_or_else(|| {
+                            AgenticFlowError::PlanningError(format!(
+                                "Step {} ({}) depends on '{}', which no step has as its id",
+                                index + 1,
+                                step.tool_name,
+                                id
+                            ))
+                        })
+                    })
+                    .collect::<Result<Vec<_>, _>>()
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let mut placed = vec![false; steps.len()];
+        let mut order = Vec::with_capacity(steps.len());
+        while order.len() < steps.len() {
+            let next = (0..steps.len()).find(|index| {
+                !placed[*index]
+                    && dependencies[*index]
+                        .iter()
+                        .all(|dependency| placed[*dependency])
+            });
+            let Some(next) = next else {
+                let waiting: Vec<String> = (0..steps.len())
+                    .filter(|index| !placed[*index])
+                    .map(|index| match &steps[index].id {
+                        Some(id) => format!("'{}'", id),
+                        None => format!("{} ({})", index + 1, steps[index].tool_name),
+                    })
+                    .collect();
+                return Err(AgenticFlowError::PlanningError(format!(
+                    "Steps {} cannot run: their dependencies form a cycle",
+                    waiting.join(", ")
+                )));
+            };
+            placed[next] = true;
+            order.push(next);
+        }
+
+        Ok(Self {
+            order,
+            dependencies,
+        })
+    }
+}
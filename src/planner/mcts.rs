@@ -0,0 +1,801 @@
+//! Monte Carlo tree search over partial plans, and the plan sampling it replaced.
+
+use std::sync::Arc;
+
+use futures::stream::{self, StreamExt};
+use serde::{Deserialize, Serialize};
+use tokio::sync::Mutex;
+
+use super::{
+    PlanStep, Planner,
+    beam::{DONE_TOOL, describe_plan, done_tool, next_step_prompt},
+    plan_from_response, plan_tools, planning_failed,
+};
+use crate::{
+    errors::AgenticFlowError,
+    llm_client::{BATCH_CONCURRENCY, LLMClient, RequestOptions, ToolChoice},
+    model::ChatMessage,
+    prompt::{ChatPrompt, describe_tools},
+    tool_registry::ToolRegistry,
+};
+
+pub const DEFAULT_MCTS_DEPTH: usize = 8;
+pub const DEFAULT_MCTS_BRANCHING: usize = 3;
+/// The exploration constant of UCB1, √2.
+pub const DEFAULT_MCTS_EXPLORATION: f64 = std::f64::consts::SQRT_2;
+
+/// How a [`MonteCarloTreeSearchPlanner`] searches, from `mcts_strategy` in the `planner`
+/// section.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MctsStrategy {
+    /// Grows a tree of partial plans, see [`MonteCarloTreeSearchPlanner`].
+    #[default]
+    Tree,
+    /// Samples whole plans independently and keeps the shortest, as earlier versions did.
+    Sampling,
+}
+
+/// Proposes the steps a [`MonteCarloTreeSearchPlanner`] grows its tree with. The planner
+/// uses [`LLMStepGenerator`] unless given another, such as a fixed one for tests.
+#[async_trait::async_trait]
+pub trait StepGenerator: Send + Sync {
+    /// Candidate next steps after `steps`, where `None` ends the plan. No candidates end
+    /// it too.
+    async fn next_steps(
+        &self,
+        task: &str,
+        steps: &[PlanStep],
+    ) -> Result<Vec<Option<PlanStep>>, AgenticFlowError>;
+
+    /// `steps` followed by the rest of a plan, of at most `max_steps` steps in all. By
+    /// default this adds the first candidate of [`next_steps`](Self::next_steps) until it
+    /// ends the plan.
+    async fn rollout(
+        &self,
+        task: &str,
+        steps: &[PlanStep],
+        max_steps: usize,
+    ) -> Result<Vec<PlanStep>, AgenticFlowError> {
+        let mut plan = steps.to_vec();
+        while plan.len() < max_steps {
+            match self
+                .next_steps(task, &plan)
+                .await?
+                .into_iter()
+                .next()
+                .flatten()
+            {
+                Some(step) => plan.push(step),
+                None => break,
+            }
+        }
+        Ok(plan)
+    }
+}
+
+/// Asks the model for `branching` next steps at once, each a single required tool call or
+/// a call to `done`, and for the rest of the plan in one call when rolling out. Proposals
+/// it cannot use are dropped; it fails only when none can be used.
+pub struct LLMStepGenerator {
+    llm_client: LLMClient,
+    tool_registry: Arc<Mutex<ToolRegistry>>,
+    branching: usize,
+    prompt: ChatPrompt,
+}
+
+impl LLMStepGenerator {
+    pub fn new(llm_client: LLMClient, tool_registry: Arc<Mutex<ToolRegistry>>) -> Self {
+        Self {
+            llm_client,
+            tool_registry,
+            branching: DEFAULT_MCTS_BRANCHING,
+            prompt: next_step_prompt(),
+        }
+    }
+
+    /// How many next steps are asked for at each expanded node. At least 1.
+    pub fn with_branching(mut self, branching: usize) -> Self {
+        self.branching = branching.max(1);
+        self
+    }
+
+    /// Replaces the prompts asking for the next step, which may use `{task}`, `{tools}`
+    /// and `{plan}`, the numbered steps so far.
+    pub fn with_prompt(mut self, prompt: ChatPrompt) -> Self {
+        self.prompt = prompt;
+        self
+    }
+
+    async fn tools(&self) -> Vec<serde_json::Value> {
+        let mut tools = plan_tools(self.tool_registry.lock().await.get_tools_for_planner());
+        tools.push(done_tool());
+        tools
+    }
+}
+
+#[async_trait::async_trait]
+impl StepGenerator for LLMStepGenerator {
+    async fn next_steps(
+        &self,
+        task: &str,
+        steps: &[PlanStep],
+    ) -> Result<Vec<Option<PlanStep>>, AgenticFlowError> {
+        let tools = self.tools().await;
+        let plan = describe_plan(steps);
+        let messages = self.prompt.messages(&[
+            ("task", task),
+            ("tools", &describe_tools(&tools)),
+            ("plan", &plan),
+        ])?;
+        // Several samples of the same plan should differ.
+        let llm_client = self.llm_client.clone().with_temperature(0.9);
+        let options = RequestOptions::default().with_tool_choice(ToolChoice::Required);
+        let responses = llm_client
+            .chat_completions_batch_with(vec![messages; self.branching], tools, &options)
+            .await;
+
+        let mut proposals = Vec::new();
+        let mut last_error = None;
+        for response in responses {
+            let response = response.map_err(planning_failed("mcts", "expand"))?;
+            match plan_from_response("mcts", "expand", response.as_ref(), llm_client.max_tokens()) {
+                Ok(proposed) => {
+                    let next = proposed
+                        .into_iter()
+                        .next()
+                        .filter(|step| step.tool_name != DONE_TOOL);
+                    if !proposals.contains(&next) {
+                        proposals.push(next);
+                    }
+                }
+                Err(error) => last_error = Some(error),
+            }
+        }
+        match last_error.filter(|_| proposals.is_empty()) {
+            Some(error) => Err(error),
+            None => Ok(proposals),
+        }
+    }
+
+    async fn rollout(
+        &self,
+        task: &str,
+        steps: &[PlanStep],
+        max_steps: usize,
+    ) -> Result<Vec<PlanStep>, AgenticFlowError> {
+        let messages = vec![
+            ChatMessage::system(
+                "Complete the plan for the task: call, in order, each tool still needed after \
+                 the steps so far, then done."
+                    .to_string(),
+            ),
+            ChatMessage::user(format!(
+                "Task: {}\n\nPlan so far:\n{}",
+                task,
+                describe_plan(steps)
+            )),
+        ];
+        let options = RequestOptions::default().with_tool_choice(ToolChoice::Required);
+        let response = self
+            .llm_client
+            .chat_completions_with(messages, self.tools().await, &options)
+            .await
+            .map_err(planning_failed("mcts", "rollout"))?;
+        let rest = plan_from_response(
+            "mcts",
+            "rollout",
+            response.as_ref(),
+            self.llm_client.max_tokens(),
+        )?;
+
+        let mut plan = steps.to_vec();
+        plan.extend(
+            rest.into_iter()
+                .take_while(|step| step.tool_name != DONE_TOOL),
+        );
+        plan.truncate(max_steps.max(steps.len()));
+        Ok(plan)
+    }
+}
+
+/// Scores the complete plans of a [`MonteCarloTreeSearchPlanner`], from 0 for a useless
+/// plan to 1 for one that fully achieves the task.
+#[async_trait::async_trait]
+pub trait PlanEvaluator: Send + Sync {
+    async fn score(&self, task: &str, plan: &[PlanStep]) -> Result<f64, AgenticFlowError>;
+}
+
+/// The default evaluator: 1 divided by the number of steps, and 0 for an empty plan. It
+/// prefers the shortest plan whether or not it achieves the task.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct PlanLengthEvaluator;
+
+#[async_trait::async_trait]
+impl PlanEvaluator for PlanLengthEvaluator {
+    async fn score(&self, _task: &str, plan: &[PlanStep]) -> Result<f64, AgenticFlowError> {
+        Ok(match plan.len() {
+            0 => 0.0,
+            len => 1.0 / len as f64,
+        })
+    }
+}
+
+/// Asks the model to rate how well a plan covers the task, how plausible its parameters
+/// are and how little of it is redundant, as one score from 0 to 1. The score is read
+/// from the last line with a number, as a fraction such as `0.8`, `8/10` or `80%`; a
+/// bare number above 1 is read as out of 10, or of 100 above 10. An answer without a
+/// number fails with [`AgenticFlowError::PlanningError`].
+pub struct LLMJudgeEvaluator {
+    llm_client: LLMClient,
+}
+
+impl LLMJudgeEvaluator {
+    pub fn new(llm_client: LLMClient) -> Self {
+        Self { llm_client }
+    }
+}
+
+#[async_trait::async_trait]
+impl PlanEvaluator for LLMJudgeEvaluator {
+    async fn score(&self, task: &str, plan: &[PlanStep]) -> Result<f64, AgenticFlowError> {
+        let messages = vec![
+            ChatMessage::system(
+                "Judge whether a plan achieves a task. Consider whether it covers everything \
+                 the task asks for, whether its parameters are plausible, and whether any \
+                 steps are redundant. End with one overall score from 0 to 1, such as 0.7, \
+                 alone on the last line."
+                    .to_string(),
+            ),
+            ChatMessage::user(format!("Task: {}\n\nPlan:\n{}", task, describe_plan(plan))),
+        ];
+        let response = self
+            .llm_client
+            .chat_completions(messages, vec![])
+            .await
+            .map_err(planning_failed("mcts", "evaluate"))?;
+        let content = &response.message().content;
+        content.lines().rev().find_map(read_score).ok_or_else(|| {
+            AgenticFlowError::PlanningError(format!(
+                "The plan judge gave no score: {}",
+                content.trim()
+            ))
+        })
+    }
+}
+
+/// The first number of `line` as a score from 0 to 1, see [`LLMJudgeEvaluator`].
+fn read_score(line: &str) -> Option<f64> {
+    let start = line.find(|c: char| c.is_ascii_digit())?;
+    let number_end = |text: &str| {
+        text.find(|c: char| !c.is_ascii_digit() && c != '.')
+            .unwrap_or(text.len())
+    };
+    let end = start + number_end(&line[start..]);
+    let value: f64 = line[start..end].trim_end_matches('.').parse().ok()?;
+
+    let rest = line[end..].trim_start();
+    let score = if let Some(denominator) = rest.strip_prefix('/') {
+        let denominator = denominator.trim_start();
+        let denominator: f64 = denominator[..number_end(denominator)].parse().ok()?;
+        value / denominator
+    } else if rest.starts_with('%') || value > 10.0 {
+        value / 100.0
+    } else if value > 1.0 {
+        value / 10.0
+    } else {
+        value
+    };
+    score.is_finite().then(|| score.clamp(0.0, 1.0))
+}
+
+/// Plans by Monte Carlo tree search over partial plans. Each of the `simulations`
+/// iterations selects a node of the tree by UCB1, expands it with the candidate next
+/// steps of a [`StepGenerator`], rolls the first new step out to a complete plan, scores
+/// that plan with a [`PlanEvaluator`], and adds the score to every node on the way back to
+/// the root. The plan returned follows the most visited steps from the root, completed by
+/// the best rollout through them.
+///
+/// Plans have at most `max_depth` steps. Steps come from an [`LLMStepGenerator`] and are
+/// scored by [`PlanLengthEvaluator`] unless replaced. An iteration whose expansion,
+/// rollout or evaluation fails is skipped; the plan fails only if every iteration does.
+///
+/// Up to `max_concurrency` iterations run at once, each from a different node: those still
+/// running count as visits without a reward when the next is selected. Their results are
+/// added in the order they were selected, so the search is as deterministic as its
+/// generator and evaluator.
+///
+/// [`MctsStrategy::Sampling`] instead samples `simulations` whole plans, `max_concurrency`
+/// at a time, and keeps the best scored, the earliest of equally good ones.
+#[derive(Clone)]
+pub struct MonteCarloTreeSearchPlanner {
+    llm_client: LLMClient,
+    tool_registry: Arc<Mutex<ToolRegistry>>,
+    simulations: usize,
+    strategy: MctsStrategy,
+    max_depth: usize,
+    exploration: f64,
+    max_concurrency: usize,
+    generator: Arc<dyn StepGenerator>,
+    evaluator: Arc<dyn PlanEvaluator>,
+}
+
+impl MonteCarloTreeSearchPlanner {
+    pub fn new(
+        llm_client: LLMClient,
+        tool_registry: Arc<Mutex<ToolRegistry>>,
+        simulations: usize,
+    ) -> Self {
+        let generator = LLMStepGenerator::new(llm_client.clone(), tool_registry.clone());
+        Self {
+            llm_client,
+            tool_registry,
+            simulations,
+            strategy: MctsStrategy::default(),
+            max_depth: DEFAULT_MCTS_DEPTH,
+            exploration: DEFAULT_MCTS_EXPLORATION,
+            max_concurrency: BATCH_CONCURRENCY,
+            generator: Arc::new(generator),
+            evaluator: Arc::new(PlanLengthEvaluator),
+        }
+    }
+
+    pub fn with_strategy(mut self, strategy: MctsStrategy) -> Self {
+        self.strategy = strategy;
+        self
+    }
+
+    /// The most steps a plan of the tree may have.
+    pub fn with_max_depth(mut self, max_depth: usize) -> Self {
+        self.max_depth = max_depth;
+        self
+    }
+
+    /// The weight UCB1 gives to steps tried less often, against those that scored well.
+    pub fn with_exploration(mut self, exploration: f64) -> Self {
+        self.exploration = exploration;
+        self
+    }
+
+    /// How many simulations run at once, by default [`BATCH_CONCURRENCY`]. At least 1;
+    /// with 1 the tree search runs its iterations one after another.
+    pub fn with_max_concurrency(mut self, max_concurrency: usize) -> Self {
+        self.max_concurrency = max_concurrency.max(1);
+        self
+    }
+
+    pub fn with_step_generator(mut self, generator: impl StepGenerator + 'static) -> Self {
+        self.generator = Arc::new(generator);
+        self
+    }
+
+    /// Scores the complete plans, in place of [`PlanLengthEvaluator`]; scores between 0
+    /// and 1 suit the default exploration.
+    pub fn with_evaluator(mut self, evaluator: Box<dyn PlanEvaluator>) -> Self {
+        self.evaluator = Arc::from(evaluator);
+        self
+    }
+
+    async fn search(&self, task: &str) -> Result<Vec<PlanStep>, AgenticFlowError> {
+        let mut tree = SearchTree::new();
+        let mut errors = Vec::new();
+        let mut simulated = 0;
+        while simulated < self.simulations {
+            // The simulations of a round run together, each from a different node.
+            let round = self.max_concurrency.min(self.simulations - simulated);
+            let mut selected: Vec<usize> = Vec::with_capacity(round);
+            while selected.len() < round {
+                let node = tree.select(self.exploration);
+                if selected.contains(&node) {
+                    break;
+                }
+                tree.add_pending(node);
+                selected.push(node);
+            }
+            simulated += selected.len();
+
+            let mut simulations = Vec::with_capacity(selected.len());
+            for (index, node) in selected.into_iter().enumerate() {
+                let work = self.prepare(&mut tree, node);
+                simulations.push(async move { (index, self.simulate(task, work).await) });
+            }
+            let mut outcomes: Vec<(usize, Outcome)> = stream::iter(simulations)
+                .buffer_unordered(self.max_concurrency)
+                .collect()
+                .await;
+            // Applied in the order they were selected, so the search does not depend on
+            // which request finished first.
+            outcomes.sort_by_key(|(index, _)| *index);
+            tree.clear_pending();
+            for (_, outcome) in outcomes {
+                if let Err(error) = tree.apply(outcome) {
+                    errors.push(error);
+                }
+            }
+        }
+
+        match tree.best_plan() {
+            Some(plan) => Ok(plan),
+            None if errors.is_empty() => Ok(Vec::new()),
+            None => Err(simulations_failed(errors)),
+        }
+    }
+
+    /// What a simulation from `node` needs of the tree. A node at the depth limit ends
+    /// the plan there.
+    fn prepare(&self, tree: &mut SearchTree, node: usize) -> Work {
+        let current = &mut tree.nodes[node];
+        if !current.expanded && !current.terminal && current.depth >= self.max_depth {
+            current.expanded = true;
+            current.terminal = true;
+        }
+        let current = &tree.nodes[node];
+        Work {
+            node,
+            path: tree.path(node),
+            expand: !current.expanded && !current.terminal,
+            // A finished plan scores the same every time.
+            finished: current.best.clone().filter(|_| current.terminal),
+        }
+    }
+
+    /// Expands the node of `work` if it can be, then rolls out from it or its first new
+    /// child and scores the plan.
+    async fn simulate(&self, task: &str, work: Work) -> Outcome {
+        let Work {
+            node,
+            mut path,
+            expand,
+            finished,
+        } = work;
+        let mut proposals = None;
+        let mut terminal = !expand;
+        if expand {
+            match self.generator.next_steps(task, &path).await {
+                Ok(steps) => {
+                    match steps.first() {
+                        Some(Some(step)) => path.push(step.clone()),
+                        _ => terminal = true,
+                    }
+                    proposals = Some(steps);
+                }
+                Err(error) => {
+                    return Outcome {
+                        node,
+                        proposals,
+                        result: Err(error),
+                    };
+                }
+            }
+        }
+
+        let result = match finished {
+            Some(finished) => Ok(finished),
+            None if terminal => self.score(task, path).await,
+            None => match self.generator.rollout(task, &path, self.max_depth).await {
+                Ok(plan) => self.score(task, plan).await,
+                Err(error) => Err(error),
+            },
+        };
+        Outcome {
+            node,
+            proposals,
+            result,
+        }
+    }
+
+    /// The evaluator's score of `plan`, with the plan.
+    async fn score(&self, task: &str, plan: Vec<PlanStep>) -> Scored {
+        Ok((self.evaluator.score(task, &plan).await?, plan))
+    }
+
+    async fn sample(&self, task: &str) -> Result<Vec<PlanStep>, AgenticFlowError> {
+        let tools = plan_tools(self.tool_registry.lock().await.get_tools_for_planner());
+        let llm_client = self.llm_client.clone().with_temperature(0.9);
+        // Perform multiple simulations, which are independent of each other.
+        let simulation_messages = vec![
+            ChatMessage::system(
+                "Simulate a potential plan for task execution using Monte Carlo Tree Search."
+                    .to_string(),
+            ),
+            ChatMessage::user(format!("Task: {}", task)),
+        ];
+        let mut simulations = Vec::with_capacity(self.simulations);
+        for index in 0..self.simulations {
+            let (llm_client, messages, tools) = (&llm_client, &simulation_messages, &tools);
+            simulations.push(async move {
+                let simulate = async {
+                    let response = llm_client
+                        .chat_completions(messages.clone(), tools.clone())
+                        .await
+                        .map_err(planning_failed("mcts", "simulate"))?;
+                    let plan = plan_from_response(
+                        "mcts",
+                        "simulate",
+                        response.as_ref(),
+                        llm_client.max_tokens(),
+                    )?;
+                    // The evaluator may ask the model too.
+                    self.score(task, plan).await
+                };
+                (index, simulate.await)
+            });
+        }
+        let mut results: Vec<(usize, Scored)> = stream::iter(simulations)
+            .buffer_unordered(self.max_concurrency)
+            .collect()
+            .await;
+        results.sort_by_key(|(index, _)| *index);
+
+        // A simulation that failed is skipped, the others may still succeed. Of equally
+        // good plans the earliest simulation's wins.
+        let mut best: Option<(f64, Vec<PlanStep>)> = None;
+        let mut errors = Vec::new();
+        for (_, result) in results {
+            match result {
+                Ok((score, plan)) => {
+                    if best.as_ref().is_none_or(|(best, _)| score > *best) {
+                        best = Some((score, plan));
+                    }
+                }
+                Err(error) => errors.push(error),
+            }
+        }
+
+        match best {
+            Some((_, plan)) => Ok(plan),
+            None if errors.is_empty() => Ok(Vec::new()),
+            None => Err(simulations_failed(errors)),
+        }
+    }
+}
+
+/// The error of a search in which every simulation failed: an
+/// [`AgenticFlowError::PlanningFailed`] in the phase of the last failure, with its
+/// diagnostics, whose source is a [`AgenticFlowError::PlanningError`] listing every
+/// failure. The earlier simulations count as retries. A lone failure, or a spent budget,
+/// is returned as it is.
+fn simulations_failed(mut errors: Vec<AgenticFlowError>) -> AgenticFlowError {
+    let retries = errors.len().saturating_sub(1);
+    let summary = errors
+        .iter()
+        .enumerate()
+        .map(|(index, error)| format!("simulation {}: {}", index + 1, error))
+        .collect::<Vec<_>>()
+        .join("; ");
+    let Some(last) = errors.pop() else {
+        return AgenticFlowError::PlanningError("No simulation ran".to_string());
+    };
+    if retries == 0 || last.budget_overrun().is_some() {
+        return last;
+    }
+
+    let kind = last.kind();
+    let (phase, mut diagnostics) = match last {
+        AgenticFlowError::PlanningFailed {
+            phase, diagnostics, ..
+        } => (phase, diagnostics),
+        _ => ("simulate", Box::default()),
+    };
+    diagnostics.retries = retries;
+    let source = AgenticFlowError::PlanningError(format!(
+        "All {} simulations failed: {}",
+        retries + 1,
+        summary
+    ));
+    AgenticFlowError::PlanningFailed {
+        planner: "mcts".to_string(),
+        phase,
+        // Retried, or not, like the last failure.
+        source: Box::new(source.with_kind(kind)),
+        diagnostics,
+    }
+}
+
+/// What a simulation of the tree search starts from.
+struct Work {
+    node: usize,
+    path: Vec<PlanStep>,
+    /// `node` is to be expanded first.
+    expand: bool,
+    /// The score and plan of `node` if it ends a plan that was scored already.
+    finished: Option<(f64, Vec<PlanStep>)>,
+}
+
+/// What a simulation found, applied to the tree once its round ends.
+struct Outcome {
+    node: usize,
+    /// The candidate next steps after `node`, if it was expanded.
+    proposals: Option<Vec<Option<PlanStep>>>,
+    result: Scored,
+}
+
+/// The score of a plan rolled out, and the plan.
+type Scored = Result<(f64, Vec<PlanStep>), AgenticFlowError>;
+
+#[async_trait::async_trait]
+impl Planner for MonteCarloTreeSearchPlanner {
+    async fn plan(&self, task: &str) -> Result<Vec<PlanStep>, AgenticFlowError> {
+        match self.strategy {
+            MctsStrategy::Tree => self.search(task).await,
+            MctsStrategy::Sampling => self.sample(task).await,
+        }
+    }
+}
+
+/// A step of a partial plan in the tree, with the rewards of the rollouts through it.
+struct Node {
+    /// `None` for the root and for the end of a plan.
+    step: Option<PlanStep>,
+    parent: Option<usize>,
+    children: Vec<usize>,
+    /// Steps from the root to this node.
+    depth: usize,
+    expanded: bool,
+    /// The plan ends here.
+    terminal: bool,
+    visits: u32,
+    /// Simulations through this node that are still running, counted as visits without a
+    /// reward so that the others of their round try other nodes.
+    pending: u32,
+    total_reward: f64,
+    /// The best rollout through this node and its reward.
+    best: Option<(f64, Vec<PlanStep>)>,
+}
+
+impl Node {
+    fn new(step: Option<PlanStep>, parent: Option<usize>, depth: usize) -> Self {
+        Self {
+            terminal: parent.is_some() && step.is_none(),
+            step,
+            parent,
+            children: Vec::new(),
+            depth,
+            expanded: false,
+            visits: 0,
+            pending: 0,
+            total_reward: 0.0,
+            best: None,
+        }
+    }
+}
+
+/// The nodes of the search, with the root first.
+struct SearchTree {
+    nodes: Vec<Node>,
+}
+
+impl SearchTree {
+    fn new() -> Self {
+        Self {
+            nodes: vec![Node::new(None, None, 0)],
+        }
+    }
+
+    /// Follows the child with the highest UCB1 value from the root, trying unvisited
+    /// children first, to a node that is not expanded or ends the plan.
+    fn select(&self, exploration: f64) -> usize {
+        let mut node = 0;
+        loop {
+            let current = &self.nodes[node];
+            if !current.expanded || current.terminal || current.children.is_empty() {
+                return node;
+            }
+            let parent_visits = f64::from((current.visits + current.pending).max(1)).ln();
+            let ucb1 = |child: &Node| match child.visits + child.pending {
+                0 => f64::INFINITY,
+                visits => {
+                    let visits = f64::from(visits);
+                    child.total_reward / visits + exploration * (parent_visits / visits).sqrt()
+                }
+            };
+            let mut best = current.children[0];
+            for &child in &current.children[1..] {
+                if ucb1(&self.nodes[child]) > ucb1(&self.nodes[best]) {
+                    best = child;
+                }
+            }
+            node = best;
+        }
+    }
+
+    /// Adds the distinct `proposals` as children of `node`; without any the plan ends there.
+    fn expand(&mut self, node: usize, proposals: Vec<Option<PlanStep>>) {
+        let depth = self.nodes[node].depth + 1;
+        for step in proposals {
+            let known = self.nodes[node]
+                .children
+                .iter()
+                .any(|&child| self.nodes[child].step == step);
+            if !known {
+                self.nodes.push(Node::new(step, Some(node), depth));
+                let child = self.nodes.len() - 1;
+                self.nodes[node].children.push(child);
+            }
+        }
+        self.nodes[node].expanded = true;
+        self.nodes[node].terminal = self.nodes[node].children.is_empty();
+    }
+
+    /// Counts a running simulation from `node` on it and each node above it.
+    fn add_pending(&mut self, mut node: usize) {
+        loop {
+            self.nodes[node].pending += 1;
+            match self.nodes[node].parent {
+                Some(parent) => node = parent,
+                None => return,
+            }
+        }
+    }
+
+    fn clear_pending(&mut self) {
+        for node in &mut self.nodes {
+            node.pending = 0;
+        }
+    }
+
+    /// Adds the children `outcome` proposed, and backpropagates its reward from the first
+    /// of them, or from its node if there are none.
+    fn apply(&mut self, outcome: Outcome) -> Result<(), AgenticFlowError> {
+        let mut node = outcome.node;
+        if let Some(proposals) = outcome.proposals {
+            let first = proposals.first().cloned();
+            self.expand(node, proposals);
+            if let Some(first) = first {
+                let children = &self.nodes[node].children;
+                if let Some(&child) = children.iter().find(|&&c| self.nodes[c].step == first) {
+                    node = child;
+                }
+            }
+        }
+        let (reward, plan) = outcome.result?;
+        self.backpropagate(node, reward, plan);
+        Ok(())
+    }
+
+    /// The steps from the root to `node`.
+    fn path(&self, mut node: usize) -> Vec<PlanStep> {
+        let mut steps = Vec::with_capacity(self.nodes[node].depth);
+        while let Some(parent) = self.nodes[node].parent {
+            steps.extend(self.nodes[node].step.clone());
+            node = parent;
+        }
+        steps.reverse();
+        steps
+    }
+
+    /// Adds `reward` to `node` and each node above it, and keeps `plan` where it is the
+    /// best so far.
+    fn backpropagate(&mut self, mut node: usize, reward: f64, plan: Vec<PlanStep>) {
+        loop {
+            let current = &mut self.nodes[node];
+            current.visits += 1;
+            current.total_reward += reward;
+            if current.best.as_ref().is_none_or(|(best, _)| reward > *best) {
+                current.best = Some((reward, plan.clone()));
+            }
+            match current.parent {
+                Some(parent) => node = parent,
+                None => return,
+            }
+        }
+    }
+
+    /// The best rollout through the most visited path, or `None` before any rollout.
+    fn best_plan(&self) -> Option<Vec<PlanStep>> {
+        let mut node = 0;
+        loop {
+            let mut most_visited: Option<usize> = None;
+            for &child in &self.nodes[node].children {
+                let visits = self.nodes[child].visits;
+                if visits > most_visited.map_or(0, |best| self.nodes[best].visits) {
+                    most_visited = Some(child);
+                }
+            }
+            match most_visited {
+                Some(child) => node = child,
+                None => return self.nodes[node].best.as_ref().map(|(_, plan)| plan.clone()),
+            }
+        }
+    }
+}
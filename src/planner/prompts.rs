@@ -0,0 +1,128 @@
+//! The prompts of the built-in planners, replaced together with `with_prompts`.
+
+use crate::{errors::AgenticFlowError, prompt::ChatPrompt};
+
+/// The system and user prompts of [`MultiStepPlanner`](super::MultiStepPlanner),
+/// [`ChainOfThoughtPlanner`](super::ChainOfThoughtPlanner) and
+/// [`HTNPlanner`](super::HTNPlanner), one field per planner phase. The default is the
+/// built-in wording.
+///
+/// Each planner's `with_prompts` takes the fields it uses and checks their placeholders
+/// then, rather than when planning: a prompt may only use the values its phase has, and
+/// must use the one the phase exists to pass on, such as `{hierarchy}` for
+/// [`htn_refine`](Self::htn_refine).
+///
+/// ```rust
+/// use agentic_flow_lib::{
+///     llm_client::{LLMClient, MockLLMProvider},
+///     planner::{MultiStepPlanner, PlannerPrompts},
+///     prompt::{ChatPrompt, PromptTemplate},
+///     tool_registry::ToolRegistry,
+/// };
+/// use std::sync::Arc;
+/// use tokio::sync::Mutex;
+///
+/// let client = LLMClient::from(MockLLMProvider::new());
+/// let registry = Arc::new(Mutex::new(ToolRegistry::new()));
+/// let prompts = PlannerPrompts {
+///     multistep: ChatPrompt::new(
+///         PromptTemplate::new("Planifie la tâche avec ces outils :\n{tools}").unwrap(),
+///         PromptTemplate::new("Tâche : {task}").unwrap(),
+///     ),
+///     ..PlannerPrompts::default()
+/// };
+/// let planner = MultiStepPlanner::new(client.clone(), registry.clone())
+///     .with_prompts(&prompts)
+///     .unwrap();
+///
+/// // A multi-step plan has no hierarchy to fill in.
+/// let prompts = PlannerPrompts {
+///     multistep: ChatPrompt::new(
+///         PromptTemplate::new("Plan it.").unwrap(),
+///         PromptTemplate::new("{task} given {hierarchy}").unwrap(),
+///     ),
+///     ..PlannerPrompts::default()
+/// };
+/// assert!(MultiStepPlanner::new(client, registry).with_prompts(&prompts).is_err());
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct PlannerPrompts {
+    /// The plan of [`MultiStepPlanner`](super::MultiStepPlanner), with `{task}` and
+    /// `{tools}`.
+    pub multistep: ChatPrompt,
+    /// The reasoning of [`ChainOfThoughtPlanner`](super::ChainOfThoughtPlanner), with
+    /// `{task}` and `{tools}`. The answer is cut at a line starting with `Plan:`.
+    pub chain_of_thought: ChatPrompt,
+    /// The plan of [`ChainOfThoughtPlanner`](super::ChainOfThoughtPlanner), with `{task}`,
+    /// `{tools}` and `{chain_of_thought}`.
+    pub chain_of_thought_plan: ChatPrompt,
+    /// The subtasks of [`HTNPlanner`](super::HTNPlanner), with `{task}` and `{tools}`.
+    pub htn_decompose: ChatPrompt,
+    /// The plan of [`HTNPlanner`](super::HTNPlanner), with `{task}`, `{tools}` and
+    /// `{hierarchy}`.
+    pub htn_refine: ChatPrompt,
+}
+
+impl Default for PlannerPrompts {
+    fn default() -> Self {
+        Self {
+            multistep: ChatPrompt::builtin(
+                "Analyze the task and create a multi-step plan.",
+                "{task}",
+            ),
+            chain_of_thought: ChatPrompt::builtin(
+                "Provide a detailed chain-of-thought analysis before forming a plan.",
+                "Task: {task}\nChain-of-Thought:",
+            ),
+            chain_of_thought_plan: ChatPrompt::builtin(
+                "Generate a multi-step plan using the provided chain-of-thought.",
+                "Based on the following chain-of-thought, generate a multi-step plan with tool \
+                 calls in JSON format.\n\nChain-of-Thought:\n{chain_of_thought}\n\nPlan:",
+            ),
+            htn_decompose: ChatPrompt::builtin(
+                "You are an HTN planner. Decompose the high-level task into logical subtasks.",
+                "Task: {task}\nDecompose this into a hierarchy of subtasks:",
+            ),
+            htn_refine: ChatPrompt::builtin(
+                "Based on the task hierarchy, generate a concrete execution plan using \
+                 available tools.",
+                "Task: {task}\n\nTask Hierarchy:\n{hierarchy}\n\nGenerate a detailed plan using \
+                 tool calls that implements this hierarchy:",
+            ),
+        }
+    }
+}
+
+/// Fails with [`AgenticFlowError::ConfigError`] if `prompt`, the field `field` of
+/// [`PlannerPrompts`], uses a placeholder other than `{task}`, `{tools}` and `extra`, or
+/// does not use `required`.
+pub(super) fn check(
+    field: &str,
+    prompt: &ChatPrompt,
+    extra: Option<&str>,
+    required: &str,
+) -> Result<(), AgenticFlowError> {
+    let allowed: Vec<&str> = ["task", "tools"].into_iter().chain(extra).collect();
+    let placeholders: Vec<&str> = prompt
+        .system
+        .placeholders()
+        .into_iter()
+        .chain(prompt.user.placeholders())
+        .collect();
+    if let Some(unknown) = placeholders.iter().find(|name| !allowed.contains(name)) {
+        let allowed: Vec<String> = allowed.iter().map(|name| format!("{{{}}}", name)).collect();
+        return Err(AgenticFlowError::ConfigError(format!(
+            "prompt '{}' uses {{{}}}, which the planner has no value for; it may use {}",
+            field,
+            unknown,
+            allowed.join(", ")
+        )));
+    }
+    if !placeholders.contains(&required) {
+        return Err(AgenticFlowError::ConfigError(format!(
+            "prompt '{}' must use {{{}}}",
+            field, required
+        )));
+    }
+    Ok(())
+}
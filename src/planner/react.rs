@@ -0,0 +1,127 @@
+//! Reasoning and acting in turns: the model calls one tool, sees its result, and decides
+//! what to do next until it can answer.
+
+use std::sync::Arc;
+
+use tokio::sync::Mutex;
+
+use super::{
+    IterativePlanner, NextStep, PlanStep, Planner, StepOutcome, plan_from_response, planning_failed,
+};
+use crate::{
+    errors::AgenticFlowError,
+    llm_client::LLMClient,
+    model::{ChatMessage, ToolCall},
+    prompt::{ChatPrompt, describe_tools},
+    tool_registry::ToolRegistry,
+};
+
+/// Asks the LLM for one tool call at a time, with the results of the earlier calls as
+/// tool messages, until it answers without calling a tool. The steps run as they are
+/// chosen, so it is an [`IterativePlanner`]; [`plan`](Planner::plan) fails, as there is
+/// no plan to make up front.
+///
+/// Of several calls in one answer only the first runs; the model sees its result before
+/// making the next. Calling the same tool with the same arguments as the step before
+/// fails with [`AgenticFlowError::PlanningError`] rather than running it again.
+pub struct ReActPlanner {
+    llm_client: LLMClient,
+    tool_registry: Arc<Mutex<ToolRegistry>>,
+    prompt: ChatPrompt,
+}
+
+impl ReActPlanner {
+    pub fn new(llm_client: LLMClient, tool_registry: Arc<Mutex<ToolRegistry>>) -> Self {
+        Self {
+            llm_client,
+            tool_registry,
+            prompt: ChatPrompt::builtin(
+                "Solve the task step by step. Call one tool at a time and wait for its result \
+                 before deciding on the next step. Once you can answer the task, answer it \
+                 without calling a tool.",
+                "{task}",
+            ),
+        }
+    }
+
+    /// Replaces the system and user prompts, which may use `{task}` and `{tools}`.
+    pub fn with_prompt(mut self, prompt: ChatPrompt) -> Self {
+        self.prompt = prompt;
+        self
+    }
+}
+
+#[async_trait::async_trait]
+impl Planner for ReActPlanner {
+    async fn plan(&self, _task: &str) -> Result<Vec<PlanStep>, AgenticFlowError> {
+        Err(AgenticFlowError::PlanningError(
+            "ReActPlanner chooses its steps while they run; run it with \
+             Executor::execute_iteratively"
+                .to_string(),
+        ))
+    }
+
+    fn as_iterative(&self) -> Option<&dyn IterativePlanner> {
+        Some(self)
+    }
+}
+
+#[async_trait::async_trait]
+impl IterativePlanner for ReActPlanner {
+    async fn next_step(
+        &self,
+        task: &str,
+        observed: &[StepOutcome],
+    ) -> Result<NextStep, AgenticFlowError> {
+        // Such a model would answer every task without calling a tool.
+        if !self.llm_client.capabilities().supports_tools && !self.llm_client.emulates_tools() {
+            return Err(AgenticFlowError::PlanningError(format!(
+                "model {} does not support tool calls, which ReActPlanner acts with; \
+                 use a model with tool support, LLMClient::with_capabilities if it has it, \
+                 or ToolEmulation::Auto",
+                self.llm_client.model_name().unwrap_or("unknown")
+            )));
+        }
+        let tools = self.tool_registry.lock().await.get_tools_for_planner();
+        let mut messages = self
+            .prompt
+            .messages(&[("task", task), ("tools", &describe_tools(&tools))])?;
+        messages.extend(observed.iter().flat_map(observation));
+
+        let response = self
+            .llm_client
+            .chat_completions(messages, tools)
+            .await
+            .map_err(planning_failed("react", "next_step"))?;
+        let steps = plan_from_response(
+            "react",
+            "next_step",
+            response.as_ref(),
+            self.llm_client.max_tokens(),
+        )?;
+        let Some(step) = steps.into_iter().next() else {
+            return Ok(NextStep::Finish(response.message().content.clone()));
+        };
+
+        let repeated = observed
+            .last()
+            .is_some_and(|last| last.tool == step.tool_name && last.params == step.params);
+        if repeated {
+            return Err(AgenticFlowError::PlanningError(format!(
+                "the model called '{}' with the same arguments twice in a row: {}",
+                step.tool_name, step.params
+            )));
+        }
+        Ok(NextStep::Call(step))
+    }
+}
+
+/// The call that ran `outcome` and its result, as the model would have seen them.
+fn observation(outcome: &StepOutcome) -> [ChatMessage; 2] {
+    let id = format!("call_{}", outcome.step_index + 1);
+    let call = ToolCall::new(outcome.tool.clone(), outcome.params.clone()).with_id(id.clone());
+    [
+        ChatMessage::assistant(String::new()).with_tool_calls(vec![call]),
+        ChatMessage::tool(outcome.result.to_string(), id).with_name(outcome.tool.clone()),
+    ]
+}
@@ -0,0 +1,210 @@
+//! Planning in rounds of self-critique: a critic judges each plan against the task and
+//! the tools, and the inner planner plans again with that critique in hand.
+
+use std::sync::Arc;
+
+use serde_json::Value;
+use tokio::sync::Mutex;
+
+use super::{PlanStep, Planner, describe_step, planning_failed};
+use crate::{
+    errors::AgenticFlowError,
+    llm_client::LLMClient,
+    prompt::{ChatPrompt, describe_tools},
+    tool_registry::ToolRegistry,
+};
+
+/// The line a critique ends with to say whether the plan may run.
+const VERDICT: &str = "VERDICT:";
+
+/// A plan from [`ReflexionPlanner::reflect`] and the critique of it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ReflectedPlan {
+    pub steps: Vec<PlanStep>,
+    /// The critic's view of `steps`, without its verdict line.
+    pub critique: String,
+    /// Whether the critic found `steps` acceptable; a plan that ran out of rounds is not.
+    pub accepted: bool,
+    /// How many times the plan was made again after a critique.
+    pub revisions: usize,
+}
+
+/// Makes a plan with an inner planner, asks the LLM to critique it against the task and
+/// the available tools, and makes it again with the critique appended to the task, up to
+/// `rounds` times or until the critic accepts the plan. The returned plan has always been
+/// critiqued; [`reflect`](Self::reflect) also returns that critique. Without a
+/// [`with_tool_registry`](Self::with_tool_registry) the critic is not shown the tools and
+/// is not asked whether the plan calls ones that do not exist.
+///
+/// Unlike [`CritiquePlanner`](super::CritiquePlanner), the critic only writes; the plan is
+/// always made by the inner planner.
+pub struct ReflexionPlanner {
+    inner: Box<dyn Planner>,
+    llm_client: LLMClient,
+    tool_registry: Option<Arc<Mutex<ToolRegistry>>>,
+    rounds: usize,
+    /// Set by [`with_prompt`](Self::with_prompt); otherwise the built-in prompt for
+    /// whether there is a tool registry.
+    prompt: Option<ChatPrompt>,
+}
+
+impl ReflexionPlanner {
+    pub fn new(inner: Box<dyn Planner>, llm_client: LLMClient, rounds: usize) -> Self {
+        Self {
+            inner,
+            llm_client,
+            tool_registry: None,
+            rounds,
+            prompt: None,
+        }
+    }
+
+    /// Lists the tools of `tool_registry`, with their parameter schemas, to the critic, so
+    /// it can spot calls to tools that do not exist.
+    pub fn with_tool_registry(mut self, tool_registry: Arc<Mutex<ToolRegistry>>) -> Self {
+        self.tool_registry = Some(tool_registry);
+        self
+    }
+
+    /// Replaces the critic's prompts, which may use `{task}`, `{tools}` and `{plan}`;
+    /// `{tools}` is `(none listed)` without a tool registry. The critique should end with
+    /// a `VERDICT: ACCEPT` or `VERDICT: REVISE` line; one without counts as `REVISE`.
+    pub fn with_prompt(mut self, prompt: ChatPrompt) -> Self {
+        self.prompt = Some(prompt);
+        self
+    }
+
+    /// The plan for `task` with the last critique of it.
+    pub async fn reflect(&self, task: &str) -> Result<ReflectedPlan, AgenticFlowError> {
+        let tools = match &self.tool_registry {
+            Some(tool_registry) => {
+                describe_tools_with_schemas(&tool_registry.lock().await.get_tools_for_planner())
+            }
+            None => "(none listed)".to_string(),
+        };
+
+        let mut steps = self.inner.plan(task).await?;
+        let mut revisions = 0;
+        loop {
+            let (critique, accepted) = self.critique(task, &tools, &steps).await?;
+            if accepted || revisions == self.rounds {
+                return Ok(ReflectedPlan {
+                    steps,
+                    critique,
+                    accepted,
+                    revisions,
+                });
+            }
+            let revised_task = format!(
+                "{}\n\nA critique of an earlier plan for this task, to address in the new \
+                 plan:\n{}",
+                task, critique
+            );
+            steps = self.inner.plan(&revised_task).await?;
+            revisions += 1;
+        }
+    }
+
+    /// The critique of `steps` and whether it accepts them.
+    async fn critique(
+        &self,
+        task: &str,
+        tools: &str,
+        steps: &[PlanStep],
+    ) -> Result<(String, bool), AgenticFlowError> {
+        let plan = if steps.is_empty() {
+            "(no steps)".to_string()
+        } else {
+            steps
+                .iter()
+                .enumerate()
+                .map(|(index, step)| describe_step(index, step))
+                .collect::<Vec<_>>()
+                .join("\n")
+        };
+        let prompt = match &self.prompt {
+            Some(prompt) => prompt.clone(),
+            None => critic_prompt(self.tool_registry.is_some()),
+        };
+        let messages = prompt.messages(&[("task", task), ("tools", tools), ("plan", &plan)])?;
+        let response = self
+            .llm_client
+            .chat_completions(messages, vec![])
+            .await
+            .map_err(planning_failed("reflexion", "critique"))?;
+
+        let content = response.message().content.trim();
+        let verdict = content
+            .rsplit_once('\n')
+            .map_or(content, |(_, last)| last)
+            .trim();
+        match verdict
+            .get(..VERDICT.len())
+            .filter(|prefix| prefix.eq_ignore_ascii_case(VERDICT))
+        {
+            Some(_) => {
+                let accepted = verdict[VERDICT.len()..]
+                    .trim()
+                    .to_ascii_uppercase()
+                    .starts_with("ACCEPT");
+                let critique = content[..content.len() - verdict.len()].trim_end();
+                Ok((critique.to_string(), accepted))
+            }
+            None => Ok((content.to_string(), false)),
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl Planner for ReflexionPlanner {
+    async fn plan(&self, task: &str) -> Result<Vec<PlanStep>, AgenticFlowError> {
+        Ok(self.reflect(task).await?.steps)
+    }
+}
+
+/// The built-in prompts of the critic. Only a critic shown the tools is asked about their
+/// schemas and whether the plan calls tools that do not exist; one that is not would
+/// take every call for one.
+fn critic_prompt(lists_tools: bool) -> ChatPrompt {
+    const VERDICT_LINE: &str = "Answer with your critique, then a last line that is either VERDICT: ACCEPT or \
+         VERDICT: REVISE.";
+    if lists_tools {
+        ChatPrompt::builtin(
+            &format!(
+                "You review plans before they run. Will the plan achieve the task? What is \
+                 missing? Are the parameters plausible given the schemas of the tools? Does \
+                 it call tools that do not exist? {}",
+                VERDICT_LINE
+            ),
+            "Task: {task}\n\nAvailable tools:\n{tools}\n\nPlan:\n{plan}",
+        )
+    } else {
+        ChatPrompt::builtin(
+            &format!(
+                "You review plans before they run. Will the plan achieve the task? What is \
+                 missing? Are the parameters plausible? {}",
+                VERDICT_LINE
+            ),
+            "Task: {task}\n\nPlan:\n{plan}",
+        )
+    }
+}
+
+/// The `{tools}` of the critic's prompt: each tool's line from
+/// [`describe_tools`] followed by its parameter schema.
+fn describe_tools_with_schemas(tools: &[Value]) -> String {
+    if tools.is_empty() {
+        return "(none)".to_string();
+    }
+    tools
+        .iter()
+        .map(|tool| {
+            let line = describe_tools(std::slice::from_ref(tool));
+            match tool["function"].get("parameters") {
+                Some(parameters) => format!("{}\n  parameters: {}", line, parameters),
+                None => line,
+            }
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
@@ -0,0 +1,192 @@
+//! Checking the params of plan steps against the parameter schemas of their tools, and
+//! asking the model to fix the steps that do not match.
+
+use std::sync::Arc;
+
+use serde_json::Value;
+use tokio::sync::Mutex;
+
+use super::{PlanStep, Planner, plan_from_response, planning_failed};
+use crate::{
+    errors::AgenticFlowError,
+    llm_client::{LLMClient, RequestOptions, ToolChoice},
+    model::ChatMessage,
+    tool_registry::ToolRegistry,
+};
+
+/// The repair rounds of
+/// [`MultiStepPlanner::with_schema_repair`](super::MultiStepPlanner::with_schema_repair).
+pub const DEFAULT_SCHEMA_REPAIR_ROUNDS: usize = 1;
+
+/// Checks the plan of an inner planner against the parameter schemas of the tools, and
+/// gives the model up to `rounds` chances to fix the steps that do not match, such as
+/// params of the wrong type or without a required field. Each round sends the broken
+/// calls and their violations, and the answer replaces those steps in order.
+///
+/// Steps still broken after the last round fail the plan with
+/// [`AgenticFlowError::PlanningError`] listing their violations; with 0 rounds the plan
+/// is only checked. Steps of unknown tools,
+/// and tools whose schema is not a valid JSON schema, are not checked.
+pub struct SchemaRepairPlanner {
+    inner: Box<dyn Planner>,
+    llm_client: LLMClient,
+    tool_registry: Arc<Mutex<ToolRegistry>>,
+    rounds: usize,
+}
+
+impl SchemaRepairPlanner {
+    pub fn new(
+        inner: Box<dyn Planner>,
+        llm_client: LLMClient,
+        tool_registry: Arc<Mutex<ToolRegistry>>,
+        rounds: usize,
+    ) -> Self {
+        Self {
+            inner,
+            llm_client,
+            tool_registry,
+            rounds,
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl Planner for SchemaRepairPlanner {
+    async fn plan(&self, task: &str) -> Result<Vec<PlanStep>, AgenticFlowError> {
+        let steps = self.inner.plan(task).await?;
+        let tools = self.tool_registry.lock().await.get_tools_for_planner();
+        repair(
+            "schema_repair",
+            &self.llm_client,
+            task,
+            &tools,
+            steps,
+            self.rounds,
+        )
+        .await
+    }
+}
+
+/// A step whose params do not match the schema of its tool.
+struct Violation {
+    step_index: usize,
+    errors: Vec<String>,
+}
+
+/// `steps` with the broken ones fixed by the model in up to `rounds` rounds, as
+/// [`SchemaRepairPlanner`] describes. Failed calls are reported as the `schema_repair`
+/// phase of `planner`.
+pub(super) async fn repair(
+    planner: &'static str,
+    llm_client: &LLMClient,
+    task: &str,
+    tools: &[Value],
+    mut steps: Vec<PlanStep>,
+    rounds: usize,
+) -> Result<Vec<PlanStep>, AgenticFlowError> {
+    let mut violations = check(&steps, tools);
+    for _ in 0..rounds {
+        if violations.is_empty() {
+            break;
+        }
+        let broken: Vec<String> = violations
+            .iter()
+            .map(|violation| {
+                let step = &steps[violation.step_index];
+                format!(
+                    "- {} {}\n  {}",
+                    step.tool_name,
+                    step.params,
+                    violation.errors.join("\n  ")
+                )
+            })
+            .collect();
+        let messages = vec![
+            ChatMessage::system(
+                "The arguments of the tool calls below do not match the schemas of their \
+                 tools. Call each of these tools again with corrected arguments, in the same \
+                 order, and nothing else."
+                    .to_string(),
+            ),
+            ChatMessage::user(format!(
+                "Task: {}\n\nTool calls and their errors:\n{}",
+                task,
+                broken.join("\n")
+            )),
+        ];
+        let offered: Vec<Value> = tools
+            .iter()
+            .filter(|tool| {
+                violations.iter().any(|violation| {
+                    tool["function"]["name"].as_str()
+                        == Some(steps[violation.step_index].tool_name.as_str())
+                })
+            })
+            .cloned()
+            .collect();
+        let options = RequestOptions::default().with_tool_choice(ToolChoice::Required);
+
+        let response = llm_client
+            .chat_completions_with(messages, offered, &options)
+            .await
+            .map_err(planning_failed(planner, "schema_repair"))?;
+        let fixed = plan_from_response(
+            planner,
+            "schema_repair",
+            response.as_ref(),
+            llm_client.max_tokens(),
+        )?;
+        for (violation, mut step) in violations.iter().zip(fixed) {
+            let broken = &mut steps[violation.step_index];
+            step.id = broken.id.take();
+            step.description = broken.description.take();
+            step.depends_on = std::mem::take(&mut broken.depends_on);
+            *broken = step;
+        }
+        violations = check(&steps, tools);
+    }
+
+    if violations.is_empty() {
+        return Ok(steps);
+    }
+    let listed: Vec<String> = violations
+        .iter()
+        .map(|violation| {
+            format!(
+                "step {} ({}): {}",
+                violation.step_index + 1,
+                steps[violation.step_index].tool_name,
+                violation.errors.join("; ")
+            )
+        })
+        .collect();
+    Err(AgenticFlowError::PlanningError(format!(
+        "Plan steps do not match the schemas of their tools (repair rounds: {}): {}",
+        rounds,
+        listed.join(", ")
+    )))
+}
+
+/// The steps whose params break the schema of their tool, in plan order.
+fn check(steps: &[PlanStep], tools: &[Value]) -> Vec<Violation> {
+    steps
+        .iter()
+        .enumerate()
+        .filter_map(|(step_index, step)| {
+            let schema = tools
+                .iter()
+                .map(|tool| &tool["function"])
+                .find(|function| function["name"].as_str() == Some(step.tool_name.as_str()))?
+                .get("parameters")?;
+            let validator = jsonschema::validator_for(schema).ok()?;
+            let errors: Vec<String> = validator
+                .iter_errors(&step.params)
+                .map(|error| match error.instance_path().as_str() {
+                    "" => error.to_string(),
+                    path => format!("{}: {}", path, error),
+                })
+                .collect();
+            (!errors.is_empty()).then_some(Violation { step_index, errors })
+        })
+        .collect()
+}
@@ -0,0 +1,203 @@
+//! Prompt templates with named placeholders, such as `Task: {task}`, for planners whose
+//! wording users want to change.
+
+use std::{fmt, fs, path::Path, str::FromStr};
+
+use serde_json::Value;
+
+use crate::{errors::AgenticFlowError, model::ChatMessage};
+
+/// Text with `{name}` placeholders, filled in by [`render`](Self::render). `{{` and `}}`
+/// stand for literal braces. Names are letters, digits and underscores.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PromptTemplate {
+    source: String,
+    parts: Vec<Part>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Part {
+    Text(String),
+    Placeholder(String),
+}
+
+impl PromptTemplate {
+    /// Parses `source`. Fails with [`AgenticFlowError::ConfigError`] on a brace that is
+    /// neither doubled nor part of a placeholder.
+    pub fn new(source: impl Into<String>) -> Result<Self, AgenticFlowError> {
+        let source = source.into();
+        let parts = parse(&source)?;
+        Ok(Self { source, parts })
+    }
+
+    /// Reads and parses the template in the file at `path`.
+    pub fn from_file(path: impl AsRef<Path>) -> Result<Self, AgenticFlowError> {
+        let path = path.as_ref();
+        let source = fs::read_to_string(path).map_err(|error| {
+            AgenticFlowError::ConfigError(format!(
+                "cannot read prompt template '{}': {}",
+                path.display(),
+                error
+            ))
+        })?;
+        Self::new(source)
+    }
+
+    /// The text the template was parsed from.
+    pub fn source(&self) -> &str {
+        &self.source
+    }
+
+    /// The names of the placeholders, in the order they first appear.
+    pub fn placeholders(&self) -> Vec<&str> {
+        let mut names: Vec<&str> = Vec::new();
+        let placeholders = self.parts.iter().filter_map(|part| match part {
+            Part::Placeholder(name) => Some(name.as_str()),
+            Part::Text(_) => None,
+        });
+        for name in placeholders {
+            if !names.contains(&name) {
+                names.push(name);
+            }
+        }
+        names
+    }
+
+    /// The template with each placeholder replaced by its value in `values`. Values are
+    /// inserted as they are, braces included. Values for placeholders the template does
+    /// not have are ignored; a placeholder without a value fails with
+    /// [`AgenticFlowError::PlanningError`] naming it.
+    pub fn render(&self, values: &[(&str, &str)]) -> Result<String, AgenticFlowError> {
+        let mut rendered = String::with_capacity(self.source.len());
+        for part in &self.parts {
+            match part {
+                Part::Text(text) => rendered.push_str(text),
+                Part::Placeholder(name) => {
+                    let value = values
+                        .iter()
+                        .find(|(key, _)| key == name)
+                        .map(|(_, value)| *value)
+                        .ok_or_else(|| {
+                            AgenticFlowError::PlanningError(format!(
+                                "prompt template has no value for placeholder {{{}}}",
+                                name
+                            ))
+                        })?;
+                    rendered.push_str(value);
+                }
+            }
+        }
+        Ok(rendered)
+    }
+}
+
+impl FromStr for PromptTemplate {
+    type Err = AgenticFlowError;
+
+    fn from_str(source: &str) -> Result<Self, Self::Err> {
+        Self::new(source)
+    }
+}
+
+impl fmt::Display for PromptTemplate {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.source)
+    }
+}
+
+fn parse(source: &str) -> Result<Vec<Part>, AgenticFlowError> {
+    let invalid = |position: usize, reason: &str| {
+        AgenticFlowError::ConfigError(format!(
+            "invalid prompt template at byte {}: {}",
+            position, reason
+        ))
+    };
+    let mut parts = Vec::new();
+    let mut text = String::new();
+    let mut chars = source.char_indices().peekable();
+    while let Some((position, c)) = chars.next() {
+        match c {
+            '{' if chars.next_if(|(_, next)| *next == '{').is_some() => text.push('{'),
+            '}' if chars.next_if(|(_, next)| *next == '}').is_some() => text.push('}'),
+            '}' => return Err(invalid(position, "unmatched '}', write '}}' for a brace")),
+            '{' => {
+                let mut name = String::new();
+                loop {
+                    match chars.next() {
+                        Some((_, '}')) => break,
+                        Some((_, c)) if c.is_alphanumeric() || c == '_' => name.push(c),
+                        _ => {
+                            return Err(invalid(
+                                position,
+                                "'{' does not start a placeholder, write '{{' for a brace",
+                            ));
+                        }
+                    }
+                }
+                if name.is_empty() {
+                    return Err(invalid(position, "empty placeholder '{}'"));
+                }
+                if !text.is_empty() {
+                    parts.push(Part::Text(std::mem::take(&mut text)));
+                }
+                parts.push(Part::Placeholder(name));
+            }
+            c => text.push(c),
+        }
+    }
+    if !text.is_empty() {
+        parts.push(Part::Text(text));
+    }
+    Ok(parts)
+}
+
+/// The `{tools}` value for planner prompts: one `- name: description` line per tool
+/// definition, as [`ToolRegistry::get_tools_for_planner`] returns them.
+///
+/// [`ToolRegistry::get_tools_for_planner`]: crate::tool_registry::ToolRegistry::get_tools_for_planner
+pub fn describe_tools(tools: &[Value]) -> String {
+    tools
+        .iter()
+        .map(|tool| {
+            let function = &tool["function"];
+            let name = function["name"].as_str().unwrap_or_default();
+            match function["description"]
+                .as_str()
+                .filter(|text| !text.is_empty())
+            {
+                Some(description) => format!("- {}: {}", name, description),
+                None => format!("- {}", name),
+            }
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// The system and user prompts of one chat call, such as a phase of a planner.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ChatPrompt {
+    pub system: PromptTemplate,
+    pub user: PromptTemplate,
+}
+
+impl ChatPrompt {
+    pub fn new(system: PromptTemplate, user: PromptTemplate) -> Self {
+        Self { system, user }
+    }
+
+    /// Parses both templates, for the ones built into the crate.
+    pub(crate) fn builtin(system: &str, user: &str) -> Self {
+        let parse = |source: &str| {
+            PromptTemplate::new(source).expect("built-in prompt templates are valid")
+        };
+        Self::new(parse(system), parse(user))
+    }
+
+    /// The system and the user message, rendered with `values`.
+    pub fn messages(&self, values: &[(&str, &str)]) -> Result<Vec<ChatMessage>, AgenticFlowError> {
+        Ok(vec![
+            ChatMessage::system(self.system.render(values)?),
+            ChatMessage::user(self.user.render(values)?),
+        ])
+    }
+}
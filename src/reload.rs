@@ -0,0 +1,124 @@
+use std::sync::Arc;
+
+use crate::{
+    AgenticSystem, Runtime,
+    config::{ConfigDiff, SystemConfig},
+    errors::AgenticFlowError,
+    llm_client::{LLMClient, Purpose},
+    observer::{self, ErrorContext},
+    secrets::resolve_mcp_config,
+};
+
+/// The outcome of [`AgenticSystem::reload_config`]: what changed and what was done about it.
+#[derive(Debug, Clone, Default)]
+pub struct ReloadReport {
+    pub diff: ConfigDiff,
+    pub started: Vec<String>,
+    pub stopped: Vec<String>,
+    pub restarted: Vec<String>,
+    /// Servers that could not be started, stopped or restarted, with the error.
+    pub failed: Vec<(String, AgenticFlowError)>,
+    /// Whether a new LLM client was built from the new `llm_config`.
+    pub llm_client_rebuilt: bool,
+}
+
+impl ReloadReport {
+    pub fn is_success(&self) -> bool {
+        self.failed.is_empty()
+    }
+}
+
+impl AgenticSystem {
+    /// Applies `new_config` without restarting the whole system.
+    ///
+    /// Removed servers are stopped, added servers are started and servers whose
+    /// `ServerConfig` changed are restarted; the MCP tools are then rediscovered.
+    /// LLM, agent and planner changes apply to runs started after the reload,
+    /// runs already executing keep the config they started with. A client passed
+    /// to [`AgenticSystem::new`] is kept as is.
+    ///
+    /// The new config is validated first and nothing is touched if it is invalid.
+    /// Individual server failures do not abort the reload, they are listed in
+    /// [`ReloadReport::failed`].
+    pub async fn reload_config(
+        &self,
+        new_config: SystemConfig,
+    ) -> Result<ReloadReport, AgenticFlowError> {
+        new_config.validate()?;
+        crate::warn_ignored_settings(&new_config);
+
+        let current = self.runtime();
+        let diff = current.config.diff(&new_config);
+        let mut report = ReloadReport::default();
+
+        let llm = if diff.llm_config_changed && self.llm_client_from_config {
+            report.llm_client_rebuilt = true;
+            let tracker = current.llm.client(Purpose::Default).cost_tracker().clone();
+            LLMClient::from_config_with_resolver(&new_config.llm_config, &*self.secret_resolver)?
+                .with_cost_tracker(tracker)
+                .into()
+        } else {
+            current.llm.clone()
+        };
+        let mcp_config = resolve_mcp_config(&new_config.mcp_config, &*self.secret_resolver)?;
+
+        let servers_changed = !(diff.servers_added.is_empty()
+            && diff.servers_removed.is_empty()
+            && diff.servers_changed.is_empty());
+
+        if servers_changed {
+            let mut manager = self.manager.lock().await;
+            manager.set_config(mcp_config);
+
+            for name in &diff.servers_removed {
+                match manager.stop_server(name).await {
+                    Ok(()) => report.stopped.push(name.clone()),
+                    Err(e) => report.failed.push((name.clone(), e)),
+                }
+            }
+
+            for name in &diff.servers_changed {
+                let restart = match manager.stop_server(name).await {
+                    Ok(()) => manager.start_server(name).await,
+                    Err(e) => Err(e),
+                };
+                match restart {
+                    Ok(()) => report.restarted.push(name.clone()),
+                    Err(e) => report.failed.push((name.clone(), e)),
+                }
+            }
+
+            for name in &diff.servers_added {
+                match manager.start_server(name).await {
+                    Ok(()) => report.started.push(name.clone()),
+                    Err(e) => report.failed.push((name.clone(), e)),
+                }
+            }
+
+            self.tool_registry
+                .lock()
+                .await
+                .refresh_mcp_tools(&manager)
+                .await?;
+        }
+
+        for (_, error) in &report.failed {
+            observer::report(&self.error_observer, error, ErrorContext::new("mcp", error));
+        }
+
+        let runtime = Runtime::new(
+            new_config,
+            llm,
+            &self.manager,
+            &self.tool_registry,
+            &self.error_observer,
+        );
+        *self
+            .runtime
+            .write()
+            .unwrap_or_else(|poisoned| poisoned.into_inner()) = Arc::new(runtime);
+
+        report.diff = diff;
+        Ok(report)
+    }
+}
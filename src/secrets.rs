@@ -0,0 +1,199 @@
+//! Credentials referenced from the config and resolved once at startup.
+
+use std::{collections::HashMap, fmt, fs, sync::Mutex};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{config::MCPConfig, errors::AgenticFlowError};
+
+/// Where a credential comes from, written in config files as a single-key table:
+///
+/// ```toml
+/// api_key = { env = "OPENROUTER_API_KEY" }
+/// api_key = { file = "/run/secrets/openrouter" }
+/// api_key = { command = ["vault-read", "secret/openrouter"] }  # `secret-command` feature
+/// ```
+///
+/// `literal` is accepted as well but discouraged, since the secret then lives in the file.
+#[derive(Clone, PartialEq, Eq, Hash, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SecretRef {
+    Env(String),
+    /// A file holding the secret, such as a Docker or Kubernetes secret. Trailing
+    /// newlines are stripped.
+    File(String),
+    Literal(String),
+    /// A program printing the secret to stdout.
+    #[cfg(feature = "secret-command")]
+    Command(Vec<String>),
+}
+
+impl fmt::Display for SecretRef {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SecretRef::Env(name) => write!(f, "env:{}", name),
+            SecretRef::File(path) => write!(f, "file:{}", path),
+            SecretRef::Literal(_) => write!(f, "literal"),
+            #[cfg(feature = "secret-command")]
+            SecretRef::Command(command) => write!(f, "command:{}", command.join(" ")),
+        }
+    }
+}
+
+impl fmt::Debug for SecretRef {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "SecretRef({})", self)
+    }
+}
+
+/// A secret value, such as an API key, that `Debug` does not print.
+#[derive(Clone, PartialEq, Eq)]
+pub struct SecretString(String);
+
+impl SecretString {
+    pub fn new(secret: impl Into<String>) -> Self {
+        Self(secret.into())
+    }
+
+    /// The secret itself, for the places that send it.
+    pub fn expose(&self) -> &str {
+        &self.0
+    }
+}
+
+impl fmt::Debug for SecretString {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "SecretString(***)")
+    }
+}
+
+impl From<String> for SecretString {
+    fn from(secret: String) -> Self {
+        Self(secret)
+    }
+}
+
+impl From<&str> for SecretString {
+    fn from(secret: &str) -> Self {
+        Self(secret.to_string())
+    }
+}
+
+/// Turns a [`SecretRef`] into the secret value.
+pub trait SecretResolver: Send + Sync {
+    fn resolve(&self, secret: &SecretRef) -> Result<String, AgenticFlowError>;
+}
+
+/// Resolves env, file, literal and (with the `secret-command` feature) command refs.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct DefaultSecretResolver;
+
+impl SecretResolver for DefaultSecretResolver {
+    fn resolve(&self, secret: &SecretRef) -> Result<String, AgenticFlowError> {
+        match secret {
+            SecretRef::Env(name) => std::env::var(name).map_err(|_| {
+                AgenticFlowError::ConfigError("environment variable is not set".to_string())
+            }),
+            SecretRef::File(path) => fs::read_to_string(path)
+                .map(|contents| contents.trim_end_matches(['\r', '\n']).to_string())
+                .map_err(|e| AgenticFlowError::ConfigError(format!("failed to read file: {}", e))),
+            SecretRef::Literal(value) => Ok(value.clone()),
+            #[cfg(feature = "secret-command")]
+            SecretRef::Command(command) => run_secret_command(command),
+        }
+    }
+}
+
+#[cfg(feature = "secret-command")]
+fn run_secret_command(command: &[String]) -> Result<String, AgenticFlowError> {
+    let (program, args) = command
+        .split_first()
+        .ok_or_else(|| AgenticFlowError::ConfigError("command is empty".to_string()))?;
+
+    let output = std::process::Command::new(program)
+        .args(args)
+        .output()
+        .map_err(|e| AgenticFlowError::ConfigError(format!("failed to run command: {}", e)))?;
+    if !output.status.success() {
+        return Err(AgenticFlowError::ConfigError(format!(
+            "command exited with {}",
+            output.status
+        )));
+    }
+
+    String::from_utf8(output.stdout)
+        .map(|stdout| stdout.trim_end_matches(['\r', '\n']).to_string())
+        .map_err(|_| AgenticFlowError::ConfigError("command output is not UTF-8".to_string()))
+}
+
+/// Wraps a resolver so each ref is resolved at most once. Values are kept in memory only.
+pub struct CachedSecretResolver<R> {
+    inner: R,
+    cache: Mutex<HashMap<SecretRef, String>>,
+}
+
+impl<R: SecretResolver> CachedSecretResolver<R> {
+    pub fn new(inner: R) -> Self {
+        Self {
+            inner,
+            cache: Mutex::new(HashMap::new()),
+        }
+    }
+}
+
+impl Default for CachedSecretResolver<DefaultSecretResolver> {
+    fn default() -> Self {
+        Self::new(DefaultSecretResolver)
+    }
+}
+
+impl<R: SecretResolver> SecretResolver for CachedSecretResolver<R> {
+    fn resolve(&self, secret: &SecretRef) -> Result<String, AgenticFlowError> {
+        if let Some(value) = self.cache.lock().unwrap().get(secret) {
+            return Ok(value.clone());
+        }
+
+        let value = self.inner.resolve(secret)?;
+        self.cache
+            .lock()
+            .unwrap()
+            .insert(secret.clone(), value.clone());
+        Ok(value)
+    }
+}
+
+/// Resolves `secret`, naming what needed it when resolution fails.
+pub fn resolve_for(
+    resolver: &dyn SecretResolver,
+    secret: &SecretRef,
+    needed_by: &str,
+) -> Result<String, AgenticFlowError> {
+    resolver.resolve(secret).map_err(|e| {
+        let reason = match e {
+            AgenticFlowError::ConfigError(message) => message,
+            other => other.to_string(),
+        };
+        AgenticFlowError::ConfigError(format!(
+            "Failed to resolve secret '{}' for {}: {}",
+            secret, needed_by, reason
+        ))
+    })
+}
+
+/// Returns a copy of `config` with every server's `secrets` resolved into its `env`.
+pub fn resolve_mcp_config(
+    config: &MCPConfig,
+    resolver: &dyn SecretResolver,
+) -> Result<MCPConfig, AgenticFlowError> {
+    let mut resolved = config.clone();
+
+    for (name, server) in resolved.servers.iter_mut() {
+        for (var, secret) in &server.secrets {
+            let needed_by = format!("server '{}' (env {})", name, var);
+            let value = resolve_for(resolver, secret, &needed_by)?;
+            server.env.insert(var.clone(), value);
+        }
+    }
+
+    Ok(resolved)
+}
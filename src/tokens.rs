@@ -0,0 +1,175 @@
+//! Estimating how many tokens a text or a conversation takes, and trimming conversations
+//! to a token budget.
+
+use crate::model::{ChatMessage, Role};
+
+/// Tokens a chat message costs besides its content, for the role and separators.
+pub const MESSAGE_OVERHEAD_TOKENS: usize = 4;
+
+/// Appended to a message shortened by [`TrimStrategy::TruncateLongest`].
+const TRUNCATED_MARKER: &str = "... [truncated]";
+
+/// Roughly how many tokens `text` takes, splitting it the way the cl100k tokenizer of
+/// OpenAI models does: words of up to five letters count as one token, numbers as one
+/// per three digits, punctuation as one per two characters, line breaks as one and
+/// characters beyond ASCII as one each. The estimate is usually within a fifth of the
+/// exact count for English text and code, and errs high for other languages.
+///
+/// `model` names the model the text is for. Every model gets the same estimate today;
+/// it is taken so that callers keep working once model-specific tokenizers are added.
+pub fn estimate_tokens(text: &str, _model: &str) -> usize {
+    let mut tokens = 0;
+    let mut chars = text.chars().peekable();
+    while let Some(c) = chars.next() {
+        let mut run: usize = 1;
+        let class = CharClass::of(c);
+        if class != CharClass::Other {
+            while chars
+                .next_if(|next| CharClass::of(*next) == class)
+                .is_some()
+            {
+                run += 1;
+            }
+        }
+        tokens += match class {
+            CharClass::Letter => run.div_ceil(5),
+            CharClass::Digit => run.div_ceil(3),
+            CharClass::Punctuation => run.div_ceil(2),
+            // Spaces join the word after them; indentation and line breaks do not.
+            CharClass::Space if run == 1 && c == ' ' => 0,
+            CharClass::Space => run.div_ceil(4),
+            CharClass::Other => 1,
+        };
+    }
+    tokens
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum CharClass {
+    Letter,
+    Digit,
+    Punctuation,
+    Space,
+    /// Characters beyond ASCII, counted one by one.
+    Other,
+}
+
+impl CharClass {
+    fn of(c: char) -> Self {
+        if !c.is_ascii() {
+            CharClass::Other
+        } else if c.is_ascii_alphabetic() {
+            CharClass::Letter
+        } else if c.is_ascii_digit() {
+            CharClass::Digit
+        } else if c.is_ascii_whitespace() {
+            CharClass::Space
+        } else {
+            CharClass::Punctuation
+        }
+    }
+}
+
+/// Roughly how many tokens `message` takes: its content, the names and arguments of its
+/// tool calls, and [`MESSAGE_OVERHEAD_TOKENS`].
+pub fn estimate_message_tokens(message: &ChatMessage, model: &str) -> usize {
+    let tool_calls: usize = message
+        .tool_calls
+        .iter()
+        .flatten()
+        .map(|call| {
+            estimate_tokens(&call.function.name, model)
+                + estimate_tokens(&call.function.arguments.to_string(), model)
+        })
+        .sum();
+    MESSAGE_OVERHEAD_TOKENS + estimate_tokens(&message.content, model) + tool_calls
+}
+
+/// Roughly how many tokens `messages` take together.
+pub fn estimate_messages_tokens(messages: &[ChatMessage], model: &str) -> usize {
+    messages
+        .iter()
+        .map(|message| estimate_message_tokens(message, model))
+        .sum()
+}
+
+/// How [`trim_messages`] makes a conversation fit its budget. System messages are kept
+/// whole by every strategy.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum TrimStrategy {
+    /// Drops the oldest messages other than system messages, keeping the last message.
+    #[default]
+    DropOldest,
+    /// Shortens the longest message other than a system message, then the next longest
+    /// while the budget is still exceeded, marking each as truncated.
+    TruncateLongest,
+}
+
+/// `messages` cut down to about `budget` tokens, as [`estimate_messages_tokens`] counts
+/// them, with `strategy`. Conversations within the budget are returned as they are. The
+/// result may still exceed the budget when system messages alone, or for
+/// [`TrimStrategy::DropOldest`] the system messages and the last message, do.
+pub fn trim_messages(
+    mut messages: Vec<ChatMessage>,
+    budget: usize,
+    strategy: TrimStrategy,
+) -> Vec<ChatMessage> {
+    let mut excess = estimate_messages_tokens(&messages, "").saturating_sub(budget);
+    match strategy {
+        TrimStrategy::DropOldest => {
+            let last = messages.len().saturating_sub(1);
+            let mut kept = Vec::with_capacity(messages.len());
+            for (index, message) in messages.into_iter().enumerate() {
+                let droppable = message.role != Role::System && index < last;
+                if excess > 0 && droppable {
+                    excess = excess.saturating_sub(estimate_message_tokens(&message, ""));
+                } else {
+                    kept.push(message);
+                }
+            }
+            kept
+        }
+        TrimStrategy::TruncateLongest => {
+            let mut longest_first: Vec<usize> = (0..messages.len())
+                .filter(|index| messages[*index].role != Role::System)
+                .collect();
+            longest_first.sort_by_key(|index| {
+                std::cmp::Reverse(estimate_tokens(&messages[*index].content, ""))
+            });
+            for index in longest_first {
+                if excess == 0 {
+                    break;
+                }
+                let message = &mut messages[index];
+                let before = estimate_tokens(&message.content, "");
+                if before <= estimate_tokens(TRUNCATED_MARKER, "") {
+                    continue;
+                }
+                message.content = truncated(&message.content, before.saturating_sub(excess));
+                let after = estimate_tokens(&message.content, "");
+                excess = excess.saturating_sub(before.saturating_sub(after));
+            }
+            messages
+        }
+    }
+}
+
+/// The longest start of `text` that, with [`TRUNCATED_MARKER`], takes at most `tokens`.
+fn truncated(text: &str, tokens: usize) -> String {
+    let marker = estimate_tokens(TRUNCATED_MARKER, "");
+    let available = tokens.saturating_sub(marker);
+    let boundaries: Vec<usize> = text.char_indices().map(|(index, _)| index).collect();
+    let end = |chars: usize| boundaries.get(chars).copied().unwrap_or(text.len());
+    // The number of characters to keep: the most whose estimate fits.
+    let (mut low, mut high) = (0, boundaries.len());
+    while low < high {
+        let middle = (low + high).div_ceil(2);
+        if estimate_tokens(&text[..end(middle)], "") <= available {
+            low = middle;
+        } else {
+            high = middle - 1;
+        }
+    }
+    let end = end(low);
+    format!("{}{}", &text[..end], TRUNCATED_MARKER)
+}
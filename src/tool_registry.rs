@@ -2,9 +2,10 @@ use async_trait::async_trait;
 use rmcp::model::CallToolRequestParam;
 use serde_json::Value;
 use std::collections::HashMap;
+use std::sync::Arc;
 
-use crate::errors::AgenticFlowError;
-use crate::mcp_manager::MCPManager;
+use crate::errors::{AgenticFlowError, ToolOrigin};
+use crate::mcp_manager::{self, MCPManager};
 
 #[async_trait]
 pub trait LocalTool: Send + Sync {
@@ -18,7 +19,11 @@ pub trait LocalTool: Send + Sync {
     ) -> Result<serde_json::Value, AgenticFlowError>;
 }
 
-#[derive(Debug, Clone)]
+/// Values shared between the steps of a run.
+///
+/// Serializes as a plain JSON object of its entries.
+#[derive(Debug, Clone, Default, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(transparent)]
 pub struct ExecutionContext {
     data: HashMap<String, serde_json::Value>,
 }
@@ -41,6 +46,11 @@ impl ExecutionContext {
     pub fn data(&self) -> &HashMap<String, serde_json::Value> {
         &self.data
     }
+
+    /// Copies every entry of `other` into this context, overwriting existing keys.
+    pub fn extend(&mut self, other: ExecutionContext) {
+        self.data.extend(other.data);
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -69,11 +79,17 @@ pub enum ToolDescriptor {
 }
 
 pub struct ToolRegistry {
-    local_tools: HashMap<String, Box<dyn LocalTool>>,
+    local_tools: HashMap<String, Arc<dyn LocalTool>>,
     mcp_tool_map: HashMap<String, MCPToolDescriptor>,
     available_tools: Vec<ToolDescriptor>,
 }
 
+impl Default for ToolRegistry {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl ToolRegistry {
     pub fn new() -> Self {
         Self {
@@ -91,7 +107,7 @@ impl ToolRegistry {
             schema: tool.parameter_schema(),
         };
 
-        self.local_tools.insert(name, tool);
+        self.local_tools.insert(name, Arc::from(tool));
         self.available_tools.push(descriptor);
     }
 
@@ -190,7 +206,7 @@ impl ToolRegistry {
     ) -> Result<serde_json::Value, AgenticFlowError> {
         // 1. Check if it's a local tool
         if let Some(local_tool) = self.local_tools.get(tool_name) {
-            return local_tool.execute(params, context).await;
+            return execute_local(&**local_tool, params, context).await;
         }
 
         // 2. Check if it's an MCP tool
@@ -198,10 +214,15 @@ impl ToolRegistry {
             return self.execute_mcp_tool(mcp_descriptor, params, manager).await;
         }
 
-        Err(AgenticFlowError::ToolError(format!(
-            "Tool '{}' not found",
-            tool_name
-        )))
+        Err(AgenticFlowError::ToolNotFound {
+            tool: tool_name.to_string(),
+        })
+    }
+
+    /// The local tool named `tool_name`, to run with [`execute_local`] without holding the
+    /// registry, e.g. from steps running in parallel.
+    pub fn local_tool(&self, tool_name: &str) -> Option<Arc<dyn LocalTool>> {
+        self.local_tools.get(tool_name).cloned()
     }
 
     async fn execute_mcp_tool(
@@ -212,7 +233,7 @@ impl ToolRegistry {
     ) -> Result<serde_json::Value, AgenticFlowError> {
         let connection = manager
             .get_server_connection(&descriptor.server_name)
-            .ok_or(AgenticFlowError::ServerNotFound)?;
+            .ok_or_else(|| mcp_manager::not_running(&descriptor.server_name))?;
 
         let result = connection
             .call_tool(CallToolRequestParam {
@@ -221,12 +242,40 @@ impl ToolRegistry {
             })
             .await
             .map_err(|e| {
-                AgenticFlowError::ToolError(format!(
-                    "Failed to call MCP tool '{}': {}",
-                    descriptor.tool_name, e
-                ))
+                AgenticFlowError::mcp_server(
+                    &descriptor.server_name,
+                    Some(&descriptor.tool_name),
+                    AgenticFlowError::ToolError(e.to_string()),
+                )
             })?;
 
+        if result.is_error == Some(true) {
+            return Err(AgenticFlowError::ToolExecutionFailed {
+                tool: descriptor.tool_name.clone(),
+                origin: ToolOrigin::Mcp {
+                    server: descriptor.server_name.clone(),
+                },
+                source: Box::new(AgenticFlowError::ToolError(
+                    "The tool reported an error".to_string(),
+                )),
+            });
+        }
+
         Ok(result.structured_content.unwrap_or_default())
     }
 }
+
+/// Runs `tool`, wrapping its errors as [`ToolRegistry::execute_tool`] does.
+pub async fn execute_local(
+    tool: &dyn LocalTool,
+    params: serde_json::Value,
+    context: &mut ExecutionContext,
+) -> Result<serde_json::Value, AgenticFlowError> {
+    tool.execute(params, context)
+        .await
+        .map_err(|e| AgenticFlowError::ToolExecutionFailed {
+            tool: tool.name().to_string(),
+            origin: ToolOrigin::Local,
+            source: Box::new(e),
+        })
+}
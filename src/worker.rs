@@ -11,6 +11,7 @@ use tokio::{
 use crate::{
     agent::Agent, 
     errors::AgenticFlowError, 
+    observer::{self, ErrorContext, ErrorObserver},
     planner::PlanStep, 
     tool_registry::ExecutionContext,
 };
@@ -27,12 +28,19 @@ use crate::{
 /// - Provide simple parallel execution for independent steps
 ///
 /// # Usage
-/// ```rust
-/// let tool_registry = Arc::new(Mutex::new(ToolRegistry::new()));
-/// let mut pool = AgenticTaskPool::new(4, tool_registry.clone());
-/// let steps = vec![PlanStep { tool_name: "echo".to_string(), params: json!({"text": "hello"}) }];
+/// ```rust,no_run
+/// # use std::sync::Arc;
+/// # use serde_json::json;
+/// # use tokio::sync::Mutex;
+/// # use agentic_flow_lib::{agent::Agent, errors::AgenticFlowError, planner::PlanStep};
+/// # use agentic_flow_lib::worker::AgenticTaskPool;
+/// # async fn example(agent: Arc<Mutex<Agent>>) -> Result<(), AgenticFlowError> {
+/// let pool = AgenticTaskPool::new(4, agent);
+/// let steps = vec![PlanStep::new("echo", json!({"text": "hello"}))];
 /// let results = pool.execute_parallel(steps).await?;
-/// pool.shutdown().await;
+/// pool.shutdown().await?;
+/// # Ok(())
+/// # }
 /// ```
 pub struct AgenticTaskPool {
     /// Collection of worker task handles for managing concurrent execution
@@ -41,6 +49,8 @@ pub struct AgenticTaskPool {
     sender: Option<Sender<WorkerTask>>,
     /// Channel capacity for buffering tasks
     capacity: usize,
+    /// Receives the errors of failed steps
+    error_observer: Option<Arc<dyn ErrorObserver>>,
 }
 
 /// Internal task structure for worker communication
@@ -117,9 +127,16 @@ impl AgenticTaskPool {
             workers,
             sender: Some(sender),
             capacity,
+            error_observer: None,
         }
     }
 
+    /// Reports failed steps to `observer` as `pool` errors.
+    pub fn with_error_observer(mut self, observer: Arc<dyn ErrorObserver>) -> Self {
+        self.error_observer = Some(observer);
+        self
+    }
+
     /// Executes a single plan step by sending it to an available worker.
     ///
     /// # Arguments
@@ -131,6 +148,17 @@ impl AgenticTaskPool {
     /// # Errors
     /// Returns error if the task pool has been shut down or execution fails
     pub async fn execute_step(&self, step: PlanStep) -> Result<Value, AgenticFlowError> {
+        let tool_name = step.tool_name.clone();
+        let result = self.send_step(step).await;
+        if let Err(error) = &result {
+            let mut context = ErrorContext::new("pool", error);
+            context.tool = Some(tool_name);
+            observer::report(&self.error_observer, error, context);
+        }
+        result
+    }
+
+    async fn send_step(&self, step: PlanStep) -> Result<Value, AgenticFlowError> {
         match &self.sender {
             Some(sender) => {
                 let (response_tx, response_rx) = tokio::sync::oneshot::channel();
@@ -194,9 +222,7 @@ impl AgenticTaskPool {
 
         // Wait for all workers to complete
         for worker in self.workers {
-            worker
-                .await
-                .map_err(|e| AgenticFlowError::ExecutionError(format!("Worker error: {}", e)))?;
+            worker.await?;
         }
 
         Ok(())
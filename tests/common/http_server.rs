@@ -0,0 +1,215 @@
+use std::{
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use serde_json::Value;
+use tokio::{
+    io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt},
+    net::TcpListener,
+};
+
+/// A canned HTTP response served by [`MockHttpServer`].
+#[derive(Clone, Debug)]
+pub struct MockResponse {
+    pub status: u16,
+    pub headers: Vec<(String, String)>,
+    pub body: String,
+    /// How long to wait after reading the request before replying.
+    pub delay: Duration,
+    /// Closes the connection after this many bytes of the body, which keeps the
+    /// `Content-Length` of the whole body.
+    pub truncate_at: Option<usize>,
+}
+
+impl MockResponse {
+    pub fn json(status: u16, body: Value) -> Self {
+        Self {
+            status,
+            headers: vec![],
+            body: body.to_string(),
+            delay: Duration::ZERO,
+            truncate_at: None,
+        }
+    }
+
+    /// A response whose body is sent as-is, for replies that are not valid JSON.
+    pub fn raw(status: u16, body: &str) -> Self {
+        Self {
+            status,
+            headers: vec![],
+            body: body.to_string(),
+            delay: Duration::ZERO,
+            truncate_at: None,
+        }
+    }
+
+    pub fn with_delay(mut self, delay: Duration) -> Self {
+        self.delay = delay;
+        self
+    }
+
+    pub fn truncated_at(mut self, bytes: usize) -> Self {
+        self.truncate_at = Some(bytes);
+        self
+    }
+
+    pub fn with_header(mut self, name: &str, value: &str) -> Self {
+        self.headers.push((name.to_string(), value.to_string()));
+        self
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct RecordedRequest {
+    pub method: String,
+    pub path: String,
+    pub headers: Vec<(String, String)>,
+    pub body: Value,
+}
+
+impl RecordedRequest {
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(key, _)| key.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.as_str())
+    }
+}
+
+/// Minimal HTTP/1.1 server on localhost that replays responses in order
+/// (repeating the last one) and records every request it receives.
+pub struct MockHttpServer {
+    pub base_url: String,
+    requests: Arc<Mutex<Vec<RecordedRequest>>>,
+}
+
+impl MockHttpServer {
+    pub async fn start(responses: Vec<MockResponse>) -> Self {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let base_url = format!("http://{}", listener.local_addr().unwrap());
+        let requests = Arc::new(Mutex::new(Vec::new()));
+
+        let recorded = requests.clone();
+        tokio::spawn(async move {
+            let mut served = 0;
+            while let Ok((stream, _)) = listener.accept().await {
+                let response = nth_response(&responses, served);
+                served += 1;
+
+                handle_connection(stream, &response, &recorded).await;
+            }
+        });
+
+        Self { base_url, requests }
+    }
+
+    /// A server on the unix socket at `path`, whose requests are sent to
+    /// `http://localhost`.
+    #[cfg(unix)]
+    pub async fn start_unix(path: &std::path::Path, responses: Vec<MockResponse>) -> Self {
+        let listener = tokio::net::UnixListener::bind(path).unwrap();
+        let requests = Arc::new(Mutex::new(Vec::new()));
+
+        let recorded = requests.clone();
+        tokio::spawn(async move {
+            let mut served = 0;
+            while let Ok((stream, _)) = listener.accept().await {
+                let response = nth_response(&responses, served);
+                served += 1;
+
+                handle_connection(stream, &response, &recorded).await;
+            }
+        });
+
+        Self {
+            base_url: "http://localhost".to_string(),
+            requests,
+        }
+    }
+
+    pub fn requests(&self) -> Vec<RecordedRequest> {
+        self.requests.lock().unwrap().clone()
+    }
+}
+
+fn nth_response(responses: &[MockResponse], served: usize) -> MockResponse {
+    responses
+        .get(served)
+        .or(responses.last())
+        .cloned()
+        .expect("MockHttpServer needs at least one response")
+}
+
+async fn handle_connection(
+    mut stream: impl AsyncRead + AsyncWrite + Unpin,
+    response: &MockResponse,
+    recorded: &Mutex<Vec<RecordedRequest>>,
+) -> Option<()> {
+    let mut buffer = Vec::new();
+    let mut chunk = [0u8; 4096];
+
+    let header_end = loop {
+        let read = stream.read(&mut chunk).await.ok()?;
+        if read == 0 {
+            return None;
+        }
+        buffer.extend_from_slice(&chunk[..read]);
+        if let Some(position) = buffer.windows(4).position(|w| w == b"\r\n\r\n") {
+            break position + 4;
+        }
+    };
+
+    let head = String::from_utf8_lossy(&buffer[..header_end]).to_string();
+    let mut lines = head.lines();
+    let mut request_line = lines.next()?.split_whitespace();
+    let method = request_line.next()?.to_string();
+    let path = request_line.next()?.to_string();
+    let headers: Vec<(String, String)> = lines
+        .filter_map(|line| line.split_once(':'))
+        .map(|(name, value)| (name.trim().to_string(), value.trim().to_string()))
+        .collect();
+
+    let content_length = headers
+        .iter()
+        .find(|(name, _)| name.eq_ignore_ascii_case("content-length"))
+        .and_then(|(_, value)| value.parse::<usize>().ok())
+        .unwrap_or(0);
+
+    while buffer.len() < header_end + content_length {
+        let read = stream.read(&mut chunk).await.ok()?;
+        if read == 0 {
+            break;
+        }
+        buffer.extend_from_slice(&chunk[..read]);
+    }
+
+    let body = serde_json::from_slice(&buffer[header_end..]).unwrap_or(Value::Null);
+    // Record before replying so the request is visible once the client has its response.
+    recorded.lock().unwrap().push(RecordedRequest {
+        method,
+        path,
+        headers,
+        body,
+    });
+
+    tokio::time::sleep(response.delay).await;
+
+    let mut reply = format!(
+        "HTTP/1.1 {} Mock\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n",
+        response.status,
+        response.body.len()
+    );
+    for (name, value) in &response.headers {
+        reply.push_str(&format!("{}: {}\r\n", name, value));
+    }
+    reply.push_str("\r\n");
+    let body = response.body.as_bytes();
+    let sent = response.truncate_at.unwrap_or(body.len()).min(body.len());
+    let mut reply = reply.into_bytes();
+    reply.extend_from_slice(&body[..sent]);
+
+    stream.write_all(&reply).await.ok()?;
+    stream.shutdown().await.ok();
+    Some(())
+}
@@ -0,0 +1,31 @@
+use agentic_flow_lib::config::{ServerConfig, ServerType};
+
+/// A minimal MCP server in POSIX sh: answers `initialize` and lists a single tool
+/// named by `$STUB_TOOL_NAME`. Every other message is ignored.
+const STUB_SERVER_SCRIPT: &str = r#"
+while IFS= read -r line; do
+  id=$(printf '%s' "$line" | sed -n 's/.*"id":\([0-9]*\).*/\1/p')
+  case "$line" in
+    *'"method":"initialize"'*)
+      printf '{"jsonrpc":"2.0","id":%s,"result":{"protocolVersion":"2024-11-05","capabilities":{"tools":{}},"serverInfo":{"name":"stub","version":"0.1.0"}}}\n' "$id"
+      ;;
+    *'"method":"tools/list"'*)
+      printf '{"jsonrpc":"2.0","id":%s,"result":{"tools":[{"name":"%s","description":"Stub tool","inputSchema":{"type":"object"}}]}}\n' "$id" "$STUB_TOOL_NAME"
+      ;;
+  esac
+done
+"#;
+
+pub fn stub_server(tool_name: &str) -> ServerConfig {
+    ServerConfig {
+        server_type: ServerType::Command,
+        module_name: None,
+        package_name: None,
+        command: Some("sh".to_string()),
+        args: vec!["-c".to_string(), STUB_SERVER_SCRIPT.to_string()],
+        env: [("STUB_TOOL_NAME".to_string(), tool_name.to_string())].into(),
+        secrets: Default::default(),
+        auto_install: false,
+        config: None,
+    }
+}
@@ -1,2 +1,22 @@
+#![allow(dead_code)]
+
+use agentic_flow_lib::model::{ChatMessage, ToolCall};
+use serde_json::Value;
+
+pub mod http_server;
+pub mod mcp_stub;
 pub mod tools;
-pub mod llm_provider;
\ No newline at end of file
+
+/// A conversation of one user message.
+pub fn user_messages(text: &str) -> Vec<ChatMessage> {
+    vec![ChatMessage::user(text.to_string())]
+}
+
+/// An answer calling each tool of `calls` with its arguments, in order.
+pub fn calling(calls: &[(&str, Value)]) -> ChatMessage {
+    let calls = calls
+        .iter()
+        .map(|(name, arguments)| ToolCall::new(name.to_string(), arguments.clone()))
+        .collect();
+    ChatMessage::assistant(String::new()).with_tool_calls(calls)
+}
@@ -2,7 +2,7 @@ use agentic_flow_lib::{
     errors::AgenticFlowError,
     tool_registry::{ExecutionContext, LocalTool},
 };
-use serde_json::{json, Value};
+use serde_json::{Value, json};
 
 pub struct MockTool;
 
@@ -90,11 +90,59 @@ impl LocalTool for EchoTool {
         "Echoes the input text"
     }
 
-    async fn execute(&self, params: Value, context: &mut ExecutionContext) -> Result<Value, AgenticFlowError> {
-        let text = params.get("text").and_then(Value::as_str).ok_or_else(|| {
-            AgenticFlowError::ToolError("text".to_string())
-        })?;
+    async fn execute(
+        &self,
+        params: Value,
+        context: &mut ExecutionContext,
+    ) -> Result<Value, AgenticFlowError> {
+        let text = params
+            .get("text")
+            .and_then(Value::as_str)
+            .ok_or_else(|| AgenticFlowError::ToolError("text".to_string()))?;
         context.set("echoed_text".to_string(), json!(text));
         Ok(json!({"text": text}))
     }
 }
+
+/// Fails with a `ToolError` for the first `failures` calls, then echoes its params.
+pub struct FlakyTool {
+    pub failures: std::sync::atomic::AtomicUsize,
+}
+
+impl FlakyTool {
+    pub fn failing(times: usize) -> Self {
+        Self {
+            failures: std::sync::atomic::AtomicUsize::new(times),
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl LocalTool for FlakyTool {
+    fn name(&self) -> &str {
+        "flaky"
+    }
+
+    fn description(&self) -> &str {
+        "Fails a configured number of times before succeeding"
+    }
+
+    fn parameter_schema(&self) -> serde_json::Value {
+        json!({})
+    }
+
+    async fn execute(
+        &self,
+        params: Value,
+        _context: &mut ExecutionContext,
+    ) -> Result<Value, AgenticFlowError> {
+        use std::sync::atomic::Ordering;
+
+        let remaining = self.failures.load(Ordering::SeqCst);
+        if remaining > 0 {
+            self.failures.store(remaining - 1, Ordering::SeqCst);
+            return Err(AgenticFlowError::ToolError("flaky failure".to_string()));
+        }
+        Ok(params)
+    }
+}
@@ -0,0 +1,275 @@
+mod common;
+
+use std::sync::Arc;
+
+use serde_json::json;
+use tokio::sync::Mutex;
+
+use agentic_flow_lib::{
+    agent::{Agent, AgentConfig, FailurePolicy, RunLimits, StepRetryPolicy, SynthesisConfig},
+    config::MCPConfig,
+    errors::AgenticFlowError,
+    llm_client::{LLMClient, MockLLMProvider},
+    mcp_manager::MCPManager,
+    planner::{Executor, PlanStep},
+    tokens::estimate_messages_tokens,
+    tool_registry::{LocalTool, ToolRegistry},
+};
+
+use common::tools::{EchoTool, FlakyTool};
+
+async fn make_agent(tools: Vec<Box<dyn LocalTool>>, config: AgentConfig) -> Agent {
+    let manager = Arc::new(Mutex::new(MCPManager::new(MCPConfig::default())));
+    let mut tool_registry = ToolRegistry::new();
+    for tool in tools {
+        tool_registry.register_local_tool(tool);
+    }
+    let provider = MockLLMProvider::new().with_chat_response(None).await;
+
+    Agent::new(
+        manager,
+        Arc::new(Mutex::new(tool_registry)),
+        LLMClient::from(provider),
+    )
+    .with_config(config)
+}
+
+fn raw_context_config() -> AgentConfig {
+    AgentConfig {
+        synthesis: SynthesisConfig {
+            enabled: false,
+            ..SynthesisConfig::default()
+        },
+        ..AgentConfig::default()
+    }
+}
+
+fn step(tool_name: &str, params: serde_json::Value) -> PlanStep {
+    PlanStep::new(tool_name, params)
+}
+
+#[tokio::test]
+async fn test_retry_recovers_flaky_step() {
+    let config = AgentConfig {
+        retry: StepRetryPolicy {
+            max_attempts: 3,
+            backoff_ms: 1,
+        },
+        ..raw_context_config()
+    };
+    let agent = make_agent(vec![Box::new(FlakyTool::failing(2))], config).await;
+
+    let result = agent
+        .execute(vec![step("flaky", json!({"value": 1}))])
+        .await
+        .unwrap();
+
+    let context: serde_json::Value = serde_json::from_str(&result).unwrap();
+    assert_eq!(context["1: flaky"], json!({"value": 1}));
+}
+
+#[tokio::test]
+async fn test_abort_policy_returns_step_error() {
+    let agent = make_agent(vec![Box::new(FlakyTool::failing(1))], raw_context_config()).await;
+
+    let err = agent
+        .execute(vec![step("flaky", json!({}))])
+        .await
+        .unwrap_err();
+
+    assert!(matches!(err, AgenticFlowError::ExecutionFailed(_)));
+    assert_eq!(err.step_index(), Some(0));
+    assert_eq!(err.tool_name(), Some("flaky"));
+    assert!(matches!(err.root_cause(), AgenticFlowError::ToolError(_)));
+}
+
+#[tokio::test]
+async fn test_abort_keeps_completed_steps_and_context() {
+    let agent = make_agent(
+        vec![Box::new(EchoTool), Box::new(FlakyTool::failing(1))],
+        raw_context_config(),
+    )
+    .await;
+
+    let err = agent
+        .execute(vec![
+            step("echo", json!({"text": "first"})),
+            step("flaky", json!({"value": 2})),
+        ])
+        .await
+        .unwrap_err();
+
+    let failure = err.execution_failure().unwrap();
+    assert_eq!(failure.step_index, 1);
+    assert_eq!(failure.tool, "flaky");
+    assert_eq!(failure.params, json!({"value": 2}));
+    assert!(matches!(
+        failure.error,
+        AgenticFlowError::ToolExecutionFailed { .. }
+    ));
+    assert_eq!(failure.completed.len(), 1);
+    assert_eq!(failure.completed[0].tool, "echo");
+    assert_eq!(failure.completed[0].result, json!({"text": "first"}));
+    assert_eq!(failure.context["1: echo"], json!({"text": "first"}));
+    assert_eq!(failure.context["echoed_text"], json!("first"));
+
+    let logged = serde_json::to_value(failure).unwrap();
+    assert_eq!(logged["completed"][0]["result"], json!({"text": "first"}));
+    assert!(logged["error"].as_str().unwrap().contains("flaky failure"));
+}
+
+#[tokio::test]
+async fn test_step_ids_reach_outcomes_and_failures() {
+    let agent = make_agent(
+        vec![Box::new(EchoTool), Box::new(FlakyTool::failing(1))],
+        raw_context_config(),
+    )
+    .await;
+
+    let err = agent
+        .execute(vec![
+            step("echo", json!({"text": "first"})).with_id("greet"),
+            step("flaky", json!({})).with_id("fetch-docs"),
+        ])
+        .await
+        .unwrap_err();
+
+    let failure = err.execution_failure().unwrap();
+    assert_eq!(failure.step_id.as_deref(), Some("fetch-docs"));
+    assert_eq!(failure.completed[0].step_id.as_deref(), Some("greet"));
+}
+
+#[tokio::test]
+async fn test_continue_policy_records_error_and_runs_next_step() {
+    let config = AgentConfig {
+        on_step_failure: FailurePolicy::Continue,
+        ..raw_context_config()
+    };
+    let agent = make_agent(
+        vec![Box::new(FlakyTool::failing(1)), Box::new(EchoTool)],
+        config,
+    )
+    .await;
+
+    let result = agent
+        .execute(vec![
+            step("flaky", json!({})),
+            step("echo", json!({"text": "still running"})),
+        ])
+        .await
+        .unwrap();
+
+    let context: serde_json::Value = serde_json::from_str(&result).unwrap();
+    assert!(
+        context["1: flaky"]["error"]
+            .as_str()
+            .unwrap()
+            .contains("flaky failure")
+    );
+    assert_eq!(context["2: echo"], json!({"text": "still running"}));
+}
+
+#[tokio::test]
+async fn test_max_result_chars_truncates_results() {
+    let config = AgentConfig {
+        max_result_chars: 10,
+        ..raw_context_config()
+    };
+    let agent = make_agent(vec![Box::new(EchoTool)], config).await;
+
+    let result = agent
+        .execute(vec![step(
+            "echo",
+            json!({"text": "a fairly long piece of text"}),
+        )])
+        .await
+        .unwrap();
+
+    let context: serde_json::Value = serde_json::from_str(&result).unwrap();
+    assert_eq!(context["1: echo"], "{\"text\":\"a... [truncated]");
+}
+
+#[tokio::test]
+async fn test_tool_call_limit_counts_retries() {
+    let config = AgentConfig {
+        retry: StepRetryPolicy {
+            max_attempts: 5,
+            backoff_ms: 1,
+        },
+        run_limits: RunLimits {
+            max_tool_calls: Some(2),
+            ..RunLimits::default()
+        },
+        ..raw_context_config()
+    };
+    let agent = make_agent(vec![Box::new(FlakyTool::failing(4))], config).await;
+
+    let err = agent
+        .execute(vec![step("flaky", json!({}))])
+        .await
+        .unwrap_err();
+
+    assert!(matches!(
+        err.root_cause(),
+        AgenticFlowError::ExecutionError(_)
+    ));
+}
+
+#[tokio::test]
+async fn test_permanent_errors_are_not_retried() {
+    let config = AgentConfig {
+        retry: StepRetryPolicy {
+            max_attempts: 3,
+            backoff_ms: 1,
+        },
+        run_limits: RunLimits {
+            max_tool_calls: Some(1),
+            ..RunLimits::default()
+        },
+        ..raw_context_config()
+    };
+    let agent = make_agent(vec![], config).await;
+
+    let err = agent
+        .execute(vec![step("missing", json!({}))])
+        .await
+        .unwrap_err();
+
+    // A retry would have tripped the tool call limit instead.
+    assert!(matches!(
+        err.root_cause(),
+        AgenticFlowError::ToolNotFound { .. }
+    ));
+}
+
+#[tokio::test]
+async fn test_max_context_tokens_trims_the_synthesis_request() {
+    let mock = MockLLMProvider::new().with_chat_response(None).await;
+    let mut tool_registry = ToolRegistry::new();
+    tool_registry.register_local_tool(Box::new(EchoTool));
+    let agent = Agent::new(
+        Arc::new(Mutex::new(MCPManager::new(MCPConfig::default()))),
+        Arc::new(Mutex::new(tool_registry)),
+        LLMClient::from(mock.clone()),
+    )
+    .with_config(AgentConfig {
+        run_limits: RunLimits {
+            max_context_tokens: Some(40),
+            ..RunLimits::default()
+        },
+        ..AgentConfig::default()
+    });
+
+    agent
+        .execute(vec![step("echo", json!({"text": "word ".repeat(200)}))])
+        .await
+        .unwrap();
+
+    let messages = &mock.calls()[0].messages;
+    assert_eq!(
+        messages[0].content,
+        SynthesisConfig::default().system_prompt
+    );
+    assert!(messages[1].content.ends_with("... [truncated]"));
+    assert!(estimate_messages_tokens(messages, "") <= 40);
+}
@@ -0,0 +1,83 @@
+use std::{sync::atomic::Ordering, time::Duration};
+
+use agentic_flow_lib::{
+    errors::AgenticFlowError,
+    llm_client::{BATCH_CONCURRENCY, LLMClient, MockLLMProvider},
+    model::ChatMessage,
+};
+
+fn conversations(count: usize) -> Vec<Vec<ChatMessage>> {
+    (1..=count)
+        .map(|n| vec![ChatMessage::user(format!("candidate {}", n))])
+        .collect()
+}
+
+#[tokio::test]
+async fn test_chat_batch_keeps_input_order_and_isolates_failures() {
+    let mock = MockLLMProvider::new()
+        .with_delay_on_call(1, Duration::from_millis(100))
+        .with_failure_on_call(1, AgenticFlowError::ApiClientError("bad".to_string()))
+        .with_chat_responses(vec![
+            ChatMessage::assistant("second".to_string()),
+            ChatMessage::assistant("third".to_string()),
+        ]);
+    let client = LLMClient::from(mock.clone());
+
+    let results = client
+        .chat_completions_batch(conversations(3), vec![])
+        .await;
+
+    assert_eq!(results.len(), 3);
+    assert!(matches!(
+        results[0],
+        Err(AgenticFlowError::ApiClientError(_))
+    ));
+    let answers: Vec<&str> = results[1..]
+        .iter()
+        .map(|result| result.as_ref().unwrap().message().content.as_str())
+        .collect();
+    assert_eq!(answers, vec!["second", "third"]);
+    // The others were sent while the slow first request was pending.
+    assert_eq!(mock.max_in_flight().load(Ordering::SeqCst), 2);
+    assert_eq!(mock.calls().len(), 3);
+}
+
+#[tokio::test]
+async fn test_chat_batch_is_bounded_by_the_concurrency_limits() {
+    let mock = MockLLMProvider::new().with_chat_delay(Duration::from_millis(20));
+    let client = LLMClient::from(mock.clone());
+
+    client
+        .chat_completions_batch(conversations(BATCH_CONCURRENCY + 4), vec![])
+        .await;
+    assert_eq!(
+        mock.max_in_flight().load(Ordering::SeqCst),
+        BATCH_CONCURRENCY
+    );
+
+    let limited = MockLLMProvider::new().with_chat_delay(Duration::from_millis(20));
+    let client = LLMClient::from(limited.clone()).with_max_concurrency(2);
+
+    let results = client
+        .chat_completions_batch(conversations(6), vec![])
+        .await;
+
+    assert!(results.iter().all(Result::is_ok));
+    assert_eq!(limited.max_in_flight().load(Ordering::SeqCst), 2);
+}
+
+#[tokio::test]
+async fn test_completion_batch_returns_each_text() {
+    let mock = MockLLMProvider::new()
+        .with_completion_response(Some("scored".to_string()))
+        .await;
+    let client = LLMClient::from(mock);
+
+    let results = client
+        .completion_batch(vec!["a".to_string(), "b".to_string()])
+        .await;
+
+    let texts: Vec<String> = results.into_iter().map(Result::unwrap).collect();
+    assert_eq!(texts, vec!["scored", "scored"]);
+    assert!(client.completion_batch(vec![]).await.is_empty());
+}
@@ -0,0 +1,173 @@
+mod common;
+
+use std::sync::Arc;
+
+use serde_json::{Value, json};
+use tokio::sync::Mutex;
+
+use agentic_flow_lib::{
+    errors::AgenticFlowError,
+    llm_client::{LLMClient, MockLLMProvider},
+    model::ChatMessage,
+    planner::{BeamSearchPlanner, LLMScorer, PlanScorer, PlanStep, Planner},
+    tool_registry::ToolRegistry,
+};
+
+use common::calling;
+use common::tools::{EchoTool, MockTool};
+
+fn done() -> ChatMessage {
+    calling(&[("done", json!({}))])
+}
+
+fn planner(mock: &MockLLMProvider) -> BeamSearchPlanner {
+    let mut registry = ToolRegistry::new();
+    registry.register_local_tool(Box::new(MockTool));
+    registry.register_local_tool(Box::new(EchoTool));
+    BeamSearchPlanner::new(
+        LLMClient::from(mock.clone()),
+        Arc::new(Mutex::new(registry)),
+    )
+}
+
+/// Scores each `echo` step a point, so plans that echo more win.
+struct CountsEchoes;
+
+#[async_trait::async_trait]
+impl PlanScorer for CountsEchoes {
+    async fn score(
+        &self,
+        _task: &str,
+        steps: &[PlanStep],
+        _finished: bool,
+    ) -> Result<f64, AgenticFlowError> {
+        Ok(steps.iter().filter(|step| step.tool_name == "echo").count() as f64)
+    }
+}
+
+#[tokio::test]
+async fn test_only_the_best_partial_plans_are_extended() {
+    let mock = MockLLMProvider::new().with_chat_responses(vec![
+        // Depth 1, two proposals for the empty plan.
+        calling(&[("mock_tool", json!({"foo": "a"}))]),
+        calling(&[("echo", json!({"text": "one"}))]),
+        // Depth 2, for the `echo` plan, which scores higher, then the `mock_tool` one.
+        calling(&[("echo", json!({"text": "two"}))]),
+        done(),
+        done(),
+        done(),
+        // Depth 3, only the plan that echoes twice is left to end.
+        done(),
+        done(),
+    ]);
+
+    let steps = planner(&mock)
+        .with_beam_width(2)
+        .with_max_depth(5)
+        .with_scorer(CountsEchoes)
+        .plan("echo twice")
+        .await
+        .unwrap();
+
+    assert_eq!(
+        steps,
+        vec![
+            PlanStep::new("echo", json!({"text": "one"})),
+            PlanStep::new("echo", json!({"text": "two"})),
+        ]
+    );
+    let calls = mock.calls();
+    assert_eq!(calls.len(), 8);
+    assert!(calls[0].messages[1].content.contains("(no steps yet)"));
+    assert!(
+        calls[2].messages[1]
+            .content
+            .contains("Plan so far:\n1. echo {\"text\":\"one\"}")
+    );
+    assert!(calls[6].messages[1].content.contains("2. echo"));
+    let offered: Vec<&Value> = calls[0]
+        .tools
+        .iter()
+        .map(|tool| &tool["function"]["name"])
+        .collect();
+    assert!(offered.contains(&&json!("done")));
+}
+
+#[tokio::test]
+async fn test_finished_plans_beat_partial_ones_at_the_depth_limit() {
+    let mock = MockLLMProvider::new().with_chat_responses(vec![
+        calling(&[("echo", json!({"text": "one"}))]),
+        calling(&[("echo", json!({"text": "two"}))]),
+        // The first plan ends; the second grows past the depth limit and scores higher.
+        done(),
+        done(),
+        calling(&[("echo", json!({"text": "three"}))]),
+        calling(&[("echo", json!({"text": "four"}))]),
+    ]);
+
+    let steps = planner(&mock)
+        .with_beam_width(2)
+        .with_max_depth(2)
+        .with_scorer(CountsEchoes)
+        .plan("any task")
+        .await
+        .unwrap();
+
+    assert_eq!(steps, vec![PlanStep::new("echo", json!({"text": "one"}))]);
+    assert_eq!(mock.calls().len(), 6);
+}
+
+#[tokio::test]
+async fn test_heuristic_scorer_prefers_plans_without_repeats() {
+    let mock = MockLLMProvider::new().with_chat_responses(vec![
+        calling(&[("echo", json!({"text": "one"}))]),
+        calling(&[("echo", json!({"text": "one"}))]),
+        calling(&[("echo", json!({"text": "one"}))]),
+        calling(&[("mock_tool", json!({"foo": "a"}))]),
+    ]);
+
+    let steps = planner(&mock)
+        .with_beam_width(2)
+        .with_max_depth(2)
+        .plan("any task")
+        .await
+        .unwrap();
+
+    // Identical proposals count once, so the second depth extends a single plan.
+    assert_eq!(mock.calls().len(), 4);
+    assert_eq!(steps[1].tool_name, "mock_tool");
+}
+
+#[tokio::test]
+async fn test_a_plan_ended_before_its_first_step_does_not_win() {
+    let mock = MockLLMProvider::new().with_chat_responses(vec![
+        done(),
+        calling(&[("mock_tool", json!({"foo": "a"}))]),
+        done(),
+        done(),
+    ]);
+
+    let steps = planner(&mock)
+        .with_beam_width(2)
+        .with_max_depth(3)
+        .plan("any task")
+        .await
+        .unwrap();
+
+    assert_eq!(steps, vec![PlanStep::new("mock_tool", json!({"foo": "a"}))]);
+    assert_eq!(mock.calls().len(), 4);
+}
+
+#[tokio::test]
+async fn test_llm_scorer_reads_the_rating() {
+    let mock = MockLLMProvider::new().with_chat_responses(vec![
+        ChatMessage::assistant("7.5".to_string()),
+        ChatMessage::assistant("I cannot rate this.".to_string()),
+    ]);
+    let scorer = LLMScorer::new(LLMClient::from(mock.clone()));
+    let steps = [PlanStep::new("echo", json!({"text": "hi"}))];
+
+    assert_eq!(scorer.score("say hi", &steps, true).await.unwrap(), 7.5);
+    assert_eq!(scorer.score("say hi", &steps, false).await.unwrap(), 0.0);
+    assert!(mock.calls()[0].messages[1].content.contains("1. echo"));
+}
@@ -0,0 +1,143 @@
+mod common;
+
+use serde_json::json;
+
+use agentic_flow_lib::{
+    AgenticSystem, RunOptions,
+    errors::AgenticFlowError,
+    llm_client::{
+        Budget, CostTracker, LLMClient, MockLLMProvider, ModelPrice, PriceTable, summing_run,
+        summing_run_within,
+    },
+    model::{ChatMessage, ToolCall, Usage},
+};
+
+use common::tools::MockTool;
+use common::user_messages;
+
+async fn system(client: LLMClient) -> AgenticSystem {
+    AgenticSystem::builder()
+        .tool(MockTool)
+        .llm_client(client)
+        .build()
+        .await
+        .unwrap()
+}
+
+fn within(budget: Budget) -> RunOptions {
+    RunOptions {
+        budget: Some(budget),
+    }
+}
+
+fn mock_tool_plan() -> ChatMessage {
+    ChatMessage::assistant(String::new()).with_tool_calls(vec![ToolCall::new(
+        "mock_tool".to_string(),
+        json!({"foo": "bar"}),
+    )])
+}
+
+#[tokio::test]
+async fn test_spent_token_budget_keeps_the_executed_steps() {
+    let mock = MockLLMProvider::new()
+        .with_usage("small", 600, 400)
+        .with_chat_responses(vec![mock_tool_plan()]);
+    let system = system(LLMClient::from(mock.clone())).await;
+
+    let error = system
+        .plan_and_execute_with("any task", within(Budget::tokens(1000)))
+        .await
+        .unwrap_err();
+
+    // The planning call spent the budget, so the synthesis call was refused.
+    assert_eq!(mock.calls().len(), 1);
+    let AgenticFlowError::BudgetExceeded(overrun) = &error else {
+        panic!("expected a spent budget, got {:?}", error);
+    };
+    assert_eq!(overrun.totals.usage, Some(Usage::new(600, 400)));
+    assert_eq!(overrun.completed.len(), 1);
+    assert_eq!(overrun.completed[0].tool, "mock_tool");
+    assert!(overrun.context.contains_key("1: mock_tool"));
+    assert_eq!(
+        error.to_string(),
+        "The run exhausted its budget: 1000 of 1000 tokens"
+    );
+    assert_eq!(error.to_wire().code, "budget_exceeded");
+    assert_eq!(system.last_run_totals().responses, 1);
+}
+
+#[tokio::test]
+async fn test_budget_spent_while_planning_is_not_wrapped() {
+    let mock = MockLLMProvider::new().with_usage("small", 600, 400);
+    let system = system(LLMClient::from(mock.clone())).await;
+
+    let error = system
+        .plan_and_execute_with("any task", within(Budget::tokens(0)))
+        .await
+        .unwrap_err();
+
+    assert!(mock.calls().is_empty());
+    match error {
+        AgenticFlowError::BudgetExceeded(overrun) => assert!(overrun.completed.is_empty()),
+        error => panic!("expected a spent budget, got {:?}", error),
+    }
+    assert!(
+        system
+            .plan_and_execute_with("any task", within(Budget::tokens(5000)))
+            .await
+            .is_ok()
+    );
+}
+
+#[tokio::test]
+async fn test_cost_budget_counts_priced_models() {
+    let prices = PriceTable::from([("small".to_string(), ModelPrice::new(1.0, 2.0))]);
+    let client = LLMClient::from(MockLLMProvider::new().with_usage("small", 1000, 500))
+        .with_cost_tracker(CostTracker::with_prices(prices));
+    let unpriced = LLMClient::from(MockLLMProvider::new().with_usage("large", 1000, 500));
+
+    let (results, totals) = summing_run_within(Budget::cost_usd(0.003), async {
+        unpriced
+            .chat_completions(user_messages("hi"), vec![])
+            .await
+            .unwrap();
+        let mut results = Vec::new();
+        for _ in 0..3 {
+            results.push(
+                client
+                    .chat_completions(user_messages("hi"), vec![])
+                    .await
+                    .map(|_| ()),
+            );
+        }
+        results
+    })
+    .await;
+
+    // Each priced call costs $0.002: the second reaches the budget, the third is refused.
+    assert!(results[0].is_ok() && results[1].is_ok());
+    let Err(AgenticFlowError::BudgetExceeded(overrun)) = &results[2] else {
+        panic!("expected a spent budget, got {:?}", results[2]);
+    };
+    assert_eq!(overrun.totals.cost_usd, Some(0.004));
+    assert_eq!(totals.cost_usd, Some(0.004));
+    assert_eq!(totals.responses, 3);
+}
+
+#[tokio::test]
+async fn test_outer_budget_applies_to_inner_runs() {
+    let client = LLMClient::from(MockLLMProvider::new().with_usage("small", 5, 5));
+
+    let ((inner, first, second), outer) = summing_run_within(Budget::tokens(10), async {
+        let (first, inner) =
+            summing_run(client.chat_completions(user_messages("hi"), vec![])).await;
+        let second = client.chat_completions(user_messages("hi"), vec![]).await;
+        (inner, first, second)
+    })
+    .await;
+
+    assert!(first.is_ok());
+    assert!(matches!(second, Err(AgenticFlowError::BudgetExceeded(_))));
+    assert_eq!(inner.responses, 1);
+    assert_eq!(outer.usage, Some(Usage::new(5, 5)));
+}
@@ -0,0 +1,137 @@
+mod common;
+
+use std::{path::PathBuf, time::Duration};
+
+use agentic_flow_lib::{
+    config::{LLMConfig, ProviderKind},
+    errors::AgenticFlowError,
+    llm_client::{
+        LLMClient, LLMClientBuilder, MockLLMProvider, OpenRouterModel, OpenRouterProvider,
+        SamplingOptions,
+    },
+    model::ChatMessage,
+};
+
+use common::http_server::{MockHttpServer, MockResponse};
+
+fn response_fixture(name: &str) -> String {
+    let path = PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+        .join("tests")
+        .join("fixtures")
+        .join("responses")
+        .join(name);
+    std::fs::read_to_string(path).unwrap()
+}
+
+fn invalid_reason(result: Result<LLMClient, AgenticFlowError>) -> String {
+    match result {
+        Err(AgenticFlowError::ApiClientError(message)) => message,
+        Err(error) => panic!("unexpected error: {:?}", error),
+        Ok(_) => panic!("the client was built"),
+    }
+}
+
+#[tokio::test]
+async fn test_builder_sends_its_settings_and_headers() {
+    let body = response_fixture("openrouter_tool_calls.json");
+    let server = MockHttpServer::start(vec![MockResponse::raw(200, &body)]).await;
+    let client = LLMClient::builder()
+        .provider(
+            OpenRouterProvider::new(OpenRouterModel::Flash2)
+                .with_base_url(&server.base_url)
+                .with_api_key("sk-or-key"),
+        )
+        .temperature(0.2)
+        .max_tokens(64)
+        .timeout(Duration::from_secs(10))
+        .sampling(SamplingOptions {
+            seed: Some(7),
+            ..SamplingOptions::default()
+        })
+        .headers([("X-Team", "search"), ("X-Request-Source", "tests")])
+        .build()
+        .unwrap();
+
+    client
+        .chat_completions(vec![ChatMessage::user("hi".to_string())], vec![])
+        .await
+        .unwrap();
+
+    let request = &server.requests()[0];
+    assert_eq!(request.header("x-team"), Some("search"));
+    assert_eq!(request.header("x-request-source"), Some("tests"));
+    assert_eq!(request.header("authorization"), Some("Bearer sk-or-key"));
+    assert_eq!(request.body["temperature"].as_f64(), Some(0.2f32 as f64));
+    assert_eq!(request.body["max_tokens"], 64);
+    assert_eq!(request.body["seed"], 7);
+    assert_eq!(client.temperature(), 0.2);
+    assert_eq!(client.max_tokens(), Some(64));
+}
+
+#[test]
+fn test_builder_rejects_invalid_settings() {
+    let mock = || LLMClientBuilder::new().provider(MockLLMProvider::new());
+
+    let cases = [
+        (
+            LLMClientBuilder::new().temperature(0.5),
+            "a provider is required",
+        ),
+        (
+            mock().temperature(-0.5),
+            "temperature must be between 0.0 and 2.0, got -0.5",
+        ),
+        (
+            mock().temperature(f32::NAN),
+            "temperature must be between 0.0 and 2.0",
+        ),
+        (mock().max_tokens(0), "max_tokens must be greater than zero"),
+        (
+            mock().timeout(Duration::ZERO),
+            "timeout must be greater than zero",
+        ),
+        (
+            mock().max_concurrency(0),
+            "max_concurrency must be greater than zero",
+        ),
+        (
+            mock().sampling(SamplingOptions {
+                top_p: Some(1.5),
+                ..SamplingOptions::default()
+            }),
+            "top_p must be between 0.0 and 1.0, got 1.5",
+        ),
+        (
+            mock().headers([("X Team", "search")]),
+            "'X Team' is not a valid header name",
+        ),
+        (
+            mock().headers([("X-Team", "line\nbreak")]),
+            "the value of header 'X-Team' is not valid",
+        ),
+    ];
+
+    for (builder, expected) in cases {
+        let message = invalid_reason(builder.build());
+        assert!(message.starts_with("invalid LLM client: "), "{}", message);
+        assert!(message.contains(expected), "{}", message);
+    }
+    assert!(mock().temperature(2.0).build().is_ok());
+}
+
+#[test]
+fn test_from_config_checks_settings_like_the_builder() {
+    let config = LLMConfig {
+        provider: ProviderKind::Ollama,
+        temperature: 5.0,
+        ..LLMConfig::default()
+    };
+
+    let message = invalid_reason(LLMClient::from_config(&config));
+
+    assert!(
+        message.contains("temperature must be between 0.0 and 2.0, got 5"),
+        "{}",
+        message
+    );
+}
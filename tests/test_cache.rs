@@ -0,0 +1,158 @@
+mod common;
+
+use std::time::Duration;
+
+use agentic_flow_lib::{
+    llm_client::{CacheConfig, CacheStats, LLMClient, MockLLMProvider},
+    model::ChatMessage,
+};
+
+fn ask(question: &str) -> Vec<ChatMessage> {
+    vec![ChatMessage::user(question.to_string())]
+}
+
+fn cached_client(provider: MockLLMProvider, config: CacheConfig) -> LLMClient {
+    LLMClient::from(provider)
+        .with_temperature(0.0)
+        .with_cache(config)
+}
+
+#[tokio::test]
+async fn test_identical_request_is_answered_from_the_cache() {
+    let provider = MockLLMProvider::new()
+        .with_chat_response(Some(ChatMessage::assistant("42".to_string())))
+        .await
+        .with_usage("small", 10, 5);
+    let calls = provider.chat_calls();
+    let client = cached_client(provider, CacheConfig::default());
+
+    let first = client
+        .chat_completions(ask("answer?"), vec![])
+        .await
+        .unwrap();
+    let second = client
+        .chat_completions(ask("answer?"), vec![])
+        .await
+        .unwrap();
+
+    assert_eq!(calls.lock().unwrap().len(), 1);
+    assert_eq!(first.message().content, "42");
+    assert_eq!(second.message().content, "42");
+    assert_eq!(second.usage(), None);
+    assert_eq!(client.usage_report().calls(), 1);
+    assert_eq!(
+        client.cache_stats(),
+        Some(CacheStats { hits: 1, misses: 1 })
+    );
+}
+
+#[tokio::test]
+async fn test_different_requests_miss() {
+    let provider = MockLLMProvider::new();
+    let calls = provider.chat_calls();
+    let client = cached_client(provider, CacheConfig::default());
+
+    client.chat_completions(ask("one"), vec![]).await.unwrap();
+    client.chat_completions(ask("two"), vec![]).await.unwrap();
+    client
+        .chat_completions(ask("one"), vec![serde_json::json!({"name": "search"})])
+        .await
+        .unwrap();
+
+    assert_eq!(calls.lock().unwrap().len(), 3);
+    assert_eq!(
+        client.cache_stats(),
+        Some(CacheStats { hits: 0, misses: 3 })
+    );
+}
+
+#[tokio::test]
+async fn test_clones_share_the_cache() {
+    let provider = MockLLMProvider::new();
+    let calls = provider.chat_calls();
+    let client = cached_client(provider, CacheConfig::default());
+
+    client.chat_completions(ask("hi"), vec![]).await.unwrap();
+    client
+        .clone()
+        .chat_completions(ask("hi"), vec![])
+        .await
+        .unwrap();
+
+    assert_eq!(calls.lock().unwrap().len(), 1);
+    assert_eq!(client.cache_stats().unwrap().hits, 1);
+}
+
+#[tokio::test]
+async fn test_sampled_requests_are_cached_only_when_asked() {
+    let provider = MockLLMProvider::new();
+    let calls = provider.chat_calls();
+    let client = LLMClient::from(provider).with_cache(CacheConfig::default());
+
+    client.chat_completions(ask("hi"), vec![]).await.unwrap();
+    client.chat_completions(ask("hi"), vec![]).await.unwrap();
+    assert_eq!(calls.lock().unwrap().len(), 2);
+    assert_eq!(client.cache_stats(), Some(CacheStats::default()));
+
+    let provider = MockLLMProvider::new();
+    let calls = provider.chat_calls();
+    let client = LLMClient::from(provider).with_cache(CacheConfig {
+        cache_sampled: true,
+        ..CacheConfig::default()
+    });
+
+    client.chat_completions(ask("hi"), vec![]).await.unwrap();
+    client.chat_completions(ask("hi"), vec![]).await.unwrap();
+    assert_eq!(calls.lock().unwrap().len(), 1);
+}
+
+#[tokio::test]
+async fn test_entries_expire_after_the_ttl() {
+    let provider = MockLLMProvider::new();
+    let calls = provider.chat_calls();
+    let config = CacheConfig {
+        ttl: Some(Duration::from_millis(20)),
+        ..CacheConfig::default()
+    };
+    let client = cached_client(provider, config);
+
+    client.chat_completions(ask("hi"), vec![]).await.unwrap();
+    tokio::time::sleep(Duration::from_millis(40)).await;
+    client.chat_completions(ask("hi"), vec![]).await.unwrap();
+
+    assert_eq!(calls.lock().unwrap().len(), 2);
+}
+
+#[tokio::test]
+async fn test_least_recently_used_entry_is_evicted() {
+    let provider = MockLLMProvider::new();
+    let calls = provider.chat_calls();
+    let config = CacheConfig {
+        capacity: 2,
+        ..CacheConfig::default()
+    };
+    let client = cached_client(provider, config);
+
+    client.chat_completions(ask("one"), vec![]).await.unwrap();
+    client.chat_completions(ask("two"), vec![]).await.unwrap();
+    client.chat_completions(ask("one"), vec![]).await.unwrap();
+    client.chat_completions(ask("three"), vec![]).await.unwrap();
+    client.chat_completions(ask("one"), vec![]).await.unwrap();
+    client.chat_completions(ask("two"), vec![]).await.unwrap();
+
+    // "two" made room for "three", "one" stayed because it was used.
+    assert_eq!(calls.lock().unwrap().len(), 4);
+}
+
+#[tokio::test]
+async fn test_no_cache_by_default() {
+    let provider = MockLLMProvider::new();
+    let calls = provider.chat_calls();
+    let client = LLMClient::from(provider).with_temperature(0.0);
+
+    client.chat_completions(ask("hi"), vec![]).await.unwrap();
+    client.chat_completions(ask("hi"), vec![]).await.unwrap();
+
+    assert_eq!(calls.lock().unwrap().len(), 2);
+    assert_eq!(client.cache_stats(), None);
+}
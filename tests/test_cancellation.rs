@@ -0,0 +1,109 @@
+mod common;
+
+use std::time::{Duration, Instant};
+
+use agentic_flow_lib::{
+    errors::AgenticFlowError,
+    llm_client::{CancellationToken, LLMClient, MockLLMProvider, RequestOptions, RetryPolicy},
+};
+
+use common::user_messages;
+
+fn cancel_after(token: &CancellationToken, delay: Duration) {
+    let token = token.clone();
+    tokio::spawn(async move {
+        tokio::time::sleep(delay).await;
+        token.cancel();
+    });
+}
+
+#[tokio::test]
+async fn test_cancelling_stops_the_request_in_flight() {
+    let client = LLMClient::from(MockLLMProvider::new().with_chat_delay(Duration::from_secs(30)));
+    let token = CancellationToken::new();
+    cancel_after(&token, Duration::from_millis(20));
+    let started = Instant::now();
+
+    let result = client
+        .chat_completions_with(
+            user_messages("hi"),
+            vec![],
+            &RequestOptions::default().with_cancellation(token),
+        )
+        .await;
+
+    assert!(
+        matches!(
+            result,
+            Err(AgenticFlowError::Cancelled {
+                operation: "llm chat"
+            })
+        ),
+        "{:?}",
+        result.err()
+    );
+    assert!(started.elapsed() < Duration::from_secs(5));
+}
+
+#[tokio::test]
+async fn test_cancelling_stops_waiting_to_retry() {
+    let mock = MockLLMProvider::new().with_chat_error(AgenticFlowError::NetworkError(
+        "connection reset".to_string(),
+    ));
+    let client = LLMClient::from(mock.clone()).with_retry(RetryPolicy {
+        max_attempts: 3,
+        jitter: false,
+        base_delay: Duration::from_secs(30),
+        ..RetryPolicy::default()
+    });
+    let token = CancellationToken::new();
+    cancel_after(&token, Duration::from_millis(20));
+    let started = Instant::now();
+
+    let error = client
+        .chat_completions_with(
+            user_messages("hi"),
+            vec![],
+            &RequestOptions::default().with_cancellation(token),
+        )
+        .await
+        .err()
+        .unwrap();
+
+    assert!(
+        matches!(error, AgenticFlowError::Cancelled { .. }),
+        "{:?}",
+        error
+    );
+    assert!(!error.is_retryable());
+    assert_eq!(mock.calls().len(), 1);
+    assert!(started.elapsed() < Duration::from_secs(5));
+}
+
+#[tokio::test]
+async fn test_cancelled_token_sends_nothing() {
+    let mock = MockLLMProvider::new();
+    let client = LLMClient::from(mock.clone());
+    let token = CancellationToken::new();
+    token.cancel();
+
+    let options = RequestOptions::default().with_cancellation(token);
+    let chat = client
+        .chat_completions_with(user_messages("hi"), vec![], &options)
+        .await;
+    let completion = client.completion_with("hi".to_string(), &options).await;
+
+    assert!(matches!(
+        chat,
+        Err(AgenticFlowError::Cancelled {
+            operation: "llm chat"
+        })
+    ));
+    assert!(matches!(
+        completion,
+        Err(AgenticFlowError::Cancelled {
+            operation: "llm completion"
+        })
+    ));
+    assert!(mock.calls().is_empty());
+}
@@ -0,0 +1,128 @@
+mod common;
+
+use std::sync::Arc;
+
+use serde_json::json;
+use tokio::sync::Mutex;
+
+use agentic_flow_lib::{
+    errors::AgenticFlowError,
+    llm_client::{
+        AnthropicModel, LLMClient, MockLLMProvider, ModelCapabilities, OllamaModel, ToolEmulation,
+    },
+    planner::{MultiStepPlanner, Planner},
+    tool_registry::ToolRegistry,
+};
+use common::http_server::{MockHttpServer, MockResponse};
+use common::tools::MockTool;
+
+fn make_tool_registry() -> Arc<Mutex<ToolRegistry>> {
+    let mut registry = ToolRegistry::new();
+    registry.register_local_tool(Box::new(MockTool));
+    Arc::new(Mutex::new(registry))
+}
+
+#[test]
+fn test_built_in_capabilities_by_family_and_vendor() {
+    let qwen = ModelCapabilities::built_in("qwen3:14b").unwrap();
+    assert!(qwen.supports_tools);
+    assert_eq!(qwen.context_length, Some(40_960));
+
+    let gemma = ModelCapabilities::built_in("gemma2:2b").unwrap();
+    assert!(!gemma.supports_tools);
+
+    let mini = ModelCapabilities::built_in("openai/gpt-4o-mini").unwrap();
+    assert!(mini.supports_vision);
+    assert_eq!(mini.context_length, Some(128_000));
+
+    assert_eq!(ModelCapabilities::built_in("my-finetune:latest"), None);
+}
+
+#[test]
+fn test_client_capabilities_fall_back_to_default() {
+    let client = LLMClient::from_ollama(OllamaModel::Custom("my-finetune".to_string()));
+    assert_eq!(client.capabilities(), ModelCapabilities::default());
+
+    let client = LLMClient::from_ollama(OllamaModel::Gemma2_2b);
+    assert!(!client.capabilities().supports_tools);
+}
+
+#[test]
+fn test_with_capabilities_overrides_built_in() {
+    let capabilities = ModelCapabilities {
+        supports_tools: true,
+        context_length: Some(4_096),
+        ..ModelCapabilities::default()
+    };
+    let client = LLMClient::from_ollama(OllamaModel::Gemma2_2b).with_capabilities(capabilities);
+
+    assert_eq!(client.capabilities(), capabilities);
+}
+
+#[test]
+fn test_json_mode_needs_output_format_support() {
+    let client = LLMClient::from_anthropic_with_key(AnthropicModel::ClaudeSonnet4, "key")
+        .with_capabilities(ModelCapabilities::default());
+
+    assert!(!client.capabilities().supports_json_mode);
+}
+
+#[tokio::test]
+async fn test_multistep_planner_refuses_model_without_tools() {
+    let mock = MockLLMProvider::new();
+    let client = LLMClient::from(mock.clone())
+        .with_capabilities(ModelCapabilities {
+            supports_tools: false,
+            ..ModelCapabilities::default()
+        })
+        .with_tool_emulation(ToolEmulation::Never);
+    let planner = MultiStepPlanner::new(client, make_tool_registry());
+
+    let error = planner.plan("Say hello").await.unwrap_err();
+
+    assert!(
+        matches!(&error, AgenticFlowError::PlanningError(message) if message.contains("tool calls")),
+        "{:?}",
+        error
+    );
+    assert!(mock.calls().is_empty());
+}
+
+#[tokio::test]
+async fn test_refresh_capabilities_from_ollama_show() {
+    let server = MockHttpServer::start(vec![MockResponse::json(
+        200,
+        json!({
+            "capabilities": ["completion", "tools", "vision"],
+            "model_info": {"gemma3.context_length": 8192},
+        }),
+    )])
+    .await;
+    let client =
+        LLMClient::from_ollama_at(server.base_url.clone(), OllamaModel::Gemma3_4b).unwrap();
+    assert!(!client.capabilities().supports_tools);
+
+    let refreshed = client.refresh_capabilities().await.unwrap();
+
+    assert!(refreshed.supports_tools);
+    assert!(refreshed.supports_vision);
+    assert_eq!(refreshed.context_length, Some(8192));
+    assert_eq!(client.clone().capabilities(), refreshed);
+    let requests = server.requests();
+    assert_eq!(requests[0].path, "/api/show");
+    assert_eq!(requests[0].body["model"], "gemma3:4b");
+}
+
+#[tokio::test]
+async fn test_refresh_capabilities_unsupported_by_provider() {
+    let client = LLMClient::from(MockLLMProvider::new());
+
+    let error = client.refresh_capabilities().await.unwrap_err();
+
+    assert!(
+        matches!(error, AgenticFlowError::ApiClientError(_)),
+        "{:?}",
+        error
+    );
+    assert_eq!(client.capabilities(), ModelCapabilities::default());
+}
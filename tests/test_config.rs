@@ -0,0 +1,845 @@
+use std::path::PathBuf;
+
+use agentic_flow_lib::{
+    agent::{
+        AgentConfig, ExecutionConfig, ExecutionMode, ExecutionStrategy, FailurePolicy, RunLimits,
+        StepRetryPolicy,
+    },
+    config::{
+        ConfigFormat, LLMConfig, REDACTED, ServerConfig, ServerType, SystemConfig, is_secret_key,
+    },
+    errors::AgenticFlowError,
+    http::HttpConfig,
+    planner::{BeamScorer, MctsStrategy, PlannerConfig, PlannerKind, PlannerStrategy},
+};
+
+fn fixture(name: &str) -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+        .join("tests")
+        .join("fixtures")
+        .join(name)
+}
+
+fn assert_example_structure(config: &SystemConfig) {
+    assert_eq!(config.enabled_servers, vec!["web_search", "filesystem"]);
+    assert_eq!(config.llm_config.model, "qwen3:8b");
+    assert_eq!(config.agent_config.max_steps, 5);
+    assert_eq!(config.agent_config.timeout_seconds, 60);
+    assert_eq!(config.mcp_config.servers.len(), 2);
+
+    let web_search = &config.mcp_config.servers["web_search"];
+    assert!(matches!(web_search.server_type, ServerType::Python));
+    assert_eq!(
+        web_search.module_name.as_deref(),
+        Some("mcp_server_brave_search")
+    );
+    assert!(!web_search.auto_install);
+
+    let filesystem = &config.mcp_config.servers["filesystem"];
+    assert!(matches!(filesystem.server_type, ServerType::Node));
+    assert_eq!(
+        filesystem.package_name.as_deref(),
+        Some("@modelcontextprotocol/server-filesystem")
+    );
+    assert!(filesystem.auto_install);
+}
+
+#[test]
+fn test_load_toml_config() {
+    let config = SystemConfig::from_file(fixture("system_config.toml")).unwrap();
+    assert_example_structure(&config);
+}
+
+#[test]
+fn test_load_json_config() {
+    let config = SystemConfig::from_file(fixture("system_config.json")).unwrap();
+    assert_example_structure(&config);
+}
+
+#[cfg(feature = "yaml")]
+#[test]
+fn test_load_yaml_config() {
+    let config = SystemConfig::from_file(fixture("system_config.yaml")).unwrap();
+    assert_example_structure(&config);
+}
+
+#[test]
+fn test_round_trip_through_file() {
+    let config = SystemConfig::from_file(fixture("system_config.toml")).unwrap();
+    let path = std::env::temp_dir().join("agentic_flow_round_trip.json");
+
+    config.to_file(&path).unwrap();
+    let reloaded = SystemConfig::from_file(&path).unwrap();
+    std::fs::remove_file(&path).ok();
+
+    assert_example_structure(&reloaded);
+}
+
+#[test]
+fn test_missing_sections_use_defaults() {
+    let config = SystemConfig::parse("enabled_servers = []", ConfigFormat::Toml).unwrap();
+    let defaults = SystemConfig::default();
+
+    assert_eq!(config.llm_config.model, defaults.llm_config.model);
+    assert_eq!(
+        config.agent_config.max_steps,
+        defaults.agent_config.max_steps
+    );
+    assert!(config.mcp_config.servers.is_empty());
+}
+
+#[test]
+fn test_unknown_field_names_key_path() {
+    let contents = r#"
+        [mcp_config.servers.web_search]
+        server_type = "Python"
+        modul_name = "mcp_server_brave_search"
+    "#;
+
+    let err = SystemConfig::parse(contents, ConfigFormat::Toml).unwrap_err();
+
+    match err {
+        AgenticFlowError::ConfigError(msg) => {
+            assert!(msg.contains("mcp_config.servers.web_search"), "{}", msg);
+            assert!(msg.contains("modul_name"), "{}", msg);
+        }
+        other => panic!("unexpected error: {:?}", other),
+    }
+}
+
+#[test]
+fn test_invalid_value_names_field() {
+    let contents = r#"{"agent_config": {"max_steps": "ten"}}"#;
+
+    let err = SystemConfig::parse(contents, ConfigFormat::Json).unwrap_err();
+
+    match err {
+        AgenticFlowError::ConfigError(msg) => {
+            assert!(msg.contains("agent_config.max_steps"), "{}", msg)
+        }
+        other => panic!("unexpected error: {:?}", other),
+    }
+}
+
+#[test]
+fn test_unsupported_extension() {
+    let err = SystemConfig::from_file("config.ini").unwrap_err();
+    assert!(matches!(err, AgenticFlowError::ConfigError(_)));
+}
+
+#[test]
+fn test_env_var_expansion() {
+    // SAFETY: the variable name is unique to this test.
+    unsafe { std::env::set_var("AGENTIC_FLOW_TEST_MODEL", "qwen3:8b") };
+    let contents = r#"
+        [llm_config]
+        model = "${AGENTIC_FLOW_TEST_MODEL}"
+
+        [mcp_config.servers.web_search]
+        server_type = "Python"
+        module_name = "mcp_server_brave_search"
+        config = { endpoint = "https://${AGENTIC_FLOW_TEST_UNSET_HOST:-localhost}/api", price = "$$5" }
+    "#;
+
+    let config = SystemConfig::parse(contents, ConfigFormat::Toml).unwrap();
+
+    assert_eq!(config.llm_config.model, "qwen3:8b");
+    let server_config = config.mcp_config.servers["web_search"]
+        .config
+        .as_ref()
+        .unwrap();
+    assert_eq!(server_config["endpoint"], "https://localhost/api");
+    assert_eq!(server_config["price"], "$5");
+}
+
+#[test]
+fn test_env_var_missing_names_variable_and_path() {
+    let contents = r#"{"llm_config": {"model": "${AGENTIC_FLOW_TEST_DEFINITELY_UNSET}"}}"#;
+
+    let err = SystemConfig::parse(contents, ConfigFormat::Json).unwrap_err();
+
+    match err {
+        AgenticFlowError::ConfigError(msg) => {
+            assert!(
+                msg.contains("AGENTIC_FLOW_TEST_DEFINITELY_UNSET"),
+                "{}",
+                msg
+            );
+            assert!(msg.contains("llm_config.model"), "{}", msg);
+        }
+        other => panic!("unexpected error: {:?}", other),
+    }
+}
+
+#[test]
+fn test_overrides_take_precedence_over_file() {
+    let config = SystemConfig::from_file(fixture("system_config.toml")).unwrap();
+
+    let overridden = config
+        .apply_overrides(vec![
+            (
+                "AGENTIC_FLOW__LLM__MODEL".to_string(),
+                "gemma3:4b".to_string(),
+            ),
+            (
+                "AGENTIC_FLOW__AGENT__MAX_STEPS".to_string(),
+                "3".to_string(),
+            ),
+            (
+                "AGENTIC_FLOW__MCP__SERVERS__WEB_SEARCH__MODULE_NAME".to_string(),
+                "other_module".to_string(),
+            ),
+            ("UNRELATED".to_string(), "ignored".to_string()),
+        ])
+        .unwrap();
+
+    assert_eq!(overridden.llm_config.model, "gemma3:4b");
+    assert_eq!(overridden.agent_config.max_steps, 3);
+    // Not overridden, still comes from the file.
+    assert_eq!(overridden.agent_config.timeout_seconds, 60);
+    assert_eq!(
+        overridden.mcp_config.servers["web_search"]
+            .module_name
+            .as_deref(),
+        Some("other_module")
+    );
+}
+
+#[test]
+fn test_override_with_wrong_type_names_field() {
+    let err = SystemConfig::default()
+        .apply_overrides(vec![(
+            "AGENTIC_FLOW__AGENT__MAX_STEPS".to_string(),
+            "many".to_string(),
+        )])
+        .unwrap_err();
+
+    match err {
+        AgenticFlowError::ConfigError(msg) => {
+            assert!(msg.contains("agent_config.max_steps"), "{}", msg)
+        }
+        other => panic!("unexpected error: {:?}", other),
+    }
+}
+
+fn python_server(module_name: &str) -> ServerConfig {
+    ServerConfig {
+        server_type: ServerType::Python,
+        module_name: Some(module_name.to_string()),
+        package_name: None,
+        command: None,
+        args: vec![],
+        env: Default::default(),
+        secrets: Default::default(),
+        auto_install: false,
+        config: None,
+    }
+}
+
+#[test]
+fn test_builder_minimal() {
+    let config = SystemConfig::builder().build().unwrap();
+
+    assert!(config.mcp_config.servers.is_empty());
+    assert!(config.enabled_servers.is_empty());
+    assert_eq!(config.planner.planner_kind(), PlannerKind::MultiStep);
+}
+
+#[test]
+fn test_builder_maximal() {
+    let config = SystemConfig::builder()
+        .llm(LLMConfig {
+            model: "qwen3:8b".to_string(),
+            ..LLMConfig::default()
+        })
+        .agent(AgentConfig {
+            max_steps: 4,
+            timeout_seconds: 15,
+            ..AgentConfig::default()
+        })
+        .mcp_server("web_search", python_server("mcp_server_brave_search"))
+        .mcp_server("fetch", python_server("mcp_server_fetch"))
+        .enable_server("web_search")
+        .enable_server("fetch")
+        .enable_server("fetch")
+        .planner(PlannerKind::Mcts { simulations: 5 })
+        .build()
+        .unwrap();
+
+    assert_eq!(config.llm_config.model, "qwen3:8b");
+    assert_eq!(config.agent_config.max_steps, 4);
+    assert_eq!(config.mcp_config.servers.len(), 2);
+    assert_eq!(config.enabled_servers, vec!["web_search", "fetch"]);
+    assert_eq!(
+        config.planner.planner_kind(),
+        PlannerKind::Mcts { simulations: 5 }
+    );
+}
+
+#[test]
+fn test_builder_rejects_undefined_enabled_server() {
+    let err = SystemConfig::builder()
+        .mcp_server("web_search", python_server("mcp_server_brave_search"))
+        .enable_server("filesystem")
+        .build()
+        .unwrap_err();
+
+    match err {
+        AgenticFlowError::ConfigError(msg) => assert!(msg.contains("filesystem"), "{}", msg),
+        other => panic!("unexpected error: {:?}", other),
+    }
+}
+
+#[test]
+fn test_builder_rejects_zero_mcts_simulations() {
+    let err = SystemConfig::builder()
+        .planner(PlannerKind::Mcts { simulations: 0 })
+        .build()
+        .unwrap_err();
+
+    assert!(matches!(err, AgenticFlowError::ConfigError(_)));
+}
+
+fn server(server_type: ServerType) -> ServerConfig {
+    ServerConfig {
+        server_type,
+        module_name: None,
+        package_name: None,
+        command: None,
+        args: vec![],
+        env: Default::default(),
+        secrets: Default::default(),
+        auto_install: false,
+        config: None,
+    }
+}
+
+#[test]
+fn test_validate_accepts_default_config() {
+    assert!(SystemConfig::default().validate().is_ok());
+}
+
+#[test]
+fn test_validate_rules() {
+    type Case = (&'static str, Box<dyn Fn(&mut SystemConfig)>);
+    let cases: Vec<Case> = vec![
+        (
+            "mcp_config.servers.py.module_name",
+            Box::new(|c: &mut SystemConfig| {
+                c.mcp_config
+                    .servers
+                    .insert("py".into(), server(ServerType::Python));
+            }),
+        ),
+        (
+            "mcp_config.servers.node.package_name",
+            Box::new(|c: &mut SystemConfig| {
+                c.mcp_config
+                    .servers
+                    .insert("node".into(), server(ServerType::Node));
+            }),
+        ),
+        (
+            "mcp_config.servers.cmd.command",
+            Box::new(|c: &mut SystemConfig| {
+                c.mcp_config
+                    .servers
+                    .insert("cmd".into(), server(ServerType::Command));
+            }),
+        ),
+        (
+            "enabled_servers.0",
+            Box::new(|c: &mut SystemConfig| c.enabled_servers.push("missing".into())),
+        ),
+        (
+            "llm_config.model",
+            Box::new(|c: &mut SystemConfig| c.llm_config.model = " ".into()),
+        ),
+        (
+            "llm_config.temperature",
+            Box::new(|c: &mut SystemConfig| c.llm_config.temperature = 2.5),
+        ),
+        (
+            "llm_config.temperature",
+            Box::new(|c: &mut SystemConfig| c.llm_config.temperature = -0.1),
+        ),
+        (
+            "llm_config.max_tokens",
+            Box::new(|c: &mut SystemConfig| c.llm_config.max_tokens = Some(0)),
+        ),
+        (
+            "llm_config.timeout_seconds",
+            Box::new(|c: &mut SystemConfig| c.llm_config.timeout_seconds = Some(0)),
+        ),
+        (
+            "llm_config.http.max_response_bytes",
+            Box::new(|c: &mut SystemConfig| {
+                c.llm_config.http = Some(HttpConfig {
+                    max_response_bytes: Some(0),
+                    ..HttpConfig::default()
+                })
+            }),
+        ),
+        (
+            "agent_config.max_steps",
+            Box::new(|c: &mut SystemConfig| c.agent_config.max_steps = 0),
+        ),
+        (
+            "agent_config.timeout_seconds",
+            Box::new(|c: &mut SystemConfig| c.agent_config.timeout_seconds = 0),
+        ),
+        (
+            "planner.mcts_simulations",
+            Box::new(|c: &mut SystemConfig| {
+                c.planner = PlannerKind::Mcts { simulations: 0 }.into()
+            }),
+        ),
+        (
+            "planner.fallback.0",
+            Box::new(|c: &mut SystemConfig| c.planner.fallback = vec![PlannerStrategy::React]),
+        ),
+        (
+            "planner.beam_width",
+            Box::new(|c: &mut SystemConfig| c.planner.beam_width = Some(0)),
+        ),
+        (
+            "planner.beam_depth",
+            Box::new(|c: &mut SystemConfig| c.planner.beam_depth = Some(0)),
+        ),
+        (
+            "execution.workers",
+            Box::new(|c: &mut SystemConfig| {
+                c.execution = Some(ExecutionConfig {
+                    mode: ExecutionStrategy::Parallel,
+                    workers: Some(0),
+                })
+            }),
+        ),
+    ];
+
+    for (expected_path, mutate) in cases {
+        let mut config = SystemConfig::default();
+        mutate(&mut config);
+
+        let report = config.validate().unwrap_err();
+
+        assert_eq!(report.paths(), vec![expected_path]);
+    }
+}
+
+#[test]
+fn test_validate_aggregates_all_violations() {
+    let mut config = SystemConfig::default();
+    config
+        .mcp_config
+        .servers
+        .insert("py".into(), server(ServerType::Python));
+    config.enabled_servers.push("missing".into());
+    config.llm_config.temperature = 5.0;
+    config.agent_config.max_steps = 0;
+
+    let report = config.validate().unwrap_err();
+
+    assert_eq!(
+        report.paths(),
+        vec![
+            "mcp_config.servers.py.module_name",
+            "enabled_servers.0",
+            "llm_config.temperature",
+            "agent_config.max_steps",
+        ]
+    );
+    let message = report.to_string();
+    assert!(message.starts_with("4 config violation(s)"), "{}", message);
+}
+
+#[test]
+fn test_minimal_agent_section_uses_defaults() {
+    let contents = r#"
+        [agent_config]
+        max_steps = 3
+        timeout_seconds = 10
+    "#;
+
+    let config = SystemConfig::parse(contents, ConfigFormat::Toml).unwrap();
+    let agent = config.agent_config;
+    let defaults = AgentConfig::default();
+
+    assert_eq!(agent.max_steps, 3);
+    assert_eq!(agent.timeout_seconds, 10);
+    assert_eq!(agent.retry, defaults.retry);
+    assert_eq!(agent.on_step_failure, FailurePolicy::Abort);
+    assert_eq!(agent.synthesis, defaults.synthesis);
+    assert_eq!(agent.max_result_chars, defaults.max_result_chars);
+    assert_eq!(agent.run_limits, RunLimits::default());
+    assert_eq!(agent.execution_mode, ExecutionMode::Sequential);
+}
+
+#[test]
+fn test_full_agent_section() {
+    let contents = r#"
+        [agent_config]
+        max_steps = 8
+        timeout_seconds = 120
+        on_step_failure = "continue"
+        max_result_chars = 4000
+        execution_mode = { parallel = { workers = 4 } }
+
+        [agent_config.retry]
+        max_attempts = 3
+        backoff_ms = 250
+
+        [agent_config.synthesis]
+        enabled = false
+        system_prompt = "Summarize."
+
+        [agent_config.run_limits]
+        max_tool_calls = 20
+        max_context_chars = 16000
+        max_context_tokens = 4000
+    "#;
+
+    let agent = SystemConfig::parse(contents, ConfigFormat::Toml)
+        .unwrap()
+        .agent_config;
+
+    assert_eq!(agent.max_steps, 8);
+    assert_eq!(
+        agent.retry,
+        StepRetryPolicy {
+            max_attempts: 3,
+            backoff_ms: 250
+        }
+    );
+    assert_eq!(agent.on_step_failure, FailurePolicy::Continue);
+    assert!(!agent.synthesis.enabled);
+    assert_eq!(agent.synthesis.system_prompt, "Summarize.");
+    assert_eq!(agent.max_result_chars, 4000);
+    assert_eq!(agent.run_limits.max_tool_calls, Some(20));
+    assert_eq!(agent.run_limits.max_context_chars, Some(16000));
+    assert_eq!(agent.run_limits.max_context_tokens, Some(4000));
+    assert_eq!(agent.execution_mode, ExecutionMode::Parallel { workers: 4 });
+}
+
+#[test]
+fn test_planner_and_execution_sections() {
+    let contents = r#"
+        [planner]
+        kind = "mcts"
+        mcts_simulations = 8
+        critique_rounds = 2
+        fallback = ["cot", "multistep"]
+
+        [execution]
+        mode = "parallel"
+        workers = 3
+    "#;
+
+    let config = SystemConfig::parse(contents, ConfigFormat::Toml).unwrap();
+
+    assert_eq!(
+        config.planner,
+        PlannerConfig {
+            kind: PlannerStrategy::Mcts,
+            mcts_simulations: Some(8),
+            critique_rounds: Some(2),
+            fallback: vec![PlannerStrategy::ChainOfThought, PlannerStrategy::MultiStep],
+            ..PlannerConfig::default()
+        }
+    );
+    assert_eq!(
+        config.planner.planner_kind(),
+        PlannerKind::Mcts { simulations: 8 }
+    );
+    assert_eq!(
+        config.resolved_agent_config().execution_mode,
+        ExecutionMode::Parallel { workers: 3 }
+    );
+    assert!(config.validate().is_ok());
+    assert!(config.warnings().is_empty());
+}
+
+#[test]
+fn test_planner_section_defaults() {
+    let config = SystemConfig::parse("[planner]\nkind = \"mcts\"", ConfigFormat::Toml).unwrap();
+
+    assert_eq!(
+        config.planner.planner_kind(),
+        PlannerKind::Mcts { simulations: 5 }
+    );
+    assert_eq!(
+        config.resolved_agent_config().execution_mode,
+        ExecutionMode::Sequential
+    );
+}
+
+#[test]
+fn test_ignored_planner_and_execution_settings_warn() {
+    let contents = r#"
+        [planner]
+        kind = "htn"
+        mcts_simulations = 8
+        fallback = ["htn"]
+
+        [execution]
+        mode = "sequential"
+        workers = 2
+    "#;
+
+    let config = SystemConfig::parse(contents, ConfigFormat::Toml).unwrap();
+
+    assert!(config.validate().is_ok());
+    assert_eq!(
+        config.warnings().paths(),
+        vec![
+            "planner.mcts_simulations",
+            "planner.fallback.0",
+            "execution.workers"
+        ]
+    );
+}
+
+#[test]
+fn test_react_planner_ignores_critique_and_fallbacks() {
+    let contents = "[planner]\nkind = \"react\"\ncritique_rounds = 1\nschema_repair_rounds = 1\n\
+                    fallback = [\"multistep\"]";
+
+    let config = SystemConfig::parse(contents, ConfigFormat::Toml).unwrap();
+
+    assert_eq!(config.planner.planner_kind(), PlannerKind::React);
+    assert_eq!(
+        config.warnings().paths(),
+        vec![
+            "planner.critique_rounds",
+            "planner.schema_repair_rounds",
+            "planner.fallback"
+        ]
+    );
+}
+
+#[test]
+fn test_mcts_strategy_setting() {
+    let contents = "[planner]\nkind = \"mcts\"\nmcts_strategy = \"sampling\"";
+
+    let config = SystemConfig::parse(contents, ConfigFormat::Toml).unwrap();
+
+    assert_eq!(
+        config.planner.planner_kind(),
+        PlannerKind::MctsSampling { simulations: 5 }
+    );
+    assert_eq!(
+        PlannerConfig::from(PlannerKind::MctsSampling { simulations: 5 }).mcts_strategy,
+        Some(MctsStrategy::Sampling)
+    );
+
+    let ignored =
+        SystemConfig::parse("[planner]\nmcts_strategy = \"tree\"", ConfigFormat::Toml).unwrap();
+    assert_eq!(ignored.warnings().paths(), vec!["planner.mcts_strategy"]);
+}
+
+#[test]
+fn test_beam_planner_settings() {
+    let contents = "[planner]\nkind = \"beam\"\nbeam_width = 2\nbeam_scorer = \"llm\"";
+
+    let config = SystemConfig::parse(contents, ConfigFormat::Toml).unwrap();
+
+    assert_eq!(
+        config.planner.planner_kind(),
+        PlannerKind::BeamSearch {
+            width: 2,
+            depth: 8,
+            scorer: BeamScorer::Llm
+        }
+    );
+    assert!(config.warnings().is_empty());
+
+    let ignored = SystemConfig::parse(
+        "[planner]\nkind = \"htn\"\nbeam_depth = 4\nbeam_scorer = \"heuristic\"",
+        ConfigFormat::Toml,
+    )
+    .unwrap();
+    assert_eq!(
+        ignored.warnings().paths(),
+        vec!["planner.beam_depth", "planner.beam_scorer"]
+    );
+}
+
+#[test]
+fn test_ollama_base_url_is_checked() {
+    let malformed =
+        SystemConfig::parse("[llm_config]\nbase_url = \"http://\"", ConfigFormat::Toml).unwrap();
+    assert_eq!(
+        malformed.validate().unwrap_err().paths(),
+        vec!["llm_config.base_url"]
+    );
+
+    let ignored = SystemConfig::parse(
+        "[llm_config]\nprovider = \"openai\"\nmodel = \"gpt-4o\"\nbase_url = \"localhost:1234\"",
+        ConfigFormat::Toml,
+    )
+    .unwrap();
+    assert!(ignored.validate().is_ok());
+    assert_eq!(ignored.warnings().paths(), vec!["llm_config.base_url"]);
+}
+
+#[test]
+fn test_app_attribution_is_only_for_open_router() {
+    let open_router = SystemConfig::parse(
+        "[llm_config]\nprovider = \"open_router\"\nmodel = \"x\"\napp_url = \"https://example.com\"\napp_name = \"Example\"",
+        ConfigFormat::Toml,
+    )
+    .unwrap();
+    assert_eq!(open_router.llm_config.app_name.as_deref(), Some("Example"));
+    assert!(open_router.warnings().is_empty());
+
+    let ignored = SystemConfig::parse(
+        "[llm_config]\nprovider = \"openai\"\nmodel = \"gpt-4o\"\napp_name = \"Example\"",
+        ConfigFormat::Toml,
+    )
+    .unwrap();
+    assert_eq!(ignored.warnings().paths(), vec!["llm_config.app_name"]);
+}
+
+#[test]
+fn test_embedding_model_is_only_for_providers_with_embeddings() {
+    let ollama = SystemConfig::parse(
+        "[llm_config]\nembedding_model = \"nomic-embed-text\"",
+        ConfigFormat::Toml,
+    )
+    .unwrap();
+    assert!(ollama.warnings().is_empty());
+
+    let ignored = SystemConfig::parse(
+        "[llm_config]\nprovider = \"anthropic\"\nmodel = \"claude\"\nembedding_model = \"x\"",
+        ConfigFormat::Toml,
+    )
+    .unwrap();
+    assert_eq!(
+        ignored.warnings().paths(),
+        vec!["llm_config.embedding_model"]
+    );
+}
+
+#[test]
+fn test_ollama_options_are_only_for_ollama() {
+    let ollama = SystemConfig::parse(
+        "[llm_config.ollama]\nnum_ctx = 16384\nkeep_alive = \"10m\"",
+        ConfigFormat::Toml,
+    )
+    .unwrap();
+    let options = ollama.llm_config.ollama.as_ref().unwrap();
+    assert_eq!(options.num_ctx, Some(16384));
+    assert_eq!(options.keep_alive.as_deref(), Some("10m"));
+    assert!(ollama.warnings().is_empty());
+
+    let ignored = SystemConfig::parse(
+        "[llm_config]\nprovider = \"openai\"\nmodel = \"gpt-4o\"\n[llm_config.ollama]\nnum_ctx = 1",
+        ConfigFormat::Toml,
+    )
+    .unwrap();
+    assert_eq!(ignored.warnings().paths(), vec!["llm_config.ollama"]);
+}
+
+#[test]
+fn test_verify_on_startup_is_only_for_ollama() {
+    let ollama =
+        SystemConfig::parse("[llm_config]\nverify_on_startup = true", ConfigFormat::Toml).unwrap();
+    assert!(ollama.llm_config.verify_on_startup);
+    assert!(ollama.warnings().is_empty());
+
+    let ignored = SystemConfig::parse(
+        "[llm_config]\nprovider = \"groq\"\nmodel = \"llama\"\nverify_on_startup = true",
+        ConfigFormat::Toml,
+    )
+    .unwrap();
+    assert_eq!(
+        ignored.warnings().paths(),
+        vec!["llm_config.verify_on_startup"]
+    );
+}
+
+#[test]
+fn test_http_config_is_read_and_checked() {
+    let config = SystemConfig::parse(
+        "[llm_config.http]\nproxy = \"http://proxy.internal:3128\"\nconnect_timeout_seconds = 5\n",
+        ConfigFormat::Toml,
+    )
+    .unwrap();
+    let http = config.llm_config.http.as_ref().unwrap();
+    assert_eq!(http.proxy.as_deref(), Some("http://proxy.internal:3128"));
+    assert_eq!(http.connect_timeout_seconds, Some(5));
+    assert!(config.warnings().is_empty());
+
+    let insecure = SystemConfig::parse(
+        "[llm_config.http]\ndanger_accept_invalid_certs = true",
+        ConfigFormat::Toml,
+    )
+    .unwrap();
+    assert_eq!(
+        insecure.warnings().paths(),
+        vec!["llm_config.http.danger_accept_invalid_certs"]
+    );
+}
+
+#[test]
+fn test_to_value_round_trip() {
+    let config = SystemConfig::from_file(fixture("system_config.toml")).unwrap();
+
+    let value = config.to_value().unwrap();
+    let restored: SystemConfig = serde_json::from_value(value).unwrap();
+
+    assert_eq!(restored, config);
+}
+
+#[test]
+fn test_debug_masks_secrets() {
+    let mut server = server(ServerType::Command);
+    server.command = Some("github-mcp-server".to_string());
+    server.env = [
+        (
+            "GITHUB_PERSONAL_ACCESS_TOKEN".to_string(),
+            "ghp_very_secret".to_string(),
+        ),
+        ("LOG_LEVEL".to_string(), "debug".to_string()),
+    ]
+    .into();
+    server.config = Some(serde_json::json!({ "auth": { "apiKey": "sk-nested" }, "region": "eu" }));
+    let config = SystemConfig::builder()
+        .mcp_server("github", server)
+        .llm(LLMConfig {
+            api_key_env: Some("OPENROUTER_API_KEY".to_string()),
+            max_tokens: Some(512),
+            ..LLMConfig::default()
+        })
+        .build()
+        .unwrap();
+
+    let debug = format!("{:?}", config);
+    assert!(!debug.contains("ghp_very_secret"), "{}", debug);
+    assert!(!debug.contains("sk-nested"), "{}", debug);
+    assert!(debug.contains(REDACTED));
+    assert!(debug.contains("\"debug\""));
+    assert!(debug.contains("OPENROUTER_API_KEY"));
+
+    let redacted = config.to_redacted_value().unwrap().to_string();
+    assert!(!redacted.contains("ghp_very_secret"), "{}", redacted);
+    assert!(!redacted.contains("sk-nested"), "{}", redacted);
+    assert!(redacted.contains("\"max_tokens\":512"), "{}", redacted);
+    assert!(redacted.contains("OPENROUTER_API_KEY"), "{}", redacted);
+}
+
+#[test]
+fn test_secret_key_detection() {
+    for key in [
+        "GITHUB_TOKEN",
+        "api_key",
+        "clientSecret",
+        "DB_PASSWORD",
+        "Authorization",
+    ] {
+        assert!(is_secret_key(key), "{}", key);
+    }
+    for key in ["max_tokens", "LOG_LEVEL", "api_key_env", "keyboard_layout"] {
+        assert!(!is_secret_key(key), "{}", key);
+    }
+}
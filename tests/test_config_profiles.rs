@@ -0,0 +1,111 @@
+use std::path::PathBuf;
+
+use agentic_flow_lib::{
+    config::{ConfigFormat, PROFILE_ENV_VAR, ProfileOverrides, SystemConfig},
+    errors::AgenticFlowError,
+};
+
+fn fixture_path() -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+        .join("tests")
+        .join("fixtures")
+        .join("profiles.toml")
+}
+
+#[test]
+fn test_base_config_ignores_profiles() {
+    let contents = std::fs::read_to_string(fixture_path()).unwrap();
+    let config = SystemConfig::parse(&contents, ConfigFormat::Toml).unwrap();
+
+    assert_eq!(config.llm_config.model, "qwen3:8b");
+    assert_eq!(config.enabled_servers, vec!["web_search"]);
+}
+
+#[test]
+fn test_profile_overrides_scalars() {
+    let config = SystemConfig::from_file_with_profile(fixture_path(), "prod").unwrap();
+
+    assert_eq!(config.llm_config.model, "gpt-oss:20b");
+    assert_eq!(config.agent_config.max_steps, 4);
+    // Siblings of overridden keys keep their base values.
+    assert_eq!(config.llm_config.timeout_seconds, Some(30));
+    assert_eq!(config.agent_config.timeout_seconds, 30);
+}
+
+#[test]
+fn test_profile_deep_merges_maps() {
+    let config = SystemConfig::from_file_with_profile(fixture_path(), "prod").unwrap();
+
+    let web_search = &config.mcp_config.servers["web_search"];
+    assert_eq!(web_search.env["LOG_LEVEL"], "warn");
+    assert_eq!(
+        web_search.module_name.as_deref(),
+        Some("mcp_server_brave_search")
+    );
+    assert_eq!(config.mcp_config.servers.len(), 2);
+}
+
+#[test]
+fn test_profile_replaces_lists() {
+    let config = SystemConfig::from_file_with_profile(fixture_path(), "prod").unwrap();
+
+    assert_eq!(config.enabled_servers, vec!["filesystem"]);
+}
+
+#[test]
+fn test_profile_appends_lists() {
+    let config = SystemConfig::from_file_with_profile(fixture_path(), "staging").unwrap();
+
+    assert_eq!(config.enabled_servers, vec!["web_search", "filesystem"]);
+    assert_eq!(config.llm_config.timeout_seconds, Some(60));
+    assert_eq!(config.llm_config.model, "qwen3:8b");
+}
+
+#[test]
+fn test_unknown_profile_lists_available_ones() {
+    let err = SystemConfig::from_file_with_profile(fixture_path(), "qa").unwrap_err();
+
+    match err {
+        AgenticFlowError::ConfigError(message) => {
+            assert!(message.contains("'qa'"), "{}", message);
+            assert!(message.contains("prod, staging"), "{}", message);
+        }
+        other => panic!("unexpected error: {:?}", other),
+    }
+}
+
+#[test]
+fn test_profile_overrides_report() {
+    let overrides = SystemConfig::profile_overrides(fixture_path()).unwrap();
+
+    assert_eq!(
+        overrides,
+        vec![
+            ProfileOverrides {
+                profile: "prod".to_string(),
+                keys: vec![
+                    "agent_config.max_steps".to_string(),
+                    "enabled_servers".to_string(),
+                    "llm_config.model".to_string(),
+                    "mcp_config.servers.web_search.env.LOG_LEVEL".to_string(),
+                ],
+            },
+            ProfileOverrides {
+                profile: "staging".to_string(),
+                keys: vec![
+                    "+enabled_servers".to_string(),
+                    "llm_config.timeout_seconds".to_string(),
+                ],
+            },
+        ]
+    );
+}
+
+#[test]
+fn test_profile_selected_from_env() {
+    unsafe { std::env::set_var(PROFILE_ENV_VAR, "prod") };
+    let config = SystemConfig::from_file(fixture_path());
+    unsafe { std::env::remove_var(PROFILE_ENV_VAR) };
+
+    assert_eq!(config.unwrap().llm_config.model, "gpt-oss:20b");
+}
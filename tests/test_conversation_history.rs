@@ -0,0 +1,133 @@
+use agentic_flow_lib::{
+    errors::AgenticFlowError,
+    llm_client::{LLMClient, MockLLMProvider},
+    model::{ChatMessage, ConversationHistory, RetentionStrategy, Role, ToolCall},
+    tokens::estimate_messages_tokens,
+};
+use serde_json::json;
+
+fn contents(history: &ConversationHistory) -> Vec<String> {
+    history
+        .as_messages()
+        .into_iter()
+        .map(|message| message.content)
+        .collect()
+}
+
+#[test]
+fn test_as_messages_starts_with_the_system_prompt() {
+    let mut history = ConversationHistory::new("Be brief.");
+    history.push_user("hi");
+    history.push_assistant("hello");
+
+    let messages = history.as_messages();
+
+    assert_eq!(messages[0].role, Role::System);
+    assert_eq!(contents(&history), vec!["Be brief.", "hi", "hello"]);
+    assert_eq!(history.len(), 2);
+}
+
+#[test]
+fn test_max_messages_keeps_the_latest_turns() {
+    let mut history =
+        ConversationHistory::new("Be brief.").with_retention(RetentionStrategy::MaxMessages(3));
+    for turn in 1..=5 {
+        history.push_user(format!("question {}", turn));
+    }
+
+    assert_eq!(
+        contents(&history),
+        vec!["Be brief.", "question 3", "question 4", "question 5"]
+    );
+}
+
+#[test]
+fn test_window_drops_tool_results_left_without_their_call() {
+    let mut history =
+        ConversationHistory::new("Use tools.").with_retention(RetentionStrategy::MaxMessages(2));
+    history.push_user("weather?");
+    history.push(ChatMessage::assistant(String::new()).with_tool_calls(vec![
+        ToolCall::new("weather".to_string(), json!({})).with_id("call_1".to_string()),
+    ]));
+    history.push_tool("sunny", "call_1");
+    history.push_assistant("It is sunny.");
+
+    assert_eq!(contents(&history), vec!["Use tools.", "It is sunny."]);
+}
+
+#[test]
+fn test_max_tokens_keeps_the_history_within_the_budget() {
+    let mut history =
+        ConversationHistory::new("Be brief.").with_retention(RetentionStrategy::MaxTokens(40));
+    for _ in 0..10 {
+        history.push_user("a question of several words");
+        history.push_assistant("an answer of several words");
+    }
+
+    let messages = history.as_messages();
+    assert!(estimate_messages_tokens(&messages, "") <= 40);
+    assert_eq!(messages[0].content, "Be brief.");
+    assert_eq!(
+        messages.last().unwrap().content,
+        "an answer of several words"
+    );
+    assert!(history.len() < 20);
+}
+
+#[tokio::test]
+async fn test_summarize_replaces_evicted_turns_with_one_summary() {
+    let mock = MockLLMProvider::new()
+        .with_chat_response(Some(ChatMessage::assistant(
+            "The user asked about Paris.".to_string(),
+        )))
+        .await;
+    let mut history =
+        ConversationHistory::new("Be brief.").with_retention(RetentionStrategy::Summarize {
+            client: Box::new(LLMClient::from(mock.clone())),
+            max_messages: 4,
+            keep_recent: 2,
+        });
+    for turn in 1..=5 {
+        history.push_user(format!("question {}", turn));
+    }
+
+    history.compact().await.unwrap();
+
+    assert_eq!(
+        contents(&history),
+        vec![
+            "Be brief.",
+            "Summary of the conversation so far: The user asked about Paris.",
+            "question 4",
+            "question 5",
+        ]
+    );
+    assert_eq!(history.turns()[0].role, Role::Assistant);
+    let calls = mock.calls();
+    assert_eq!(calls.len(), 1);
+    assert_eq!(
+        calls[0].messages[1].content,
+        "user: question 1\nuser: question 2\nuser: question 3"
+    );
+
+    // Below the limit nothing is sent.
+    history.compact().await.unwrap();
+    assert_eq!(mock.calls().len(), 1);
+}
+
+#[tokio::test]
+async fn test_failed_summary_keeps_the_turns() {
+    let mock = MockLLMProvider::new()
+        .with_chat_error(AgenticFlowError::ApiClientError("down".to_string()));
+    let mut history =
+        ConversationHistory::new("Be brief.").with_retention(RetentionStrategy::Summarize {
+            client: Box::new(LLMClient::from(mock)),
+            max_messages: 1,
+            keep_recent: 1,
+        });
+    history.push_user("first");
+    history.push_user("second");
+
+    assert!(history.compact().await.is_err());
+    assert_eq!(contents(&history), vec!["Be brief.", "first", "second"]);
+}
@@ -0,0 +1,121 @@
+mod common;
+
+use std::sync::Arc;
+
+use futures::future::join_all;
+use tokio::sync::Mutex;
+
+use agentic_flow_lib::{
+    AgenticSystem,
+    llm_client::{CostTracker, LLMClient, MockLLMProvider, ModelPrice, PriceTable},
+    model::Usage,
+    planner::{MctsStrategy, MonteCarloTreeSearchPlanner, Planner},
+    tool_registry::ToolRegistry,
+};
+
+use common::tools::MockTool;
+use common::user_messages;
+
+fn prices() -> PriceTable {
+    PriceTable::from([("small".to_string(), ModelPrice::new(1.0, 2.0))])
+}
+
+#[tokio::test]
+async fn test_usage_report_by_model() {
+    let tracker = CostTracker::with_prices(prices());
+    let small = LLMClient::from(MockLLMProvider::new().with_usage("small", 1000, 500))
+        .with_cost_tracker(tracker.clone());
+    let large = LLMClient::from(MockLLMProvider::new().with_usage("large", 10, 20))
+        .with_cost_tracker(tracker.clone());
+
+    small
+        .chat_completions(user_messages("hi"), vec![])
+        .await
+        .unwrap();
+    small
+        .chat_completions(user_messages("hi"), vec![])
+        .await
+        .unwrap();
+    large
+        .chat_completions(user_messages("hi"), vec![])
+        .await
+        .unwrap();
+
+    let report = tracker.report();
+    assert_eq!(report, small.usage_report());
+    assert_eq!(report.calls(), 3);
+    assert_eq!(report.models["small"].usage, Some(Usage::new(2000, 1000)));
+    // 2000 prompt tokens at $1 and 1000 completion tokens at $2 per million.
+    assert_eq!(report.models["small"].cost_usd, Some(0.004));
+    assert_eq!(report.models["large"].calls, 1);
+    assert_eq!(report.models["large"].cost_usd, None);
+    assert_eq!(report.total_usage(), Some(Usage::new(2010, 1020)));
+    assert_eq!(report.total_cost_usd(), Some(0.004));
+}
+
+#[tokio::test]
+async fn test_calls_without_usage_are_counted() {
+    let client = LLMClient::from(MockLLMProvider::new());
+
+    client
+        .chat_completions(user_messages("hi"), vec![])
+        .await
+        .unwrap();
+
+    let report = client.usage_report();
+    assert_eq!(report.calls(), 1);
+    assert_eq!(report.total_usage(), None);
+    assert_eq!(report.total_cost_usd(), None);
+}
+
+#[tokio::test]
+async fn test_concurrent_clones_share_the_tracker() {
+    let client = LLMClient::from(MockLLMProvider::new().with_usage("small", 10, 5))
+        .with_cost_tracker(CostTracker::with_prices(prices()));
+
+    let calls = (0..20).map(|index| {
+        let client = client.clone().with_temperature(index as f32 / 20.0);
+        async move {
+            client
+                .chat_completions(user_messages("hi"), vec![])
+                .await
+                .unwrap()
+        }
+    });
+    join_all(calls).await;
+
+    let report = client.usage_report();
+    assert_eq!(report.calls(), 20);
+    assert_eq!(report.total_usage(), Some(Usage::new(200, 100)));
+}
+
+#[tokio::test]
+async fn test_mcts_simulations_add_to_the_report() {
+    let client = LLMClient::from(MockLLMProvider::new().with_usage("small", 10, 5));
+    let mut registry = ToolRegistry::new();
+    registry.register_local_tool(Box::new(MockTool));
+    let planner =
+        MonteCarloTreeSearchPlanner::new(client.clone(), Arc::new(Mutex::new(registry)), 4)
+            .with_strategy(MctsStrategy::Sampling);
+
+    planner.plan("do the thing").await.unwrap();
+
+    assert_eq!(client.usage_report().calls(), 4);
+}
+
+#[tokio::test]
+async fn test_system_usage_report() {
+    let client = LLMClient::from(MockLLMProvider::new().with_usage("small", 10, 5));
+    let system = AgenticSystem::builder()
+        .tool(MockTool)
+        .llm_client(client.clone())
+        .build()
+        .await
+        .unwrap();
+
+    system.plan_and_execute("any task").await.unwrap();
+
+    // One planning call and one synthesis call.
+    assert_eq!(system.usage_report().calls(), 2);
+    assert_eq!(system.usage_report(), client.usage_report());
+}
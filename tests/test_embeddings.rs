@@ -0,0 +1,146 @@
+mod common;
+
+use serde_json::json;
+
+use agentic_flow_lib::{
+    errors::AgenticFlowError,
+    llm_client::{
+        LLMClient, MockLLMProvider, OllamaModel, OllamaProvider, OpenRouterModel,
+        OpenRouterProvider,
+    },
+};
+
+use common::http_server::{MockHttpServer, MockResponse};
+
+fn inputs(count: usize) -> Vec<String> {
+    (0..count).map(|index| format!("input {}", index)).collect()
+}
+
+fn ollama_embeddings(count: usize) -> MockResponse {
+    let embeddings: Vec<Vec<f32>> = (0..count).map(|index| vec![index as f32, 1.0]).collect();
+    MockResponse::json(
+        200,
+        json!({ "model": "nomic-embed-text", "embeddings": embeddings }),
+    )
+}
+
+fn ollama_at(server: &MockHttpServer) -> OllamaProvider {
+    OllamaProvider::with_client(
+        reqwest::Client::new(),
+        OllamaModel::Qwen3_8B,
+        server.base_url.clone(),
+    )
+}
+
+#[tokio::test]
+async fn test_ollama_embeds_with_the_embedding_model() {
+    let server = MockHttpServer::start(vec![ollama_embeddings(2)]).await;
+    let client = LLMClient::from(
+        ollama_at(&server)
+            .with_embedding_model(OllamaModel::Custom("nomic-embed-text".to_string())),
+    );
+
+    let vectors = client.embed(inputs(2)).await.unwrap();
+
+    assert_eq!(vectors, vec![vec![0.0, 1.0], vec![1.0, 1.0]]);
+    let requests = server.requests();
+    assert_eq!(requests[0].path, "/api/embed");
+    assert_eq!(
+        requests[0].body,
+        json!({ "model": "nomic-embed-text", "input": ["input 0", "input 1"] })
+    );
+}
+
+#[tokio::test]
+async fn test_ollama_embeds_with_the_chat_model_by_default() {
+    let server = MockHttpServer::start(vec![ollama_embeddings(1)]).await;
+    let client = LLMClient::from(ollama_at(&server));
+
+    client.embed(inputs(1)).await.unwrap();
+
+    assert_eq!(server.requests()[0].body["model"], json!("qwen3:8b"));
+}
+
+#[tokio::test]
+async fn test_many_inputs_are_sent_in_batches() {
+    // 500 inputs in batches of 64: seven full batches and one of 52.
+    let mut responses = vec![ollama_embeddings(64); 7];
+    responses.push(ollama_embeddings(52));
+    let server = MockHttpServer::start(responses).await;
+    let client = LLMClient::from(ollama_at(&server));
+
+    let vectors = client.embed(inputs(500)).await.unwrap();
+
+    assert_eq!(vectors.len(), 500);
+    let requests = server.requests();
+    assert_eq!(requests.len(), 8);
+    assert_eq!(requests[0].body["input"][0], json!("input 0"));
+    assert_eq!(requests[1].body["input"][0], json!("input 64"));
+    assert_eq!(requests[7].body["input"].as_array().unwrap().len(), 52);
+    assert_eq!(requests[7].body["input"][51], json!("input 499"));
+}
+
+#[tokio::test]
+async fn test_open_router_embeddings_are_in_input_order() {
+    let server = MockHttpServer::start(vec![MockResponse::json(
+        200,
+        json!({
+            "object": "list",
+            "model": "openai/text-embedding-3-small",
+            "data": [
+                { "object": "embedding", "index": 1, "embedding": [0.5, 0.5] },
+                { "object": "embedding", "index": 0, "embedding": [0.1, 0.9] }
+            ],
+            "usage": { "prompt_tokens": 4, "total_tokens": 4 }
+        }),
+    )])
+    .await;
+    let client = LLMClient::from(
+        OpenRouterProvider::new(OpenRouterModel::GPTMini)
+            .with_base_url(&server.base_url)
+            .with_api_key("test-key")
+            .with_embedding_model(OpenRouterModel::Custom(
+                "openai/text-embedding-3-small".to_string(),
+            )),
+    );
+
+    let vectors = client.embed(inputs(2)).await.unwrap();
+
+    assert_eq!(vectors, vec![vec![0.1, 0.9], vec![0.5, 0.5]]);
+    let requests = server.requests();
+    assert_eq!(requests[0].path, "/embeddings");
+    assert_eq!(
+        requests[0].body["model"],
+        json!("openai/text-embedding-3-small")
+    );
+    assert_eq!(requests[0].header("authorization"), Some("Bearer test-key"));
+}
+
+#[tokio::test]
+async fn test_a_wrong_number_of_vectors_fails() {
+    let server = MockHttpServer::start(vec![ollama_embeddings(1)]).await;
+    let client = LLMClient::from(ollama_at(&server));
+
+    let error = client.embed(inputs(2)).await.err().unwrap();
+
+    assert!(
+        matches!(&error, AgenticFlowError::ParseError(message)
+            if message == "expected 2 embeddings, got 1"),
+        "{:?}",
+        error
+    );
+}
+
+#[tokio::test]
+async fn test_providers_without_embeddings_fail() {
+    let client = LLMClient::from(MockLLMProvider::new());
+
+    let error = client.embed(inputs(1)).await.err().unwrap();
+
+    assert!(
+        matches!(&error, AgenticFlowError::ApiClientError(message)
+            if message == "embeddings not supported"),
+        "{:?}",
+        error
+    );
+}
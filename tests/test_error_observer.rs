@@ -0,0 +1,160 @@
+mod common;
+
+use std::sync::{Arc, Mutex};
+
+use serde_json::json;
+
+use agentic_flow_lib::{
+    AgenticSystem,
+    agent::Agent,
+    config::MCPConfig,
+    errors::AgenticFlowError,
+    llm_client::{LLMClient, MockLLMProvider},
+    mcp_manager::MCPManager,
+    model::{ChatMessage, ToolCall},
+    observer::{ErrorContext, ErrorObserver},
+    planner::PlanStep,
+    tool_registry::ToolRegistry,
+    worker::AgenticTaskPool,
+};
+
+use common::tools::FlakyTool;
+
+/// Keeps the display message and context of every reported error.
+#[derive(Default)]
+struct RecordingObserver {
+    seen: Mutex<Vec<(String, ErrorContext)>>,
+}
+
+impl RecordingObserver {
+    fn seen(&self) -> Vec<(String, ErrorContext)> {
+        self.seen.lock().unwrap().clone()
+    }
+}
+
+impl ErrorObserver for RecordingObserver {
+    fn on_error(&self, error: &AgenticFlowError, context: &ErrorContext) {
+        self.seen
+            .lock()
+            .unwrap()
+            .push((error.to_string(), context.clone()));
+    }
+}
+
+struct PanickingObserver;
+
+impl ErrorObserver for PanickingObserver {
+    fn on_error(&self, _error: &AgenticFlowError, _context: &ErrorContext) {
+        panic!("observer failed");
+    }
+}
+
+async fn plan_calling_flaky() -> LLMClient {
+    let response = ChatMessage::assistant("".to_string())
+        .with_tool_calls(vec![ToolCall::new("flaky".to_string(), json!({}))]);
+    LLMClient::from(
+        MockLLMProvider::new()
+            .with_chat_response(Some(response))
+            .await,
+    )
+}
+
+#[tokio::test]
+async fn test_observer_sees_tool_failure() {
+    let observer = Arc::new(RecordingObserver::default());
+    let system = AgenticSystem::builder()
+        .tool(FlakyTool::failing(1))
+        .llm_client(plan_calling_flaky().await)
+        .error_observer(observer.clone())
+        .build()
+        .await
+        .unwrap();
+
+    let error = system.plan_and_execute("do the thing").await.unwrap_err();
+
+    assert_eq!(error.tool_name(), Some("flaky"));
+    let seen = observer.seen();
+    assert_eq!(seen.len(), 1, "{:?}", seen);
+    let (message, context) = &seen[0];
+    assert!(message.contains("flaky failure"), "{}", message);
+    assert_eq!(
+        *context,
+        ErrorContext {
+            component: "step",
+            run_id: Some("1".to_string()),
+            tool: Some("flaky".to_string()),
+            server: None,
+        }
+    );
+}
+
+#[tokio::test]
+async fn test_observer_sees_llm_failure() {
+    let observer = Arc::new(RecordingObserver::default());
+    let provider = MockLLMProvider::new().with_chat_error(AgenticFlowError::NetworkError(
+        "connection refused".to_string(),
+    ));
+    let system = AgenticSystem::builder()
+        .llm_client(LLMClient::from(provider))
+        .error_observer(observer.clone())
+        .build()
+        .await
+        .unwrap();
+
+    system.plan_and_execute("first").await.unwrap_err();
+    let error = system.plan_and_execute("second").await.unwrap_err();
+
+    assert!(matches!(
+        error.root_cause(),
+        AgenticFlowError::NetworkError(_)
+    ));
+    let contexts: Vec<ErrorContext> = observer.seen().into_iter().map(|(_, c)| c).collect();
+    let llm = |run_id: &str| ErrorContext {
+        component: "llm",
+        run_id: Some(run_id.to_string()),
+        tool: None,
+        server: None,
+    };
+    assert_eq!(contexts, vec![llm("1"), llm("2")]);
+}
+
+#[tokio::test]
+async fn test_panicking_observer_leaves_error_unchanged() {
+    let system = AgenticSystem::builder()
+        .tool(FlakyTool::failing(1))
+        .llm_client(plan_calling_flaky().await)
+        .error_observer(Arc::new(PanickingObserver))
+        .build()
+        .await
+        .unwrap();
+
+    let error = system.plan_and_execute("do the thing").await.unwrap_err();
+
+    assert!(error.execution_failure().is_some(), "{:?}", error);
+}
+
+#[tokio::test]
+async fn test_pool_reports_failed_steps() {
+    let observer = Arc::new(RecordingObserver::default());
+    let agent = Agent::new(
+        Arc::new(tokio::sync::Mutex::new(MCPManager::new(
+            MCPConfig::default(),
+        ))),
+        Arc::new(tokio::sync::Mutex::new(ToolRegistry::new())),
+        LLMClient::from(MockLLMProvider::new()),
+    );
+    let pool = AgenticTaskPool::new(1, Arc::new(tokio::sync::Mutex::new(agent)))
+        .with_error_observer(observer.clone());
+
+    let error = pool
+        .execute_step(PlanStep::new("missing", json!({})))
+        .await
+        .unwrap_err();
+
+    assert!(matches!(error, AgenticFlowError::ToolNotFound { .. }));
+    let seen = observer.seen();
+    assert_eq!(seen[0].1.component, "pool");
+    assert_eq!(seen[0].1.tool.as_deref(), Some("missing"));
+    assert_eq!(seen[0].1.run_id, None);
+    pool.shutdown().await.unwrap();
+}
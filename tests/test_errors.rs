@@ -0,0 +1,611 @@
+mod common;
+
+use std::{error::Error, path::PathBuf, sync::Arc, time::Duration};
+
+use serde_json::{Value, json};
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    sync::Mutex,
+};
+
+use agentic_flow_lib::{
+    config::{LLMConfig, MCPConfig, ProviderKind},
+    errors::{AgenticFlowError, ErrorKind, ToolOrigin, with_timeout},
+    http::HttpConfig,
+    llm_client::{LLMClient, MockLLMProvider, RequestOptions},
+    mcp_manager::MCPManager,
+    model::ChatMessage,
+    planner::{HTNPlanner, Planner},
+    tool_registry::{ExecutionContext, ToolRegistry},
+};
+
+use common::http_server::{MockHttpServer, MockResponse};
+use common::tools::FlakyTool;
+
+fn registry_with_flaky_tool() -> ToolRegistry {
+    let mut tool_registry = ToolRegistry::new();
+    tool_registry.register_local_tool(Box::new(FlakyTool::failing(1)));
+    tool_registry
+}
+
+#[tokio::test]
+async fn test_unknown_tool_is_tool_not_found() {
+    let manager = MCPManager::new(MCPConfig::default());
+
+    let err = ToolRegistry::new()
+        .execute_tool("missing", json!({}), &manager, &mut ExecutionContext::new())
+        .await
+        .unwrap_err();
+
+    assert!(matches!(&err, AgenticFlowError::ToolNotFound { tool } if tool == "missing"));
+    assert_eq!(err.to_string(), "Tool 'missing' not found");
+}
+
+#[tokio::test]
+async fn test_local_tool_failure_keeps_origin_and_source() {
+    let manager = MCPManager::new(MCPConfig::default());
+
+    let err = registry_with_flaky_tool()
+        .execute_tool("flaky", json!({}), &manager, &mut ExecutionContext::new())
+        .await
+        .unwrap_err();
+
+    assert!(matches!(
+        &err,
+        AgenticFlowError::ToolExecutionFailed {
+            origin: ToolOrigin::Local,
+            ..
+        }
+    ));
+    assert_eq!(err.tool_name(), Some("flaky"));
+    assert_eq!(err.server_name(), None);
+    assert!(matches!(err.root_cause(), AgenticFlowError::ToolError(_)));
+    assert!(err.source().is_some());
+}
+
+#[tokio::test]
+async fn test_unknown_server_is_mcp_server_error() {
+    let mut manager = MCPManager::new(MCPConfig::default());
+
+    let err = manager.start_server("github").await.unwrap_err();
+
+    assert!(matches!(
+        &err,
+        AgenticFlowError::McpServerError { tool: None, .. }
+    ));
+    assert_eq!(err.server_name(), Some("github"));
+    assert!(err.to_string().contains("MCP server 'github'"), "{}", err);
+}
+
+#[tokio::test]
+async fn test_listing_tools_of_stopped_server_names_server() {
+    let manager = MCPManager::new(MCPConfig::default());
+
+    let err = manager.get_server_tools("github").await.unwrap_err();
+
+    assert_eq!(err.server_name(), Some("github"));
+}
+
+#[tokio::test]
+async fn test_planner_failure_names_planner_and_phase() {
+    let provider = MockLLMProvider::new().with_chat_error(AgenticFlowError::NetworkError(
+        "connection refused".to_string(),
+    ));
+    let planner = HTNPlanner::new(
+        LLMClient::from(provider),
+        Arc::new(Mutex::new(ToolRegistry::new())),
+    );
+
+    let err = planner.plan("do the thing").await.unwrap_err();
+
+    assert!(matches!(
+        &err,
+        AgenticFlowError::PlanningFailed {
+            phase: "decompose",
+            ..
+        }
+    ));
+    assert!(matches!(
+        err.root_cause(),
+        AgenticFlowError::NetworkError(_)
+    ));
+    assert_eq!(
+        err.to_string(),
+        "Planner 'htn' failed during decompose: Network error: connection refused"
+    );
+}
+
+#[test]
+fn test_step_failed_display_is_one_based() {
+    let err = AgenticFlowError::StepFailed {
+        step_index: 1,
+        step_id: None,
+        tool: "fetch".to_string(),
+        source: Box::new(AgenticFlowError::McpServerError {
+            server: "web".to_string(),
+            tool: Some("fetch".to_string()),
+            source: Box::new(AgenticFlowError::ToolError("timed out".to_string())),
+        }),
+    };
+
+    assert_eq!(
+        err.to_string(),
+        "Step 2 ('fetch') failed: MCP server 'web' failed calling 'fetch': Tool error: timed out"
+    );
+    assert_eq!(err.step_index(), Some(1));
+    assert_eq!(err.server_name(), Some("web"));
+}
+
+fn boxed(error: AgenticFlowError) -> Box<AgenticFlowError> {
+    Box::new(error)
+}
+
+#[test]
+fn test_error_kinds() {
+    let network = || AgenticFlowError::NetworkError("connection reset".to_string());
+    let cases = vec![
+        (network(), ErrorKind::Transient),
+        (
+            AgenticFlowError::ToolError("flaky".to_string()),
+            ErrorKind::Transient,
+        ),
+        (
+            AgenticFlowError::PlanningError("no plan".to_string()),
+            ErrorKind::Permanent,
+        ),
+        (
+            AgenticFlowError::ParseError("bad json".to_string()),
+            ErrorKind::Permanent,
+        ),
+        (
+            AgenticFlowError::ExecutionError("limit".to_string()),
+            ErrorKind::Permanent,
+        ),
+        (
+            AgenticFlowError::ConfigError("bad value".to_string()),
+            ErrorKind::InvalidInput,
+        ),
+        (
+            AgenticFlowError::Timeout {
+                operation: "llm chat",
+                limit: Duration::from_secs(60),
+            },
+            ErrorKind::Timeout,
+        ),
+        (
+            AgenticFlowError::Cancelled { operation: "task" },
+            ErrorKind::Cancelled,
+        ),
+        (
+            AgenticFlowError::ToolNotFound {
+                tool: "x".to_string(),
+            },
+            ErrorKind::Permanent,
+        ),
+        (
+            AgenticFlowError::ApiClientError("Failed to build HTTP client".to_string()),
+            ErrorKind::Permanent,
+        ),
+        (
+            AgenticFlowError::ToolExecutionFailed {
+                tool: "x".to_string(),
+                origin: ToolOrigin::Local,
+                source: boxed(network()),
+            },
+            ErrorKind::Transient,
+        ),
+        (
+            AgenticFlowError::McpServerError {
+                server: "s".to_string(),
+                tool: None,
+                source: boxed(AgenticFlowError::ConfigError("missing".to_string())),
+            },
+            ErrorKind::InvalidInput,
+        ),
+        (
+            AgenticFlowError::StepFailed {
+                step_index: 0,
+                step_id: None,
+                tool: "x".to_string(),
+                source: boxed(network()),
+            },
+            ErrorKind::Transient,
+        ),
+        (
+            AgenticFlowError::PlanningFailed {
+                planner: "htn".to_string(),
+                phase: "refine",
+                source: boxed(network()),
+                diagnostics: Default::default(),
+            },
+            ErrorKind::Transient,
+        ),
+        (
+            network().with_kind(ErrorKind::Permanent),
+            ErrorKind::Permanent,
+        ),
+    ];
+
+    for (error, expected) in cases {
+        assert_eq!(error.kind(), expected, "{:?}", error);
+    }
+}
+
+#[test]
+fn test_http_status_kinds() {
+    let cases = [
+        (400, ErrorKind::InvalidInput),
+        (401, ErrorKind::AuthFailure),
+        (403, ErrorKind::AuthFailure),
+        (404, ErrorKind::Permanent),
+        (408, ErrorKind::Timeout),
+        (422, ErrorKind::InvalidInput),
+        (429, ErrorKind::RateLimited { retry_after: None }),
+        (500, ErrorKind::Transient),
+        (503, ErrorKind::Transient),
+    ];
+
+    for (status, expected) in cases {
+        let error = AgenticFlowError::from_http_response(status, None, "{}");
+        assert_eq!(error.kind(), expected, "status {}", status);
+    }
+}
+
+#[test]
+fn test_retryable_kinds() {
+    let cases = [
+        (ErrorKind::Transient, true),
+        (ErrorKind::RateLimited { retry_after: None }, true),
+        (ErrorKind::Timeout, true),
+        (ErrorKind::Permanent, false),
+        (ErrorKind::AuthFailure, false),
+        (ErrorKind::InvalidInput, false),
+        (ErrorKind::Cancelled, false),
+    ];
+
+    for (kind, expected) in cases {
+        assert_eq!(kind.is_retryable(), expected, "{:?}", kind);
+    }
+}
+
+#[test]
+fn test_with_kind_keeps_display_and_context() {
+    let error = AgenticFlowError::McpServerError {
+        server: "web".to_string(),
+        tool: Some("fetch".to_string()),
+        source: boxed(AgenticFlowError::ToolError("timed out".to_string())),
+    };
+    let message = error.to_string();
+
+    let error = error.with_kind(ErrorKind::InvalidInput);
+
+    assert!(!error.is_retryable());
+    assert_eq!(error.to_string(), message);
+    assert_eq!(error.server_name(), Some("web"));
+    assert!(matches!(error.root_cause(), AgenticFlowError::ToolError(_)));
+}
+
+#[test]
+fn test_timeout_and_cancelled_display() {
+    let timeout = AgenticFlowError::Timeout {
+        operation: "llm chat",
+        limit: Duration::from_secs(60),
+    };
+    let cancelled = AgenticFlowError::Cancelled { operation: "task" };
+
+    assert_eq!(
+        timeout.to_string(),
+        "operation 'llm chat' timed out after 60s"
+    );
+    assert!(timeout.is_retryable());
+    assert_eq!(cancelled.to_string(), "operation 'task' was cancelled");
+    assert!(!cancelled.is_retryable());
+}
+
+#[tokio::test]
+async fn test_llm_request_over_limit_is_timeout() {
+    let server = MockHttpServer::start(vec![
+        MockResponse::json(200, json!({})).with_delay(Duration::from_secs(5)),
+    ])
+    .await;
+    let config = LLMConfig {
+        provider: ProviderKind::OpenAICompatible {
+            base_url: server.base_url.clone(),
+        },
+        model: "test-model".to_string(),
+        ..LLMConfig::default()
+    };
+    let client = LLMClient::from_config(&config)
+        .unwrap()
+        .with_timeout(Duration::from_millis(50));
+
+    let error = client
+        .chat_completions(vec![ChatMessage::user("hi".to_string())], vec![])
+        .await
+        .err()
+        .unwrap();
+
+    assert!(
+        matches!(
+            error,
+            AgenticFlowError::Timeout {
+                operation: "llm chat",
+                ..
+            }
+        ),
+        "{:?}",
+        error
+    );
+    assert_eq!(error.kind(), ErrorKind::Timeout);
+}
+
+#[tokio::test]
+async fn test_reqwest_timeout_converts_to_timeout() {
+    let server = MockHttpServer::start(vec![
+        MockResponse::json(200, json!({})).with_delay(Duration::from_secs(5)),
+    ])
+    .await;
+    let client = reqwest::Client::builder()
+        .timeout(Duration::from_millis(50))
+        .build()
+        .unwrap();
+
+    let error = AgenticFlowError::from(client.get(&server.base_url).send().await.unwrap_err());
+
+    assert!(
+        matches!(
+            error,
+            AgenticFlowError::Timeout {
+                operation: "http request",
+                limit: Duration::ZERO,
+            }
+        ),
+        "{:?}",
+        error
+    );
+    assert_eq!(error.to_string(), "operation 'http request' timed out");
+    assert_eq!(error.kind(), ErrorKind::Timeout);
+}
+
+#[tokio::test]
+async fn test_connect_timeout_is_timeout_with_the_configured_limit() {
+    // Accepts connections but never answers the TLS handshake, which the connect timeout
+    // covers.
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let address = listener.local_addr().unwrap();
+    tokio::spawn(async move {
+        let mut open = Vec::new();
+        while let Ok((stream, _)) = listener.accept().await {
+            open.push(stream);
+        }
+    });
+    let config = LLMConfig {
+        provider: ProviderKind::OpenAICompatible {
+            base_url: format!("https://{}", address),
+        },
+        model: "test-model".to_string(),
+        http: Some(HttpConfig {
+            connect_timeout_seconds: Some(1),
+            ..HttpConfig::default()
+        }),
+        ..LLMConfig::default()
+    };
+    let client = LLMClient::from_config(&config).unwrap();
+
+    let error = client
+        .chat_completions(vec![ChatMessage::user("hi".to_string())], vec![])
+        .await
+        .err()
+        .unwrap();
+
+    assert!(
+        matches!(
+            error,
+            AgenticFlowError::Timeout {
+                operation: "http request",
+                limit,
+            } if limit == Duration::from_secs(1)
+        ),
+        "{:?}",
+        error
+    );
+    assert!(error.is_retryable());
+}
+
+fn openai_compatible(base_url: &str) -> LLMClient {
+    LLMClient::from_config(&LLMConfig {
+        provider: ProviderKind::OpenAICompatible {
+            base_url: base_url.to_string(),
+        },
+        model: "test-model".to_string(),
+        ..LLMConfig::default()
+    })
+    .unwrap()
+}
+
+fn openrouter_stop() -> String {
+    std::fs::read_to_string(
+        PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+            .join("tests/fixtures/responses/openrouter_stop.json"),
+    )
+    .unwrap()
+}
+
+#[tokio::test]
+async fn test_per_call_timeout_overrides_the_client() {
+    let server = MockHttpServer::start(vec![
+        MockResponse::raw(200, &openrouter_stop()).with_delay(Duration::from_millis(200)),
+    ])
+    .await;
+    let client = openai_compatible(&server.base_url);
+    let messages = vec![ChatMessage::user("hi".to_string())];
+
+    let error = client
+        .clone()
+        .with_timeout(Duration::from_secs(5))
+        .chat_completions_with(
+            messages.clone(),
+            vec![],
+            &RequestOptions::default().with_timeout(Duration::from_millis(50)),
+        )
+        .await
+        .err()
+        .unwrap();
+    assert!(
+        matches!(
+            error,
+            AgenticFlowError::Timeout { limit, .. } if limit == Duration::from_millis(50)
+        ),
+        "{:?}",
+        error
+    );
+
+    let response = client
+        .with_timeout(Duration::from_millis(50))
+        .chat_completions_with(
+            messages,
+            vec![],
+            &RequestOptions::default().with_timeout(Duration::from_secs(5)),
+        )
+        .await;
+    assert!(response.is_ok(), "{:?}", response.err());
+}
+
+#[tokio::test]
+async fn test_timeout_covers_a_stalled_body() {
+    // Sends the headers and part of the body, then stalls.
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let base_url = format!("http://{}", listener.local_addr().unwrap());
+    tokio::spawn(async move {
+        let (mut stream, _) = listener.accept().await.unwrap();
+        let mut request = [0u8; 4096];
+        let _ = stream.read(&mut request).await;
+        let head = "HTTP/1.1 200 OK\r\nContent-Length: 100\r\n\r\n";
+        stream.write_all(head.as_bytes()).await.unwrap();
+        stream.write_all(b"{\"choices\": [").await.unwrap();
+        tokio::time::sleep(Duration::from_secs(5)).await;
+    });
+
+    let error = openai_compatible(&base_url)
+        .with_timeout(Duration::from_millis(100))
+        .completion("hi".to_string())
+        .await
+        .err()
+        .unwrap();
+
+    assert!(
+        matches!(
+            error,
+            AgenticFlowError::Timeout {
+                operation: "llm completion",
+                ..
+            }
+        ),
+        "{:?}",
+        error
+    );
+}
+
+#[tokio::test]
+async fn test_reqwest_connection_failure_converts_to_network_error() {
+    let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    let url = format!("http://{}", listener.local_addr().unwrap());
+    drop(listener);
+
+    let error = AgenticFlowError::from(reqwest::get(&url).await.unwrap_err());
+
+    assert!(
+        matches!(error, AgenticFlowError::NetworkError(_)),
+        "{:?}",
+        error
+    );
+}
+
+#[tokio::test]
+async fn test_reqwest_builder_error_converts_to_api_client_error() {
+    let error = AgenticFlowError::from(reqwest::get("not a url").await.unwrap_err());
+
+    assert!(
+        matches!(error, AgenticFlowError::ApiClientError(_)),
+        "{:?}",
+        error
+    );
+}
+
+#[test]
+fn test_serde_json_error_converts_to_parse_error() {
+    let error = AgenticFlowError::from(serde_json::from_str::<Value>("{").unwrap_err());
+
+    assert!(
+        matches!(error, AgenticFlowError::ParseError(_)),
+        "{:?}",
+        error
+    );
+}
+
+#[tokio::test]
+async fn test_with_timeout_reports_operation_and_limit() {
+    let limit = Duration::from_millis(1);
+
+    let error = with_timeout("pool task", limit, std::future::pending::<Result<(), _>>())
+        .await
+        .unwrap_err();
+
+    assert!(matches!(
+        error,
+        AgenticFlowError::Timeout {
+            operation: "pool task",
+            limit: actual,
+        } if actual == limit
+    ));
+}
+
+#[tokio::test]
+async fn test_join_error_converts_to_execution_error() {
+    let join_error = tokio::spawn(async { panic!("worker panicked") })
+        .await
+        .unwrap_err();
+
+    assert!(matches!(
+        AgenticFlowError::from(join_error),
+        AgenticFlowError::ExecutionError(_)
+    ));
+}
+
+#[tokio::test]
+async fn test_aborted_task_converts_to_cancelled() {
+    let task = tokio::spawn(std::future::pending::<()>());
+    task.abort();
+
+    let join_error = task.await.unwrap_err();
+
+    assert!(matches!(
+        AgenticFlowError::from(join_error),
+        AgenticFlowError::Cancelled { operation: "task" }
+    ));
+}
+
+#[tokio::test]
+async fn test_provider_reports_invalid_body_as_parse_error() {
+    let server = MockHttpServer::start(vec![MockResponse::raw(200, "not json")]).await;
+    let config = LLMConfig {
+        provider: ProviderKind::OpenAICompatible {
+            base_url: server.base_url.clone(),
+        },
+        model: "test-model".to_string(),
+        ..LLMConfig::default()
+    };
+
+    let error = LLMClient::from_config(&config)
+        .unwrap()
+        .chat_completions(vec![ChatMessage::user("hi".to_string())], vec![])
+        .await
+        .err()
+        .unwrap();
+
+    assert!(
+        matches!(&error, AgenticFlowError::ParseError(message) if message.contains("not json")),
+        "{:?}",
+        error
+    );
+}
@@ -0,0 +1,180 @@
+mod common;
+
+use std::path::PathBuf;
+
+use serde_json::json;
+
+use agentic_flow_lib::{
+    config::{LLMConfig, ProviderKind},
+    errors::AgenticFlowError,
+    llm_client::{LLMClient, MockLLMProvider},
+    model::ChatMessage,
+};
+
+use common::http_server::{MockHttpServer, MockResponse};
+use common::user_messages;
+
+async fn answering(content: &str) -> MockLLMProvider {
+    MockLLMProvider::new()
+        .with_chat_response(Some(ChatMessage::assistant(content.to_string())))
+        .await
+}
+
+fn openai_compatible(server: &MockHttpServer) -> LLMClient {
+    LLMClient::from_config(&LLMConfig {
+        provider: ProviderKind::OpenAICompatible {
+            base_url: server.base_url.clone(),
+        },
+        model: "test-model".to_string(),
+        ..LLMConfig::default()
+    })
+    .unwrap()
+}
+
+#[tokio::test]
+async fn test_falls_over_when_the_primary_is_unreachable() {
+    let primary = answering("primary")
+        .await
+        .with_chat_error(AgenticFlowError::NetworkError(
+            "connection refused".to_string(),
+        ));
+    let primary_calls = primary.chat_calls();
+    let secondary = answering("secondary").await;
+    let secondary_calls = secondary.chat_calls();
+    let client = LLMClient::from(primary).with_fallback(LLMClient::from(secondary));
+
+    let response = client
+        .chat_completions(user_messages("hi"), vec![])
+        .await
+        .unwrap();
+
+    assert_eq!(response.message().content, "secondary");
+    assert_eq!(response.fallback(), Some(1));
+    assert_eq!(primary_calls.lock().unwrap().len(), 1);
+    assert_eq!(secondary_calls.lock().unwrap().len(), 1);
+}
+
+#[tokio::test]
+async fn test_primary_success_does_not_fall_over() {
+    let secondary = answering("secondary").await;
+    let secondary_calls = secondary.chat_calls();
+    let client =
+        LLMClient::from(answering("primary").await).with_fallback(LLMClient::from(secondary));
+
+    let response = client
+        .chat_completions(user_messages("hi"), vec![])
+        .await
+        .unwrap();
+
+    assert_eq!(response.message().content, "primary");
+    assert_eq!(response.fallback(), None);
+    assert!(secondary_calls.lock().unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn test_rejected_requests_do_not_fall_over() {
+    for error in [
+        AgenticFlowError::from_http_response(400, None, "bad request"),
+        AgenticFlowError::from_http_response(401, None, "bad key"),
+        AgenticFlowError::ParseError("not JSON".to_string()),
+    ] {
+        let secondary = answering("secondary").await;
+        let secondary_calls = secondary.chat_calls();
+        let client = LLMClient::from(MockLLMProvider::new().with_chat_error(error.clone()))
+            .with_fallback(LLMClient::from(secondary));
+
+        let result = client.chat_completions(user_messages("hi"), vec![]).await;
+
+        assert!(result.is_err(), "{:?}", error);
+        assert!(secondary_calls.lock().unwrap().is_empty(), "{:?}", error);
+    }
+}
+
+#[tokio::test]
+async fn test_fallbacks_are_tried_in_order() {
+    let timeout = AgenticFlowError::Timeout {
+        operation: "llm chat",
+        limit: std::time::Duration::from_secs(1),
+    };
+    let server_error = AgenticFlowError::from_http_response(503, None, "overloaded");
+    let client = LLMClient::from(MockLLMProvider::new().with_chat_error(timeout))
+        .with_fallback(LLMClient::from(
+            MockLLMProvider::new().with_chat_error(server_error),
+        ))
+        .with_fallback(LLMClient::from(answering("third").await));
+
+    let response = client
+        .chat_completions(user_messages("hi"), vec![])
+        .await
+        .unwrap();
+
+    assert_eq!(response.message().content, "third");
+    assert_eq!(response.fallback(), Some(2));
+}
+
+#[tokio::test]
+async fn test_last_error_is_returned_when_every_client_fails() {
+    let client = LLMClient::from(
+        MockLLMProvider::new().with_chat_error(AgenticFlowError::NetworkError("down".to_string())),
+    )
+    .with_fallback(LLMClient::from(MockLLMProvider::new().with_chat_error(
+        AgenticFlowError::from_http_response(502, None, "bad gateway"),
+    )));
+
+    let error = client
+        .chat_completions(user_messages("hi"), vec![])
+        .await
+        .err()
+        .unwrap();
+
+    assert!(
+        matches!(
+            error,
+            AgenticFlowError::ApiResponseError { status: 502, .. }
+        ),
+        "{:?}",
+        error
+    );
+}
+
+#[tokio::test]
+async fn test_fallback_gets_the_tools_and_returns_tool_calls() {
+    let body = std::fs::read_to_string(
+        PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+            .join("tests/fixtures/responses/openrouter_tool_calls.json"),
+    )
+    .unwrap();
+    let primary = MockHttpServer::start(vec![MockResponse::raw(503, "overloaded")]).await;
+    let secondary = MockHttpServer::start(vec![MockResponse::raw(200, &body)]).await;
+    let client = openai_compatible(&primary).with_fallback(openai_compatible(&secondary));
+    let tools = vec![json!({
+        "type": "function",
+        "function": { "name": "search", "parameters": { "type": "object" } }
+    })];
+
+    let response = client
+        .chat_completions(user_messages("hi"), tools.clone())
+        .await
+        .unwrap();
+
+    assert_eq!(primary.requests()[0].body["tools"], json!(tools));
+    assert_eq!(secondary.requests()[0].body["tools"], json!(tools));
+    assert_eq!(response.fallback(), Some(1));
+    let tool_calls = response.message().tool_calls.clone().unwrap();
+    assert_eq!(tool_calls[0].function.name, "search");
+    assert_eq!(tool_calls[0].function.arguments, json!({ "query": "rust" }));
+}
+
+#[tokio::test]
+async fn test_completion_falls_over() {
+    let primary = MockHttpServer::start(vec![MockResponse::raw(500, "boom")]).await;
+    let secondary = MockLLMProvider::new()
+        .with_completion_response(Some("from the fallback".to_string()))
+        .await;
+    let client = openai_compatible(&primary).with_fallback(LLMClient::from(secondary));
+
+    let response = client.completion("hi".to_string()).await.unwrap();
+
+    assert_eq!(response.response(), "from the fallback");
+    assert_eq!(response.fallback(), Some(1));
+}
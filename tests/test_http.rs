@@ -0,0 +1,243 @@
+mod common;
+
+use serde_json::json;
+
+use agentic_flow_lib::{
+    config::LLMConfig,
+    errors::AgenticFlowError,
+    http::HttpConfig,
+    llm_client::{LLMClient, OllamaModel, OllamaProvider, OpenRouterModel},
+    model::ChatMessage,
+};
+
+use common::http_server::{MockHttpServer, MockResponse};
+
+fn client_error(result: Result<LLMClient, AgenticFlowError>) -> String {
+    match result {
+        Err(AgenticFlowError::ApiClientError(message)) => message,
+        Err(other) => panic!("unexpected error: {:?}", other),
+        Ok(_) => panic!("the client was built"),
+    }
+}
+
+#[tokio::test]
+async fn test_requests_go_through_the_proxy() {
+    let answer = json!({
+        "model": "gemma3:4b",
+        "message": { "role": "assistant", "content": "Hello" },
+        "done": true
+    });
+    let proxy = MockHttpServer::start(vec![MockResponse::json(200, answer)]).await;
+    let http = HttpConfig {
+        proxy: Some(proxy.base_url.clone()),
+        connect_timeout_seconds: Some(5),
+        pool_max_idle: Some(2),
+        ..HttpConfig::default()
+    };
+    let provider = OllamaProvider::with_client(
+        reqwest::Client::new(),
+        OllamaModel::Gemma3_4b,
+        "http://ollama.invalid:11434".to_string(),
+    )
+    .with_http(&http)
+    .unwrap();
+
+    let response = LLMClient::from(provider)
+        .chat_completions(vec![ChatMessage::user("hi".to_string())], vec![])
+        .await
+        .unwrap();
+
+    assert_eq!(response.message().content, "Hello");
+    assert_eq!(
+        proxy.requests()[0].path,
+        "http://ollama.invalid:11434/api/chat"
+    );
+}
+
+#[test]
+fn test_invalid_proxy_fails_at_construction() {
+    let http = HttpConfig {
+        proxy: Some("http://[::1".to_string()),
+        ..HttpConfig::default()
+    };
+
+    let message = client_error(LLMClient::from_open_router_with_http(
+        OpenRouterModel::Flash2,
+        &http,
+    ));
+
+    assert!(
+        message.starts_with("invalid proxy URL 'http://[::1'"),
+        "{}",
+        message
+    );
+}
+
+#[test]
+fn test_unreadable_ca_bundle_fails_at_construction() {
+    let missing = std::env::temp_dir().join("agentic-flow-missing-ca.pem");
+    let not_pem = std::env::temp_dir().join(format!("agentic-flow-ca-{}.pem", std::process::id()));
+    std::fs::write(&not_pem, "not a certificate").unwrap();
+
+    for path in [missing, not_pem.clone()] {
+        let config = LLMConfig {
+            http: Some(HttpConfig {
+                ca_bundle_path: Some(path.clone()),
+                ..HttpConfig::default()
+            }),
+            ..LLMConfig::default()
+        };
+
+        let message = client_error(LLMClient::from_config(&config));
+
+        assert!(
+            message.starts_with(&format!("cannot read CA bundle '{}'", path.display())),
+            "{}",
+            message
+        );
+    }
+    std::fs::remove_file(not_pem).unwrap();
+}
+
+fn ollama_chat_body() -> String {
+    json!({
+        "model": "gemma3:4b",
+        "message": { "role": "assistant", "content": "Hello" },
+        "done": true
+    })
+    .to_string()
+}
+
+async fn ollama_chat(
+    server: &MockHttpServer,
+    http: &HttpConfig,
+) -> Result<String, AgenticFlowError> {
+    let config = LLMConfig {
+        model: "gemma3:4b".to_string(),
+        base_url: Some(server.base_url.clone()),
+        http: Some(http.clone()),
+        ..LLMConfig::default()
+    };
+    LLMClient::from_config(&config)
+        .unwrap()
+        .chat_completions(vec![ChatMessage::user("hi".to_string())], vec![])
+        .await
+        .map(|response| response.message().content.clone())
+}
+
+#[tokio::test]
+async fn test_truncated_body_is_a_network_error() {
+    let server = MockHttpServer::start(vec![
+        MockResponse::raw(200, &ollama_chat_body()).truncated_at(20),
+    ])
+    .await;
+
+    let error = ollama_chat(&server, &HttpConfig::default())
+        .await
+        .unwrap_err();
+
+    assert!(
+        matches!(error, AgenticFlowError::NetworkError(_)),
+        "{:?}",
+        error
+    );
+}
+
+#[tokio::test]
+async fn test_body_over_the_limit_is_a_client_error() {
+    let server = MockHttpServer::start(vec![MockResponse::raw(200, &ollama_chat_body())]).await;
+    let http = HttpConfig {
+        max_response_bytes: Some(16),
+        ..HttpConfig::default()
+    };
+
+    let error = ollama_chat(&server, &http).await.unwrap_err();
+
+    match error {
+        AgenticFlowError::ApiClientError(message) => {
+            assert_eq!(message, "the response body exceeds the limit of 16 bytes")
+        }
+        error => panic!("expected a client error, got {:?}", error),
+    }
+    let answer = ollama_chat(&server, &HttpConfig::default()).await.unwrap();
+    assert_eq!(answer, "Hello");
+}
+
+#[tokio::test]
+async fn test_unparseable_body_is_quoted_in_the_parse_error() {
+    let body = format!("<html>{}</html>", "x".repeat(2000));
+    let server = MockHttpServer::start(vec![MockResponse::raw(200, &body)]).await;
+
+    let error = ollama_chat(&server, &HttpConfig::default())
+        .await
+        .unwrap_err();
+
+    match error {
+        AgenticFlowError::ParseError(message) => {
+            let (_, quoted) = message.split_once("in response body: ").unwrap();
+            assert!(quoted.starts_with("<html>xxx"), "{}", message);
+            assert_eq!(quoted.len(), 500);
+        }
+        error => panic!("expected a parse error, got {:?}", error),
+    }
+}
+
+#[cfg(unix)]
+fn socket_path(name: &str) -> std::path::PathBuf {
+    let path =
+        std::env::temp_dir().join(format!("agentic-flow-{}-{}.sock", name, std::process::id()));
+    std::fs::remove_file(&path).ok();
+    path
+}
+
+#[cfg(unix)]
+#[tokio::test]
+async fn test_ollama_over_a_unix_socket() {
+    let path = socket_path("ollama");
+    let server = MockHttpServer::start_unix(
+        &path,
+        vec![
+            MockResponse::raw(200, &ollama_chat_body()),
+            MockResponse::json(200, json!({ "models": [{ "name": "gemma3:4b" }] })),
+        ],
+    )
+    .await;
+    let client = LLMClient::from_ollama_socket(&path, OllamaModel::Gemma3_4b).unwrap();
+
+    let response = client
+        .chat_completions(vec![ChatMessage::user("hi".to_string())], vec![])
+        .await
+        .unwrap();
+    let models = client.available_models().await.unwrap();
+
+    assert_eq!(response.message().content, "Hello");
+    assert_eq!(models, vec!["gemma3:4b".to_string()]);
+    let requests = server.requests();
+    assert_eq!(requests[0].path, "/api/chat");
+    assert_eq!(requests[1].path, "/api/tags");
+    std::fs::remove_file(path).unwrap();
+}
+
+#[cfg(unix)]
+#[tokio::test]
+async fn test_missing_unix_socket_is_named_in_the_error() {
+    let path = socket_path("missing");
+    let client = LLMClient::from_ollama_socket(&path, OllamaModel::Gemma3_4b).unwrap();
+
+    let error = client
+        .chat_completions(vec![ChatMessage::user("hi".to_string())], vec![])
+        .await
+        .unwrap_err();
+
+    match error {
+        AgenticFlowError::NetworkError(message) => assert!(
+            message.starts_with(&format!(
+                "cannot connect to the unix socket '{}'",
+                path.display()
+            )),
+            "{}",
+            message
+        ),
+        error => panic!("expected a network error, got {:?}", error),
+    }
+}
@@ -1,9 +1,9 @@
 mod common;
 
 use agentic_flow_lib::{
-    AgenticSystem, 
-    config::SystemConfig, 
-    llm_client::LLMClient, 
+    AgenticSystem,
+    config::SystemConfig,
+    llm_client::{LLMClient, MockLLMProvider},
     tool_registry::LocalTool,
 };
 
@@ -39,3 +39,20 @@ async fn test_plan_and_execute() {
     assert!(result.contains("test successful step 1"));
     assert!(result.contains("test successful step 2"));
 }
+
+#[tokio::test]
+async fn test_builder_with_custom_client() {
+    let provider = MockLLMProvider::new().with_chat_response(None).await;
+    let agentic_system = AgenticSystem::builder()
+        .config(SystemConfig::builder().build().unwrap())
+        .tool(MockTool)
+        .tool(MockToolFollowUp)
+        .llm_client(LLMClient::from(provider))
+        .build()
+        .await
+        .unwrap();
+
+    let result = agentic_system.get_available_tools().await;
+
+    assert_eq!(result, vec!["mock_tool", "mock_tool_follow_up"]);
+}
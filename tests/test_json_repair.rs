@@ -0,0 +1,74 @@
+use serde_json::{Value, json};
+
+use agentic_flow_lib::errors::AgenticFlowError;
+use agentic_flow_lib::json_repair::{parse_lenient, repair_json};
+
+fn repaired(text: &str) -> Value {
+    serde_json::from_str(&repair_json(text)).unwrap()
+}
+
+#[test]
+fn test_common_defects_are_repaired() {
+    let cases = [
+        ("{\"a\": 1, \"b\": [1, 2,],}", json!({"a": 1, "b": [1, 2]})),
+        (
+            "{'a': 'it\\'s \"quoted\"'}",
+            json!({"a": "it's \"quoted\""}),
+        ),
+        (
+            "{query: \"rust\", max_results: 5}",
+            json!({"query": "rust", "max_results": 5}),
+        ),
+        (
+            "Sure, here are the arguments:\n{\"a\": 1}\nLet me know!",
+            json!({"a": 1}),
+        ),
+        (
+            "{\"flag\": True, \"other\": None}",
+            json!({"flag": true, "other": null}),
+        ),
+        ("{\"a\": {\"b\": [1, 2", json!({"a": {"b": [1, 2]}})),
+        ("{\"a\": \"unterminated", json!({"a": "unterminated"})),
+    ];
+
+    for (text, expected) in cases {
+        assert_eq!(repaired(text), expected, "{}", text);
+    }
+}
+
+#[test]
+fn test_valid_json_is_not_marked_repaired() {
+    let json = parse_lenient("{\"note\": \"True, None: {x}\"}").unwrap();
+
+    assert_eq!(json.value, json!({"note": "True, None: {x}"}));
+    assert!(!json.repaired);
+}
+
+#[test]
+fn test_repaired_json_is_marked() {
+    let json = parse_lenient("{'a': 1,}").unwrap();
+
+    assert_eq!(json.value, json!({"a": 1}));
+    assert!(json.repaired);
+}
+
+#[test]
+fn test_unrepairable_json_quotes_original_and_repair() {
+    let error = parse_lenient("{'city': Paris de France,}").unwrap_err();
+
+    match error {
+        AgenticFlowError::ParseError(message) => {
+            assert!(
+                message.contains("{'city': Paris de France,}"),
+                "{}",
+                message
+            );
+            assert!(
+                message.contains("attempted repair \"{\\\"city\\\": Paris de France}\""),
+                "{}",
+                message
+            );
+        }
+        error => panic!("expected a parse error, got {:?}", error),
+    }
+}
@@ -0,0 +1,192 @@
+mod common;
+
+use std::{
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use agentic_flow_lib::{
+    errors::AgenticFlowError,
+    llm_client::{
+        CacheConfig, LLMClient, LLMLayer, LLMRequest, LLMResponse, LoggingLayer, MockLLMProvider,
+        Next, RetryLayer, RetryPolicy,
+    },
+    model::{ChatMessage, OllamaResponse},
+};
+
+use common::user_messages;
+
+/// Notes its name each time a request passes through it.
+struct Tracing {
+    name: &'static str,
+    seen: Arc<Mutex<Vec<&'static str>>>,
+}
+
+#[async_trait::async_trait]
+impl LLMLayer for Tracing {
+    async fn call(
+        &self,
+        request: LLMRequest,
+        next: Next<'_>,
+    ) -> Result<LLMResponse, AgenticFlowError> {
+        self.seen.lock().unwrap().push(self.name);
+        next.run(request).await
+    }
+}
+
+/// Replaces words containing `@` in chat messages.
+struct ScrubEmails;
+
+#[async_trait::async_trait]
+impl LLMLayer for ScrubEmails {
+    async fn call(
+        &self,
+        mut request: LLMRequest,
+        next: Next<'_>,
+    ) -> Result<LLMResponse, AgenticFlowError> {
+        if let LLMRequest::Chat { messages, .. } = &mut request {
+            for message in messages {
+                message.content = message
+                    .content
+                    .split(' ')
+                    .map(|word| if word.contains('@') { "[email]" } else { word })
+                    .collect::<Vec<_>>()
+                    .join(" ");
+            }
+        }
+        next.run(request).await
+    }
+}
+
+/// Answers every chat request itself.
+struct Canned;
+
+#[async_trait::async_trait]
+impl LLMLayer for Canned {
+    async fn call(
+        &self,
+        _request: LLMRequest,
+        _next: Next<'_>,
+    ) -> Result<LLMResponse, AgenticFlowError> {
+        let mut response = OllamaResponse::default();
+        response.message = ChatMessage::assistant("canned".to_string());
+        Ok(LLMResponse::Chat(Box::new(response)))
+    }
+}
+
+#[tokio::test]
+async fn test_layers_can_rewrite_requests() {
+    let mock = MockLLMProvider::new();
+    let client = LLMClient::from(mock.clone()).layer(ScrubEmails);
+
+    client
+        .chat_completions(user_messages("mail jane@example.com today"), vec![])
+        .await
+        .unwrap();
+
+    assert_eq!(mock.calls()[0].messages[0].content, "mail [email] today");
+}
+
+#[tokio::test]
+async fn test_layers_run_in_the_order_they_were_added() {
+    let seen = Arc::new(Mutex::new(Vec::new()));
+    let layer = |name| Tracing {
+        name,
+        seen: seen.clone(),
+    };
+    let client = LLMClient::from(MockLLMProvider::new())
+        .layer(layer("outer"))
+        .layer(layer("inner"));
+
+    client
+        .chat_completions(user_messages("hi"), vec![])
+        .await
+        .unwrap();
+    client.completion("hi".to_string()).await.unwrap();
+
+    assert_eq!(
+        *seen.lock().unwrap(),
+        vec!["outer", "inner", "outer", "inner"]
+    );
+}
+
+#[tokio::test]
+async fn test_a_layer_can_answer_without_the_provider() {
+    let mock = MockLLMProvider::new();
+    let client = LLMClient::from(mock.clone()).layer(Canned);
+
+    let response = client
+        .chat_completions(user_messages("hi"), vec![])
+        .await
+        .unwrap();
+
+    assert_eq!(response.message().content, "canned");
+    assert!(mock.calls().is_empty());
+    let error = client.completion("hi".to_string()).await.unwrap_err();
+    assert!(
+        error
+            .to_string()
+            .contains("a completion request with a chat response"),
+        "{}",
+        error
+    );
+}
+
+#[tokio::test]
+async fn test_retry_layer_sends_again_through_the_inner_layers() {
+    let seen = Arc::new(Mutex::new(Vec::new()));
+    let mock = MockLLMProvider::new().with_chat_failures(vec![
+        AgenticFlowError::NetworkError("reset".to_string()),
+        AgenticFlowError::NetworkError("reset".to_string()),
+    ]);
+    let client = LLMClient::from(mock.clone())
+        .layer(RetryLayer::new(RetryPolicy {
+            max_attempts: 3,
+            base_delay: Duration::from_millis(1),
+            max_delay: Duration::from_millis(5),
+            jitter: false,
+        }))
+        .layer(Tracing {
+            name: "attempt",
+            seen: seen.clone(),
+        });
+
+    client
+        .chat_completions(user_messages("hi"), vec![])
+        .await
+        .unwrap();
+
+    assert_eq!(mock.calls().len(), 3);
+    assert_eq!(seen.lock().unwrap().len(), 3);
+}
+
+#[tokio::test]
+async fn test_client_settings_are_layers_around_the_added_ones() {
+    let seen = Arc::new(Mutex::new(Vec::new()));
+    let logs = Arc::new(Mutex::new(Vec::new()));
+    let recorded = logs.clone();
+    let client = LLMClient::from(MockLLMProvider::new())
+        .layer(Tracing {
+            name: "custom",
+            seen: seen.clone(),
+        })
+        .with_temperature(0.0)
+        .with_cache(CacheConfig::default())
+        .layer(LoggingLayer::new(move |log| {
+            recorded.lock().unwrap().push(log.operation)
+        }));
+
+    client
+        .chat_completions(user_messages("hi"), vec![])
+        .await
+        .unwrap();
+    client
+        .chat_completions(user_messages("hi"), vec![])
+        .await
+        .unwrap();
+
+    // The second request was answered by the cache before reaching the added layers.
+    assert_eq!(seen.lock().unwrap().len(), 1);
+    assert_eq!(*logs.lock().unwrap(), vec!["llm chat"]);
+    assert_eq!(client.cache_stats().unwrap().hits, 1);
+}
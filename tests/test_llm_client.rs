@@ -1,5 +1,25 @@
-use agentic_flow_lib::llm_client::{LLMClient, OllamaModel};
-use agentic_flow_lib::model::ChatMessage;
+mod common;
+
+use agentic_flow_lib::AgenticSystem;
+use agentic_flow_lib::agent::{AgentConfig, SynthesisConfig};
+use agentic_flow_lib::config::{LLMConfig, ProviderKind, SystemConfig};
+use agentic_flow_lib::errors::AgenticFlowError;
+use agentic_flow_lib::llm_client::{
+    AnthropicModel, GeminiModel, GroqModel, LLMClient, LlamaCppProvider, OllamaModel,
+    OllamaOptions, OllamaProvider, OpenAIModel, OpenRouterModel, OpenRouterProvider, OutputFormat,
+    RequestOptions, SamplingOptions, ToolChoice, ToolEmulation, summing_run,
+};
+use agentic_flow_lib::model::{ChatMessage, ImageData, ToolCall, Usage};
+use agentic_flow_lib::planner::{ChainOfThoughtPlanner, MultiStepPlanner, Planner};
+use agentic_flow_lib::tool_registry::{LocalTool, ToolRegistry};
+use serde_json::json;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::Mutex;
+
+use common::http_server::{MockHttpServer, MockResponse};
+use common::tools::{EchoTool, MockTool};
 
 #[tokio::test]
 async fn test_ollama_chat_completion_gemma() {
@@ -31,3 +51,1302 @@ async fn test_ollama_text_completion_gemma() {
     );
     assert!(!result.unwrap().response().is_empty());
 }
+
+#[test]
+fn test_client_from_config_for_each_provider() {
+    let providers = vec![
+        ProviderKind::Ollama,
+        ProviderKind::OpenRouter,
+        ProviderKind::OpenAI,
+        ProviderKind::Anthropic,
+        ProviderKind::Gemini,
+        ProviderKind::Groq,
+        ProviderKind::OpenAICompatible {
+            base_url: "http://localhost:8000/v1/".to_string(),
+        },
+    ];
+
+    for provider in providers {
+        let config = LLMConfig {
+            provider: provider.clone(),
+            model: "some-model".to_string(),
+            temperature: 0.1,
+            timeout_seconds: Some(5),
+            ..LLMConfig::default()
+        };
+
+        let client = LLMClient::from_config(&config).unwrap();
+
+        assert_eq!(client.model_name(), Some("some-model"), "{:?}", provider);
+        assert_eq!(client.temperature(), 0.1, "{:?}", provider);
+    }
+}
+
+#[test]
+fn test_openai_models() {
+    let cases = [
+        (OpenAIModel::GPT4o, "gpt-4o"),
+        (OpenAIModel::GPT4oMini, "gpt-4o-mini"),
+        (OpenAIModel::O3Mini, "o3-mini"),
+        (OpenAIModel::Custom("gpt-4.1".to_string()), "gpt-4.1"),
+    ];
+
+    for (model, name) in cases {
+        assert_eq!(LLMClient::from_openai(model).model_name(), Some(name));
+    }
+}
+
+#[test]
+fn test_anthropic_models() {
+    let cases = [
+        (AnthropicModel::ClaudeOpus4, "claude-opus-4-0"),
+        (AnthropicModel::ClaudeSonnet4, "claude-sonnet-4-0"),
+        (AnthropicModel::Claude35Haiku, "claude-3-5-haiku-latest"),
+        (
+            AnthropicModel::Custom("claude-3-opus-latest".to_string()),
+            "claude-3-opus-latest",
+        ),
+    ];
+
+    for (model, name) in cases {
+        assert_eq!(LLMClient::from_anthropic(model).model_name(), Some(name));
+    }
+}
+
+#[test]
+fn test_gemini_models() {
+    let cases = [
+        (GeminiModel::Flash2, "gemini-2.0-flash"),
+        (GeminiModel::Pro15, "gemini-1.5-pro"),
+        (
+            GeminiModel::Custom("gemini-2.5-pro".to_string()),
+            "gemini-2.5-pro",
+        ),
+    ];
+
+    for (model, name) in cases {
+        assert_eq!(LLMClient::from_gemini(model).model_name(), Some(name));
+    }
+    let client = LLMClient::from_gemini_with_key(GeminiModel::Flash2, "test-key");
+    assert_eq!(client.model_name(), Some("gemini-2.0-flash"));
+}
+
+#[test]
+fn test_groq_models() {
+    let cases = [
+        (GroqModel::Llama33_70B, "llama-3.3-70b-versatile"),
+        (
+            GroqModel::Custom("llama-3.1-8b-instant".to_string()),
+            "llama-3.1-8b-instant",
+        ),
+    ];
+
+    for (model, name) in cases {
+        assert_eq!(LLMClient::from_groq(model).model_name(), Some(name));
+    }
+}
+
+#[test]
+fn test_openai_provider_from_config_file() {
+    let config: LLMConfig = toml::from_str(
+        r#"
+        provider = "openai"
+        model = "gpt-4o"
+        "#,
+    )
+    .unwrap();
+
+    assert_eq!(config.provider, ProviderKind::OpenAI);
+    assert_eq!(
+        LLMClient::from_config(&config).unwrap().model_name(),
+        Some("gpt-4o")
+    );
+}
+
+#[tokio::test]
+async fn test_system_uses_configured_model_and_temperature() {
+    let server = MockHttpServer::start(vec![MockResponse::json(
+        200,
+        json!({
+            "choices": [{
+                "message": {"role": "assistant", "content": "done"},
+                "finish_reason": "stop"
+            }]
+        }),
+    )])
+    .await;
+
+    let config = SystemConfig {
+        llm_config: LLMConfig {
+            provider: ProviderKind::OpenAICompatible {
+                base_url: server.base_url.clone(),
+            },
+            model: "configured-model".to_string(),
+            temperature: 0.25,
+            ..LLMConfig::default()
+        },
+        ..SystemConfig::default()
+    };
+    let tools = vec![Box::new(MockTool) as Box<dyn LocalTool>];
+    let agentic_system = AgenticSystem::from_config(config, tools).await.unwrap();
+
+    let result = agentic_system.plan_and_execute("any task").await.unwrap();
+
+    assert_eq!(result, "done");
+    let requests = server.requests();
+    // One planning call and one synthesis call.
+    assert_eq!(requests.len(), 2);
+    for request in requests {
+        assert_eq!(request.path, "/chat/completions");
+        assert_eq!(request.body["model"], "configured-model");
+        assert_eq!(request.body["temperature"].as_f64(), Some(0.25));
+    }
+}
+
+#[tokio::test]
+async fn test_tool_conversation_wire_format() {
+    let server = MockHttpServer::start(vec![MockResponse::json(
+        200,
+        json!({
+            "choices": [{
+                "message": {"role": "assistant", "content": "It is sunny"},
+                "finish_reason": "stop"
+            }]
+        }),
+    )])
+    .await;
+    let config = LLMConfig {
+        provider: ProviderKind::OpenAICompatible {
+            base_url: server.base_url.clone(),
+        },
+        model: "test-model".to_string(),
+        ..LLMConfig::default()
+    };
+    let messages = vec![
+        ChatMessage::assistant("".to_string()).with_tool_calls(vec![
+            ToolCall::new("weather".to_string(), json!({"city": "Oslo"}))
+                .with_id("call_1".to_string()),
+        ]),
+        ChatMessage::tool("sunny".to_string(), "call_1".to_string())
+            .with_name("weather".to_string()),
+    ];
+
+    LLMClient::from_config(&config)
+        .unwrap()
+        .chat_completions(messages, vec![])
+        .await
+        .unwrap();
+
+    assert_eq!(
+        server.requests()[0].body["messages"],
+        json!([
+            {
+                "role": "assistant",
+                "content": "",
+                "thinking": null,
+                "tool_calls": [{
+                    "id": "call_1",
+                    "type": "function",
+                    "function": { "name": "weather", "arguments": "{\"city\":\"Oslo\"}" }
+                }]
+            },
+            {
+                "role": "tool",
+                "content": "sunny",
+                "thinking": null,
+                "tool_call_id": "call_1",
+                "name": "weather"
+            }
+        ])
+    );
+}
+
+#[test]
+fn test_tool_call_without_id_parses() {
+    let tool_call: ToolCall = serde_json::from_value(json!({
+        "function": { "name": "weather", "arguments": {} }
+    }))
+    .unwrap();
+
+    assert_eq!(tool_call.id, None);
+    assert_eq!(tool_call.call_type, None);
+}
+
+#[tokio::test]
+async fn test_string_arguments_reach_the_tool() {
+    let server = MockHttpServer::start(vec![MockResponse::json(
+        200,
+        json!({
+            "choices": [{
+                "message": {
+                    "role": "assistant",
+                    "content": "",
+                    "tool_calls": [{
+                        "id": "call_1",
+                        "type": "function",
+                        "function": { "name": "echo", "arguments": "{\"text\": \"hello\"}" }
+                    }]
+                },
+                "finish_reason": "tool_calls"
+            }]
+        }),
+    )])
+    .await;
+    let config = SystemConfig {
+        llm_config: LLMConfig {
+            provider: ProviderKind::OpenAICompatible {
+                base_url: server.base_url.clone(),
+            },
+            model: "test-model".to_string(),
+            ..LLMConfig::default()
+        },
+        agent_config: AgentConfig {
+            synthesis: SynthesisConfig {
+                enabled: false,
+                ..SynthesisConfig::default()
+            },
+            ..AgentConfig::default()
+        },
+        ..SystemConfig::default()
+    };
+    let tools = vec![Box::new(EchoTool) as Box<dyn LocalTool>];
+    let agentic_system = AgenticSystem::from_config(config, tools).await.unwrap();
+
+    let result = agentic_system.plan_and_execute("say hello").await.unwrap();
+
+    assert!(result.contains(r#""echoed_text":"hello""#), "{}", result);
+}
+
+#[tokio::test]
+async fn test_run_usage_sums_planner_and_synthesis() {
+    let body = response_fixture("openrouter_stop.json");
+    let server = MockHttpServer::start(vec![MockResponse::raw(200, &body)]).await;
+    let system = AgenticSystem::builder()
+        .tool(MockTool)
+        .llm_client(openai_compatible(&server))
+        .build()
+        .await
+        .unwrap();
+    assert_eq!(system.last_run_usage(), None);
+
+    system.plan_and_execute("any task").await.unwrap();
+
+    // One planning call and one synthesis call of 12 + 30 tokens each.
+    assert_eq!(server.requests().len(), 2);
+    assert_eq!(system.last_run_usage(), Some(Usage::new(24, 60)));
+}
+
+#[tokio::test]
+async fn test_run_stats_sum_ollama_timings() {
+    let body = response_fixture("ollama_stop.json");
+    let server = MockHttpServer::start(vec![MockResponse::raw(200, &body)]).await;
+    let client = LLMClient::from_ollama_at(server.base_url.clone(), OllamaModel::Qwen3_8B).unwrap();
+    let system = AgenticSystem::builder()
+        .tool(MockTool)
+        .llm_client(client)
+        .build()
+        .await
+        .unwrap();
+
+    system.plan_and_execute("any task").await.unwrap();
+
+    let totals = system.last_run_totals();
+    assert_eq!(totals.responses, 2);
+    let stats = system.last_run_stats().unwrap();
+    assert_eq!(stats.eval_count, Some(564));
+    assert_eq!(
+        stats.total_duration,
+        Some(Duration::from_nanos(2 * 4_883_583_458))
+    );
+}
+
+#[tokio::test]
+async fn test_nested_runs_count_for_the_outer_run() {
+    let body = response_fixture("ollama_stop.json");
+    let server = MockHttpServer::start(vec![MockResponse::raw(200, &body)]).await;
+    let client = LLMClient::from_ollama_at(server.base_url.clone(), OllamaModel::Qwen3_8B).unwrap();
+    let messages = vec![ChatMessage::user("hi".to_string())];
+
+    let ((_, inner), outer) = summing_run(async {
+        client
+            .chat_completions(messages.clone(), vec![])
+            .await
+            .unwrap();
+        summing_run(client.chat_completions(messages.clone(), vec![])).await
+    })
+    .await;
+
+    assert_eq!(inner.responses, 1);
+    assert_eq!(inner.stats.unwrap().eval_count, Some(282));
+    assert_eq!(outer.responses, 2);
+    assert_eq!(outer.usage, Some(Usage::new(52, 564)));
+}
+
+#[tokio::test]
+async fn test_run_usage_is_none_when_unreported() {
+    let server = MockHttpServer::start(vec![MockResponse::json(
+        200,
+        json!({"choices": [{"message": {"role": "assistant", "content": "done"}}]}),
+    )])
+    .await;
+    let system = AgenticSystem::builder()
+        .tool(MockTool)
+        .llm_client(openai_compatible(&server))
+        .build()
+        .await
+        .unwrap();
+
+    system.plan_and_execute("any task").await.unwrap();
+
+    assert_eq!(system.last_run_usage(), None);
+}
+
+fn response_fixture(name: &str) -> String {
+    let path = PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+        .join("tests")
+        .join("fixtures")
+        .join("responses")
+        .join(name);
+    std::fs::read_to_string(path).unwrap()
+}
+
+fn openai_compatible(server: &MockHttpServer) -> LLMClient {
+    LLMClient::from_config(&LLMConfig {
+        provider: ProviderKind::OpenAICompatible {
+            base_url: server.base_url.clone(),
+        },
+        model: "test-model".to_string(),
+        ..LLMConfig::default()
+    })
+    .unwrap()
+}
+
+#[tokio::test]
+async fn test_ollama_at_custom_address() {
+    let body = response_fixture("ollama_stop.json");
+    let server = MockHttpServer::start(vec![MockResponse::raw(200, &body)]).await;
+    let host_port = server.base_url.trim_start_matches("http://").to_string();
+
+    for address in [format!("{}/", server.base_url), host_port] {
+        let client = LLMClient::from_ollama_at(address.clone(), OllamaModel::Gemma3_4b).unwrap();
+        let response = client
+            .chat_completions(vec![ChatMessage::user("hi".to_string())], vec![])
+            .await;
+        assert!(response.is_ok(), "{}: {:?}", address, response);
+    }
+
+    let requests = server.requests();
+    assert_eq!(requests.len(), 2);
+    assert!(requests.iter().all(|request| request.path == "/api/chat"));
+}
+
+#[test]
+fn test_ollama_at_rejects_malformed_urls() {
+    for address in [
+        "http://",
+        "ftp://ollama:11434",
+        "localhost:99999",
+        "http://bad host",
+    ] {
+        let message = match LLMClient::from_ollama_at(address, OllamaModel::Gemma3_4b) {
+            Err(AgenticFlowError::ApiClientError(message)) => message,
+            other => panic!("{}: {:?}", address, other.err()),
+        };
+        assert!(message.contains(address), "{}", message);
+    }
+}
+
+#[tokio::test]
+async fn test_ollama_base_url_from_config() {
+    let body = response_fixture("ollama_stop.json");
+    let server = MockHttpServer::start(vec![MockResponse::raw(200, &body)]).await;
+    let config = LLMConfig {
+        base_url: Some(server.base_url.clone()),
+        ..LLMConfig::default()
+    };
+
+    let client = LLMClient::from_config(&config).unwrap();
+    client
+        .chat_completions(vec![ChatMessage::user("hi".to_string())], vec![])
+        .await
+        .unwrap();
+
+    assert_eq!(server.requests()[0].path, "/api/chat");
+    let malformed = LLMConfig {
+        base_url: Some("http://".to_string()),
+        ..LLMConfig::default()
+    };
+    assert!(matches!(
+        LLMClient::from_config(&malformed),
+        Err(AgenticFlowError::ApiClientError(_))
+    ));
+}
+
+#[tokio::test]
+async fn test_ollama_max_tokens_is_num_predict() {
+    let body = response_fixture("ollama_stop.json");
+    let server = MockHttpServer::start(vec![
+        MockResponse::raw(200, &body),
+        MockResponse::json(200, json!({"response": "hello"})),
+    ])
+    .await;
+    let client = LLMClient::from_ollama_at(server.base_url.clone(), OllamaModel::Gemma3_4b)
+        .unwrap()
+        .with_max_tokens(64);
+
+    client
+        .chat_completions(vec![ChatMessage::user("hi".to_string())], vec![])
+        .await
+        .unwrap();
+    client.completion("hi".to_string()).await.unwrap();
+
+    let requests = server.requests();
+    assert_eq!(requests[1].path, "/api/generate");
+    for request in &requests {
+        assert_eq!(request.body["options"]["num_predict"], 64);
+        assert!(request.body.get("max_tokens").is_none(), "{}", request.body);
+    }
+}
+
+fn large_context() -> OllamaOptions {
+    OllamaOptions {
+        num_ctx: Some(16384),
+        keep_alive: Some("10m".to_string()),
+        num_gpu: Some(0),
+        num_thread: Some(8),
+    }
+}
+
+#[tokio::test]
+async fn test_ollama_options_are_sent_with_chat_and_generate() {
+    let body = response_fixture("ollama_stop.json");
+    let server = MockHttpServer::start(vec![
+        MockResponse::raw(200, &body),
+        MockResponse::json(200, json!({"response": "hello"})),
+    ])
+    .await;
+    let client = LLMClient::from(
+        OllamaProvider::with_client(
+            reqwest::Client::new(),
+            OllamaModel::Gemma3_4b,
+            server.base_url.clone(),
+        )
+        .with_options(large_context()),
+    )
+    .with_temperature(0.0);
+
+    client
+        .chat_completions(vec![ChatMessage::user("hi".to_string())], vec![])
+        .await
+        .unwrap();
+    client.completion("hi".to_string()).await.unwrap();
+
+    let requests = server.requests();
+    let options = json!({
+        "temperature": 0.0,
+        "num_ctx": 16384,
+        "num_gpu": 0,
+        "num_thread": 8
+    });
+    assert_eq!(requests[0].body["options"], options);
+    assert_eq!(requests[0].body["keep_alive"], "10m");
+    assert_eq!(
+        requests[1].body,
+        json!({
+            "model": "gemma3:4b",
+            "prompt": "hi",
+            "stream": false,
+            "keep_alive": "10m",
+            "options": options
+        })
+    );
+}
+
+/// Runs against a local Ollama server with `gemma2:2b` when `AGENTIC_FLOW_LIVE_OLLAMA` is
+/// set.
+#[tokio::test]
+async fn test_live_ollama_accepts_options() {
+    if std::env::var_os("AGENTIC_FLOW_LIVE_OLLAMA").is_none() {
+        return;
+    }
+    let client = LLMClient::from(OllamaProvider::new(OllamaModel::Gemma2_2b).with_options(
+        OllamaOptions {
+            num_ctx: Some(8192),
+            keep_alive: Some("1m".to_string()),
+            ..OllamaOptions::default()
+        },
+    ));
+
+    let response = client
+        .chat_completions(vec![ChatMessage::user("Say hello.".to_string())], vec![])
+        .await
+        .unwrap();
+
+    assert!(!response.message().content.is_empty());
+}
+
+#[tokio::test]
+async fn test_openai_compatible_max_tokens() {
+    let body = response_fixture("openrouter_stop.json");
+    let server = MockHttpServer::start(vec![MockResponse::raw(200, &body)]).await;
+    let messages = vec![ChatMessage::user("hi".to_string())];
+
+    let client = openai_compatible(&server);
+    client
+        .chat_completions(messages.clone(), vec![])
+        .await
+        .unwrap();
+    let client = client.with_max_tokens(64);
+    client
+        .chat_completions(messages.clone(), vec![])
+        .await
+        .unwrap();
+    client
+        .chat_completions_with(
+            messages,
+            vec![],
+            &RequestOptions::default().with_max_tokens(128),
+        )
+        .await
+        .unwrap();
+
+    let requests = server.requests();
+    assert!(
+        requests[0].body.get("max_tokens").is_none(),
+        "{}",
+        requests[0].body
+    );
+    assert_eq!(requests[1].body["max_tokens"], 64);
+    assert_eq!(requests[2].body["max_tokens"], 128);
+}
+
+fn sampling() -> SamplingOptions {
+    SamplingOptions {
+        top_p: Some(0.5),
+        top_k: Some(20),
+        seed: Some(42),
+        repeat_penalty: Some(1.1),
+        ..SamplingOptions::default()
+    }
+}
+
+#[tokio::test]
+async fn test_ollama_sampling_goes_in_options() {
+    let body = response_fixture("ollama_stop.json");
+    let server = MockHttpServer::start(vec![MockResponse::raw(200, &body)]).await;
+    let client = LLMClient::from_ollama_at(server.base_url.clone(), OllamaModel::Gemma3_4b)
+        .unwrap()
+        .with_temperature(0.0)
+        .with_sampling(sampling());
+
+    client
+        .chat_completions(vec![ChatMessage::user("hi".to_string())], vec![])
+        .await
+        .unwrap();
+
+    let body = &server.requests()[0].body;
+    assert_eq!(body["options"]["temperature"], 0.0);
+    assert_eq!(body["options"]["seed"], 42);
+    assert_eq!(body["options"]["top_k"], 20);
+    assert_eq!(body["options"]["top_p"], 0.5);
+    assert!(body["options"]["repeat_penalty"].is_number(), "{}", body);
+    assert!(body.get("temperature").is_none(), "{}", body);
+    assert!(
+        body["options"].get("frequency_penalty").is_none(),
+        "{}",
+        body
+    );
+}
+
+#[tokio::test]
+async fn test_openai_compatible_skips_unsupported_sampling() {
+    let body = response_fixture("openrouter_stop.json");
+    let server = MockHttpServer::start(vec![MockResponse::raw(200, &body)]).await;
+
+    openai_compatible(&server)
+        .with_sampling(sampling())
+        .chat_completions(vec![ChatMessage::user("hi".to_string())], vec![])
+        .await
+        .unwrap();
+
+    let body = &server.requests()[0].body;
+    assert_eq!(body["seed"], 42);
+    assert_eq!(body["top_p"], 0.5);
+    for unsupported in ["top_k", "repeat_penalty", "repetition_penalty", "options"] {
+        assert!(body.get(unsupported).is_none(), "{}: {}", unsupported, body);
+    }
+}
+
+#[tokio::test]
+async fn test_ollama_stop_goes_in_options() {
+    let body = response_fixture("ollama_stop.json");
+    let server = MockHttpServer::start(vec![
+        MockResponse::raw(200, &body),
+        MockResponse::json(200, json!({"response": "hello"})),
+    ])
+    .await;
+    let client = LLMClient::from_ollama_at(server.base_url.clone(), OllamaModel::Gemma3_4b)
+        .unwrap()
+        .with_stop(vec!["\nPlan:".to_string()]);
+
+    client
+        .chat_completions(vec![ChatMessage::user("hi".to_string())], vec![])
+        .await
+        .unwrap();
+    client.completion("hi".to_string()).await.unwrap();
+
+    for request in server.requests() {
+        assert_eq!(request.body["options"]["stop"], json!(["\nPlan:"]));
+        assert!(request.body.get("stop").is_none(), "{}", request.body);
+    }
+}
+
+#[tokio::test]
+async fn test_openai_compatible_stop_and_per_call_override() {
+    let body = response_fixture("openrouter_stop.json");
+    let server = MockHttpServer::start(vec![MockResponse::raw(200, &body)]).await;
+    let client = openai_compatible(&server).with_stop(vec!["END".to_string()]);
+    let messages = vec![ChatMessage::user("hi".to_string())];
+
+    client
+        .chat_completions(messages.clone(), vec![])
+        .await
+        .unwrap();
+    let options = RequestOptions::default().with_stop(vec!["STOP".to_string()]);
+    client
+        .chat_completions_with(messages.clone(), vec![], &options)
+        .await
+        .unwrap();
+    let options = RequestOptions::default().with_stop(vec![]);
+    client
+        .chat_completions_with(messages, vec![], &options)
+        .await
+        .unwrap();
+
+    let requests = server.requests();
+    assert_eq!(requests[0].body["stop"], json!(["END"]));
+    assert_eq!(requests[1].body["stop"], json!(["STOP"]));
+    assert!(
+        requests[2].body.get("stop").is_none(),
+        "{}",
+        requests[2].body
+    );
+}
+
+#[tokio::test]
+async fn test_chain_of_thought_stops_before_the_plan() {
+    let body = response_fixture("openrouter_stop.json");
+    let server = MockHttpServer::start(vec![MockResponse::raw(200, &body)]).await;
+    let mut registry = ToolRegistry::new();
+    registry.register_local_tool(Box::new(MockTool));
+    let planner =
+        ChainOfThoughtPlanner::new(openai_compatible(&server), Arc::new(Mutex::new(registry)));
+
+    planner.plan("do the thing").await.unwrap();
+
+    let requests = server.requests();
+    assert_eq!(requests.len(), 2);
+    assert_eq!(requests[0].body["stop"], json!(["\nPlan:"]));
+    assert_eq!(requests[0].body["tool_choice"], json!("none"));
+    assert!(
+        requests[1].body.get("stop").is_none(),
+        "{}",
+        requests[1].body
+    );
+    assert!(
+        requests[1].body.get("tool_choice").is_none(),
+        "{}",
+        requests[1].body
+    );
+}
+
+#[test]
+fn test_max_tokens_from_config() {
+    let config = LLMConfig {
+        max_tokens: Some(512),
+        ..LLMConfig::default()
+    };
+
+    assert_eq!(
+        LLMClient::from_config(&config).unwrap().max_tokens(),
+        Some(512)
+    );
+    assert_eq!(LLMClient::default().max_tokens(), None);
+}
+
+#[tokio::test]
+async fn test_llama_cpp_chat_reads_tool_calls_json() {
+    let body = response_fixture("llama_cpp_tool_calls.json");
+    let server = MockHttpServer::start(vec![MockResponse::raw(200, &body)]).await;
+    let client = LLMClient::from(
+        LlamaCppProvider::with_base_url(&server.base_url)
+            .with_grammar(LlamaCppProvider::TOOL_CALL_GRAMMAR),
+    );
+
+    let messages = vec![
+        ChatMessage::system("Plan the task.".to_string()),
+        ChatMessage::user("Find rust docs".to_string()),
+    ];
+    let tools = vec![json!({
+        "type": "function",
+        "function": {"name": "search", "parameters": {"type": "object"}}
+    })];
+    let response = client.chat_completions(messages, tools).await.unwrap();
+
+    let tool_calls = response.message().tool_calls.as_ref().unwrap();
+    assert_eq!(tool_calls.len(), 2);
+    assert_eq!(tool_calls[0].function.name, "search");
+    assert_eq!(tool_calls[0].function.arguments, json!({"query": "rust"}));
+    assert_eq!(tool_calls[1].function.name, "list_files");
+    assert_eq!(response.message().content, "");
+
+    let request = &server.requests()[0];
+    assert_eq!(request.path, "/completion");
+    assert_eq!(request.body["grammar"], LlamaCppProvider::TOOL_CALL_GRAMMAR);
+    assert_eq!(request.body["stop"], json!(["<|im_end|>"]));
+    let prompt = request.body["prompt"].as_str().unwrap();
+    assert!(prompt.contains("\"name\":\"search\""), "{}", prompt);
+    assert!(prompt.contains("<|im_start|>system\nPlan the task.<|im_end|>\n"));
+    assert!(prompt.contains("<|im_start|>user\nFind rust docs<|im_end|>\n"));
+    assert!(prompt.ends_with("<|im_start|>assistant\n"));
+}
+
+#[tokio::test]
+async fn test_llama_cpp_completion_sends_raw_prompt() {
+    let body = response_fixture("llama_cpp_completion.json");
+    let server = MockHttpServer::start(vec![MockResponse::raw(200, &body)]).await;
+    let client = LLMClient::from(LlamaCppProvider::with_base_url(&server.base_url));
+
+    let response = client
+        .completion("Once upon a time".to_string())
+        .await
+        .unwrap();
+
+    assert_eq!(response.response(), " Here is the answer");
+    let request = &server.requests()[0];
+    assert_eq!(request.body["prompt"], "Once upon a time");
+    assert!(request.body.get("grammar").is_none());
+}
+
+#[tokio::test]
+async fn test_raw_response_keeps_dropped_fields() {
+    let body = response_fixture("openrouter_stop.json");
+    let server = MockHttpServer::start(vec![MockResponse::raw(200, &body)]).await;
+
+    let response = openai_compatible(&server)
+        .chat_completions(vec![ChatMessage::user("hi".to_string())], vec![])
+        .await
+        .unwrap();
+
+    let raw = response.raw().unwrap();
+    assert_eq!(raw["provider"], "OpenAI");
+    assert_eq!(raw["choices"][0]["native_finish_reason"], "stop");
+}
+
+#[tokio::test]
+async fn test_raw_responses_can_be_disabled() {
+    let body = response_fixture("openrouter_stop.json");
+    let server = MockHttpServer::start(vec![MockResponse::raw(200, &body)]).await;
+
+    let response = openai_compatible(&server)
+        .with_raw_responses(false)
+        .chat_completions(vec![ChatMessage::user("hi".to_string())], vec![])
+        .await
+        .unwrap();
+
+    assert!(response.raw().is_none());
+    assert_eq!(response.message().content, "Here is the answer");
+}
+
+fn answer_schema() -> serde_json::Value {
+    json!({
+        "type": "object",
+        "properties": { "answer": { "type": "string" } },
+        "required": ["answer"]
+    })
+}
+
+#[tokio::test]
+async fn test_ollama_output_format() {
+    let body = response_fixture("ollama_stop.json");
+    let server = MockHttpServer::start(vec![MockResponse::raw(200, &body)]).await;
+    let client = LLMClient::from_ollama_at(server.base_url.clone(), OllamaModel::Gemma3_4b)
+        .unwrap()
+        .with_output_format(OutputFormat::Json);
+    let messages = vec![ChatMessage::user("hi".to_string())];
+
+    client
+        .chat_completions(messages.clone(), vec![])
+        .await
+        .unwrap();
+    let options = RequestOptions::default().with_format(OutputFormat::Schema(answer_schema()));
+    client
+        .chat_completions_with(messages.clone(), vec![], &options)
+        .await
+        .unwrap();
+    let options = RequestOptions::default().with_format(OutputFormat::None);
+    client
+        .chat_completions_with(messages, vec![], &options)
+        .await
+        .unwrap();
+
+    let requests = server.requests();
+    assert_eq!(requests[0].body["format"], json!("json"));
+    assert_eq!(requests[1].body["format"], answer_schema());
+    for request in &requests {
+        assert!(
+            request.body.get("response_format").is_none(),
+            "{}",
+            request.body
+        );
+    }
+    assert!(
+        requests[2].body.get("format").is_none(),
+        "{}",
+        requests[2].body
+    );
+}
+
+#[tokio::test]
+async fn test_open_router_response_format() {
+    let body = response_fixture("openrouter_stop.json");
+    let server = MockHttpServer::start(vec![MockResponse::raw(200, &body)]).await;
+    let client =
+        LLMClient::from_open_router_at(&server.base_url, OpenRouterModel::GPTMini, "test-key");
+    let messages = vec![ChatMessage::user("hi".to_string())];
+
+    for format in [
+        OutputFormat::Json,
+        OutputFormat::Schema(answer_schema()),
+        OutputFormat::None,
+    ] {
+        let options = RequestOptions::default().with_format(format);
+        client
+            .chat_completions_with(messages.clone(), vec![], &options)
+            .await
+            .unwrap();
+    }
+
+    let requests = server.requests();
+    assert_eq!(requests[0].path, "/chat/completions");
+    assert_eq!(
+        requests[0].body["response_format"],
+        json!({ "type": "json_object" })
+    );
+    assert_eq!(
+        requests[1].body["response_format"],
+        json!({
+            "type": "json_schema",
+            "json_schema": { "name": "response", "strict": true, "schema": answer_schema() }
+        })
+    );
+    for request in &requests[..2] {
+        assert_eq!(
+            request.body["provider"],
+            json!({ "require_parameters": true })
+        );
+    }
+    assert!(
+        requests[2].body.get("response_format").is_none(),
+        "{}",
+        requests[2].body
+    );
+    assert!(
+        requests[2].body.get("provider").is_none(),
+        "{}",
+        requests[2].body
+    );
+}
+
+#[tokio::test]
+async fn test_open_router_unsupported_format_is_a_client_error() {
+    let server = MockHttpServer::start(vec![MockResponse::json(
+        404,
+        json!({
+            "error": {
+                "message": "No endpoints found that can handle the requested parameters.",
+                "code": 404
+            }
+        }),
+    )])
+    .await;
+    let client =
+        LLMClient::from_open_router_at(&server.base_url, OpenRouterModel::Flash2, "test-key")
+            .with_output_format(OutputFormat::Schema(answer_schema()));
+
+    let error = client
+        .chat_completions(vec![ChatMessage::user("hi".to_string())], vec![])
+        .await
+        .err()
+        .unwrap();
+
+    assert!(
+        matches!(&error, AgenticFlowError::ApiClientError(message)
+            if message.contains("'google/gemini-2.0-flash-001' does not support a JSON schema")),
+        "{:?}",
+        error
+    );
+}
+
+#[tokio::test]
+async fn test_anthropic_rejects_output_formats() {
+    let client = LLMClient::from_anthropic(AnthropicModel::ClaudeSonnet4)
+        .with_output_format(OutputFormat::Json);
+
+    let error = client
+        .chat_completions(vec![ChatMessage::user("hi".to_string())], vec![])
+        .await
+        .err()
+        .unwrap();
+
+    assert!(
+        matches!(&error, AgenticFlowError::ApiClientError(message)
+            if message.contains("does not support JSON output")),
+        "{:?}",
+        error
+    );
+}
+
+fn tool(name: &str) -> serde_json::Value {
+    json!({
+        "type": "function",
+        "function": { "name": name, "parameters": { "type": "object" } }
+    })
+}
+
+#[tokio::test]
+async fn test_open_router_tool_choice() {
+    let body = response_fixture("openrouter_tool_calls.json");
+    let server = MockHttpServer::start(vec![MockResponse::raw(200, &body)]).await;
+    let client =
+        LLMClient::from_open_router_at(&server.base_url, OpenRouterModel::GPTMini, "test-key");
+    let messages = vec![ChatMessage::user("hi".to_string())];
+    let tools = vec![tool("search"), tool("fetch")];
+
+    for tool_choice in [
+        ToolChoice::Auto,
+        ToolChoice::None,
+        ToolChoice::Required,
+        ToolChoice::Specific("search".to_string()),
+    ] {
+        let options = RequestOptions::default().with_tool_choice(tool_choice);
+        client
+            .chat_completions_with(messages.clone(), tools.clone(), &options)
+            .await
+            .unwrap();
+    }
+    let options = RequestOptions::default().with_tool_choice(ToolChoice::Required);
+    client
+        .chat_completions_with(messages, vec![], &options)
+        .await
+        .unwrap();
+
+    let requests = server.requests();
+    assert!(
+        requests[0].body.get("tool_choice").is_none(),
+        "{}",
+        requests[0].body
+    );
+    assert_eq!(requests[1].body["tool_choice"], json!("none"));
+    assert_eq!(requests[2].body["tool_choice"], json!("required"));
+    assert_eq!(
+        requests[3].body["tool_choice"],
+        json!({ "type": "function", "function": { "name": "search" } })
+    );
+    assert!(
+        requests[4].body.get("tool_choice").is_none(),
+        "{}",
+        requests[4].body
+    );
+    for request in &requests[..4] {
+        assert_eq!(request.body["tools"], json!(tools));
+    }
+}
+
+#[tokio::test]
+async fn test_ollama_narrows_tools_for_a_tool_choice() {
+    let body = response_fixture("ollama_stop.json");
+    let server = MockHttpServer::start(vec![MockResponse::raw(200, &body)]).await;
+    let client = LLMClient::from_ollama_at(server.base_url.clone(), OllamaModel::Gemma3_4b)
+        .unwrap()
+        .with_tool_emulation(ToolEmulation::Never);
+    let messages = vec![ChatMessage::user("hi".to_string())];
+    let tools = vec![tool("search"), tool("fetch")];
+
+    for tool_choice in [
+        ToolChoice::None,
+        ToolChoice::Specific("fetch".to_string()),
+        ToolChoice::Required,
+    ] {
+        let options = RequestOptions::default().with_tool_choice(tool_choice);
+        client
+            .chat_completions_with(messages.clone(), tools.clone(), &options)
+            .await
+            .unwrap();
+    }
+
+    let requests = server.requests();
+    assert_eq!(requests[0].body["tools"], json!([]));
+    assert_eq!(requests[1].body["tools"], json!([tool("fetch")]));
+    assert_eq!(requests[2].body["tools"], json!(tools));
+    for request in &requests {
+        assert!(
+            request.body.get("tool_choice").is_none(),
+            "{}",
+            request.body
+        );
+    }
+}
+
+#[tokio::test]
+async fn test_multistep_planner_requires_a_tool_call() {
+    let body = response_fixture("openrouter_tool_calls.json");
+    let server = MockHttpServer::start(vec![MockResponse::raw(200, &body)]).await;
+    let mut registry = ToolRegistry::new();
+    registry.register_local_tool(Box::new(MockTool));
+    let planner = MultiStepPlanner::new(openai_compatible(&server), Arc::new(Mutex::new(registry)));
+
+    planner.plan("do the thing").await.unwrap();
+
+    assert_eq!(server.requests()[0].body["tool_choice"], json!("required"));
+}
+
+#[tokio::test]
+async fn test_parallel_tool_calls_flag() {
+    let body = response_fixture("openrouter_parallel_tool_calls.json");
+    let server = MockHttpServer::start(vec![MockResponse::raw(200, &body)]).await;
+    let client = openai_compatible(&server);
+    let messages = vec![ChatMessage::user("hi".to_string())];
+    let tools = vec![tool("search")];
+
+    client
+        .chat_completions(messages.clone(), tools.clone())
+        .await
+        .unwrap();
+    let client = client.with_parallel_tool_calls(false);
+    client
+        .chat_completions(messages.clone(), tools)
+        .await
+        .unwrap();
+    client.chat_completions(messages, vec![]).await.unwrap();
+
+    let requests = server.requests();
+    assert!(
+        requests[0].body.get("parallel_tool_calls").is_none(),
+        "{}",
+        requests[0].body
+    );
+    assert_eq!(requests[1].body["parallel_tool_calls"], json!(false));
+    assert!(
+        requests[2].body.get("parallel_tool_calls").is_none(),
+        "{}",
+        requests[2].body
+    );
+}
+
+#[tokio::test]
+async fn test_planner_gets_tool_calls_split_across_choices() {
+    let body = response_fixture("openrouter_split_tool_calls.json");
+    let server = MockHttpServer::start(vec![MockResponse::raw(200, &body)]).await;
+    let mut registry = ToolRegistry::new();
+    registry.register_local_tool(Box::new(MockTool));
+    let planner = MultiStepPlanner::new(openai_compatible(&server), Arc::new(Mutex::new(registry)));
+
+    let steps = planner.plan("do the thing").await.unwrap();
+
+    let tool_names: Vec<&str> = steps.iter().map(|step| step.tool_name.as_str()).collect();
+    assert_eq!(tool_names, vec!["search", "fetch"]);
+}
+
+#[tokio::test]
+async fn test_open_router_sends_the_key_it_was_given() {
+    let body = response_fixture("openrouter_stop.json");
+    let server = MockHttpServer::start(vec![MockResponse::raw(200, &body)]).await;
+    let client =
+        LLMClient::from_open_router_at(&server.base_url, OpenRouterModel::GPTMini, "tenant-key");
+
+    client
+        .chat_completions(vec![ChatMessage::user("hi".to_string())], vec![])
+        .await
+        .unwrap();
+
+    assert_eq!(
+        server.requests()[0].header("authorization"),
+        Some("Bearer tenant-key")
+    );
+}
+
+#[tokio::test]
+async fn test_missing_api_key_fails_before_sending() {
+    let server = MockHttpServer::start(vec![]).await;
+    let client = LLMClient::from_config(&LLMConfig {
+        provider: ProviderKind::OpenAICompatible {
+            base_url: server.base_url.clone(),
+        },
+        model: "test-model".to_string(),
+        api_key_env: Some("AGENTIC_FLOW_TEST_UNSET_KEY".to_string()),
+        ..LLMConfig::default()
+    })
+    .unwrap();
+
+    let error = client
+        .chat_completions(vec![ChatMessage::user("hi".to_string())], vec![])
+        .await
+        .err()
+        .unwrap();
+
+    assert!(
+        matches!(&error, AgenticFlowError::ApiClientError(message)
+            if message.starts_with("missing API key for")
+                && message.contains("AGENTIC_FLOW_TEST_UNSET_KEY")),
+        "{:?}",
+        error
+    );
+    assert!(server.requests().is_empty());
+}
+
+#[tokio::test]
+async fn test_open_router_attribution_headers() {
+    let body = response_fixture("openrouter_stop.json");
+    let server = MockHttpServer::start(vec![MockResponse::raw(200, &body)]).await;
+    let messages = vec![ChatMessage::user("hi".to_string())];
+    let attributed = LLMClient::from(
+        OpenRouterProvider::new(OpenRouterModel::Flash2)
+            .with_base_url(&server.base_url)
+            .with_api_key("test-key")
+            .with_app_url("https://example.com")
+            .with_app_name("Example"),
+    );
+    let anonymous =
+        LLMClient::from_open_router_at(&server.base_url, OpenRouterModel::Flash2, "test-key");
+
+    attributed
+        .chat_completions(messages.clone(), vec![])
+        .await
+        .unwrap();
+    anonymous.chat_completions(messages, vec![]).await.unwrap();
+
+    let requests = server.requests();
+    assert_eq!(
+        requests[0].header("http-referer"),
+        Some("https://example.com")
+    );
+    assert_eq!(requests[0].header("x-title"), Some("Example"));
+    assert_eq!(requests[0].header("authorization"), Some("Bearer test-key"));
+    assert_eq!(requests[1].header("http-referer"), None);
+    assert_eq!(requests[1].header("x-title"), None);
+}
+
+fn looking_at_a_chart() -> Vec<ChatMessage> {
+    vec![
+        ChatMessage::system("Describe images briefly.".to_string()),
+        ChatMessage::user_with_images(
+            "What does this chart show?".to_string(),
+            vec![ImageData::new("image/png", "iVBORw0KGgo=")],
+        ),
+    ]
+}
+
+#[tokio::test]
+async fn test_ollama_images_are_base64_strings() {
+    let body = response_fixture("ollama_stop.json");
+    let server = MockHttpServer::start(vec![MockResponse::raw(200, &body)]).await;
+    let client =
+        LLMClient::from_ollama_at(server.base_url.clone(), OllamaModel::Gemma3_4b).unwrap();
+
+    client
+        .chat_completions(looking_at_a_chart(), vec![])
+        .await
+        .unwrap();
+
+    let messages = &server.requests()[0].body["messages"];
+    assert_eq!(messages[1]["content"], "What does this chart show?");
+    assert_eq!(messages[1]["images"], json!(["iVBORw0KGgo="]));
+    assert!(messages[0].get("images").is_none(), "{}", messages[0]);
+}
+
+#[tokio::test]
+async fn test_open_router_images_are_content_parts() {
+    let body = response_fixture("openrouter_stop.json");
+    let server = MockHttpServer::start(vec![MockResponse::raw(200, &body)]).await;
+    let client =
+        LLMClient::from_open_router_at(&server.base_url, OpenRouterModel::Flash2, "test-key");
+
+    client
+        .chat_completions(looking_at_a_chart(), vec![])
+        .await
+        .unwrap();
+
+    let messages = &server.requests()[0].body["messages"];
+    // Messages without images keep the string content providers without vision expect.
+    assert_eq!(messages[0]["content"], "Describe images briefly.");
+    assert_eq!(
+        messages[1]["content"],
+        json!([
+            { "type": "text", "text": "What does this chart show?" },
+            { "type": "image_url", "image_url": { "url": "data:image/png;base64,iVBORw0KGgo=" } }
+        ])
+    );
+    assert!(messages[1].get("images").is_none(), "{}", messages[1]);
+}
+
+fn ollama_answer(message: serde_json::Value) -> MockResponse {
+    MockResponse::json(
+        200,
+        json!({ "model": "qwen3:8b", "message": message, "done_reason": "stop", "done": true }),
+    )
+}
+
+#[tokio::test]
+async fn test_ollama_think_blocks_move_to_thinking() {
+    let server = MockHttpServer::start(vec![
+        ollama_answer(json!({
+            "role": "assistant",
+            "content": "<think>First idea.</think>\n\nThe answer<think> Second idea. </think> is 42."
+        })),
+        ollama_answer(json!({
+            "role": "assistant",
+            "content": "Partial answer <think>still reasoning when the tokens ran out"
+        })),
+    ])
+    .await;
+    let client = LLMClient::from_ollama_at(server.base_url.clone(), OllamaModel::Qwen3_8B).unwrap();
+    let hi = vec![ChatMessage::user("hi".to_string())];
+
+    let closed = client.chat_completions(hi.clone(), vec![]).await.unwrap();
+    let unterminated = client.chat_completions(hi, vec![]).await.unwrap();
+
+    assert_eq!(closed.message().content, "The answer is 42.");
+    assert_eq!(
+        closed.message().thinking.as_deref(),
+        Some("First idea.\n\nSecond idea.")
+    );
+    assert_eq!(unterminated.message().content, "Partial answer");
+    assert_eq!(
+        unterminated.message().thinking.as_deref(),
+        Some("still reasoning when the tokens ran out")
+    );
+    assert!(server.requests()[0].body.get("think").is_none());
+}
+
+#[tokio::test]
+async fn test_ollama_native_thinking_is_requested() {
+    let server = MockHttpServer::start(vec![ollama_answer(json!({
+        "role": "assistant",
+        "content": "42",
+        "thinking": "6 times 7."
+    }))])
+    .await;
+    let client = LLMClient::from_ollama_at(server.base_url.clone(), OllamaModel::Qwen3_8B)
+        .unwrap()
+        .with_thinking(true);
+
+    let response = client
+        .chat_completions(vec![ChatMessage::user("6 * 7?".to_string())], vec![])
+        .await
+        .unwrap();
+
+    assert_eq!(server.requests()[0].body["think"], true);
+    assert_eq!(response.message().content, "42");
+    assert_eq!(response.message().thinking.as_deref(), Some("6 times 7."));
+}
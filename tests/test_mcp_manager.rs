@@ -0,0 +1,127 @@
+use std::ffi::OsStr;
+
+use agentic_flow_lib::{
+    config::{ConfigFormat, ServerConfig, ServerType, SystemConfig},
+    mcp_manager::build_server_command,
+};
+
+fn server(server_type: ServerType) -> ServerConfig {
+    ServerConfig {
+        server_type,
+        module_name: None,
+        package_name: None,
+        command: None,
+        args: vec!["--port".to_string(), "8080".to_string()],
+        env: [("API_TOKEN".to_string(), "secret".to_string())].into(),
+        secrets: Default::default(),
+        auto_install: false,
+        config: None,
+    }
+}
+
+fn program_and_args(config: &ServerConfig) -> (String, Vec<String>) {
+    let command = build_server_command(config).unwrap();
+    let command = command.as_std();
+    (
+        command.get_program().to_string_lossy().to_string(),
+        command
+            .get_args()
+            .map(|arg| arg.to_string_lossy().to_string())
+            .collect(),
+    )
+}
+
+#[test]
+fn test_python_args_follow_module() {
+    let mut config = server(ServerType::Python);
+    config.module_name = Some("mcp_server_fetch".to_string());
+
+    let (program, args) = program_and_args(&config);
+
+    assert_eq!(program, "python");
+    assert_eq!(args, vec!["-m", "mcp_server_fetch", "--port", "8080"]);
+}
+
+#[test]
+fn test_node_args_follow_package() {
+    let mut config = server(ServerType::Node);
+    config.package_name = Some("@modelcontextprotocol/server-filesystem".to_string());
+    config.args = vec!["/tmp".to_string(), "--read-only".to_string()];
+
+    let (program, args) = program_and_args(&config);
+
+    assert_eq!(program, "npx");
+    assert_eq!(
+        args,
+        vec![
+            "-y",
+            "@modelcontextprotocol/server-filesystem",
+            "/tmp",
+            "--read-only"
+        ]
+    );
+}
+
+#[test]
+fn test_command_args_follow_program() {
+    let mut config = server(ServerType::Command);
+    config.command = Some("uvx".to_string());
+    config.args = vec![
+        "mcp-server-git".to_string(),
+        "--repository".to_string(),
+        ".".to_string(),
+    ];
+
+    let (program, args) = program_and_args(&config);
+
+    assert_eq!(program, "uvx");
+    assert_eq!(args, vec!["mcp-server-git", "--repository", "."]);
+}
+
+#[test]
+fn test_env_is_applied() {
+    let mut config = server(ServerType::Command);
+    config.command = Some("my-server".to_string());
+
+    let command = build_server_command(&config).unwrap();
+    let envs: Vec<_> = command.as_std().get_envs().collect();
+
+    assert_eq!(
+        envs,
+        vec![(OsStr::new("API_TOKEN"), Some(OsStr::new("secret")))]
+    );
+}
+
+#[test]
+fn test_missing_base_command_is_an_error() {
+    assert!(build_server_command(&server(ServerType::Python)).is_err());
+    assert!(build_server_command(&server(ServerType::Node)).is_err());
+    assert!(build_server_command(&server(ServerType::Command)).is_err());
+}
+
+#[test]
+fn test_args_and_env_default_and_expand() {
+    // SAFETY: the variable name is unique to this test.
+    unsafe { std::env::set_var("AGENTIC_FLOW_TEST_ROOT", "/srv/data") };
+    let contents = r#"
+        [mcp_config.servers.plain]
+        server_type = "Python"
+        module_name = "mcp_server_fetch"
+
+        [mcp_config.servers.filesystem]
+        server_type = "Node"
+        package_name = "@modelcontextprotocol/server-filesystem"
+        args = ["${AGENTIC_FLOW_TEST_ROOT}"]
+        env = { LOG_LEVEL = "${AGENTIC_FLOW_TEST_LOG_LEVEL:-info}" }
+    "#;
+
+    let config = SystemConfig::parse(contents, ConfigFormat::Toml).unwrap();
+
+    let plain = &config.mcp_config.servers["plain"];
+    assert!(plain.args.is_empty());
+    assert!(plain.env.is_empty());
+
+    let filesystem = &config.mcp_config.servers["filesystem"];
+    assert_eq!(filesystem.args, vec!["/srv/data"]);
+    assert_eq!(filesystem.env["LOG_LEVEL"], "info");
+}
@@ -0,0 +1,98 @@
+use std::path::PathBuf;
+
+use serde_json::json;
+
+use agentic_flow_lib::{
+    config::{MCPConfig, ServerConfig, ServerType},
+    errors::AgenticFlowError,
+};
+
+fn fixture_path() -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+        .join("tests")
+        .join("fixtures")
+        .join("claude_desktop_config.json")
+}
+
+#[test]
+fn test_import_mcp_servers_file() {
+    let config = MCPConfig::from_mcp_servers_file(fixture_path()).unwrap();
+
+    assert_eq!(config.servers.len(), 2);
+
+    let filesystem = &config.servers["filesystem"];
+    assert_eq!(filesystem.server_type, ServerType::Command);
+    assert_eq!(filesystem.command.as_deref(), Some("npx"));
+    assert_eq!(
+        filesystem.args,
+        vec![
+            "-y",
+            "@modelcontextprotocol/server-filesystem",
+            "/Users/me/Desktop"
+        ]
+    );
+    assert!(filesystem.env.is_empty());
+
+    let fetch = &config.servers["fetch"];
+    assert_eq!(fetch.server_type, ServerType::Command);
+    assert_eq!(fetch.command.as_deref(), Some("uvx"));
+    assert_eq!(fetch.args, vec!["mcp-server-fetch", "--ignore-robots-txt"]);
+    assert_eq!(fetch.env["HTTP_PROXY"], "http://proxy.local:8080");
+}
+
+#[test]
+fn test_mcp_servers_round_trip() {
+    let imported = MCPConfig::from_mcp_servers_file(fixture_path()).unwrap();
+
+    let exported = imported.to_mcp_servers_json().unwrap();
+    let reimported = MCPConfig::from_mcp_servers_json(&exported).unwrap();
+
+    assert_eq!(reimported, imported);
+    assert!(exported["mcpServers"]["fetch"].get("disabled").is_none());
+}
+
+#[test]
+fn test_export_resolves_typed_servers() {
+    let mut config = MCPConfig::default();
+    config.servers.insert(
+        "brave".to_string(),
+        ServerConfig {
+            server_type: ServerType::Python,
+            module_name: Some("mcp_server_brave_search".to_string()),
+            package_name: None,
+            command: None,
+            args: vec!["--verbose".to_string()],
+            env: [("BRAVE_API_KEY".to_string(), "key".to_string())].into(),
+            secrets: Default::default(),
+            auto_install: false,
+            config: None,
+        },
+    );
+
+    let exported = config.to_mcp_servers_json().unwrap();
+
+    assert_eq!(
+        exported,
+        json!({
+            "mcpServers": {
+                "brave": {
+                    "command": "python",
+                    "args": ["-m", "mcp_server_brave_search", "--verbose"],
+                    "env": { "BRAVE_API_KEY": "key" }
+                }
+            }
+        })
+    );
+}
+
+#[test]
+fn test_import_requires_command() {
+    let value = json!({ "mcpServers": { "remote": { "url": "https://example.com/mcp" } } });
+
+    let err = MCPConfig::from_mcp_servers_json(&value).unwrap_err();
+
+    match err {
+        AgenticFlowError::ConfigError(message) => assert!(message.contains("'remote'")),
+        other => panic!("unexpected error: {:?}", other),
+    }
+}
@@ -0,0 +1,366 @@
+mod common;
+
+use std::sync::{
+    Arc,
+    atomic::{AtomicUsize, Ordering},
+};
+
+use serde_json::json;
+use tokio::sync::Mutex;
+
+use agentic_flow_lib::{
+    errors::AgenticFlowError,
+    llm_client::{LLMClient, MockLLMProvider},
+    model::{ChatMessage, ToolCall},
+    planner::{
+        LLMJudgeEvaluator, LLMStepGenerator, MctsStrategy, MonteCarloTreeSearchPlanner,
+        PlanEvaluator, PlanStep, Planner, StepGenerator,
+    },
+    tool_registry::ToolRegistry,
+};
+
+use common::tools::{EchoTool, MockTool};
+
+fn echo(text: &str) -> PlanStep {
+    PlanStep::new("echo", json!({ "text": text }))
+}
+
+/// Offers `a`, then `b`, then the end of the plan after any steps, and counts its calls.
+#[derive(Default)]
+struct AOrB {
+    calls: Arc<AtomicUsize>,
+}
+
+#[async_trait::async_trait]
+impl StepGenerator for AOrB {
+    async fn next_steps(
+        &self,
+        _task: &str,
+        _steps: &[PlanStep],
+    ) -> Result<Vec<Option<PlanStep>>, AgenticFlowError> {
+        self.calls.fetch_add(1, Ordering::SeqCst);
+        Ok(vec![Some(echo("a")), Some(echo("b")), None])
+    }
+}
+
+/// The share of three steps that echo `b`.
+struct CountsBs;
+
+#[async_trait::async_trait]
+impl PlanEvaluator for CountsBs {
+    async fn score(&self, _task: &str, plan: &[PlanStep]) -> Result<f64, AgenticFlowError> {
+        let bs = plan
+            .iter()
+            .filter(|step| step.params["text"] == "b")
+            .count();
+        Ok(bs as f64 / 3.0)
+    }
+}
+
+fn planner(simulations: usize) -> MonteCarloTreeSearchPlanner {
+    MonteCarloTreeSearchPlanner::new(
+        LLMClient::from(MockLLMProvider::new()),
+        Arc::new(Mutex::new(ToolRegistry::new())),
+        simulations,
+    )
+    .with_max_depth(3)
+    .with_evaluator(Box::new(CountsBs))
+}
+
+#[tokio::test]
+async fn test_tree_search_finds_the_best_path() {
+    let steps = planner(200)
+        .with_step_generator(AOrB::default())
+        .plan("echo b three times")
+        .await
+        .unwrap();
+
+    assert_eq!(steps, vec![echo("b"), echo("b"), echo("b")]);
+}
+
+#[tokio::test]
+async fn test_one_simulation_expands_the_root_and_rolls_out_its_first_child() {
+    let generator = AOrB::default();
+    let calls = generator.calls.clone();
+
+    let steps = planner(1)
+        .with_step_generator(generator)
+        .plan("any task")
+        .await
+        .unwrap();
+
+    // The default rollout takes the first candidate until the depth limit.
+    assert_eq!(steps, vec![echo("a"), echo("a"), echo("a")]);
+    assert_eq!(calls.load(Ordering::SeqCst), 3);
+}
+
+#[tokio::test]
+async fn test_tree_search_fails_only_when_every_simulation_does() {
+    struct Failing;
+
+    #[async_trait::async_trait]
+    impl StepGenerator for Failing {
+        async fn next_steps(
+            &self,
+            _task: &str,
+            _steps: &[PlanStep],
+        ) -> Result<Vec<Option<PlanStep>>, AgenticFlowError> {
+            Err(AgenticFlowError::PlanningFailed {
+                planner: "mcts".to_string(),
+                phase: "expand",
+                source: Box::new(AgenticFlowError::PlanningError("no".to_string())),
+                diagnostics: Box::default(),
+            })
+        }
+    }
+
+    let error = planner(4)
+        .with_step_generator(Failing)
+        .plan("any task")
+        .await
+        .unwrap_err();
+
+    assert_eq!(error.planning_diagnostics().unwrap().retries, 3);
+}
+
+#[tokio::test]
+async fn test_llm_step_generator_expands_and_rolls_out() {
+    let calling = |names: &[(&str, &str)]| {
+        let calls = names
+            .iter()
+            .map(|(name, text)| ToolCall::new(name.to_string(), json!({ "text": text })))
+            .collect();
+        ChatMessage::assistant(String::new()).with_tool_calls(calls)
+    };
+    let mock = MockLLMProvider::new().with_chat_responses(vec![
+        calling(&[("echo", "one")]),
+        calling(&[("done", "")]),
+        calling(&[("echo", "two"), ("done", ""), ("echo", "ignored")]),
+    ]);
+    let mut registry = ToolRegistry::new();
+    registry.register_local_tool(Box::new(MockTool));
+    registry.register_local_tool(Box::new(EchoTool));
+    let registry = Arc::new(Mutex::new(registry));
+    let client = LLMClient::from(mock.clone());
+    let generator = LLMStepGenerator::new(client.clone(), registry.clone()).with_branching(2);
+
+    let steps = MonteCarloTreeSearchPlanner::new(client, registry, 1)
+        .with_step_generator(generator)
+        .plan("echo twice")
+        .await
+        .unwrap();
+
+    assert_eq!(steps, vec![echo("one"), echo("two")]);
+    let calls = mock.calls();
+    assert_eq!(calls.len(), 3);
+    assert!(calls[0].messages[1].content.contains("(no steps yet)"));
+    assert!(
+        calls[2].messages[1]
+            .content
+            .contains("1. echo {\"text\":\"one\"}")
+    );
+    assert!(calls[2].messages[0].content.contains("Complete the plan"));
+}
+
+#[tokio::test]
+async fn test_judge_prefers_a_complete_plan_to_a_short_one() {
+    let short = ChatMessage::assistant(String::new()).with_tool_calls(vec![ToolCall::new(
+        "mock_tool".to_string(),
+        json!({"foo": "fetch"}),
+    )]);
+    let complete = ChatMessage::assistant(String::new()).with_tool_calls(vec![
+        ToolCall::new("mock_tool".to_string(), json!({"foo": "fetch"})),
+        ToolCall::new("echo".to_string(), json!({"text": "summary"})),
+    ]);
+    let planner_mock = MockLLMProvider::new().with_chat_responses(vec![short, complete]);
+    let judge_mock = MockLLMProvider::new().with_chat_responses(vec![
+        ChatMessage::assistant("Nothing reports the result.\nScore: 3/10".to_string()),
+        ChatMessage::assistant("Covers the task.\n0.9".to_string()),
+    ]);
+    let mut registry = ToolRegistry::new();
+    registry.register_local_tool(Box::new(MockTool));
+    registry.register_local_tool(Box::new(EchoTool));
+    let planner = MonteCarloTreeSearchPlanner::new(
+        LLMClient::from(planner_mock),
+        Arc::new(Mutex::new(registry)),
+        2,
+    )
+    .with_strategy(MctsStrategy::Sampling)
+    .with_evaluator(Box::new(LLMJudgeEvaluator::new(LLMClient::from(
+        judge_mock.clone(),
+    ))));
+
+    let steps = planner.plan("fetch and summarize").await.unwrap();
+
+    assert_eq!(steps.len(), 2);
+    assert_eq!(steps[1], echo("summary"));
+    let calls = judge_mock.calls();
+    assert_eq!(calls.len(), 2);
+    assert!(calls[0].messages[1].content.contains("1. mock_tool"));
+    assert!(calls[0].messages[0].content.contains("redundant"));
+}
+
+#[tokio::test]
+async fn test_judge_reads_scores_in_several_forms() {
+    let answers = [
+        ("0.75", 0.75),
+        ("Coverage is good.\nOverall: 80%", 0.8),
+        ("Score: 7 out of 10", 0.7),
+        ("8.5/10.", 0.85),
+        ("1", 1.0),
+        ("Rating: 1.5", 0.15),
+    ];
+    let mock = MockLLMProvider::new().with_chat_responses(
+        answers
+            .iter()
+            .map(|(answer, _)| ChatMessage::assistant(answer.to_string()))
+            .chain([ChatMessage::assistant("I cannot tell.".to_string())])
+            .collect(),
+    );
+    let judge = LLMJudgeEvaluator::new(LLMClient::from(mock));
+    let plan = [echo("hi")];
+
+    for (answer, expected) in answers {
+        let score = judge.score("say hi", &plan).await.unwrap();
+        assert!(
+            (score - expected).abs() < 1e-9,
+            "{:?} gave {}",
+            answer,
+            score
+        );
+    }
+    assert!(matches!(
+        judge.score("say hi", &plan).await,
+        Err(AgenticFlowError::PlanningError(message)) if message.contains("I cannot tell.")
+    ));
+}
+
+/// Offers `a` or `b`, and records the most calls it was in at once.
+#[derive(Default)]
+struct Slow {
+    running: Arc<AtomicUsize>,
+    peak: Arc<AtomicUsize>,
+}
+
+#[async_trait::async_trait]
+impl StepGenerator for Slow {
+    async fn next_steps(
+        &self,
+        _task: &str,
+        _steps: &[PlanStep],
+    ) -> Result<Vec<Option<PlanStep>>, AgenticFlowError> {
+        let running = self.running.fetch_add(1, Ordering::SeqCst) + 1;
+        self.peak.fetch_max(running, Ordering::SeqCst);
+        tokio::time::sleep(std::time::Duration::from_millis(20)).await;
+        self.running.fetch_sub(1, Ordering::SeqCst);
+        Ok(vec![Some(echo("a")), Some(echo("b")), None])
+    }
+}
+
+#[tokio::test]
+async fn test_tree_search_runs_simulations_from_different_nodes_at_once() {
+    // After the root, the second round rolls out `b` and `a` together; the end of the
+    // plan needs no calls.
+    for (max_concurrency, expected_peak) in [(1, 1), (3, 2)] {
+        let generator = Slow::default();
+        let peak = generator.peak.clone();
+
+        let steps = planner(12)
+            .with_max_concurrency(max_concurrency)
+            .with_step_generator(generator)
+            .plan("echo b three times")
+            .await
+            .unwrap();
+
+        assert_eq!(peak.load(Ordering::SeqCst), expected_peak);
+        assert!(!steps.is_empty());
+    }
+}
+
+#[tokio::test]
+async fn test_concurrent_tree_search_is_deterministic() {
+    let first = planner(60)
+        .with_max_concurrency(4)
+        .with_step_generator(AOrB::default())
+        .plan("echo b three times")
+        .await
+        .unwrap();
+    let second = planner(60)
+        .with_max_concurrency(4)
+        .with_step_generator(AOrB::default())
+        .plan("echo b three times")
+        .await
+        .unwrap();
+
+    assert_eq!(first, vec![echo("b"), echo("b"), echo("b")]);
+    assert_eq!(first, second);
+}
+
+/// Scores every plan the same, but takes longer the fewer steps a plan has, and fails
+/// plans that echo `fail`.
+struct SlowerForShorterPlans;
+
+#[async_trait::async_trait]
+impl PlanEvaluator for SlowerForShorterPlans {
+    async fn score(&self, _task: &str, plan: &[PlanStep]) -> Result<f64, AgenticFlowError> {
+        let delay = 60u64.saturating_sub(20 * plan.len() as u64);
+        tokio::time::sleep(std::time::Duration::from_millis(delay)).await;
+        if plan.iter().any(|step| step.params["text"] == "fail") {
+            return Err(AgenticFlowError::PlanningError("cannot score".to_string()));
+        }
+        Ok(0.5)
+    }
+}
+
+fn sampling(responses: Vec<ChatMessage>, simulations: usize) -> MonteCarloTreeSearchPlanner {
+    let mut registry = ToolRegistry::new();
+    registry.register_local_tool(Box::new(EchoTool));
+    MonteCarloTreeSearchPlanner::new(
+        LLMClient::from(MockLLMProvider::new().with_chat_responses(responses)),
+        Arc::new(Mutex::new(registry)),
+        simulations,
+    )
+    .with_strategy(MctsStrategy::Sampling)
+    .with_evaluator(Box::new(SlowerForShorterPlans))
+}
+
+fn echoing(texts: &[&str]) -> ChatMessage {
+    let calls = texts
+        .iter()
+        .map(|text| ToolCall::new("echo".to_string(), json!({ "text": text })))
+        .collect();
+    ChatMessage::assistant(String::new()).with_tool_calls(calls)
+}
+
+#[tokio::test]
+async fn test_sampling_breaks_ties_by_simulation_order() {
+    // The first plan is scored last, as it is the shortest.
+    let responses = vec![
+        echoing(&["first"]),
+        echoing(&["second", "plan"]),
+        echoing(&["fail"]),
+    ];
+
+    let steps = sampling(responses, 3).plan("any task").await.unwrap();
+
+    assert_eq!(steps, vec![echo("first")]);
+}
+
+#[tokio::test]
+async fn test_sampling_fails_with_every_failure_when_no_simulation_succeeds() {
+    let responses = vec![echoing(&["fail"]), echoing(&["fail", "fail"])];
+
+    let error = sampling(responses, 2).plan("any task").await.unwrap_err();
+
+    match &error {
+        AgenticFlowError::PlanningFailed { phase, source, .. } => {
+            assert_eq!(*phase, "simulate");
+            let message = source.to_string();
+            assert!(message.contains("All 2 simulations failed"), "{}", message);
+            assert!(message.contains("simulation 1: Planning error: cannot score"));
+            assert!(message.contains("simulation 2: "));
+        }
+        other => panic!("unexpected error: {:?}", other),
+    }
+    assert_eq!(error.planning_diagnostics().unwrap().retries, 1);
+}
@@ -0,0 +1,805 @@
+use std::{path::PathBuf, time::Duration};
+
+use serde_json::json;
+
+use agentic_flow_lib::model::{
+    AnthropicResponse, ChatMessage, ChatResponse, ChatStreamChunk, CompletionResponse,
+    FinishReason, Function, GeminiResponse, LlamaCppResponse, MessageAccumulator,
+    OllamaCompletionResponse, OllamaResponse, OllamaStreamChunk, OpenRouterCompletionResponse,
+    OpenRouterResponse, OpenRouterStreamChunk, Role, ToolCall, Usage,
+};
+use agentic_flow_lib::planner::PlanStep;
+use agentic_flow_lib::tool_registry::ExecutionContext;
+
+fn fixture(name: &str) -> String {
+    read_fixture("responses", name)
+}
+
+fn read_fixture(dir: &str, name: &str) -> String {
+    let path = PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+        .join("tests")
+        .join("fixtures")
+        .join(dir)
+        .join(name);
+    std::fs::read_to_string(path).unwrap()
+}
+
+/// Replays a captured OpenRouter event stream, skipping comments and the `[DONE]` marker.
+fn replay_openrouter(name: &str) -> MessageAccumulator {
+    let mut accumulator = MessageAccumulator::new();
+    for line in read_fixture("streams", name).lines() {
+        let data = line.strip_prefix("data: ").filter(|data| *data != "[DONE]");
+        if let Some(data) = data {
+            let chunk: OpenRouterStreamChunk = serde_json::from_str(data).unwrap();
+            accumulator.push(ChatStreamChunk::from(chunk));
+        }
+    }
+    accumulator
+}
+
+fn replay_ollama(name: &str) -> MessageAccumulator {
+    let mut accumulator = MessageAccumulator::new();
+    for line in read_fixture("streams", name).lines() {
+        let chunk: OllamaStreamChunk = serde_json::from_str(line).unwrap();
+        accumulator.push(ChatStreamChunk::from(chunk));
+    }
+    accumulator
+}
+
+fn assert_same_message(streamed: &ChatMessage, expected: &ChatMessage) {
+    assert_eq!(streamed.role, expected.role);
+    assert_eq!(streamed.content, expected.content);
+    assert_eq!(
+        serde_json::to_value(&streamed.tool_calls).unwrap(),
+        serde_json::to_value(&expected.tool_calls).unwrap()
+    );
+}
+
+#[test]
+fn test_roles_round_trip() {
+    let cases = [
+        (Role::System, "system"),
+        (Role::User, "user"),
+        (Role::Assistant, "assistant"),
+        (Role::Tool, "tool"),
+    ];
+
+    for (role, wire) in cases {
+        assert_eq!(serde_json::to_value(&role).unwrap(), json!(wire));
+        assert_eq!(serde_json::from_value::<Role>(json!(wire)).unwrap(), role);
+    }
+}
+
+#[test]
+fn test_unknown_role_round_trips() {
+    let role: Role = serde_json::from_value(json!("developer")).unwrap();
+
+    assert_eq!(role, Role::Other("developer".to_string()));
+    assert_eq!(serde_json::to_value(&role).unwrap(), json!("developer"));
+}
+
+#[test]
+fn test_constructors_serialize_wire_roles() {
+    let messages = [
+        ChatMessage::system("s".to_string()),
+        ChatMessage::user("u".to_string()),
+        ChatMessage::assistant("a".to_string()),
+    ];
+
+    let roles: Vec<_> = messages
+        .iter()
+        .map(|message| serde_json::to_value(message).unwrap()["role"].clone())
+        .collect();
+
+    assert_eq!(
+        roles,
+        vec![json!("system"), json!("user"), json!("assistant")]
+    );
+}
+
+#[test]
+fn test_response_with_unknown_role_parses() {
+    let message: ChatMessage = serde_json::from_value(json!({
+        "role": "ipython",
+        "content": "42",
+        "thinking": null
+    }))
+    .unwrap();
+
+    assert_eq!(message.role, Role::Other("ipython".to_string()));
+    assert_eq!(message.role.to_string(), "ipython");
+}
+
+#[test]
+fn test_openrouter_finish_reasons() {
+    let cases = [
+        ("openrouter_stop.json", FinishReason::Stop),
+        ("openrouter_length.json", FinishReason::Length),
+        ("openrouter_tool_calls.json", FinishReason::ToolCalls),
+        (
+            "openrouter_content_filter.json",
+            FinishReason::ContentFilter,
+        ),
+    ];
+
+    for (name, expected) in cases {
+        let response: OpenRouterResponse = serde_json::from_str(&fixture(name)).unwrap();
+
+        assert_eq!(response.finish_reason(), Some(expected), "{}", name);
+        assert_eq!(response.model(), Some("openai/gpt-4o-mini"));
+    }
+}
+
+#[test]
+fn test_ollama_done_reasons() {
+    let cases = [
+        ("ollama_stop.json", FinishReason::Stop),
+        ("ollama_length.json", FinishReason::Length),
+    ];
+
+    for (name, expected) in cases {
+        let response: OllamaResponse = serde_json::from_str(&fixture(name)).unwrap();
+
+        assert_eq!(response.finish_reason(), Some(expected), "{}", name);
+        assert_eq!(response.model(), Some("qwen3:8b"));
+    }
+}
+
+#[test]
+fn test_unknown_and_missing_finish_reasons() {
+    let response: OpenRouterResponse = serde_json::from_value(json!({
+        "choices": [{
+            "message": { "role": "assistant", "content": "" },
+            "finish_reason": "error"
+        }]
+    }))
+    .unwrap();
+    let ollama: OllamaResponse = serde_json::from_value(json!({
+        "message": { "role": "assistant", "content": "" }
+    }))
+    .unwrap();
+
+    assert_eq!(
+        response.finish_reason(),
+        Some(FinishReason::Other("error".to_string()))
+    );
+    assert_eq!(response.model(), None);
+    assert_eq!(ollama.finish_reason(), None);
+}
+
+#[test]
+fn test_tool_call_arguments_from_both_providers() {
+    let openrouter: OpenRouterResponse =
+        serde_json::from_str(&fixture("openrouter_tool_calls.json")).unwrap();
+    let ollama: OllamaResponse = serde_json::from_str(&fixture("ollama_tool_calls.json")).unwrap();
+
+    for message in [openrouter.message(), ollama.message()] {
+        let tool_calls = message.tool_calls.as_ref().unwrap();
+        assert_eq!(tool_calls[0].function.arguments, json!({"query": "rust"}));
+    }
+}
+
+fn plan_tool_names(response: &OpenRouterResponse) -> Vec<String> {
+    response
+        .message()
+        .tool_calls
+        .iter()
+        .flatten()
+        .map(|tool_call| PlanStep::from(tool_call).tool_name)
+        .collect()
+}
+
+#[test]
+fn test_parallel_tool_calls_in_one_choice() {
+    let response: OpenRouterResponse =
+        serde_json::from_str(&fixture("openrouter_parallel_tool_calls.json")).unwrap();
+
+    assert_eq!(
+        plan_tool_names(&response),
+        vec!["search", "fetch", "summarize"]
+    );
+    assert_eq!(response.message().content, "");
+    assert_eq!(response.finish_reason(), Some(FinishReason::ToolCalls));
+}
+
+#[test]
+fn test_tool_calls_split_across_choices_are_merged() {
+    let response: OpenRouterResponse =
+        serde_json::from_str(&fixture("openrouter_split_tool_calls.json")).unwrap();
+
+    assert_eq!(plan_tool_names(&response), vec!["search", "fetch"]);
+    let tool_calls = response.message().tool_calls.as_ref().unwrap();
+    assert_eq!(tool_calls[1].id.as_deref(), Some("call_2"));
+    assert_eq!(
+        tool_calls[1].function.arguments,
+        json!({"url": "https://www.rust-lang.org"})
+    );
+}
+
+#[test]
+fn test_argument_strings_are_normalized() {
+    let cases = [
+        (json!(""), json!({})),
+        (json!("  "), json!({})),
+        (json!("{\"a\": 1}"), json!({"a": 1})),
+        (json!("not json"), json!("not json")),
+        (json!({"a": 1}), json!({"a": 1})),
+    ];
+
+    for (arguments, expected) in cases {
+        let function: Function =
+            serde_json::from_value(json!({ "name": "f", "arguments": arguments })).unwrap();
+        assert_eq!(function.arguments, expected);
+        assert!(!function.repaired);
+    }
+}
+
+#[test]
+fn test_almost_valid_argument_strings_are_repaired() {
+    let function: Function = serde_json::from_value(
+        json!({ "name": "f", "arguments": "Here you go: {'city': 'Paris', days: 3,}" }),
+    )
+    .unwrap();
+
+    assert_eq!(function.arguments, json!({"city": "Paris", "days": 3}));
+    assert!(function.repaired);
+}
+
+#[test]
+fn test_unrepairable_argument_strings_fail_with_both_texts() {
+    let error = serde_json::from_value::<Function>(
+        json!({ "name": "f", "arguments": "{\"city\": Paris de France}" }),
+    )
+    .unwrap_err()
+    .to_string();
+
+    assert!(error.contains("Paris de France"), "{}", error);
+    assert!(error.contains("attempted repair"), "{}", error);
+}
+
+#[test]
+fn test_openrouter_stream_matches_response() {
+    let cases = [
+        (
+            "openrouter_text.sse",
+            "openrouter_stop.json",
+            FinishReason::Stop,
+        ),
+        (
+            "openrouter_tool_calls.sse",
+            "openrouter_tool_calls.json",
+            FinishReason::ToolCalls,
+        ),
+    ];
+
+    for (stream, response, finish_reason) in cases {
+        let expected: OpenRouterResponse = serde_json::from_str(&fixture(response)).unwrap();
+        let accumulator = replay_openrouter(stream);
+
+        assert_eq!(
+            accumulator.finish_reason(),
+            Some(&finish_reason),
+            "{}",
+            stream
+        );
+        assert_eq!(accumulator.usage(), Some(&Usage::new(12, 30)));
+        assert_same_message(&accumulator.finish(), expected.message());
+    }
+}
+
+#[test]
+fn test_ollama_stream_matches_response() {
+    let cases = [
+        ("ollama_text.ndjson", "ollama_stop.json"),
+        ("ollama_tool_calls.ndjson", "ollama_tool_calls.json"),
+    ];
+
+    for (stream, response) in cases {
+        let expected: OllamaResponse = serde_json::from_str(&fixture(response)).unwrap();
+        let accumulator = replay_ollama(stream);
+
+        assert_eq!(
+            accumulator.finish_reason(),
+            Some(&FinishReason::Stop),
+            "{}",
+            stream
+        );
+        assert_same_message(&accumulator.finish(), expected.message());
+    }
+}
+
+#[test]
+fn test_ollama_final_chunk_carries_usage() {
+    let accumulator = replay_ollama("ollama_text.ndjson");
+
+    assert_eq!(accumulator.usage(), Some(&Usage::new(26, 282)));
+}
+
+#[test]
+fn test_accumulator_merges_tool_call_fragments_by_index() {
+    let mut accumulator = MessageAccumulator::new();
+    let chunks = [
+        json!({"choices": [{"delta": {"tool_calls": [
+            {"index": 0, "id": "call_1", "type": "function", "function": {"name": "search", "arguments": "{\"q\""}},
+            {"index": 1, "id": "call_2", "type": "function", "function": {"name": "fetch"}}
+        ]}}]}),
+        json!({"choices": [{"delta": {"tool_calls": [
+            {"index": 1, "function": {"arguments": "{}"}},
+            {"index": 0, "function": {"arguments": ": 1}"}}
+        ]}}]}),
+    ];
+    for chunk in chunks {
+        let chunk: OpenRouterStreamChunk = serde_json::from_value(chunk).unwrap();
+        accumulator.push(chunk.into());
+    }
+
+    let message = accumulator.finish();
+
+    assert_eq!(message.role, Role::Assistant);
+    assert_eq!(
+        serde_json::to_value(message.tool_calls.unwrap()).unwrap(),
+        json!([
+            {"id": "call_1", "type": "function", "function": {"name": "search", "arguments": {"q": 1}}},
+            {"id": "call_2", "type": "function", "function": {"name": "fetch", "arguments": {}}}
+        ])
+    );
+}
+
+#[test]
+fn test_openrouter_usage() {
+    let response: OpenRouterResponse =
+        serde_json::from_str(&fixture("openrouter_stop.json")).unwrap();
+    let cached: OpenRouterResponse =
+        serde_json::from_str(&fixture("openrouter_cached_usage.json")).unwrap();
+
+    assert_eq!(response.usage(), Some(Usage::new(12, 30)));
+    assert_eq!(
+        cached.usage(),
+        Some(Usage {
+            prompt_tokens: 1200,
+            completion_tokens: 40,
+            total_tokens: 1240,
+            cached_tokens: Some(1024),
+        })
+    );
+}
+
+#[test]
+fn test_openrouter_usage_fields_default() {
+    let cases = [
+        (
+            json!({"prompt_tokens": 5, "completion_tokens": 7}),
+            Usage::new(5, 7),
+        ),
+        (json!({"completion_tokens": 7}), Usage::new(0, 7)),
+        (json!({}), Usage::default()),
+    ];
+
+    for (usage, expected) in cases {
+        let parsed: Usage = serde_json::from_value(usage.clone()).unwrap();
+        assert_eq!(parsed, expected, "{}", usage);
+    }
+}
+
+#[test]
+fn test_usage_adds_up() {
+    let cached = Usage {
+        cached_tokens: Some(4),
+        ..Usage::new(10, 1)
+    };
+
+    assert_eq!(Usage::new(1, 2) + Usage::new(3, 4), Usage::new(4, 6));
+    assert_eq!(
+        Usage::new(1, 2) + cached,
+        Usage {
+            cached_tokens: Some(4),
+            ..Usage::new(11, 3)
+        }
+    );
+}
+
+#[test]
+fn test_missing_openrouter_usage_is_none() {
+    let response: OpenRouterResponse = serde_json::from_value(json!({
+        "choices": [{"message": {"role": "assistant", "content": "hi"}}]
+    }))
+    .unwrap();
+
+    assert_eq!(response.usage(), None);
+}
+
+#[test]
+fn test_ollama_usage() {
+    let response: OllamaResponse = serde_json::from_str(&fixture("ollama_stop.json")).unwrap();
+    let without_counts: OllamaResponse =
+        serde_json::from_str(&fixture("ollama_tool_calls.json")).unwrap();
+    let cached_prompt: OllamaResponse = serde_json::from_value(json!({
+        "message": {"role": "assistant", "content": "hi"},
+        "eval_count": 3
+    }))
+    .unwrap();
+
+    assert_eq!(response.usage(), Some(Usage::new(26, 282)));
+    assert_eq!(without_counts.usage(), None);
+    assert_eq!(cached_prompt.usage(), Some(Usage::new(0, 3)));
+}
+
+#[test]
+fn test_ollama_stats() {
+    let response: OllamaResponse = serde_json::from_str(&fixture("ollama_stop.json")).unwrap();
+    let without_timings: OllamaResponse = serde_json::from_value(json!({
+        "message": {"role": "assistant", "content": "hi"}
+    }))
+    .unwrap();
+
+    let stats = response.stats().unwrap();
+    assert_eq!(
+        stats.total_duration,
+        Some(Duration::from_nanos(4_883_583_458))
+    );
+    assert_eq!(stats.load_duration, Some(Duration::from_nanos(1_334_875)));
+    assert_eq!(stats.prompt_eval_count, Some(26));
+    assert_eq!(stats.eval_count, Some(282));
+    assert_eq!(
+        stats.eval_duration,
+        Some(Duration::from_nanos(4_535_599_000))
+    );
+    let rate = stats.eval_tokens_per_second().unwrap();
+    assert!((rate - 62.17).abs() < 0.01, "{}", rate);
+    assert_eq!(without_timings.stats(), None);
+}
+
+#[test]
+fn test_llama_cpp_stats_and_sums() {
+    let response: LlamaCppResponse =
+        serde_json::from_str(&fixture("llama_cpp_completion.json")).unwrap();
+
+    let stats = response.stats().unwrap();
+    assert_eq!(stats.prompt_eval_count, Some(4));
+    assert_eq!(stats.eval_duration, Some(Duration::from_micros(88_100)));
+    assert_eq!(stats.total_duration, None);
+
+    let sum = stats + stats;
+    assert_eq!(sum.eval_count, Some(8));
+    assert_eq!(sum.eval_duration, Some(Duration::from_micros(176_200)));
+    assert_eq!(sum.load_duration, None);
+}
+
+#[test]
+fn test_completion_usage() {
+    let openrouter: OpenRouterCompletionResponse = serde_json::from_value(json!({
+        "id": "gen-1",
+        "choices": [{"text": "hi", "index": 0, "finish_reason": "stop"}],
+        "usage": {"prompt_tokens": 4, "completion_tokens": 1, "total_tokens": 5}
+    }))
+    .unwrap();
+    let ollama: OllamaCompletionResponse = serde_json::from_value(json!({
+        "response": "hi",
+        "prompt_eval_count": 4,
+        "eval_count": 1
+    }))
+    .unwrap();
+
+    assert_eq!(openrouter.usage(), Some(Usage::new(4, 1)));
+    assert_eq!(ollama.usage(), Some(Usage::new(4, 1)));
+}
+
+#[test]
+fn test_builder_sets_every_field() {
+    let message = ChatMessage::builder(Role::Assistant)
+        .content("done")
+        .thinking("let me see")
+        .tool_calls(vec![ToolCall::new("search".to_string(), json!({}))])
+        .build();
+    let tool_result = ChatMessage::builder(Role::Tool)
+        .content("sunny")
+        .tool_call_id("call_1")
+        .name("weather")
+        .build();
+
+    assert_eq!(
+        serde_json::to_value(&message).unwrap(),
+        json!({
+            "role": "assistant",
+            "content": "done",
+            "thinking": "let me see",
+            "tool_calls": [{"function": {"name": "search", "arguments": {}}}]
+        })
+    );
+    assert_eq!(
+        serde_json::to_value(&tool_result).unwrap(),
+        serde_json::to_value(
+            ChatMessage::tool("sunny".to_string(), "call_1".to_string())
+                .with_name("weather".to_string())
+        )
+        .unwrap()
+    );
+}
+
+#[test]
+fn test_builder_defaults_match_constructors() {
+    let built = ChatMessage::builder(Role::User).content("hi").build();
+
+    assert_eq!(
+        serde_json::to_value(&built).unwrap(),
+        json!({"role": "user", "content": "hi", "thinking": null})
+    );
+    assert_eq!(
+        serde_json::to_value(&built).unwrap(),
+        serde_json::to_value(ChatMessage::user("hi".to_string())).unwrap()
+    );
+}
+
+#[test]
+fn test_is_tool_call() {
+    let call = ChatMessage::assistant("".to_string())
+        .with_tool_calls(vec![ToolCall::new("search".to_string(), json!({}))]);
+    let empty = ChatMessage::assistant("".to_string()).with_tool_calls(vec![]);
+
+    assert!(call.is_tool_call());
+    assert!(!empty.is_tool_call());
+    assert!(!ChatMessage::assistant("hi".to_string()).is_tool_call());
+}
+
+#[test]
+fn test_text_strips_think_blocks() {
+    let cases = [
+        ("Here is the answer", "Here is the answer"),
+        (
+            "<think>hmm</think>\n\nHere is the answer",
+            "Here is the answer",
+        ),
+        ("A <think>x</think>B<think>y</think> C", "A B C"),
+        ("Answer <think>still thinking", "Answer"),
+    ];
+
+    for (content, expected) in cases {
+        assert_eq!(ChatMessage::assistant(content.to_string()).text(), expected);
+    }
+}
+
+#[test]
+fn test_truncated_cuts_content_and_thinking() {
+    let message = ChatMessage::builder(Role::Assistant)
+        .content("abcdefgh")
+        .thinking("ünïcödé")
+        .build();
+
+    let truncated = message.truncated(3);
+
+    assert_eq!(truncated.content, "abc... [truncated]");
+    assert_eq!(truncated.thinking.as_deref(), Some("ünï... [truncated]"));
+    assert_eq!(message.truncated(100).content, "abcdefgh");
+}
+
+#[test]
+fn test_plan_step_json_is_pinned() {
+    let step = PlanStep::new("search", json!({"query": "rust"}))
+        .with_id("fetch-docs")
+        .with_description("Look up the docs");
+
+    let json = serde_json::to_value(&step).unwrap();
+
+    assert_eq!(
+        json,
+        json!({
+            "id": "fetch-docs",
+            "tool_name": "search",
+            "params": {"query": "rust"},
+            "description": "Look up the docs"
+        })
+    );
+    assert_eq!(serde_json::from_value::<PlanStep>(json).unwrap(), step);
+}
+
+#[test]
+fn test_minimal_plan_step_round_trips() {
+    let step = PlanStep::new("search", json!({}));
+
+    let json = serde_json::to_value(&step).unwrap();
+
+    assert_eq!(json, json!({"tool_name": "search", "params": {}}));
+    assert_eq!(serde_json::from_value::<PlanStep>(json).unwrap(), step);
+    assert_eq!(
+        serde_json::from_value::<PlanStep>(json!({"tool_name": "search"})).unwrap(),
+        PlanStep::new("search", json!(null))
+    );
+}
+
+#[test]
+fn test_execution_context_round_trips_as_object() {
+    let mut context = ExecutionContext::new();
+    context.set(
+        "1: search".to_string(),
+        json!({"hits": [{"title": "a", "tags": ["x", "y"]}, {"title": "b", "tags": []}]}),
+    );
+    context.set("echoed_text".to_string(), json!("hello"));
+    context.set("count".to_string(), json!(2));
+
+    let json = serde_json::to_value(&context).unwrap();
+
+    assert_eq!(json["1: search"]["hits"][0]["tags"], json!(["x", "y"]));
+    assert_eq!(json["echoed_text"], json!("hello"));
+    assert_eq!(json.as_object().unwrap().len(), 3);
+    assert_eq!(
+        serde_json::from_value::<ExecutionContext>(json).unwrap(),
+        context
+    );
+}
+
+#[test]
+fn test_openai_chat_response() {
+    let response: OpenRouterResponse =
+        serde_json::from_str(&fixture("openai_tool_calls.json")).unwrap();
+
+    let message = response.message();
+    assert_eq!(message.content, "");
+    assert_eq!(
+        serde_json::to_value(message.tool_calls.as_ref().unwrap()).unwrap(),
+        json!([{
+            "id": "call_abc123",
+            "type": "function",
+            "function": {"name": "search", "arguments": {"query": "rust"}}
+        }])
+    );
+    assert_eq!(response.finish_reason(), Some(FinishReason::ToolCalls));
+    assert_eq!(response.model(), Some("gpt-4o-mini-2024-07-18"));
+    assert_eq!(
+        response.usage(),
+        Some(Usage {
+            cached_tokens: Some(0),
+            ..Usage::new(82, 17)
+        })
+    );
+}
+
+#[test]
+fn test_openai_completion_response() {
+    let response: OpenRouterCompletionResponse =
+        serde_json::from_str(&fixture("openai_completion.json")).unwrap();
+
+    assert_eq!(response.response(), "Here is the answer");
+    assert_eq!(response.usage(), Some(Usage::new(5, 7)));
+}
+
+#[test]
+fn test_groq_string_arguments_are_decoded() {
+    let response: OpenRouterResponse =
+        serde_json::from_str(&fixture("groq_tool_calls.json")).unwrap();
+
+    let tool_calls = response.message().tool_calls.as_ref().unwrap();
+    assert_eq!(tool_calls[0].function.arguments, json!({"query": "rust"}));
+    assert_eq!(tool_calls[1].function.arguments, json!({}));
+    assert_eq!(response.message().content, "");
+    assert_eq!(response.finish_reason(), Some(FinishReason::ToolCalls));
+    assert_eq!(response.usage(), Some(Usage::new(224, 38)));
+}
+
+#[test]
+fn test_anthropic_tool_use_becomes_tool_calls() {
+    let response: AnthropicResponse =
+        serde_json::from_str(&fixture("anthropic_tool_use.json")).unwrap();
+
+    let message = response.message();
+    assert_eq!(message.role, Role::Assistant);
+    assert_eq!(message.content, "I'll search for that.");
+    assert_eq!(
+        message.thinking.as_deref(),
+        Some("The user wants docs, so search.")
+    );
+    assert_eq!(
+        serde_json::to_value(message.tool_calls.as_ref().unwrap()).unwrap(),
+        json!([{
+            "id": "toolu_01A09q90qw90lq917835lq9",
+            "type": "function",
+            "function": {"name": "search", "arguments": {"query": "rust"}}
+        }])
+    );
+    assert_eq!(response.finish_reason(), Some(FinishReason::ToolCalls));
+    assert_eq!(response.model(), Some("claude-sonnet-4-20250514"));
+    assert_eq!(
+        ChatResponse::usage(&response),
+        Some(Usage {
+            cached_tokens: Some(256),
+            ..Usage::new(412, 64)
+        })
+    );
+}
+
+#[test]
+fn test_anthropic_max_tokens_is_length() {
+    let response: AnthropicResponse =
+        serde_json::from_str(&fixture("anthropic_max_tokens.json")).unwrap();
+
+    assert_eq!(response.finish_reason(), Some(FinishReason::Length));
+    assert_eq!(response.response(), "Here is the ans");
+    assert!(response.message().tool_calls.is_none());
+}
+
+#[test]
+fn test_anthropic_stop_reasons() {
+    let cases = [
+        ("end_turn", FinishReason::Stop),
+        ("stop_sequence", FinishReason::Stop),
+        ("refusal", FinishReason::ContentFilter),
+        ("pause_turn", FinishReason::Other("pause_turn".to_string())),
+    ];
+
+    for (stop_reason, expected) in cases {
+        let response: AnthropicResponse = serde_json::from_value(json!({
+            "content": [{"type": "text", "text": "hi"}, {"type": "server_tool_use"}],
+            "stop_reason": stop_reason
+        }))
+        .unwrap();
+        assert_eq!(response.finish_reason(), Some(expected), "{}", stop_reason);
+        assert_eq!(response.message().content, "hi");
+    }
+}
+
+#[test]
+fn test_llama_cpp_completion_response() {
+    let response: LlamaCppResponse =
+        serde_json::from_str(&fixture("llama_cpp_completion.json")).unwrap();
+
+    assert_eq!(response.response(), " Here is the answer");
+    assert_eq!(response.finish_reason(), FinishReason::Length);
+    assert_eq!(
+        response.usage(),
+        Some(Usage {
+            cached_tokens: Some(8),
+            ..Usage::new(12, 4)
+        })
+    );
+
+    let older: LlamaCppResponse =
+        serde_json::from_value(json!({"content": "hi", "stopped_limit": true})).unwrap();
+    assert_eq!(older.finish_reason(), FinishReason::Length);
+    assert_eq!(older.usage(), None);
+}
+
+#[test]
+fn test_gemini_parallel_function_calls() {
+    let response: GeminiResponse =
+        serde_json::from_str(&fixture("gemini_parallel_calls.json")).unwrap();
+
+    let message = response.message();
+    assert_eq!(message.role, Role::Assistant);
+    assert_eq!(message.content, "");
+    assert_eq!(message.thinking.as_deref(), Some("Comparing both."));
+    let tool_calls = message.tool_calls.as_ref().unwrap();
+    assert_eq!(tool_calls.len(), 2);
+    assert_eq!(tool_calls[0].function.name, "search");
+    assert_eq!(tool_calls[0].function.arguments, json!({"query": "rust"}));
+    assert_eq!(tool_calls[1].function.name, "fetch");
+    assert_eq!(
+        tool_calls[1].function.arguments,
+        json!({"url": "https://www.rust-lang.org"})
+    );
+    assert_eq!(response.finish_reason(), Some(FinishReason::ToolCalls));
+    assert_eq!(response.model(), Some("gemini-2.0-flash"));
+    assert_eq!(
+        ChatResponse::usage(&response),
+        Some(Usage {
+            prompt_tokens: 120,
+            completion_tokens: 42,
+            total_tokens: 162,
+            cached_tokens: None,
+        })
+    );
+}
+
+#[test]
+fn test_gemini_max_tokens_is_length() {
+    let response: GeminiResponse = serde_json::from_str(&fixture("gemini_text.json")).unwrap();
+
+    assert_eq!(response.finish_reason(), Some(FinishReason::Length));
+    assert_eq!(response.response(), "Here is the answer");
+    assert!(response.message().tool_calls.is_none());
+    assert_eq!(
+        CompletionResponse::usage(&response),
+        Some(Usage {
+            cached_tokens: Some(6),
+            ..Usage::new(8, 4)
+        })
+    );
+}
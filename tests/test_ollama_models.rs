@@ -0,0 +1,161 @@
+mod common;
+
+use std::sync::{Arc, Mutex};
+
+use serde_json::json;
+
+use agentic_flow_lib::{
+    AgenticSystem,
+    config::{LLMConfig, SystemConfig},
+    errors::AgenticFlowError,
+    llm_client::{LLMClient, MockLLMProvider, OllamaModel, PullProgress},
+};
+
+use common::http_server::{MockHttpServer, MockResponse};
+
+fn tags(names: &[&str]) -> MockResponse {
+    let models: Vec<_> = names.iter().map(|name| json!({ "name": name })).collect();
+    MockResponse::json(200, json!({ "models": models }))
+}
+
+fn qwen_at(server: &MockHttpServer) -> LLMClient {
+    LLMClient::from_ollama_at(server.base_url.clone(), OllamaModel::Qwen3_8B).unwrap()
+}
+
+#[tokio::test]
+async fn test_available_models_lists_the_tags() {
+    let server = MockHttpServer::start(vec![tags(&["qwen3:8b", "gemma3:4b"])]).await;
+
+    let models = qwen_at(&server).available_models().await.unwrap();
+
+    assert_eq!(models, vec!["qwen3:8b", "gemma3:4b"]);
+    let requests = server.requests();
+    assert_eq!(requests[0].method, "GET");
+    assert_eq!(requests[0].path, "/api/tags");
+}
+
+#[tokio::test]
+async fn test_missing_model_error_suggests_ollama_pull() {
+    let server = MockHttpServer::start(vec![tags(&["gemma3:4b"])]).await;
+
+    let error = qwen_at(&server).ensure_model(None).await.err().unwrap();
+
+    assert!(
+        matches!(&error, AgenticFlowError::ApiClientError(message)
+            if message.contains("'qwen3:8b'") && message.contains("`ollama pull qwen3:8b`")),
+        "{:?}",
+        error
+    );
+    assert_eq!(server.requests().len(), 1);
+}
+
+#[tokio::test]
+async fn test_untagged_models_match_their_latest_tag() {
+    let server = MockHttpServer::start(vec![tags(&["mistral:latest"])]).await;
+    let client = LLMClient::from_ollama_at(
+        server.base_url.clone(),
+        OllamaModel::Custom("mistral".to_string()),
+    )
+    .unwrap();
+
+    client.ensure_model(None).await.unwrap();
+}
+
+#[tokio::test]
+async fn test_ensure_model_pulls_with_progress() {
+    let pull = concat!(
+        "{\"status\":\"pulling manifest\"}\n",
+        "{\"status\":\"pulling a1b2\",\"digest\":\"sha256:a1b2\",\"total\":100,\"completed\":40}\n",
+        "{\"status\":\"success\"}\n",
+    );
+    let server =
+        MockHttpServer::start(vec![tags(&["gemma3:4b"]), MockResponse::raw(200, pull)]).await;
+    let seen: Arc<Mutex<Vec<PullProgress>>> = Arc::default();
+    let record = {
+        let seen = seen.clone();
+        move |progress: &PullProgress| seen.lock().unwrap().push(progress.clone())
+    };
+
+    qwen_at(&server).ensure_model(Some(&record)).await.unwrap();
+
+    let seen = seen.lock().unwrap();
+    let statuses: Vec<&str> = seen
+        .iter()
+        .map(|progress| progress.status.as_str())
+        .collect();
+    assert_eq!(
+        statuses,
+        vec!["pulling manifest", "pulling a1b2", "success"]
+    );
+    assert_eq!(seen[1].completed, Some(40));
+    assert_eq!(seen[1].total, Some(100));
+    let requests = server.requests();
+    assert_eq!(requests[1].path, "/api/pull");
+    assert_eq!(
+        requests[1].body,
+        json!({ "model": "qwen3:8b", "stream": true })
+    );
+}
+
+#[tokio::test]
+async fn test_failed_pull_names_the_model() {
+    let pull = "{\"status\":\"pulling manifest\"}\n{\"error\":\"file does not exist\"}\n";
+    let server = MockHttpServer::start(vec![tags(&[]), MockResponse::raw(200, pull)]).await;
+
+    let error = qwen_at(&server)
+        .ensure_model(Some(&|_: &PullProgress| {}))
+        .await
+        .err()
+        .unwrap();
+
+    assert!(
+        matches!(&error, AgenticFlowError::ApiClientError(message)
+            if message == "pulling model 'qwen3:8b' failed: file does not exist"),
+        "{:?}",
+        error
+    );
+}
+
+#[tokio::test]
+async fn test_verify_skips_providers_that_cannot_list_models() {
+    let client = LLMClient::from(MockLLMProvider::new());
+
+    client.verify().await.unwrap();
+    assert!(client.available_models().await.is_err());
+}
+
+#[tokio::test]
+async fn test_system_verifies_the_model_on_startup_when_asked() {
+    let server = MockHttpServer::start(vec![tags(&["gemma3:4b"])]).await;
+    let config = SystemConfig {
+        llm_config: LLMConfig {
+            model: OllamaModel::Qwen3_8B.to_string(),
+            base_url: Some(server.base_url.clone()),
+            verify_on_startup: true,
+            ..LLMConfig::default()
+        },
+        ..SystemConfig::default()
+    };
+
+    let error = AgenticSystem::from_config(config.clone(), vec![])
+        .await
+        .err()
+        .unwrap();
+    assert!(
+        error.to_string().contains("ollama pull qwen3:8b"),
+        "{}",
+        error
+    );
+
+    let unverified = SystemConfig {
+        llm_config: LLMConfig {
+            verify_on_startup: false,
+            ..config.llm_config
+        },
+        ..config
+    };
+    AgenticSystem::from_config(unverified, vec![])
+        .await
+        .unwrap();
+    assert_eq!(server.requests().len(), 1);
+}
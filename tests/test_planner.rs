@@ -1,17 +1,20 @@
 mod common;
 
-use std::sync::Arc;
+use serde_json::json;
+use std::{sync::Arc, time::Duration};
 use tokio::sync::Mutex;
 
-use agentic_flow_lib::llm_client::{
-    LLMClient, 
-};
+use agentic_flow_lib::errors::AgenticFlowError;
+use agentic_flow_lib::llm_client::{LLMClient, MockLLMProvider, SamplingOptions};
+use agentic_flow_lib::model::{ChatMessage, ToolCall};
 use agentic_flow_lib::planner::{
-    ChainOfThoughtPlanner, HTNPlanner, MonteCarloTreeSearchPlanner, MultiStepPlanner, PlanStep,
-    Planner,
+    ChainOfThoughtPlanner, HTNPlanner, MctsStrategy, MonteCarloTreeSearchPlanner, MultiStepPlanner,
+    Planner, PlannerPrompts,
 };
-use common::tools::{MockTool};
+use agentic_flow_lib::prompt::{ChatPrompt, PromptTemplate};
 use agentic_flow_lib::tool_registry::ToolRegistry;
+use common::calling;
+use common::tools::MockTool;
 
 fn make_llm_client() -> LLMClient {
     LLMClient::default()
@@ -23,39 +26,340 @@ fn make_tool_registry() -> Arc<Mutex<ToolRegistry>> {
     Arc::new(Mutex::new(registry))
 }
 
+/// An answer calling `mock_tool` once for each of the `values` of its `foo` parameter.
+fn calling_mock_tool(values: &[&str]) -> ChatMessage {
+    let calls: Vec<_> = values
+        .iter()
+        .map(|value| ("mock_tool", json!({"foo": value})))
+        .collect();
+    calling(&calls)
+}
+
+fn thinking(thought: &str) -> ChatMessage {
+    ChatMessage::assistant(thought.to_string())
+}
+
 #[tokio::test]
 async fn test_multistep_planner() {
-    let planner = MultiStepPlanner::new(make_llm_client(), make_tool_registry());
+    let mock = MockLLMProvider::new().with_chat_responses(vec![calling_mock_tool(&["bar"])]);
+    let planner = MultiStepPlanner::new(LLMClient::from(mock.clone()), make_tool_registry());
     let steps = planner.plan("test task with bar param").await.unwrap();
     assert_eq!(steps.len(), 1);
     assert_eq!(steps[0].tool_name, "mock_tool");
     assert_eq!(steps[0].params["foo"], "bar");
+
+    let calls = mock.calls();
+    assert_eq!(calls.len(), 1);
+    assert_eq!(calls[0].messages[1].content, "test task with bar param");
+    assert_eq!(calls[0].tools.len(), 1);
+    assert!(calls[0].tools[0].to_string().contains("mock_tool"));
+}
+
+#[tokio::test]
+async fn test_multistep_planner_repairs_arguments() {
+    let call = ToolCall::new("mock_tool".to_string(), json!("{'foo': 'bar',}"));
+    let answer = ChatMessage::assistant("".to_string()).with_tool_calls(vec![call]);
+    let mock = MockLLMProvider::new().with_chat_responses(vec![answer]);
+    let planner = MultiStepPlanner::new(LLMClient::from(mock), make_tool_registry());
+
+    let steps = planner.plan("test task with bar param").await.unwrap();
+
+    assert_eq!(steps[0].params, json!({"foo": "bar"}));
+    assert!(steps[0].params_repaired);
 }
 
 #[tokio::test]
 async fn test_chain_of_thought_planner() {
-    let planner = ChainOfThoughtPlanner::new(make_llm_client(), make_tool_registry());
+    let mock = MockLLMProvider::new().with_chat_responses(vec![
+        thinking("The task needs mock_tool with foo set to bar."),
+        calling_mock_tool(&["bar"]),
+    ]);
+    let planner = ChainOfThoughtPlanner::new(LLMClient::from(mock.clone()), make_tool_registry());
     let steps = planner.plan("test task with bar param").await.unwrap();
     assert_eq!(steps.len(), 1);
     assert_eq!(steps[0].tool_name, "mock_tool");
     assert_eq!(steps[0].params["foo"], "bar");
+
+    let calls = mock.calls();
+    assert_eq!(calls.len(), 2);
+    assert!(
+        calls[1].messages[1]
+            .content
+            .contains("The task needs mock_tool with foo set to bar.")
+    );
 }
 
 #[tokio::test]
 async fn test_htn_planner() {
-    let planner = HTNPlanner::new(make_llm_client(), make_tool_registry());
+    let mock = MockLLMProvider::new().with_chat_responses(vec![
+        thinking("1. Call mock_tool with bar"),
+        calling_mock_tool(&["bar"]),
+    ]);
+    let planner = HTNPlanner::new(LLMClient::from(mock.clone()), make_tool_registry());
     let steps = planner.plan("test task with bar param").await.unwrap();
     assert_eq!(steps.len(), 1);
     assert_eq!(steps[0].tool_name, "mock_tool");
     assert_eq!(steps[0].params["foo"], "bar");
+
+    let calls = mock.calls();
+    assert_eq!(calls.len(), 2);
+    assert!(
+        calls[1].messages[1]
+            .content
+            .contains("1. Call mock_tool with bar")
+    );
+}
+
+#[tokio::test]
+async fn test_planner_prompts_can_be_replaced() {
+    let mock = MockLLMProvider::new().with_chat_responses(vec![
+        thinking("Use mock_tool."),
+        calling_mock_tool(&["bar"]),
+    ]);
+    let planner = ChainOfThoughtPlanner::new(LLMClient::from(mock.clone()), make_tool_registry())
+        .with_plan_prompt(ChatPrompt::new(
+            PromptTemplate::new("Plan with:\n{tools}").unwrap(),
+            PromptTemplate::new("{task}\nReasoning: {chain_of_thought}").unwrap(),
+        ));
+
+    planner.plan("test task with bar param").await.unwrap();
+
+    let calls = mock.calls();
+    assert!(
+        calls[0].messages[1]
+            .content
+            .starts_with("Task: test task with bar param")
+    );
+    assert_eq!(
+        calls[1].messages[0].content,
+        "Plan with:\n- mock_tool: Mock tool for testing"
+    );
+    assert_eq!(
+        calls[1].messages[1].content,
+        "test task with bar param\nReasoning: Use mock_tool."
+    );
+}
+
+#[tokio::test]
+async fn test_prompt_without_a_value_for_a_placeholder_fails() {
+    let mock = MockLLMProvider::new();
+    let planner = MultiStepPlanner::new(LLMClient::from(mock.clone()), make_tool_registry())
+        .with_prompt(ChatPrompt::new(
+            PromptTemplate::new("Plan it.").unwrap(),
+            PromptTemplate::new("{task} given {hierarchy}").unwrap(),
+        ));
+
+    let error = planner.plan("test task").await.err().unwrap();
+
+    assert!(
+        matches!(&error, AgenticFlowError::PlanningError(message) if message.contains("{hierarchy}")),
+        "{:?}",
+        error
+    );
+    assert!(mock.calls().is_empty());
+}
+
+fn prompt(system: &str, user: &str) -> ChatPrompt {
+    ChatPrompt::new(
+        PromptTemplate::new(system).unwrap(),
+        PromptTemplate::new(user).unwrap(),
+    )
+}
+
+#[tokio::test]
+async fn test_planners_use_the_prompts_they_are_given() {
+    let prompts = PlannerPrompts {
+        multistep: prompt("Planifie avec :\n{tools}", "Tâche : {task}"),
+        htn_decompose: prompt("Décompose.", "{task}"),
+        htn_refine: prompt("Affine.", "{task}\nSous-tâches : {hierarchy}"),
+        ..PlannerPrompts::default()
+    };
+    let mock = MockLLMProvider::new().with_chat_responses(vec![
+        calling_mock_tool(&["bar"]),
+        thinking("1. Call mock_tool"),
+        calling_mock_tool(&["bar"]),
+    ]);
+    let client = LLMClient::from(mock.clone());
+
+    MultiStepPlanner::new(client.clone(), make_tool_registry())
+        .with_prompts(&prompts)
+        .unwrap()
+        .plan("appelle mock_tool")
+        .await
+        .unwrap();
+    HTNPlanner::new(client, make_tool_registry())
+        .with_prompts(&prompts)
+        .unwrap()
+        .plan("appelle mock_tool")
+        .await
+        .unwrap();
+
+    let calls = mock.calls();
+    assert_eq!(
+        calls[0].messages[0].content,
+        "Planifie avec :\n- mock_tool: Mock tool for testing"
+    );
+    assert_eq!(calls[0].messages[1].content, "Tâche : appelle mock_tool");
+    assert_eq!(calls[1].messages[0].content, "Décompose.");
+    assert_eq!(
+        calls[2].messages[1].content,
+        "appelle mock_tool\nSous-tâches : 1. Call mock_tool"
+    );
+}
+
+#[test]
+fn test_default_prompts_are_the_built_in_ones() {
+    let prompts = PlannerPrompts::default();
+
+    assert_eq!(prompts.multistep.user.source(), "{task}");
+    assert!(
+        prompts
+            .chain_of_thought_plan
+            .user
+            .placeholders()
+            .contains(&"chain_of_thought")
+    );
+    assert!(
+        prompts
+            .htn_refine
+            .user
+            .placeholders()
+            .contains(&"hierarchy")
+    );
+    let (client, registry) = (make_llm_client(), make_tool_registry());
+    assert!(
+        MultiStepPlanner::new(client.clone(), registry.clone())
+            .with_prompts(&prompts)
+            .is_ok()
+    );
+    let cot = ChainOfThoughtPlanner::new(client.clone(), registry.clone());
+    assert!(cot.with_prompts(&prompts).is_ok());
+    assert!(
+        HTNPlanner::new(client, registry)
+            .with_prompts(&prompts)
+            .is_ok()
+    );
+}
+
+#[test]
+fn test_prompts_with_wrong_placeholders_fail_when_given_to_the_planner() {
+    let client = make_llm_client();
+    let unknown = PlannerPrompts {
+        chain_of_thought: prompt("Think in {language}.", "{task}"),
+        ..PlannerPrompts::default()
+    };
+    let error = ChainOfThoughtPlanner::new(client.clone(), make_tool_registry())
+        .with_prompts(&unknown)
+        .err()
+        .unwrap();
+    assert!(
+        matches!(&error, AgenticFlowError::ConfigError(message)
+            if message.contains("'chain_of_thought' uses {language}")),
+        "{:?}",
+        error
+    );
+
+    // The hierarchy belongs to the refine phase only.
+    let misplaced = PlannerPrompts {
+        htn_decompose: prompt("Decompose.", "{task} into {hierarchy}"),
+        ..PlannerPrompts::default()
+    };
+    let htn = HTNPlanner::new(client.clone(), make_tool_registry());
+    assert!(htn.with_prompts(&misplaced).is_err());
+
+    let missing = PlannerPrompts {
+        htn_refine: prompt("Plan it.", "{task}"),
+        ..PlannerPrompts::default()
+    };
+    let error = HTNPlanner::new(client, make_tool_registry())
+        .with_prompts(&missing)
+        .err()
+        .unwrap();
+    assert!(
+        matches!(&error, AgenticFlowError::ConfigError(message)
+            if message.contains("'htn_refine' must use {hierarchy}")),
+        "{:?}",
+        error
+    );
+}
+
+#[tokio::test]
+async fn test_htn_planner_reports_the_phase_that_failed() {
+    let mock = MockLLMProvider::new()
+        .with_chat_responses(vec![thinking("1. Call mock_tool with bar")])
+        .with_failure_on_call(
+            2,
+            AgenticFlowError::NetworkError("connection reset".to_string()),
+        );
+    let planner = HTNPlanner::new(LLMClient::from(mock), make_tool_registry());
+
+    let error = planner
+        .plan("test task with bar param")
+        .await
+        .err()
+        .unwrap();
+
+    assert!(
+        matches!(&error, AgenticFlowError::PlanningFailed { phase: "refine", source, .. }
+            if matches!(**source, AgenticFlowError::NetworkError(_))),
+        "{:?}",
+        error
+    );
 }
 
 #[tokio::test]
 async fn test_mcts_planner() {
-    let planner = MonteCarloTreeSearchPlanner::new(make_llm_client(), make_tool_registry(), 3);
+    let mock = MockLLMProvider::new().with_chat_responses(vec![
+        calling_mock_tool(&["baz", "qux"]),
+        calling_mock_tool(&["bar"]),
+        calling_mock_tool(&["baz", "qux", "quux"]),
+    ]);
+    let planner =
+        MonteCarloTreeSearchPlanner::new(LLMClient::from(mock.clone()), make_tool_registry(), 3)
+            .with_strategy(MctsStrategy::Sampling);
     let steps = planner.plan("test task with bar param").await.unwrap();
     assert_eq!(steps.len(), 1);
     assert_eq!(steps[0].tool_name, "mock_tool");
     assert_eq!(steps[0].params["foo"], "bar");
+    assert_eq!(mock.calls().len(), 3);
 }
 
+#[tokio::test]
+async fn test_slow_planning_call_times_out() {
+    let mock = MockLLMProvider::new()
+        .with_chat_responses(vec![calling_mock_tool(&["bar"])])
+        .with_delay_on_call(1, Duration::from_secs(5));
+    let client = LLMClient::from(mock).with_timeout(Duration::from_millis(20));
+    let planner = MultiStepPlanner::new(client, make_tool_registry());
+
+    let error = planner
+        .plan("test task with bar param")
+        .await
+        .err()
+        .unwrap();
+    assert!(
+        matches!(&error, AgenticFlowError::PlanningFailed { source, .. }
+            if matches!(**source, AgenticFlowError::Timeout { .. })),
+        "{:?}",
+        error
+    );
+
+    let steps = planner.plan("test task with bar param").await.unwrap();
+    assert_eq!(steps[0].params["foo"], "bar");
+}
+
+#[tokio::test]
+async fn test_multistep_planner_is_deterministic_with_seed() {
+    let client = make_llm_client()
+        .with_temperature(0.0)
+        .with_sampling(SamplingOptions {
+            seed: Some(42),
+            ..SamplingOptions::default()
+        });
+    let planner = MultiStepPlanner::new(client, make_tool_registry());
+
+    let first = planner.plan("test task with bar param").await.unwrap();
+    let second = planner.plan("test task with bar param").await.unwrap();
+
+    assert_eq!(first, second);
+}
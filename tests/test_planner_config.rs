@@ -0,0 +1,130 @@
+mod common;
+
+use agentic_flow_lib::{
+    AgenticSystem,
+    config::{ConfigFormat, SystemConfig},
+    llm_client::{LLMClient, MockLLMProvider},
+};
+
+use common::tools::MockTool;
+
+/// Runs a task with the planner from `planner_section` and returns the system prompt
+/// of every LLM call. Synthesis is disabled so only planner calls are recorded.
+async fn planner_calls(planner_section: &str) -> Vec<String> {
+    let contents = format!(
+        "{}\n\n[agent_config.synthesis]\nenabled = false\n",
+        planner_section
+    );
+    let config = SystemConfig::parse(&contents, ConfigFormat::Toml).unwrap();
+
+    let provider = MockLLMProvider::new().with_chat_response(None).await;
+    let calls = provider.chat_calls();
+    let system = AgenticSystem::builder()
+        .config(config)
+        .tool(MockTool)
+        .llm_client(LLMClient::from(provider))
+        .build()
+        .await
+        .unwrap();
+
+    system.plan_and_execute("do the thing").await.unwrap();
+
+    let calls = calls.lock().unwrap();
+    calls
+        .iter()
+        .map(|messages| messages[0].content.clone())
+        .collect()
+}
+
+#[tokio::test]
+async fn test_config_selects_multistep_planner() {
+    let calls = planner_calls("[planner]\nkind = \"multistep\"").await;
+
+    assert_eq!(calls.len(), 1);
+    assert!(calls[0].contains("multi-step plan"));
+}
+
+#[tokio::test]
+async fn test_config_selects_chain_of_thought_planner() {
+    let calls = planner_calls("[planner]\nkind = \"cot\"").await;
+
+    assert_eq!(calls.len(), 2);
+    assert!(calls[0].contains("chain-of-thought analysis"));
+}
+
+#[tokio::test]
+async fn test_config_selects_htn_planner() {
+    let calls = planner_calls("[planner]\nkind = \"htn\"").await;
+
+    assert_eq!(calls.len(), 2);
+    assert!(calls[0].contains("HTN planner"));
+}
+
+#[tokio::test]
+async fn test_config_selects_mcts_planner() {
+    let calls = planner_calls(
+        "[planner]\nkind = \"mcts\"\nmcts_simulations = 3\nmcts_strategy = \"sampling\"",
+    )
+    .await;
+
+    assert_eq!(calls.len(), 3);
+    assert!(
+        calls
+            .iter()
+            .all(|prompt| prompt.contains("Monte Carlo Tree Search"))
+    );
+}
+
+#[tokio::test]
+async fn test_config_selects_mcts_tree_search() {
+    let calls = planner_calls("[planner]\nkind = \"mcts\"\nmcts_simulations = 2").await;
+
+    // The root gets three proposals that all end the plan, which the second simulation
+    // finds finished.
+    assert_eq!(calls.len(), 3);
+    assert!(
+        calls
+            .iter()
+            .all(|prompt| prompt.contains("one step at a time"))
+    );
+}
+
+#[tokio::test]
+async fn test_config_selects_react_planner() {
+    let calls = planner_calls("[planner]\nkind = \"react\"").await;
+
+    // The answer calls no tool, so it is the final one.
+    assert_eq!(calls.len(), 1);
+    assert!(calls[0].contains("one tool at a time"));
+}
+
+#[tokio::test]
+async fn test_config_falls_back_on_empty_plan() {
+    let calls = planner_calls("[planner]\nkind = \"multistep\"\nfallback = [\"htn\"]").await;
+
+    assert_eq!(calls.len(), 3);
+    assert!(calls[0].contains("multi-step plan"));
+    assert!(calls[1].contains("HTN planner"));
+}
+
+#[tokio::test]
+async fn test_config_adds_critique_rounds() {
+    let calls = planner_calls("[planner]\nkind = \"multistep\"\ncritique_rounds = 2").await;
+
+    // The mock never revises the plan, so critique stops after the first round.
+    assert_eq!(calls.len(), 2);
+    assert!(calls[1].starts_with("Critique the plan"));
+}
+
+#[tokio::test]
+async fn test_config_selects_beam_planner() {
+    let calls = planner_calls("[planner]\nkind = \"beam\"\nbeam_width = 2").await;
+
+    // Both proposals for the first step call no tool, which ends the plan.
+    assert_eq!(calls.len(), 2);
+    assert!(
+        calls
+            .iter()
+            .all(|prompt| prompt.contains("one step at a time"))
+    );
+}
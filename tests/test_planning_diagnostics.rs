@@ -0,0 +1,230 @@
+mod common;
+
+use std::sync::Arc;
+
+use serde_json::json;
+use tokio::sync::Mutex;
+
+use agentic_flow_lib::{
+    AgenticSystem,
+    config::{ConfigFormat, SystemConfig},
+    errors::AgenticFlowError,
+    llm_client::{LLMClient, MockLLMProvider},
+    model::{ChatMessage, FinishReason, Role, ToolCall},
+    planner::{
+        ChainOfThoughtPlanner, CritiquePlanner, FallbackPlanner, HTNPlanner,
+        MonteCarloTreeSearchPlanner, MultiStepPlanner, PlanStep, Planner,
+    },
+    tool_registry::ToolRegistry,
+};
+
+use common::tools::MockTool;
+
+const RAW_CONTENT: &str = "Sure! First I would call mock_tool.";
+
+/// A response whose tool call arguments are not JSON.
+fn unparseable_response() -> ChatMessage {
+    serde_json::from_value(json!({
+        "role": "assistant",
+        "content": RAW_CONTENT,
+        "thinking": null,
+        "tool_calls": [{ "function": { "name": "mock_tool", "arguments": "foo=bar" } }]
+    }))
+    .unwrap()
+}
+
+async fn client(response: ChatMessage) -> LLMClient {
+    LLMClient::from(
+        MockLLMProvider::new()
+            .with_chat_response(Some(response))
+            .await,
+    )
+}
+
+fn tool_registry() -> Arc<Mutex<ToolRegistry>> {
+    let mut registry = ToolRegistry::new();
+    registry.register_local_tool(Box::new(MockTool));
+    Arc::new(Mutex::new(registry))
+}
+
+fn assert_planning_failed(error: &AgenticFlowError, planner: &str, phase: &str) {
+    match error {
+        AgenticFlowError::PlanningFailed {
+            planner: actual_planner,
+            phase: actual_phase,
+            diagnostics,
+            ..
+        } => {
+            assert_eq!(actual_planner, planner);
+            assert_eq!(*actual_phase, phase);
+            assert_eq!(diagnostics.raw_content.as_deref(), Some(RAW_CONTENT));
+            assert!(diagnostics.tool_calls.as_ref().unwrap().contains("foo=bar"));
+        }
+        other => panic!("unexpected error: {:?}", other),
+    }
+}
+
+#[tokio::test]
+async fn test_each_planner_reports_raw_output() {
+    let response = unparseable_response();
+    let planners: Vec<(Box<dyn Planner>, &str, &str)> = vec![
+        (
+            Box::new(MultiStepPlanner::new(
+                client(response.clone()).await,
+                tool_registry(),
+            )),
+            "multistep",
+            "plan",
+        ),
+        (
+            Box::new(ChainOfThoughtPlanner::new(
+                client(response.clone()).await,
+                tool_registry(),
+            )),
+            "cot",
+            "plan",
+        ),
+        (
+            Box::new(HTNPlanner::new(
+                client(response.clone()).await,
+                tool_registry(),
+            )),
+            "htn",
+            "refine",
+        ),
+        (
+            Box::new(MonteCarloTreeSearchPlanner::new(
+                client(response.clone()).await,
+                tool_registry(),
+                3,
+            )),
+            "mcts",
+            "expand",
+        ),
+    ];
+
+    for (planner, name, phase) in planners {
+        let error = planner.plan("do the thing").await.unwrap_err();
+        assert_planning_failed(&error, name, phase);
+    }
+}
+
+#[tokio::test]
+async fn test_mcts_counts_failed_simulations_as_retries() {
+    let planner =
+        MonteCarloTreeSearchPlanner::new(client(unparseable_response()).await, tool_registry(), 4);
+
+    let error = planner.plan("do the thing").await.unwrap_err();
+
+    assert_eq!(error.planning_diagnostics().unwrap().retries, 3);
+}
+
+#[tokio::test]
+async fn test_fallback_counts_earlier_planners_as_retries() {
+    let planner = FallbackPlanner::new(vec![
+        Box::new(MultiStepPlanner::new(
+            client(unparseable_response()).await,
+            tool_registry(),
+        )),
+        Box::new(HTNPlanner::new(
+            client(unparseable_response()).await,
+            tool_registry(),
+        )),
+    ]);
+
+    let error = planner.plan("do the thing").await.unwrap_err();
+
+    assert_planning_failed(&error, "htn", "refine");
+    assert_eq!(error.planning_diagnostics().unwrap().retries, 1);
+}
+
+struct FixedPlanner;
+
+#[async_trait::async_trait]
+impl Planner for FixedPlanner {
+    async fn plan(&self, _task: &str) -> Result<Vec<PlanStep>, AgenticFlowError> {
+        Ok(vec![PlanStep::new("mock_tool", json!({}))])
+    }
+}
+
+#[tokio::test]
+async fn test_critique_reports_raw_output() {
+    let planner = CritiquePlanner::new(
+        Box::new(FixedPlanner),
+        client(unparseable_response()).await,
+        tool_registry(),
+        1,
+    );
+
+    let error = planner.plan("do the thing").await.unwrap_err();
+
+    assert_planning_failed(&error, "critique", "critique");
+}
+
+#[tokio::test]
+async fn test_json_string_arguments_become_params() {
+    let response = ChatMessage::builder(Role::Assistant)
+        .tool_calls(vec![ToolCall::new(
+            "mock_tool".to_string(),
+            json!("{\"foo\": \"bar\"}"),
+        )])
+        .build();
+    let planner = MultiStepPlanner::new(client(response).await, tool_registry());
+
+    let steps = planner.plan("do the thing").await.unwrap();
+
+    assert_eq!(steps[0].params, json!({"foo": "bar"}));
+}
+
+#[tokio::test]
+async fn test_plan_cut_off_at_token_limit_is_output_truncated() {
+    let response = ChatMessage::builder(Role::Assistant)
+        .tool_calls(vec![ToolCall::new("mock_tool".to_string(), json!({}))])
+        .build();
+    let provider = MockLLMProvider::new()
+        .with_chat_response(Some(response))
+        .await
+        .with_finish_reason(FinishReason::Length);
+    let client = LLMClient::from(provider).with_max_tokens(32);
+    let planner = MultiStepPlanner::new(client, tool_registry());
+
+    let error = planner.plan("do the thing").await.unwrap_err();
+
+    match &error {
+        AgenticFlowError::PlanningFailed { source, .. } => assert!(
+            matches!(
+                **source,
+                AgenticFlowError::OutputTruncated {
+                    max_tokens: Some(32)
+                }
+            ),
+            "{:?}",
+            source
+        ),
+        other => panic!("unexpected error: {:?}", other),
+    }
+    assert!(error.planning_diagnostics().is_some());
+}
+
+#[tokio::test]
+async fn test_config_truncates_and_redacts_diagnostics() {
+    let contents = "[planner.diagnostics]\nmax_chars = 5\nredact = true\n";
+    let config = SystemConfig::parse(contents, ConfigFormat::Toml).unwrap();
+    let system = AgenticSystem::builder()
+        .config(config)
+        .tool(MockTool)
+        .llm_client(client(unparseable_response()).await)
+        .build()
+        .await
+        .unwrap();
+
+    let error = system.plan_and_execute("do the thing").await.unwrap_err();
+
+    let diagnostics = error.planning_diagnostics().unwrap();
+    assert_eq!(
+        diagnostics.raw_content.as_deref(),
+        Some("Sure!... [truncated]")
+    );
+    assert!(diagnostics.redacted);
+    assert!(!error.to_wire().details.to_string().contains("Sure!"));
+}
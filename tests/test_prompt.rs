@@ -0,0 +1,118 @@
+use agentic_flow_lib::{
+    errors::AgenticFlowError,
+    prompt::{ChatPrompt, PromptTemplate, describe_tools},
+};
+use serde_json::json;
+
+#[test]
+fn test_template_fills_in_placeholders() {
+    let template = PromptTemplate::new("Task: {task}\nAgain: {task}, with {tools}").unwrap();
+
+    assert_eq!(template.placeholders(), vec!["task", "tools"]);
+    let rendered = template
+        .render(&[
+            ("tools", "a search tool"),
+            ("task", "find {docs}"),
+            ("unused", "x"),
+        ])
+        .unwrap();
+    assert_eq!(
+        rendered,
+        "Task: find {docs}\nAgain: find {docs}, with a search tool"
+    );
+}
+
+#[test]
+fn test_doubled_braces_are_literal() {
+    let template: PromptTemplate = r#"Answer with {{"plan": {task}}}"#.parse().unwrap();
+
+    assert_eq!(template.placeholders(), vec!["task"]);
+    assert_eq!(
+        template.render(&[("task", "[]")]).unwrap(),
+        r#"Answer with {"plan": []}"#
+    );
+    assert_eq!(template.to_string(), r#"Answer with {{"plan": {task}}}"#);
+}
+
+#[test]
+fn test_missing_value_names_the_placeholder() {
+    let template = PromptTemplate::new("{task} using {chain_of_thought}").unwrap();
+
+    let error = template.render(&[("task", "plan")]).err().unwrap();
+
+    assert!(
+        matches!(&error, AgenticFlowError::PlanningError(message)
+            if message.contains("{chain_of_thought}")),
+        "{:?}",
+        error
+    );
+}
+
+#[test]
+fn test_malformed_templates_are_rejected() {
+    let cases = [
+        ("Task: {task", "byte 6: '{' does not start a placeholder"),
+        (
+            "Task: {the task}",
+            "byte 6: '{' does not start a placeholder",
+        ),
+        ("Empty {}", "byte 6: empty placeholder"),
+        (
+            "JSON: {\"a\": 1}",
+            "byte 6: '{' does not start a placeholder",
+        ),
+        ("Closing } brace", "byte 8: unmatched '}'"),
+    ];
+
+    for (source, expected) in cases {
+        let error = PromptTemplate::new(source).err().unwrap();
+        assert!(
+            matches!(&error, AgenticFlowError::ConfigError(message) if message.contains(expected)),
+            "{}: {:?}",
+            source,
+            error
+        );
+    }
+}
+
+#[test]
+fn test_template_is_read_from_a_file() {
+    let path = std::env::temp_dir().join(format!("agentic-flow-prompt-{}.txt", std::process::id()));
+    std::fs::write(&path, "Plan {task} step by step.").unwrap();
+
+    let template = PromptTemplate::from_file(&path).unwrap();
+    std::fs::remove_file(&path).unwrap();
+
+    assert_eq!(
+        template.render(&[("task", "the release")]).unwrap(),
+        "Plan the release step by step."
+    );
+    let error = PromptTemplate::from_file(&path).err().unwrap();
+    assert!(
+        matches!(error, AgenticFlowError::ConfigError(_)),
+        "{:?}",
+        error
+    );
+}
+
+#[test]
+fn test_chat_prompt_renders_both_messages() {
+    let prompt = ChatPrompt::new(
+        PromptTemplate::new("You plan with these tools:\n{tools}").unwrap(),
+        PromptTemplate::new("Task: {task}").unwrap(),
+    );
+    let tools = describe_tools(&[
+        json!({"type": "function", "function": {"name": "search", "description": "Searches the web"}}),
+        json!({"type": "function", "function": {"name": "noop", "description": ""}}),
+    ]);
+
+    let messages = prompt
+        .messages(&[("task", "find docs"), ("tools", &tools)])
+        .unwrap();
+
+    assert_eq!(
+        messages[0].content,
+        "You plan with these tools:\n- search: Searches the web\n- noop"
+    );
+    assert_eq!(messages[1].content, "Task: find docs");
+}
@@ -0,0 +1,277 @@
+mod common;
+
+use std::{
+    path::PathBuf,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use agentic_flow_lib::{
+    config::{LLMConfig, ProviderKind},
+    errors::{AgenticFlowError, ErrorKind, ProviderError},
+    llm_client::LLMClient,
+    model::ChatMessage,
+};
+
+use common::http_server::{MockHttpServer, MockResponse};
+
+fn fixture(name: &str) -> String {
+    let path = PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+        .join("tests")
+        .join("fixtures")
+        .join("provider_errors")
+        .join(name);
+    std::fs::read_to_string(path).unwrap()
+}
+
+#[test]
+fn test_openrouter_rate_limit() {
+    let error = AgenticFlowError::from_http_response(
+        429,
+        Some("30"),
+        &fixture("openrouter_rate_limited.json"),
+    );
+
+    match &error {
+        AgenticFlowError::RateLimited {
+            retry_after,
+            provider_error: Some(provider_error),
+        } => {
+            assert_eq!(*retry_after, Some(Duration::from_secs(30)));
+            assert_eq!(provider_error.code.as_deref(), Some("429"));
+            assert!(provider_error.message.starts_with("Rate limit exceeded"));
+        }
+        other => panic!("unexpected error: {:?}", other),
+    }
+    assert_eq!(
+        error.kind(),
+        ErrorKind::RateLimited {
+            retry_after: Some(Duration::from_secs(30))
+        }
+    );
+}
+
+fn retry_after(header: &str) -> Option<Duration> {
+    match AgenticFlowError::from_http_response(429, Some(header), "") {
+        AgenticFlowError::RateLimited { retry_after, .. } => retry_after,
+        other => panic!("unexpected error: {:?}", other),
+    }
+}
+
+#[test]
+fn test_retry_after_http_date() {
+    // 2100-01-01T00:00:00Z.
+    let until =
+        Duration::from_secs(4_102_444_800) - SystemTime::now().duration_since(UNIX_EPOCH).unwrap();
+
+    let waited = retry_after("Fri, 01 Jan 2100 00:00:00 GMT").unwrap();
+
+    assert!(
+        until.abs_diff(waited) < Duration::from_secs(5),
+        "{:?}",
+        waited
+    );
+    assert_eq!(
+        retry_after("Wed, 21 Oct 2015 07:28:00 GMT"),
+        Some(Duration::ZERO)
+    );
+    assert_eq!(retry_after("Wed, 21 Oct 2015 07:28:00 CET"), None);
+    assert_eq!(retry_after("soon"), None);
+}
+
+#[test]
+fn test_openrouter_unauthorized() {
+    let error =
+        AgenticFlowError::from_http_response(401, None, &fixture("openrouter_unauthorized.json"));
+
+    assert!(matches!(
+        &error,
+        AgenticFlowError::Unauthorized {
+            status: 401,
+            provider_error: Some(ProviderError { message, .. }),
+        } if message == "No auth credentials found"
+    ));
+    assert_eq!(
+        error.to_string(),
+        "The provider rejected the credentials (401): No auth credentials found"
+    );
+}
+
+#[test]
+fn test_context_length_exceeded() {
+    for name in [
+        "openrouter_context_length.json",
+        "openai_context_length.json",
+    ] {
+        let error = AgenticFlowError::from_http_response(400, None, &fixture(name));
+
+        assert!(
+            matches!(error, AgenticFlowError::ContextLengthExceeded { .. }),
+            "{}: {:?}",
+            name,
+            error
+        );
+        assert!(!error.is_retryable());
+    }
+}
+
+#[test]
+fn test_openai_error_type_and_code() {
+    let provider_error = ProviderError::from_body(&fixture("openai_context_length.json")).unwrap();
+
+    assert_eq!(
+        provider_error.code.as_deref(),
+        Some("context_length_exceeded")
+    );
+    assert_eq!(
+        provider_error.error_type.as_deref(),
+        Some("invalid_request_error")
+    );
+}
+
+#[test]
+fn test_openrouter_server_error() {
+    let error =
+        AgenticFlowError::from_http_response(500, None, &fixture("openrouter_server_error.json"));
+
+    assert!(matches!(
+        &error,
+        AgenticFlowError::ApiResponseError { status: 500, message, .. } if message == "Internal Server Error"
+    ));
+    assert!(error.is_retryable());
+}
+
+#[test]
+fn test_ollama_model_not_found() {
+    let error =
+        AgenticFlowError::from_http_response(404, None, &fixture("ollama_model_not_found.json"));
+
+    match &error {
+        AgenticFlowError::ApiResponseError {
+            status: 404,
+            provider_error: Some(provider_error),
+            ..
+        } => {
+            assert_eq!(
+                provider_error.message,
+                "model \"llama9\" not found, try pulling it first"
+            );
+            assert_eq!(provider_error.code, None);
+        }
+        other => panic!("unexpected error: {:?}", other),
+    }
+    assert_eq!(error.kind(), ErrorKind::Permanent);
+}
+
+#[test]
+fn test_body_without_error_json_becomes_message() {
+    let error = AgenticFlowError::from_http_response(502, None, "<html>Bad Gateway</html>\n");
+
+    assert!(matches!(
+        &error,
+        AgenticFlowError::ApiResponseError {
+            status: 502,
+            message,
+            provider_error: None,
+        } if message == "<html>Bad Gateway</html>"
+    ));
+}
+
+#[tokio::test]
+async fn test_provider_surfaces_status_and_retry_after() {
+    let server = MockHttpServer::start(vec![
+        MockResponse::raw(429, &fixture("openrouter_rate_limited.json"))
+            .with_header("Retry-After", "2"),
+    ])
+    .await;
+    let config = LLMConfig {
+        provider: ProviderKind::OpenAICompatible {
+            base_url: server.base_url.clone(),
+        },
+        model: "test-model".to_string(),
+        ..LLMConfig::default()
+    };
+
+    let error = LLMClient::from_config(&config)
+        .unwrap()
+        .chat_completions(vec![ChatMessage::user("hi".to_string())], vec![])
+        .await
+        .err()
+        .unwrap();
+
+    assert!(
+        matches!(
+            error,
+            AgenticFlowError::RateLimited {
+                retry_after: Some(retry_after),
+                provider_error: Some(_),
+            } if retry_after == Duration::from_secs(2)
+        ),
+        "{:?}",
+        error
+    );
+}
+
+async fn chat_with_body(body: &str) -> Result<String, AgenticFlowError> {
+    let server = MockHttpServer::start(vec![MockResponse::raw(200, body)]).await;
+    let config = LLMConfig {
+        provider: ProviderKind::OpenAICompatible {
+            base_url: server.base_url.clone(),
+        },
+        model: "test-model".to_string(),
+        ..LLMConfig::default()
+    };
+
+    LLMClient::from_config(&config)
+        .unwrap()
+        .chat_completions(vec![ChatMessage::user("hi".to_string())], vec![])
+        .await
+        .map(|response| response.message().content.clone())
+}
+
+#[tokio::test]
+async fn test_rate_limit_sent_with_200_is_retryable() {
+    let error = chat_with_body(&fixture("openrouter_rate_limited_in_200.json"))
+        .await
+        .err()
+        .unwrap();
+
+    match &error {
+        AgenticFlowError::RateLimited {
+            retry_after: None,
+            provider_error: Some(provider_error),
+        } => assert!(provider_error.message.contains("rate-limited upstream")),
+        other => panic!("unexpected error: {:?}", other),
+    }
+    assert!(error.is_retryable());
+}
+
+#[tokio::test]
+async fn test_moderation_refusal_sent_with_200() {
+    let error = chat_with_body(&fixture("openrouter_moderation_in_200.json"))
+        .await
+        .err()
+        .unwrap();
+
+    assert!(
+        matches!(&error, AgenticFlowError::ApiClientError(message)
+            if message == "the provider answered with error 403: Input was flagged by moderation"),
+        "{:?}",
+        error
+    );
+    assert!(!error.is_retryable());
+}
+
+#[tokio::test]
+async fn test_response_without_choices() {
+    let error = chat_with_body(&fixture("openrouter_no_choices.json"))
+        .await
+        .err()
+        .unwrap();
+
+    assert!(
+        matches!(&error, AgenticFlowError::ApiClientError(message)
+            if message == "the provider answered without any choices"),
+        "{:?}",
+        error
+    );
+}
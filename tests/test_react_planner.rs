@@ -0,0 +1,106 @@
+mod common;
+
+use serde_json::json;
+
+use agentic_flow_lib::{
+    AgenticSystem,
+    config::SystemConfig,
+    errors::AgenticFlowError,
+    llm_client::{LLMClient, MockLLMProvider},
+    model::{ChatMessage, Role, ToolCall},
+    planner::{Planner, PlannerKind, ReActPlanner},
+};
+
+use common::tools::EchoTool;
+
+fn echoing(text: &str) -> ChatMessage {
+    ChatMessage::assistant(String::new()).with_tool_calls(vec![ToolCall::new(
+        "echo".to_string(),
+        json!({"text": text}),
+    )])
+}
+
+async fn react_system(mock: &MockLLMProvider, config: SystemConfig) -> AgenticSystem {
+    AgenticSystem::builder()
+        .config(config)
+        .tool(EchoTool)
+        .planner(PlannerKind::React)
+        .llm_client(LLMClient::from(mock.clone()))
+        .build()
+        .await
+        .unwrap()
+}
+
+#[tokio::test]
+async fn test_each_step_sees_the_result_of_the_last() {
+    let mock = MockLLMProvider::new().with_chat_responses(vec![
+        echoing("first"),
+        echoing("second"),
+        ChatMessage::assistant("The echoes were first and second".to_string()),
+    ]);
+    let system = react_system(&mock, SystemConfig::default()).await;
+
+    let answer = system.plan_and_execute("echo twice").await.unwrap();
+
+    assert_eq!(answer, "The echoes were first and second");
+    let calls = mock.calls();
+    assert_eq!(calls.len(), 3);
+    let observed = &calls[2].messages[2..];
+    assert_eq!(observed.len(), 4);
+    assert_eq!(
+        observed[0].tool_calls.as_ref().unwrap()[0].id.as_deref(),
+        Some("call_1")
+    );
+    assert_eq!(observed[1].role, Role::Tool);
+    assert_eq!(observed[1].tool_call_id.as_deref(), Some("call_1"));
+    assert_eq!(observed[1].content, json!({"text": "first"}).to_string());
+    assert_eq!(observed[3].content, json!({"text": "second"}).to_string());
+}
+
+#[tokio::test]
+async fn test_repeated_tool_call_fails_the_run() {
+    let mock = MockLLMProvider::new().with_chat_responses(vec![echoing("again"), echoing("again")]);
+    let system = react_system(&mock, SystemConfig::default()).await;
+
+    let error = system.plan_and_execute("echo forever").await.unwrap_err();
+
+    assert_eq!(mock.calls().len(), 2);
+    match error {
+        AgenticFlowError::PlanningError(message) => {
+            assert!(message.contains("'echo' with the same arguments twice in a row"))
+        }
+        error => panic!("expected a planning error, got {:?}", error),
+    }
+}
+
+#[tokio::test]
+async fn test_run_out_of_steps_is_synthesized() {
+    let mock = MockLLMProvider::new().with_chat_responses(vec![
+        echoing("one"),
+        echoing("two"),
+        ChatMessage::assistant("synthesized".to_string()),
+    ]);
+    let mut config = SystemConfig::default();
+    config.agent_config.max_steps = 2;
+    let system = react_system(&mock, config).await;
+
+    let answer = system.plan_and_execute("echo a lot").await.unwrap();
+
+    assert_eq!(answer, "synthesized");
+    let calls = mock.calls();
+    assert_eq!(calls.len(), 3);
+    assert!(calls[2].messages[1].content.contains("\"2: echo\""));
+}
+
+#[tokio::test]
+async fn test_react_planner_makes_no_plan_up_front() {
+    let mock = MockLLMProvider::new();
+    let planner = ReActPlanner::new(LLMClient::from(mock.clone()), Default::default());
+
+    assert!(planner.as_iterative().is_some());
+    assert!(matches!(
+        planner.plan("any task").await,
+        Err(AgenticFlowError::PlanningError(_))
+    ));
+    assert!(mock.calls().is_empty());
+}
@@ -0,0 +1,145 @@
+mod common;
+
+use std::path::PathBuf;
+
+use serde_json::Value;
+
+use agentic_flow_lib::{
+    errors::AgenticFlowError,
+    llm_client::{LLMClient, MockLLMProvider, RecordingProvider},
+    model::{ChatMessage, FinishReason},
+};
+
+fn cassette(name: &str) -> PathBuf {
+    std::env::temp_dir().join(format!(
+        "agentic-flow-cassette-{}-{}.json",
+        name,
+        std::process::id()
+    ))
+}
+
+fn ask(question: &str) -> Vec<ChatMessage> {
+    vec![ChatMessage::user(question.to_string())]
+}
+
+async fn answer(client: &LLMClient, question: &str) -> String {
+    client
+        .chat_completions(ask(question), vec![])
+        .await
+        .unwrap()
+        .message()
+        .content
+        .clone()
+}
+
+#[tokio::test]
+async fn test_replays_what_was_recorded() {
+    let path = cassette("replay");
+    let provider = MockLLMProvider::new()
+        .with_chat_responses(vec![
+            ChatMessage::assistant("Paris".to_string()),
+            ChatMessage::assistant("Rome".to_string()),
+        ])
+        .with_usage("gemma3:4b", 10, 2)
+        .with_finish_reason(FinishReason::Stop)
+        .with_completion_response(Some("Once upon a time".to_string()))
+        .await;
+    let calls = provider.chat_calls();
+    let recorder = RecordingProvider::record(provider, &path);
+    assert!(recorder.is_recording());
+    let recording = LLMClient::from(recorder);
+    assert_eq!(answer(&recording, "Capital of France?").await, "Paris");
+    assert_eq!(answer(&recording, "Capital of Italy?").await, "Rome");
+    recording
+        .completion("Tell a story".to_string())
+        .await
+        .unwrap();
+    assert_eq!(calls.lock().unwrap().len(), 2);
+
+    let written: Value = serde_json::from_str(&std::fs::read_to_string(&path).unwrap()).unwrap();
+    assert_eq!(written["interactions"].as_array().unwrap().len(), 3);
+    assert_eq!(
+        written["interactions"][0]["hash"].as_str().unwrap().len(),
+        16
+    );
+
+    let replaying = LLMClient::from(RecordingProvider::replay(&path).unwrap());
+    assert_eq!(answer(&replaying, "Capital of Italy?").await, "Rome");
+    let response = replaying
+        .chat_completions(ask("Capital of France?"), vec![])
+        .await
+        .unwrap();
+    assert_eq!(response.message().content, "Paris");
+    assert_eq!(response.finish_reason(), Some(FinishReason::Stop));
+    assert_eq!(response.model(), Some("gemma3:4b"));
+    assert_eq!(response.usage().unwrap().total_tokens, 12);
+    let story = replaying
+        .completion("Tell a story".to_string())
+        .await
+        .unwrap();
+    assert_eq!(story.response(), "Once upon a time");
+    std::fs::remove_file(path).unwrap();
+}
+
+#[tokio::test]
+async fn test_repeated_requests_replay_in_order() {
+    let path = cassette("repeated");
+    let provider = MockLLMProvider::new().with_chat_responses(vec![
+        ChatMessage::assistant("heads".to_string()),
+        ChatMessage::assistant("tails".to_string()),
+    ]);
+    let recording = LLMClient::from(RecordingProvider::record(provider, &path));
+    answer(&recording, "Flip a coin").await;
+    answer(&recording, "Flip a coin").await;
+
+    let replaying = LLMClient::from(RecordingProvider::replay(&path).unwrap());
+
+    assert_eq!(answer(&replaying, "Flip a coin").await, "heads");
+    assert_eq!(answer(&replaying, "Flip a coin").await, "tails");
+    assert_eq!(answer(&replaying, "Flip a coin").await, "tails");
+    std::fs::remove_file(path).unwrap();
+}
+
+#[tokio::test]
+async fn test_unrecorded_request_shows_the_nearest_one() {
+    let path = cassette("mismatch");
+    let provider =
+        MockLLMProvider::new().with_chat_responses(vec![ChatMessage::assistant("4".to_string())]);
+    let recording = LLMClient::from(RecordingProvider::record(provider, &path));
+    answer(&recording, "What is 2 + 2?").await;
+
+    let replaying =
+        LLMClient::from(RecordingProvider::replay(&path).unwrap()).with_temperature(0.0);
+    let error = replaying
+        .chat_completions(ask("What is 2 + 3?"), vec![])
+        .await
+        .err()
+        .unwrap();
+
+    let AgenticFlowError::ApiClientError(message) = &error else {
+        panic!("unexpected error: {:?}", error);
+    };
+    assert!(
+        message.contains(
+            "differs in 2 place(s), first at `.messages[0].content`: recorded \"What is 2 + 2?\", sent \"What is 2 + 3?\""
+        ),
+        "{}",
+        message
+    );
+    assert!(message.contains("AGENTIC_FLOW_RECORD=1"), "{}", message);
+    std::fs::remove_file(path).unwrap();
+}
+
+#[test]
+fn test_missing_cassette_fails_to_replay() {
+    let path = cassette("missing");
+
+    let error = RecordingProvider::replay(&path).err().unwrap();
+
+    assert!(
+        matches!(&error, AgenticFlowError::ApiClientError(message)
+            if message.starts_with(&format!("cannot read cassette '{}'", path.display()))),
+        "{:?}",
+        error
+    );
+}
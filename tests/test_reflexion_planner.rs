@@ -0,0 +1,122 @@
+mod common;
+
+use std::sync::Arc;
+
+use serde_json::json;
+use tokio::sync::Mutex;
+
+use agentic_flow_lib::{
+    llm_client::{LLMClient, MockLLMProvider},
+    model::ChatMessage,
+    planner::{MultiStepPlanner, PlanStep, Planner, ReflexionPlanner},
+    tool_registry::ToolRegistry,
+};
+
+use common::calling;
+use common::tools::{EchoTool, MockTool};
+
+fn answer(text: &str) -> ChatMessage {
+    ChatMessage::assistant(text.to_string())
+}
+
+fn planner(mock: &MockLLMProvider, rounds: usize) -> ReflexionPlanner {
+    let mut registry = ToolRegistry::new();
+    registry.register_local_tool(Box::new(MockTool));
+    registry.register_local_tool(Box::new(EchoTool));
+    let registry = Arc::new(Mutex::new(registry));
+    let client = LLMClient::from(mock.clone());
+    let inner = MultiStepPlanner::new(client.clone(), registry.clone());
+    ReflexionPlanner::new(Box::new(inner), client, rounds).with_tool_registry(registry)
+}
+
+#[tokio::test]
+async fn test_plans_are_made_again_with_the_critique_until_accepted() {
+    let mock = MockLLMProvider::new().with_chat_responses(vec![
+        calling(&[("mock_tool", json!({"foo": "bar"}))]),
+        answer("The task asks to say hello; nothing echoes it.\nVERDICT: REVISE"),
+        calling(&[("echo", json!({"text": "hello"}))]),
+        answer("This says hello.\nverdict: accept"),
+    ]);
+
+    let plan = planner(&mock, 3).reflect("say hello").await.unwrap();
+
+    assert_eq!(
+        plan.steps,
+        vec![PlanStep::new("echo", json!({"text": "hello"}))]
+    );
+    assert_eq!(plan.critique, "This says hello.");
+    assert!(plan.accepted);
+    assert_eq!(plan.revisions, 1);
+
+    let calls = mock.calls();
+    assert_eq!(calls.len(), 4);
+    let critique_request = &calls[1].messages[1].content;
+    assert!(critique_request.contains("1. mock_tool {\"foo\":\"bar\"}"));
+    assert!(critique_request.contains("- echo: "));
+    assert!(critique_request.contains("parameters: {"));
+    assert!(calls[1].messages[0].content.contains("do not exist"));
+    assert!(calls[1].tools.is_empty());
+    let replan_request = &calls[2].messages[1].content;
+    assert!(replan_request.contains("say hello"));
+    assert!(replan_request.contains("nothing echoes it."));
+    assert!(!replan_request.contains("VERDICT"));
+}
+
+#[tokio::test]
+async fn test_the_last_plan_is_returned_when_the_rounds_run_out() {
+    let mock = MockLLMProvider::new().with_chat_responses(vec![
+        calling(&[("mock_tool", json!({"foo": "a"}))]),
+        answer("Wrong tool.\nVERDICT: REVISE"),
+        calling(&[("mock_tool", json!({"foo": "b"}))]),
+        answer("Still the wrong tool."),
+    ]);
+
+    let plan = planner(&mock, 1).reflect("say hello").await.unwrap();
+
+    assert_eq!(plan.steps[0].params, json!({"foo": "b"}));
+    assert_eq!(plan.critique, "Still the wrong tool.");
+    assert!(!plan.accepted);
+    assert_eq!(plan.revisions, 1);
+    assert_eq!(mock.calls().len(), 4);
+}
+
+#[tokio::test]
+async fn test_accepted_plans_are_not_made_again() {
+    let mock = MockLLMProvider::new().with_chat_responses(vec![
+        calling(&[("echo", json!({"text": "hi"}))]),
+        answer("VERDICT: ACCEPT"),
+    ]);
+
+    let steps = planner(&mock, 2).plan("say hi").await.unwrap();
+
+    assert_eq!(steps, vec![PlanStep::new("echo", json!({"text": "hi"}))]);
+    assert_eq!(mock.calls().len(), 2);
+}
+
+#[tokio::test]
+async fn test_critic_without_a_registry_is_not_asked_about_missing_tools() {
+    let mock = MockLLMProvider::new().with_chat_responses(vec![
+        calling(&[("echo", json!({"text": "hi"}))]),
+        answer("VERDICT: ACCEPT"),
+    ]);
+    let mut registry = ToolRegistry::new();
+    registry.register_local_tool(Box::new(EchoTool));
+    let client = LLMClient::from(mock.clone());
+    let inner = MultiStepPlanner::new(client.clone(), Arc::new(Mutex::new(registry)));
+
+    let plan = ReflexionPlanner::new(Box::new(inner), client, 2)
+        .reflect("say hi")
+        .await
+        .unwrap();
+
+    assert!(plan.accepted);
+    let critique_request = &mock.calls()[1].messages;
+    assert!(!critique_request[0].content.contains("do not exist"));
+    assert!(critique_request[0].content.ends_with("VERDICT: REVISE."));
+    assert!(!critique_request[1].content.contains("Available tools"));
+    assert!(
+        critique_request[1]
+            .content
+            .contains("1. echo {\"text\":\"hi\"}")
+    );
+}
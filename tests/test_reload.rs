@@ -0,0 +1,118 @@
+#![cfg(unix)]
+
+mod common;
+
+use agentic_flow_lib::{
+    AgenticSystem,
+    config::{ConfigDiff, SystemConfig},
+    llm_client::{LLMClient, MockLLMProvider},
+    planner::PlannerKind,
+};
+
+use common::mcp_stub::stub_server;
+use common::tools::MockTool;
+
+async fn system_with(config: SystemConfig) -> AgenticSystem {
+    let provider = MockLLMProvider::new().with_chat_response(None).await;
+    AgenticSystem::builder()
+        .config(config)
+        .tool(MockTool)
+        .llm_client(LLMClient::from(provider))
+        .build()
+        .await
+        .unwrap()
+}
+
+#[tokio::test]
+async fn test_reload_adds_and_removes_servers() {
+    let initial = SystemConfig::builder()
+        .mcp_server("alpha", stub_server("alpha_tool"))
+        .mcp_server("beta", stub_server("beta_tool"))
+        .build()
+        .unwrap();
+    let system = system_with(initial).await;
+    assert_eq!(system.get_active_servers().await, vec!["alpha", "beta"]);
+
+    let updated = SystemConfig::builder()
+        .mcp_server("beta", stub_server("beta_tool"))
+        .mcp_server("gamma", stub_server("gamma_tool"))
+        .build()
+        .unwrap();
+    let report = system.reload_config(updated.clone()).await.unwrap();
+
+    assert_eq!(
+        report.diff,
+        ConfigDiff {
+            servers_added: vec!["gamma".to_string()],
+            servers_removed: vec!["alpha".to_string()],
+            ..ConfigDiff::default()
+        }
+    );
+    assert_eq!(report.started, vec!["gamma"]);
+    assert_eq!(report.stopped, vec!["alpha"]);
+    assert!(report.restarted.is_empty());
+    assert!(report.is_success());
+    assert!(!report.llm_client_rebuilt);
+
+    assert_eq!(system.get_active_servers().await, vec!["beta", "gamma"]);
+    let mut tools = system.get_available_tools().await;
+    tools.sort();
+    assert_eq!(tools, vec!["beta_tool", "gamma_tool", "mock_tool"]);
+    assert_eq!(system.config(), updated);
+
+    system.shutdown().await.unwrap();
+}
+
+#[tokio::test]
+async fn test_reload_restarts_changed_server() {
+    let initial = SystemConfig::builder()
+        .mcp_server("alpha", stub_server("alpha_tool"))
+        .build()
+        .unwrap();
+    let system = system_with(initial).await;
+
+    let updated = SystemConfig::builder()
+        .mcp_server("alpha", stub_server("renamed_tool"))
+        .build()
+        .unwrap();
+    let report = system.reload_config(updated).await.unwrap();
+
+    assert_eq!(report.diff.servers_changed, vec!["alpha"]);
+    assert_eq!(report.restarted, vec!["alpha"]);
+    let mut tools = system.get_available_tools().await;
+    tools.sort();
+    assert_eq!(tools, vec!["mock_tool", "renamed_tool"]);
+
+    system.shutdown().await.unwrap();
+}
+
+#[tokio::test]
+async fn test_reload_applies_agent_and_planner_changes() {
+    let system = system_with(SystemConfig::default()).await;
+
+    let mut updated = SystemConfig::default();
+    updated.agent_config.max_steps = 3;
+    updated.planner = PlannerKind::ChainOfThought.into();
+    let report = system.reload_config(updated).await.unwrap();
+
+    assert!(report.diff.agent_config_changed);
+    assert!(report.diff.planner_changed);
+    assert!(!report.diff.llm_config_changed);
+    assert!(report.started.is_empty() && report.stopped.is_empty());
+    assert_eq!(system.config().agent_config.max_steps, 3);
+    assert_eq!(
+        system.config().planner.planner_kind(),
+        PlannerKind::ChainOfThought
+    );
+}
+
+#[tokio::test]
+async fn test_reload_rejects_invalid_config() {
+    let system = system_with(SystemConfig::default()).await;
+
+    let mut invalid = SystemConfig::default();
+    invalid.agent_config.max_steps = 0;
+
+    assert!(system.reload_config(invalid).await.is_err());
+    assert_eq!(system.config(), SystemConfig::default());
+}
@@ -0,0 +1,123 @@
+mod common;
+
+use std::{
+    path::PathBuf,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use agentic_flow_lib::{
+    llm_client::{LLMClient, LLMRequestLog, MockLLMProvider, OpenRouterModel, RetryPolicy},
+    model::FinishReason,
+};
+
+use common::http_server::{MockHttpServer, MockResponse};
+use common::user_messages;
+
+fn response_fixture(name: &str) -> String {
+    let path = PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+        .join("tests")
+        .join("fixtures")
+        .join("responses")
+        .join(name);
+    std::fs::read_to_string(path).unwrap()
+}
+
+fn logging(client: LLMClient) -> (LLMClient, Arc<Mutex<Vec<LLMRequestLog>>>) {
+    let logs: Arc<Mutex<Vec<LLMRequestLog>>> = Arc::default();
+    let record = {
+        let logs = logs.clone();
+        move |log: &LLMRequestLog| logs.lock().unwrap().push(log.clone())
+    };
+    (client.with_request_observer(record), logs)
+}
+
+#[tokio::test]
+async fn test_observer_gets_the_redacted_request_and_a_summary() {
+    let body = response_fixture("openrouter_tool_calls.json");
+    let server = MockHttpServer::start(vec![MockResponse::raw(200, &body)]).await;
+    let (client, logs) = logging(LLMClient::from_open_router_at(
+        &server.base_url,
+        OpenRouterModel::Flash2,
+        "sk-or-very-secret",
+    ));
+
+    client
+        .chat_completions(user_messages("hi"), vec![])
+        .await
+        .unwrap();
+
+    let logs = logs.lock().unwrap();
+    assert_eq!(logs.len(), 1);
+    let log = &logs[0];
+    assert_eq!(log.operation, "llm chat");
+    assert_eq!(
+        log.endpoint.as_deref(),
+        Some(format!("{}/chat/completions", server.base_url).as_str())
+    );
+    assert_eq!(log.status, Some(200));
+    let authorization = log
+        .headers
+        .iter()
+        .find(|(name, _)| name == "Authorization")
+        .map(|(_, value)| value.as_str());
+    assert_eq!(authorization, Some("Bearer ***"));
+    assert_eq!(
+        log.request.as_ref().unwrap()["messages"][0]["content"],
+        "hi"
+    );
+    assert!(!format!("{:?}", log).contains("sk-or-very-secret"));
+
+    let summary = log.outcome.as_ref().unwrap();
+    assert_eq!(summary.model.as_deref(), Some("openai/gpt-4o-mini"));
+    assert_eq!(summary.finish_reason, Some(FinishReason::ToolCalls));
+    assert_eq!(summary.tool_calls, vec!["search"]);
+    assert_eq!(summary.usage.unwrap().total_tokens, 42);
+    // The observer's copy is its own; the request went out with the real key.
+    assert_eq!(
+        server.requests()[0].header("authorization"),
+        Some("Bearer sk-or-very-secret")
+    );
+}
+
+#[tokio::test]
+async fn test_failed_requests_are_logged_once_after_retries() {
+    let server = MockHttpServer::start(vec![
+        MockResponse::raw(500, "{\"error\": {\"message\": \"upstream down\"}}"),
+        MockResponse::raw(503, "{\"error\": {\"message\": \"still down\"}}"),
+    ])
+    .await;
+    let client = LLMClient::from_open_router_at(&server.base_url, OpenRouterModel::Flash2, "key")
+        .with_retry(RetryPolicy {
+            max_attempts: 2,
+            jitter: false,
+            base_delay: Duration::from_millis(1),
+            ..RetryPolicy::default()
+        });
+    let (client, logs) = logging(client);
+
+    client
+        .chat_completions(user_messages("hi"), vec![])
+        .await
+        .err()
+        .unwrap();
+
+    let logs = logs.lock().unwrap();
+    assert_eq!(logs.len(), 1);
+    assert_eq!(logs[0].status, Some(503));
+    let error = logs[0].outcome.as_ref().err().unwrap();
+    assert!(error.contains("still down"), "{}", error);
+}
+
+#[tokio::test]
+async fn test_completions_without_http_are_logged_without_a_request() {
+    let (client, logs) = logging(LLMClient::from(MockLLMProvider::new()));
+
+    client.completion("Say hi".to_string()).await.unwrap();
+
+    let logs = logs.lock().unwrap();
+    assert_eq!(logs[0].operation, "llm completion");
+    assert_eq!(logs[0].endpoint, None);
+    assert_eq!(logs[0].request, None);
+    assert!(logs[0].outcome.is_ok());
+}
@@ -0,0 +1,240 @@
+mod common;
+
+use std::{path::PathBuf, time::Duration};
+
+use agentic_flow_lib::{
+    config::{LLMConfig, ProviderKind},
+    errors::AgenticFlowError,
+    llm_client::{LLMClient, MockLLMProvider, RetryPolicy},
+};
+
+use common::http_server::{MockHttpServer, MockResponse};
+use common::user_messages;
+
+fn fast_retries(max_attempts: usize) -> RetryPolicy {
+    RetryPolicy {
+        max_attempts,
+        base_delay: Duration::from_millis(1),
+        max_delay: Duration::from_millis(5),
+        jitter: false,
+    }
+}
+
+fn server_error() -> AgenticFlowError {
+    AgenticFlowError::from_http_response(503, None, "overloaded")
+}
+
+#[tokio::test]
+async fn test_retries_until_success() {
+    let provider = MockLLMProvider::new().with_chat_failures(vec![
+        server_error(),
+        AgenticFlowError::NetworkError("reset".to_string()),
+    ]);
+    let calls = provider.chat_calls();
+    let client = LLMClient::from(provider).with_retry(fast_retries(3));
+
+    let response = client.chat_completions(user_messages("hi"), vec![]).await;
+
+    assert!(response.is_ok(), "{:?}", response.err());
+    assert_eq!(calls.lock().unwrap().len(), 3);
+}
+
+#[tokio::test]
+async fn test_gives_up_after_max_attempts() {
+    let provider = MockLLMProvider::new().with_chat_error(server_error());
+    let calls = provider.chat_calls();
+    let client = LLMClient::from(provider).with_retry(fast_retries(3));
+
+    let error = client
+        .chat_completions(user_messages("hi"), vec![])
+        .await
+        .err()
+        .unwrap();
+
+    assert!(
+        matches!(
+            &error,
+            AgenticFlowError::RetriesExhausted { attempts: 3, source }
+                if matches!(**source, AgenticFlowError::ApiResponseError { status: 503, .. })
+        ),
+        "{:?}",
+        error
+    );
+    assert!(error.to_string().contains("after 3 attempts"), "{}", error);
+    assert!(error.is_retryable());
+    assert_eq!(calls.lock().unwrap().len(), 3);
+}
+
+#[tokio::test]
+async fn test_non_retryable_errors_fail_at_once() {
+    for status in [400, 401, 404] {
+        let error = AgenticFlowError::from_http_response(status, None, "nope");
+        let provider = MockLLMProvider::new().with_chat_error(error);
+        let calls = provider.chat_calls();
+        let client = LLMClient::from(provider).with_retry(fast_retries(3));
+
+        let error = client
+            .chat_completions(user_messages("hi"), vec![])
+            .await
+            .err()
+            .unwrap();
+
+        assert!(
+            !matches!(error, AgenticFlowError::RetriesExhausted { .. }),
+            "{}: {:?}",
+            status,
+            error
+        );
+        assert_eq!(calls.lock().unwrap().len(), 1, "{}", status);
+    }
+}
+
+#[tokio::test]
+async fn test_no_retries_without_a_policy() {
+    let provider = MockLLMProvider::new().with_chat_failures(vec![server_error()]);
+    let calls = provider.chat_calls();
+
+    let error = LLMClient::from(provider)
+        .chat_completions(user_messages("hi"), vec![])
+        .await
+        .err()
+        .unwrap();
+
+    assert!(matches!(
+        error,
+        AgenticFlowError::ApiResponseError { status: 503, .. }
+    ));
+    assert_eq!(calls.lock().unwrap().len(), 1);
+}
+
+#[test]
+fn test_backoff_doubles_up_to_max_delay() {
+    let policy = RetryPolicy {
+        max_attempts: 5,
+        base_delay: Duration::from_millis(100),
+        max_delay: Duration::from_millis(250),
+        jitter: false,
+    };
+    let delays: Vec<Duration> = (1..=4)
+        .map(|attempt| policy.delay(attempt, &server_error()))
+        .collect();
+
+    assert_eq!(
+        delays,
+        vec![
+            Duration::from_millis(100),
+            Duration::from_millis(200),
+            Duration::from_millis(250),
+            Duration::from_millis(250),
+        ]
+    );
+}
+
+#[test]
+fn test_jitter_stays_within_half_the_backoff() {
+    let policy = RetryPolicy {
+        base_delay: Duration::from_millis(400),
+        ..RetryPolicy::default()
+    };
+
+    for _ in 0..50 {
+        let delay = policy.delay(1, &server_error());
+        assert!(
+            (Duration::from_millis(200)..=Duration::from_millis(400)).contains(&delay),
+            "{:?}",
+            delay
+        );
+    }
+}
+
+#[test]
+fn test_retry_after_replaces_the_backoff() {
+    let error = AgenticFlowError::from_http_response(429, Some("2"), "{}");
+
+    assert_eq!(
+        RetryPolicy::default().delay(1, &error),
+        Duration::from_secs(2)
+    );
+}
+
+#[test]
+fn test_retry_after_is_capped_at_max_delay() {
+    let policy = RetryPolicy {
+        max_delay: Duration::from_secs(30),
+        ..RetryPolicy::default()
+    };
+    let error = AgenticFlowError::from_http_response(429, Some("86400"), "{}");
+
+    assert_eq!(policy.delay(1, &error), Duration::from_secs(30));
+}
+
+#[tokio::test]
+async fn test_http_retries_honor_retry_after() {
+    let body = std::fs::read_to_string(
+        PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+            .join("tests/fixtures/responses/openrouter_stop.json"),
+    )
+    .unwrap();
+    let server = MockHttpServer::start(vec![
+        MockResponse::raw(429, "{}").with_header("Retry-After", "0.05"),
+        MockResponse::raw(502, "<html>Bad Gateway</html>"),
+        MockResponse::raw(200, &body),
+    ])
+    .await;
+    let client = LLMClient::from_config(&LLMConfig {
+        provider: ProviderKind::OpenAICompatible {
+            base_url: server.base_url.clone(),
+        },
+        model: "test-model".to_string(),
+        ..LLMConfig::default()
+    })
+    .unwrap()
+    .with_retry(RetryPolicy {
+        max_delay: Duration::from_secs(1),
+        ..fast_retries(3)
+    });
+
+    let started = std::time::Instant::now();
+    let response = client
+        .chat_completions(user_messages("hi"), vec![])
+        .await
+        .unwrap();
+
+    assert_eq!(response.message().content, "Here is the answer");
+    assert_eq!(server.requests().len(), 3);
+    assert!(started.elapsed() >= Duration::from_millis(50));
+}
+
+#[tokio::test]
+async fn test_http_retries_wait_at_most_max_delay_for_retry_after() {
+    let body = std::fs::read_to_string(
+        PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+            .join("tests/fixtures/responses/openrouter_stop.json"),
+    )
+    .unwrap();
+    let server = MockHttpServer::start(vec![
+        MockResponse::raw(429, "{}").with_header("Retry-After", "86400"),
+        MockResponse::raw(200, &body),
+    ])
+    .await;
+    let client = LLMClient::from_config(&LLMConfig {
+        provider: ProviderKind::OpenAICompatible {
+            base_url: server.base_url.clone(),
+        },
+        model: "test-model".to_string(),
+        ..LLMConfig::default()
+    })
+    .unwrap()
+    .with_retry(fast_retries(2));
+
+    let response = tokio::time::timeout(
+        Duration::from_secs(5),
+        client.chat_completions(user_messages("hi"), vec![]),
+    )
+    .await
+    .expect("the retry waited for the whole Retry-After")
+    .unwrap();
+
+    assert_eq!(response.message().content, "Here is the answer");
+    assert_eq!(server.requests().len(), 2);
+}
@@ -0,0 +1,90 @@
+mod common;
+
+use agentic_flow_lib::{
+    AgenticSystem,
+    config::SystemConfig,
+    llm_client::{CostTracker, LLMClient, LLMRouter, MockLLMProvider, Purpose},
+    model::ChatMessage,
+};
+
+use common::tools::MockTool;
+use common::user_messages;
+
+#[tokio::test]
+async fn test_planning_and_synthesis_use_their_own_clients() {
+    let planner = MockLLMProvider::new();
+    let planner_calls = planner.chat_calls();
+    let synthesizer = MockLLMProvider::new()
+        .with_chat_response(Some(ChatMessage::assistant("the answer".to_string())))
+        .await;
+    let synthesizer_calls = synthesizer.chat_calls();
+    let router = LLMRouter::new(LLMClient::from(planner))
+        .with_client(Purpose::Synthesis, LLMClient::from(synthesizer));
+    let system = AgenticSystem::new(SystemConfig::default(), vec![Box::new(MockTool)], router)
+        .await
+        .unwrap();
+
+    let result = system.plan_and_execute("any task").await.unwrap();
+
+    assert_eq!(result, "the answer");
+    let planner_calls = planner_calls.lock().unwrap();
+    assert_eq!(planner_calls.len(), 1);
+    assert_eq!(planner_calls[0][1].content, "any task");
+    let synthesizer_calls = synthesizer_calls.lock().unwrap();
+    assert_eq!(synthesizer_calls.len(), 1);
+    assert!(synthesizer_calls[0][1].content.starts_with("Context: "));
+    assert_eq!(system.usage_report().calls(), 2);
+}
+
+#[tokio::test]
+async fn test_purposes_without_a_client_use_the_default() {
+    let default = MockLLMProvider::new();
+    let default_calls = default.chat_calls();
+    let planning = MockLLMProvider::new();
+    let planning_calls = planning.chat_calls();
+    let router = LLMRouter::new(LLMClient::from(default))
+        .with_client(Purpose::Planning, LLMClient::from(planning));
+
+    for purpose in [Purpose::Synthesis, Purpose::Reflection, Purpose::Default] {
+        router
+            .client(purpose)
+            .chat_completions(user_messages("hi"), vec![])
+            .await
+            .unwrap();
+    }
+    router
+        .client(Purpose::Planning)
+        .chat_completions(user_messages("hi"), vec![])
+        .await
+        .unwrap();
+
+    assert_eq!(default_calls.lock().unwrap().len(), 3);
+    assert_eq!(planning_calls.lock().unwrap().len(), 1);
+}
+
+#[tokio::test]
+async fn test_shared_trackers_are_reported_once() {
+    let tracker = CostTracker::new();
+    let small = LLMClient::from(MockLLMProvider::new().with_usage("small", 10, 5))
+        .with_cost_tracker(tracker.clone());
+    let large = LLMClient::from(MockLLMProvider::new().with_usage("large", 100, 50))
+        .with_cost_tracker(tracker.clone());
+    let router = LLMRouter::new(small.clone())
+        .with_client(Purpose::Planning, small)
+        .with_client(Purpose::Synthesis, large);
+
+    router
+        .client(Purpose::Planning)
+        .chat_completions(user_messages("hi"), vec![])
+        .await
+        .unwrap();
+    router
+        .client(Purpose::Synthesis)
+        .chat_completions(user_messages("hi"), vec![])
+        .await
+        .unwrap();
+
+    let report = router.usage_report();
+    assert_eq!(report, tracker.report());
+    assert_eq!(report.calls(), 2);
+}
@@ -0,0 +1,97 @@
+mod common;
+
+use std::sync::Arc;
+
+use serde_json::json;
+use tokio::sync::Mutex;
+
+use agentic_flow_lib::{
+    errors::AgenticFlowError,
+    llm_client::{LLMClient, MockLLMProvider},
+    planner::{MultiStepPlanner, PlanStep, Planner, SchemaRepairPlanner},
+    tool_registry::ToolRegistry,
+};
+
+use common::calling;
+use common::tools::{EchoTool, MockTool};
+
+fn tool_registry() -> Arc<Mutex<ToolRegistry>> {
+    let mut registry = ToolRegistry::new();
+    registry.register_local_tool(Box::new(MockTool));
+    registry.register_local_tool(Box::new(EchoTool));
+    Arc::new(Mutex::new(registry))
+}
+
+#[tokio::test]
+async fn test_broken_steps_are_repaired() {
+    let mock = MockLLMProvider::new().with_chat_responses(vec![
+        calling(&[("echo", json!({})), ("mock_tool", json!({"foo": "bar"}))]),
+        calling(&[("echo", json!({"text": "hello"}))]),
+    ]);
+    let planner =
+        MultiStepPlanner::new(LLMClient::from(mock.clone()), tool_registry()).with_schema_repair();
+
+    let steps = planner.plan("say hello").await.unwrap();
+
+    assert_eq!(
+        steps,
+        vec![
+            PlanStep::new("echo", json!({"text": "hello"})),
+            PlanStep::new("mock_tool", json!({"foo": "bar"})),
+        ]
+    );
+    let calls = mock.calls();
+    assert_eq!(calls.len(), 2);
+    let request = &calls[1].messages[1].content;
+    assert!(request.contains("- echo {}"));
+    assert!(request.contains("\"text\" is a required property"));
+    assert_eq!(calls[1].tools.len(), 1);
+    assert_eq!(calls[1].tools[0]["function"]["name"], "echo");
+}
+
+#[tokio::test]
+async fn test_unrepaired_steps_fail_with_their_violations() {
+    let broken = calling(&[("mock_tool", json!({"foo": 1}))]);
+    let mock = MockLLMProvider::new().with_chat_responses(vec![broken.clone(), broken]);
+    let planner =
+        MultiStepPlanner::new(LLMClient::from(mock.clone()), tool_registry()).with_schema_repair();
+
+    let error = planner.plan("any task").await.unwrap_err();
+
+    assert_eq!(mock.calls().len(), 2);
+    match error {
+        AgenticFlowError::PlanningError(message) => assert_eq!(
+            message,
+            "Plan steps do not match the schemas of their tools (repair rounds: 1): \
+             step 1 (mock_tool): /foo: 1 is not of type \"string\""
+        ),
+        error => panic!("expected a planning error, got {:?}", error),
+    }
+}
+
+#[tokio::test]
+async fn test_valid_plans_need_no_repair() {
+    let mock = MockLLMProvider::new()
+        .with_chat_responses(vec![calling(&[("echo", json!({"text": "hi"}))])]);
+    let planner = MultiStepPlanner::new(LLMClient::from(mock.clone()), tool_registry())
+        .with_schema_repair_rounds(3);
+
+    assert_eq!(planner.plan("say hi").await.unwrap().len(), 1);
+    assert_eq!(mock.calls().len(), 1);
+}
+
+#[tokio::test]
+async fn test_schema_repair_wraps_other_planners() {
+    let registry = tool_registry();
+    let mock = MockLLMProvider::new().with_chat_responses(vec![
+        calling(&[("mock_tool", json!({"foo": 1}))]),
+        calling(&[("mock_tool", json!({"foo": "1"}))]),
+    ]);
+    let client = LLMClient::from(mock.clone());
+    let inner = MultiStepPlanner::new(client.clone(), registry.clone());
+    let planner = SchemaRepairPlanner::new(Box::new(inner), client, registry, 1);
+
+    let steps = planner.plan("any task").await.unwrap();
+
+    assert_eq!(steps[0].params, json!({"foo": "1"}));
+}
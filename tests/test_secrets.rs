@@ -0,0 +1,220 @@
+mod common;
+
+use std::{
+    collections::HashMap,
+    sync::{
+        Arc,
+        atomic::{AtomicUsize, Ordering},
+    },
+};
+
+use serde_json::json;
+
+use agentic_flow_lib::{
+    config::{ConfigFormat, LLMConfig, MCPConfig, ProviderKind, SystemConfig},
+    errors::AgenticFlowError,
+    llm_client::LLMClient,
+    model::ChatMessage,
+    secrets::{
+        CachedSecretResolver, DefaultSecretResolver, SecretRef, SecretResolver, SecretString,
+        resolve_mcp_config,
+    },
+};
+
+use common::http_server::{MockHttpServer, MockResponse};
+
+/// Resolves every ref to `resolved-<ref>` and counts the calls.
+#[derive(Default)]
+struct MockResolver {
+    calls: Arc<AtomicUsize>,
+}
+
+impl SecretResolver for MockResolver {
+    fn resolve(&self, secret: &SecretRef) -> Result<String, AgenticFlowError> {
+        self.calls.fetch_add(1, Ordering::SeqCst);
+        Ok(format!("resolved-{}", secret))
+    }
+}
+
+fn config_error(err: AgenticFlowError) -> String {
+    match err {
+        AgenticFlowError::ConfigError(message) => message,
+        other => panic!("unexpected error: {:?}", other),
+    }
+}
+
+#[test]
+fn test_secret_refs_deserialize() {
+    let contents = r#"
+        [llm_config]
+        api_key = { file = "/run/secrets/openrouter" }
+
+        [mcp_config.servers.github]
+        server_type = "Command"
+        command = "github-mcp-server"
+        secrets = { GITHUB_TOKEN = { env = "GH_TOKEN" } }
+    "#;
+
+    let config = SystemConfig::parse(contents, ConfigFormat::Toml).unwrap();
+
+    assert_eq!(
+        config.llm_config.api_key,
+        Some(SecretRef::File("/run/secrets/openrouter".to_string()))
+    );
+    assert_eq!(
+        config.mcp_config.servers["github"].secrets["GITHUB_TOKEN"],
+        SecretRef::Env("GH_TOKEN".to_string())
+    );
+}
+
+#[test]
+fn test_resolve_env_secret() {
+    unsafe { std::env::set_var("AGENTIC_FLOW_TEST_SECRET", "from-env") };
+
+    let value = DefaultSecretResolver
+        .resolve(&SecretRef::Env("AGENTIC_FLOW_TEST_SECRET".to_string()))
+        .unwrap();
+
+    assert_eq!(value, "from-env");
+}
+
+#[test]
+fn test_resolve_file_secret() {
+    let path = std::env::temp_dir().join(format!("agentic-flow-secret-{}", std::process::id()));
+    std::fs::write(&path, "from-file\n").unwrap();
+
+    let value = DefaultSecretResolver
+        .resolve(&SecretRef::File(path.to_string_lossy().to_string()))
+        .unwrap();
+    std::fs::remove_file(&path).unwrap();
+
+    assert_eq!(value, "from-file");
+}
+
+#[test]
+fn test_literal_secret_is_not_printed() {
+    let secret = SecretRef::Literal("sk-literal".to_string());
+
+    assert!(!format!("{:?}", secret).contains("sk-literal"));
+    assert!(!secret.to_string().contains("sk-literal"));
+}
+
+#[test]
+fn test_secret_string_is_not_printed() {
+    let secret = SecretString::new("sk-tenant");
+
+    assert_eq!(format!("{:?}", secret), "SecretString(***)");
+    assert_eq!(secret.expose(), "sk-tenant");
+}
+
+#[test]
+fn test_cached_resolver_resolves_once() {
+    let mock = MockResolver::default();
+    let calls = mock.calls.clone();
+    let resolver = CachedSecretResolver::new(mock);
+    let secret = SecretRef::Env("TOKEN".to_string());
+
+    assert_eq!(resolver.resolve(&secret).unwrap(), "resolved-env:TOKEN");
+    assert_eq!(resolver.resolve(&secret).unwrap(), "resolved-env:TOKEN");
+    resolver
+        .resolve(&SecretRef::Env("OTHER".to_string()))
+        .unwrap();
+
+    assert_eq!(calls.load(Ordering::SeqCst), 2);
+}
+
+#[test]
+fn test_server_secrets_injected_into_env() {
+    let mut config = MCPConfig::default();
+    config.servers.insert(
+        "github".to_string(),
+        serde_json::from_value(json!({
+            "server_type": "Command",
+            "command": "github-mcp-server",
+            "env": { "LOG_LEVEL": "info" },
+            "secrets": { "GITHUB_TOKEN": { "env": "GH_TOKEN" } }
+        }))
+        .unwrap(),
+    );
+
+    let resolved = resolve_mcp_config(&config, &MockResolver::default()).unwrap();
+
+    let env: &HashMap<String, String> = &resolved.servers["github"].env;
+    assert_eq!(env["GITHUB_TOKEN"], "resolved-env:GH_TOKEN");
+    assert_eq!(env["LOG_LEVEL"], "info");
+}
+
+#[test]
+fn test_failure_names_ref_and_server() {
+    let mut config = MCPConfig::default();
+    config.servers.insert(
+        "github".to_string(),
+        serde_json::from_value(json!({
+            "server_type": "Command",
+            "command": "github-mcp-server",
+            "secrets": { "GITHUB_TOKEN": { "env": "AGENTIC_FLOW_TEST_UNSET_SECRET" } }
+        }))
+        .unwrap(),
+    );
+
+    let message = config_error(resolve_mcp_config(&config, &DefaultSecretResolver).unwrap_err());
+
+    assert!(
+        message.contains("env:AGENTIC_FLOW_TEST_UNSET_SECRET"),
+        "{}",
+        message
+    );
+    assert!(message.contains("server 'github'"), "{}", message);
+}
+
+#[test]
+fn test_failure_names_ref_and_provider() {
+    let config = LLMConfig {
+        provider: ProviderKind::OpenRouter,
+        api_key: Some(SecretRef::File("/nonexistent/openrouter".to_string())),
+        ..LLMConfig::default()
+    };
+
+    let message = config_error(LLMClient::from_config(&config).err().unwrap());
+
+    assert!(
+        message.contains("file:/nonexistent/openrouter"),
+        "{}",
+        message
+    );
+    assert!(message.contains("OpenRouter"), "{}", message);
+}
+
+#[tokio::test]
+async fn test_provider_sends_resolved_api_key() {
+    let server = MockHttpServer::start(vec![MockResponse::json(
+        200,
+        json!({
+            "choices": [{
+                "message": {"role": "assistant", "content": "ok"},
+                "finish_reason": "stop"
+            }]
+        }),
+    )])
+    .await;
+    let config = LLMConfig {
+        provider: ProviderKind::OpenAICompatible {
+            base_url: server.base_url.clone(),
+        },
+        model: "test-model".to_string(),
+        api_key: Some(SecretRef::Env("API".to_string())),
+        ..LLMConfig::default()
+    };
+
+    let client = LLMClient::from_config_with_resolver(&config, &MockResolver::default()).unwrap();
+    client
+        .chat_completions(vec![ChatMessage::user("hi".to_string())], vec![])
+        .await
+        .unwrap();
+
+    let requests = server.requests();
+    assert_eq!(
+        requests[0].header("authorization"),
+        Some("Bearer resolved-env:API")
+    );
+}
@@ -0,0 +1,198 @@
+mod common;
+
+use std::{
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use serde_json::{Value, json};
+use tokio::sync::Mutex;
+
+use agentic_flow_lib::{
+    agent::{Agent, AgentConfig, ExecutionMode, SynthesisConfig},
+    config::MCPConfig,
+    errors::AgenticFlowError,
+    llm_client::{LLMClient, MockLLMProvider},
+    mcp_manager::MCPManager,
+    model::{ChatMessage, ToolCall},
+    planner::{Executor, MultiStepPlanner, PlanStep, Planner},
+    tool_registry::{ExecutionContext, LocalTool, ToolRegistry},
+};
+
+use common::tools::MockTool;
+
+const STEP_DELAY: Duration = Duration::from_millis(150);
+
+/// Takes [`STEP_DELAY`] and returns the context keys it saw.
+struct SlowTool;
+
+#[async_trait::async_trait]
+impl LocalTool for SlowTool {
+    fn name(&self) -> &str {
+        "slow"
+    }
+
+    fn description(&self) -> &str {
+        "Waits, then lists the results it can see"
+    }
+
+    fn parameter_schema(&self) -> Value {
+        json!({"type": "object"})
+    }
+
+    async fn execute(
+        &self,
+        _params: Value,
+        context: &mut ExecutionContext,
+    ) -> Result<Value, AgenticFlowError> {
+        tokio::time::sleep(STEP_DELAY).await;
+        let mut seen: Vec<&String> = context.data().keys().collect();
+        seen.sort();
+        Ok(json!({ "seen": seen }))
+    }
+}
+
+fn agent(execution_mode: ExecutionMode) -> Agent {
+    let mut tool_registry = ToolRegistry::new();
+    tool_registry.register_local_tool(Box::new(SlowTool));
+    let config = AgentConfig {
+        execution_mode,
+        synthesis: SynthesisConfig {
+            enabled: false,
+            ..SynthesisConfig::default()
+        },
+        ..AgentConfig::default()
+    };
+    Agent::new(
+        Arc::new(Mutex::new(MCPManager::new(MCPConfig::default()))),
+        Arc::new(Mutex::new(tool_registry)),
+        LLMClient::from(MockLLMProvider::new()),
+    )
+    .with_config(config)
+}
+
+/// Two branches from `root`, joined by `merge`, listed out of order.
+fn diamond() -> Vec<PlanStep> {
+    vec![
+        PlanStep::new("slow", json!({}))
+            .with_id("merge")
+            .with_depends_on(["left", "right"]),
+        PlanStep::new("slow", json!({})).with_id("root"),
+        PlanStep::new("slow", json!({}))
+            .with_id("left")
+            .with_depends_on(["root"]),
+        PlanStep::new("slow", json!({}))
+            .with_id("right")
+            .with_depends_on(["root"]),
+    ]
+}
+
+async fn timed_run(execution_mode: ExecutionMode) -> (Value, Duration) {
+    let agent = agent(execution_mode);
+    let started = Instant::now();
+    let context = agent.execute(diamond()).await.unwrap();
+    (serde_json::from_str(&context).unwrap(), started.elapsed())
+}
+
+#[tokio::test]
+async fn test_independent_steps_of_a_diamond_run_in_parallel() {
+    let (serial_context, serial) = timed_run(ExecutionMode::Sequential).await;
+    let (context, parallel) = timed_run(ExecutionMode::Parallel { workers: 4 }).await;
+
+    // Three levels of steps, against four steps one after the other.
+    assert!(serial >= STEP_DELAY * 4, "serial run took {:?}", serial);
+    assert!(
+        parallel < STEP_DELAY * 4,
+        "parallel run took {:?}",
+        parallel
+    );
+    for context in [&serial_context, &context] {
+        assert_eq!(context["2: slow"]["seen"], json!([]));
+        assert_eq!(
+            context["1: slow"]["seen"],
+            json!(["2: slow", "3: slow", "4: slow"])
+        );
+    }
+    assert_eq!(context["3: slow"]["seen"], json!(["2: slow"]));
+    assert_eq!(context["4: slow"]["seen"], json!(["2: slow"]));
+}
+
+#[tokio::test]
+async fn test_dependency_cycles_are_rejected() {
+    let steps = vec![
+        PlanStep::new("slow", json!({})).with_id("first"),
+        PlanStep::new("slow", json!({}))
+            .with_id("a")
+            .with_depends_on(["b"]),
+        PlanStep::new("slow", json!({}))
+            .with_id("b")
+            .with_depends_on(["a"]),
+    ];
+
+    let agent = agent(ExecutionMode::Parallel { workers: 2 });
+    let started = Instant::now();
+    let error = agent.execute(steps).await.unwrap_err();
+
+    assert!(
+        started.elapsed() < STEP_DELAY,
+        "a step ran before the check"
+    );
+    match error {
+        AgenticFlowError::PlanningError(message) => assert_eq!(
+            message,
+            "Steps 'a', 'b' cannot run: their dependencies form a cycle"
+        ),
+        error => panic!("expected a planning error, got {:?}", error),
+    }
+}
+
+#[tokio::test]
+async fn test_unknown_dependencies_are_rejected() {
+    let steps = vec![PlanStep::new("slow", json!({})).with_depends_on(["missing"])];
+
+    let error = agent(ExecutionMode::Sequential)
+        .execute(steps)
+        .await
+        .unwrap_err();
+
+    assert!(matches!(error, AgenticFlowError::PlanningError(message)
+        if message.contains("depends on 'missing'")));
+}
+
+#[tokio::test]
+async fn test_planner_reads_step_ids_and_dependencies_from_tool_calls() {
+    let calls = vec![
+        ToolCall::new(
+            "mock_tool".to_string(),
+            json!({"foo": "a", "_step_id": "a"}),
+        ),
+        ToolCall::new(
+            "mock_tool".to_string(),
+            json!({"foo": "b", "_step_id": "b", "_depends_on": ["a"]}),
+        ),
+    ];
+    let mock = MockLLMProvider::new().with_chat_responses(vec![
+        ChatMessage::assistant(String::new()).with_tool_calls(calls),
+    ]);
+    let mut registry = ToolRegistry::new();
+    registry.register_local_tool(Box::new(MockTool));
+    let planner = MultiStepPlanner::new(
+        LLMClient::from(mock.clone()),
+        Arc::new(Mutex::new(registry)),
+    );
+
+    let steps = planner.plan("any task").await.unwrap();
+
+    assert_eq!(
+        steps,
+        vec![
+            PlanStep::new("mock_tool", json!({"foo": "a"})).with_id("a"),
+            PlanStep::new("mock_tool", json!({"foo": "b"}))
+                .with_id("b")
+                .with_depends_on(["a"]),
+        ]
+    );
+    let properties = &mock.calls()[0].tools[0]["function"]["parameters"]["properties"];
+    assert_eq!(properties["foo"], json!({"type": "string"}));
+    assert_eq!(properties["_depends_on"]["type"], "array");
+}
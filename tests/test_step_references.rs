@@ -0,0 +1,160 @@
+mod common;
+
+use std::sync::Arc;
+
+use serde_json::{Value, json};
+use tokio::sync::Mutex;
+
+use agentic_flow_lib::{
+    agent::{Agent, AgentConfig, ExecutionMode, SynthesisConfig},
+    config::MCPConfig,
+    errors::AgenticFlowError,
+    llm_client::{LLMClient, MockLLMProvider},
+    mcp_manager::MCPManager,
+    planner::{Executor, PlanStep},
+    tool_registry::ToolRegistry,
+};
+
+use common::tools::MockTool;
+
+fn agent(execution_mode: ExecutionMode) -> Agent {
+    let mut tool_registry = ToolRegistry::new();
+    tool_registry.register_local_tool(Box::new(MockTool));
+    let config = AgentConfig {
+        execution_mode,
+        synthesis: SynthesisConfig {
+            enabled: false,
+            ..SynthesisConfig::default()
+        },
+        ..AgentConfig::default()
+    };
+    Agent::new(
+        Arc::new(Mutex::new(MCPManager::new(MCPConfig::default()))),
+        Arc::new(Mutex::new(tool_registry)),
+        LLMClient::from(MockLLMProvider::new()),
+    )
+    .with_config(config)
+}
+
+/// The params the second step of `steps` ran with.
+async fn second_params(steps: Vec<PlanStep>) -> Value {
+    let context = agent(ExecutionMode::Sequential)
+        .execute(steps)
+        .await
+        .unwrap();
+    let context: Value = serde_json::from_str(&context).unwrap();
+    context["2: mock_tool"]["params"].clone()
+}
+
+fn first_step() -> PlanStep {
+    PlanStep::new(
+        "mock_tool",
+        json!({"foo": "https://example.com", "tags": ["a", "b"], "count": 2}),
+    )
+    .with_id("fetch")
+}
+
+#[tokio::test]
+async fn test_whole_references_keep_the_type_of_their_value() {
+    let params = second_params(vec![
+        first_step(),
+        PlanStep::new(
+            "mock_tool",
+            json!({
+                "url": "{{steps.1.output.params.foo}}",
+                "nested": {"tags": "{{ steps.1.output.params.tags }}"},
+                "list": ["{{steps.1.output.params.count}}", "{{steps.1.output.params.tags.1}}"],
+            }),
+        ),
+    ])
+    .await;
+
+    assert_eq!(
+        params,
+        json!({
+            "url": "https://example.com",
+            "nested": {"tags": ["a", "b"]},
+            "list": [2, "b"],
+        })
+    );
+}
+
+#[tokio::test]
+async fn test_embedded_references_are_replaced_by_their_text() {
+    let params = second_params(vec![
+        first_step(),
+        PlanStep::new(
+            "mock_tool",
+            json!({
+                "foo": "Open {{steps.fetch.output.params.foo}} for {{steps.1.output.params.count}} items",
+                "escaped": "\\{{steps.1.output}} and {{other}}",
+            }),
+        ),
+    ])
+    .await;
+
+    assert_eq!(params["foo"], "Open https://example.com for 2 items");
+    assert_eq!(params["escaped"], "{{steps.1.output}} and {{other}}");
+}
+
+#[tokio::test]
+async fn test_references_resolve_when_steps_run_in_parallel() {
+    let steps = vec![
+        PlanStep::new(
+            "mock_tool",
+            json!({"foo": "{{steps.fetch.output.params.foo}}"}),
+        )
+        .with_depends_on(["fetch"]),
+        first_step(),
+    ];
+
+    let context = agent(ExecutionMode::Parallel { workers: 2 })
+        .execute(steps)
+        .await
+        .unwrap();
+    let context: Value = serde_json::from_str(&context).unwrap();
+
+    assert_eq!(
+        context["1: mock_tool"]["params"]["foo"],
+        "https://example.com"
+    );
+}
+
+#[tokio::test]
+async fn test_dangling_references_fail_the_step() {
+    for (reference, reason) in [
+        ("{{steps.3.output}}", "step 3 has not run"),
+        (
+            "{{steps.1.output.params.missing}}",
+            "the output of step 1 has no 'missing'",
+        ),
+    ] {
+        let steps = vec![
+            first_step(),
+            PlanStep::new("mock_tool", json!({ "foo": reference })),
+        ];
+
+        let error = agent(ExecutionMode::Sequential)
+            .execute(steps)
+            .await
+            .unwrap_err();
+
+        let AgenticFlowError::ExecutionFailed(failure) = error else {
+            panic!("expected a failed execution, got {:?}", error);
+        };
+        assert_eq!(failure.step_index, 1);
+        assert_eq!(failure.params, json!({ "foo": reference }));
+        assert_eq!(failure.completed.len(), 1);
+        match failure.error {
+            AgenticFlowError::ExecutionError(message) => assert_eq!(
+                message,
+                format!(
+                    "Cannot resolve '{}': {}",
+                    reference.trim_matches(['{', '}']),
+                    reason
+                )
+            ),
+            error => panic!("expected an execution error, got {:?}", error),
+        }
+    }
+}
@@ -0,0 +1,100 @@
+mod common;
+
+use std::{
+    sync::atomic::Ordering,
+    time::{Duration, Instant},
+};
+
+use futures::future::join_all;
+
+use agentic_flow_lib::{
+    errors::{AgenticFlowError, ErrorKind},
+    llm_client::{LLMClient, MockLLMProvider},
+};
+
+use common::user_messages;
+
+async fn chat_concurrently(client: &LLMClient, calls: usize) -> Vec<bool> {
+    let calls = (0..calls).map(|_| {
+        let client = client.clone();
+        async move {
+            client
+                .chat_completions(user_messages("hi"), vec![])
+                .await
+                .is_ok()
+        }
+    });
+    join_all(calls).await
+}
+
+#[tokio::test]
+async fn test_max_concurrency_bounds_calls_in_flight() {
+    let provider = MockLLMProvider::new().with_chat_delay(Duration::from_millis(20));
+    let max_in_flight = provider.max_in_flight();
+    let calls = provider.chat_calls();
+    let client = LLMClient::from(provider).with_max_concurrency(3);
+
+    let results = chat_concurrently(&client, 20).await;
+
+    assert!(results.iter().all(|ok| *ok));
+    assert_eq!(calls.lock().unwrap().len(), 20);
+    assert_eq!(max_in_flight.load(Ordering::SeqCst), 3);
+}
+
+#[tokio::test]
+async fn test_no_concurrency_limit_by_default() {
+    let provider = MockLLMProvider::new().with_chat_delay(Duration::from_millis(20));
+    let max_in_flight = provider.max_in_flight();
+    let client = LLMClient::from(provider);
+
+    chat_concurrently(&client, 20).await;
+
+    assert_eq!(max_in_flight.load(Ordering::SeqCst), 20);
+}
+
+#[tokio::test]
+async fn test_queue_timeout_fails_waiting_calls() {
+    let provider = MockLLMProvider::new().with_chat_delay(Duration::from_millis(200));
+    let calls = provider.chat_calls();
+    let client = LLMClient::from(provider)
+        .with_max_concurrency(1)
+        .with_queue_timeout(Duration::from_millis(20));
+
+    let (first, second) = tokio::join!(
+        client.chat_completions(user_messages("hi"), vec![]),
+        client.chat_completions(user_messages("hi"), vec![])
+    );
+
+    assert!(first.is_ok());
+    let error = second.err().unwrap();
+    assert!(
+        matches!(error, AgenticFlowError::QueueTimeout { waited } if waited == Duration::from_millis(20)),
+        "{:?}",
+        error
+    );
+    assert_eq!(error.kind(), ErrorKind::Timeout);
+    assert_eq!(calls.lock().unwrap().len(), 1);
+}
+
+#[tokio::test]
+async fn test_rate_limit_spaces_calls_after_a_burst() {
+    let provider = MockLLMProvider::new();
+    let calls = provider.chat_calls();
+    // Ten calls a second, in bursts of ten.
+    let client = LLMClient::from(provider).with_rate_limit(600);
+
+    let started = Instant::now();
+    for _ in 0..12 {
+        client
+            .chat_completions(user_messages("hi"), vec![])
+            .await
+            .unwrap();
+    }
+
+    assert_eq!(calls.lock().unwrap().len(), 12);
+    assert!(
+        started.elapsed() >= Duration::from_millis(180),
+        "{:?}",
+        started.elapsed()
+    );
+}
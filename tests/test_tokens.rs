@@ -0,0 +1,112 @@
+use agentic_flow_lib::{
+    model::{ChatMessage, Role, ToolCall},
+    tokens::{
+        TrimStrategy, estimate_message_tokens, estimate_messages_tokens, estimate_tokens,
+        trim_messages,
+    },
+};
+use serde_json::json;
+
+fn conversation() -> Vec<ChatMessage> {
+    vec![
+        ChatMessage::system("You are a careful assistant.".to_string()),
+        ChatMessage::user("first question ".repeat(40)),
+        ChatMessage::assistant("first answer ".repeat(40)),
+        ChatMessage::system("Answer in one sentence.".to_string()),
+        ChatMessage::user("second question ".repeat(20)),
+        ChatMessage::user("What is the status?".to_string()),
+    ]
+}
+
+fn contents(messages: &[ChatMessage]) -> Vec<&str> {
+    messages.iter().map(|m| m.content.as_str()).collect()
+}
+
+#[test]
+fn test_estimate_tokens_counts_words_numbers_and_symbols() {
+    assert_eq!(estimate_tokens("", "gpt-4o"), 0);
+    assert_eq!(estimate_tokens("hello world", "gpt-4o"), 2);
+    assert_eq!(estimate_tokens("internationalization", "gpt-4o"), 4);
+    assert_eq!(estimate_tokens("2024", "gpt-4o"), 2);
+    assert_eq!(estimate_tokens("{\"a\": 1}", "gpt-4o"), 5);
+    assert_eq!(estimate_tokens("日本語", "gpt-4o"), 3);
+    assert_eq!(
+        estimate_tokens("hello world", "gpt-4o"),
+        estimate_tokens("hello world", "llama3")
+    );
+}
+
+#[test]
+fn test_message_estimate_includes_overhead_and_tool_calls() {
+    let plain = ChatMessage::assistant("done".to_string());
+    assert_eq!(estimate_message_tokens(&plain, ""), 5);
+
+    let mut calling = ChatMessage::assistant(String::new());
+    calling.tool_calls = Some(vec![ToolCall::new(
+        "search".to_string(),
+        json!({"query": "rust"}),
+    )]);
+    assert!(estimate_message_tokens(&calling, "") > estimate_message_tokens(&plain, ""));
+}
+
+#[test]
+fn test_messages_within_budget_are_unchanged() {
+    let messages = conversation();
+    let budget = estimate_messages_tokens(&messages, "");
+
+    for strategy in [TrimStrategy::DropOldest, TrimStrategy::TruncateLongest] {
+        let trimmed = trim_messages(messages.clone(), budget, strategy);
+        assert_eq!(contents(&trimmed), contents(&messages));
+    }
+}
+
+#[test]
+fn test_drop_oldest_keeps_system_messages_and_the_last_message() {
+    let messages = conversation();
+
+    let trimmed = trim_messages(messages.clone(), 120, TrimStrategy::DropOldest);
+
+    assert_eq!(
+        contents(&trimmed),
+        vec![
+            "You are a careful assistant.",
+            "Answer in one sentence.",
+            messages[4].content.as_str(),
+            "What is the status?",
+        ]
+    );
+    assert!(estimate_messages_tokens(&trimmed, "") <= 120);
+}
+
+#[test]
+fn test_truncate_longest_shortens_messages_to_the_budget() {
+    let messages = conversation();
+
+    let trimmed = trim_messages(messages.clone(), 80, TrimStrategy::TruncateLongest);
+
+    assert_eq!(trimmed.len(), messages.len());
+    assert!(estimate_messages_tokens(&trimmed, "") <= 80);
+    assert!(trimmed[1].content.ends_with("... [truncated]"));
+    assert!(trimmed[2].content.ends_with("... [truncated]"));
+    assert_eq!(trimmed[5].content, "What is the status?");
+}
+
+#[test]
+fn test_system_messages_are_never_dropped_or_cut() {
+    let messages = conversation();
+
+    for strategy in [TrimStrategy::DropOldest, TrimStrategy::TruncateLongest] {
+        let trimmed = trim_messages(messages.clone(), 1, strategy);
+        let system: Vec<&str> = trimmed
+            .iter()
+            .filter(|m| m.role == Role::System)
+            .map(|m| m.content.as_str())
+            .collect();
+        assert_eq!(
+            system,
+            vec!["You are a careful assistant.", "Answer in one sentence."],
+            "{:?}",
+            strategy
+        );
+    }
+}
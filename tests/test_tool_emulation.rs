@@ -0,0 +1,219 @@
+mod common;
+
+use std::sync::Arc;
+
+use serde_json::{Value, json};
+use tokio::sync::Mutex;
+
+use agentic_flow_lib::{
+    errors::AgenticFlowError,
+    llm_client::{LLMClient, MockLLMProvider, ModelCapabilities, ToolEmulation},
+    model::{ChatMessage, FinishReason, Role, ToolCall},
+    planner::{MultiStepPlanner, Planner},
+    tool_registry::ToolRegistry,
+};
+use common::tools::MockTool;
+
+fn make_tool_registry() -> Arc<Mutex<ToolRegistry>> {
+    let mut registry = ToolRegistry::new();
+    registry.register_local_tool(Box::new(MockTool));
+    Arc::new(Mutex::new(registry))
+}
+
+fn weather_tool() -> Value {
+    json!({
+        "type": "function",
+        "function": {
+            "name": "get_weather",
+            "description": "The weather in a city",
+            "parameters": {"type": "object", "properties": {"city": {"type": "string"}}},
+        },
+    })
+}
+
+fn emulating(mock: &MockLLMProvider) -> LLMClient {
+    LLMClient::from(mock.clone()).with_tool_emulation(ToolEmulation::Always)
+}
+
+#[tokio::test]
+async fn test_fenced_json_becomes_tool_calls() {
+    let mock = MockLLMProvider::new().with_chat_responses(vec![ChatMessage::assistant(
+        "```json\n{\"tool_calls\": [{\"name\": \"get_weather\", \"arguments\": {\"city\": \"Paris\"}}]}\n```"
+            .to_string(),
+    )]);
+    let client = emulating(&mock);
+
+    let response = client
+        .chat_completions(
+            vec![
+                ChatMessage::system("You are helpful.".to_string()),
+                ChatMessage::user("Weather in Paris?".to_string()),
+            ],
+            vec![weather_tool()],
+        )
+        .await
+        .unwrap();
+
+    let calls = response.message().tool_calls.clone().unwrap();
+    assert_eq!(calls.len(), 1);
+    assert_eq!(calls[0].function.name, "get_weather");
+    assert_eq!(calls[0].function.arguments, json!({"city": "Paris"}));
+    assert_eq!(response.finish_reason(), Some(FinishReason::ToolCalls));
+
+    let sent = &mock.calls()[0];
+    assert!(sent.tools.is_empty());
+    assert_eq!(sent.messages.len(), 2);
+    assert!(sent.messages[0].content.starts_with("You are helpful."));
+    assert!(
+        sent.messages[0]
+            .content
+            .contains("\"name\":\"get_weather\"")
+    );
+}
+
+#[tokio::test]
+async fn test_plain_answer_is_returned_as_is() {
+    let mock = MockLLMProvider::new()
+        .with_chat_responses(vec![ChatMessage::assistant("It is sunny.".to_string())]);
+    let client = emulating(&mock);
+
+    let response = client
+        .chat_completions(
+            vec![ChatMessage::user("Weather?".to_string())],
+            vec![weather_tool()],
+        )
+        .await
+        .unwrap();
+
+    assert_eq!(response.message().content, "It is sunny.");
+    assert!(response.message().tool_calls.is_none());
+    assert_eq!(mock.calls()[0].messages[0].role, Role::System);
+}
+
+#[tokio::test]
+async fn test_malformed_json_gets_one_correction() {
+    let mock = MockLLMProvider::new().with_chat_responses(vec![
+        ChatMessage::assistant("{\"tool_calls\": [{\"name\": get weather}]}".to_string()),
+        ChatMessage::assistant(
+            "{\"name\": \"get_weather\", \"arguments\": {\"city\": \"Oslo\"}}".to_string(),
+        ),
+    ]);
+    let client = emulating(&mock);
+
+    let response = client
+        .chat_completions(
+            vec![ChatMessage::user("Weather in Oslo?".to_string())],
+            vec![weather_tool()],
+        )
+        .await
+        .unwrap();
+
+    let calls = response.message().tool_calls.clone().unwrap();
+    assert_eq!(calls[0].function.arguments, json!({"city": "Oslo"}));
+    let calls = mock.calls();
+    assert_eq!(calls.len(), 2);
+    let correction = calls[1].messages.last().unwrap();
+    assert_eq!(correction.role, Role::User);
+    assert!(correction.content.contains("could not be read"));
+}
+
+#[tokio::test]
+async fn test_almost_valid_json_is_repaired() {
+    let mock = MockLLMProvider::new().with_chat_responses(vec![ChatMessage::assistant(
+        "Calling it now: {'tool_calls': [{'name': 'get_weather', arguments: {city: 'Oslo'},}"
+            .to_string(),
+    )]);
+    let client = emulating(&mock);
+
+    let response = client
+        .chat_completions(
+            vec![ChatMessage::user("Weather in Oslo?".to_string())],
+            vec![weather_tool()],
+        )
+        .await
+        .unwrap();
+
+    let calls = response.message().tool_calls.clone().unwrap();
+    assert_eq!(calls[0].function.arguments, json!({"city": "Oslo"}));
+    assert!(calls[0].function.repaired);
+    assert_eq!(mock.calls().len(), 1);
+}
+
+#[tokio::test]
+async fn test_second_malformed_answer_is_parse_error_with_raw_text() {
+    let mock = MockLLMProvider::new().with_chat_responses(vec![
+        ChatMessage::assistant("{\"name\": \"launch_rocket\"}".to_string()),
+        ChatMessage::assistant("{\"name\": \"launch_rocket\", \"arguments\": {}}".to_string()),
+    ]);
+    let client = emulating(&mock);
+
+    let error = client
+        .chat_completions(
+            vec![ChatMessage::user("Launch it".to_string())],
+            vec![weather_tool()],
+        )
+        .await
+        .unwrap_err();
+
+    match error {
+        AgenticFlowError::ParseError(message) => {
+            assert!(
+                message.contains("unknown tool 'launch_rocket'"),
+                "{}",
+                message
+            );
+            assert!(message.contains("\"arguments\": {}"), "{}", message);
+        }
+        error => panic!("expected a parse error, got {:?}", error),
+    }
+    assert_eq!(mock.calls().len(), 2);
+}
+
+#[tokio::test]
+async fn test_earlier_tool_calls_are_sent_as_text() {
+    let mock = MockLLMProvider::new();
+    let client = emulating(&mock);
+    let call = ToolCall::new("get_weather".to_string(), json!({"city": "Rome"}))
+        .with_id("call_1".to_string());
+
+    client
+        .chat_completions(
+            vec![
+                ChatMessage::user("Weather in Rome?".to_string()),
+                ChatMessage::assistant(String::new()).with_tool_calls(vec![call]),
+                ChatMessage::tool("22 degrees".to_string(), "call_1".to_string()),
+            ],
+            vec![weather_tool()],
+        )
+        .await
+        .unwrap();
+
+    let sent = &mock.calls()[0].messages;
+    assert!(sent[2].tool_calls.is_none());
+    assert!(sent[2].content.contains("\"tool_calls\""));
+    assert_eq!(sent[3].role, Role::User);
+    assert_eq!(
+        sent[3].content,
+        "Result of the tool get_weather: 22 degrees"
+    );
+}
+
+#[tokio::test]
+async fn test_auto_emulation_lets_multistep_planner_plan() {
+    let mock = MockLLMProvider::new().with_chat_responses(vec![ChatMessage::assistant(
+        "{\"tool_calls\": [{\"name\": \"mock_tool\", \"arguments\": {\"foo\": \"bar\"}}]}"
+            .to_string(),
+    )]);
+    let client = LLMClient::from(mock.clone()).with_capabilities(ModelCapabilities {
+        supports_tools: false,
+        ..ModelCapabilities::default()
+    });
+    assert!(client.emulates_tools());
+    let planner = MultiStepPlanner::new(client, make_tool_registry());
+
+    let plan = planner.plan("Run the mock tool").await.unwrap();
+
+    assert_eq!(plan.len(), 1);
+    assert_eq!(plan[0].tool_name, "mock_tool");
+    assert!(mock.calls()[0].tools.is_empty());
+}
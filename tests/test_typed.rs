@@ -0,0 +1,183 @@
+mod common;
+
+use serde::Deserialize;
+use serde_json::{Value, json};
+
+use agentic_flow_lib::{
+    errors::AgenticFlowError,
+    llm_client::{LLMClient, MockLLMProvider, OllamaModel},
+    model::{ChatMessage, FinishReason, Role},
+};
+
+use common::http_server::{MockHttpServer, MockResponse};
+
+#[derive(Debug, PartialEq, Deserialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+struct Answer {
+    answer: String,
+    confidence: f32,
+}
+
+fn answer_schema() -> Value {
+    json!({
+        "type": "object",
+        "properties": {
+            "answer": { "type": "string" },
+            "confidence": { "type": "number" }
+        },
+        "required": ["answer", "confidence"]
+    })
+}
+
+fn question() -> Vec<ChatMessage> {
+    vec![ChatMessage::user("What is 6 times 7?".to_string())]
+}
+
+fn answering(contents: &[&str]) -> MockLLMProvider {
+    MockLLMProvider::new().with_chat_responses(
+        contents
+            .iter()
+            .map(|content| ChatMessage::assistant(content.to_string()))
+            .collect(),
+    )
+}
+
+#[tokio::test]
+async fn test_reads_json_inside_think_blocks_and_code_fences() {
+    let provider = answering(&[
+        "<think>6 * 7 = 42</think>Sure:\n```json\n{\"answer\": \"42\", \"confidence\": 0.9}\n```",
+    ]);
+    let calls = provider.chat_calls();
+    let client = LLMClient::from(provider);
+
+    let answer: Answer = client
+        .generate_typed_with_schema(question(), answer_schema())
+        .await
+        .unwrap();
+
+    assert_eq!(
+        answer,
+        Answer {
+            answer: "42".to_string(),
+            confidence: 0.9
+        }
+    );
+    let calls = calls.lock().unwrap();
+    assert_eq!(calls.len(), 1);
+    assert_eq!(calls[0][0].role, Role::System);
+    assert!(calls[0][0].content.contains(&answer_schema().to_string()));
+    assert_eq!(calls[0][1].content, "What is 6 times 7?");
+}
+
+#[tokio::test]
+async fn test_reprompts_once_with_the_parse_error() {
+    let provider = answering(&[
+        "{\"answer\": 42}",
+        "{\"answer\": \"42\", \"confidence\": 1.0}",
+    ]);
+    let calls = provider.chat_calls();
+    let client = LLMClient::from(provider);
+
+    let answer: Answer = client
+        .generate_typed_with_schema(question(), answer_schema())
+        .await
+        .unwrap();
+
+    assert_eq!(answer.answer, "42");
+    let calls = calls.lock().unwrap();
+    assert_eq!(calls.len(), 2);
+    let correction = &calls[1];
+    assert_eq!(correction.len(), 4);
+    assert_eq!(correction[2].role, Role::Assistant);
+    assert_eq!(correction[2].content, "{\"answer\": 42}");
+    assert_eq!(correction[3].role, Role::User);
+    assert!(
+        correction[3].content.contains("invalid type: integer `42`"),
+        "{}",
+        correction[3].content
+    );
+}
+
+#[tokio::test]
+async fn test_fails_with_a_parse_error_after_the_correction() {
+    let provider = answering(&["I think it is 42.", "Still 42."]);
+    let calls = provider.chat_calls();
+    let client = LLMClient::from(provider);
+
+    let error = client
+        .generate_typed_with_schema::<Answer>(question(), answer_schema())
+        .await
+        .err()
+        .unwrap();
+
+    assert!(
+        matches!(&error, AgenticFlowError::ParseError(message)
+            if message.ends_with("in answer: Still 42.")),
+        "{:?}",
+        error
+    );
+    assert_eq!(calls.lock().unwrap().len(), 2);
+}
+
+#[tokio::test]
+async fn test_truncated_answers_are_not_reprompted() {
+    let provider = answering(&["{\"answer\": \"4"]).with_finish_reason(FinishReason::Length);
+    let calls = provider.chat_calls();
+    let client = LLMClient::from(provider).with_max_tokens(8);
+
+    let error = client
+        .generate_typed_with_schema::<Answer>(question(), answer_schema())
+        .await
+        .err()
+        .unwrap();
+
+    assert!(
+        matches!(
+            error,
+            AgenticFlowError::OutputTruncated {
+                max_tokens: Some(8)
+            }
+        ),
+        "{:?}",
+        error
+    );
+    assert_eq!(calls.lock().unwrap().len(), 1);
+}
+
+#[tokio::test]
+async fn test_schema_is_sent_as_the_output_format() {
+    let body = json!({
+        "model": "gemma3:4b",
+        "message": {
+            "role": "assistant",
+            "content": "{\"answer\": \"42\", \"confidence\": 0.5}"
+        },
+        "done_reason": "stop",
+        "done": true
+    });
+    let server = MockHttpServer::start(vec![MockResponse::json(200, body)]).await;
+    let client =
+        LLMClient::from_ollama_at(server.base_url.clone(), OllamaModel::Gemma3_4b).unwrap();
+
+    let answer: Answer = client
+        .generate_typed_with_schema(question(), answer_schema())
+        .await
+        .unwrap();
+
+    assert_eq!(answer.confidence, 0.5);
+    assert_eq!(server.requests()[0].body["format"], answer_schema());
+}
+
+#[cfg(feature = "schemars")]
+#[tokio::test]
+async fn test_generate_typed_derives_the_schema() {
+    let provider = answering(&["{\"answer\": \"42\", \"confidence\": 1.0}"]);
+    let calls = provider.chat_calls();
+    let client = LLMClient::from(provider);
+
+    let answer: Answer = client.generate_typed(question()).await.unwrap();
+
+    assert_eq!(answer.answer, "42");
+    let prompt = &calls.lock().unwrap()[0][0].content;
+    assert!(prompt.contains("\"confidence\""), "{}", prompt);
+}
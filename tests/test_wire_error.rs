@@ -0,0 +1,425 @@
+use std::time::Duration;
+
+use serde_json::{Value, json};
+
+use agentic_flow_lib::{
+    errors::{
+        AgenticFlowError, ErrorKind, ExecutionFailure, PlanningDiagnostics, ProviderError,
+        ToolOrigin,
+    },
+    planner::StepOutcome,
+};
+
+fn wire(error: &AgenticFlowError) -> Value {
+    serde_json::to_value(error.to_wire()).unwrap()
+}
+
+fn tool_error() -> Box<AgenticFlowError> {
+    Box::new(AgenticFlowError::ToolError("boom".to_string()))
+}
+
+fn tool_error_wire() -> Value {
+    json!({
+        "code": "tool_error",
+        "message": "Tool error: boom",
+        "details": {},
+        "retryable": true
+    })
+}
+
+fn provider_error() -> Option<ProviderError> {
+    Some(ProviderError {
+        code: Some("429".to_string()),
+        message: "slow down".to_string(),
+        error_type: None,
+    })
+}
+
+#[test]
+fn test_message_variants() {
+    let cases = vec![
+        (
+            AgenticFlowError::PlanningError("x".to_string()),
+            "planning_error",
+            false,
+        ),
+        (
+            AgenticFlowError::ToolError("x".to_string()),
+            "tool_error",
+            true,
+        ),
+        (
+            AgenticFlowError::ApiClientError("x".to_string()),
+            "api_client_error",
+            false,
+        ),
+        (
+            AgenticFlowError::ParseError("x".to_string()),
+            "parse_error",
+            false,
+        ),
+        (
+            AgenticFlowError::NetworkError("x".to_string()),
+            "network_error",
+            true,
+        ),
+        (
+            AgenticFlowError::ExecutionError("x".to_string()),
+            "execution_error",
+            false,
+        ),
+        (
+            AgenticFlowError::ConfigError("x".to_string()),
+            "config_error",
+            false,
+        ),
+    ];
+
+    for (error, code, retryable) in cases {
+        assert_eq!(
+            wire(&error),
+            json!({
+                "code": code,
+                "message": error.to_string(),
+                "details": {},
+                "retryable": retryable
+            })
+        );
+    }
+}
+
+#[test]
+fn test_retries_exhausted() {
+    let error = AgenticFlowError::RetriesExhausted {
+        attempts: 3,
+        source: Box::new(AgenticFlowError::NetworkError("reset".to_string())),
+    };
+
+    let wire = wire(&error);
+
+    assert_eq!(wire["code"], "retries_exhausted");
+    assert_eq!(
+        wire["message"],
+        "Network error: reset (gave up after 3 attempts)"
+    );
+    assert_eq!(wire["details"]["attempts"], 3);
+    assert_eq!(wire["details"]["cause"]["code"], "network_error");
+    assert_eq!(wire["retryable"], true);
+}
+
+#[test]
+fn test_api_response_error() {
+    let error = AgenticFlowError::ApiResponseError {
+        status: 503,
+        message: "overloaded".to_string(),
+        provider_error: None,
+    };
+
+    assert_eq!(
+        wire(&error),
+        json!({
+            "code": "api_response_error",
+            "message": "API request failed with status 503: overloaded",
+            "details": { "status": 503, "provider_error": null },
+            "retryable": true
+        })
+    );
+}
+
+#[test]
+fn test_rate_limited() {
+    let error = AgenticFlowError::RateLimited {
+        retry_after: Some(Duration::from_secs(30)),
+        provider_error: provider_error(),
+    };
+
+    assert_eq!(
+        wire(&error),
+        json!({
+            "code": "rate_limited",
+            "message": "Rate limited by the provider, retry after 30s",
+            "details": {
+                "retry_after_seconds": 30.0,
+                "provider_error": { "code": "429", "message": "slow down" }
+            },
+            "retryable": true
+        })
+    );
+}
+
+#[test]
+fn test_unauthorized() {
+    let error = AgenticFlowError::Unauthorized {
+        status: 401,
+        provider_error: None,
+    };
+
+    assert_eq!(
+        wire(&error),
+        json!({
+            "code": "unauthorized",
+            "message": "The provider rejected the credentials (401)",
+            "details": { "status": 401, "provider_error": null },
+            "retryable": false
+        })
+    );
+}
+
+#[test]
+fn test_context_length_exceeded() {
+    let error = AgenticFlowError::ContextLengthExceeded {
+        provider_error: None,
+    };
+
+    assert_eq!(
+        wire(&error),
+        json!({
+            "code": "context_length_exceeded",
+            "message": "The prompt exceeds the model's context length",
+            "details": { "provider_error": null },
+            "retryable": false
+        })
+    );
+}
+
+#[test]
+fn test_output_truncated() {
+    let error = AgenticFlowError::OutputTruncated {
+        max_tokens: Some(256),
+    };
+
+    assert_eq!(
+        wire(&error),
+        json!({
+            "code": "output_truncated",
+            "message": "The response was cut off at the limit of 256 tokens",
+            "details": { "max_tokens": 256 },
+            "retryable": false
+        })
+    );
+}
+
+#[test]
+fn test_timeout() {
+    let error = AgenticFlowError::Timeout {
+        operation: "llm chat",
+        limit: Duration::from_millis(1500),
+    };
+
+    assert_eq!(
+        wire(&error),
+        json!({
+            "code": "timeout",
+            "message": "operation 'llm chat' timed out after 1.5s",
+            "details": { "operation": "llm chat", "limit_seconds": 1.5 },
+            "retryable": true
+        })
+    );
+}
+
+#[test]
+fn test_queue_timeout() {
+    let error = AgenticFlowError::QueueTimeout {
+        waited: Duration::from_millis(250),
+    };
+
+    assert_eq!(
+        wire(&error),
+        json!({
+            "code": "queue_timeout",
+            "message": "request waited 0.25s for the client's concurrency or rate limit",
+            "details": { "waited_seconds": 0.25 },
+            "retryable": true
+        })
+    );
+}
+
+#[test]
+fn test_cancelled() {
+    let error = AgenticFlowError::Cancelled { operation: "task" };
+
+    assert_eq!(
+        wire(&error),
+        json!({
+            "code": "cancelled",
+            "message": "operation 'task' was cancelled",
+            "details": { "operation": "task" },
+            "retryable": false
+        })
+    );
+}
+
+#[test]
+fn test_tool_not_found() {
+    let error = AgenticFlowError::ToolNotFound {
+        tool: "fetch".to_string(),
+    };
+
+    assert_eq!(
+        wire(&error),
+        json!({
+            "code": "tool_not_found",
+            "message": "Tool 'fetch' not found",
+            "details": { "tool": "fetch" },
+            "retryable": false
+        })
+    );
+}
+
+#[test]
+fn test_tool_execution_failed() {
+    let error = AgenticFlowError::ToolExecutionFailed {
+        tool: "fetch".to_string(),
+        origin: ToolOrigin::Mcp {
+            server: "web".to_string(),
+        },
+        source: tool_error(),
+    };
+
+    assert_eq!(
+        wire(&error),
+        json!({
+            "code": "tool_execution_failed",
+            "message": "Tool 'fetch' (MCP server 'web') failed: Tool error: boom",
+            "details": { "tool": "fetch", "server": "web", "cause": tool_error_wire() },
+            "retryable": true
+        })
+    );
+}
+
+#[test]
+fn test_mcp_server_error() {
+    let error = AgenticFlowError::McpServerError {
+        server: "web".to_string(),
+        tool: None,
+        source: tool_error(),
+    };
+
+    assert_eq!(
+        wire(&error),
+        json!({
+            "code": "mcp_server_error",
+            "message": "MCP server 'web' failed: Tool error: boom",
+            "details": { "server": "web", "tool": null, "cause": tool_error_wire() },
+            "retryable": true
+        })
+    );
+}
+
+#[test]
+fn test_step_failed() {
+    let error = AgenticFlowError::StepFailed {
+        step_index: 0,
+        step_id: Some("fetch-docs".to_string()),
+        tool: "fetch".to_string(),
+        source: tool_error(),
+    };
+
+    assert_eq!(
+        wire(&error),
+        json!({
+            "code": "step_failed",
+            "message": "Step 1 ('fetch') failed: Tool error: boom",
+            "details": {
+                "step_index": 0,
+                "step_id": "fetch-docs",
+                "tool": "fetch",
+                "cause": tool_error_wire()
+            },
+            "retryable": true
+        })
+    );
+}
+
+#[test]
+fn test_execution_failed_omits_params_and_context() {
+    let error = AgenticFlowError::ExecutionFailed(Box::new(ExecutionFailure {
+        step_index: 1,
+        step_id: None,
+        tool: "send_email".to_string(),
+        params: json!({"to": "someone@example.com"}),
+        error: *tool_error(),
+        completed: vec![StepOutcome {
+            step_index: 0,
+            step_id: None,
+            tool: "lookup".to_string(),
+            params: json!({"name": "someone"}),
+            result: json!({"email": "someone@example.com"}),
+        }],
+        context: [("1: lookup".to_string(), json!("someone@example.com"))].into(),
+    }));
+
+    let wire = wire(&error);
+
+    assert_eq!(
+        wire,
+        json!({
+            "code": "execution_failed",
+            "message": "Step 2 ('send_email') failed: Tool error: boom",
+            "details": {
+                "step_index": 1,
+                "step_id": null,
+                "tool": "send_email",
+                "completed_steps": [{ "step_index": 0, "tool": "lookup" }],
+                "cause": tool_error_wire()
+            },
+            "retryable": true
+        })
+    );
+    assert!(!wire.to_string().contains("someone@example.com"));
+}
+
+fn planning_failed(redacted: bool) -> AgenticFlowError {
+    AgenticFlowError::PlanningFailed {
+        planner: "htn".to_string(),
+        phase: "refine",
+        source: tool_error(),
+        diagnostics: Box::new(PlanningDiagnostics {
+            raw_content: Some("I would rather not".to_string()),
+            tool_calls: None,
+            retries: 1,
+            redacted,
+        }),
+    }
+}
+
+#[test]
+fn test_planning_failed() {
+    assert_eq!(
+        wire(&planning_failed(false)),
+        json!({
+            "code": "planning_failed",
+            "message": "Planner 'htn' failed during refine: Tool error: boom",
+            "details": {
+                "planner": "htn",
+                "phase": "refine",
+                "diagnostics": { "raw_content": "I would rather not", "retries": 1 },
+                "cause": tool_error_wire()
+            },
+            "retryable": true
+        })
+    );
+}
+
+#[test]
+fn test_planning_failed_redacted() {
+    assert_eq!(
+        wire(&planning_failed(true))["details"]["diagnostics"],
+        json!({ "raw_content": "***", "retries": 1 })
+    );
+}
+
+#[test]
+fn test_classified_keeps_inner_code_with_override() {
+    let error = AgenticFlowError::ToolError("boom".to_string()).with_kind(ErrorKind::Permanent);
+
+    assert_eq!(
+        wire(&error),
+        json!({
+            "code": "tool_error",
+            "message": "Tool error: boom",
+            "details": {},
+            "retryable": false
+        })
+    );
+}
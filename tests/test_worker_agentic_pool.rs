@@ -1,18 +1,22 @@
 mod common;
 
-use agentic_flow_lib::model::Function;
 use agentic_flow_lib::model::ToolCall;
 use serde_json::json;
 use std::{str, sync::Arc};
 use tokio::sync::Mutex;
 
 use agentic_flow_lib::{
-    agent::Agent, config::MCPConfig, errors::AgenticFlowError, llm_client::LLMClient,
-    mcp_manager::MCPManager, model::ChatMessage, planner::PlanStep, tool_registry::ToolRegistry,
+    agent::Agent,
+    config::MCPConfig,
+    errors::AgenticFlowError,
+    llm_client::{LLMClient, MockLLMProvider},
+    mcp_manager::MCPManager,
+    model::ChatMessage,
+    planner::PlanStep,
+    tool_registry::ToolRegistry,
     worker::AgenticTaskPool,
 };
 
-use crate::common::llm_provider::MockLLMProvider;
 use crate::common::tools::EchoTool;
 
 async fn make_mock_agent(response: Option<ChatMessage>) -> Arc<Mutex<Agent>> {
@@ -36,12 +40,7 @@ async fn make_mock_agent(response: Option<ChatMessage>) -> Arc<Mutex<Agent>> {
 }
 
 fn make_tool_call(text: &str) -> ToolCall {
-    ToolCall {
-        function: Function {
-            name: "echo".to_string(),
-            arguments: json!({"text": text}),
-        },
-    }
+    ToolCall::new("echo".to_string(), json!({"text": text}))
 }
 
 #[tokio::test]
@@ -62,10 +61,7 @@ async fn test_agentic_task_pool_execute_step() -> Result<(), AgenticFlowError> {
     let pool = AgenticTaskPool::new(2, agent.clone());
 
     // Create a simple echo step (the mock agent should return the input parameters)
-    let step = PlanStep {
-        tool_name: "echo".to_string(),
-        params: json!({"text": "hello, world!"}),
-    };
+    let step = PlanStep::new("echo", json!({"text": "hello, world!"}));
 
     let result = pool.execute_step(step).await?;
     // For an echo tool the result should equal the input parameters.
@@ -86,18 +82,9 @@ async fn test_agentic_task_pool_execute_parallel() -> Result<(), AgenticFlowErro
     let pool = AgenticTaskPool::new(3, agent.clone());
 
     let steps = vec![
-        PlanStep {
-            tool_name: "echo".to_string(),
-            params: json!({"text": "one"}),
-        },
-        PlanStep {
-            tool_name: "echo".to_string(),
-            params: json!({"text": "two"}),
-        },
-        PlanStep {
-            tool_name: "echo".to_string(),
-            params: json!({"text": "three"}),
-        },
+        PlanStep::new("echo", json!({"text": "one"})),
+        PlanStep::new("echo", json!({"text": "two"})),
+        PlanStep::new("echo", json!({"text": "three"})),
     ];
 
     let results = pool.execute_parallel(steps).await?;